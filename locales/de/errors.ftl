@@ -0,0 +1,113 @@
+age_requirement_not_met = Ein Geburtsdatum, das die Volljährigkeit des Kontoinhabers belegt, ist erforderlich
+nsfw_acknowledgement_required = Dieser Inhalt ist als NSFW markiert und erfordert ein altersbestätigtes Konto
+request_timeout = Die Anfrage hat zu lange gedauert
+database_unavailable = Das Forum ist vorübergehend nicht erreichbar, bitte versuche es gleich noch einmal
+unknown_language = { $tag } ist kein bekanntes Sprachkürzel
+invalid_api_key = Ein gültiger API-Schlüssel ist erforderlich
+quota_exceeded = { $period ->
+    [daily] Das tägliche
+   *[monthly] Das monatliche
+} API-Kontingent für diesen Schlüssel ist aufgebraucht
+content_blocked = Dies enthält Wörter oder Links, die hier nicht erlaubt sind
+authentication_required = Melde dich an, um das zu tun
+invalid_token = Das Zugriffstoken ist ungültig oder abgelaufen
+invalid_credentials = Benutzername oder Passwort ist falsch
+not_account_owner = Das ist nur für dein eigenes Konto möglich
+not_author = Nur der Verfasser kann das ändern
+invalid_session = Deine Sitzung ist abgelaufen, bitte melde dich erneut an
+invalid_refresh_token = Das Aktualisierungstoken ist ungültig oder abgelaufen
+refresh_token_reused = Dieses Aktualisierungstoken wurde bereits verwendet, daher wurde die zugehörige Anmeldung beendet
+oauth_provider_not_configured = Die Anmeldung mit { $provider } ist nicht verfügbar
+invalid_oauth_state = Dieser Anmeldelink ist abgelaufen oder wurde bereits verwendet, bitte beginne erneut
+oauth_denied = Die Anmeldung beim Anbieter wurde abgebrochen
+identity_already_linked = Dieses Konto ist bereits mit einem anderen Benutzer verknüpft
+provider_already_linked = Dein Konto ist bereits mit einem anderen Konto bei diesem Anbieter verknüpft
+invalid_email = Das ist keine gültige E-Mail-Adresse
+email_taken = Diese E-Mail-Adresse wird bereits verwendet
+invalid_verification_token = Dieser Bestätigungslink ist ungültig oder abgelaufen
+email_already_verified = Diese E-Mail-Adresse wurde bereits bestätigt
+email_missing = Dieses Konto hat keine E-Mail-Adresse
+email_verification_required = Bestätige deine E-Mail-Adresse, bevor du etwas veröffentlichst
+api_key_scopes_required = Ein API-Schlüssel braucht mindestens einen Geltungsbereich
+insufficient_scope = Dieser API-Schlüssel braucht dafür den Geltungsbereich { $scope }
+login_required = Dafür können keine API-Schlüssel verwendet werden, bitte melde dich an
+role_required = Für diese Aktion ist die Rolle { $role } erforderlich
+rate_limited = Zu viele Anfragen ({ $group }), bitte versuche es in { $retry_after } Sekunden erneut
+account_locked = Dieses Konto ist nach zu vielen fehlgeschlagenen Anmeldungen gesperrt, bitte versuche es später erneut
+weak_password = Dieses Passwort erfüllt die Passwortanforderungen nicht
+csrf_token_invalid = Diese Anfrage braucht ein gültiges CSRF-Token von GET /auth/csrf
+invalid_vote = Eine Stimme muss 1 oder -1 sein
+invalid_cursor = Dieser Cursor ist ungültig oder gehört zu einer anders sortierten Liste
+empty_search = Eine Suche braucht mindestens ein Wort, nach dem gesucht wird
+already_published = Dieser Beitrag wurde bereits veröffentlicht
+drafts_private = Entwürfe sind nur für ihren Verfasser sichtbar
+invalid_pin_order = Eine Anheftposition muss zwischen 1 und { $max } liegen
+pin_limit_reached = { $sub } hat bereits { $max } angeheftete Beiträge
+thread_locked = Dieser Thread ist gesperrt und nimmt keine neuen Kommentare an
+post_archived = Dieser Beitrag ist archiviert und nimmt keine Stimmen oder Kommentare mehr an
+not_archived = Dieser Beitrag ist nicht archiviert
+invalid_flair_text = Ein Flair-Text muss zwischen 1 und { $max } Zeichen lang sein
+invalid_flair_color = Eine Flair-Farbe muss eine Hex-Farbe wie #ff8800 sein
+flair_exists = Dieses Sub hat bereits ein Flair mit diesem Text
+unknown_flair = { $sub } hat kein Flair mit dieser ID
+invalid_poll = Eine Umfrage braucht 2 bis 10 Optionen mit 1 bis 100 Zeichen und ein Ende in der Zukunft
+poll_closed = Diese Umfrage ist beendet
+unknown_poll_option = Diese Umfrage hat keine solche Option
+already_voted_in_poll = Du hast in dieser Umfrage bereits abgestimmt
+crosspost_same_sub = Ein Beitrag kann nur in ein anderes Sub gecrosspostet werden
+invalid_link_url = Ein Link muss eine öffentliche http- oder https-URL sein
+publish_at_in_past = Ein Beitrag kann nur für einen zukünftigen Zeitpunkt geplant werden
+empty_post_template_title = Ein geplanter Beitrag braucht einen Titel
+duplicate_link = Dieser Link wurde vor Kurzem schon in { $sub } gepostet
+invalid_sub_name = Sub-Namen bestehen aus { $min } bis { $max } Buchstaben, Ziffern oder Unterstrichen und beginnen mit einem Buchstaben
+sub_exists = Es gibt bereits einen Sub mit diesem Namen
+upload_too_large = Uploads dürfen höchstens { $max } Bytes groß sein
+invalid_upload = Uploads müssen multipart/form-data sein, mit der Datei im Feld file
+unsupported_media_type = Nur PNG-, JPEG-, GIF- und WebP-Bilder können hochgeladen werden
+too_many_attachments = Es können höchstens { $max } Dateien angehängt werden
+unknown_media = Nur eigene Uploads können angehängt werden
+invalid_image = Das Bild konnte nicht gelesen werden
+media_scan_failed = Uploads können gerade nicht geprüft werden, versuche es später noch einmal
+media_rejected = Diese Datei wurde vom Malware-Scanner abgelehnt
+sub_moderator_required = Nur Moderatoren von { $sub } können das tun
+already_sub_moderator = { $username } moderiert { $sub } bereits
+mod_permission_required = Moderatoren von { $sub } brauchen dafür die Berechtigung { $permission }
+invalid_rule_title = Regeltitel müssen zwischen 1 und { $max } Zeichen lang sein
+private_sub = Nur freigeschaltete Mitglieder können { $sub } lesen
+sub_members_only = Nur freigeschaltete Mitglieder können in { $sub } posten
+sub_open = In { $sub } kann jeder posten, ohne beizutreten
+already_sub_member = Du bist bereits Mitglied von { $sub }
+join_request_too_long = Die Nachricht einer Beitrittsanfrage darf höchstens { $max } Zeichen lang sein
+banned_from_sub = Du bist aus { $sub } gesperrt
+ban_expiry_in_past = Eine Sperre kann nur in der Zukunft ablaufen
+cannot_ban_moderator = { $username } moderiert { $sub } und kann dort nicht gesperrt werden
+invalid_theme_color = Themenfarben müssen Hex-Farben wie #ff8800 sein
+invalid_wiki_page_name = Wiki-Seitennamen bestehen aus 1 bis { $max } Buchstaben, Ziffern, Unterstrichen oder Bindestrichen
+wiki_page_too_long = Wiki-Seiten dürfen höchstens { $max } Zeichen lang sein
+wiki_members_only = Nur freigeschaltete Mitglieder von { $sub } können diese Seite bearbeiten
+invalid_feed_name = Feed-Namen bestehen aus 1 bis { $max } Buchstaben, Ziffern, Unterstrichen oder Bindestrichen
+feed_exists = Du hast bereits einen Feed mit diesem Namen
+too_many_feed_subs = Ein Feed kann höchstens { $max } Subs enthalten
+unknown_sub = Es gibt keinen Sub namens { $sub }
+report_reason_too_long = Meldegründe dürfen höchstens { $max } Zeichen lang sein
+unknown_rule = { $sub } hat keine solche Regel
+report_reason_required = Gib an, gegen welche Regel verstoßen wird oder warum du das meldest
+already_reported = Du hast das bereits gemeldet
+not_approvable = Aus rechtlichen Gründen entfernte oder vom Verfasser gelöschte Inhalte können nicht freigegeben werden
+invalid_removal_reason_title = Titel von Entfernungsgründen müssen zwischen 1 und { $max } Zeichen lang sein
+invalid_removal_message = Entfernungsnachrichten müssen zwischen 1 und { $max } Zeichen lang sein
+suspension_expiry_in_past = Eine Kontosperre kann nur in der Zukunft ablaufen
+cannot_suspend_admin = { $username } ist Administrator und kann nicht gesperrt werden
+account_suspended = Dieses Konto ist gesperrt
+too_many_automod_rules = Ein Sub kann höchstens { $max } AutoModerator-Regeln haben
+invalid_automod_rule = Die Regel „{ $rule }“ ist ungültig: { $problem }
+invalid_filter_domain = Das Muster eines Domainfilters muss eine Domain wie example.com sein
+domain_filter_cannot_mask = Domainfilter können Inhalte nur zurückhalten oder blockieren
+account_requirements_unmet = Dein Konto muss mindestens { $days } Tage alt sein und mindestens { $karma } Karma haben, um das zu tun
+invalid_account_requirement = Die Kontovoraussetzung ist ungültig: { $problem }
+invalid_appeal_message = Einsprüche und Antworten darauf müssen zwischen 1 und { $max } Zeichen lang sein
+nothing_to_appeal = Hier gibt es keine Entfernung, Sperre oder Kontosperrung von dir, gegen die du Einspruch erheben kannst
+appeal_pending = Du hast hiergegen bereits einen Einspruch, über den noch nicht entschieden wurde
+appeal_resolved = Über diesen Einspruch wurde bereits entschieden
+invalid_mod_note = Moderationsnotizen müssen zwischen 1 und { $max } Zeichen lang sein
+parent_not_on_post = Der Kommentar, auf den du antwortest, gehört nicht zu diesem Beitrag
@@ -0,0 +1,111 @@
+# Messages for ApiError codes. The message id is the error code.
+age_requirement_not_met = A date of birth showing the account holder is an adult is required
+nsfw_acknowledgement_required = This content is marked NSFW and requires an age-confirmed account
+request_timeout = The request took too long to complete
+database_unavailable = The forum is temporarily unavailable, please try again shortly
+unknown_language = { $tag } is not a recognised language tag
+invalid_api_key = A valid API key is required
+quota_exceeded = The { $period } API quota for this key has been used up
+content_blocked = This contains words or links that aren't allowed here
+authentication_required = Sign in to do this
+invalid_token = The access token is invalid or has expired
+invalid_credentials = The username or password is incorrect
+not_account_owner = You can only do this for your own account
+not_author = Only the author can change this
+invalid_session = Your session has ended, please sign in again
+invalid_refresh_token = The refresh token is invalid or has expired
+refresh_token_reused = This refresh token was already used, so the sign-in it belongs to has been ended
+oauth_provider_not_configured = Signing in with { $provider } is not available
+invalid_oauth_state = This sign-in link has expired or was already used, please start again
+oauth_denied = Signing in with the provider was cancelled
+identity_already_linked = That account is already linked to a different user
+provider_already_linked = Your account is already linked to a different account at this provider
+invalid_email = That is not a valid email address
+email_taken = That email address is already in use
+invalid_verification_token = This verification link is invalid or has expired
+email_already_verified = This email address has already been verified
+email_missing = This account has no email address
+email_verification_required = Verify your email address before posting
+api_key_scopes_required = An API key needs at least one scope
+insufficient_scope = This API key needs the { $scope } scope to do this
+login_required = API keys can't be used for this, sign in instead
+role_required = This action requires the { $role } role
+rate_limited = Too many { $group } requests, try again in { $retry_after } seconds
+account_locked = This account is locked after too many failed logins, try again later
+weak_password = That password doesn't meet the password requirements
+csrf_token_invalid = This request needs a valid CSRF token from GET /auth/csrf
+invalid_vote = A vote must be 1 or -1
+invalid_cursor = This cursor is malformed or belongs to a listing with another sort
+empty_search = A search needs at least one word to look for
+already_published = This post has already been published
+drafts_private = Drafts are only visible to their author
+invalid_pin_order = A pin order must be between 1 and { $max }
+pin_limit_reached = { $sub } already has { $max } pinned posts
+thread_locked = This thread is locked and takes no new comments
+post_archived = This post is archived and takes no more votes or comments
+not_archived = This post is not archived
+invalid_flair_text = Flair text must be between 1 and { $max } characters
+invalid_flair_color = A flair color must be a hex color such as #ff8800
+flair_exists = This sub already has a flair with that text
+unknown_flair = { $sub } has no flair with that id
+invalid_poll = A poll needs 2 to 10 options of 1 to 100 characters and an end time in the future
+poll_closed = This poll has closed
+unknown_poll_option = This poll has no such option
+already_voted_in_poll = You have already voted in this poll
+crosspost_same_sub = A post can only be crossposted to another sub
+invalid_link_url = A link must be a public http or https URL
+publish_at_in_past = A post can only be scheduled for a future time
+empty_post_template_title = A scheduled post needs a title
+duplicate_link = This link was already posted in { $sub } recently
+invalid_sub_name = Sub names are { $min } to { $max } letters, digits or underscores, starting with a letter
+sub_exists = A sub with that name already exists
+upload_too_large = Uploads can be at most { $max } bytes
+invalid_upload = Uploads must be multipart/form-data with the file in a field named file
+unsupported_media_type = Only PNG, JPEG, GIF and WebP images can be uploaded
+too_many_attachments = At most { $max } files can be attached
+unknown_media = Only your own uploads can be attached
+invalid_image = The image could not be read
+media_scan_failed = Uploads can't be checked right now, try again later
+media_rejected = This file was rejected by the malware scanner
+sub_moderator_required = Only moderators of { $sub } can do this
+already_sub_moderator = { $username } already moderates { $sub }
+mod_permission_required = Moderators of { $sub } need the { $permission } permission for this
+invalid_rule_title = Rule titles must be between 1 and { $max } characters
+private_sub = Only approved members can read { $sub }
+sub_members_only = Only approved members can post in { $sub }
+sub_open = Anyone can post in { $sub } without joining
+already_sub_member = You are already a member of { $sub }
+join_request_too_long = A join request's message can be at most { $max } characters
+banned_from_sub = You are banned from { $sub }
+ban_expiry_in_past = A ban can only be set to expire in the future
+cannot_ban_moderator = { $username } moderates { $sub } and can't be banned from it
+invalid_theme_color = Theme colors must be hex colors such as #ff8800
+invalid_wiki_page_name = Wiki page names are 1 to { $max } letters, digits, underscores or hyphens
+wiki_page_too_long = Wiki pages can be at most { $max } characters
+wiki_members_only = Only approved members of { $sub } can edit this page
+invalid_feed_name = Feed names are 1 to { $max } letters, digits, underscores or hyphens
+feed_exists = You already have a feed with that name
+too_many_feed_subs = A feed can gather at most { $max } subs
+unknown_sub = There is no sub named { $sub }
+report_reason_too_long = Report reasons can be at most { $max } characters
+unknown_rule = { $sub } has no such rule
+report_reason_required = Say which rule this breaks or why you are reporting it
+already_reported = You have already reported this
+not_approvable = Content taken down for legal reasons or deleted by its author can't be approved
+invalid_removal_reason_title = Removal reason titles must be between 1 and { $max } characters
+invalid_removal_message = Removal messages must be between 1 and { $max } characters
+suspension_expiry_in_past = A suspension can only be set to expire in the future
+cannot_suspend_admin = { $username } is an admin and can't be suspended
+account_suspended = This account is suspended
+too_many_automod_rules = A sub can have at most { $max } AutoModerator rules
+invalid_automod_rule = The rule "{ $rule }" is invalid: { $problem }
+invalid_filter_domain = A domain filter's pattern must be a domain such as example.com
+domain_filter_cannot_mask = Domain filters can only queue or block
+account_requirements_unmet = Your account must be at least { $days } days old and have at least { $karma } karma to do this
+invalid_account_requirement = The account requirement is invalid: { $problem }
+invalid_appeal_message = Appeals and responses to them must be between 1 and { $max } characters
+nothing_to_appeal = There is no removal, ban or suspension of yours here to appeal
+appeal_pending = You already have an appeal of this waiting for a decision
+appeal_resolved = This appeal has already been decided
+invalid_mod_note = Mod notes must be between 1 and { $max } characters
+parent_not_on_post = The comment being replied to isn't on this post
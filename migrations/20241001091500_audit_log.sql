@@ -0,0 +1,56 @@
+CREATE TABLE audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    table_name TEXT NOT NULL,
+    row_key TEXT NOT NULL,
+    operation TEXT NOT NULL,
+    old_data JSONB,
+    new_data JSONB,
+    changed_by INTEGER,
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_log_table_row ON audit_log(table_name, row_key);
+CREATE INDEX idx_audit_log_changed_at ON audit_log(changed_at);
+
+-- Generic row audit trigger.
+-- TG_ARGV[0] is the key column of the audited table; any further arguments
+-- name columns that must never be copied into the audit log (e.g. password hashes).
+-- The acting user is read from the transaction-local `ferris.actor_id` setting when present.
+CREATE FUNCTION audit_row_change() RETURNS TRIGGER AS $$
+DECLARE
+    old_row JSONB;
+    new_row JSONB;
+    excluded TEXT[] := TG_ARGV[1:TG_NARGS - 1];
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        old_row := to_jsonb(OLD) - excluded;
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        new_row := to_jsonb(NEW) - excluded;
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND old_row = new_row THEN
+        RETURN NEW;
+    END IF;
+
+    INSERT INTO audit_log (table_name, row_key, operation, old_data, new_data, changed_by)
+    VALUES (
+        TG_TABLE_NAME,
+        COALESCE(new_row, old_row) ->> TG_ARGV[0],
+        TG_OP,
+        old_row,
+        new_row,
+        NULLIF(current_setting('ferris.actor_id', true), '')::INTEGER
+    );
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_users
+AFTER INSERT OR UPDATE OR DELETE ON users
+FOR EACH ROW EXECUTE FUNCTION audit_row_change('id', 'password_hash');
+
+CREATE TRIGGER audit_subs
+AFTER INSERT OR UPDATE OR DELETE ON subs
+FOR EACH ROW EXECUTE FUNCTION audit_row_change('name');
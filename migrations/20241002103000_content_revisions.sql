@@ -0,0 +1,21 @@
+CREATE TABLE post_revisions (
+    post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (post_id, revision)
+);
+
+CREATE TABLE comment_revisions (
+    comment_id UUID NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (comment_id, revision)
+);
+
+INSERT INTO post_revisions (post_id, revision, content, created_at)
+SELECT id, 1, content, timestamp FROM posts;
+
+INSERT INTO comment_revisions (comment_id, revision, content, created_at)
+SELECT id, 1, content, timestamp FROM comments;
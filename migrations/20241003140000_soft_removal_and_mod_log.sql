@@ -0,0 +1,19 @@
+ALTER TABLE posts ADD COLUMN removed_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE posts ADD COLUMN removed_by INTEGER REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE comments ADD COLUMN removed_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE comments ADD COLUMN removed_by INTEGER REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE TABLE mod_log (
+    id BIGSERIAL PRIMARY KEY,
+    moderator_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    action TEXT NOT NULL,
+    target_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    sub TEXT REFERENCES subs(name) ON DELETE SET NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_mod_log_moderator_id ON mod_log(moderator_id);
+CREATE INDEX idx_mod_log_target_user_id ON mod_log(target_user_id);
+CREATE INDEX idx_mod_log_created_at ON mod_log(created_at);
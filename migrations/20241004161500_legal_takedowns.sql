@@ -0,0 +1,42 @@
+ALTER TABLE posts ADD COLUMN removal_kind TEXT CHECK (removal_kind IN ('moderator', 'legal'));
+ALTER TABLE comments ADD COLUMN removal_kind TEXT CHECK (removal_kind IN ('moderator', 'legal'));
+
+UPDATE posts SET removal_kind = 'moderator' WHERE removed_at IS NOT NULL;
+UPDATE comments SET removal_kind = 'moderator' WHERE removed_at IS NOT NULL;
+
+ALTER TABLE posts ADD CONSTRAINT chk_posts_removal_kind
+CHECK ((removed_at IS NULL) = (removal_kind IS NULL));
+ALTER TABLE comments ADD CONSTRAINT chk_comments_removal_kind
+CHECK ((removed_at IS NULL) = (removal_kind IS NULL));
+
+CREATE TABLE takedown_cases (
+    id UUID PRIMARY KEY,
+    status TEXT NOT NULL DEFAULT 'received'
+        CHECK (status IN ('received', 'actioned', 'rejected', 'restored')),
+    complainant_name TEXT NOT NULL,
+    complainant_contact TEXT NOT NULL,
+    claim TEXT NOT NULL,
+    handled_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE takedown_items (
+    case_id UUID NOT NULL REFERENCES takedown_cases(id) ON DELETE CASCADE,
+    post_id UUID REFERENCES posts(id) ON DELETE CASCADE,
+    comment_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    CHECK ((post_id IS NULL) <> (comment_id IS NULL))
+);
+
+CREATE TABLE takedown_documents (
+    id BIGSERIAL PRIMARY KEY,
+    case_id UUID NOT NULL REFERENCES takedown_cases(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('notice', 'counter_notice', 'correspondence')),
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_takedown_cases_status ON takedown_cases(status);
+CREATE INDEX idx_takedown_items_case_id ON takedown_items(case_id);
+CREATE INDEX idx_takedown_documents_case_id ON takedown_documents(case_id);
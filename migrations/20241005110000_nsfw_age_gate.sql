@@ -0,0 +1,5 @@
+ALTER TABLE users ADD COLUMN date_of_birth DATE;
+ALTER TABLE users ADD COLUMN nsfw_acknowledged_at TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE subs ADD COLUMN nsfw BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE posts ADD COLUMN nsfw BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,6 @@
+-- ISO 639-1 code of the language a post is written in; NULL when unknown
+ALTER TABLE posts ADD COLUMN language TEXT;
+CREATE INDEX idx_posts_language ON posts (language);
+
+-- Languages a user wants to see in site-wide listings; empty means all
+ALTER TABLE users ADD COLUMN preferred_languages TEXT[] NOT NULL DEFAULT '{}';
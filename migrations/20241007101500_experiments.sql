@@ -0,0 +1,18 @@
+CREATE TABLE experiments (
+    key TEXT PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT '',
+    variants TEXT[] NOT NULL CHECK (cardinality(variants) > 0),
+    ramp_percent INTEGER NOT NULL DEFAULT 0 CHECK (ramp_percent BETWEEN 0 AND 100),
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- First time each user was shown a variant; later exposures don't add rows
+CREATE TABLE experiment_exposures (
+    experiment_key TEXT NOT NULL REFERENCES experiments(key) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    variant TEXT NOT NULL,
+    exposed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (experiment_key, user_id)
+);
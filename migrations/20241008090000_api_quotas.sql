@@ -0,0 +1,20 @@
+-- Keys for third-party clients and bots. Only a SHA-256 of the key is stored.
+CREATE TABLE api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    key_hash TEXT NOT NULL UNIQUE,
+    label TEXT NOT NULL DEFAULT '',
+    tier TEXT NOT NULL DEFAULT 'free' CHECK (tier IN ('free', 'standard', 'partner')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
+
+-- Request counts per key and UTC day; monthly usage is the sum over the month's days
+CREATE TABLE api_usage (
+    api_key_id BIGINT NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    requests BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (api_key_id, day)
+);
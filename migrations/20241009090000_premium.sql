@@ -0,0 +1,25 @@
+-- Kept in step with premium_subscriptions by grants, payment webhooks and the expiry job
+ALTER TABLE users ADD COLUMN is_premium BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE premium_subscriptions (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    source TEXT NOT NULL CHECK (source IN ('manual', 'payment')),
+    -- The payment provider's subscription id; NULL for manual grants
+    external_id TEXT UNIQUE,
+    granted_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    starts_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    cancelled_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX premium_subscriptions_user_id_idx ON premium_subscriptions (user_id, expires_at);
+
+-- Every payment webhook delivery processed, so redelivered events are applied once
+CREATE TABLE payment_webhook_events (
+    event_id TEXT PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
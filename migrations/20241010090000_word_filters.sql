@@ -0,0 +1,23 @@
+-- Content caught by a word filter's 'queue' action is held, hidden like a removal,
+-- until a moderator approves it
+ALTER TABLE posts DROP CONSTRAINT posts_removal_kind_check;
+ALTER TABLE posts ADD CONSTRAINT posts_removal_kind_check
+CHECK (removal_kind IN ('moderator', 'legal', 'filter'));
+ALTER TABLE comments DROP CONSTRAINT comments_removal_kind_check;
+ALTER TABLE comments ADD CONSTRAINT comments_removal_kind_check
+CHECK (removal_kind IN ('moderator', 'legal', 'filter'));
+
+CREATE TABLE word_filters (
+    id BIGSERIAL PRIMARY KEY,
+    -- NULL applies site-wide
+    sub TEXT REFERENCES subs(name) ON DELETE CASCADE,
+    pattern TEXT NOT NULL,
+    match_kind TEXT NOT NULL CHECK (match_kind IN ('exact', 'wildcard', 'regex')),
+    -- ISO 639-1 code of the language the list is for; NULL applies to all languages
+    locale TEXT,
+    action TEXT NOT NULL CHECK (action IN ('mask', 'queue', 'block')),
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX word_filters_sub_idx ON word_filters (sub);
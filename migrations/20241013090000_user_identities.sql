@@ -0,0 +1,20 @@
+-- Accounts at external OAuth providers linked to local users. An account can link
+-- several providers but only one identity per provider.
+CREATE TABLE user_identities (
+    provider TEXT NOT NULL CHECK (provider IN ('github', 'google')),
+    subject TEXT NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    email TEXT,
+    linked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (provider, subject),
+    UNIQUE (user_id, provider)
+);
+
+-- In-flight authorization requests. The state is single use; `link_user_id` is set when a
+-- signed-in user is adding a provider rather than signing in.
+CREATE TABLE oauth_states (
+    state_hash TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    link_user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,17 @@
+ALTER TABLE users
+    ADD COLUMN email TEXT,
+    ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+CREATE UNIQUE INDEX users_email_key ON users (LOWER(email));
+
+-- Outstanding verification links. Only a SHA-256 of the token is stored; `email` is the
+-- address the link was sent to, so a link stops working if the address changes.
+CREATE TABLE email_verifications (
+    token_hash TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX email_verifications_user_id_idx ON email_verifications (user_id);
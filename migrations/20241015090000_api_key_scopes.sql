@@ -0,0 +1,3 @@
+-- Keys can now authenticate as their owner. Keys created before that only identified
+-- clients for quotas, so they keep read access only.
+ALTER TABLE api_keys ADD COLUMN scopes TEXT[] NOT NULL DEFAULT '{read}';
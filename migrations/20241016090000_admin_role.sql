@@ -0,0 +1,5 @@
+-- Site admins, above moderators. Moderators could reach every admin endpoint until
+-- now, so they keep that access.
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE users SET is_admin = TRUE WHERE is_moderator;
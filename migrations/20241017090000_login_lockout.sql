@@ -0,0 +1,14 @@
+-- Failed password checks, kept until the window they count towards has passed or the
+-- user signs in successfully
+CREATE TABLE login_failures (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    failed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX login_failures_user_id_idx ON login_failures (user_id, failed_at);
+
+CREATE TABLE account_lockouts (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    locked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Access tokens revoked before they expire, by `jti`. Rows are only needed until the
+-- token would have expired anyway.
+CREATE TABLE revoked_access_tokens (
+    jti UUID PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX revoked_access_tokens_expires_at_idx ON revoked_access_tokens (expires_at);
+
+-- Signing out of all devices rejects every access token issued before this time.
+ALTER TABLE users ADD COLUMN tokens_revoked_at TIMESTAMPTZ;
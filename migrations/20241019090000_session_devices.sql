@@ -0,0 +1,11 @@
+-- Device details for the session list. Browser sessions get an id that can be shown to
+-- the user, since the token hash can't be; refresh token logins use their family id.
+ALTER TABLE sessions
+    ADD COLUMN id UUID NOT NULL DEFAULT gen_random_uuid() UNIQUE,
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN ip_address TEXT,
+    ADD COLUMN last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+ALTER TABLE refresh_tokens
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN ip_address TEXT;
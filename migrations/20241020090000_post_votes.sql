@@ -0,0 +1,13 @@
+-- One vote per user and post, up (1) or down (-1). `posts.score` is the sum, kept up to
+-- date as votes change so listings don't have to aggregate.
+CREATE TABLE post_votes (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    value SMALLINT NOT NULL CHECK (value IN (-1, 1)),
+    voted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, post_id)
+);
+
+CREATE INDEX post_votes_post_id_idx ON post_votes (post_id);
+
+ALTER TABLE posts ADD COLUMN score INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- Comment votes work like post votes: one per user and comment, summed into
+-- `comments.score` as they change.
+CREATE TABLE comment_votes (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    comment_id UUID NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+    value SMALLINT NOT NULL CHECK (value IN (-1, 1)),
+    voted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, comment_id)
+);
+
+CREATE INDEX comment_votes_comment_id_idx ON comment_votes (comment_id);
+
+ALTER TABLE comments ADD COLUMN score INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,37 @@
+-- Karma is the net score of a user's posts and comments. Vote changes update these
+-- totals and the per-sub rows in the same transaction, so nothing is summed per request.
+ALTER TABLE users
+    ADD COLUMN post_karma INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN comment_karma INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE user_sub_karma (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    sub TEXT NOT NULL,
+    post_karma INTEGER NOT NULL DEFAULT 0,
+    comment_karma INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, sub)
+);
+
+-- Votes cast before this migration.
+INSERT INTO user_sub_karma (user_id, sub, post_karma, comment_karma)
+SELECT user_id, sub, SUM(post_karma), SUM(comment_karma)
+FROM (
+    SELECT posts.user_id, posts.sub, posts.score AS post_karma, 0 AS comment_karma
+    FROM posts
+    UNION ALL
+    SELECT comments.user_id, posts.sub, 0, comments.score
+    FROM comments
+    JOIN posts ON posts.id = comments.post_id
+) AS scores
+GROUP BY user_id, sub
+HAVING SUM(post_karma) <> 0 OR SUM(comment_karma) <> 0;
+
+UPDATE users
+SET post_karma = totals.post_karma, comment_karma = totals.comment_karma
+FROM (
+    SELECT user_id, SUM(post_karma)::INTEGER AS post_karma,
+        SUM(comment_karma)::INTEGER AS comment_karma
+    FROM user_sub_karma
+    GROUP BY user_id
+) AS totals
+WHERE users.id = totals.user_id;
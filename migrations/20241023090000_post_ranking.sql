@@ -0,0 +1,42 @@
+-- Vote counts for the controversial sort, kept up to date alongside `posts.score`.
+ALTER TABLE posts
+    ADD COLUMN upvotes INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN downvotes INTEGER NOT NULL DEFAULT 0;
+
+UPDATE posts
+SET upvotes = counts.upvotes, downvotes = counts.downvotes
+FROM (
+    SELECT post_id, COUNT(*) FILTER (WHERE value = 1)::INTEGER AS upvotes,
+        COUNT(*) FILTER (WHERE value = -1)::INTEGER AS downvotes
+    FROM post_votes
+    GROUP BY post_id
+) AS counts
+WHERE posts.id = counts.post_id;
+
+-- Reddit's hot ranking: every tenfold increase in score is worth as much as being 12.5
+-- hours newer. Mirrors `repo::post::hot_rank`.
+CREATE FUNCTION hot_rank(score INTEGER, posted_at TIMESTAMPTZ) RETURNS DOUBLE PRECISION AS $$
+    SELECT SIGN(score)::DOUBLE PRECISION * LOG(GREATEST(ABS(score), 1)::DOUBLE PRECISION)
+        + (EXTRACT(EPOCH FROM posted_at)::DOUBLE PRECISION - 1134028003) / 45000
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Many votes split evenly rank highest. Mirrors `repo::post::controversy_rank`.
+CREATE FUNCTION controversy_rank(upvotes INTEGER, downvotes INTEGER)
+RETURNS DOUBLE PRECISION AS $$
+    SELECT CASE
+        WHEN upvotes <= 0 OR downvotes <= 0 THEN 0
+        ELSE POWER(
+            (upvotes + downvotes)::DOUBLE PRECISION,
+            LEAST(upvotes, downvotes)::DOUBLE PRECISION / GREATEST(upvotes, downvotes)
+        )
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE INDEX idx_posts_sub_hot ON posts (sub, hot_rank(score, timestamp) DESC);
+CREATE INDEX idx_posts_sub_score ON posts (sub, score DESC);
+CREATE INDEX idx_posts_sub_controversy ON posts (sub, controversy_rank(upvotes, downvotes) DESC);
+CREATE INDEX idx_posts_sub_timestamp ON posts (sub, timestamp DESC);
+CREATE INDEX idx_posts_hot ON posts (hot_rank(score, timestamp) DESC);
+CREATE INDEX idx_posts_score ON posts (score DESC);
+CREATE INDEX idx_posts_controversy ON posts (controversy_rank(upvotes, downvotes) DESC);
+CREATE INDEX idx_posts_timestamp ON posts (timestamp DESC);
@@ -0,0 +1,16 @@
+-- The value a post listing sorts on, highest first. Listings page on (rank, id), so the
+-- same expression is needed in the select list, the cursor condition and the ordering.
+CREATE FUNCTION listing_rank(
+    sort TEXT,
+    score INTEGER,
+    upvotes INTEGER,
+    downvotes INTEGER,
+    posted_at TIMESTAMPTZ
+) RETURNS DOUBLE PRECISION AS $$
+    SELECT CASE sort
+        WHEN 'hot' THEN hot_rank(score, posted_at)
+        WHEN 'top' THEN score::DOUBLE PRECISION
+        WHEN 'controversial' THEN controversy_rank(upvotes, downvotes)
+        ELSE EXTRACT(EPOCH FROM posted_at)::DOUBLE PRECISION
+    END
+$$ LANGUAGE SQL IMMUTABLE;
@@ -0,0 +1,2 @@
+-- Comment trees walk from each comment to its replies.
+CREATE INDEX idx_comments_parent_id ON comments (parent_id);
@@ -0,0 +1,49 @@
+-- Vote counts for the best and controversial comment sorts, kept up to date alongside
+-- `comments.score`.
+ALTER TABLE comments
+    ADD COLUMN upvotes INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN downvotes INTEGER NOT NULL DEFAULT 0;
+
+UPDATE comments
+SET upvotes = counts.upvotes, downvotes = counts.downvotes
+FROM (
+    SELECT comment_id, COUNT(*) FILTER (WHERE value = 1)::INTEGER AS upvotes,
+        COUNT(*) FILTER (WHERE value = -1)::INTEGER AS downvotes
+    FROM comment_votes
+    GROUP BY comment_id
+) AS counts
+WHERE comments.id = counts.comment_id;
+
+-- The lower bound of the Wilson score interval for the share of upvotes, at 95%
+-- confidence: a few votes count for less than many at the same ratio. Mirrors
+-- `repo::comment::wilson_rank`.
+CREATE FUNCTION wilson_rank(upvotes INTEGER, downvotes INTEGER) RETURNS DOUBLE PRECISION AS $$
+    SELECT CASE
+        WHEN upvotes + downvotes <= 0 THEN 0
+        ELSE (
+            upvotes::DOUBLE PRECISION / (upvotes + downvotes)
+            + 1.9208 / (upvotes + downvotes)
+            - 1.96 * SQRT(
+                upvotes::DOUBLE PRECISION * downvotes / (upvotes + downvotes) + 0.9604
+            ) / (upvotes + downvotes)
+        ) / (1 + 3.8416 / (upvotes + downvotes))
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- The value comment listings and trees sort on, highest first, like `listing_rank`
+-- for posts.
+CREATE FUNCTION comment_rank(
+    sort TEXT,
+    score INTEGER,
+    upvotes INTEGER,
+    downvotes INTEGER,
+    posted_at TIMESTAMPTZ
+) RETURNS DOUBLE PRECISION AS $$
+    SELECT CASE sort
+        WHEN 'best' THEN wilson_rank(upvotes, downvotes)
+        WHEN 'top' THEN score::DOUBLE PRECISION
+        WHEN 'controversial' THEN controversy_rank(upvotes, downvotes)
+        WHEN 'old' THEN -EXTRACT(EPOCH FROM posted_at)::DOUBLE PRECISION
+        ELSE EXTRACT(EPOCH FROM posted_at)::DOUBLE PRECISION
+    END
+$$ LANGUAGE SQL IMMUTABLE;
@@ -0,0 +1,20 @@
+-- Full-text search over posts. Titles weigh more than content when ranking matches.
+ALTER TABLE posts ADD COLUMN search_vector TSVECTOR;
+
+CREATE FUNCTION posts_search_vector() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector := setweight(to_tsvector('english', NEW.title), 'A')
+        || setweight(to_tsvector('english', NEW.content), 'B');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER posts_search_vector
+BEFORE INSERT OR UPDATE OF title, content ON posts
+FOR EACH ROW EXECUTE FUNCTION posts_search_vector();
+
+UPDATE posts
+SET search_vector = setweight(to_tsvector('english', title), 'A')
+    || setweight(to_tsvector('english', content), 'B');
+
+CREATE INDEX idx_posts_search ON posts USING GIN (search_vector);
@@ -0,0 +1,17 @@
+-- Full-text search over comments, maintained like `posts.search_vector`.
+ALTER TABLE comments ADD COLUMN search_vector TSVECTOR;
+
+CREATE FUNCTION comments_search_vector() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector := to_tsvector('english', NEW.content);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER comments_search_vector
+BEFORE INSERT OR UPDATE OF content ON comments
+FOR EACH ROW EXECUTE FUNCTION comments_search_vector();
+
+UPDATE comments SET search_vector = to_tsvector('english', content);
+
+CREATE INDEX idx_comments_search ON comments USING GIN (search_vector);
@@ -0,0 +1,5 @@
+-- Trigram indexes so user and sub searches still match misspelled names.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_users_username_trgm ON users USING GIN (username gin_trgm_ops);
+CREATE INDEX idx_subs_name_trgm ON subs USING GIN (name gin_trgm_ops);
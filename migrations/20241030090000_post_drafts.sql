@@ -0,0 +1,4 @@
+ALTER TABLE posts ADD COLUMN status TEXT NOT NULL DEFAULT 'published'
+    CHECK (status IN ('draft', 'published'));
+
+CREATE INDEX idx_posts_drafts ON posts(user_id, timestamp) WHERE status = 'draft';
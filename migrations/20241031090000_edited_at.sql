@@ -0,0 +1,20 @@
+ALTER TABLE posts ADD COLUMN edited_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE comments ADD COLUMN edited_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE posts SET edited_at = latest.created_at
+FROM (
+    SELECT post_id, MAX(created_at) AS created_at
+    FROM post_revisions
+    WHERE revision > 1
+    GROUP BY post_id
+) AS latest
+WHERE posts.id = latest.post_id;
+
+UPDATE comments SET edited_at = latest.created_at
+FROM (
+    SELECT comment_id, MAX(created_at) AS created_at
+    FROM comment_revisions
+    WHERE revision > 1
+    GROUP BY comment_id
+) AS latest
+WHERE comments.id = latest.comment_id;
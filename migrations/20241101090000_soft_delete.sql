@@ -0,0 +1,12 @@
+ALTER TABLE posts DROP CONSTRAINT posts_removal_kind_check;
+ALTER TABLE posts ADD CONSTRAINT posts_removal_kind_check
+CHECK (removal_kind IN ('moderator', 'legal', 'filter', 'deleted'));
+ALTER TABLE comments DROP CONSTRAINT comments_removal_kind_check;
+ALTER TABLE comments ADD CONSTRAINT comments_removal_kind_check
+CHECK (removal_kind IN ('moderator', 'legal', 'filter', 'deleted'));
+
+-- Authors' deletions now keep the row, so only an admin purge deletes a comment, and
+-- its replies go with it.
+ALTER TABLE comments DROP CONSTRAINT comments_parent_id_fkey;
+ALTER TABLE comments ADD CONSTRAINT comments_parent_id_fkey
+FOREIGN KEY (parent_id) REFERENCES comments(id) ON DELETE CASCADE;
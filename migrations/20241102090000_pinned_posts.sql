@@ -0,0 +1,3 @@
+ALTER TABLE posts ADD COLUMN pin_order INTEGER;
+
+CREATE INDEX idx_posts_pinned ON posts(sub, pin_order) WHERE pin_order IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Locked threads stay readable but take no new comments.
+ALTER TABLE posts ADD COLUMN locked BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE posts ADD COLUMN lock_reason TEXT;
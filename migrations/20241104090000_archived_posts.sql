@@ -0,0 +1,5 @@
+-- Old posts are archived by a background job and take no more votes or comments.
+-- Posts an admin unarchives are exempt so the job doesn't archive them again.
+ALTER TABLE posts ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE posts ADD COLUMN archive_exempt BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE INDEX idx_posts_archivable ON posts (timestamp) WHERE NOT archived AND NOT archive_exempt;
@@ -0,0 +1,11 @@
+CREATE TABLE flairs (
+    id SERIAL PRIMARY KEY,
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    color TEXT NOT NULL CHECK (color ~ '^#[0-9a-f]{6}$'),
+    mod_only BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (sub, text)
+);
+
+ALTER TABLE posts ADD COLUMN flair_id INTEGER REFERENCES flairs(id) ON DELETE SET NULL;
+CREATE INDEX idx_posts_flair_id ON posts (flair_id) WHERE flair_id IS NOT NULL;
@@ -0,0 +1,27 @@
+-- Flair moderators offer for users to show next to their name in a sub.
+CREATE TABLE user_flairs (
+    id SERIAL PRIMARY KEY,
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    color TEXT NOT NULL CHECK (color ~ '^#[0-9a-f]{6}$'),
+    UNIQUE (sub, text)
+);
+
+CREATE TABLE user_flair_choices (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE,
+    flair_id INTEGER NOT NULL REFERENCES user_flairs(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, sub)
+);
+
+CREATE TYPE author_flair AS (text TEXT, color TEXT);
+
+-- The flair the author of a post, or of a comment on it, picked in the post's sub.
+CREATE FUNCTION author_flair(author_id INTEGER, post_id UUID) RETURNS author_flair
+LANGUAGE sql STABLE AS $$
+    SELECT ROW(user_flairs.text, user_flairs.color)::author_flair
+    FROM posts
+    INNER JOIN user_flair_choices ON user_flair_choices.sub = posts.sub
+    INNER JOIN user_flairs ON user_flairs.id = user_flair_choices.flair_id
+    WHERE posts.id = $2 AND user_flair_choices.user_id = $1
+$$;
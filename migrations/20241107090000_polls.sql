@@ -0,0 +1,25 @@
+CREATE TABLE polls (
+    post_id UUID PRIMARY KEY REFERENCES posts(id) ON DELETE CASCADE,
+    ends_at TIMESTAMPTZ,
+    hide_results BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE TABLE poll_options (
+    id SERIAL PRIMARY KEY,
+    post_id UUID NOT NULL REFERENCES polls(post_id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    UNIQUE (post_id, position),
+    -- Lets poll_votes check that the option belongs to the poll voted in
+    UNIQUE (post_id, id)
+);
+
+-- One vote per user and poll.
+CREATE TABLE poll_votes (
+    post_id UUID NOT NULL REFERENCES polls(post_id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    option_id INTEGER NOT NULL,
+    voted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (post_id, user_id),
+    FOREIGN KEY (post_id, option_id) REFERENCES poll_options(post_id, id) ON DELETE CASCADE
+);
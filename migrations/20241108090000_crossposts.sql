@@ -0,0 +1,3 @@
+ALTER TABLE posts ADD COLUMN crosspost_parent_id UUID REFERENCES posts(id) ON DELETE SET NULL;
+CREATE INDEX idx_posts_crosspost_parent_id ON posts (crosspost_parent_id)
+    WHERE crosspost_parent_id IS NOT NULL;
@@ -0,0 +1,9 @@
+ALTER TABLE posts ADD COLUMN link_url TEXT;
+
+CREATE TABLE link_previews (
+    post_id UUID PRIMARY KEY REFERENCES posts(id) ON DELETE CASCADE,
+    title TEXT,
+    description TEXT,
+    thumbnail_url TEXT,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,9 @@
+CREATE TABLE media_renditions (
+    media_id UUID NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    storage_key TEXT NOT NULL UNIQUE,
+    url TEXT NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    PRIMARY KEY (media_id, kind)
+);
@@ -0,0 +1,3 @@
+ALTER TABLE posts ADD COLUMN spoiler BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE subs ADD COLUMN spoiler_by_default BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN show_nsfw BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,11 @@
+CREATE TABLE saved_items (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    post_id UUID REFERENCES posts(id) ON DELETE CASCADE,
+    comment_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    saved_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((post_id IS NULL) <> (comment_id IS NULL))
+);
+
+CREATE UNIQUE INDEX saved_items_user_post ON saved_items (user_id, post_id);
+CREATE UNIQUE INDEX saved_items_user_comment ON saved_items (user_id, comment_id);
@@ -0,0 +1,3 @@
+ALTER TABLE posts ADD COLUMN publish_at TIMESTAMPTZ;
+
+CREATE INDEX idx_posts_scheduled ON posts(publish_at) WHERE status = 'draft' AND publish_at IS NOT NULL;
@@ -0,0 +1,12 @@
+CREATE TABLE post_templates (
+    id SERIAL PRIMARY KEY,
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE,
+    author_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    repeat TEXT NOT NULL CHECK (repeat IN ('daily', 'weekly', 'monthly')),
+    next_run_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_post_templates_next_run ON post_templates (next_run_at);
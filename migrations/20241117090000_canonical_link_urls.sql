@@ -0,0 +1,4 @@
+ALTER TABLE posts ADD COLUMN canonical_url TEXT;
+
+CREATE INDEX idx_posts_canonical_url ON posts (sub, canonical_url, timestamp)
+    WHERE canonical_url IS NOT NULL;
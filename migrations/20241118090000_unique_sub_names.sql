@@ -0,0 +1 @@
+CREATE UNIQUE INDEX idx_subs_name_lower ON subs (LOWER(name));
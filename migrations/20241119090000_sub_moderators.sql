@@ -0,0 +1,18 @@
+CREATE TABLE sub_moderators (
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    added_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (sub, user_id)
+);
+
+CREATE INDEX idx_sub_moderators_user_id ON sub_moderators (user_id);
+
+-- Site-wide moderators keep moderating every sub that exists today.
+INSERT INTO sub_moderators (sub, user_id)
+SELECT subs.name, users.id
+FROM subs
+CROSS JOIN users
+WHERE users.is_moderator;
+
+ALTER TABLE users DROP COLUMN is_moderator;
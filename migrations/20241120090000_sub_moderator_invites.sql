@@ -0,0 +1,9 @@
+CREATE TABLE sub_moderator_invites (
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    invited_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    invited_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (sub, user_id)
+);
+
+CREATE INDEX idx_sub_moderator_invites_user_id ON sub_moderator_invites (user_id);
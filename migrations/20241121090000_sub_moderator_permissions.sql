@@ -0,0 +1,3 @@
+-- A bitset of `ModPermission`s; 16 is `full`, which existing moderators keep.
+ALTER TABLE sub_moderators ADD COLUMN permissions INTEGER NOT NULL DEFAULT 16;
+ALTER TABLE sub_moderator_invites ADD COLUMN permissions INTEGER NOT NULL DEFAULT 16;
@@ -0,0 +1,10 @@
+CREATE TABLE sub_rules (
+    id SERIAL PRIMARY KEY,
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    position INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_sub_rules_sub ON sub_rules (sub, position);
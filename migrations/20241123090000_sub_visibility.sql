@@ -0,0 +1,23 @@
+-- `public` subs are open to everyone; `restricted` ones can be read by everyone but
+-- only approved members may post or comment; `private` ones are for members only.
+ALTER TABLE subs ADD COLUMN visibility TEXT NOT NULL DEFAULT 'public'
+    CHECK (visibility IN ('public', 'restricted', 'private'));
+
+CREATE TABLE sub_members (
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    approved_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    approved_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (sub, user_id)
+);
+
+CREATE INDEX idx_sub_members_user_id ON sub_members (user_id);
+
+-- Whether `viewer` may take part in the sub: approved members, its moderators and
+-- admins may. Anonymous viewers (NULL) never may.
+CREATE FUNCTION sub_member(sub_name TEXT, viewer INTEGER) RETURNS BOOLEAN
+LANGUAGE sql STABLE AS $$
+    SELECT EXISTS(SELECT 1 FROM sub_members WHERE sub = sub_name AND user_id = viewer)
+        OR EXISTS(SELECT 1 FROM sub_moderators WHERE sub = sub_name AND user_id = viewer)
+        OR EXISTS(SELECT 1 FROM users WHERE id = viewer AND is_admin)
+$$;
@@ -0,0 +1,9 @@
+-- Users asking to become members of a restricted or private sub, until a moderator
+-- approves or denies them.
+CREATE TABLE sub_join_requests (
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    message TEXT NOT NULL DEFAULT '',
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (sub, user_id)
+);
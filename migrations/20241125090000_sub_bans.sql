@@ -0,0 +1,12 @@
+-- Users barred from posting and commenting in a sub, for good or until `expires_at`.
+CREATE TABLE sub_bans (
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    banned_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    banned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    PRIMARY KEY (sub, user_id)
+);
+
+CREATE INDEX idx_sub_bans_expires_at ON sub_bans (expires_at) WHERE expires_at IS NOT NULL;
@@ -0,0 +1,6 @@
+-- How frontends brand a sub: an icon and banner picked from uploads, and `#rrggbb` theme colors.
+ALTER TABLE subs
+    ADD COLUMN icon_media_id UUID REFERENCES media(id) ON DELETE SET NULL,
+    ADD COLUMN banner_media_id UUID REFERENCES media(id) ON DELETE SET NULL,
+    ADD COLUMN primary_color TEXT,
+    ADD COLUMN accent_color TEXT;
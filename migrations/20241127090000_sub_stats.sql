@@ -0,0 +1,13 @@
+-- Each sub's subscribers, and what it gained over the last week, recounted by a
+-- background job for the trending and popular listings.
+CREATE TABLE sub_stats (
+    sub TEXT PRIMARY KEY REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    subscribers BIGINT NOT NULL,
+    new_subscribers BIGINT NOT NULL,
+    recent_posts BIGINT NOT NULL,
+    recent_comments BIGINT NOT NULL,
+    trending_score DOUBLE PRECISION NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_sub_stats_trending_score ON sub_stats (trending_score DESC);
@@ -0,0 +1,25 @@
+-- Each sub's wiki: pages at their latest revision, and every revision saved.
+CREATE TABLE sub_wiki_pages (
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    page TEXT NOT NULL,
+    content TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    editors TEXT NOT NULL DEFAULT 'moderators'
+        CHECK (editors IN ('moderators', 'members', 'anyone')),
+    edited_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    edited_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (sub, page)
+);
+
+CREATE TABLE sub_wiki_revisions (
+    sub TEXT NOT NULL,
+    page TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    edited_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (sub, page, revision),
+    FOREIGN KEY (sub, page) REFERENCES sub_wiki_pages(sub, page)
+        ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,15 @@
+-- Named collections of subs a user reads together as one feed.
+CREATE TABLE custom_feeds (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, name)
+);
+
+CREATE TABLE custom_feed_subs (
+    user_id INTEGER NOT NULL,
+    feed TEXT NOT NULL,
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    PRIMARY KEY (user_id, feed, sub),
+    FOREIGN KEY (user_id, feed) REFERENCES custom_feeds(user_id, name) ON DELETE CASCADE
+);
@@ -0,0 +1,17 @@
+CREATE TABLE reports (
+    id BIGSERIAL PRIMARY KEY,
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    post_id UUID REFERENCES posts(id) ON DELETE CASCADE,
+    comment_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    reporter_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rule_id INTEGER REFERENCES sub_rules(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((post_id IS NULL) <> (comment_id IS NULL))
+);
+
+CREATE UNIQUE INDEX idx_reports_post_reporter ON reports (post_id, reporter_id)
+    WHERE post_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_reports_comment_reporter ON reports (comment_id, reporter_id)
+    WHERE comment_id IS NOT NULL;
+CREATE INDEX idx_reports_sub ON reports (sub, created_at);
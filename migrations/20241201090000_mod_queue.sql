@@ -0,0 +1,10 @@
+ALTER TABLE reports ADD COLUMN resolved_at TIMESTAMPTZ;
+ALTER TABLE reports ADD COLUMN resolved_by INTEGER REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE posts ADD COLUMN approved_at TIMESTAMPTZ;
+ALTER TABLE posts ADD COLUMN approved_by INTEGER REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE comments ADD COLUMN approved_at TIMESTAMPTZ;
+ALTER TABLE comments ADD COLUMN approved_by INTEGER REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_reports_open ON reports (sub) WHERE resolved_at IS NULL;
+CREATE INDEX idx_posts_held ON posts (sub) WHERE removal_kind = 'filter';
@@ -0,0 +1,13 @@
+CREATE TABLE sub_removal_reasons (
+    id SERIAL PRIMARY KEY,
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    title TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_sub_removal_reasons_sub ON sub_removal_reasons (sub, id);
+
+-- The message the author was given, copied so later edits to the template don't change it.
+ALTER TABLE posts ADD COLUMN removal_reason TEXT;
+ALTER TABLE comments ADD COLUMN removal_reason TEXT;
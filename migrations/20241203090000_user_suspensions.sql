@@ -0,0 +1,11 @@
+-- Site-wide suspensions. Without expires_at the account is banned until an admin lifts it.
+CREATE TABLE user_suspensions (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    suspended_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    suspended_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_user_suspensions_expires_at ON user_suspensions (expires_at)
+    WHERE expires_at IS NOT NULL;
@@ -0,0 +1,11 @@
+ALTER TABLE users ADD COLUMN shadowbanned BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Whether `viewer` may see content `author` made in `sub_name`. A shadowbanned author's
+-- content is only shown to the author, the sub's moderators and admins.
+CREATE FUNCTION author_visible(author INTEGER, sub_name TEXT, viewer INTEGER) RETURNS BOOLEAN
+LANGUAGE sql STABLE AS $$
+    SELECT NOT EXISTS(SELECT 1 FROM users WHERE id = author AND shadowbanned)
+        OR author IS NOT DISTINCT FROM viewer
+        OR EXISTS(SELECT 1 FROM sub_moderators WHERE sub = sub_name AND user_id = viewer)
+        OR EXISTS(SELECT 1 FROM users WHERE id = viewer AND is_admin)
+$$;
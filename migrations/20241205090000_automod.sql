@@ -0,0 +1,10 @@
+-- Each sub's AutoModerator rules, as the JSON array moderators saved.
+CREATE TABLE sub_automod (
+    sub TEXT PRIMARY KEY REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    rules JSONB NOT NULL DEFAULT '[]',
+    updated_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Reports AutoModerator files have no reporter.
+ALTER TABLE reports ALTER COLUMN reporter_id DROP NOT NULL;
@@ -0,0 +1,4 @@
+-- 'domain' filters match links to a domain and its subdomains
+ALTER TABLE word_filters DROP CONSTRAINT word_filters_match_kind_check;
+ALTER TABLE word_filters ADD CONSTRAINT word_filters_match_kind_check
+CHECK (match_kind IN ('exact', 'wildcard', 'regex', 'domain'));
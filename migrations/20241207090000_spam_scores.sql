@@ -0,0 +1,9 @@
+-- How likely new content looked to be spam when it was submitted, and why. NULL when it
+-- scored nothing.
+ALTER TABLE posts ADD COLUMN spam_score INTEGER;
+ALTER TABLE posts ADD COLUMN spam_reasons TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE comments ADD COLUMN spam_score INTEGER;
+ALTER TABLE comments ADD COLUMN spam_reasons TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX idx_posts_user_timestamp ON posts (user_id, timestamp);
+CREATE INDEX idx_comments_user_timestamp ON comments (user_id, timestamp);
@@ -0,0 +1,16 @@
+-- How old and how well-regarded an account must be to post, comment or start a sub.
+CREATE TABLE account_requirements (
+    id SERIAL PRIMARY KEY,
+    -- NULL applies site-wide
+    sub TEXT REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    contribution TEXT NOT NULL CHECK (contribution IN ('post', 'comment', 'create_sub')),
+    min_account_age_days INTEGER NOT NULL DEFAULT 0 CHECK (min_account_age_days >= 0),
+    min_karma INTEGER NOT NULL DEFAULT 0,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Subs are started outside of any sub
+    CHECK (sub IS NULL OR contribution <> 'create_sub')
+);
+
+CREATE UNIQUE INDEX idx_account_requirements_sub_contribution
+    ON account_requirements ((COALESCE(sub, '')), contribution);
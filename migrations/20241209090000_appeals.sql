@@ -0,0 +1,28 @@
+-- A user asking the sub's moderators to reverse a removal or a ban, or admins to lift
+-- their suspension. `sub` is NULL only for suspensions.
+CREATE TABLE appeals (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL
+        CHECK (kind IN ('post_removal', 'comment_removal', 'sub_ban', 'suspension')),
+    sub TEXT REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    post_id UUID REFERENCES posts(id) ON DELETE CASCADE,
+    comment_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    message TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'granted', 'denied')),
+    resolved_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    response TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ,
+    CHECK ((kind = 'suspension') = (sub IS NULL)),
+    CHECK ((kind = 'post_removal') = (post_id IS NOT NULL)),
+    CHECK ((kind = 'comment_removal') = (comment_id IS NOT NULL))
+);
+
+-- One open appeal per removal, ban or suspension.
+CREATE UNIQUE INDEX idx_appeals_pending ON appeals
+    (user_id, kind, (COALESCE(sub, '')),
+        (COALESCE(post_id, comment_id, '00000000-0000-0000-0000-000000000000')))
+    WHERE status = 'pending';
+CREATE INDEX idx_appeals_sub_status ON appeals (sub, status, created_at);
+CREATE INDEX idx_appeals_user ON appeals (user_id, created_at);
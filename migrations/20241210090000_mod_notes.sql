@@ -0,0 +1,12 @@
+-- Moderators' private notes on a user, kept per sub. Notes outlive the accounts of the
+-- moderators who wrote them.
+CREATE TABLE mod_notes (
+    id BIGSERIAL PRIMARY KEY,
+    sub TEXT NOT NULL REFERENCES subs(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    author_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    note TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_mod_notes_sub_user ON mod_notes (sub, user_id, created_at);
@@ -0,0 +1,9 @@
+-- Bans and suspensions are audited like the users and subs they restrict. Both are
+-- keyed by the user they apply to; a ban's sub is in its data.
+CREATE TRIGGER audit_sub_bans
+AFTER INSERT OR UPDATE OR DELETE ON sub_bans
+FOR EACH ROW EXECUTE FUNCTION audit_row_change('user_id');
+
+CREATE TRIGGER audit_user_suspensions
+AFTER INSERT OR UPDATE OR DELETE ON user_suspensions
+FOR EACH ROW EXECUTE FUNCTION audit_row_change('user_id');
@@ -0,0 +1,7 @@
+-- Karma totals change on every vote, which would bury real account changes in the
+-- audit log. They are left out like password hashes, so a vote alone logs nothing.
+DROP TRIGGER audit_users ON users;
+
+CREATE TRIGGER audit_users
+AFTER INSERT OR UPDATE OR DELETE ON users
+FOR EACH ROW EXECUTE FUNCTION audit_row_change('id', 'password_hash', 'post_karma', 'comment_karma');
@@ -0,0 +1,7 @@
+-- Ties each authorization request to the browser that started it. The browser holds the
+-- nonce in a cookie and the callback only accepts a state whose nonce matches, so a
+-- callback link can't be replayed in someone else's browser. Pending requests from
+-- before this have no nonce and are dropped.
+DELETE FROM oauth_states;
+
+ALTER TABLE oauth_states ADD COLUMN nonce_hash TEXT NOT NULL;
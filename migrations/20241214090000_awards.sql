@@ -0,0 +1,15 @@
+-- Awards given to posts and comments. Which award types a member may give depends on
+-- their premium membership; the type is checked when the award is given. Awards outlive
+-- the accounts of the members who gave them.
+CREATE TABLE awards (
+    id BIGSERIAL PRIMARY KEY,
+    award_type TEXT NOT NULL,
+    giver_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    post_id UUID REFERENCES posts(id) ON DELETE CASCADE,
+    comment_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((post_id IS NULL) <> (comment_id IS NULL))
+);
+
+CREATE INDEX idx_awards_post ON awards (post_id) WHERE post_id IS NOT NULL;
+CREATE INDEX idx_awards_comment ON awards (comment_id) WHERE comment_id IS NOT NULL;
@@ -0,0 +1,170 @@
+use crate::api::filter::require_filter_moderator;
+use crate::auth::AuthenticatedUser;
+use crate::error::ApiError;
+use crate::model::account_requirement::{
+    AccountRequirement, AccountRequirementQuery, Contribution, NewAccountRequirement, Threshold,
+};
+use crate::model::moderation::ModAction;
+use crate::model::user::Role;
+use crate::repo::{
+    account_requirement as account_requirement_repo, moderation as moderation_repo,
+    sub::SubRepository, user::UserRepository,
+};
+use actix_web::{
+    delete, get, http::StatusCode, put, web::Data, web::Json, web::Path, web::Query, HttpResponse,
+};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::PgPool;
+
+/// Refuses the contribution if the user's account is younger, or has less karma, than
+/// the strictest of the site-wide requirements and those of `sub`. Admins and the sub's
+/// moderators are exempt.
+pub async fn require_account_standing(
+    subs: &dyn SubRepository,
+    users: &dyn UserRepository,
+    user_id: i32,
+    sub: Option<&str>,
+    contribution: Contribution,
+) -> Result<(), actix_web::Error> {
+    let requirements = subs
+        .get_account_requirements(sub, contribution)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let threshold = Threshold::strictest(&requirements);
+    if threshold == Threshold::default() {
+        return Ok(());
+    }
+
+    let user = users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if threshold.met_by(&user, Utc::now()) || user.role() == Role::Admin {
+        return Ok(());
+    }
+    if let Some(sub) = sub {
+        let moderates = subs
+            .get_moderator_permissions(user_id, sub)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if moderates.is_some() {
+            return Ok(());
+        }
+    }
+
+    Err(ApiError::forbidden(
+        "account_requirements_unmet",
+        format!(
+            "Your account must be at least {} days old and have at least {} karma to do this",
+            threshold.min_account_age_days, threshold.min_karma
+        ),
+    )
+    .with_arg("days", threshold.min_account_age_days.to_string())
+    .with_arg("karma", threshold.min_karma.to_string())
+    .with_field("requirement", json!(threshold))
+    .into())
+}
+
+/// A sub's requirements are set by its moderators, the site-wide ones by admins. Only
+/// site-wide requirements can cover creating subs.
+#[put("/admin/account_requirements")]
+pub async fn set_account_requirement(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    body: Json<NewAccountRequirement>,
+) -> Result<Json<AccountRequirement>, actix_web::Error> {
+    let body = body.into_inner();
+    let moderator = require_filter_moderator(&pool, caller.user_id, body.sub.as_deref()).await?;
+    if body.sub.is_some() && body.contribution == Contribution::CreateSub {
+        return Err(invalid_account_requirement_error(
+            "creating subs can only be limited site-wide",
+        )
+        .into());
+    }
+    if body.min_account_age_days < 0 {
+        return Err(
+            invalid_account_requirement_error("the minimum account age can't be negative").into(),
+        );
+    }
+
+    let requirement = account_requirement_repo::set_account_requirement(&pool, &body, moderator.id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.id,
+        ModAction::SetAccountRequirement,
+        None,
+        requirement.sub.as_deref(),
+        json!({
+            "requirement_id": requirement.id,
+            "contribution": requirement.contribution,
+            "min_account_age_days": requirement.min_account_age_days,
+            "min_karma": requirement.min_karma,
+        }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(requirement))
+}
+
+/// The site-wide requirements, plus those of `sub` if given. Anyone signed in can see
+/// them, so they know what they need before they try.
+#[get("/admin/account_requirements")]
+pub async fn get_account_requirements(
+    pool: Data<PgPool>,
+    _user: AuthenticatedUser,
+    query: Query<AccountRequirementQuery>,
+) -> Result<Json<Vec<AccountRequirement>>, actix_web::Error> {
+    let requirements =
+        account_requirement_repo::get_account_requirements(&pool, query.sub.as_deref())
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(requirements))
+}
+
+#[delete("/admin/account_requirements/{requirement_id}")]
+pub async fn delete_account_requirement(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let requirement_id = path.into_inner();
+    let requirement = account_requirement_repo::get_account_requirement(&pool, requirement_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Account requirement not found"))?;
+    let moderator =
+        require_filter_moderator(&pool, caller.user_id, requirement.sub.as_deref()).await?;
+
+    account_requirement_repo::delete_account_requirement(&pool, requirement_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.id,
+        ModAction::RemoveAccountRequirement,
+        None,
+        requirement.sub.as_deref(),
+        json!({ "requirement_id": requirement_id, "contribution": requirement.contribution }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!(
+        "Account requirement {} was deleted",
+        requirement_id
+    )))
+}
+
+fn invalid_account_requirement_error(problem: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_account_requirement",
+        format!("The account requirement is invalid: {}", problem),
+    )
+    .with_arg("problem", problem.to_string())
+}
@@ -0,0 +1,220 @@
+use crate::auth::{Admin, AuthenticatedUser, RequireRole};
+use crate::config::RuntimeSettings;
+use crate::error::ApiError;
+use crate::model::api_key::{
+    generate_api_key, hash_api_key, ApiKey, ApiKeySummary, CreatedApiKey, NewApiKey, TierUpdate,
+    UsageReport, KEY_PREFIX,
+};
+use crate::repo::api_key as api_key_repo;
+use actix_web::http::header::{HeaderMap, AUTHORIZATION};
+use actix_web::http::StatusCode;
+use actix_web::{
+    delete, get, post, put, web::Data, web::Json, web::Path, HttpRequest, HttpResponse,
+};
+use chrono::Utc;
+use sqlx::PgPool;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// A key sent in the `X-Api-Key` header, or as a bearer token in place of a JWT. A request
+/// sending both must send the same key in each, so the key that's metered is always the
+/// one that signed the request in.
+pub fn presented_api_key(headers: &HeaderMap) -> Result<Option<&str>, ApiError> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    let Some(value) = headers.get(API_KEY_HEADER) else {
+        return Ok(bearer.filter(|token| token.starts_with(KEY_PREFIX)));
+    };
+
+    let key = value.to_str().ok();
+    if bearer.is_some_and(|token| Some(token) != key) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "conflicting_credentials",
+            "The X-Api-Key header and the bearer token must carry the same key",
+        ));
+    }
+
+    Ok(key)
+}
+
+/// The active key presented with the request.
+pub async fn require_api_key(
+    pool: &PgPool,
+    headers: &HeaderMap,
+) -> Result<ApiKey, actix_web::Error> {
+    let key = match presented_api_key(headers)? {
+        Some(presented) => api_key_repo::get_api_key(pool, &hash_api_key(presented))
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?,
+        None => None,
+    };
+
+    key.ok_or_else(|| {
+        ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "invalid_api_key",
+            "A valid API key is required",
+        )
+        .into()
+    })
+}
+
+#[post("/users/{user_id}/api-keys")]
+pub async fn create_api_key(
+    pool: Data<PgPool>,
+    user: AuthenticatedUser,
+    path: Path<i32>,
+    body: Json<NewApiKey>,
+) -> Result<Json<CreatedApiKey>, actix_web::Error> {
+    let user_id = path.into_inner();
+    user.require_self(user_id)?;
+    user.require_login()?;
+    if body.scopes.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "api_key_scopes_required",
+            "An API key needs at least one scope",
+        )
+        .into());
+    }
+    let key = generate_api_key();
+
+    let (id, tier) = api_key_repo::create_api_key(
+        &pool,
+        user_id,
+        &hash_api_key(&key),
+        &body.label,
+        &body.scopes,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(CreatedApiKey {
+        id,
+        key,
+        tier,
+        scopes: body.into_inner().scopes,
+    }))
+}
+
+#[get("/users/{user_id}/api-keys")]
+pub async fn get_api_keys(
+    pool: Data<PgPool>,
+    user: AuthenticatedUser,
+    path: Path<i32>,
+) -> Result<Json<Vec<ApiKeySummary>>, actix_web::Error> {
+    let user_id = path.into_inner();
+    user.require_self(user_id)?;
+
+    let keys = api_key_repo::get_api_keys(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(keys))
+}
+
+#[delete("/users/{user_id}/api-keys/{key_id}")]
+pub async fn revoke_api_key(
+    pool: Data<PgPool>,
+    user: AuthenticatedUser,
+    path: Path<(i32, i64)>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (user_id, key_id) = path.into_inner();
+    user.require_self(user_id)?;
+    user.require_login()?;
+
+    let revoked = api_key_repo::revoke_api_key(&pool, user_id, key_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if revoked == 0 {
+        return Err(actix_web::error::ErrorNotFound("API key not found"));
+    }
+
+    Ok(HttpResponse::Ok().body(format!("API key {} has been revoked", key_id)))
+}
+
+#[put("/admin/api-keys/{key_id}/tier")]
+pub async fn set_api_key_tier(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    path: Path<i64>,
+    body: Json<TierUpdate>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let key_id = path.into_inner();
+
+    let updated = api_key_repo::set_api_key_tier(&pool, key_id, body.tier)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if updated == 0 {
+        return Err(actix_web::error::ErrorNotFound("API key not found"));
+    }
+
+    Ok(HttpResponse::Ok().body(format!(
+        "API key {} is now on the {} tier",
+        key_id, body.tier
+    )))
+}
+
+#[get("/me/usage")]
+pub async fn get_usage(
+    pool: Data<PgPool>,
+    settings: Data<RuntimeSettings>,
+    req: HttpRequest,
+) -> Result<Json<UsageReport>, actix_web::Error> {
+    let key = require_api_key(&pool, req.headers()).await?;
+    let quota = settings.current().quotas.for_tier(key.tier);
+
+    let usage = api_key_repo::get_usage(&pool, key.id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(UsageReport::new(
+        key.tier,
+        usage,
+        quota.daily,
+        quota.monthly,
+        Utc::now(),
+    )))
+}
+
+#[cfg(test)]
+mod api_key_api_tests {
+    use super::*;
+    use actix_web::http::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(name.parse().unwrap(), HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_bearer_and_header_keys_must_match() {
+        let same = headers(&[
+            (API_KEY_HEADER, "ffk_abc"),
+            ("authorization", "Bearer ffk_abc"),
+        ]);
+        assert_eq!(presented_api_key(&same).unwrap(), Some("ffk_abc"));
+
+        let other = headers(&[
+            (API_KEY_HEADER, "ffk_abc"),
+            ("authorization", "Bearer ffk_xyz"),
+        ]);
+        assert!(presented_api_key(&other).is_err());
+
+        let jwt = headers(&[
+            (API_KEY_HEADER, "ffk_abc"),
+            ("authorization", "Bearer eyJ.jwt"),
+        ]);
+        assert!(presented_api_key(&jwt).is_err());
+
+        let bearer = headers(&[("authorization", "Bearer ffk_abc")]);
+        assert_eq!(presented_api_key(&bearer).unwrap(), Some("ffk_abc"));
+    }
+}
@@ -0,0 +1,365 @@
+use crate::api::mod_note::with_mod_notes;
+use crate::auth::role::{require_role, require_sub_moderator};
+use crate::auth::{Admin, AuthenticatedUser, ManageUsers, RequireRole, RequireSubModerator};
+use crate::error::ApiError;
+use crate::mail::Mailer;
+use crate::model::appeal::{
+    Appeal, AppealKind, AppealQuery, AppealResolution, AppealStatus, AppealTarget, NewAppeal,
+    MAX_APPEAL_MESSAGE_CHARS,
+};
+use crate::model::mod_note::WithModNotes;
+use crate::model::moderation::{ModAction, RemovalKind};
+use crate::model::report::ReportTarget;
+use crate::model::sub::ModPermission;
+use crate::model::user::{Role, User};
+use crate::repo::{
+    appeal as appeal_repo, comment as comment_repo, moderation as moderation_repo,
+    modqueue as modqueue_repo, post as post_repo, sub::SubRepository,
+    suspension as suspension_repo, user::UserRepository,
+};
+use actix_web::{get, http::StatusCode, post, web::Data, web::Json, web::Path, web::Query};
+use serde_json::json;
+use sqlx::PgPool;
+
+/// Appeals a removal of one of the caller's posts or comments or their ban from a sub,
+/// which go to the sub's moderators, or their suspension, which goes to admins. Each
+/// can only have one appeal waiting at a time.
+#[post("/appeals")]
+pub async fn create_appeal(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    user: AuthenticatedUser,
+    body: Json<NewAppeal>,
+) -> Result<Json<Appeal>, actix_web::Error> {
+    let body = body.into_inner();
+    let message = body.message.trim();
+    if message.is_empty() || message.chars().count() > MAX_APPEAL_MESSAGE_CHARS {
+        return Err(invalid_appeal_message_error().into());
+    }
+
+    let kind = body.target.kind();
+    let (sub, post_id, comment_id) = match body.target {
+        AppealTarget::PostRemoval { post_id } => {
+            let post = post_repo::get_post(&pool, post_id)
+                .await
+                .ok()
+                .filter(|post| post.user_id == user.user_id)
+                .ok_or_else(|| actix_web::error::ErrorNotFound("Post not found"))?;
+            if !appealable_removal(post.removal) {
+                return Err(nothing_to_appeal_error().into());
+            }
+            (Some(post.sub), Some(post_id), None)
+        }
+        AppealTarget::CommentRemoval { comment_id } => {
+            let comment = comment_repo::get_comment(&pool, comment_id)
+                .await
+                .ok()
+                .filter(|comment| comment.user_id == user.user_id)
+                .ok_or_else(|| actix_web::error::ErrorNotFound("Comment not found"))?;
+            if !appealable_removal(comment.removal) {
+                return Err(nothing_to_appeal_error().into());
+            }
+            let post = post_repo::get_post(&pool, comment.post_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            (Some(post.sub), None, Some(comment_id))
+        }
+        AppealTarget::SubBan { sub } => {
+            subs.get_sub_ban(&sub, user.user_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?
+                .ok_or_else(nothing_to_appeal_error)?;
+            (Some(sub), None, None)
+        }
+        AppealTarget::Suspension => {
+            suspension_repo::get_active_suspension(&pool, user.user_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?
+                .ok_or_else(nothing_to_appeal_error)?;
+            (None, None, None)
+        }
+    };
+
+    let appeal = appeal_repo::create_appeal(
+        &pool,
+        user.user_id,
+        kind,
+        sub.as_deref(),
+        post_id,
+        comment_id,
+        message,
+    )
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(db_error) if db_error.is_unique_violation() => ApiError::new(
+            StatusCode::CONFLICT,
+            "appeal_pending",
+            "You already have an appeal of this waiting for a decision",
+        )
+        .into(),
+        _ => actix_web::error::ErrorInternalServerError(e),
+    })?;
+
+    Ok(Json(appeal))
+}
+
+/// The caller's appeals and what became of them, most recent first.
+#[get("/appeals")]
+pub async fn get_my_appeals(
+    pool: Data<PgPool>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<Appeal>>, actix_web::Error> {
+    let appeals = appeal_repo::get_user_appeals(&pool, user.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(appeals))
+}
+
+/// Appeals of the sub's removals and bans, oldest first, with the sub's notes on the
+/// users appealing.
+#[get("/subs/{sub}/appeals")]
+pub async fn get_sub_appeals(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManageUsers>,
+    path: Path<String>,
+    query: Query<AppealQuery>,
+) -> Result<Json<Vec<WithModNotes<Appeal>>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    let Json(appeals) = appeals(&pool, Some(&sub_name), query.status).await?;
+    let appeals =
+        with_mod_notes(subs.get_ref(), &sub_name, appeals, |appeal| appeal.user_id).await?;
+
+    Ok(Json(appeals))
+}
+
+/// Appeals of suspensions, oldest first.
+#[get("/admin/appeals")]
+pub async fn get_suspension_appeals(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    query: Query<AppealQuery>,
+) -> Result<Json<Vec<Appeal>>, actix_web::Error> {
+    appeals(&pool, None, query.status).await
+}
+
+/// Grants or denies the appeal. Granting restores the post or comment, lifts the ban or
+/// lifts the suspension. The user is emailed the decision and `response` if their address
+/// is verified.
+#[post("/appeals/{id}/resolve")]
+pub async fn resolve_appeal(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    mailer: Data<Mailer>,
+    caller: AuthenticatedUser,
+    path: Path<i64>,
+    body: Json<AppealResolution>,
+) -> Result<Json<Appeal>, actix_web::Error> {
+    let appeal_id = path.into_inner();
+    let appeal = appeal_repo::get_appeal(&pool, appeal_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Appeal not found"))?;
+    let moderator = match &appeal.sub {
+        Some(sub) => {
+            require_sub_moderator(
+                users.get_ref(),
+                subs.get_ref(),
+                caller.user_id,
+                sub,
+                ModPermission::Users,
+            )
+            .await?
+        }
+        None => require_role(users.get_ref(), caller.user_id, Role::Admin).await?,
+    };
+    let body = body.into_inner();
+    let response = body.response.trim();
+    if response.chars().count() > MAX_APPEAL_MESSAGE_CHARS {
+        return Err(invalid_appeal_message_error().into());
+    }
+    let status = if body.granted {
+        AppealStatus::Granted
+    } else {
+        AppealStatus::Denied
+    };
+
+    let resolved = appeal_repo::resolve_appeal(
+        &pool,
+        appeal_id,
+        status,
+        moderator.id,
+        Some(response).filter(|response| !response.is_empty()),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?
+    .ok_or_else(|| {
+        ApiError::new(
+            StatusCode::CONFLICT,
+            "appeal_resolved",
+            "This appeal has already been decided",
+        )
+    })?;
+    if body.granted {
+        reverse(&pool, subs.get_ref(), &resolved, moderator.id).await?;
+    }
+    let action = if body.granted {
+        ModAction::GrantAppeal
+    } else {
+        ModAction::DenyAppeal
+    };
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.id,
+        action,
+        Some(resolved.user_id),
+        resolved.sub.as_deref(),
+        json!({
+            "appeal_id": resolved.id,
+            "kind": resolved.kind,
+            "post_id": resolved.post_id,
+            "comment_id": resolved.comment_id,
+        }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let user = users
+        .get_user_by_id(resolved.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    notify_user(mailer, user, &resolved);
+
+    Ok(Json(resolved))
+}
+
+async fn appeals(
+    pool: &PgPool,
+    sub: Option<&str>,
+    status: Option<AppealStatus>,
+) -> Result<Json<Vec<Appeal>>, actix_web::Error> {
+    let appeals = appeal_repo::get_appeals(pool, sub, status.unwrap_or(AppealStatus::Pending))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(appeals))
+}
+
+/// Legal takedowns aren't the moderators' to reverse, and deleted content is gone.
+fn appealable_removal(removal: Option<RemovalKind>) -> bool {
+    matches!(
+        removal,
+        Some(RemovalKind::Moderator) | Some(RemovalKind::Filter)
+    )
+}
+
+/// Undoes what the granted appeal was about. A ban or suspension that has since run
+/// out, or content its author has since deleted, is left as it is.
+async fn reverse(
+    pool: &PgPool,
+    subs: &dyn SubRepository,
+    appeal: &Appeal,
+    moderator_id: i32,
+) -> Result<(), actix_web::Error> {
+    match appeal.kind {
+        AppealKind::PostRemoval => {
+            if let Some(post_id) = appeal.post_id {
+                approve(pool, ReportTarget::Post(post_id), moderator_id).await?;
+            }
+        }
+        AppealKind::CommentRemoval => {
+            if let Some(comment_id) = appeal.comment_id {
+                approve(pool, ReportTarget::Comment(comment_id), moderator_id).await?;
+            }
+        }
+        AppealKind::SubBan => {
+            if let Some(sub) = &appeal.sub {
+                subs.unban_user_from_sub(sub, appeal.user_id)
+                    .await
+                    .map_err(actix_web::error::ErrorInternalServerError)?;
+            }
+        }
+        AppealKind::Suspension => {
+            suspension_repo::lift_suspension(pool, appeal.user_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn approve(
+    pool: &PgPool,
+    target: ReportTarget,
+    moderator_id: i32,
+) -> Result<(), actix_web::Error> {
+    modqueue_repo::approve_queue_item(pool, target, moderator_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(())
+}
+
+/// Mails the decision in the background. Delivery failures are only logged.
+fn notify_user(mailer: Data<Mailer>, user: User, appeal: &Appeal) {
+    let Some(email) = user.email.clone().filter(|_| user.email_verified()) else {
+        return;
+    };
+    let subject = match appeal.kind {
+        AppealKind::PostRemoval => "the removal of your post".to_string(),
+        AppealKind::CommentRemoval => "the removal of your comment".to_string(),
+        AppealKind::SubBan => format!(
+            "your ban from {}",
+            appeal.sub.as_deref().unwrap_or_default()
+        ),
+        AppealKind::Suspension => "your suspension".to_string(),
+    };
+    let outcome = match appeal.status {
+        AppealStatus::Granted => "granted",
+        _ => "denied",
+    };
+    let mut body = format!(
+        "Hi {},\n\nYour appeal of {} on Ferris Forums was {}.\n",
+        user.username, subject, outcome
+    );
+    if let Some(response) = &appeal.response {
+        body.push_str(&format!("\nThe moderators said:\n\n{}\n", response));
+    }
+
+    let user_id = user.id;
+    actix_web::rt::spawn(async move {
+        if let Err(e) = mailer
+            .send(&email, &format!("Your appeal was {}", outcome), body)
+            .await
+        {
+            log::error!("Failed to send appeal decision to user {}: {}", user_id, e);
+        }
+    });
+}
+
+fn invalid_appeal_message_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_appeal_message",
+        format!(
+            "Appeals and responses to them must be between 1 and {} characters",
+            MAX_APPEAL_MESSAGE_CHARS
+        ),
+    )
+    .with_arg("max", MAX_APPEAL_MESSAGE_CHARS.to_string())
+}
+
+fn nothing_to_appeal_error() -> ApiError {
+    ApiError::new(
+        StatusCode::CONFLICT,
+        "nothing_to_appeal",
+        "There is no removal, ban or suspension of yours here to appeal",
+    )
+}
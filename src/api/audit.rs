@@ -0,0 +1,18 @@
+use crate::auth::{Admin, RequireRole};
+use crate::model::audit::{AuditEntry, AuditQuery};
+use crate::repo::audit as audit_repo;
+use actix_web::{get, web::Data, web::Json, web::Query};
+use sqlx::PgPool;
+
+#[get("/admin/audit")]
+pub async fn get_audit_log(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    query: Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, actix_web::Error> {
+    let entries = audit_repo::get_audit_entries(&pool, &query)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(entries))
+}
@@ -0,0 +1,410 @@
+use crate::auth::session::{removal_cookie, session_cookie, SessionUser};
+use crate::auth::token::AccessToken;
+use crate::auth::{
+    bearer_token, generate_opaque_token, hash_opaque_token, Admin, AuthenticatedUser, JwtKeys,
+    RequireRole,
+};
+use crate::config::{AuthConfig, SessionConfig};
+use crate::csrf::{csrf_token, CsrfToken};
+use crate::error::ApiError;
+use crate::model::dto::UserPrivate;
+use crate::model::refresh_token::{LogoutRequest, RefreshOutcome, RefreshRequest};
+use crate::model::session::{DeviceInfo, MAX_USER_AGENT_LENGTH};
+use crate::model::user::{Credentials, User};
+use crate::rate_limit::request_ip;
+use crate::repo::user::UserRepository;
+use crate::repo::{
+    lockout as lockout_repo, refresh_token as refresh_token_repo, revocation as revocation_repo,
+    session as session_repo,
+};
+use actix_web::cookie::Cookie;
+use actix_web::http::header::{HeaderValue, RETRY_AFTER, USER_AGENT};
+use actix_web::{
+    delete, get, http::StatusCode, post, web::Data, web::Json, web::Path, HttpRequest, HttpResponse,
+};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::sync::OnceLock;
+
+/// Exchanges a username and password for a bearer access token and a refresh token
+/// that starts a new token family.
+#[post("/auth/login")]
+pub async fn login(
+    users: Data<dyn UserRepository>,
+    pool: Data<PgPool>,
+    keys: Data<JwtKeys>,
+    config: Data<AuthConfig>,
+    req: HttpRequest,
+    body: Json<Credentials>,
+) -> Result<Json<AccessToken>, actix_web::Error> {
+    let user = verify_credentials(users.get_ref(), &pool, &config, &body).await?;
+
+    let refresh_token = generate_opaque_token();
+    refresh_token_repo::create_refresh_token(
+        &pool,
+        user.id,
+        &hash_opaque_token(&refresh_token),
+        expires_after(config.refresh_token_ttl)?,
+        &device_info(&req),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let mut token = keys
+        .issue(user.id)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    token.refresh_token = Some(refresh_token);
+
+    Ok(Json(token))
+}
+
+/// Swaps a refresh token for a new access token and a new refresh token. Each refresh
+/// token works once; presenting one again revokes every token descended from the same
+/// login, since it means the token has been copied.
+#[post("/auth/refresh")]
+pub async fn refresh(
+    pool: Data<PgPool>,
+    keys: Data<JwtKeys>,
+    config: Data<AuthConfig>,
+    req: HttpRequest,
+    body: Json<RefreshRequest>,
+) -> Result<Json<AccessToken>, actix_web::Error> {
+    let refresh_token = generate_opaque_token();
+    let outcome = refresh_token_repo::rotate_refresh_token(
+        &pool,
+        &hash_opaque_token(&body.refresh_token),
+        &hash_opaque_token(&refresh_token),
+        expires_after(config.refresh_token_ttl)?,
+        &device_info(&req),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let user_id =
+        match outcome {
+            RefreshOutcome::Rotated { user_id } => user_id,
+            RefreshOutcome::Reused => return Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "refresh_token_reused",
+                "This refresh token was already used, so the sign-in it belongs to has been ended",
+            )
+            .into()),
+            RefreshOutcome::Invalid => {
+                return Err(ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "invalid_refresh_token",
+                    "The refresh token is invalid or has expired",
+                )
+                .into())
+            }
+        };
+
+    let mut token = keys
+        .issue(user_id)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    token.refresh_token = Some(refresh_token);
+
+    Ok(Json(token))
+}
+
+/// Signs a browser in with a session cookie instead of a token it would have to store.
+#[post("/auth/session")]
+pub async fn create_session(
+    users: Data<dyn UserRepository>,
+    pool: Data<PgPool>,
+    config: Data<SessionConfig>,
+    auth_config: Data<AuthConfig>,
+    req: HttpRequest,
+    body: Json<Credentials>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = verify_credentials(users.get_ref(), &pool, &auth_config, &body).await?;
+    let cookie = start_session(&pool, &config, user.id, &device_info(&req)).await?;
+
+    Ok(HttpResponse::Ok()
+        .cookie(cookie)
+        .json(UserPrivate::from(user)))
+}
+
+/// Stores a new session for `user_id` and returns the cookie that carries it.
+pub async fn start_session(
+    pool: &PgPool,
+    config: &SessionConfig,
+    user_id: i32,
+    device: &DeviceInfo,
+) -> Result<Cookie<'static>, actix_web::Error> {
+    let token = generate_opaque_token();
+    let expires_at = expires_after(config.max_age)?;
+    session_repo::create_session(
+        pool,
+        &hash_opaque_token(&token),
+        user_id,
+        expires_at,
+        device,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(session_cookie(config, token))
+}
+
+/// The user agent and address of the client signing in, for the session list.
+pub fn device_info(req: &HttpRequest) -> DeviceInfo {
+    let user_agent = req
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+    DeviceInfo {
+        user_agent,
+        ip_address: request_ip(req).map(|ip| ip.to_string()),
+    }
+}
+
+/// Signs out: the access token the request was made with stops working, as do the
+/// given refresh token's login and the session cookie if one was sent. With
+/// `all_devices` every login of the account ends.
+#[post("/auth/logout")]
+pub async fn logout(
+    pool: Data<PgPool>,
+    keys: Data<JwtKeys>,
+    config: Data<SessionConfig>,
+    user: AuthenticatedUser,
+    session: Option<SessionUser>,
+    req: HttpRequest,
+    body: Json<LogoutRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    user.require_login()?;
+
+    if body.all_devices {
+        revocation_repo::revoke_all_tokens(&pool, user.user_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    } else {
+        if let Some(refresh_token) = &body.refresh_token {
+            refresh_token_repo::revoke_refresh_family(
+                &pool,
+                user.user_id,
+                &hash_opaque_token(refresh_token),
+            )
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        }
+        if let Some(token) = bearer_token(&req)? {
+            let claims = keys
+                .verify(token)
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            let expires_at = DateTime::from_timestamp(claims.exp, 0).unwrap_or_else(Utc::now);
+            revocation_repo::revoke_access_token(&pool, claims.jti, expires_at)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+        }
+        if let Some(session) = &session {
+            session_repo::delete_session(&pool, &session.token_hash)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+        }
+    }
+
+    let mut response = HttpResponse::NoContent();
+    if session.is_some() {
+        response.cookie(removal_cookie(&config));
+    }
+    Ok(response.finish())
+}
+
+/// Ends the current session. The cookie is cleared even if the session had already
+/// expired.
+#[delete("/auth/session")]
+pub async fn delete_session(
+    pool: Data<PgPool>,
+    config: Data<SessionConfig>,
+    session: Option<SessionUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Some(session) = session {
+        session_repo::delete_session(&pool, &session.token_hash)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+
+    Ok(HttpResponse::NoContent()
+        .cookie(removal_cookie(&config))
+        .finish())
+}
+
+/// The token browser frontends send as `X-CSRF-Token` on writes made with the session
+/// cookie. It stays the same for the whole session.
+#[get("/auth/csrf")]
+pub async fn get_csrf_token(
+    config: Data<AuthConfig>,
+    session: SessionUser,
+) -> Result<HttpResponse, actix_web::Error> {
+    Ok(HttpResponse::Ok().json(CsrfToken {
+        csrf_token: csrf_token(&config, &session.token_hash),
+    }))
+}
+
+#[get("/auth/me")]
+pub async fn get_current_user(
+    users: Data<dyn UserRepository>,
+    user: AuthenticatedUser,
+) -> Result<Json<UserPrivate>, actix_web::Error> {
+    let user = users
+        .get_user_by_id(user.user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    Ok(Json(user.into()))
+}
+
+/// Stands in for the stored hash when the username is unknown, so those sign-ins take
+/// as long as a wrong password and don't reveal which usernames exist. Made with the
+/// configured cost on first use.
+static DUMMY_PASSWORD_HASH: OnceLock<String> = OnceLock::new();
+
+fn verify_dummy_password(config: &AuthConfig, password: &str) -> Result<(), actix_web::Error> {
+    let password_hash = match DUMMY_PASSWORD_HASH.get() {
+        Some(password_hash) => password_hash,
+        None => {
+            let password_hash = User::hash_password("dummy password", &config.password_hashing)
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            DUMMY_PASSWORD_HASH.get_or_init(|| password_hash)
+        }
+    };
+    let _ = User::verify_password_hash(password_hash, password);
+
+    Ok(())
+}
+
+/// Locked accounts are refused before the password is checked, so guessing can't
+/// continue during a lockout.
+async fn verify_credentials(
+    users: &dyn UserRepository,
+    pool: &PgPool,
+    config: &AuthConfig,
+    credentials: &Credentials,
+) -> Result<User, actix_web::Error> {
+    let Some(user) = users
+        .username_exists(&credentials.username)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+    else {
+        verify_dummy_password(config, &credentials.password)?;
+        return Err(invalid_credentials_error().into());
+    };
+
+    let lockouts = config.lockout_threshold > 0;
+    if lockouts {
+        let locked_until = lockout_repo::get_lockout(pool, user.id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if let Some(locked_until) = locked_until {
+            return Err(account_locked_error(locked_until).into());
+        }
+    }
+
+    let verified = user
+        .verify_password(&credentials.password)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !verified {
+        if lockouts {
+            let window = chrono::Duration::from_std(config.lockout_window)
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            let locked_until = lockout_repo::record_failure(
+                pool,
+                user.id,
+                config.lockout_threshold,
+                Utc::now() - window,
+                expires_after(config.lockout_duration)?,
+            )
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+            if let Some(locked_until) = locked_until {
+                log::warn!("User {} locked out after repeated failed logins", user.id);
+                return Err(account_locked_error(locked_until).into());
+            }
+        }
+        return Err(invalid_credentials_error().into());
+    }
+
+    if lockouts {
+        lockout_repo::clear_failures(pool, user.id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+    if user.needs_rehash(&config.password_hashing) {
+        rehash_password(users, &user, &credentials.password, config).await;
+    }
+
+    Ok(user)
+}
+
+/// Stores the password under the current `HashParams`. Failing to do so doesn't fail
+/// the login; the next one tries again.
+async fn rehash_password(
+    users: &dyn UserRepository,
+    user: &User,
+    password: &str,
+    config: &AuthConfig,
+) {
+    let result = match User::hash_password(password, &config.password_hashing) {
+        Ok(password_hash) => users
+            .update_user_password(user.id, &password_hash)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        log::warn!("Could not rehash the password of user {}: {}", user.id, e);
+    }
+}
+
+/// Lifts a lockout early, for account holders who have contacted support.
+#[delete("/admin/users/{user_id}/lockout")]
+pub async fn clear_lockout(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    path: Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+
+    let cleared = lockout_repo::clear_lockout(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !cleared {
+        return Err(actix_web::error::ErrorNotFound(
+            "This account is not locked",
+        ));
+    }
+
+    Ok(HttpResponse::Ok().body(format!("User ID {} has been unlocked", user_id)))
+}
+
+fn expires_after(ttl: std::time::Duration) -> Result<DateTime<Utc>, actix_web::Error> {
+    let ttl =
+        chrono::Duration::from_std(ttl).map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Utc::now() + ttl)
+}
+
+fn account_locked_error(locked_until: DateTime<Utc>) -> ApiError {
+    let retry_after = (locked_until - Utc::now()).num_seconds().max(0);
+
+    ApiError::new(
+        StatusCode::LOCKED,
+        "account_locked",
+        "This account is locked after too many failed logins, try again later",
+    )
+    .with_arg("locked_until", locked_until.to_rfc3339())
+    .with_headers(vec![(RETRY_AFTER, HeaderValue::from(retry_after))])
+}
+
+/// The same error for an unknown username and a wrong password, so logins can't be
+/// used to find out which accounts exist.
+fn invalid_credentials_error() -> ApiError {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        "invalid_credentials",
+        "The username or password is incorrect",
+    )
+}
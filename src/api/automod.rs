@@ -0,0 +1,261 @@
+use crate::api::flair::unknown_flair_error;
+use crate::auth::{ManageSettings, RequireSubModerator};
+use crate::error::ApiError;
+use crate::model::automod::{
+    evaluate, validate_rules, AutomodRule, AutomodSubject, AutomodTest, AutomodUpdate,
+    AutomodVerdict, SubAutomod, MAX_AUTOMOD_RULES,
+};
+use crate::model::comment::Comment;
+use crate::model::moderation::ModAction;
+use crate::model::report::ReportTarget;
+use crate::repo::{
+    automod as automod_repo, comment::CommentRepository, flair as flair_repo,
+    moderation as moderation_repo, report as report_repo, sub::SubRepository, user::UserRepository,
+};
+use actix_web::{get, http::StatusCode, post, put, web::Data, web::Json, web::Path};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// What the sub's rules made of new content, and who their replies are posted as: the
+/// moderator who saved the rules last, if their account still exists.
+pub struct AutomodHit {
+    pub verdict: AutomodVerdict,
+    pub replier: Option<i32>,
+}
+
+/// Checks new content by `author_id` against the sub's rules. `title` is `None` for
+/// comments. A flair that has since been deleted is left out of the verdict.
+pub async fn check_automod(
+    pool: &PgPool,
+    sub: &str,
+    author_id: i32,
+    title: Option<&str>,
+    body: &str,
+    link_url: Option<&str>,
+) -> Result<Option<AutomodHit>, actix_web::Error> {
+    let Some(automod) = automod_repo::get_automod(pool, sub)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+    else {
+        return Ok(None);
+    };
+    if automod.rules.is_empty() {
+        return Ok(None);
+    }
+
+    let author = UserRepository::get_user_by_id(pool, author_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let mut verdict = evaluate(
+        &automod.rules,
+        &AutomodSubject {
+            title,
+            body,
+            link_url,
+            author_created_at: author.created_at,
+            author_karma: i64::from(author.post_karma) + i64::from(author.comment_karma),
+        },
+    );
+    if verdict.is_empty() {
+        return Ok(None);
+    }
+    if let Some(flair_id) = verdict.flair_id {
+        let flair = flair_repo::get_flair(pool, sub, flair_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        verdict.flair_id = flair.map(|flair| flair.id);
+    }
+
+    Ok(Some(AutomodHit {
+        verdict,
+        replier: automod.updated_by,
+    }))
+}
+
+/// Takes the actions that need the content saved first: files its reports and posts its
+/// replies. Removal and flair are set before saving.
+pub async fn apply_automod(
+    pool: &PgPool,
+    sub: &str,
+    post_id: Uuid,
+    target: ReportTarget,
+    hit: &AutomodHit,
+) -> Result<(), actix_web::Error> {
+    for reason in &hit.verdict.flags {
+        report_repo::create_report(pool, sub, target, None, None, reason)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+
+    let Some(replier) = hit.replier else {
+        return Ok(());
+    };
+    let parent_id = match target {
+        ReportTarget::Post(_) => None,
+        ReportTarget::Comment(comment_id) => Some(comment_id),
+    };
+    for text in &hit.verdict.comments {
+        let reply = Comment {
+            id: Uuid::new_v4(),
+            post_id,
+            user_id: replier,
+            content: text.clone(),
+            timestamp: Utc::now(),
+            parent_id,
+            removal: None,
+            score: 0,
+            edited_at: None,
+            author_flair: None,
+        };
+        CommentRepository::create_comment(pool, &reply)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+
+    Ok(())
+}
+
+#[get("/subs/{sub}/automod")]
+pub async fn get_automod(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManageSettings>,
+    path: Path<String>,
+) -> Result<Json<SubAutomod>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    let automod = automod_repo::get_automod(&pool, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .unwrap_or(SubAutomod {
+            sub: sub_name,
+            rules: Vec::new(),
+            updated_by: None,
+            updated_at: None,
+        });
+
+    Ok(Json(automod))
+}
+
+/// Replaces the sub's rules. Replies their `comment` actions post are written in the
+/// name of whoever saved the rules last.
+#[put("/subs/{sub}/automod")]
+pub async fn update_automod(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    moderator: RequireSubModerator<ManageSettings>,
+    path: Path<String>,
+    body: Json<AutomodUpdate>,
+) -> Result<Json<SubAutomod>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let mut rules = body.into_inner().rules;
+    check_rules(&pool, &sub_name, &mut rules).await?;
+
+    let automod = automod_repo::save_automod(&pool, &sub_name, &rules, moderator.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::UpdateAutomod,
+        None,
+        Some(&sub_name),
+        json!({ "rules": rules.iter().map(|rule| &rule.name).collect::<Vec<_>>() }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(automod))
+}
+
+/// A dry run: checks a made-up post or comment against the sub's rules, or against the
+/// rules sent along, and says what would happen to it. Nothing is saved.
+#[post("/subs/{sub}/automod/test")]
+pub async fn test_automod(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    moderator: RequireSubModerator<ManageSettings>,
+    path: Path<String>,
+    body: Json<AutomodTest>,
+) -> Result<Json<AutomodVerdict>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let body = body.into_inner();
+    let rules = match body.rules {
+        Some(mut rules) => {
+            check_rules(&pool, &sub_name, &mut rules).await?;
+            rules
+        }
+        None => automod_repo::get_automod(&pool, &sub_name)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .map(|automod| automod.rules)
+            .unwrap_or_default(),
+    };
+    let author = users
+        .get_user_by_id(body.author_id.unwrap_or(moderator.user_id))
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    let verdict = evaluate(
+        &rules,
+        &AutomodSubject {
+            title: body.title.as_deref(),
+            body: &body.content,
+            link_url: body.url.as_deref(),
+            author_created_at: author.created_at,
+            author_karma: i64::from(author.post_karma) + i64::from(author.comment_karma),
+        },
+    );
+
+    Ok(Json(verdict))
+}
+
+/// Refuses rules that can't be evaluated or that name a flair the sub doesn't have.
+async fn check_rules(
+    pool: &PgPool,
+    sub: &str,
+    rules: &mut [AutomodRule],
+) -> Result<(), actix_web::Error> {
+    if rules.len() > MAX_AUTOMOD_RULES {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "too_many_automod_rules",
+            format!(
+                "A sub can have at most {} AutoModerator rules",
+                MAX_AUTOMOD_RULES
+            ),
+        )
+        .with_arg("max", MAX_AUTOMOD_RULES.to_string())
+        .into());
+    }
+    validate_rules(rules).map_err(|(rule, problem)| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_automod_rule",
+            format!("The rule \"{}\" is invalid: {}", rule, problem),
+        )
+        .with_arg("rule", rule)
+        .with_arg("problem", problem)
+    })?;
+
+    for flair_id in rules.iter().flat_map(AutomodRule::flair_ids) {
+        flair_repo::get_flair(pool, sub, flair_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .ok_or_else(|| unknown_flair_error(sub))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use crate::api::mod_note::with_mod_notes;
+use crate::auth::{ManageUsers, RequireSubModerator};
+use crate::error::ApiError;
+use crate::model::ban::{NewSubBan, SubBan};
+use crate::model::mod_note::WithModNotes;
+use crate::model::moderation::ModAction;
+use crate::repo::{moderation as moderation_repo, sub::SubRepository, user::UserRepository};
+use actix_web::{delete, get, http::StatusCode, post, web::Data, web::Json, web::Path};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::PgPool;
+
+/// Bans stop the user posting and commenting in the sub, for good or until `expires_at`.
+/// Banning someone who is already banned replaces their ban. Moderators of the sub can't
+/// be banned from it.
+#[post("/subs/{sub}/bans")]
+pub async fn ban_user_from_sub(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    moderator: RequireSubModerator<ManageUsers>,
+    path: Path<String>,
+    body: Json<NewSubBan>,
+) -> Result<Json<SubBan>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let user = users
+        .get_user_by_id(body.user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if body.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "ban_expiry_in_past",
+            "A ban can only be set to expire in the future",
+        )
+        .into());
+    }
+    let moderates = subs
+        .get_moderator_permissions(user.id, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if moderates.is_some() {
+        return Err(ApiError::forbidden(
+            "cannot_ban_moderator",
+            format!(
+                "{} moderates {} and can't be banned from it",
+                user.username, sub_name
+            ),
+        )
+        .with_arg("username", user.username)
+        .with_arg("sub", sub_name)
+        .into());
+    }
+
+    let reason = body.reason.trim();
+    subs.ban_user_from_sub(
+        &sub_name,
+        user.id,
+        moderator.user_id,
+        reason,
+        body.expires_at,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::BanUser,
+        Some(user.id),
+        Some(&sub_name),
+        json!({ "reason": reason, "expires_at": body.expires_at }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let ban = subs
+        .get_sub_ban(&sub_name, user.id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("Ban went missing"))?;
+
+    Ok(Json(ban))
+}
+
+/// Bans still in force, most recent first, with the sub's notes on the banned users.
+#[get("/subs/{sub}/bans")]
+pub async fn get_sub_bans(
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManageUsers>,
+    path: Path<String>,
+) -> Result<Json<Vec<WithModNotes<SubBan>>>, actix_web::Error> {
+    sub_bans(subs.get_ref(), &path.into_inner()).await
+}
+
+/// Lifts the user's ban early. Returns the bans still in force.
+#[delete("/subs/{sub}/bans/{user_id}")]
+pub async fn unban_user_from_sub(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    moderator: RequireSubModerator<ManageUsers>,
+    path: Path<(String, i32)>,
+) -> Result<Json<Vec<WithModNotes<SubBan>>>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+
+    let unbanned = subs
+        .unban_user_from_sub(&sub_name, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !unbanned {
+        return Err(actix_web::error::ErrorNotFound(
+            "User is not banned from this sub",
+        ));
+    }
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::UnbanUser,
+        Some(user_id),
+        Some(&sub_name),
+        json!({}),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    sub_bans(subs.get_ref(), &sub_name).await
+}
+
+async fn sub_bans(
+    subs: &dyn SubRepository,
+    sub_name: &str,
+) -> Result<Json<Vec<WithModNotes<SubBan>>>, actix_web::Error> {
+    let bans = subs
+        .get_sub_bans(sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let bans = with_mod_notes(subs, sub_name, bans, |ban| ban.user_id).await?;
+
+    Ok(Json(bans))
+}
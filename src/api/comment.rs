@@ -1,73 +1,1091 @@
-use crate::model::comment::{Comment, NewComment};
-use crate::repo::comment as comment_repo;
+use crate::api::automod::{apply_automod, check_automod};
+use crate::api::feed::invalid_cursor_error;
+use crate::api::filter::{filter_removal, load_filters};
+use crate::api::media::attachable_media;
+use crate::api::post::{
+    get_readable_post, invalid_vote_error, not_author_error, post_archived_error,
+    require_revision_reader, require_visible_post, thread_locked_error,
+};
+use crate::api::sub::{get_postable_sub, get_readable_sub};
+use crate::api::user::{require_nsfw_clearance, require_verified_email};
+use crate::auth::{AuthenticatedUser, Viewer};
+use crate::config::{EmailConfig, SpamConfig};
+use crate::error::ApiError;
+use crate::model::account_requirement::Contribution;
+use crate::model::comment::{
+    Comment, CommentContext, CommentContextQuery, CommentCursor, CommentListQuery, CommentPage,
+    CommentTree, CommentTreeQuery, NewComment, DEFAULT_COMMENT_LIMIT, DEFAULT_TREE_WIDTH,
+    MAX_COMMENT_LIMIT,
+};
+use crate::model::dto::CommentView;
+use crate::model::filter::apply_filters;
+use crate::model::language::detect_language;
+use crate::model::moderation::RemovalKind;
+use crate::model::post::Post;
+use crate::model::report::ReportTarget;
+use crate::model::revision::{DiffQuery, Revision, RevisionDiff};
+use crate::model::vote::{VoteRequest, VoteResult};
+use crate::repo::{
+    comment::CommentRepository, media as media_repo, post::PostRepository, sub::SubRepository,
+    user::UserRepository,
+};
+use crate::spam;
 use actix_web::{
-    delete, get, patch, post,
-    web::{Data, Json, Path},
+    delete, get,
+    http::StatusCode,
+    patch, post, put,
+    web::{Data, Json, Path, Query},
     HttpResponse, Result,
 };
 use chrono::Utc;
 use sqlx::PgPool;
+use std::collections::HashMap;
 use uuid::Uuid;
 
+/// Filtered and scored for spam like posts, using the word lists and AutoModerator rules
+/// of the post's sub. Only members may comment in restricted and private subs, and only
+/// on posts the author can see, NSFW posts needing the author's NSFW clearance.
 #[post("/posts/{post_id}/comments")]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_comment(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
     pool: Data<PgPool>,
+    email_config: Data<EmailConfig>,
+    spam_config: Data<SpamConfig>,
+    author: AuthenticatedUser,
     path: Path<Uuid>,
     body: Json<NewComment>,
 ) -> Result<HttpResponse> {
+    require_verified_email(pool.get_ref(), &email_config, author.user_id).await?;
     let post_id = path.into_inner();
+    let post = get_readable_post(posts.get_ref(), post_id, None).await?;
+    require_visible_post(pool.get_ref(), &post, Some(author.user_id)).await?;
+    get_postable_sub(
+        pool.get_ref(),
+        pool.get_ref(),
+        &post.sub,
+        author.user_id,
+        Contribution::Comment,
+    )
+    .await?;
+    if post.nsfw {
+        require_nsfw_clearance(pool.get_ref(), Some(author.user_id)).await?;
+    }
+    if post.archived {
+        return Err(post_archived_error().into());
+    }
+    if post.locked {
+        return Err(thread_locked_error().into());
+    }
+    if let Some(parent_id) = body.parent_id {
+        require_parent_on_post(comments.get_ref(), parent_id, post_id).await?;
+    }
+
+    let media = attachable_media(&pool, author.user_id, &body.media_ids).await?;
+
+    let filters = load_filters(&pool, Some(&post.sub)).await?;
+    let content = apply_filters(
+        &filters,
+        &body.content,
+        detect_language(&body.content).as_deref(),
+    );
+    let removal = filter_removal(content.action)?;
+    let spam =
+        spam::score_content(&pool, author.user_id, &post.sub, None, &content.text, None).await?;
+    let removal = spam::spam_removal(&spam_config, &spam, removal);
+    let automod =
+        check_automod(&pool, &post.sub, author.user_id, None, &content.text, None).await?;
+    let removal = match &automod {
+        Some(hit) if hit.verdict.remove => Some(RemovalKind::Moderator),
+        _ => removal,
+    };
+
     let comment = Comment {
         id: Uuid::new_v4(),
         post_id,
-        user_id: body.user_id,
-        content: body.content.clone(),
+        user_id: author.user_id,
+        content: content.text,
         timestamp: Utc::now(),
         parent_id: body.parent_id,
+        removal,
+        score: 0,
+        edited_at: None,
+        author_flair: None,
     };
 
-    let comment_id = comment_repo::create_comment(&pool, &comment)
+    let comment_id = comments
+        .create_comment(&comment)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let target = ReportTarget::Comment(comment_id);
+    spam::record_score(&pool, target, &spam).await?;
+    if let Some(hit) = &automod {
+        apply_automod(&pool, &post.sub, post_id, target, hit).await?;
+    }
+    if !media.is_empty() {
+        media_repo::attach_comment_media(&pool, comment_id, &media)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
 
-    Ok(HttpResponse::Ok().body(comment_id.to_string()))
+    match removal {
+        Some(_) => Ok(HttpResponse::Accepted().body(comment_id.to_string())),
+        None => Ok(HttpResponse::Ok().body(comment_id.to_string())),
+    }
 }
 
+/// Oldest first, a page at a time; large threads take several requests.
 #[get("/posts/{post_id}/comments")]
-pub async fn get_comments(pool: Data<PgPool>, path: Path<Uuid>) -> Result<Json<Vec<Comment>>> {
+pub async fn get_comments(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    path: Path<Uuid>,
+    viewer: Viewer,
+    query: Query<CommentListQuery>,
+) -> Result<Json<CommentPage>> {
+    let post_id = path.into_inner();
+    let sort = query.sort();
+    let after = match &query.after {
+        Some(cursor) => Some(
+            CommentCursor::decode(cursor)
+                .filter(|cursor| cursor.sort == sort)
+                .ok_or_else(invalid_cursor_error)?,
+        ),
+        None => None,
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_COMMENT_LIMIT)
+        .clamp(1, MAX_COMMENT_LIMIT);
+
+    let post = get_readable_post(posts.get_ref(), post_id, viewer.user_id()).await?;
+    require_visible_post(users.get_ref(), &post, viewer.user_id()).await?;
+    get_readable_sub(subs.get_ref(), users.get_ref(), &post.sub, viewer.user_id()).await?;
+    if post.nsfw {
+        require_nsfw_clearance(users.get_ref(), viewer.user_id()).await?;
+    }
+
+    let mut page = comments
+        .get_comment_page(post_id, sort, after, limit, viewer.user_id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let total = comments
+        .count_comments(post_id, viewer.user_id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let more = page.len() as i64 > limit;
+    page.truncate(limit as usize);
+    let next_cursor = page.last().filter(|_| more).map(|last| {
+        CommentCursor {
+            sort,
+            rank: last.rank,
+            id: last.comment.id,
+        }
+        .encode()
+    });
+    let page = page.into_iter().map(|ranked| ranked.comment).collect();
+
+    Ok(Json(CommentPage {
+        comments: comment_views(comments.get_ref(), post_id, viewer.user_id(), page).await?,
+        total,
+        next_cursor,
+    }))
+}
+
+/// Comments nested under their parents, `?depth=` levels deep with at most `?limit=`
+/// replies under each comment. Replies past either limit are counted, not included.
+#[get("/posts/{post_id}/comments/tree")]
+pub async fn get_comment_tree(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    path: Path<Uuid>,
+    viewer: Viewer,
+    query: Query<CommentTreeQuery>,
+) -> Result<Json<CommentTree>> {
     let post_id = path.into_inner();
-    let comments = comment_repo::get_comments_by_post(&pool, post_id)
+    let post = get_readable_post(posts.get_ref(), post_id, viewer.user_id()).await?;
+    require_visible_post(users.get_ref(), &post, viewer.user_id()).await?;
+    get_readable_sub(subs.get_ref(), users.get_ref(), &post.sub, viewer.user_id()).await?;
+    if post.nsfw {
+        require_nsfw_clearance(users.get_ref(), viewer.user_id()).await?;
+    }
+
+    comment_subtree(comments.get_ref(), post_id, None, &query, viewer.user_id()).await
+}
+
+/// Continues a tree below a comment whose `more_replies` weren't all included: its
+/// replies, nested as by `GET /posts/{post_id}/comments/tree`. `?after=` takes the
+/// last reply already shown.
+#[get("/comments/{comment_id}/children")]
+pub async fn get_comment_children(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    path: Path<Uuid>,
+    viewer: Viewer,
+    query: Query<CommentTreeQuery>,
+) -> Result<Json<CommentTree>> {
+    let comment_id = path.into_inner();
+    let comment = comments
+        .get_comment(comment_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let post = get_readable_post(posts.get_ref(), comment.post_id, viewer.user_id()).await?;
+    require_visible_post(users.get_ref(), &post, viewer.user_id()).await?;
+    require_visible_comment(users.get_ref(), &comment, &post.sub, viewer.user_id()).await?;
+    get_readable_sub(subs.get_ref(), users.get_ref(), &post.sub, viewer.user_id()).await?;
+    if post.nsfw {
+        require_nsfw_clearance(users.get_ref(), viewer.user_id()).await?;
+    }
+
+    comment_subtree(
+        comments.get_ref(),
+        post.id,
+        Some(comment_id),
+        &query,
+        viewer.user_id(),
+    )
+    .await
+}
+
+/// A comment for a permalink: the comment, `?ancestors=` parents above it (3 by
+/// default, at most 10) and its direct replies, as many as a tree includes.
+#[get("/comments/{comment_id}/context")]
+pub async fn get_comment_context(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    path: Path<Uuid>,
+    viewer: Viewer,
+    query: Query<CommentContextQuery>,
+) -> Result<Json<CommentContext>> {
+    let comment_id = path.into_inner();
+    let comment = comments
+        .get_comment(comment_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let post = get_readable_post(posts.get_ref(), comment.post_id, viewer.user_id()).await?;
+    require_visible_post(users.get_ref(), &post, viewer.user_id()).await?;
+    require_visible_comment(users.get_ref(), &comment, &post.sub, viewer.user_id()).await?;
+    get_readable_sub(subs.get_ref(), users.get_ref(), &post.sub, viewer.user_id()).await?;
+    if post.nsfw {
+        require_nsfw_clearance(users.get_ref(), viewer.user_id()).await?;
+    }
+
+    let ancestors = comments
+        .get_comment_ancestors(comment_id, query.ancestors(), viewer.user_id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let replies = comments
+        .get_comment_tree(
+            post.id,
+            Some(comment_id),
+            query.sort,
+            None,
+            1,
+            DEFAULT_TREE_WIDTH,
+            viewer.user_id(),
+        )
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let reply_count = comments
+        .count_replies(
+            post.id,
+            Some(comment_id),
+            query.sort,
+            None,
+            viewer.user_id(),
+        )
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let votes = viewer_votes(comments.get_ref(), post.id, viewer.user_id()).await?;
+
+    Ok(Json(CommentContext::build(
+        ancestors,
+        comment,
+        replies,
+        reply_count,
+        &votes,
+    )))
+}
+
+async fn comment_subtree(
+    comments: &dyn CommentRepository,
+    post_id: Uuid,
+    parent_id: Option<Uuid>,
+    query: &CommentTreeQuery,
+    viewer_id: Option<i32>,
+) -> Result<Json<CommentTree>> {
+    let tree = comments
+        .get_comment_tree(
+            post_id,
+            parent_id,
+            query.sort,
+            query.after,
+            query.depth(),
+            query.width(),
+            viewer_id,
+        )
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let remaining = comments
+        .count_replies(post_id, parent_id, query.sort, query.after, viewer_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let votes = viewer_votes(comments, post_id, viewer_id).await?;
+
+    Ok(Json(CommentTree::build(tree, parent_id, remaining, &votes)))
+}
+
+/// Includes the viewer's own votes when they're signed in.
+pub async fn comment_views(
+    comments: &dyn CommentRepository,
+    post_id: Uuid,
+    viewer_id: Option<i32>,
+    post_comments: Vec<Comment>,
+) -> Result<Vec<CommentView>, actix_web::Error> {
+    let votes = viewer_votes(comments, post_id, viewer_id).await?;
+    let mut media = comments
+        .get_comment_media(post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let mut removal_reasons = match viewer_id {
+        Some(_) => comments
+            .get_comment_removal_reasons(post_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?,
+        None => HashMap::new(),
+    };
 
-    Ok(Json(comments))
+    Ok(post_comments
+        .into_iter()
+        .map(|comment| {
+            let vote = votes.get(&comment.id).copied();
+            let media = media.remove(&comment.id).unwrap_or_default();
+            let removal_reason = match viewer_id {
+                Some(viewer_id) if viewer_id == comment.user_id => {
+                    removal_reasons.remove(&comment.id)
+                }
+                _ => None,
+            };
+            CommentView {
+                vote,
+                media,
+                removal_reason,
+                ..CommentView::from(comment)
+            }
+        })
+        .collect())
 }
 
+/// Edits go through the same checks as new comments, and are answered the same way.
 #[patch("/comments/{comments_id}")]
 pub async fn update_comment(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
     pool: Data<PgPool>,
+    spam_config: Data<SpamConfig>,
+    author: AuthenticatedUser,
     path: Path<Uuid>,
     body: String,
 ) -> Result<HttpResponse, actix_web::Error> {
     let comment_id = path.into_inner();
-    let update_content = String::from(&body);
+    let comment = require_comment_author(comments.get_ref(), &author, comment_id).await?;
+    let post = posts
+        .get_post(comment.post_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    let filters = load_filters(&pool, Some(&post.sub)).await?;
+    let content = apply_filters(&filters, &body, detect_language(&body).as_deref());
+    let removal = filter_removal(content.action)?;
+    let spam =
+        spam::score_content(&pool, author.user_id, &post.sub, None, &content.text, None).await?;
+    let removal = spam::spam_removal(&spam_config, &spam, removal);
+    let automod =
+        check_automod(&pool, &post.sub, author.user_id, None, &content.text, None).await?;
+    let removal = match &automod {
+        Some(hit) if hit.verdict.remove => Some(RemovalKind::Moderator),
+        _ => removal,
+    };
+
+    let comment_id = comments
+        .update_comment(comment_id, content.text.clone(), removal)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let target = ReportTarget::Comment(comment_id);
+    spam::record_score(&pool, target, &spam).await?;
+    if let Some(hit) = &automod {
+        apply_automod(&pool, &post.sub, post.id, target, hit).await?;
+    }
+
+    let body = format!("{} -> {}", comment_id, content.text);
+    match removal {
+        Some(_) => Ok(HttpResponse::Accepted().body(body)),
+        None => Ok(HttpResponse::Ok().body(body)),
+    }
+}
+
+#[get("/comments/{comment_id}/revisions/{from}/diff/{to}")]
+pub async fn get_comment_revision_diff(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    subs: Data<dyn SubRepository>,
+    caller: AuthenticatedUser,
+    path: Path<(Uuid, i32, i32)>,
+    query: Query<DiffQuery>,
+) -> Result<Json<RevisionDiff>, actix_web::Error> {
+    let (comment_id, from, to) = path.into_inner();
+    require_comment_revision_reader(
+        comments.get_ref(),
+        posts.get_ref(),
+        users.get_ref(),
+        subs.get_ref(),
+        &caller,
+        comment_id,
+    )
+    .await?;
+
+    let from = comments
+        .get_comment_revision(comment_id, from)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let to = comments
+        .get_comment_revision(comment_id, to)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    Ok(Json(RevisionDiff::between(&from, &to, query.granularity)))
+}
+
+/// Every revision of the comment, oldest first. Only its author and the sub's moderators
+/// may see what it said before an edit.
+#[get("/comments/{comment_id}/history")]
+pub async fn get_comment_history(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    subs: Data<dyn SubRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<Vec<Revision>>, actix_web::Error> {
+    let comment_id = path.into_inner();
+    require_comment_revision_reader(
+        comments.get_ref(),
+        posts.get_ref(),
+        users.get_ref(),
+        subs.get_ref(),
+        &caller,
+        comment_id,
+    )
+    .await?;
 
-    let comment_id = comment_repo::update_comment(&pool, comment_id, update_content.clone())
+    let revisions = comments
+        .get_comment_revisions(comment_id)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().body(format!("{} -> {}", comment_id.to_string(), update_content)))
+    Ok(Json(revisions))
 }
 
 #[delete("/comments/{comment_id}")]
 pub async fn delete_comment(
-    pool: Data<PgPool>,
+    comments: Data<dyn CommentRepository>,
+    author: AuthenticatedUser,
     path: Path<Uuid>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let comment_id = path.into_inner();
+    require_comment_author(comments.get_ref(), &author, comment_id).await?;
+
+    comments
+        .delete_comment(comment_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!("{} was deleted", comment_id)))
+}
+
+/// Casting a vote again replaces the earlier one.
+#[put("/comments/{comment_id}/vote")]
+pub async fn vote_comment(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    voter: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<VoteRequest>,
+) -> Result<Json<VoteResult>, actix_web::Error> {
+    if !body.is_valid() {
+        return Err(invalid_vote_error().into());
+    }
+    let comment_id = path.into_inner();
+    require_votable_comment(
+        comments.get_ref(),
+        posts.get_ref(),
+        subs.get_ref(),
+        users.get_ref(),
+        voter.user_id,
+        comment_id,
+    )
+    .await?;
+
+    let score = comments
+        .set_comment_vote(comment_id, voter.user_id, Some(body.value))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(VoteResult {
+        score,
+        vote: Some(body.value),
+    }))
+}
+
+#[delete("/comments/{comment_id}/vote")]
+pub async fn delete_comment_vote(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    voter: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<VoteResult>, actix_web::Error> {
+    let comment_id = path.into_inner();
+    require_votable_comment(
+        comments.get_ref(),
+        posts.get_ref(),
+        subs.get_ref(),
+        users.get_ref(),
+        voter.user_id,
+        comment_id,
+    )
+    .await?;
+
+    let score = comments
+        .set_comment_vote(comment_id, voter.user_id, None)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(VoteResult { score, vote: None }))
+}
+
+/// Comments on posts the voter couldn't read can't be voted on either.
+async fn require_votable_comment(
+    comments: &dyn CommentRepository,
+    posts: &dyn PostRepository,
+    subs: &dyn SubRepository,
+    users: &dyn UserRepository,
+    voter_id: i32,
+    comment_id: Uuid,
+) -> Result<(), actix_web::Error> {
+    let post =
+        get_readable_comment_post(comments, posts, subs, users, voter_id, comment_id).await?;
+    if post.archived {
+        return Err(post_archived_error().into());
+    }
+
+    Ok(())
+}
+
+/// The post the comment is on, if the user can read it: published, in a sub they may
+/// read, and NSFW only if they're cleared to see it.
+pub async fn get_readable_comment_post(
+    comments: &dyn CommentRepository,
+    posts: &dyn PostRepository,
+    subs: &dyn SubRepository,
+    users: &dyn UserRepository,
+    user_id: i32,
+    comment_id: Uuid,
+) -> Result<Post, actix_web::Error> {
+    let comment = comments
+        .get_comment(comment_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let post = get_readable_post(posts, comment.post_id, None).await?;
+    get_readable_sub(subs, users, &post.sub, Some(user_id)).await?;
+    if post.nsfw {
+        require_nsfw_clearance(users, Some(user_id)).await?;
+    }
+
+    Ok(post)
+}
+
+/// Comments by shadowbanned authors are only found by the author, the sub's moderators
+/// and admins.
+async fn require_visible_comment(
+    users: &dyn UserRepository,
+    comment: &Comment,
+    sub_name: &str,
+    viewer_id: Option<i32>,
+) -> Result<(), actix_web::Error> {
+    let visible = users
+        .author_visible(comment.user_id, sub_name, viewer_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !visible {
+        return Err(actix_web::error::ErrorNotFound("Comment not found"));
+    }
+
+    Ok(())
+}
+
+/// The viewer's votes on the post's comments; none for anonymous viewers.
+async fn viewer_votes(
+    comments: &dyn CommentRepository,
+    post_id: Uuid,
+    viewer_id: Option<i32>,
+) -> Result<HashMap<Uuid, i16>, actix_web::Error> {
+    match viewer_id {
+        Some(user_id) => comments
+            .get_comment_votes(post_id, user_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError),
+        None => Ok(HashMap::new()),
+    }
+}
+
+/// Replies stay in their parent's thread.
+async fn require_parent_on_post(
+    comments: &dyn CommentRepository,
+    parent_id: Uuid,
+    post_id: Uuid,
+) -> Result<(), actix_web::Error> {
+    let on_post = comments
+        .get_comment(parent_id)
+        .await
+        .is_ok_and(|parent| parent.post_id == post_id);
+    if !on_post {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "parent_not_on_post",
+            "The comment being replied to isn't on this post",
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+async fn require_comment_revision_reader(
+    comments: &dyn CommentRepository,
+    posts: &dyn PostRepository,
+    users: &dyn UserRepository,
+    subs: &dyn SubRepository,
+    caller: &AuthenticatedUser,
+    comment_id: Uuid,
+) -> Result<(), actix_web::Error> {
+    let comment = comments
+        .get_comment(comment_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let post = get_readable_post(posts, comment.post_id, Some(caller.user_id)).await?;
+
+    require_revision_reader(
+        users,
+        subs,
+        &post,
+        comment.user_id,
+        comment.removal,
+        caller.user_id,
+    )
+    .await
+}
+
+async fn require_comment_author(
+    comments: &dyn CommentRepository,
+    author: &AuthenticatedUser,
+    comment_id: Uuid,
+) -> Result<Comment, actix_web::Error> {
+    let comment = comments
+        .get_comment(comment_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    if comment.user_id != author.user_id {
+        return Err(not_author_error().into());
+    }
 
-    comment_repo::delete_comment(&pool, comment_id)
+    Ok(comment)
+}
+
+#[cfg(test)]
+mod comment_api_tests {
+    use super::*;
+    use crate::auth::token::token_tests::test_keys;
+    use crate::model::post::{Post, PostStatus};
+    use crate::model::sub::{ModPermissions, Sub, SubVisibility};
+    use crate::model::user::DbAddUser;
+    use crate::repo::{self, memory::InMemoryRepo};
+    use actix_web::{test, App};
+    use std::sync::Arc;
+
+    async fn seed_post(repo: &InMemoryRepo) -> Uuid {
+        SubRepository::create_sub(
+            repo,
+            &Sub {
+                name: "rust".to_string(),
+                description: "Rust".to_string(),
+                created_at: Utc::now(),
+                nsfw: false,
+                spoiler_by_default: false,
+                subscribers: 0,
+                visibility: SubVisibility::Public,
+                icon_url: None,
+                banner_url: None,
+                primary_color: None,
+                accent_color: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let post = Post {
+            id: Uuid::new_v4(),
+            sub: "rust".to_string(),
+            user_id: 1,
+            title: "title".to_string(),
+            content: "content".to_string(),
+            timestamp: Utc::now(),
+            removal: None,
+            nsfw: false,
+            language: None,
+            score: 0,
+            status: PostStatus::Published,
+            edited_at: None,
+            pin_order: None,
+            locked: false,
+            lock_reason: None,
+            archived: false,
+            flair_id: None,
+            crosspost_parent_id: None,
+            link_url: None,
+            spoiler: false,
+            publish_at: None,
+            author_flair: None,
+        };
+        PostRepository::create_post(repo, &post).await.unwrap()
+    }
+
+    async fn seed_comment(repo: &InMemoryRepo, post_id: Uuid, parent_id: Option<Uuid>) -> Uuid {
+        let comment = Comment {
+            id: Uuid::new_v4(),
+            post_id,
+            user_id: 1,
+            content: "comment".to_string(),
+            timestamp: Utc::now(),
+            parent_id,
+            removal: None,
+            score: 0,
+            edited_at: None,
+            author_flair: None,
+        };
+        CommentRepository::create_comment(repo, &comment)
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_comment_listing_shows_the_callers_vote() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_post(&repo).await;
+        let comment_id = seed_comment(&repo, post_id, None).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_comments)
+                .service(vote_comment),
+        )
+        .await;
+
+        let token = test_keys().issue(2).unwrap().access_token;
+        let bearer = ("Authorization", format!("Bearer {}", token));
+        let vote = test::TestRequest::put()
+            .uri(&format!("/comments/{}/vote", comment_id))
+            .insert_header(bearer.clone())
+            .set_json(serde_json::json!({ "value": -1 }))
+            .to_request();
+        let result: serde_json::Value = test::call_and_read_body_json(&app, vote).await;
+        assert_eq!(result["score"], -1);
+
+        let listing = test::TestRequest::get()
+            .uri(&format!("/posts/{}/comments", post_id))
+            .insert_header(bearer)
+            .to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, listing).await;
+        assert_eq!(page["comments"][0]["score"], -1);
+        assert_eq!(page["comments"][0]["vote"], -1);
+
+        let anonymous = test::TestRequest::get()
+            .uri(&format!("/posts/{}/comments", post_id))
+            .to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, anonymous).await;
+        assert!(page["comments"][0].get("vote").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_comment_history_is_for_author_and_moderators() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let mut user_ids = Vec::new();
+        for username in ["author", "reader", "moderator"] {
+            let user = DbAddUser {
+                username: username.to_string(),
+                password_hash: String::new(),
+                created_at: Utc::now(),
+                email: None,
+                email_verified_at: None,
+            };
+            user_ids.push(
+                UserRepository::create_user(repo.as_ref(), &user)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let post_id = seed_post(&repo).await;
+        repo.add_sub_moderator("rust", user_ids[2], None, ModPermissions::FULL)
+            .await
+            .unwrap();
+        let comment_id = seed_comment(&repo, post_id, None).await;
+        CommentRepository::update_comment(repo.as_ref(), comment_id, "edited".to_string(), None)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_comment_history),
+        )
+        .await;
+        let history = |user_id: i32| {
+            let token = test_keys().issue(user_id).unwrap().access_token;
+            test::TestRequest::get()
+                .uri(&format!("/comments/{}/history", comment_id))
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .to_request()
+        };
+
+        let revisions: Vec<serde_json::Value> =
+            test::call_and_read_body_json(&app, history(user_ids[0])).await;
+        let contents: Vec<&str> = revisions
+            .iter()
+            .map(|revision| revision["content"].as_str().unwrap())
+            .collect();
+        assert_eq!(contents, ["comment", "edited"]);
+
+        let response = test::call_service(&app, history(user_ids[1])).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::FORBIDDEN);
+        let response = test::call_service(&app, history(user_ids[2])).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_comment_revision_diffs_follow_the_post_and_removals() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let author = UserRepository::create_user(
+            repo.as_ref(),
+            &DbAddUser {
+                username: "author".to_string(),
+                password_hash: String::new(),
+                created_at: Utc::now(),
+                email: None,
+                email_verified_at: None,
+            },
+        )
+        .await
+        .unwrap();
+        let post_id = seed_post(&repo).await;
+        let comment_id = seed_comment(&repo, post_id, None).await;
+        CommentRepository::update_comment(repo.as_ref(), comment_id, "edited".to_string(), None)
+            .await
+            .unwrap();
+        let removed_id = CommentRepository::create_comment(
+            repo.as_ref(),
+            &Comment {
+                id: Uuid::new_v4(),
+                post_id,
+                user_id: author,
+                content: "comment".to_string(),
+                timestamp: Utc::now(),
+                parent_id: None,
+                removal: Some(RemovalKind::Legal),
+                score: 0,
+                edited_at: None,
+                author_flair: None,
+            },
+        )
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_comment_revision_diff),
+        )
+        .await;
+        let diff = |comment_id: Uuid, to: i32| {
+            let token = test_keys().issue(author).unwrap().access_token;
+            test::TestRequest::get()
+                .uri(&format!("/comments/{}/revisions/1/diff/{}", comment_id, to))
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .to_request()
+        };
+
+        let response = test::call_service(&app, diff(comment_id, 2)).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        let response = test::call_service(&app, diff(removed_id, 1)).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+        let mut sub = repo.get_sub_by_name("rust").await.unwrap();
+        sub.visibility = SubVisibility::Private;
+        SubRepository::update_sub(repo.as_ref(), &sub)
+            .await
+            .unwrap();
+        let response = test::call_service(&app, diff(comment_id, 2)).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_replies_must_be_on_the_parents_post() {
+        let repo = InMemoryRepo::default();
+        let post_id = seed_post(&repo).await;
+        let parent_id = seed_comment(&repo, post_id, None).await;
+        let mut other = repo.get_post(post_id).await.unwrap();
+        other.id = Uuid::new_v4();
+        let other_id = PostRepository::create_post(&repo, &other).await.unwrap();
+
+        assert!(require_parent_on_post(&repo, parent_id, post_id)
+            .await
+            .is_ok());
+        let error = require_parent_on_post(&repo, parent_id, other_id)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+        assert!(require_parent_on_post(&repo, Uuid::new_v4(), post_id)
+            .await
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_comment_listing_pages_with_cursors() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_post(&repo).await;
+        for _ in 0..3 {
+            seed_comment(&repo, post_id, None).await;
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_comments),
+        )
+        .await;
+        let page = |query: String| {
+            test::TestRequest::get()
+                .uri(&format!("/posts/{}/comments?{}", post_id, query))
+                .to_request()
+        };
+
+        let first: serde_json::Value =
+            test::call_and_read_body_json(&app, page("limit=2".to_string())).await;
+        assert_eq!(first["comments"].as_array().unwrap().len(), 2);
+        assert_eq!(first["total"], 3);
+
+        let cursor = first["next_cursor"].as_str().unwrap();
+        let rest: serde_json::Value =
+            test::call_and_read_body_json(&app, page(format!("limit=2&after={}", cursor))).await;
+        assert_eq!(rest["comments"].as_array().unwrap().len(), 1);
+        assert!(rest["next_cursor"].is_null());
+
+        let response = test::call_service(&app, page("after=nonsense".to_string())).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_comment_children_continue_after_the_last_reply() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_post(&repo).await;
+        let parent_id = seed_comment(&repo, post_id, None).await;
+        for _ in 0..3 {
+            seed_comment(&repo, post_id, Some(parent_id)).await;
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_comment_children),
+        )
+        .await;
+        let children = |query: String| {
+            test::TestRequest::get()
+                .uri(&format!("/comments/{}/children?{}", parent_id, query))
+                .to_request()
+        };
+
+        let first: serde_json::Value =
+            test::call_and_read_body_json(&app, children("limit=2".to_string())).await;
+        assert_eq!(first["comments"].as_array().unwrap().len(), 2);
+        assert_eq!(first["more_comments"], 1);
+
+        let last = first["comments"][1]["id"].as_str().unwrap();
+        let rest: serde_json::Value =
+            test::call_and_read_body_json(&app, children(format!("limit=2&after={}", last))).await;
+        assert_eq!(rest["comments"].as_array().unwrap().len(), 1);
+        assert_eq!(rest["more_comments"], 0);
+        assert_ne!(rest["comments"][0]["id"], first["comments"][0]["id"]);
+    }
+
+    #[actix_web::test]
+    async fn test_comment_context_includes_ancestors_and_replies() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_post(&repo).await;
+        let root_id = seed_comment(&repo, post_id, None).await;
+        let parent_id = seed_comment(&repo, post_id, Some(root_id)).await;
+        let comment_id = seed_comment(&repo, post_id, Some(parent_id)).await;
+        seed_comment(&repo, post_id, Some(comment_id)).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_comment_context),
+        )
+        .await;
+        let context = |query: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/comments/{}/context?{}", comment_id, query))
+                .to_request()
+        };
+
+        let full: serde_json::Value = test::call_and_read_body_json(&app, context("")).await;
+        let ancestors: Vec<String> = full["ancestors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|comment| comment["id"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(ancestors, vec![root_id.to_string(), parent_id.to_string()]);
+        assert_eq!(full["comment"]["id"], comment_id.to_string());
+        assert_eq!(full["comment"]["replies"].as_array().unwrap().len(), 1);
 
-    Ok(HttpResponse::Ok().body(format!("{} was deleted", comment_id.to_string())))
+        let nearest: serde_json::Value =
+            test::call_and_read_body_json(&app, context("ancestors=1")).await;
+        assert_eq!(nearest["ancestors"].as_array().unwrap().len(), 1);
+        assert_eq!(nearest["ancestors"][0]["id"], parent_id.to_string());
+    }
 }
@@ -0,0 +1,23 @@
+use crate::auth::{Admin, RequireRole};
+use crate::config::{RuntimeConfig, RuntimeSettings};
+use actix_web::{get, post, web::Data, web::Json};
+
+#[get("/admin/config")]
+pub async fn get_runtime_config(
+    settings: Data<RuntimeSettings>,
+    _admin: RequireRole<Admin>,
+) -> Json<RuntimeConfig> {
+    Json(settings.current())
+}
+
+#[post("/admin/config/reload")]
+pub async fn reload_runtime_config(
+    settings: Data<RuntimeSettings>,
+    _admin: RequireRole<Admin>,
+) -> Result<Json<RuntimeConfig>, actix_web::Error> {
+    let config = settings
+        .reload()
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+
+    Ok(Json(config))
+}
@@ -0,0 +1,121 @@
+use crate::auth::{Admin, RequireRole, Viewer};
+use crate::model::experiment::{
+    Assignment, Experiment, ExperimentUpdate, NewExperiment, VariantExposures,
+};
+use crate::repo::experiment as experiment_repo;
+use actix_web::{get, patch, post, web::Data, web::Json, web::Path, HttpResponse};
+use sqlx::PgPool;
+
+#[post("/admin/experiments")]
+pub async fn create_experiment(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    body: Json<NewExperiment>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if body.variants.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("An experiment needs at least one variant"));
+    }
+    if !(0..=100).contains(&body.ramp_percent) {
+        return Ok(HttpResponse::BadRequest().body("ramp_percent must be between 0 and 100"));
+    }
+
+    let key = experiment_repo::create_experiment(&pool, &body)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(key))
+}
+
+#[get("/admin/experiments")]
+pub async fn get_experiments(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+) -> Result<Json<Vec<Experiment>>, actix_web::Error> {
+    let experiments = experiment_repo::get_experiments(&pool)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(experiments))
+}
+
+#[patch("/admin/experiments/{key}")]
+pub async fn update_experiment(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    path: Path<String>,
+    body: Json<ExperimentUpdate>,
+) -> Result<Json<Experiment>, actix_web::Error> {
+    let key = path.into_inner();
+    if body
+        .ramp_percent
+        .is_some_and(|ramp| !(0..=100).contains(&ramp))
+    {
+        return Err(actix_web::error::ErrorBadRequest(
+            "ramp_percent must be between 0 and 100",
+        ));
+    }
+
+    let experiment = experiment_repo::update_experiment(&pool, &key, &body)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    Ok(Json(experiment))
+}
+
+#[get("/admin/experiments/{key}/exposures")]
+pub async fn get_experiment_exposures(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    path: Path<String>,
+) -> Result<Json<Vec<VariantExposures>>, actix_web::Error> {
+    let key = path.into_inner();
+
+    let counts = experiment_repo::get_exposure_counts(&pool, &key)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(counts))
+}
+
+/// The caller's variant. Serving an assignment counts as an exposure, so clients
+/// should ask at the point the experimental behaviour is shown.
+#[get("/experiments/{key}/assignment")]
+pub async fn get_assignment(
+    pool: Data<PgPool>,
+    path: Path<String>,
+    viewer: Viewer,
+) -> Result<Json<Assignment>, actix_web::Error> {
+    let key = path.into_inner();
+
+    let variant = match viewer.user_id() {
+        Some(user_id) => experiment_variant(&pool, &key, user_id).await?,
+        None => None,
+    };
+
+    Ok(Json(Assignment {
+        experiment: key,
+        variant,
+    }))
+}
+
+/// Looks up `user_id`'s variant of experiment `key` and logs the exposure. Server-side
+/// code paths under test branch on the result; `None` means default behaviour.
+pub async fn experiment_variant(
+    pool: &PgPool,
+    key: &str,
+    user_id: i32,
+) -> Result<Option<String>, actix_web::Error> {
+    let experiment = experiment_repo::get_experiment(pool, key)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    let Some(variant) = experiment.assign(user_id) else {
+        return Ok(None);
+    };
+
+    experiment_repo::log_exposure(pool, key, user_id, variant)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Some(variant.to_string()))
+}
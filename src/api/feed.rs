@@ -0,0 +1,372 @@
+use crate::api::saved::mark_saved_posts;
+use crate::auth::{AuthenticatedUser, Viewer};
+use crate::error::ApiError;
+use crate::model::feed::{
+    normalize_feed_name, CustomFeed, CustomFeedUpdate, NewCustomFeed, MAX_FEED_NAME_CHARS,
+    MAX_FEED_SUBS,
+};
+use crate::model::post::{
+    Listing, ListingCursor, ListingQuery, PostPage, DEFAULT_LISTING_LIMIT, MAX_LISTING_LIMIT,
+};
+use crate::repo::{
+    feed as feed_repo, post as post_repo, post::PostRepository, sub::SubRepository,
+    user::UserRepository,
+};
+use actix_web::http::header::{CacheControl, CacheDirective, VARY};
+use actix_web::{
+    delete, get, http::StatusCode, post, put, web::Data, web::Json, web::Path, web::Query,
+    CustomizeResponder, Responder,
+};
+use chrono::Utc;
+use sqlx::PgPool;
+
+/// How long shared caches may keep an anonymous `/feed/all` or `/feed/popular` page.
+const FEED_MAX_AGE_SECS: u32 = 60;
+
+/// Posts from every sub, newest first unless `?sort=` says otherwise, narrowed to the
+/// viewer's preferred languages if they have set any. NSFW posts are listed for cleared
+/// viewers as `?include_nsfw=` or their `show_nsfw` preference says.
+#[get("/feed/all")]
+pub async fn get_all_feed(
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
+    query: Query<ListingQuery>,
+) -> Result<CustomizeResponder<Json<PostPage>>, actix_web::Error> {
+    let listing = listing(&query, viewer.user_id())?;
+    let (include_nsfw, languages) = match viewer.user_id() {
+        Some(viewer_id) => {
+            let viewer = users
+                .get_user_by_id(viewer_id)
+                .await
+                .map_err(actix_web::error::ErrorNotFound)?;
+            (
+                viewer.lists_nsfw(query.include_nsfw, Utc::now().date_naive()),
+                viewer.preferred_languages,
+            )
+        }
+        None => (false, Vec::new()),
+    };
+
+    let ranked = posts
+        .get_all_posts(include_nsfw, &languages, &listing)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let mut page = PostPage::new(ranked, &listing);
+    mark_saved_posts(posts.get_ref(), viewer.user_id(), &mut page).await?;
+
+    Ok(cacheable_page(page, &viewer))
+}
+
+/// Well-received posts from subs anyone may read: those scoring at least
+/// `POPULAR_MIN_SCORE`, narrowed to the viewer's preferred languages like `/feed/all`.
+/// NSFW posts are only listed if a cleared viewer asks for them with `?include_nsfw=true`,
+/// whatever their `show_nsfw` preference.
+#[get("/feed/popular")]
+pub async fn get_popular_feed(
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
+    query: Query<ListingQuery>,
+) -> Result<CustomizeResponder<Json<PostPage>>, actix_web::Error> {
+    let listing = listing(&query, viewer.user_id())?;
+    let (include_nsfw, languages) = match viewer.user_id() {
+        Some(viewer_id) => {
+            let viewer = users
+                .get_user_by_id(viewer_id)
+                .await
+                .map_err(actix_web::error::ErrorNotFound)?;
+            (
+                viewer.lists_nsfw(
+                    Some(query.include_nsfw.unwrap_or(false)),
+                    Utc::now().date_naive(),
+                ),
+                viewer.preferred_languages,
+            )
+        }
+        None => (false, Vec::new()),
+    };
+
+    let ranked = posts
+        .get_popular_posts(include_nsfw, &languages, &listing)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let mut page = PostPage::new(ranked, &listing);
+    mark_saved_posts(posts.get_ref(), viewer.user_id(), &mut page).await?;
+
+    Ok(cacheable_page(page, &viewer))
+}
+
+/// Posts from the subs the caller is subscribed to, taking the same `?sort=&t=&limit=&after=`
+/// and `?include_nsfw=` as `GET /feed/all`.
+#[get("/feed/home")]
+pub async fn get_home_feed(
+    pool: Data<PgPool>,
+    reader: AuthenticatedUser,
+    query: Query<ListingQuery>,
+) -> Result<Json<PostPage>, actix_web::Error> {
+    let listing = listing(&query, Some(reader.user_id))?;
+    let include_nsfw = pool
+        .get_user_by_id(reader.user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?
+        .lists_nsfw(query.include_nsfw, Utc::now().date_naive());
+
+    let ranked = post_repo::get_home_posts(&pool, include_nsfw, &listing)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let mut page = PostPage::new(ranked, &listing);
+    mark_saved_posts(pool.get_ref(), Some(reader.user_id), &mut page).await?;
+
+    Ok(Json(page))
+}
+
+/// Gathers subs under a name so their posts can be read as one feed. Feeds belong to
+/// their creator and only they can see them.
+#[post("/users/{user_id}/feeds")]
+pub async fn create_custom_feed(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<i32>,
+    body: Json<NewCustomFeed>,
+) -> Result<Json<CustomFeed>, actix_web::Error> {
+    let user_id = path.into_inner();
+    caller.require_self(user_id)?;
+    let name = normalize_feed_name(&body.name).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_feed_name",
+            format!(
+                "Feed names are 1 to {} letters, digits, underscores or hyphens",
+                MAX_FEED_NAME_CHARS
+            ),
+        )
+        .with_arg("max", MAX_FEED_NAME_CHARS.to_string())
+    })?;
+    let subs = feed_subs(pool.get_ref(), &body.subs).await?;
+
+    feed_repo::create_custom_feed(&pool, user_id, &name, &subs)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(db_error) if db_error.is_unique_violation() => ApiError::new(
+                StatusCode::CONFLICT,
+                "feed_exists",
+                "You already have a feed with that name",
+            )
+            .into(),
+            _ => actix_web::error::ErrorInternalServerError(e),
+        })?;
+
+    Ok(Json(custom_feed(&pool, user_id, &name).await?))
+}
+
+#[get("/users/{user_id}/feeds")]
+pub async fn get_custom_feeds(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<i32>,
+) -> Result<Json<Vec<CustomFeed>>, actix_web::Error> {
+    let user_id = path.into_inner();
+    caller.require_self(user_id)?;
+
+    let feeds = feed_repo::get_custom_feeds(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(feeds))
+}
+
+#[get("/users/{user_id}/feeds/{name}")]
+pub async fn get_custom_feed(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<(i32, String)>,
+) -> Result<Json<CustomFeed>, actix_web::Error> {
+    let (user_id, name) = path.into_inner();
+    caller.require_self(user_id)?;
+
+    Ok(Json(custom_feed(&pool, user_id, &name).await?))
+}
+
+#[put("/users/{user_id}/feeds/{name}")]
+pub async fn update_custom_feed(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<(i32, String)>,
+    body: Json<CustomFeedUpdate>,
+) -> Result<Json<CustomFeed>, actix_web::Error> {
+    let (user_id, name) = path.into_inner();
+    caller.require_self(user_id)?;
+    let name = normalize_feed_name(&name).ok_or_else(custom_feed_not_found)?;
+    let subs = feed_subs(pool.get_ref(), &body.subs).await?;
+
+    let updated = feed_repo::set_custom_feed_subs(&pool, user_id, &name, &subs)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !updated {
+        return Err(custom_feed_not_found());
+    }
+
+    Ok(Json(custom_feed(&pool, user_id, &name).await?))
+}
+
+#[delete("/users/{user_id}/feeds/{name}")]
+pub async fn delete_custom_feed(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<(i32, String)>,
+) -> Result<Json<Vec<CustomFeed>>, actix_web::Error> {
+    let (user_id, name) = path.into_inner();
+    caller.require_self(user_id)?;
+    let name = normalize_feed_name(&name).ok_or_else(custom_feed_not_found)?;
+
+    let deleted = feed_repo::delete_custom_feed(&pool, user_id, &name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !deleted {
+        return Err(custom_feed_not_found());
+    }
+    let feeds = feed_repo::get_custom_feeds(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(feeds))
+}
+
+/// The feed's subs merged into one listing, taking the same `?sort=&t=&limit=&after=` and
+/// `?include_nsfw=` as `GET /feed/all`. Posts in private subs the owner has since left are
+/// left out.
+#[get("/users/{user_id}/feeds/{name}/posts")]
+pub async fn get_custom_feed_posts(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<(i32, String)>,
+    query: Query<ListingQuery>,
+) -> Result<Json<PostPage>, actix_web::Error> {
+    let (user_id, name) = path.into_inner();
+    caller.require_self(user_id)?;
+    let feed = custom_feed(&pool, user_id, &name).await?;
+    let listing = listing(&query, Some(user_id))?;
+    let reader = pool
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let include_nsfw = reader.lists_nsfw(query.include_nsfw, Utc::now().date_naive());
+
+    let ranked = post_repo::get_posts_in_subs(&pool, &feed.subs, include_nsfw, &listing)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let mut page = PostPage::new(ranked, &listing);
+    mark_saved_posts(pool.get_ref(), Some(user_id), &mut page).await?;
+
+    Ok(Json(page))
+}
+
+async fn custom_feed(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+) -> Result<CustomFeed, actix_web::Error> {
+    let name = normalize_feed_name(name).ok_or_else(custom_feed_not_found)?;
+    feed_repo::get_custom_feed(pool, user_id, &name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(custom_feed_not_found)
+}
+
+/// The named subs, trimmed and without duplicates. Each must exist.
+async fn feed_subs(
+    subs: &dyn SubRepository,
+    names: &[String],
+) -> Result<Vec<String>, actix_web::Error> {
+    let mut unique: Vec<String> = Vec::with_capacity(names.len());
+    for name in names {
+        let name = name.trim();
+        if !unique.iter().any(|sub| sub == name) {
+            unique.push(name.to_string());
+        }
+    }
+    if unique.len() > MAX_FEED_SUBS {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "too_many_feed_subs",
+            format!("A feed can gather at most {} subs", MAX_FEED_SUBS),
+        )
+        .with_arg("max", MAX_FEED_SUBS.to_string())
+        .into());
+    }
+
+    let found = subs
+        .get_subs_by_names(&unique)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if let Some(missing) = unique
+        .iter()
+        .find(|name| !found.iter().any(|sub| &sub.name == *name))
+    {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "unknown_sub",
+            format!("There is no sub named {}", missing),
+        )
+        .with_arg("sub", missing.clone())
+        .into());
+    }
+
+    Ok(unique)
+}
+
+fn custom_feed_not_found() -> actix_web::Error {
+    actix_web::error::ErrorNotFound("Feed not found")
+}
+
+/// Anonymous pages are the same for everyone, so shared caches may keep them for a while;
+/// signed-in pages carry the viewer's own saved and hidden posts.
+fn cacheable_page(page: PostPage, viewer: &Viewer) -> CustomizeResponder<Json<PostPage>> {
+    let cache_control = match viewer.user_id() {
+        Some(_) => CacheControl(vec![CacheDirective::Private, CacheDirective::NoCache]),
+        None => CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(FEED_MAX_AGE_SECS),
+        ]),
+    };
+
+    Json(page)
+        .customize()
+        .insert_header(cache_control)
+        .insert_header((VARY, "Authorization"))
+}
+
+/// Reads a listing's query string. Cursors from a listing with another sort are
+/// rejected with malformed ones, since their rank means nothing under this sort. Posts
+/// the viewer has hidden are left out.
+pub fn listing(query: &ListingQuery, viewer_id: Option<i32>) -> Result<Listing, ApiError> {
+    let after = match &query.after {
+        Some(cursor) => Some(
+            ListingCursor::decode(cursor)
+                .filter(|cursor| cursor.sort == query.sort)
+                .ok_or_else(invalid_cursor_error)?,
+        ),
+        None => None,
+    };
+
+    Ok(Listing {
+        sort: query.sort,
+        since: query.sort.since(query.t, Utc::now()),
+        after,
+        limit: query
+            .limit
+            .unwrap_or(DEFAULT_LISTING_LIMIT)
+            .clamp(1, MAX_LISTING_LIMIT),
+        flair: query.flair,
+        viewer: viewer_id,
+    })
+}
+
+pub fn invalid_cursor_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_cursor",
+        "This cursor is malformed or belongs to a listing with another sort",
+    )
+}
@@ -0,0 +1,283 @@
+use crate::api::user::unknown_language_error;
+use crate::auth::role::{moderation_scope, require_role, require_sub_moderator};
+use crate::auth::{AuthenticatedUser, Moderator, RequireRole};
+use crate::error::ApiError;
+use crate::model::automod::normalize_domain;
+use crate::model::dto::CommentView;
+use crate::model::filter::{
+    compile_pattern, FilterAction, HeldContent, MatchKind, NewWordFilter, WordFilter,
+    WordFilterQuery,
+};
+use crate::model::language::normalize_language_tag;
+use crate::model::moderation::{ModAction, RemovalKind};
+use crate::model::sub::ModPermission;
+use crate::model::user::{Role, User};
+use crate::repo::{
+    comment::CommentRepository, filter as filter_repo, moderation as moderation_repo,
+    post::PostRepository,
+};
+use actix_web::{
+    delete, get, http::StatusCode, post, web::Data, web::Json, web::Path, web::Query, HttpResponse,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// The site-wide filters, plus those of `sub` if given.
+pub async fn load_filters(
+    pool: &PgPool,
+    sub: Option<&str>,
+) -> Result<Vec<WordFilter>, actix_web::Error> {
+    filter_repo::get_filters(pool, sub)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)
+}
+
+/// How new content is stored given the most severe filter action it triggered:
+/// held for review on `queue`, rejected on `block`.
+pub fn filter_removal(action: Option<FilterAction>) -> Result<Option<RemovalKind>, ApiError> {
+    match action {
+        Some(FilterAction::Block) => Err(content_blocked_error()),
+        Some(FilterAction::Queue) => Ok(Some(RemovalKind::Filter)),
+        Some(FilterAction::Mask) | None => Ok(None),
+    }
+}
+
+pub fn content_blocked_error() -> ApiError {
+    ApiError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "content_blocked",
+        "This contains words or links that aren't allowed here",
+    )
+}
+
+/// A sub's filters are managed by its moderators, the site-wide ones by admins.
+pub async fn require_filter_moderator(
+    pool: &PgPool,
+    user_id: i32,
+    sub: Option<&str>,
+) -> Result<User, actix_web::Error> {
+    match sub {
+        Some(sub) => require_sub_moderator(pool, pool, user_id, sub, ModPermission::Settings).await,
+        None => require_role(pool, user_id, Role::Admin).await,
+    }
+}
+
+#[post("/admin/filters")]
+pub async fn create_filter(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    body: Json<NewWordFilter>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let moderator = require_filter_moderator(&pool, caller.user_id, body.sub.as_deref()).await?;
+    let mut filter = body.into_inner();
+    if filter.match_kind == MatchKind::Domain {
+        filter.pattern = normalize_domain(&filter.pattern).ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_filter_domain",
+                "A domain filter's pattern must be a domain such as example.com",
+            )
+        })?;
+        if filter.action == FilterAction::Mask {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "domain_filter_cannot_mask",
+                "Domain filters can only queue or block",
+            )
+            .into());
+        }
+    }
+    compile_pattern(&filter.pattern, filter.match_kind)
+        .map_err(actix_web::error::ErrorBadRequest)?;
+
+    if let Some(tag) = &filter.locale {
+        filter.locale =
+            Some(normalize_language_tag(tag).ok_or_else(|| unknown_language_error(tag))?);
+    }
+
+    let filter_id = filter_repo::create_filter(&pool, &filter, moderator.id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.id,
+        ModAction::AddWordFilter,
+        None,
+        filter.sub.as_deref(),
+        json!({
+            "filter_id": filter_id,
+            "pattern": filter.pattern,
+            "match_kind": filter.match_kind,
+            "action": filter.action,
+        }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(filter_id.to_string()))
+}
+
+/// The site-wide filters, plus those of `?sub=` for its moderators.
+#[get("/admin/filters")]
+pub async fn get_filters(
+    pool: Data<PgPool>,
+    _moderator: RequireRole<Moderator>,
+    caller: AuthenticatedUser,
+    query: Query<WordFilterQuery>,
+) -> Result<Json<Vec<WordFilter>>, actix_web::Error> {
+    if let Some(sub) = &query.sub {
+        require_filter_moderator(&pool, caller.user_id, Some(sub)).await?;
+    }
+    let filters = load_filters(&pool, query.sub.as_deref()).await?;
+
+    Ok(Json(filters))
+}
+
+#[delete("/admin/filters/{filter_id}")]
+pub async fn delete_filter(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<i64>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let filter_id = path.into_inner();
+    let filter = filter_repo::get_filter(&pool, filter_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Filter not found"))?;
+    let moderator = require_filter_moderator(&pool, caller.user_id, filter.sub.as_deref()).await?;
+
+    let deleted = filter_repo::delete_filter(&pool, filter_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Filter not found"))?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.id,
+        ModAction::RemoveWordFilter,
+        None,
+        deleted.sub.as_deref(),
+        json!({ "filter_id": filter_id, "pattern": deleted.pattern }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!("Filter {} was deleted", filter_id)))
+}
+
+/// Content held in the subs the caller moderates, or in every sub for admins.
+#[get("/admin/filters/held")]
+pub async fn get_held_content(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+) -> Result<Json<HeldContent>, actix_web::Error> {
+    let moderator_id = moderation_scope(&**pool, caller.user_id).await?;
+    let posts = filter_repo::get_held_posts(&pool, moderator_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let comments = filter_repo::get_held_comments(&pool, moderator_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(HeldContent {
+        posts,
+        comments: comments.into_iter().map(CommentView::from).collect(),
+    }))
+}
+
+#[post("/admin/filters/held/posts/{post_id}/approve")]
+pub async fn approve_held_post(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let post_id = path.into_inner();
+    let post = PostRepository::get_post(pool.get_ref(), post_id)
+        .await
+        .map_err(|_| actix_web::error::ErrorNotFound("No held post with that id"))?;
+    let moderator = require_sub_moderator(
+        pool.get_ref(),
+        pool.get_ref(),
+        caller.user_id,
+        &post.sub,
+        ModPermission::Posts,
+    )
+    .await?;
+
+    let approved = filter_repo::approve_post(&pool, post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !approved {
+        return Err(actix_web::error::ErrorNotFound("No held post with that id"));
+    }
+    log_approval(
+        &pool,
+        moderator.id,
+        &post.sub,
+        json!({ "post_id": post_id }),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().body(format!("{} was approved", post_id)))
+}
+
+#[post("/admin/filters/held/comments/{comment_id}/approve")]
+pub async fn approve_held_comment(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let comment_id = path.into_inner();
+    let comment = CommentRepository::get_comment(pool.get_ref(), comment_id)
+        .await
+        .map_err(|_| actix_web::error::ErrorNotFound("No held comment with that id"))?;
+    let post = PostRepository::get_post(pool.get_ref(), comment.post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let moderator = require_sub_moderator(
+        pool.get_ref(),
+        pool.get_ref(),
+        caller.user_id,
+        &post.sub,
+        ModPermission::Posts,
+    )
+    .await?;
+
+    let approved = filter_repo::approve_comment(&pool, comment_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !approved {
+        return Err(actix_web::error::ErrorNotFound(
+            "No held comment with that id",
+        ));
+    }
+    log_approval(
+        &pool,
+        moderator.id,
+        &post.sub,
+        json!({ "comment_id": comment_id }),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().body(format!("{} was approved", comment_id)))
+}
+
+async fn log_approval(
+    pool: &PgPool,
+    moderator_id: i32,
+    sub: &str,
+    details: serde_json::Value,
+) -> Result<(), actix_web::Error> {
+    moderation_repo::log_mod_action(
+        pool,
+        moderator_id,
+        ModAction::ApproveFilteredContent,
+        None,
+        Some(sub),
+        details,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(())
+}
@@ -0,0 +1,416 @@
+use crate::api::filter::{content_blocked_error, load_filters};
+use crate::api::post::{get_readable_post, not_author_error};
+use crate::api::sub::get_readable_sub;
+use crate::auth::role::require_sub_moderator;
+use crate::auth::{AuthenticatedUser, ManageFlair, RequireSubModerator, Viewer};
+use crate::error::ApiError;
+use crate::model::filter::{apply_filters, FilterAction};
+use crate::model::flair::{
+    normalize_color, normalize_flair_text, Flair, FlairUpdate, NewFlair, NewUserFlair,
+    PostFlairRequest, UserFlair, UserFlairChoice, UserFlairUpdate, MAX_FLAIR_TEXT_CHARS,
+};
+use crate::model::moderation::ModAction;
+use crate::model::post::Post;
+use crate::model::sub::ModPermission;
+use crate::repo::{
+    flair as flair_repo, moderation as moderation_repo, post::PostRepository, sub::SubRepository,
+    user::UserRepository,
+};
+use actix_web::{delete, get, http::StatusCode, patch, post, put, web::Data, web::Json, web::Path};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[get("/subs/{sub}/flairs")]
+pub async fn get_flairs(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
+    path: Path<String>,
+) -> Result<Json<Vec<Flair>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    get_readable_sub(subs.get_ref(), users.get_ref(), &sub_name, viewer.user_id()).await?;
+
+    let flairs = flair_repo::get_flairs(&pool, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(flairs))
+}
+
+#[post("/subs/{sub}/flairs")]
+pub async fn create_flair(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    moderator: RequireSubModerator<ManageFlair>,
+    path: Path<String>,
+    body: Json<NewFlair>,
+) -> Result<Json<Flair>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let text = normalize_flair_text(&body.text).ok_or_else(invalid_flair_text_error)?;
+    let text = filter_flair_text(&pool, &sub_name, &text).await?;
+    let color = normalize_color(&body.color).ok_or_else(invalid_flair_color_error)?;
+
+    let flair = flair_repo::create_flair(&pool, &sub_name, &text, &color, body.mod_only)
+        .await
+        .map_err(flair_write_error)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::CreateFlair,
+        None,
+        Some(&sub_name),
+        json!({ "flair_id": flair.id, "text": flair.text }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(flair))
+}
+
+#[patch("/subs/{sub}/flairs/{flair_id}")]
+pub async fn update_flair(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManageFlair>,
+    path: Path<(String, i32)>,
+    body: Json<FlairUpdate>,
+) -> Result<Json<Flair>, actix_web::Error> {
+    let (sub_name, flair_id) = path.into_inner();
+    let mut flair = flair_repo::get_flair(&pool, &sub_name, flair_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Flair not found"))?;
+    if let Some(text) = &body.text {
+        let text = normalize_flair_text(text).ok_or_else(invalid_flair_text_error)?;
+        flair.text = filter_flair_text(&pool, &sub_name, &text).await?;
+    }
+    if let Some(color) = &body.color {
+        flair.color = normalize_color(color).ok_or_else(invalid_flair_color_error)?;
+    }
+    if let Some(mod_only) = body.mod_only {
+        flair.mod_only = mod_only;
+    }
+
+    let flair = flair_repo::update_flair(&pool, &flair)
+        .await
+        .map_err(flair_write_error)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Flair not found"))?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::UpdateFlair,
+        None,
+        Some(&sub_name),
+        json!({
+            "flair_id": flair.id,
+            "text": flair.text,
+            "color": flair.color,
+            "mod_only": flair.mod_only,
+        }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(flair))
+}
+
+/// Posts wearing the flair are left without one.
+#[delete("/subs/{sub}/flairs/{flair_id}")]
+pub async fn delete_flair(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManageFlair>,
+    path: Path<(String, i32)>,
+) -> Result<Json<Flair>, actix_web::Error> {
+    let (sub_name, flair_id) = path.into_inner();
+    let flair = flair_repo::get_flair(&pool, &sub_name, flair_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Flair not found"))?;
+
+    let deleted = flair_repo::delete_flair(&pool, &sub_name, flair_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !deleted {
+        return Err(actix_web::error::ErrorNotFound("Flair not found"));
+    }
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::DeleteFlair,
+        None,
+        Some(&sub_name),
+        json!({ "flair_id": flair_id, "text": flair.text }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(flair))
+}
+
+/// Authors flair their own posts with any of the sub's flairs but `mod_only` ones;
+/// the sub's moderators can flair any post in it.
+#[patch("/posts/{id}/flair")]
+pub async fn set_post_flair(
+    pool: Data<PgPool>,
+    posts: Data<dyn PostRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<PostFlairRequest>,
+) -> Result<Json<Post>, actix_web::Error> {
+    let post_id = path.into_inner();
+    let post = get_readable_post(posts.get_ref(), post_id, Some(caller.user_id)).await?;
+    let is_author = post.user_id == caller.user_id;
+    let require_moderator = || {
+        require_sub_moderator(
+            pool.get_ref(),
+            pool.get_ref(),
+            caller.user_id,
+            &post.sub,
+            ModPermission::Flair,
+        )
+    };
+    if !is_author {
+        require_moderator().await.map_err(|_| not_author_error())?;
+    }
+    // Nor can they take a `mod_only` flair off
+    if let (true, Some(current_id)) = (is_author, post.flair_id) {
+        let current = flair_repo::get_flair(&pool, &post.sub, current_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if current.is_some_and(|flair| flair.mod_only) {
+            require_moderator().await?;
+        }
+    }
+    if let Some(flair_id) = body.flair_id {
+        let flair = flair_repo::get_flair(&pool, &post.sub, flair_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .ok_or_else(|| unknown_flair_error(&post.sub))?;
+        if flair.mod_only && is_author {
+            require_moderator().await?;
+        }
+    }
+
+    flair_repo::set_post_flair(&pool, post_id, body.flair_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(Post {
+        flair_id: body.flair_id,
+        ..post
+    }))
+}
+
+#[get("/subs/{sub}/userflairs")]
+pub async fn get_user_flairs(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
+    path: Path<String>,
+) -> Result<Json<Vec<UserFlair>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    get_readable_sub(subs.get_ref(), users.get_ref(), &sub_name, viewer.user_id()).await?;
+
+    let flairs = flair_repo::get_user_flairs(&pool, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(flairs))
+}
+
+#[post("/subs/{sub}/userflairs")]
+pub async fn create_user_flair(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    moderator: RequireSubModerator<ManageFlair>,
+    path: Path<String>,
+    body: Json<NewUserFlair>,
+) -> Result<Json<UserFlair>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let text = normalize_flair_text(&body.text).ok_or_else(invalid_flair_text_error)?;
+    let text = filter_flair_text(&pool, &sub_name, &text).await?;
+    let color = normalize_color(&body.color).ok_or_else(invalid_flair_color_error)?;
+
+    let flair = flair_repo::create_user_flair(&pool, &sub_name, &text, &color)
+        .await
+        .map_err(flair_write_error)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::CreateUserFlair,
+        None,
+        Some(&sub_name),
+        json!({ "user_flair_id": flair.id, "text": flair.text }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(flair))
+}
+
+#[patch("/subs/{sub}/userflairs/{flair_id}")]
+pub async fn update_user_flair(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManageFlair>,
+    path: Path<(String, i32)>,
+    body: Json<UserFlairUpdate>,
+) -> Result<Json<UserFlair>, actix_web::Error> {
+    let (sub_name, flair_id) = path.into_inner();
+    let mut flair = flair_repo::get_user_flair(&pool, &sub_name, flair_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Flair not found"))?;
+    if let Some(text) = &body.text {
+        let text = normalize_flair_text(text).ok_or_else(invalid_flair_text_error)?;
+        flair.text = filter_flair_text(&pool, &sub_name, &text).await?;
+    }
+    if let Some(color) = &body.color {
+        flair.color = normalize_color(color).ok_or_else(invalid_flair_color_error)?;
+    }
+
+    let flair = flair_repo::update_user_flair(&pool, &flair)
+        .await
+        .map_err(flair_write_error)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Flair not found"))?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::UpdateUserFlair,
+        None,
+        Some(&sub_name),
+        json!({ "user_flair_id": flair.id, "text": flair.text, "color": flair.color }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(flair))
+}
+
+/// Users who picked the flair are left without one.
+#[delete("/subs/{sub}/userflairs/{flair_id}")]
+pub async fn delete_user_flair(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManageFlair>,
+    path: Path<(String, i32)>,
+) -> Result<Json<UserFlair>, actix_web::Error> {
+    let (sub_name, flair_id) = path.into_inner();
+    let flair = flair_repo::get_user_flair(&pool, &sub_name, flair_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Flair not found"))?;
+
+    let deleted = flair_repo::delete_user_flair(&pool, &sub_name, flair_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !deleted {
+        return Err(actix_web::error::ErrorNotFound("Flair not found"));
+    }
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::DeleteUserFlair,
+        None,
+        Some(&sub_name),
+        json!({ "user_flair_id": flair_id, "text": flair.text }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(flair))
+}
+
+/// Picks one of the sub's user flairs for the caller, shown with their posts and
+/// comments in the sub. Returns the flair, or `null` when it was cleared.
+#[put("/subs/{sub}/userflair")]
+pub async fn choose_user_flair(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    caller: AuthenticatedUser,
+    path: Path<String>,
+    body: Json<UserFlairChoice>,
+) -> Result<Json<Option<UserFlair>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let flair = match body.flair_id {
+        Some(flair_id) => Some(
+            flair_repo::get_user_flair(&pool, &sub_name, flair_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?
+                .ok_or_else(|| unknown_flair_error(&sub_name))?,
+        ),
+        None => None,
+    };
+
+    flair_repo::choose_user_flair(&pool, caller.user_id, &sub_name, body.flair_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(flair))
+}
+
+/// Flair text goes through the sub's word filters like posts do. Masked words are
+/// starred out; there is no review queue for flairs, so held text is refused like
+/// blocked text.
+async fn filter_flair_text(
+    pool: &PgPool,
+    sub_name: &str,
+    text: &str,
+) -> Result<String, actix_web::Error> {
+    let filters = load_filters(pool, Some(sub_name)).await?;
+    let outcome = apply_filters(&filters, text, None);
+    match outcome.action {
+        Some(FilterAction::Block | FilterAction::Queue) => Err(content_blocked_error().into()),
+        Some(FilterAction::Mask) | None => Ok(outcome.text),
+    }
+}
+
+pub fn unknown_flair_error(sub: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "unknown_flair",
+        format!("{} has no flair with that id", sub),
+    )
+    .with_arg("sub", sub.to_string())
+}
+
+fn invalid_flair_text_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_flair_text",
+        format!(
+            "Flair text must be between 1 and {} characters",
+            MAX_FLAIR_TEXT_CHARS
+        ),
+    )
+    .with_arg("max", MAX_FLAIR_TEXT_CHARS.to_string())
+}
+
+fn invalid_flair_color_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_flair_color",
+        "A flair color must be a hex color such as #ff8800",
+    )
+}
+
+fn flair_write_error(e: sqlx::Error) -> actix_web::Error {
+    match e.as_database_error() {
+        Some(db_error) if db_error.is_unique_violation() => ApiError::new(
+            StatusCode::CONFLICT,
+            "flair_exists",
+            "This sub already has a flair with that text",
+        )
+        .into(),
+        _ => actix_web::error::ErrorInternalServerError(e),
+    }
+}
@@ -0,0 +1,176 @@
+use crate::auth::{Admin, RequireRole};
+use crate::model::legal::{
+    CounterNotice, NewTakedown, NewTakedownDocument, TakedownCase, TakedownCaseResponse,
+    TakedownListQuery, TakedownStatus,
+};
+use crate::model::moderation::ModAction;
+use crate::repo::{legal as legal_repo, moderation as moderation_repo};
+use actix_web::{get, post, web::Data, web::Json, web::Path, web::Query, HttpResponse};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[post("/legal/takedowns")]
+pub async fn submit_takedown(
+    pool: Data<PgPool>,
+    body: Json<NewTakedown>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if body.post_ids.is_empty() && body.comment_ids.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("A takedown must reference at least one item"));
+    }
+
+    let case_id = legal_repo::create_takedown(&pool, &body)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(case_id.to_string()))
+}
+
+#[get("/admin/legal/takedowns")]
+pub async fn get_takedowns(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    query: Query<TakedownListQuery>,
+) -> Result<Json<Vec<TakedownCase>>, actix_web::Error> {
+    let cases = legal_repo::get_takedown_cases(&pool, query.status)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(cases))
+}
+
+#[get("/admin/legal/takedowns/{case_id}")]
+pub async fn get_takedown(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    path: Path<Uuid>,
+) -> Result<Json<TakedownCaseResponse>, actix_web::Error> {
+    let case_id = path.into_inner();
+
+    let case = legal_repo::get_takedown_case(&pool, case_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let items = legal_repo::get_takedown_items(&pool, case_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let documents = legal_repo::get_takedown_documents(&pool, case_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(TakedownCaseResponse {
+        case,
+        items,
+        documents,
+    }))
+}
+
+#[post("/admin/legal/takedowns/{case_id}/documents")]
+pub async fn add_takedown_document(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    path: Path<Uuid>,
+    body: Json<NewTakedownDocument>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let case_id = path.into_inner();
+
+    let document_id =
+        legal_repo::add_takedown_document(&pool, case_id, body.kind, &body.title, &body.body)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(document_id.to_string()))
+}
+
+#[post("/admin/legal/takedowns/{case_id}/action")]
+pub async fn action_takedown(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let case_id = path.into_inner();
+    require_status(&pool, case_id, TakedownStatus::Received).await?;
+
+    legal_repo::apply_takedown(&pool, case_id, admin.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    log_takedown(&pool, admin.user_id, ModAction::LegalTakedown, case_id).await?;
+
+    Ok(HttpResponse::Ok().body(format!("Takedown {} has been actioned", case_id)))
+}
+
+#[post("/admin/legal/takedowns/{case_id}/reject")]
+pub async fn reject_takedown(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let case_id = path.into_inner();
+    require_status(&pool, case_id, TakedownStatus::Received).await?;
+
+    legal_repo::reject_takedown(&pool, case_id, admin.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    log_takedown(&pool, admin.user_id, ModAction::LegalRejection, case_id).await?;
+
+    Ok(HttpResponse::Ok().body(format!("Takedown {} has been rejected", case_id)))
+}
+
+#[post("/admin/legal/takedowns/{case_id}/counter-notice")]
+pub async fn counter_takedown(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<Uuid>,
+    body: Json<CounterNotice>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let case_id = path.into_inner();
+    require_status(&pool, case_id, TakedownStatus::Actioned).await?;
+
+    legal_repo::restore_takedown(&pool, case_id, admin.user_id, &body.title, &body.body)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    log_takedown(&pool, admin.user_id, ModAction::LegalRestoration, case_id).await?;
+
+    Ok(HttpResponse::Ok().body(format!(
+        "Content for takedown {} has been restored",
+        case_id
+    )))
+}
+
+async fn require_status(
+    pool: &PgPool,
+    case_id: Uuid,
+    expected: TakedownStatus,
+) -> Result<(), actix_web::Error> {
+    let case = legal_repo::get_takedown_case(pool, case_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    if case.status != expected {
+        return Err(actix_web::error::ErrorConflict(format!(
+            "Takedown {} is {}, expected {}",
+            case_id, case.status, expected
+        )));
+    }
+
+    Ok(())
+}
+
+async fn log_takedown(
+    pool: &PgPool,
+    admin_id: i32,
+    action: ModAction,
+    case_id: Uuid,
+) -> Result<(), actix_web::Error> {
+    moderation_repo::log_mod_action(
+        pool,
+        admin_id,
+        action,
+        None,
+        None,
+        json!({ "case_id": case_id }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(())
+}
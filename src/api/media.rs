@@ -0,0 +1,187 @@
+use crate::api::user::require_verified_email;
+use crate::auth::AuthenticatedUser;
+use crate::config::{EmailConfig, PremiumConfig};
+use crate::error::ApiError;
+use crate::media::multipart::file_part;
+use crate::media::rendition::generate_renditions;
+use crate::media::sanitize::sanitize;
+use crate::media::scan::MediaScanner;
+use crate::media::MediaStore;
+use crate::model::media::{image_extension, sniff_image_type, Media, MAX_ATTACHMENTS};
+use crate::model::premium::Perks;
+use crate::repo::{media as media_repo, user::UserRepository};
+use actix_web::http::header::ContentType;
+use actix_web::web::{self, Bytes, Data, Header, Json, Payload};
+use actix_web::{http::StatusCode, post};
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Room for the multipart boundaries and headers around the file itself.
+const MULTIPART_OVERHEAD_BYTES: u64 = 16 * 1024;
+
+/// Takes an image from the `file` field of a `multipart/form-data` body. The file's
+/// contents decide its type; a declared type that disagrees is rejected. Metadata is
+/// stripped and the file scanned before it is stored.
+#[post("/media")]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_media(
+    pool: Data<PgPool>,
+    store: Data<dyn MediaStore>,
+    scanner: Data<MediaScanner>,
+    premium_config: Data<PremiumConfig>,
+    email_config: Data<EmailConfig>,
+    uploader: AuthenticatedUser,
+    content_type: Header<ContentType>,
+    payload: Payload,
+) -> Result<Json<Media>, actix_web::Error> {
+    require_verified_email(pool.get_ref(), &email_config, uploader.user_id).await?;
+    let user = pool
+        .get_user_by_id(uploader.user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let max_bytes = Perks::for_member(user.is_premium, &premium_config).max_upload_bytes;
+
+    let body = payload
+        .to_bytes_limited((max_bytes + MULTIPART_OVERHEAD_BYTES) as usize)
+        .await
+        .map_err(|_| upload_too_large_error(max_bytes))??;
+    let file = file_part(&content_type.0 .0, &body, "file").ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_upload",
+            "Uploads must be multipart/form-data with the file in a field named file",
+        )
+    })?;
+    if file.bytes.len() as u64 > max_bytes {
+        return Err(upload_too_large_error(max_bytes).into());
+    }
+    let media_type = sniff_image_type(&file.bytes)
+        .filter(|sniffed| {
+            file.content_type
+                .as_deref()
+                .is_none_or(|declared| declared == *sniffed)
+        })
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                "Only PNG, JPEG, GIF and WebP images can be uploaded",
+            )
+        })?;
+
+    let sanitized = web::block(move || sanitize(media_type, &file.bytes))
+        .await?
+        .map(Bytes::from)
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_image",
+                "The image could not be read",
+            )
+        })?;
+    let clean = scanner
+        .is_clean(media_type, sanitized.clone())
+        .await
+        .map_err(|e| {
+            log::error!(
+                "Could not scan an upload by user {}: {}",
+                uploader.user_id,
+                e
+            );
+            ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "media_scan_failed",
+                "Uploads can't be checked right now, try again later",
+            )
+        })?;
+    if !clean {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "media_rejected",
+            "This file was rejected by the malware scanner",
+        )
+        .into());
+    }
+
+    let id = Uuid::new_v4();
+    let storage_key = format!("{}.{}", id, image_extension(media_type).unwrap_or("bin"));
+    let media = Media {
+        id,
+        user_id: uploader.user_id,
+        url: store.url(&storage_key),
+        storage_key,
+        content_type: media_type.to_string(),
+        size_bytes: sanitized.len() as i64,
+        created_at: Utc::now(),
+        renditions: Vec::new(),
+    };
+    store
+        .put(&media.storage_key, media_type, sanitized.clone())
+        .await
+        .map_err(|e| {
+            log::error!("Could not store upload {}: {}", media.id, e);
+            actix_web::error::ErrorInternalServerError("Could not store the upload")
+        })?;
+    media_repo::create_media(&pool, &media)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    actix_web::rt::spawn(generate_renditions(
+        pool.get_ref().clone(),
+        store.clone(),
+        media.clone(),
+        sanitized,
+    ));
+
+    Ok(Json(media))
+}
+
+fn upload_too_large_error(max_bytes: u64) -> ApiError {
+    ApiError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "upload_too_large",
+        format!("Uploads can be at most {} bytes", max_bytes),
+    )
+    .with_arg("max", max_bytes.to_string())
+}
+
+/// The media to attach to a new post or comment, duplicates dropped. All of it must be
+/// the author's own uploads.
+pub async fn attachable_media(
+    pool: &PgPool,
+    author_id: i32,
+    media_ids: &[Uuid],
+) -> Result<Vec<Uuid>, actix_web::Error> {
+    let mut unique = Vec::with_capacity(media_ids.len());
+    for id in media_ids {
+        if !unique.contains(id) {
+            unique.push(*id);
+        }
+    }
+    if unique.len() > MAX_ATTACHMENTS {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "too_many_attachments",
+            format!("At most {} files can be attached", MAX_ATTACHMENTS),
+        )
+        .with_arg("max", MAX_ATTACHMENTS.to_string())
+        .into());
+    }
+    if unique.is_empty() {
+        return Ok(unique);
+    }
+
+    let owned = media_repo::count_owned_media(pool, author_id, &unique)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if owned != unique.len() as i64 {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "unknown_media",
+            "Only your own uploads can be attached",
+        )
+        .into());
+    }
+
+    Ok(unique)
+}
@@ -1,4 +1,33 @@
+pub mod account_requirement;
+pub mod api_key;
+pub mod appeal;
+pub mod audit;
+pub mod auth;
+pub mod automod;
+pub mod ban;
 pub mod comment;
+pub mod config;
+pub mod experiment;
+pub mod feed;
+pub mod filter;
+pub mod flair;
+pub mod legal;
+pub mod media;
+pub mod mod_note;
+pub mod moderation;
+pub mod modqueue;
+pub mod oauth;
 pub mod post;
+pub mod post_template;
+pub mod premium;
+pub mod removal_reason;
+pub mod render;
+pub mod report;
+pub mod rule;
+pub mod saved;
+pub mod search;
+pub mod session;
 pub mod sub;
+pub mod suspension;
 pub mod user;
+pub mod wiki;
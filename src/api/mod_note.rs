@@ -0,0 +1,146 @@
+use crate::auth::{ManageUsers, RequireSubModerator};
+use crate::error::ApiError;
+use crate::model::mod_note::{ModNote, NewModNote, WithModNotes, MAX_MOD_NOTE_CHARS};
+use crate::model::moderation::ModAction;
+use crate::repo::{
+    mod_note as mod_note_repo, moderation as moderation_repo, sub::SubRepository,
+    user::UserRepository,
+};
+use actix_web::{
+    delete, get, http::StatusCode, post, web::Data, web::Json, web::Path, HttpResponse,
+};
+use serde_json::json;
+use sqlx::PgPool;
+
+/// Adds the sub's notes on each item's user to a moderation view. `user_id` picks the
+/// user an item concerns.
+pub async fn with_mod_notes<T>(
+    subs: &dyn SubRepository,
+    sub: &str,
+    items: Vec<T>,
+    user_id: impl Fn(&T) -> i32,
+) -> Result<Vec<WithModNotes<T>>, actix_web::Error> {
+    let mut user_ids: Vec<i32> = items.iter().map(&user_id).collect();
+    user_ids.sort_unstable();
+    user_ids.dedup();
+    let notes = if user_ids.is_empty() {
+        Vec::new()
+    } else {
+        subs.get_mod_notes(sub, &user_ids)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+    };
+
+    Ok(items
+        .into_iter()
+        .map(|item| {
+            let user_id = user_id(&item);
+            WithModNotes {
+                mod_notes: notes
+                    .iter()
+                    .filter(|note| note.user_id == user_id)
+                    .cloned()
+                    .collect(),
+                item,
+            }
+        })
+        .collect())
+}
+
+/// Notes are private to the sub's moderators. They are shown next to the user in the
+/// mod queue, the ban list and appeals.
+#[post("/subs/{sub}/users/{user_id}/notes")]
+pub async fn create_mod_note(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    moderator: RequireSubModerator<ManageUsers>,
+    path: Path<(String, i32)>,
+    body: Json<NewModNote>,
+) -> Result<Json<ModNote>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let note = body.note.trim();
+    if note.is_empty() || note.chars().count() > MAX_MOD_NOTE_CHARS {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_mod_note",
+            format!(
+                "Mod notes must be between 1 and {} characters",
+                MAX_MOD_NOTE_CHARS
+            ),
+        )
+        .with_arg("max", MAX_MOD_NOTE_CHARS.to_string())
+        .into());
+    }
+
+    let note = mod_note_repo::create_mod_note(&pool, &sub_name, user_id, moderator.user_id, note)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::AddModNote,
+        Some(user_id),
+        Some(&sub_name),
+        json!({ "note_id": note.id }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(note))
+}
+
+/// The sub's notes on the user, most recent first.
+#[get("/subs/{sub}/users/{user_id}/notes")]
+pub async fn get_mod_notes(
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManageUsers>,
+    path: Path<(String, i32)>,
+) -> Result<Json<Vec<ModNote>>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    let notes = subs
+        .get_mod_notes(&sub_name, &[user_id])
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(notes))
+}
+
+#[delete("/subs/{sub}/users/{user_id}/notes/{note_id}")]
+pub async fn delete_mod_note(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManageUsers>,
+    path: Path<(String, i32, i64)>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (sub_name, user_id, note_id) = path.into_inner();
+
+    let deleted = mod_note_repo::delete_mod_note(&pool, &sub_name, user_id, note_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !deleted {
+        return Err(actix_web::error::ErrorNotFound("Note not found"));
+    }
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::DeleteModNote,
+        Some(user_id),
+        Some(&sub_name),
+        json!({ "note_id": note_id }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!("Note {} was deleted", note_id)))
+}
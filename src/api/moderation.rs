@@ -0,0 +1,152 @@
+use crate::auth::role::{moderation_scope, require_role, require_sub_moderator};
+use crate::auth::{Admin, AuthenticatedUser, RequireRole};
+use crate::error::ApiError;
+use crate::model::moderation::{ModAction, ModLogEntry, ModLogQuery, NukeRequest, NukeSummary};
+use crate::model::post::Post;
+use crate::model::sub::ModPermission;
+use crate::model::user::Role;
+use crate::repo::{moderation as moderation_repo, post as post_repo};
+use actix_web::{
+    delete, get, http::StatusCode, post, web::Data, web::Json, web::Path, web::Query, HttpResponse,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Moderators can nuke a user's content in their sub; only admins can nuke it site-wide.
+#[post("/admin/users/{user_id}/nuke")]
+pub async fn nuke_user_content(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<i32>,
+    body: Json<NukeRequest>,
+) -> Result<Json<NukeSummary>, actix_web::Error> {
+    let user_id = path.into_inner();
+    let moderator = match &body.sub {
+        Some(sub) => {
+            require_sub_moderator(
+                pool.get_ref(),
+                pool.get_ref(),
+                caller.user_id,
+                sub,
+                ModPermission::Users,
+            )
+            .await?
+        }
+        None => require_role(pool.get_ref(), caller.user_id, Role::Admin).await?,
+    };
+
+    let summary = moderation_repo::nuke_user_content(&pool, user_id, moderator.id, &body)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(summary))
+}
+
+/// Moderators see the actions taken in the subs they moderate, admins every action.
+#[get("/admin/modlog")]
+pub async fn get_mod_log(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    query: Query<ModLogQuery>,
+) -> Result<Json<Vec<ModLogEntry>>, actix_web::Error> {
+    let moderator_scope = moderation_scope(&**pool, caller.user_id).await?;
+    let entries = moderation_repo::get_mod_log(&pool, &query, moderator_scope)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(entries))
+}
+
+/// Deletes a post and its whole thread for good. Authors' own deletions leave a
+/// tombstone; this is for content that mustn't stay even as one.
+#[delete("/admin/posts/{post_id}")]
+pub async fn purge_post(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let post_id = path.into_inner();
+
+    let (author_id, sub) = moderation_repo::purge_post(&pool, post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Post not found"))?;
+    moderation_repo::log_mod_action(
+        &pool,
+        admin.user_id,
+        ModAction::PurgePost,
+        Some(author_id),
+        Some(&sub),
+        json!({ "post_id": post_id }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!("{} was purged", post_id)))
+}
+
+/// Deletes a comment and every reply below it for good, as for posts.
+#[delete("/admin/comments/{comment_id}")]
+pub async fn purge_comment(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let comment_id = path.into_inner();
+
+    let (author_id, sub) = moderation_repo::purge_comment(&pool, comment_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Comment not found"))?;
+    moderation_repo::log_mod_action(
+        &pool,
+        admin.user_id,
+        ModAction::PurgeComment,
+        Some(author_id),
+        Some(&sub),
+        json!({ "comment_id": comment_id }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!("{} was purged", comment_id)))
+}
+
+/// Lets an archived post take votes and comments again. The archiving job won't
+/// archive it a second time.
+#[delete("/admin/posts/{post_id}/archive")]
+pub async fn unarchive_post(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<Uuid>,
+) -> Result<Json<Post>, actix_web::Error> {
+    let post_id = path.into_inner();
+
+    let unarchived = post_repo::unarchive_post(&pool, post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let post = post_repo::get_post(&pool, post_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if !unarchived {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "not_archived",
+            "This post is not archived",
+        )
+        .into());
+    }
+    moderation_repo::log_mod_action(
+        &pool,
+        admin.user_id,
+        ModAction::UnarchivePost,
+        Some(post.user_id),
+        Some(&post.sub),
+        json!({ "post_id": post_id }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(post))
+}
@@ -0,0 +1,200 @@
+use crate::api::mod_note::with_mod_notes;
+use crate::api::removal_reason::{invalid_removal_message_error, removal_reason_not_found};
+use crate::auth::role::require_sub_moderator;
+use crate::auth::{AuthenticatedUser, ManagePosts, RequireSubModerator};
+use crate::error::ApiError;
+use crate::model::mod_note::WithModNotes;
+use crate::model::moderation::ModAction;
+use crate::model::modqueue::{ModQueueItem, ModQueueKind, ModQueueResolution, ModQueueState};
+use crate::model::removal_reason::{removal_message, Removal, MAX_REMOVAL_MESSAGE_CHARS};
+use crate::model::report::ReportTarget;
+use crate::model::sub::ModPermission;
+use crate::repo::{
+    comment as comment_repo, moderation as moderation_repo, modqueue as modqueue_repo,
+    post as post_repo, removal_reason as removal_reason_repo, sub::SubRepository,
+};
+use actix_web::{get, http::StatusCode, post, web::Data, web::Json, web::Path};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Reported posts and comments and those held by word filters, most reported first,
+/// with the sub's notes on their authors.
+#[get("/subs/{sub}/modqueue")]
+pub async fn get_mod_queue(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManagePosts>,
+    path: Path<String>,
+) -> Result<Json<Vec<WithModNotes<ModQueueItem>>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    let queue = modqueue_repo::get_mod_queue(&pool, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let queue = with_mod_notes(subs.get_ref(), &sub_name, queue, |item| item.author_id).await?;
+
+    Ok(Json(queue))
+}
+
+/// Keeps the post or comment with the given id, publishing it if it was held or removed,
+/// and resolves its reports.
+#[post("/modqueue/{id}/approve")]
+pub async fn approve_queue_item(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<ModQueueResolution>, actix_web::Error> {
+    let item = queue_item(&pool, path.into_inner()).await?;
+    let moderator = require_sub_moderator(
+        pool.get_ref(),
+        pool.get_ref(),
+        caller.user_id,
+        &item.sub,
+        ModPermission::Posts,
+    )
+    .await?;
+
+    let reports_resolved = modqueue_repo::approve_queue_item(&pool, item.target, moderator.id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "not_approvable",
+                "Content taken down for legal reasons or deleted by its author can't be approved",
+            )
+        })?;
+    let action = match item.target {
+        ReportTarget::Post(_) => ModAction::ApprovePost,
+        ReportTarget::Comment(_) => ModAction::ApproveComment,
+    };
+    log_decision(&pool, moderator.id, action, &item).await?;
+
+    Ok(Json(
+        item.resolution(ModQueueState::Approved, reports_resolved),
+    ))
+}
+
+/// Removes the post or comment with the given id and resolves its reports. The body may
+/// pick one of the sub's removal reasons and add a note; the author is shown both.
+#[post("/modqueue/{id}/remove")]
+pub async fn remove_queue_item(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Option<Json<Removal>>,
+) -> Result<Json<ModQueueResolution>, actix_web::Error> {
+    let item = queue_item(&pool, path.into_inner()).await?;
+    let moderator = require_sub_moderator(
+        pool.get_ref(),
+        pool.get_ref(),
+        caller.user_id,
+        &item.sub,
+        ModPermission::Posts,
+    )
+    .await?;
+    let removal = body.map(Json::into_inner).unwrap_or_default();
+    let reason = match removal.reason_id {
+        Some(reason_id) => Some(
+            removal_reason_repo::get_removal_reason(&pool, &item.sub, reason_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?
+                .ok_or_else(removal_reason_not_found)?,
+        ),
+        None => None,
+    };
+    let message = removal_message(reason.as_ref(), &removal.note);
+    if message
+        .as_ref()
+        .is_some_and(|message| message.chars().count() > MAX_REMOVAL_MESSAGE_CHARS)
+    {
+        return Err(invalid_removal_message_error().into());
+    }
+
+    let reports_resolved =
+        modqueue_repo::remove_queue_item(&pool, item.target, moderator.id, message.as_deref())
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    let action = match item.target {
+        ReportTarget::Post(_) => ModAction::RemovePost,
+        ReportTarget::Comment(_) => ModAction::RemoveComment,
+    };
+    log_decision(&pool, moderator.id, action, &item).await?;
+
+    Ok(Json(
+        item.resolution(ModQueueState::Removed, reports_resolved),
+    ))
+}
+
+/// The post or comment a moderator is deciding on.
+struct QueueItem {
+    target: ReportTarget,
+    sub: String,
+    author_id: i32,
+}
+
+impl QueueItem {
+    fn resolution(&self, state: ModQueueState, reports_resolved: u64) -> ModQueueResolution {
+        let (kind, id) = match self.target {
+            ReportTarget::Post(id) => (ModQueueKind::Post, id),
+            ReportTarget::Comment(id) => (ModQueueKind::Comment, id),
+        };
+        ModQueueResolution {
+            kind,
+            id,
+            state,
+            reports_resolved,
+        }
+    }
+}
+
+/// Post and comment ids are both random UUIDs, so one id finds at most one of them.
+async fn queue_item(pool: &PgPool, id: Uuid) -> Result<QueueItem, actix_web::Error> {
+    if let Ok(post) = post_repo::get_post(pool, id).await {
+        return Ok(QueueItem {
+            target: ReportTarget::Post(id),
+            sub: post.sub,
+            author_id: post.user_id,
+        });
+    }
+    let comment = comment_repo::get_comment(pool, id)
+        .await
+        .map_err(|_| actix_web::error::ErrorNotFound("No post or comment with that id"))?;
+    let post = post_repo::get_post(pool, comment.post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(QueueItem {
+        target: ReportTarget::Comment(id),
+        sub: post.sub,
+        author_id: comment.user_id,
+    })
+}
+
+async fn log_decision(
+    pool: &PgPool,
+    moderator_id: i32,
+    action: ModAction,
+    item: &QueueItem,
+) -> Result<(), actix_web::Error> {
+    let details = match item.target {
+        ReportTarget::Post(post_id) => json!({ "post_id": post_id }),
+        ReportTarget::Comment(comment_id) => json!({ "comment_id": comment_id }),
+    };
+    moderation_repo::log_mod_action(
+        pool,
+        moderator_id,
+        action,
+        Some(item.author_id),
+        Some(&item.sub),
+        details,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use crate::api::auth::{device_info, start_session};
+use crate::api::filter::load_filters;
+use crate::auth::{generate_opaque_token, hash_opaque_token, AuthenticatedUser, Viewer};
+use crate::config::{AuthConfig, OAuthClientConfig, OAuthConfig, SessionConfig};
+use crate::error::ApiError;
+use crate::model::filter::apply_filters;
+use crate::model::oauth::{
+    derive_username, GithubUser, GoogleUser, OAuthCallback, OAuthProvider, ProviderIdentity,
+    ProviderToken, UserIdentity,
+};
+use crate::model::user::{DbAddUser, User};
+use crate::repo::oauth as oauth_repo;
+use crate::repo::user::UserRepository;
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::http::header::{HeaderValue, ACCEPT, LOCATION, SET_COOKIE, USER_AGENT};
+use actix_web::http::StatusCode;
+use actix_web::{get, web::Data, web::Json, web::Path, web::Query, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use rand::Rng;
+use sqlx::PgPool;
+use std::fmt::Display;
+
+/// How long a user has to finish signing in at the provider.
+const STATE_TTL_MINUTES: i64 = 10;
+/// Holds the nonce that binds a pending sign-in to the browser that started it.
+const NONCE_COOKIE: &str = "ff_oauth_nonce";
+const PROVIDER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Tries at finding a free username before giving up on a new account.
+const USERNAME_ATTEMPTS: usize = 5;
+
+/// Sends the browser to the provider. A signed-in caller links the provider to their
+/// account; anyone else signs in, or signs up on first use. The browser gets a nonce
+/// cookie the callback checks, so only it can complete the flow.
+#[get("/auth/oauth/{provider}/start")]
+pub async fn start_oauth(
+    pool: Data<PgPool>,
+    config: Data<OAuthConfig>,
+    session_config: Data<SessionConfig>,
+    viewer: Viewer,
+    path: Path<OAuthProvider>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let provider = path.into_inner();
+    let client = provider_client(&config, provider)?;
+    if let Some(user) = &viewer.0 {
+        user.require_login()?;
+    }
+
+    let state = generate_opaque_token();
+    let nonce = generate_opaque_token();
+    oauth_repo::create_state(
+        &pool,
+        &hash_opaque_token(&state),
+        &hash_opaque_token(&nonce),
+        provider,
+        viewer.user_id(),
+        Utc::now() + Duration::minutes(STATE_TTL_MINUTES),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let url = provider.authorize_url(&client.client_id, &config.redirect_uri(provider), &state);
+
+    Ok(HttpResponse::Found()
+        .cookie(nonce_cookie(&session_config, nonce))
+        .insert_header((LOCATION, url.as_str()))
+        .finish())
+}
+
+/// Where the provider sends the browser back. Finds or creates the local account, signs
+/// it in with a session cookie and redirects to `OAUTH_SUCCESS_REDIRECT`. The state must
+/// have been started in this browser, going by its nonce cookie.
+#[get("/auth/oauth/{provider}/callback")]
+pub async fn oauth_callback(
+    pool: Data<PgPool>,
+    config: Data<OAuthConfig>,
+    session_config: Data<SessionConfig>,
+    auth_config: Data<AuthConfig>,
+    req: HttpRequest,
+    path: Path<OAuthProvider>,
+    query: Query<OAuthCallback>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let provider = path.into_inner();
+    let client = provider_client(&config, provider)?;
+
+    let nonce_hash = req
+        .cookie(NONCE_COOKIE)
+        .map(|cookie| hash_opaque_token(cookie.value()));
+    let state = oauth_repo::take_state(&pool, &hash_opaque_token(&query.state))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .filter(|state| state.provider == provider)
+        .filter(|state| nonce_hash.as_deref() == Some(state.nonce_hash.as_str()))
+        .ok_or_else(|| invalid_state_error(&session_config))?;
+    let code = match (&query.code, &query.error) {
+        (Some(code), None) => code,
+        _ => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "oauth_denied",
+                "Signing in with the provider was cancelled",
+            )
+            .into())
+        }
+    };
+
+    let identity = fetch_identity(provider, client, code, &config.redirect_uri(provider)).await?;
+    let linked_user = oauth_repo::get_identity_user(&pool, provider, &identity.subject)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let user_id = match (linked_user, state.link_user_id) {
+        (Some(user_id), Some(link_user_id)) if user_id != link_user_id => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "identity_already_linked",
+                "That account is already linked to a different user",
+            )
+            .into())
+        }
+        (Some(user_id), _) => user_id,
+        (None, Some(link_user_id)) => {
+            oauth_repo::link_identity(&pool, link_user_id, &identity)
+                .await
+                .map_err(|e| match e.as_database_error() {
+                    Some(db_error) if db_error.is_unique_violation() => ApiError::new(
+                        StatusCode::CONFLICT,
+                        "provider_already_linked",
+                        "Your account is already linked to a different account at this provider",
+                    )
+                    .into(),
+                    _ => actix_web::error::ErrorInternalServerError(e),
+                })?;
+            link_user_id
+        }
+        (None, None) => create_oauth_user(pool.get_ref(), &pool, &auth_config, &identity).await?,
+    };
+
+    let cookie = start_session(&pool, &session_config, user_id, &device_info(&req)).await?;
+
+    Ok(HttpResponse::SeeOther()
+        .cookie(cookie)
+        .cookie(nonce_removal_cookie(&session_config))
+        .insert_header((LOCATION, config.success_redirect.as_str()))
+        .finish())
+}
+
+#[get("/users/{user_id}/identities")]
+pub async fn get_identities(
+    pool: Data<PgPool>,
+    user: AuthenticatedUser,
+    path: Path<i32>,
+) -> Result<Json<Vec<UserIdentity>>, actix_web::Error> {
+    let user_id = path.into_inner();
+    user.require_self(user_id)?;
+
+    let identities = oauth_repo::get_identities(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(identities))
+}
+
+/// Scoped to the OAuth routes and `SameSite=Lax`, so it is sent along when the provider
+/// redirects back but not on other cross-site requests.
+fn nonce_cookie(session_config: &SessionConfig, nonce: String) -> Cookie<'static> {
+    Cookie::build(NONCE_COOKIE, nonce)
+        .path("/auth/oauth")
+        .http_only(true)
+        .secure(session_config.secure)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::minutes(STATE_TTL_MINUTES))
+        .finish()
+}
+
+fn nonce_removal_cookie(session_config: &SessionConfig) -> Cookie<'static> {
+    let mut cookie = nonce_cookie(session_config, String::new());
+    cookie.make_removal();
+    cookie
+}
+
+fn invalid_state_error(session_config: &SessionConfig) -> ApiError {
+    let error = ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_oauth_state",
+        "This sign-in link has expired or was already used, please start again",
+    );
+
+    match HeaderValue::from_str(&nonce_removal_cookie(session_config).to_string()) {
+        Ok(cookie) => error.with_headers(vec![(SET_COOKIE, cookie)]),
+        Err(_) => error,
+    }
+}
+
+fn provider_client(
+    config: &OAuthConfig,
+    provider: OAuthProvider,
+) -> Result<&OAuthClientConfig, ApiError> {
+    config.client(provider).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            "oauth_provider_not_configured",
+            format!("Signing in with {} is not available", provider),
+        )
+        .with_arg("provider", provider.to_string())
+    })
+}
+
+/// Exchanges the authorization code for a provider access token and fetches the
+/// account it belongs to.
+async fn fetch_identity(
+    provider: OAuthProvider,
+    client: &OAuthClientConfig,
+    code: &str,
+    redirect_uri: &str,
+) -> Result<ProviderIdentity, actix_web::Error> {
+    let http = awc::Client::builder()
+        .timeout(PROVIDER_TIMEOUT)
+        .add_default_header((USER_AGENT, "ferris-forums"))
+        .finish();
+
+    let token: ProviderToken = http
+        .post(provider.token_endpoint())
+        .insert_header((ACCEPT, "application/json"))
+        .send_form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", client.client_id.as_str()),
+            ("client_secret", client.client_secret.as_str()),
+        ])
+        .await
+        .map_err(provider_error)?
+        .json()
+        .await
+        .map_err(provider_error)?;
+    let access_token = token
+        .access_token
+        .ok_or_else(|| provider_error(token.error.unwrap_or_default()))?;
+
+    let mut response = http
+        .get(provider.userinfo_endpoint())
+        .insert_header((ACCEPT, "application/json"))
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .map_err(provider_error)?;
+    if !response.status().is_success() {
+        return Err(provider_error(response.status()));
+    }
+
+    let identity = match provider {
+        OAuthProvider::Github => response
+            .json::<GithubUser>()
+            .await
+            .map_err(provider_error)?
+            .into(),
+        OAuthProvider::Google => response
+            .json::<GoogleUser>()
+            .await
+            .map_err(provider_error)?
+            .into(),
+    };
+
+    Ok(identity)
+}
+
+/// Signs up a first-time provider user. The username is based on the provider's,
+/// with digits added if it is taken. The account gets a random password, so it can
+/// only be signed into through the provider until the user sets one.
+async fn create_oauth_user(
+    users: &dyn UserRepository,
+    pool: &PgPool,
+    auth_config: &AuthConfig,
+    identity: &ProviderIdentity,
+) -> Result<i32, actix_web::Error> {
+    let filters = load_filters(pool, None).await?;
+    let mut base = derive_username(&identity.preferred_username);
+    if apply_filters(&filters, &base, None).action.is_some() {
+        base = "user".to_string();
+    }
+
+    let mut username = base.clone();
+    for _ in 0..USERNAME_ATTEMPTS {
+        let taken = users
+            .username_exists(&username)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if taken.is_none() {
+            break;
+        }
+        username = format!("{}{}", base, rand::thread_rng().gen_range(1000..10000));
+    }
+
+    let password_hash =
+        User::hash_password(&generate_opaque_token(), &auth_config.password_hashing)
+            .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+    // A verified provider address is taken over as-is, unless another account has it
+    let mut email = None;
+    if let Some(address) = identity
+        .email
+        .as_deref()
+        .filter(|_| identity.email_verified)
+    {
+        let taken = oauth_repo::email_in_use(pool, address)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if !taken {
+            email = Some(address.to_string());
+        }
+    }
+
+    let user = DbAddUser {
+        username,
+        password_hash,
+        created_at: Utc::now(),
+        email_verified_at: email.as_ref().map(|_| Utc::now()),
+        email,
+    };
+
+    oauth_repo::create_user_with_identity(pool, &user, identity)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)
+}
+
+fn provider_error(error: impl Display) -> actix_web::Error {
+    actix_web::error::ErrorBadGateway(format!("The sign-in provider request failed: {}", error))
+}
@@ -1,87 +1,2248 @@
-use crate::model::post::{NewPost, Post, PostResponse};
-use crate::repo::{comment as comment_repo, post as post_repo};
-use actix_web::{delete, get, patch, post, web::Data, web::Json, web::Path, HttpResponse};
+use crate::api::automod::{apply_automod, check_automod, AutomodHit};
+use crate::api::comment::comment_views;
+use crate::api::feed::listing;
+use crate::api::filter::{filter_removal, load_filters};
+use crate::api::media::attachable_media;
+use crate::api::saved::mark_saved_posts;
+use crate::api::sub::{get_postable_sub, get_readable_sub};
+use crate::api::user::{
+    require_nsfw_clearance, require_verified_email, unknown_language_error, viewer_can_view_nsfw,
+    viewer_lists_nsfw,
+};
+use crate::auth::role::require_sub_moderator;
+use crate::auth::{AuthenticatedUser, ManagePosts, RequireSubModerator, Viewer};
+use crate::config::{EmailConfig, PostConfig, SpamConfig};
+use crate::error::ApiError;
+use crate::link_preview::{self, canonical_link_url, normalize_link_url};
+use crate::model::account_requirement::Contribution;
+use crate::model::dto::{PostWithContext, UserPublic};
+use crate::model::filter::{apply_filters, check_link};
+use crate::model::language::{detect_language, normalize_language_tag};
+use crate::model::moderation::{ModAction, RemovalKind};
+use crate::model::poll::{NewPoll, PollView, PollVoteRequest};
+use crate::model::post::{
+    CrosspostRequest, FeedPost, HideResult, ListingQuery, LockRequest, NewDraft, NewPost,
+    PinRequest, Post, PostBatchQuery, PostBatchRequest, PostFlagsRequest, PostPage, PostResponse,
+    PostStatus,
+};
+use crate::model::report::ReportTarget;
+use crate::model::revision::{DiffQuery, RevisionDiff};
+use crate::model::spam::SpamScore;
+use crate::model::sub::{ModPermission, Sub};
+use crate::model::vote::{VoteRequest, VoteResult};
+use crate::repo::{
+    comment::CommentRepository, link_preview as link_preview_repo, media as media_repo,
+    moderation as moderation_repo, post as post_repo, post::PostRepository, sub::SubRepository,
+    user::UserRepository,
+};
+use crate::spam;
+use actix_web::{
+    delete, get, http::StatusCode, patch, post, put, web::Data, web::Json, web::Path, web::Query,
+    HttpResponse,
+};
 use chrono::Utc;
+use serde_json::json;
 use sqlx::PgPool;
+use std::collections::HashMap;
+use url::Url;
 use uuid::Uuid;
 
+/// Most posts one batch request may ask for.
+const MAX_BATCH_POSTS: usize = 100;
+
+/// Word filters are applied to the title and body: masked words are starred out,
+/// held posts are answered with 202 Accepted and blocked ones with 422.
 #[post("/posts/{sub}")]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_post(
+    posts: Data<dyn PostRepository>,
     pool: Data<PgPool>,
+    email_config: Data<EmailConfig>,
+    post_config: Data<PostConfig>,
+    spam_config: Data<SpamConfig>,
+    author: AuthenticatedUser,
     sub: Path<String>,
     body: Json<NewPost>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let new_post = Post {
+    require_verified_email(pool.get_ref(), &email_config, author.user_id).await?;
+    let poll = poll_options(&body)?;
+    let media = attachable_media(&pool, author.user_id, &body.media_ids).await?;
+    let (new_post, checks) = new_post(
+        &pool,
+        &spam_config,
+        author.user_id,
+        sub.into_inner(),
+        &body,
+        PostStatus::Published,
+    )
+    .await?;
+    if !body.repost {
+        check_duplicate_link(&pool, &post_config, &new_post).await?;
+    }
+
+    let response = save_post(posts.get_ref(), &new_post, body.poll.as_ref().zip(poll)).await?;
+    finish_post_checks(&pool, &new_post, &checks).await?;
+    attach_post_media(&pool, new_post.id, &media).await?;
+    spawn_link_preview(&pool, &post_config, &new_post);
+    Ok(response)
+}
+
+/// Saves a post without publishing it, filtered as by `POST /posts/{sub}`. Only the
+/// author sees it until `PATCH /posts/{id}/publish`.
+#[post("/posts/drafts")]
+pub async fn create_draft(
+    posts: Data<dyn PostRepository>,
+    pool: Data<PgPool>,
+    email_config: Data<EmailConfig>,
+    post_config: Data<PostConfig>,
+    spam_config: Data<SpamConfig>,
+    author: AuthenticatedUser,
+    body: Json<NewDraft>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_verified_email(pool.get_ref(), &email_config, author.user_id).await?;
+    let body = body.into_inner();
+    let poll = poll_options(&body.post)?;
+    let media = attachable_media(&pool, author.user_id, &body.post.media_ids).await?;
+    let (draft, checks) = new_post(
+        &pool,
+        &spam_config,
+        author.user_id,
+        body.sub,
+        &body.post,
+        PostStatus::Draft,
+    )
+    .await?;
+
+    let response = save_post(posts.get_ref(), &draft, body.post.poll.as_ref().zip(poll)).await?;
+    finish_post_checks(&pool, &draft, &checks).await?;
+    attach_post_media(&pool, draft.id, &media).await?;
+    spawn_link_preview(&pool, &post_config, &draft);
+    Ok(response)
+}
+
+async fn attach_post_media(
+    pool: &PgPool,
+    post_id: Uuid,
+    media_ids: &[Uuid],
+) -> Result<(), actix_web::Error> {
+    if media_ids.is_empty() {
+        return Ok(());
+    }
+
+    media_repo::attach_post_media(pool, post_id, media_ids)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)
+}
+
+/// What checking a new post left to do once it is saved.
+struct PostChecks {
+    spam: SpamScore,
+    automod: Option<AutomodHit>,
+}
+
+/// Keeps the post's spam score, and files the reports and posts the replies the sub's
+/// AutoModerator rules asked for.
+async fn finish_post_checks(
+    pool: &PgPool,
+    post: &Post,
+    checks: &PostChecks,
+) -> Result<(), actix_web::Error> {
+    let target = ReportTarget::Post(post.id);
+    spam::record_score(pool, target, &checks.spam).await?;
+    let Some(hit) = &checks.automod else {
+        return Ok(());
+    };
+
+    apply_automod(pool, &post.sub, post.id, target, hit).await
+}
+
+/// Starts fetching a link post's preview in the background.
+fn spawn_link_preview(pool: &PgPool, config: &PostConfig, post: &Post) {
+    let Some(url) = post.link_url.as_deref().and_then(normalize_link_url) else {
+        return;
+    };
+
+    actix_web::rt::spawn(link_preview::fetch_link_preview(
+        pool.clone(),
+        post.id,
+        url,
+        config.link_preview_timeout,
+    ));
+}
+
+/// The new post's poll options, checked and trimmed.
+fn poll_options(body: &NewPost) -> Result<Option<Vec<String>>, ApiError> {
+    let Some(poll) = &body.poll else {
+        return Ok(None);
+    };
+
+    poll.normalized_options(Utc::now())
+        .map(Some)
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_poll",
+                "A poll needs 2 to 10 options of 1 to 100 characters and an end time in the future",
+            )
+        })
+}
+
+/// Turns a link post away with `409 duplicate_link`, listing the sub's recent posts
+/// of the same page, unless the author asked to repost it.
+async fn check_duplicate_link(
+    pool: &PgPool,
+    post_config: &PostConfig,
+    post: &Post,
+) -> Result<(), actix_web::Error> {
+    let (Some(window), Some(link)) = (post_config.duplicate_link_window, &post.link_url) else {
+        return Ok(());
+    };
+    let Ok(url) = Url::parse(link) else {
+        return Ok(());
+    };
+
+    let existing = post_repo::get_recent_posts_by_link(
+        pool,
+        &post.sub,
+        &canonical_link_url(&url),
+        Utc::now() - window,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+    if existing.is_empty() {
+        return Ok(());
+    }
+    Err(ApiError::new(
+        StatusCode::CONFLICT,
+        "duplicate_link",
+        format!("This link was already posted in {} recently", post.sub),
+    )
+    .with_arg("sub", post.sub.clone())
+    .with_field("posts", json!(existing))
+    .into())
+}
+
+/// Tags the post's language, applies the sub's word filters, scores it for spam and
+/// checks it against the sub's AutoModerator rules. Only members may post in restricted
+/// and private subs.
+async fn new_post(
+    pool: &PgPool,
+    spam_config: &SpamConfig,
+    author_id: i32,
+    sub: String,
+    body: &NewPost,
+    status: PostStatus,
+) -> Result<(Post, PostChecks), actix_web::Error> {
+    let language = match &body.language {
+        Some(tag) => Some(normalize_language_tag(tag).ok_or_else(|| unknown_language_error(tag))?),
+        None => detect_language(&format!("{}\n{}", body.title, body.content)),
+    };
+
+    let link_url = match &body.url {
+        Some(url) => Some(normalize_link_url(url).ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_link_url",
+                "A link must be a public http or https URL",
+            )
+        })?),
+        None => None,
+    };
+
+    if body.publish_at.is_some_and(|at| at <= Utc::now()) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "publish_at_in_past",
+            "A post can only be scheduled for a future time",
+        )
+        .into());
+    }
+    let (status, publish_at) = match body.publish_at {
+        Some(at) => (PostStatus::Draft, Some(at)),
+        None => (status, None),
+    };
+
+    let spoiler_by_default = get_postable_sub(pool, pool, &sub, author_id, Contribution::Post)
+        .await?
+        .spoiler_by_default;
+    let spoiler = body.spoiler.unwrap_or(spoiler_by_default);
+
+    let filters = load_filters(pool, Some(&sub)).await?;
+    let title = apply_filters(&filters, &body.title, language.as_deref());
+    let content = apply_filters(&filters, &body.content, language.as_deref());
+    let link = link_url
+        .as_ref()
+        .and_then(|url| check_link(&filters, url.as_str()));
+    let removal = filter_removal(title.action.max(content.action).max(link))?;
+    let spam = spam::score_content(
+        pool,
+        author_id,
+        &sub,
+        Some(&title.text),
+        &content.text,
+        link_url.as_ref().map(canonical_link_url).as_deref(),
+    )
+    .await?;
+    let removal = spam::spam_removal(spam_config, &spam, removal);
+    let automod = check_automod(
+        pool,
+        &sub,
+        author_id,
+        Some(&title.text),
+        &content.text,
+        link_url.as_ref().map(Url::as_str),
+    )
+    .await?;
+    let removal = match &automod {
+        Some(hit) if hit.verdict.remove => Some(RemovalKind::Moderator),
+        _ => removal,
+    };
+
+    let post = Post {
         id: Uuid::new_v4(),
-        sub: sub.into_inner(),
-        user_id: body.user_id,
-        title: body.title.clone(),
-        content: body.content.clone(),
+        sub,
+        user_id: author_id,
+        title: title.text,
+        content: content.text,
         timestamp: Utc::now(),
+        removal,
+        nsfw: body.nsfw,
+        spoiler,
+        language,
+        score: 0,
+        status,
+        publish_at,
+        edited_at: None,
+        pin_order: None,
+        locked: false,
+        lock_reason: None,
+        archived: false,
+        flair_id: automod.as_ref().and_then(|hit| hit.verdict.flair_id),
+        crosspost_parent_id: None,
+        link_url: link_url.map(String::from),
+        author_flair: None,
     };
 
-    let post_id = post_repo::create_post(&pool, &new_post)
+    Ok((post, PostChecks { spam, automod }))
+}
+
+async fn save_post(
+    posts: &dyn PostRepository,
+    post: &Post,
+    poll: Option<(&NewPoll, Vec<String>)>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let post_id = posts
+        .create_post(post)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if let Some((poll, options)) = poll {
+        posts
+            .create_poll(post_id, &options, poll.ends_at, poll.hide_results)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+
+    match post.removal {
+        Some(_) => Ok(HttpResponse::Accepted().body(post_id.to_string())),
+        None => Ok(HttpResponse::Ok().body(post_id.to_string())),
+    }
+}
+
+/// Puts a draft into listings and search, dated to when it was published. The author
+/// must still be allowed to post in the sub, as when the draft was saved.
+#[patch("/posts/{id}/publish")]
+pub async fn publish_post(
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    author: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<Post>, actix_web::Error> {
+    let post_id = path.into_inner();
+    let draft = require_post_author(posts.get_ref(), &author, post_id).await?;
+    get_postable_sub(
+        subs.get_ref(),
+        users.get_ref(),
+        &draft.sub,
+        author.user_id,
+        Contribution::Post,
+    )
+    .await?;
+
+    let published = posts
+        .publish_post(post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !published {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "already_published",
+            "This post has already been published",
+        )
+        .into());
+    }
+
+    let post = posts
+        .get_post(post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(post))
+}
+
+/// Users can only list their own drafts.
+#[get("/users/{user_id}/drafts")]
+pub async fn get_user_drafts(
+    posts: Data<dyn PostRepository>,
+    caller: AuthenticatedUser,
+    path: Path<i32>,
+) -> Result<Json<Vec<Post>>, actix_web::Error> {
+    let user_id = path.into_inner();
+    if caller.user_id != user_id {
+        return Err(ApiError::forbidden(
+            "drafts_private",
+            "Drafts are only visible to their author",
+        )
+        .into());
+    }
+
+    let drafts = posts
+        .get_drafts_by_user(user_id)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().body(post_id.to_string()))
+    Ok(Json(drafts))
 }
 
 #[get("/posts/{id}")]
 pub async fn get_post(
-    pool: Data<PgPool>,
+    posts: Data<dyn PostRepository>,
+    comments: Data<dyn CommentRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
     path: Path<Uuid>,
+    viewer: Viewer,
 ) -> Result<Json<PostResponse>, actix_web::Error> {
     let post_id = path.into_inner();
-    let post = post_repo::get_post(&pool, post_id)
+    let post = get_readable_post(posts.get_ref(), post_id, viewer.user_id()).await?;
+    require_visible_post(users.get_ref(), &post, viewer.user_id()).await?;
+    get_readable_sub(subs.get_ref(), users.get_ref(), &post.sub, viewer.user_id()).await?;
+    if post.nsfw {
+        require_nsfw_clearance(users.get_ref(), viewer.user_id()).await?;
+    }
+    let post_comments = comments
+        .get_comments_by_post(post_id, viewer.user_id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let poll = poll_view(posts.get_ref(), post_id, viewer.user_id()).await?;
+    // A removed post's link and attachments go with its content
+    let (link_preview, media) = match post.removal {
+        Some(_) => (None, Vec::new()),
+        None => (
+            posts
+                .get_link_preview(post_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?,
+            posts
+                .get_post_media(post_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?,
+        ),
+    };
+    let crosspost_parent = match post.crosspost_parent_id {
+        Some(parent_id) => {
+            readable_crosspost_parent(
+                posts.get_ref(),
+                subs.get_ref(),
+                users.get_ref(),
+                parent_id,
+                viewer.user_id(),
+            )
+            .await
+        }
+        None => None,
+    };
+    let removal_reason = match viewer.user_id() {
+        Some(viewer_id) if viewer_id == post.user_id => posts
+            .get_post_removal_reason(post_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?,
+        _ => None,
+    };
+
+    Ok(Json(PostResponse {
+        post,
+        comments: comment_views(comments.get_ref(), post_id, viewer.user_id(), post_comments)
+            .await?,
+        poll,
+        link_preview,
+        crosspost_parent,
+        media,
+        removal_reason,
+    }))
+}
+
+/// The original of a crosspost, if the viewer could open it themselves: it hasn't been
+/// removed and they pass the checks `GET /posts/{id}` makes.
+async fn readable_crosspost_parent(
+    posts: &dyn PostRepository,
+    subs: &dyn SubRepository,
+    users: &dyn UserRepository,
+    parent_id: Uuid,
+    viewer_id: Option<i32>,
+) -> Option<Post> {
+    let parent = get_readable_post(posts, parent_id, viewer_id).await.ok()?;
+    if parent.removal.is_some() {
+        return None;
+    }
+    require_visible_post(users, &parent, viewer_id).await.ok()?;
+    get_readable_sub(subs, users, &parent.sub, viewer_id)
+        .await
+        .ok()?;
+    if parent.nsfw {
+        require_nsfw_clearance(users, viewer_id).await.ok()?;
+    }
+
+    Some(parent)
+}
+
+/// Shares a post into another sub as a new post pointing back at it, filtered as by
+/// `POST /posts/{sub}`. Crossposting a crosspost points at the original.
+#[post("/posts/{id}/crosspost")]
+#[allow(clippy::too_many_arguments)]
+pub async fn crosspost_post(
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    pool: Data<PgPool>,
+    email_config: Data<EmailConfig>,
+    spam_config: Data<SpamConfig>,
+    author: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<CrosspostRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_verified_email(pool.get_ref(), &email_config, author.user_id).await?;
+    let original = get_readable_post(posts.get_ref(), path.into_inner(), None).await?;
+    get_readable_sub(
+        pool.get_ref(),
+        users.get_ref(),
+        &original.sub,
+        Some(author.user_id),
+    )
+    .await?;
+    if original.removal.is_some() {
+        return Err(actix_web::error::ErrorNotFound("Post not found"));
+    }
+    if original.nsfw {
+        require_nsfw_clearance(users.get_ref(), Some(author.user_id)).await?;
+    }
+    let body = body.into_inner();
+    if body.sub == original.sub {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "crosspost_same_sub",
+            "A post can only be crossposted to another sub",
+        )
+        .into());
+    }
+    let parent_id = original.crosspost_parent_id.unwrap_or(original.id);
+    let shared = NewPost {
+        title: body.title.unwrap_or(original.title),
+        content: original.content,
+        nsfw: original.nsfw,
+        spoiler: Some(original.spoiler),
+        publish_at: None,
+        repost: true,
+        language: original.language,
+        url: original.link_url,
+        poll: None,
+        media_ids: Vec::new(),
+    };
+    let (post, checks) = new_post(
+        &pool,
+        &spam_config,
+        author.user_id,
+        body.sub,
+        &shared,
+        PostStatus::Published,
+    )
+    .await?;
+    let post = Post {
+        crosspost_parent_id: Some(parent_id),
+        ..post
+    };
+
+    let response = save_post(posts.get_ref(), &post, None).await?;
+    finish_post_checks(&pool, &post, &checks).await?;
+    link_preview_repo::copy_link_preview(&pool, original.id, post.id)
         .await
-        .map_err(|e| actix_web::error::ErrorNotFound(e))?;
-    let comments = comment_repo::get_comments_by_post(&pool, post_id)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let media = media_repo::get_post_media(&pool, original.id)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let media_ids: Vec<Uuid> = media.iter().map(|media| media.id).collect();
+    attach_post_media(&pool, post.id, &media_ids).await?;
 
-    Ok(Json(PostResponse { post, comments }))
+    Ok(response)
+}
+
+/// The post's crossposts, newest first.
+#[get("/posts/{id}/duplicates")]
+pub async fn get_post_duplicates(
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    path: Path<Uuid>,
+    viewer: Viewer,
+) -> Result<Json<Vec<Post>>, actix_web::Error> {
+    let post_id = path.into_inner();
+    let post = get_readable_post(posts.get_ref(), post_id, viewer.user_id()).await?;
+    require_visible_post(users.get_ref(), &post, viewer.user_id()).await?;
+    get_readable_sub(subs.get_ref(), users.get_ref(), &post.sub, viewer.user_id()).await?;
+    if post.nsfw {
+        require_nsfw_clearance(users.get_ref(), viewer.user_id()).await?;
+    }
+    let include_nsfw = viewer_can_view_nsfw(users.get_ref(), viewer.user_id()).await?;
+
+    let crossposts = posts
+        .get_crossposts(post_id, include_nsfw, viewer.user_id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(crossposts))
+}
+
+/// The post's poll as the viewer sees it, if it has one.
+async fn poll_view(
+    posts: &dyn PostRepository,
+    post_id: Uuid,
+    viewer_id: Option<i32>,
+) -> Result<Option<PollView>, actix_web::Error> {
+    let Some(poll) = posts
+        .get_poll(post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+    else {
+        return Ok(None);
+    };
+    let vote = match viewer_id {
+        Some(viewer_id) => posts
+            .get_poll_vote(post_id, viewer_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?,
+        None => None,
+    };
+
+    Ok(Some(poll.view(vote, Utc::now())))
+}
+
+#[get("/posts")]
+pub async fn get_posts_by_ids(
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
+    query: Query<PostBatchQuery>,
+) -> Result<Json<Vec<PostWithContext>>, actix_web::Error> {
+    let post_ids = query
+        .ids
+        .split(',')
+        .filter(|id| !id.trim().is_empty())
+        .map(|id| id.trim().parse())
+        .collect::<Result<Vec<Uuid>, _>>()
+        .map_err(actix_web::error::ErrorBadRequest)?;
+
+    let posts = posts_with_context(
+        posts.get_ref(),
+        subs.get_ref(),
+        users.get_ref(),
+        &post_ids,
+        viewer.user_id(),
+    )
+    .await?;
+
+    Ok(Json(posts))
+}
+
+#[post("/posts/by_ids")]
+pub async fn get_posts_by_ids_body(
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
+    body: Json<PostBatchRequest>,
+) -> Result<Json<Vec<PostWithContext>>, actix_web::Error> {
+    let posts = posts_with_context(
+        posts.get_ref(),
+        subs.get_ref(),
+        users.get_ref(),
+        &body.ids,
+        viewer.user_id(),
+    )
+    .await?;
+
+    Ok(Json(posts))
+}
+
+/// Fetches the posts with their authors and subs in three queries, in the order the
+/// ids were given. Posts that don't exist or that the viewer may not see, including
+/// those in private subs they aren't a member of, are left out.
+async fn posts_with_context(
+    posts: &dyn PostRepository,
+    subs: &dyn SubRepository,
+    users: &dyn UserRepository,
+    post_ids: &[Uuid],
+    viewer_id: Option<i32>,
+) -> Result<Vec<PostWithContext>, actix_web::Error> {
+    if post_ids.len() > MAX_BATCH_POSTS {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "At most {} posts can be fetched at once",
+            MAX_BATCH_POSTS
+        )));
+    }
+
+    let include_nsfw = viewer_can_view_nsfw(users, viewer_id).await?;
+    let found = posts
+        .get_posts_by_ids(post_ids, include_nsfw, viewer_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let mut author_ids: Vec<i32> = found.iter().map(|post| post.user_id).collect();
+    author_ids.sort_unstable();
+    author_ids.dedup();
+    let mut sub_names: Vec<String> = found.iter().map(|post| post.sub.clone()).collect();
+    sub_names.sort_unstable();
+    sub_names.dedup();
+
+    let authors: HashMap<i32, UserPublic> = users
+        .get_users_by_ids(&author_ids)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|user| (user.id, UserPublic::from(user)))
+        .collect();
+    let mut sub_by_name: HashMap<String, Sub> = subs
+        .get_subs_by_names(&sub_names)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|sub| (sub.name.clone(), sub))
+        .collect();
+    let private: Vec<String> = sub_by_name
+        .values()
+        .filter(|sub| !sub.visibility.readable_by_anyone())
+        .map(|sub| sub.name.clone())
+        .collect();
+    for sub_name in private {
+        if get_readable_sub(subs, users, &sub_name, viewer_id)
+            .await
+            .is_err()
+        {
+            sub_by_name.remove(&sub_name);
+        }
+    }
+
+    let mut by_id: HashMap<Uuid, Post> = found.into_iter().map(|post| (post.id, post)).collect();
+    Ok(post_ids
+        .iter()
+        .filter_map(|post_id| by_id.remove(post_id))
+        .filter_map(|post| {
+            let sub = sub_by_name.get(&post.sub)?.clone();
+            let author = authors.get(&post.user_id).cloned();
+            Some(PostWithContext { post, author, sub })
+        })
+        .collect())
 }
 
 #[get("/posts/for_sub/{sub}")]
 pub async fn get_posts_by_sub(
-    pool: Data<PgPool>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
     sub: Path<String>,
-) -> Result<Json<Vec<Post>>, actix_web::Error> {
+    viewer: Viewer,
+    query: Query<ListingQuery>,
+) -> Result<Json<PostPage>, actix_web::Error> {
     let sub_name = sub.into_inner();
+    let listing = listing(&query, viewer.user_id())?;
 
-    let posts = post_repo::get_posts_by_sub(&pool, &sub_name)
+    let sub =
+        get_readable_sub(subs.get_ref(), users.get_ref(), &sub_name, viewer.user_id()).await?;
+    // Everything in an NSFW sub is NSFW, so asking it to hide NSFW posts isn't honoured
+    let include_nsfw = if sub.nsfw {
+        require_nsfw_clearance(users.get_ref(), viewer.user_id()).await?;
+        true
+    } else {
+        viewer_lists_nsfw(users.get_ref(), viewer.user_id(), query.include_nsfw).await?
+    };
+
+    let ranked = posts
+        .get_posts_by_sub(&sub_name, include_nsfw, &listing)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let mut page = PostPage::new(ranked, &listing);
+    // Pinned posts head the first page whatever the sort, and don't count toward its
+    // limit. Listings narrowed to a flair rank them like any other post.
+    if listing.after.is_none() && listing.flair.is_none() {
+        let pinned = posts
+            .get_pinned_posts(&sub_name, include_nsfw, listing.viewer)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        page.posts
+            .splice(0..0, pinned.into_iter().map(FeedPost::from));
+    }
+    mark_saved_posts(posts.get_ref(), viewer.user_id(), &mut page).await?;
 
-    Ok(Json(posts))
+    Ok(Json(page))
 }
 
+/// Pins a post to the top of its sub's listings at `order`, lowest first. A sub holds at
+/// most `MAX_PINNED_POSTS_PER_SUB` pinned posts; pinning a post that's already pinned
+/// just moves it.
+#[patch("/subs/{sub}/posts/{id}/pin")]
+pub async fn pin_post(
+    pool: Data<PgPool>,
+    posts: Data<dyn PostRepository>,
+    config: Data<PostConfig>,
+    moderator: RequireSubModerator<ManagePosts>,
+    path: Path<(String, Uuid)>,
+    body: Option<Json<PinRequest>>,
+) -> Result<Json<Post>, actix_web::Error> {
+    let (sub_name, post_id) = path.into_inner();
+    let request = body.map(Json::into_inner).unwrap_or_default();
+    let post = get_sub_post(posts.get_ref(), &sub_name, post_id).await?;
+    if post.removal.is_some() {
+        return Err(actix_web::error::ErrorNotFound("Post not found"));
+    }
+    if request
+        .order
+        .is_some_and(|order| !(1..=config.max_pins_per_sub).contains(&order))
+    {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_pin_order",
+            format!(
+                "A pin order must be between 1 and {}",
+                config.max_pins_per_sub
+            ),
+        )
+        .with_arg("max", config.max_pins_per_sub.to_string())
+        .into());
+    }
+
+    let order = posts
+        .pin_post(post_id, request.order, config.max_pins_per_sub)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "pin_limit_reached",
+                format!(
+                    "{} already has {} pinned posts",
+                    sub_name, config.max_pins_per_sub
+                ),
+            )
+            .with_arg("sub", sub_name.clone())
+            .with_arg("max", config.max_pins_per_sub.to_string())
+        })?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::PinPost,
+        Some(post.user_id),
+        Some(&sub_name),
+        json!({ "post_id": post_id, "order": order }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(Post {
+        pin_order: Some(order),
+        ..post
+    }))
+}
+
+#[delete("/subs/{sub}/posts/{id}/pin")]
+pub async fn unpin_post(
+    pool: Data<PgPool>,
+    posts: Data<dyn PostRepository>,
+    moderator: RequireSubModerator<ManagePosts>,
+    path: Path<(String, Uuid)>,
+) -> Result<Json<Post>, actix_web::Error> {
+    let (sub_name, post_id) = path.into_inner();
+    let post = get_sub_post(posts.get_ref(), &sub_name, post_id).await?;
+
+    let unpinned = posts
+        .unpin_post(post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if unpinned {
+        moderation_repo::log_mod_action(
+            &pool,
+            moderator.user_id,
+            ModAction::UnpinPost,
+            Some(post.user_id),
+            Some(&sub_name),
+            json!({ "post_id": post_id }),
+        )
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+
+    Ok(Json(Post {
+        pin_order: None,
+        ..post
+    }))
+}
+
+/// Edits go through the same checks as new posts: masked words are starred out, edits
+/// held for review take the post down with 202 Accepted and blocked ones get 422.
 #[patch("/posts/{id}")]
 pub async fn update_post(
+    posts: Data<dyn PostRepository>,
     pool: Data<PgPool>,
+    spam_config: Data<SpamConfig>,
+    author: AuthenticatedUser,
     path: Path<Uuid>,
     update_content: String,
 ) -> Result<HttpResponse, actix_web::Error> {
     let post_id = path.into_inner();
+    let post = require_post_author(posts.get_ref(), &author, post_id).await?;
+
+    let filters = load_filters(&pool, Some(&post.sub)).await?;
+    let content = apply_filters(&filters, &update_content, post.language.as_deref());
+    let link = post
+        .link_url
+        .as_deref()
+        .and_then(|url| check_link(&filters, url));
+    let removal = filter_removal(content.action.max(link))?;
+    let canonical_url = post
+        .link_url
+        .as_deref()
+        .and_then(|link| Url::parse(link).ok())
+        .map(|url| canonical_link_url(&url));
+    let spam = spam::score_content(
+        &pool,
+        author.user_id,
+        &post.sub,
+        Some(&post.title),
+        &content.text,
+        canonical_url.as_deref(),
+    )
+    .await?;
+    let removal = spam::spam_removal(&spam_config, &spam, removal);
+    let automod = check_automod(
+        &pool,
+        &post.sub,
+        author.user_id,
+        Some(&post.title),
+        &content.text,
+        post.link_url.as_deref(),
+    )
+    .await?;
+    let removal = match &automod {
+        Some(hit) if hit.verdict.remove => Some(RemovalKind::Moderator),
+        _ => removal,
+    };
+
+    let post_id = posts
+        .update_post(post_id, content.text.clone(), removal)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    finish_post_checks(&pool, &post, &PostChecks { spam, automod }).await?;
 
-    let post_id = post_repo::update_post(&pool, post_id, update_content.clone())
+    let body = format!("{} -> {}", post_id, content.text);
+    match removal {
+        Some(_) => Ok(HttpResponse::Accepted().body(body)),
+        None => Ok(HttpResponse::Ok().body(body)),
+    }
+}
+
+/// Only the author and the sub's moderators may compare revisions, as with comment
+/// history.
+#[get("/posts/{id}/revisions/{from}/diff/{to}")]
+pub async fn get_post_revision_diff(
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    subs: Data<dyn SubRepository>,
+    caller: AuthenticatedUser,
+    path: Path<(Uuid, i32, i32)>,
+    query: Query<DiffQuery>,
+) -> Result<Json<RevisionDiff>, actix_web::Error> {
+    let (post_id, from, to) = path.into_inner();
+    let post = get_readable_post(posts.get_ref(), post_id, Some(caller.user_id)).await?;
+    require_revision_reader(
+        users.get_ref(),
+        subs.get_ref(),
+        &post,
+        post.user_id,
+        post.removal,
+        caller.user_id,
+    )
+    .await?;
+
+    let from = posts
+        .get_post_revision(post_id, from)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let to = posts
+        .get_post_revision(post_id, to)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorNotFound)?;
 
-    Ok(HttpResponse::Ok().body(format!("{} -> {}", post_id.to_string(), update_content)))
+    Ok(Json(RevisionDiff::between(&from, &to, query.granularity)))
 }
 
 #[delete("/posts/{id}")]
 pub async fn delete_post(
-    pool: Data<PgPool>,
+    posts: Data<dyn PostRepository>,
+    author: AuthenticatedUser,
     path: Path<Uuid>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let post_id = path.into_inner();
+    require_post_author(posts.get_ref(), &author, post_id).await?;
+
+    posts
+        .delete_post(post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!("{} was deleted", post_id)))
+}
+
+/// Casting a vote again replaces the earlier one.
+#[put("/posts/{id}/vote")]
+pub async fn vote_post(
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    voter: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<VoteRequest>,
+) -> Result<Json<VoteResult>, actix_web::Error> {
+    if !body.is_valid() {
+        return Err(invalid_vote_error().into());
+    }
+    let post_id = path.into_inner();
+    require_votable_post(
+        posts.get_ref(),
+        subs.get_ref(),
+        users.get_ref(),
+        voter.user_id,
+        post_id,
+    )
+    .await?;
 
-    let post_id = post_repo::delete_post(&pool, post_id)
+    let score = posts
+        .set_post_vote(post_id, voter.user_id, Some(body.value))
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(VoteResult {
+        score,
+        vote: Some(body.value),
+    }))
+}
+
+/// One vote per user, which can't be changed once cast.
+#[post("/posts/{id}/poll/vote")]
+pub async fn vote_in_poll(
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    voter: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<PollVoteRequest>,
+) -> Result<Json<PollView>, actix_web::Error> {
+    let post_id = path.into_inner();
+    require_votable_post(
+        posts.get_ref(),
+        subs.get_ref(),
+        users.get_ref(),
+        voter.user_id,
+        post_id,
+    )
+    .await?;
+
+    let poll = posts
+        .get_poll(post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Poll not found"))?;
+    if poll.is_closed(Utc::now()) {
+        return Err(ApiError::forbidden("poll_closed", "This poll has closed").into());
+    }
+    if !poll.has_option(body.option_id) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "unknown_poll_option",
+            "This poll has no such option",
+        )
+        .into());
+    }
+
+    let cast = posts
+        .cast_poll_vote(post_id, voter.user_id, body.option_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !cast {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "already_voted_in_poll",
+            "You have already voted in this poll",
+        )
+        .into());
+    }
+
+    let poll = poll_view(posts.get_ref(), post_id, Some(voter.user_id)).await?;
+    Ok(Json(poll.expect("the poll was just voted in")))
+}
+
+#[delete("/posts/{id}/vote")]
+pub async fn delete_post_vote(
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    voter: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<VoteResult>, actix_web::Error> {
+    let post_id = path.into_inner();
+    require_votable_post(
+        posts.get_ref(),
+        subs.get_ref(),
+        users.get_ref(),
+        voter.user_id,
+        post_id,
+    )
+    .await?;
+
+    let score = posts
+        .set_post_vote(post_id, voter.user_id, None)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(VoteResult { score, vote: None }))
+}
+
+/// Keeps the post out of the caller's listings. Hiding a hidden post does nothing.
+#[put("/posts/{id}/hide")]
+pub async fn hide_post(
+    posts: Data<dyn PostRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<HideResult>, actix_web::Error> {
+    let post_id = path.into_inner();
+    get_readable_post(posts.get_ref(), post_id, None).await?;
+
+    posts
+        .hide_post(post_id, caller.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(HideResult { hidden: true }))
+}
+
+#[delete("/posts/{id}/hide")]
+pub async fn unhide_post(
+    posts: Data<dyn PostRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<HideResult>, actix_web::Error> {
+    posts
+        .unhide_post(path.into_inner(), caller.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(HideResult { hidden: false }))
+}
+
+/// The posts the account holder has hidden, most recently hidden first, so they can
+/// unhide them.
+#[get("/users/{user_id}/hidden")]
+pub async fn get_hidden_posts(
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
+    path: Path<i32>,
+) -> Result<Json<Vec<Post>>, actix_web::Error> {
+    let user_id = path.into_inner();
+    caller.require_self(user_id)?;
+    let include_nsfw = viewer_can_view_nsfw(users.get_ref(), Some(user_id)).await?;
+
+    let hidden = posts
+        .get_hidden_posts(user_id, include_nsfw)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(hidden))
+}
+
+/// Only published posts the voter could read can be voted on.
+async fn require_votable_post(
+    posts: &dyn PostRepository,
+    subs: &dyn SubRepository,
+    users: &dyn UserRepository,
+    voter_id: i32,
+    post_id: Uuid,
+) -> Result<(), actix_web::Error> {
+    let post = get_readable_post(posts, post_id, None).await?;
+    get_readable_sub(subs, users, &post.sub, Some(voter_id)).await?;
+    if post.archived {
+        return Err(post_archived_error().into());
+    }
+    if post.nsfw {
+        require_nsfw_clearance(users, Some(voter_id)).await?;
+    }
+
+    Ok(())
+}
+
+/// The post, unless it's a draft and `viewer_id` isn't its author, in which case it
+/// doesn't exist as far as the viewer can tell. Pass `None` to accept only published
+/// posts.
+pub async fn get_readable_post(
+    posts: &dyn PostRepository,
+    post_id: Uuid,
+    viewer_id: Option<i32>,
+) -> Result<Post, actix_web::Error> {
+    let post = posts
+        .get_post(post_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if post.status == PostStatus::Draft && viewer_id != Some(post.user_id) {
+        return Err(actix_web::error::ErrorNotFound("Post not found"));
+    }
+
+    Ok(post)
+}
+
+/// Posts by shadowbanned authors are only found by the author, the sub's moderators and
+/// admins.
+pub async fn require_visible_post(
+    users: &dyn UserRepository,
+    post: &Post,
+    viewer_id: Option<i32>,
+) -> Result<(), actix_web::Error> {
+    let visible = users
+        .author_visible(post.user_id, &post.sub, viewer_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !visible {
+        return Err(actix_web::error::ErrorNotFound("Post not found"));
+    }
+
+    Ok(())
+}
+
+/// Earlier revisions keep what an edit or a removal took out, so only the author of the
+/// post or of a comment on it (`author_id`) and the sub's moderators may read them, and
+/// only while they can read the post. Nobody reads the revisions of content removed for
+/// legal reasons.
+pub async fn require_revision_reader(
+    users: &dyn UserRepository,
+    subs: &dyn SubRepository,
+    post: &Post,
+    author_id: i32,
+    removal: Option<RemovalKind>,
+    caller_id: i32,
+) -> Result<(), actix_web::Error> {
+    require_visible_post(users, post, Some(caller_id)).await?;
+    get_readable_sub(subs, users, &post.sub, Some(caller_id)).await?;
+    if removal == Some(RemovalKind::Legal) {
+        return Err(actix_web::error::ErrorNotFound("Revision not found"));
+    }
+    if author_id != caller_id {
+        require_sub_moderator(users, subs, caller_id, &post.sub, ModPermission::Users).await?;
+    }
+
+    Ok(())
+}
+
+/// Locks the thread so it takes no new comments. Locking a locked thread replaces its
+/// reason.
+#[patch("/posts/{id}/lock")]
+pub async fn lock_post(
+    pool: Data<PgPool>,
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    subs: Data<dyn SubRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Option<Json<LockRequest>>,
+) -> Result<Json<Post>, actix_web::Error> {
+    let post_id = path.into_inner();
+    let reason = body.map(Json::into_inner).unwrap_or_default().reason;
+    let post = get_readable_post(posts.get_ref(), post_id, None).await?;
+    let moderator = require_sub_moderator(
+        users.get_ref(),
+        subs.get_ref(),
+        caller.user_id,
+        &post.sub,
+        ModPermission::Posts,
+    )
+    .await?;
+
+    posts
+        .lock_post(post_id, reason.as_deref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.id,
+        ModAction::LockPost,
+        Some(post.user_id),
+        Some(&post.sub),
+        json!({ "post_id": post_id, "reason": reason }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(Post {
+        locked: true,
+        lock_reason: reason,
+        ..post
+    }))
+}
+
+#[delete("/posts/{id}/lock")]
+pub async fn unlock_post(
+    pool: Data<PgPool>,
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    subs: Data<dyn SubRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<Post>, actix_web::Error> {
+    let post_id = path.into_inner();
+    let post = get_readable_post(posts.get_ref(), post_id, None).await?;
+    let moderator = require_sub_moderator(
+        users.get_ref(),
+        subs.get_ref(),
+        caller.user_id,
+        &post.sub,
+        ModPermission::Posts,
+    )
+    .await?;
+
+    let unlocked = posts
+        .unlock_post(post_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if unlocked {
+        moderation_repo::log_mod_action(
+            &pool,
+            moderator.id,
+            ModAction::UnlockPost,
+            Some(post.user_id),
+            Some(&post.sub),
+            json!({ "post_id": post_id }),
+        )
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+
+    Ok(Json(Post {
+        locked: false,
+        lock_reason: None,
+        ..post
+    }))
+}
+
+/// Authors mark their own posts NSFW or as spoilers; the sub's moderators can mark any
+/// post in it.
+#[patch("/posts/{id}/flags")]
+pub async fn set_post_flags(
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    subs: Data<dyn SubRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<PostFlagsRequest>,
+) -> Result<Json<Post>, actix_web::Error> {
+    let post_id = path.into_inner();
+    let post = get_readable_post(posts.get_ref(), post_id, Some(caller.user_id)).await?;
+    if post.user_id != caller.user_id {
+        require_sub_moderator(
+            users.get_ref(),
+            subs.get_ref(),
+            caller.user_id,
+            &post.sub,
+            ModPermission::Posts,
+        )
+        .await
+        .map_err(|_| not_author_error())?;
+    }
+    let nsfw = body.nsfw.unwrap_or(post.nsfw);
+    let spoiler = body.spoiler.unwrap_or(post.spoiler);
+
+    posts
+        .set_post_flags(post_id, nsfw, spoiler)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(Post {
+        nsfw,
+        spoiler,
+        ..post
+    }))
+}
+
+/// The published post, if it belongs to the sub in the path.
+async fn get_sub_post(
+    posts: &dyn PostRepository,
+    sub_name: &str,
+    post_id: Uuid,
+) -> Result<Post, actix_web::Error> {
+    let post = get_readable_post(posts, post_id, None).await?;
+    if post.sub != sub_name {
+        return Err(actix_web::error::ErrorNotFound("Post not found"));
+    }
+
+    Ok(post)
+}
+
+pub fn post_archived_error() -> ApiError {
+    ApiError::forbidden(
+        "post_archived",
+        "This post is archived and takes no more votes or comments",
+    )
+}
+
+pub fn thread_locked_error() -> ApiError {
+    ApiError::forbidden(
+        "thread_locked",
+        "This thread is locked and takes no new comments",
+    )
+}
+
+pub fn invalid_vote_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_vote",
+        "A vote must be 1 or -1",
+    )
+}
+
+async fn require_post_author(
+    posts: &dyn PostRepository,
+    author: &AuthenticatedUser,
+    post_id: Uuid,
+) -> Result<Post, actix_web::Error> {
+    let post = posts
+        .get_post(post_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    if post.user_id != author.user_id {
+        return Err(not_author_error().into());
+    }
+
+    Ok(post)
+}
+
+pub fn not_author_error() -> ApiError {
+    ApiError::forbidden("not_author", "Only the author can change this")
+}
+
+#[cfg(test)]
+mod post_api_tests {
+    use super::*;
+    use crate::api::feed::{get_all_feed, get_popular_feed};
+    use crate::api::sub::approve_sub_member;
+    use crate::auth::token::token_tests::test_keys;
+    use crate::model::post::POPULAR_MIN_SCORE;
+    use crate::model::sub::{ModPermissions, SubVisibility};
+    use crate::model::user::DbAddUser;
+    use crate::repo::{self, memory::InMemoryRepo};
+    use actix_web::http::header::CACHE_CONTROL;
+    use actix_web::{test, App};
+    use chrono::NaiveDate;
+    use std::sync::Arc;
+
+    async fn seed_nsfw_post(repo: &InMemoryRepo) -> Uuid {
+        SubRepository::create_sub(
+            repo,
+            &Sub {
+                name: "rust".to_string(),
+                description: "Rust".to_string(),
+                created_at: Utc::now(),
+                nsfw: false,
+                spoiler_by_default: false,
+                subscribers: 0,
+                visibility: SubVisibility::Public,
+                icon_url: None,
+                banner_url: None,
+                primary_color: None,
+                accent_color: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let post = Post {
+            id: Uuid::new_v4(),
+            sub: "rust".to_string(),
+            user_id: 1,
+            title: "title".to_string(),
+            content: "content".to_string(),
+            timestamp: Utc::now(),
+            removal: None,
+            nsfw: true,
+            language: None,
+            score: 0,
+            status: PostStatus::Published,
+            edited_at: None,
+            pin_order: None,
+            locked: false,
+            lock_reason: None,
+            archived: false,
+            flair_id: None,
+            crosspost_parent_id: None,
+            link_url: None,
+            spoiler: false,
+            publish_at: None,
+            author_flair: None,
+        };
+        PostRepository::create_post(repo, &post).await.unwrap()
+    }
+
+    fn bearer(user_id: i32) -> (&'static str, String) {
+        let token = test_keys().issue(user_id).unwrap();
+        ("Authorization", format!("Bearer {}", token.access_token))
+    }
+
+    async fn seed_user(repo: &InMemoryRepo, adult: bool) -> i32 {
+        let user_id = UserRepository::create_user(
+            repo,
+            &DbAddUser {
+                username: "viewer".to_string(),
+                password_hash: String::new(),
+                created_at: Utc::now(),
+                email: None,
+                email_verified_at: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        if adult {
+            let date_of_birth = NaiveDate::from_ymd_opt(1990, 1, 1).unwrap();
+            repo.set_date_of_birth(user_id, date_of_birth)
+                .await
+                .unwrap();
+            repo.acknowledge_nsfw(user_id).await.unwrap();
+        }
+
+        user_id
+    }
+
+    #[actix_web::test]
+    async fn test_nsfw_post_requires_clearance() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_nsfw_post(&repo).await;
+        let minor_id = seed_user(&repo, false).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_post),
+        )
+        .await;
+
+        let anonymous = test::TestRequest::get()
+            .uri(&format!("/posts/{}", post_id))
+            .to_request();
+        let response = test::call_service(&app, anonymous).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let unconfirmed = test::TestRequest::get()
+            .uri(&format!("/posts/{}", post_id))
+            .insert_header(bearer(minor_id))
+            .to_request();
+        let response = test::call_service(&app, unconfirmed).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let forged = test::TestRequest::get()
+            .uri(&format!("/posts/{}", post_id))
+            .insert_header(("Authorization", "Bearer not-a-token"))
+            .to_request();
+        let response = test::call_service(&app, forged).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_nsfw_post_served_to_acknowledged_adult() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_nsfw_post(&repo).await;
+        let adult_id = seed_user(&repo, true).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_post)
+                .service(get_posts_by_sub),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/posts/{}", post_id))
+            .insert_header(bearer(adult_id))
+            .to_request();
+        let response: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(response["post"]["id"], post_id.to_string());
+
+        let listing = test::TestRequest::get()
+            .uri("/posts/for_sub/rust")
+            .insert_header(bearer(adult_id))
+            .to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, listing).await;
+        assert_eq!(page["posts"].as_array().unwrap().len(), 1);
+        assert!(page["next_cursor"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn test_sub_listing_follows_the_nsfw_preference_unless_overridden() {
+        let repo = Arc::new(InMemoryRepo::default());
+        seed_nsfw_post(&repo).await;
+        let adult_id = seed_user(&repo, true).await;
+        repo.set_show_nsfw(adult_id, false).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_posts_by_sub),
+        )
+        .await;
+
+        for (uri, expected) in [
+            ("/posts/for_sub/rust", 0),
+            ("/posts/for_sub/rust?include_nsfw=true", 1),
+        ] {
+            let listing = test::TestRequest::get()
+                .uri(uri)
+                .insert_header(bearer(adult_id))
+                .to_request();
+            let page: serde_json::Value = test::call_and_read_body_json(&app, listing).await;
+            assert_eq!(page["posts"].as_array().unwrap().len(), expected, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_hidden_posts_leave_only_the_hider_listing() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_nsfw_post(&repo).await;
+        let hider_id = seed_user(&repo, true).await;
+        let other_id = seed_user(&repo, true).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(hide_post)
+                .service(get_hidden_posts)
+                .service(get_posts_by_sub),
+        )
+        .await;
+
+        let request = test::TestRequest::put()
+            .uri(&format!("/posts/{}/hide", post_id))
+            .insert_header(bearer(hider_id))
+            .to_request();
+        let hidden: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(hidden["hidden"], true);
+
+        for (user_id, expected) in [(hider_id, 0), (other_id, 1)] {
+            let listing = test::TestRequest::get()
+                .uri("/posts/for_sub/rust")
+                .insert_header(bearer(user_id))
+                .to_request();
+            let page: serde_json::Value = test::call_and_read_body_json(&app, listing).await;
+            assert_eq!(page["posts"].as_array().unwrap().len(), expected);
+        }
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/users/{}/hidden", hider_id))
+            .insert_header(bearer(hider_id))
+            .to_request();
+        let hidden: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(hidden[0]["id"], post_id.to_string());
+    }
+
+    #[actix_web::test]
+    async fn test_only_the_author_or_a_moderator_sets_post_flags() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_nsfw_post(&repo).await;
+        let author_id = seed_user(&repo, true).await;
+        let stranger_id = seed_user(&repo, true).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(set_post_flags),
+        )
+        .await;
+
+        let request = test::TestRequest::patch()
+            .uri(&format!("/posts/{}/flags", post_id))
+            .insert_header(bearer(stranger_id))
+            .set_json(serde_json::json!({ "nsfw": false }))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let request = test::TestRequest::patch()
+            .uri(&format!("/posts/{}/flags", post_id))
+            .insert_header(bearer(author_id))
+            .set_json(serde_json::json!({ "spoiler": true }))
+            .to_request();
+        let post: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(post["nsfw"], true);
+        assert_eq!(post["spoiler"], true);
+        assert!(
+            PostRepository::get_post(repo.as_ref(), post_id)
+                .await
+                .unwrap()
+                .spoiler
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_sub_listing_pages_with_cursors() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let first_id = seed_nsfw_post(&repo).await;
+        let adult_id = seed_user(&repo, true).await;
+        let second = Post {
+            id: Uuid::new_v4(),
+            ..PostRepository::get_post(repo.as_ref(), first_id)
+                .await
+                .unwrap()
+        };
+        PostRepository::create_post(repo.as_ref(), &second)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_posts_by_sub),
+        )
+        .await;
+        let page = |query: String| {
+            test::TestRequest::get()
+                .uri(&format!("/posts/for_sub/rust?{}", query))
+                .insert_header(bearer(adult_id))
+                .to_request()
+        };
+
+        let first: serde_json::Value =
+            test::call_and_read_body_json(&app, page("sort=top&limit=1".to_string())).await;
+        let cursor = first["next_cursor"].as_str().unwrap();
+        let rest: serde_json::Value =
+            test::call_and_read_body_json(&app, page(format!("sort=top&limit=1&after={}", cursor)))
+                .await;
+        assert_eq!(rest["posts"].as_array().unwrap().len(), 1);
+        assert_ne!(rest["posts"][0]["id"], first["posts"][0]["id"]);
+        assert!(rest["next_cursor"].is_null());
+
+        let response = test::call_service(&app, page(format!("sort=hot&after={}", cursor))).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_batch_fetch_keeps_order_and_embeds_context() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let nsfw_id = seed_nsfw_post(&repo).await;
+        let author_id = seed_user(&repo, false).await;
+        let mut post_ids = Vec::new();
+        for title in ["first", "second"] {
+            let post = Post {
+                id: Uuid::new_v4(),
+                sub: "rust".to_string(),
+                user_id: author_id,
+                title: title.to_string(),
+                content: "content".to_string(),
+                timestamp: Utc::now(),
+                removal: None,
+                nsfw: false,
+                language: None,
+                score: 0,
+                status: PostStatus::Published,
+                edited_at: None,
+                pin_order: None,
+                locked: false,
+                lock_reason: None,
+                archived: false,
+                flair_id: None,
+                crosspost_parent_id: None,
+                link_url: None,
+                spoiler: false,
+                publish_at: None,
+                author_flair: None,
+            };
+            post_ids.push(PostRepository::create_post(&*repo, &post).await.unwrap());
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_posts_by_ids),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri(&format!(
+                "/posts?ids={},{},{},{}",
+                post_ids[1],
+                nsfw_id,
+                Uuid::new_v4(),
+                post_ids[0]
+            ))
+            .to_request();
+        let posts: Vec<serde_json::Value> = test::call_and_read_body_json(&app, request).await;
+
+        let titles: Vec<&str> = posts
+            .iter()
+            .map(|post| post["post"]["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, ["second", "first"]);
+        assert_eq!(posts[0]["author"]["username"], "viewer");
+        assert_eq!(posts[0]["sub"]["name"], "rust");
+    }
+
+    #[actix_web::test]
+    async fn test_drafts_stay_hidden_until_published() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let nsfw_id = seed_nsfw_post(&repo).await;
+        let author_id = seed_user(&repo, true).await;
+        let draft = Post {
+            id: Uuid::new_v4(),
+            user_id: author_id,
+            nsfw: false,
+            status: PostStatus::Draft,
+            ..PostRepository::get_post(repo.as_ref(), nsfw_id)
+                .await
+                .unwrap()
+        };
+        PostRepository::create_post(repo.as_ref(), &draft)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_post)
+                .service(get_posts_by_sub)
+                .service(publish_post)
+                .service(get_user_drafts),
+        )
+        .await;
+        let listed = || async {
+            let request = test::TestRequest::get()
+                .uri("/posts/for_sub/rust")
+                .insert_header(bearer(author_id))
+                .to_request();
+            let page: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+            page["posts"].as_array().unwrap().len()
+        };
+        let publish = || {
+            test::TestRequest::patch()
+                .uri(&format!("/posts/{}/publish", draft.id))
+                .insert_header(bearer(author_id))
+                .to_request()
+        };
+
+        assert_eq!(listed().await, 1);
+        let anonymous = test::TestRequest::get()
+            .uri(&format!("/posts/{}", draft.id))
+            .to_request();
+        let response = test::call_service(&app, anonymous).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let drafts = test::TestRequest::get()
+            .uri(&format!("/users/{}/drafts", author_id))
+            .insert_header(bearer(author_id))
+            .to_request();
+        let drafts: Vec<serde_json::Value> = test::call_and_read_body_json(&app, drafts).await;
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0]["status"], "draft");
+        let someone_elses = test::TestRequest::get()
+            .uri(&format!("/users/{}/drafts", author_id))
+            .insert_header(bearer(author_id + 1))
+            .to_request();
+        let response = test::call_service(&app, someone_elses).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let published: serde_json::Value = test::call_and_read_body_json(&app, publish()).await;
+        assert_eq!(published["status"], "published");
+        assert_eq!(listed().await, 2);
+        let response = test::call_service(&app, publish()).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn test_changing_a_vote_replaces_it() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_nsfw_post(&repo).await;
+        let voter_id = seed_user(&repo, true).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(vote_post)
+                .service(delete_post_vote),
+        )
+        .await;
+        let vote = |value: i16| {
+            test::TestRequest::put()
+                .uri(&format!("/posts/{}/vote", post_id))
+                .insert_header(bearer(voter_id))
+                .set_json(serde_json::json!({ "value": value }))
+                .to_request()
+        };
+
+        let up: serde_json::Value = test::call_and_read_body_json(&app, vote(1)).await;
+        assert_eq!(up["score"], 1);
+        let down: serde_json::Value = test::call_and_read_body_json(&app, vote(-1)).await;
+        assert_eq!(down["score"], -1);
+
+        let response = test::call_service(&app, vote(2)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let withdraw = test::TestRequest::delete()
+            .uri(&format!("/posts/{}/vote", post_id))
+            .insert_header(bearer(voter_id))
+            .to_request();
+        let withdrawn: serde_json::Value = test::call_and_read_body_json(&app, withdraw).await;
+        assert_eq!(withdrawn["score"], 0);
+        assert!(withdrawn["vote"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn test_poll_takes_one_vote_per_user() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_nsfw_post(&repo).await;
+        let voter_id = seed_user(&repo, true).await;
+        let options = ["Yes".to_string(), "No".to_string()];
+        PostRepository::create_poll(repo.as_ref(), post_id, &options, None, true)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_post)
+                .service(vote_in_poll),
+        )
+        .await;
+        let vote = |option_id: i32| {
+            test::TestRequest::post()
+                .uri(&format!("/posts/{}/poll/vote", post_id))
+                .insert_header(bearer(voter_id))
+                .set_json(serde_json::json!({ "option_id": option_id }))
+                .to_request()
+        };
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/posts/{}", post_id))
+            .insert_header(bearer(voter_id))
+            .to_request();
+        let before: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(before["poll"]["results_hidden"], true);
+        assert!(before["poll"]["options"][0]["votes"].is_null());
+        let no_id = before["poll"]["options"][1]["id"].as_i64().unwrap() as i32;
+
+        let response = test::call_service(&app, vote(no_id + 100)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let voted: serde_json::Value = test::call_and_read_body_json(&app, vote(no_id)).await;
+        assert_eq!(voted["vote"], no_id);
+        assert_eq!(voted["total_votes"], 1);
+        assert_eq!(voted["options"][1]["votes"], 1);
+
+        let response = test::call_service(&app, vote(no_id - 1)).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn test_crossposts_link_both_ways() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let original_id = seed_nsfw_post(&repo).await;
+        let adult_id = seed_user(&repo, true).await;
+        let crosspost = Post {
+            id: Uuid::new_v4(),
+            crosspost_parent_id: Some(original_id),
+            ..PostRepository::get_post(repo.as_ref(), original_id)
+                .await
+                .unwrap()
+        };
+        PostRepository::create_post(repo.as_ref(), &crosspost)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_post)
+                .service(get_post_duplicates),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/posts/{}", crosspost.id))
+            .insert_header(bearer(adult_id))
+            .to_request();
+        let response: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(
+            response["post"]["crosspost_parent_id"],
+            original_id.to_string()
+        );
+        assert_eq!(response["crosspost_parent"]["id"], original_id.to_string());
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/posts/{}/duplicates", original_id))
+            .insert_header(bearer(adult_id))
+            .to_request();
+        let duplicates: Vec<serde_json::Value> = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0]["id"], crosspost.id.to_string());
+    }
+
+    #[actix_web::test]
+    async fn test_removed_posts_hide_their_title_link_and_crossposted_copies() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let original_id = seed_nsfw_post(&repo).await;
+        let adult_id = seed_user(&repo, true).await;
+        let seeded = PostRepository::get_post(repo.as_ref(), original_id)
+            .await
+            .unwrap();
+        let removed = Post {
+            id: Uuid::new_v4(),
+            removal: Some(RemovalKind::Legal),
+            link_url: Some("https://example.com/leak".to_string()),
+            ..seeded.clone()
+        };
+        PostRepository::create_post(repo.as_ref(), &removed)
+            .await
+            .unwrap();
+        let crosspost = Post {
+            id: Uuid::new_v4(),
+            crosspost_parent_id: Some(removed.id),
+            ..seeded
+        };
+        PostRepository::create_post(repo.as_ref(), &crosspost)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_post),
+        )
+        .await;
+        let read = |post_id: Uuid| {
+            test::TestRequest::get()
+                .uri(&format!("/posts/{}", post_id))
+                .insert_header(bearer(adult_id))
+                .to_request()
+        };
+
+        let response: serde_json::Value =
+            test::call_and_read_body_json(&app, read(removed.id)).await;
+        assert_eq!(response["post"]["title"], "[removed for legal reasons]");
+        assert_eq!(response["post"]["content"], "[removed for legal reasons]");
+        assert!(response["post"]["link_url"].is_null());
+        assert!(response.get("media").is_none());
+
+        let response: serde_json::Value =
+            test::call_and_read_body_json(&app, read(crosspost.id)).await;
+        assert!(response["crosspost_parent"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn test_popular_feed_lists_well_received_safe_posts() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let seeded = seed_nsfw_post(&repo).await;
+        let adult_id = seed_user(&repo, true).await;
+        let seeded = repo.get_post(seeded).await.unwrap();
+        let mut popular = Vec::new();
+        for nsfw in [false, true] {
+            let post = Post {
+                id: Uuid::new_v4(),
+                nsfw,
+                score: POPULAR_MIN_SCORE,
+                ..seeded.clone()
+            };
+            popular.push(
+                PostRepository::create_post(repo.as_ref(), &post)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_popular_feed),
+        )
+        .await;
+
+        let anonymous = test::TestRequest::get().uri("/feed/popular").to_request();
+        let response = test::call_service(&app, anonymous).await;
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            "public, max-age=60"
+        );
+        let page: serde_json::Value = test::read_body_json(response).await;
+        let posts = page["posts"].as_array().unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0]["id"], popular[0].to_string());
+
+        for (uri, expected) in [("/feed/popular", 1), ("/feed/popular?include_nsfw=true", 2)] {
+            let request = test::TestRequest::get()
+                .uri(uri)
+                .insert_header(bearer(adult_id))
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(
+                response.headers().get(CACHE_CONTROL).unwrap(),
+                "private, no-cache"
+            );
+            let page: serde_json::Value = test::read_body_json(response).await;
+            assert_eq!(page["posts"].as_array().unwrap().len(), expected, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_private_sub_posts_are_for_members_only() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_nsfw_post(&repo).await;
+        let moderator = seed_user(&repo, true).await;
+        let reader = seed_user(&repo, true).await;
+        let mut sub = repo.get_sub_by_name("rust").await.unwrap();
+        sub.visibility = SubVisibility::Private;
+        SubRepository::update_sub(repo.as_ref(), &sub)
+            .await
+            .unwrap();
+        repo.add_sub_moderator("rust", moderator, None, ModPermissions::FULL)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_post)
+                .service(get_all_feed)
+                .service(approve_sub_member),
+        )
+        .await;
+        let read = |user_id: Option<i32>| {
+            let request = test::TestRequest::get().uri(&format!("/posts/{}", post_id));
+            match user_id {
+                Some(user_id) => request.insert_header(bearer(user_id)),
+                None => request,
+            }
+            .to_request()
+        };
+        let feed = |user_id: i32| {
+            test::TestRequest::get()
+                .uri("/feed/all?include_nsfw=true")
+                .insert_header(bearer(user_id))
+                .to_request()
+        };
+
+        for user_id in [None, Some(reader)] {
+            let response = test::call_service(&app, read(user_id)).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        let page: serde_json::Value = test::call_and_read_body_json(&app, feed(reader)).await;
+        assert_eq!(page["posts"].as_array().unwrap().len(), 0);
+        let response = test::call_service(&app, read(Some(moderator))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let approve = test::TestRequest::put()
+            .uri(&format!("/subs/rust/members/{}", reader))
+            .insert_header(bearer(moderator))
+            .to_request();
+        let members: Vec<serde_json::Value> = test::call_and_read_body_json(&app, approve).await;
+        assert_eq!(members[0]["user_id"], reader);
+
+        let response = test::call_service(&app, read(Some(reader))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let page: serde_json::Value = test::call_and_read_body_json(&app, feed(reader)).await;
+        assert_eq!(page["posts"][0]["id"], post_id.to_string());
+    }
+
+    #[actix_web::test]
+    async fn test_revision_diffs_are_for_author_and_moderators_who_can_read_the_post() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let post_id = seed_nsfw_post(&repo).await;
+        let author = seed_user(&repo, false).await;
+        let moderator = seed_user(&repo, false).await;
+        let reader = seed_user(&repo, false).await;
+        repo.add_sub_moderator("rust", moderator, None, ModPermissions::FULL)
+            .await
+            .unwrap();
+        PostRepository::update_post(repo.as_ref(), post_id, "edited".to_string(), None)
+            .await
+            .unwrap();
+        let mut removed = repo.get_post(post_id).await.unwrap();
+        removed.id = Uuid::new_v4();
+        removed.removal = Some(RemovalKind::Legal);
+        let removed_id = PostRepository::create_post(repo.as_ref(), &removed)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(get_post_revision_diff),
+        )
+        .await;
+        let diff = |post_id: Uuid, to: i32, user_id: Option<i32>| {
+            let request = test::TestRequest::get()
+                .uri(&format!("/posts/{}/revisions/1/diff/{}", post_id, to));
+            match user_id {
+                Some(user_id) => request.insert_header(bearer(user_id)),
+                None => request,
+            }
+            .to_request()
+        };
+
+        for (user_id, status) in [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some(reader), StatusCode::FORBIDDEN),
+            (Some(author), StatusCode::OK),
+            (Some(moderator), StatusCode::OK),
+        ] {
+            let response = test::call_service(&app, diff(post_id, 2, user_id)).await;
+            assert_eq!(response.status(), status);
+        }
+        let response = test::call_service(&app, diff(removed_id, 1, Some(moderator))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
 
-    Ok(HttpResponse::Ok().body(format!("{:?} was deleted", post_id)))
+        let mut sub = repo.get_sub_by_name("rust").await.unwrap();
+        sub.visibility = SubVisibility::Private;
+        SubRepository::update_sub(repo.as_ref(), &sub)
+            .await
+            .unwrap();
+        let response = test::call_service(&app, diff(post_id, 2, Some(author))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = test::call_service(&app, diff(post_id, 2, Some(moderator))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
 }
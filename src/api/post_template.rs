@@ -0,0 +1,163 @@
+use crate::auth::{ManagePosts, RequireSubModerator};
+use crate::error::ApiError;
+use crate::model::moderation::ModAction;
+use crate::model::post_template::{NewPostTemplate, PostTemplate, PostTemplateUpdate};
+use crate::repo::{
+    moderation as moderation_repo, post_template as post_template_repo, sub::SubRepository,
+};
+use actix_web::{delete, get, http::StatusCode, patch, post, web::Data, web::Json, web::Path};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[get("/subs/{sub}/scheduled-posts")]
+pub async fn get_post_templates(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManagePosts>,
+    path: Path<String>,
+) -> Result<Json<Vec<PostTemplate>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    let templates = post_template_repo::get_post_templates(&pool, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(templates))
+}
+
+/// Posts made from the template are made as the moderator who created it.
+#[post("/subs/{sub}/scheduled-posts")]
+pub async fn create_post_template(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    moderator: RequireSubModerator<ManagePosts>,
+    path: Path<String>,
+    body: Json<NewPostTemplate>,
+) -> Result<Json<PostTemplate>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let title = normalize_template_title(&body.title)?;
+
+    let template = post_template_repo::create_post_template(
+        &pool,
+        &sub_name,
+        moderator.user_id,
+        &title,
+        &body.content,
+        body.repeat,
+        body.starts_at,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::CreatePostTemplate,
+        None,
+        Some(&sub_name),
+        json!({ "template_id": template.id, "title": template.title }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(template))
+}
+
+#[patch("/subs/{sub}/scheduled-posts/{template_id}")]
+pub async fn update_post_template(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManagePosts>,
+    path: Path<(String, i32)>,
+    body: Json<PostTemplateUpdate>,
+) -> Result<Json<PostTemplate>, actix_web::Error> {
+    let (sub_name, template_id) = path.into_inner();
+    let mut template = post_template_repo::get_post_template(&pool, &sub_name, template_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Scheduled post not found"))?;
+    let body = body.into_inner();
+    if let Some(title) = &body.title {
+        template.title = normalize_template_title(title)?;
+    }
+    if let Some(content) = body.content {
+        template.content = content;
+    }
+    if let Some(repeat) = body.repeat {
+        template.repeat = repeat;
+    }
+    if let Some(next_run_at) = body.next_run_at {
+        template.next_run_at = next_run_at;
+    }
+
+    let template = post_template_repo::update_post_template(&pool, &template)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Scheduled post not found"))?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::UpdatePostTemplate,
+        None,
+        Some(&sub_name),
+        json!({
+            "template_id": template.id,
+            "title": template.title,
+            "repeat": template.repeat,
+            "next_run_at": template.next_run_at,
+        }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(template))
+}
+
+/// Posts already made from the template are kept.
+#[delete("/subs/{sub}/scheduled-posts/{template_id}")]
+pub async fn delete_post_template(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManagePosts>,
+    path: Path<(String, i32)>,
+) -> Result<Json<PostTemplate>, actix_web::Error> {
+    let (sub_name, template_id) = path.into_inner();
+    let template = post_template_repo::get_post_template(&pool, &sub_name, template_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Scheduled post not found"))?;
+
+    let deleted = post_template_repo::delete_post_template(&pool, &sub_name, template_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !deleted {
+        return Err(actix_web::error::ErrorNotFound("Scheduled post not found"));
+    }
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::DeletePostTemplate,
+        None,
+        Some(&sub_name),
+        json!({ "template_id": template_id, "title": template.title }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(template))
+}
+
+fn normalize_template_title(title: &str) -> Result<String, ApiError> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "empty_post_template_title",
+            "A scheduled post needs a title",
+        ));
+    }
+    Ok(title.to_string())
+}
@@ -0,0 +1,252 @@
+use crate::api::comment::get_readable_comment_post;
+use crate::api::post::{get_readable_post, require_visible_post};
+use crate::api::sub::get_readable_sub;
+use crate::api::user::require_nsfw_clearance;
+use crate::auth::{Admin, AuthenticatedUser, RequireRole};
+use crate::config::PremiumConfig;
+use crate::error::ApiError;
+use crate::model::premium::{
+    verify_webhook_signature, AwardCount, NewAward, PaymentEvent, Perks, PremiumGrant,
+    PremiumStatus, PremiumSubscription,
+};
+use crate::repo::premium as premium_repo;
+use crate::repo::{
+    comment::CommentRepository, post::PostRepository, sub::SubRepository, user::UserRepository,
+};
+use actix_web::{
+    get, post, web::Bytes, web::Data, web::Json, web::Path, HttpRequest, HttpResponse,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+#[get("/users/{user_id}/premium")]
+pub async fn get_premium_status(
+    pool: Data<PgPool>,
+    users: Data<dyn UserRepository>,
+    config: Data<PremiumConfig>,
+    path: Path<i32>,
+) -> Result<Json<PremiumStatus>, actix_web::Error> {
+    let user_id = path.into_inner();
+
+    let user = users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let expires_at = premium_repo::get_premium_expiry(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(PremiumStatus {
+        user_id,
+        is_premium: user.is_premium,
+        expires_at,
+        perks: Perks::for_member(user.is_premium, &config),
+    }))
+}
+
+#[post("/admin/premium/{user_id}")]
+pub async fn grant_premium(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<i32>,
+    body: Json<PremiumGrant>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+    if body.days <= 0 {
+        return Err(actix_web::error::ErrorBadRequest(
+            "days must be a positive number",
+        ));
+    }
+
+    premium_repo::grant_premium(&pool, user_id, admin.user_id, body.days)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!(
+        "User {} has premium for {} days",
+        user_id, body.days
+    )))
+}
+
+#[post("/admin/premium/{user_id}/revoke")]
+pub async fn revoke_premium(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    path: Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+
+    premium_repo::revoke_premium(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!("User {} no longer has premium", user_id)))
+}
+
+#[get("/admin/premium/{user_id}/subscriptions")]
+pub async fn get_premium_subscriptions(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+    path: Path<i32>,
+) -> Result<Json<Vec<PremiumSubscription>>, actix_web::Error> {
+    let user_id = path.into_inner();
+
+    let subscriptions = premium_repo::get_subscriptions(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(subscriptions))
+}
+
+/// Gives the post an award. Returns the post's awards by type.
+#[post("/posts/{id}/awards")]
+#[allow(clippy::too_many_arguments)]
+pub async fn award_post(
+    pool: Data<PgPool>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    config: Data<PremiumConfig>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<NewAward>,
+) -> Result<Json<Vec<AwardCount>>, actix_web::Error> {
+    let post_id = path.into_inner();
+    require_award_perk(users.get_ref(), &config, caller.user_id, &body).await?;
+
+    let post = get_readable_post(posts.get_ref(), post_id, None).await?;
+    if post.removal.is_some() {
+        return Err(actix_web::error::ErrorNotFound("Post not found"));
+    }
+    require_visible_post(users.get_ref(), &post, Some(caller.user_id)).await?;
+    get_readable_sub(
+        subs.get_ref(),
+        users.get_ref(),
+        &post.sub,
+        Some(caller.user_id),
+    )
+    .await?;
+    if post.nsfw {
+        require_nsfw_clearance(users.get_ref(), Some(caller.user_id)).await?;
+    }
+
+    let counts =
+        premium_repo::give_award(&pool, caller.user_id, body.award_type, Some(post_id), None)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(counts))
+}
+
+/// Gives the comment an award. Returns the comment's awards by type.
+#[post("/comments/{comment_id}/awards")]
+#[allow(clippy::too_many_arguments)]
+pub async fn award_comment(
+    pool: Data<PgPool>,
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    config: Data<PremiumConfig>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<NewAward>,
+) -> Result<Json<Vec<AwardCount>>, actix_web::Error> {
+    let comment_id = path.into_inner();
+    require_award_perk(users.get_ref(), &config, caller.user_id, &body).await?;
+
+    get_readable_comment_post(
+        comments.get_ref(),
+        posts.get_ref(),
+        subs.get_ref(),
+        users.get_ref(),
+        caller.user_id,
+        comment_id,
+    )
+    .await?;
+    let comment = comments
+        .get_comment(comment_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if comment.removal.is_some() {
+        return Err(actix_web::error::ErrorNotFound("Comment not found"));
+    }
+
+    let counts = premium_repo::give_award(
+        &pool,
+        caller.user_id,
+        body.award_type,
+        None,
+        Some(comment_id),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(counts))
+}
+
+/// Premium-only award types are among the perks of premium membership.
+async fn require_award_perk(
+    users: &dyn UserRepository,
+    config: &PremiumConfig,
+    user_id: i32,
+    award: &NewAward,
+) -> Result<(), actix_web::Error> {
+    let giver = users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let perks = Perks::for_member(giver.is_premium, config);
+    if !perks.award_types.contains(&award.award_type) {
+        return Err(ApiError::forbidden(
+            "premium_required",
+            format!("Only premium members can give {} awards", award.award_type),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Subscription events from the payment provider, signed with `PAYMENT_WEBHOOK_SECRET`
+/// in the `X-Webhook-Signature` header. Redelivered events are acknowledged but
+/// applied only once.
+#[post("/webhooks/payments")]
+pub async fn payment_webhook(
+    pool: Data<PgPool>,
+    config: Data<PremiumConfig>,
+    req: HttpRequest,
+    body: Bytes,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(secret) = &config.webhook_secret else {
+        return Err(actix_web::error::ErrorServiceUnavailable(
+            "Payment webhooks are not configured",
+        ));
+    };
+    let signature = req
+        .headers()
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !verify_webhook_signature(secret, &body, signature) {
+        return Err(actix_web::error::ErrorUnauthorized(
+            "Invalid webhook signature",
+        ));
+    }
+
+    let payload: serde_json::Value =
+        serde_json::from_slice(&body).map_err(actix_web::error::ErrorBadRequest)?;
+    let event: PaymentEvent =
+        serde_json::from_value(payload.clone()).map_err(actix_web::error::ErrorBadRequest)?;
+
+    let applied = premium_repo::apply_payment_event(&pool, &event, &payload)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !applied {
+        log::info!("Payment event {} was already processed", event.id);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,167 @@
+use crate::auth::{ManagePosts, ManageSettings, RequireSubModerator};
+use crate::error::ApiError;
+use crate::model::moderation::ModAction;
+use crate::model::removal_reason::{
+    normalize_removal_message, normalize_removal_reason_title, NewRemovalReason, RemovalReason,
+    RemovalReasonUpdate, MAX_REMOVAL_MESSAGE_CHARS, MAX_REMOVAL_REASON_TITLE_CHARS,
+};
+use crate::repo::{
+    moderation as moderation_repo, removal_reason as removal_reason_repo, sub::SubRepository,
+};
+use actix_web::{delete, get, http::StatusCode, patch, post, web::Data, web::Json, web::Path};
+use serde_json::json;
+use sqlx::PgPool;
+
+/// Only moderators who can remove content need the sub's removal reasons.
+#[get("/subs/{sub}/removal_reasons")]
+pub async fn get_removal_reasons(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManagePosts>,
+    path: Path<String>,
+) -> Result<Json<Vec<RemovalReason>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    let reasons = removal_reason_repo::get_removal_reasons(&pool, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(reasons))
+}
+
+#[post("/subs/{sub}/removal_reasons")]
+pub async fn create_removal_reason(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    moderator: RequireSubModerator<ManageSettings>,
+    path: Path<String>,
+    body: Json<NewRemovalReason>,
+) -> Result<Json<RemovalReason>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let title = normalize_removal_reason_title(&body.title)
+        .ok_or_else(invalid_removal_reason_title_error)?;
+    let message =
+        normalize_removal_message(&body.message).ok_or_else(invalid_removal_message_error)?;
+
+    let reason = removal_reason_repo::create_removal_reason(&pool, &sub_name, &title, &message)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::CreateRemovalReason,
+        None,
+        Some(&sub_name),
+        json!({ "removal_reason_id": reason.id, "title": reason.title }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(reason))
+}
+
+#[patch("/subs/{sub}/removal_reasons/{reason_id}")]
+pub async fn update_removal_reason(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManageSettings>,
+    path: Path<(String, i32)>,
+    body: Json<RemovalReasonUpdate>,
+) -> Result<Json<RemovalReason>, actix_web::Error> {
+    let (sub_name, reason_id) = path.into_inner();
+    let mut reason = removal_reason_repo::get_removal_reason(&pool, &sub_name, reason_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(removal_reason_not_found)?;
+    if let Some(title) = &body.title {
+        reason.title =
+            normalize_removal_reason_title(title).ok_or_else(invalid_removal_reason_title_error)?;
+    }
+    if let Some(message) = &body.message {
+        reason.message =
+            normalize_removal_message(message).ok_or_else(invalid_removal_message_error)?;
+    }
+
+    let reason = removal_reason_repo::update_removal_reason(&pool, &reason)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(removal_reason_not_found)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::UpdateRemovalReason,
+        None,
+        Some(&sub_name),
+        json!({ "removal_reason_id": reason.id, "title": reason.title }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(reason))
+}
+
+/// Content already removed with the reason keeps its message.
+#[delete("/subs/{sub}/removal_reasons/{reason_id}")]
+pub async fn delete_removal_reason(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManageSettings>,
+    path: Path<(String, i32)>,
+) -> Result<Json<RemovalReason>, actix_web::Error> {
+    let (sub_name, reason_id) = path.into_inner();
+    let reason = removal_reason_repo::get_removal_reason(&pool, &sub_name, reason_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(removal_reason_not_found)?;
+
+    let deleted = removal_reason_repo::delete_removal_reason(&pool, &sub_name, reason_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !deleted {
+        return Err(removal_reason_not_found());
+    }
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::DeleteRemovalReason,
+        None,
+        Some(&sub_name),
+        json!({ "removal_reason_id": reason_id, "title": reason.title }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(reason))
+}
+
+pub fn removal_reason_not_found() -> actix_web::Error {
+    actix_web::error::ErrorNotFound("Removal reason not found")
+}
+
+fn invalid_removal_reason_title_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_removal_reason_title",
+        format!(
+            "Removal reason titles must be between 1 and {} characters",
+            MAX_REMOVAL_REASON_TITLE_CHARS
+        ),
+    )
+    .with_arg("max", MAX_REMOVAL_REASON_TITLE_CHARS.to_string())
+}
+
+pub fn invalid_removal_message_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_removal_message",
+        format!(
+            "Removal messages must be between 1 and {} characters",
+            MAX_REMOVAL_MESSAGE_CHARS
+        ),
+    )
+    .with_arg("max", MAX_REMOVAL_MESSAGE_CHARS.to_string())
+}
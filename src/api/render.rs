@@ -0,0 +1,11 @@
+use crate::render::{markdown_to_html, Preview, PreviewRequest};
+use actix_web::{post, web::Json};
+
+/// Renders Markdown the way post and comment content is rendered, for live previews
+/// while writing.
+#[post("/render/preview")]
+pub async fn preview_markdown(request: Json<PreviewRequest>) -> Json<Preview> {
+    Json(Preview {
+        html: markdown_to_html(&request.markdown),
+    })
+}
@@ -0,0 +1,143 @@
+use crate::api::comment::get_readable_comment_post;
+use crate::api::post::get_readable_post;
+use crate::api::sub::get_readable_sub;
+use crate::api::user::require_nsfw_clearance;
+use crate::auth::AuthenticatedUser;
+use crate::error::ApiError;
+use crate::model::report::{NewReport, Report, ReportTarget, MAX_REPORT_REASON_CHARS};
+use crate::repo::{comment as comment_repo, report as report_repo, rule as rule_repo};
+use actix_web::{http::StatusCode, post, web::Data, web::Json, web::Path};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Reports the post to its sub's moderators. Each user can report a post once.
+#[post("/posts/{id}/report")]
+pub async fn report_post(
+    pool: Data<PgPool>,
+    reporter: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<NewReport>,
+) -> Result<Json<Report>, actix_web::Error> {
+    let post_id = path.into_inner();
+    let post = get_readable_post(pool.get_ref(), post_id, None).await?;
+    get_readable_sub(
+        pool.get_ref(),
+        pool.get_ref(),
+        &post.sub,
+        Some(reporter.user_id),
+    )
+    .await?;
+    if post.nsfw {
+        require_nsfw_clearance(pool.get_ref(), Some(reporter.user_id)).await?;
+    }
+    if post.removal.is_some() {
+        return Err(actix_web::error::ErrorNotFound("Post not found"));
+    }
+
+    let report = file_report(
+        &pool,
+        &post.sub,
+        ReportTarget::Post(post_id),
+        reporter.user_id,
+        &body,
+    )
+    .await?;
+
+    Ok(Json(report))
+}
+
+/// Reports the comment to its sub's moderators. Each user can report a comment once.
+#[post("/comments/{id}/report")]
+pub async fn report_comment(
+    pool: Data<PgPool>,
+    reporter: AuthenticatedUser,
+    path: Path<Uuid>,
+    body: Json<NewReport>,
+) -> Result<Json<Report>, actix_web::Error> {
+    let comment_id = path.into_inner();
+    let post = get_readable_comment_post(
+        pool.get_ref(),
+        pool.get_ref(),
+        pool.get_ref(),
+        pool.get_ref(),
+        reporter.user_id,
+        comment_id,
+    )
+    .await?;
+    let comment = comment_repo::get_comment(&pool, comment_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if comment.removal.is_some() {
+        return Err(actix_web::error::ErrorNotFound("Comment not found"));
+    }
+
+    let report = file_report(
+        &pool,
+        &post.sub,
+        ReportTarget::Comment(comment_id),
+        reporter.user_id,
+        &body,
+    )
+    .await?;
+
+    Ok(Json(report))
+}
+
+/// Checks the report names a rule of `sub` or gives a reason, then files it.
+async fn file_report(
+    pool: &PgPool,
+    sub: &str,
+    target: ReportTarget,
+    reporter_id: i32,
+    body: &NewReport,
+) -> Result<Report, actix_web::Error> {
+    let reason = body.reason.trim();
+    if reason.chars().count() > MAX_REPORT_REASON_CHARS {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "report_reason_too_long",
+            format!(
+                "Report reasons can be at most {} characters",
+                MAX_REPORT_REASON_CHARS
+            ),
+        )
+        .with_arg("max", MAX_REPORT_REASON_CHARS.to_string())
+        .into());
+    }
+    match body.rule_id {
+        Some(rule_id) => {
+            rule_repo::get_rule(pool, sub, rule_id)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?
+                .ok_or_else(|| {
+                    ApiError::new(
+                        StatusCode::BAD_REQUEST,
+                        "unknown_rule",
+                        format!("{} has no such rule", sub),
+                    )
+                    .with_arg("sub", sub.to_string())
+                })?;
+        }
+        None if reason.is_empty() => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "report_reason_required",
+                "Say which rule this breaks or why you are reporting it",
+            )
+            .into());
+        }
+        None => {}
+    }
+
+    report_repo::create_report(pool, sub, target, Some(reporter_id), body.rule_id, reason)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "already_reported",
+                "You have already reported this",
+            )
+            .into()
+        })
+}
@@ -0,0 +1,152 @@
+use crate::api::sub::get_readable_sub;
+use crate::auth::{ManageSettings, RequireSubModerator, Viewer};
+use crate::error::ApiError;
+use crate::model::moderation::ModAction;
+use crate::model::rule::{
+    normalize_rule_title, NewSubRule, SubRule, SubRuleUpdate, MAX_RULE_TITLE_CHARS,
+};
+use crate::repo::{
+    moderation as moderation_repo, rule as rule_repo, sub::SubRepository, user::UserRepository,
+};
+use actix_web::{delete, get, http::StatusCode, patch, post, web::Data, web::Json, web::Path};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[get("/subs/{sub}/rules")]
+pub async fn get_rules(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
+    path: Path<String>,
+) -> Result<Json<Vec<SubRule>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    get_readable_sub(subs.get_ref(), users.get_ref(), &sub_name, viewer.user_id()).await?;
+
+    let rules = rule_repo::get_rules(&pool, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(rules))
+}
+
+#[post("/subs/{sub}/rules")]
+pub async fn create_rule(
+    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    moderator: RequireSubModerator<ManageSettings>,
+    path: Path<String>,
+    body: Json<NewSubRule>,
+) -> Result<Json<SubRule>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let title = normalize_rule_title(&body.title).ok_or_else(invalid_rule_title_error)?;
+
+    let rule = rule_repo::create_rule(
+        &pool,
+        &sub_name,
+        &title,
+        body.description.trim(),
+        body.order,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::CreateRule,
+        None,
+        Some(&sub_name),
+        json!({ "rule_id": rule.id, "title": rule.title }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(rule))
+}
+
+#[patch("/subs/{sub}/rules/{rule_id}")]
+pub async fn update_rule(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManageSettings>,
+    path: Path<(String, i32)>,
+    body: Json<SubRuleUpdate>,
+) -> Result<Json<SubRule>, actix_web::Error> {
+    let (sub_name, rule_id) = path.into_inner();
+    let mut rule = rule_repo::get_rule(&pool, &sub_name, rule_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Rule not found"))?;
+    if let Some(title) = &body.title {
+        rule.title = normalize_rule_title(title).ok_or_else(invalid_rule_title_error)?;
+    }
+    if let Some(description) = &body.description {
+        rule.description = description.trim().to_string();
+    }
+    if let Some(order) = body.order {
+        rule.order = order;
+    }
+
+    let rule = rule_repo::update_rule(&pool, &rule)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Rule not found"))?;
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::UpdateRule,
+        None,
+        Some(&sub_name),
+        json!({ "rule_id": rule.id, "title": rule.title, "order": rule.order }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(rule))
+}
+
+#[delete("/subs/{sub}/rules/{rule_id}")]
+pub async fn delete_rule(
+    pool: Data<PgPool>,
+    moderator: RequireSubModerator<ManageSettings>,
+    path: Path<(String, i32)>,
+) -> Result<Json<SubRule>, actix_web::Error> {
+    let (sub_name, rule_id) = path.into_inner();
+    let rule = rule_repo::get_rule(&pool, &sub_name, rule_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Rule not found"))?;
+
+    let deleted = rule_repo::delete_rule(&pool, &sub_name, rule_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !deleted {
+        return Err(actix_web::error::ErrorNotFound("Rule not found"));
+    }
+    moderation_repo::log_mod_action(
+        &pool,
+        moderator.user_id,
+        ModAction::DeleteRule,
+        None,
+        Some(&sub_name),
+        json!({ "rule_id": rule_id, "title": rule.title }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(rule))
+}
+
+fn invalid_rule_title_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_rule_title",
+        format!(
+            "Rule titles must be between 1 and {} characters",
+            MAX_RULE_TITLE_CHARS
+        ),
+    )
+    .with_arg("max", MAX_RULE_TITLE_CHARS.to_string())
+}
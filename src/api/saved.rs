@@ -0,0 +1,323 @@
+use crate::api::comment::get_readable_comment_post;
+use crate::api::feed::invalid_cursor_error;
+use crate::api::post::get_readable_post;
+use crate::api::sub::get_readable_sub;
+use crate::api::user::{require_nsfw_clearance, viewer_can_view_nsfw};
+use crate::auth::AuthenticatedUser;
+use crate::model::dto::CommentView;
+use crate::model::post::PostPage;
+use crate::model::saved::{
+    SaveResult, SavedItem, SavedPage, SavedQuery, DEFAULT_SAVED_LIMIT, MAX_SAVED_LIMIT,
+};
+use crate::repo::{
+    comment as comment_repo, comment::CommentRepository, post as post_repo, post::PostRepository,
+    saved as saved_repo, sub::SubRepository, user::UserRepository,
+};
+use actix_web::{
+    delete, get, put,
+    web::{Data, Json, Path, Query},
+};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Saving a post that's already saved does nothing.
+#[put("/posts/{id}/save")]
+pub async fn save_post(
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<SaveResult>, actix_web::Error> {
+    let post_id = path.into_inner();
+    let post = get_readable_post(posts.get_ref(), post_id, None).await?;
+    get_readable_sub(
+        subs.get_ref(),
+        users.get_ref(),
+        &post.sub,
+        Some(caller.user_id),
+    )
+    .await?;
+    if post.nsfw {
+        require_nsfw_clearance(users.get_ref(), Some(caller.user_id)).await?;
+    }
+
+    posts
+        .save_post(post_id, caller.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(SaveResult { saved: true }))
+}
+
+#[delete("/posts/{id}/save")]
+pub async fn unsave_post(
+    posts: Data<dyn PostRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<SaveResult>, actix_web::Error> {
+    posts
+        .unsave_post(path.into_inner(), caller.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(SaveResult { saved: false }))
+}
+
+/// Saving a comment that's already saved does nothing.
+#[put("/comments/{comment_id}/save")]
+pub async fn save_comment(
+    comments: Data<dyn CommentRepository>,
+    posts: Data<dyn PostRepository>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<SaveResult>, actix_web::Error> {
+    let comment_id = path.into_inner();
+    get_readable_comment_post(
+        comments.get_ref(),
+        posts.get_ref(),
+        subs.get_ref(),
+        users.get_ref(),
+        caller.user_id,
+        comment_id,
+    )
+    .await?;
+
+    comments
+        .save_comment(comment_id, caller.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(SaveResult { saved: true }))
+}
+
+#[delete("/comments/{comment_id}/save")]
+pub async fn unsave_comment(
+    comments: Data<dyn CommentRepository>,
+    caller: AuthenticatedUser,
+    path: Path<Uuid>,
+) -> Result<Json<SaveResult>, actix_web::Error> {
+    comments
+        .unsave_comment(path.into_inner(), caller.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(SaveResult { saved: false }))
+}
+
+/// The account holder's saved posts and comments, most recently saved first. Items
+/// they can no longer read, such as posts turned NSFW without their clearance, are
+/// left out, so a page can hold fewer than `limit` items.
+#[get("/users/{user_id}/saved")]
+pub async fn get_saved_items(
+    pool: Data<PgPool>,
+    caller: AuthenticatedUser,
+    path: Path<i32>,
+    query: Query<SavedQuery>,
+) -> Result<Json<SavedPage>, actix_web::Error> {
+    let user_id = path.into_inner();
+    caller.require_self(user_id)?;
+    let before = match &query.after {
+        Some(cursor) => Some(cursor.parse().map_err(|_| invalid_cursor_error())?),
+        None => None,
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SAVED_LIMIT)
+        .clamp(1, MAX_SAVED_LIMIT);
+
+    let mut entries = saved_repo::get_saved_entries(&pool, user_id, before, limit)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let more = entries.len() as i64 > limit;
+    entries.truncate(limit as usize);
+    let next_cursor = entries
+        .last()
+        .filter(|_| more)
+        .map(|last| last.id.to_string());
+
+    let include_nsfw = viewer_can_view_nsfw(pool.get_ref(), Some(user_id)).await?;
+    let post_ids: Vec<Uuid> = entries.iter().filter_map(|entry| entry.post_id).collect();
+    let comment_ids: Vec<Uuid> = entries
+        .iter()
+        .filter_map(|entry| entry.comment_id)
+        .collect();
+    let mut posts: HashMap<_, _> =
+        post_repo::get_posts_by_ids(&pool, &post_ids, include_nsfw, Some(user_id))
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|post| (post.id, post))
+            .collect();
+    let mut comments: HashMap<_, _> =
+        comment_repo::get_comments_by_ids(&pool, &comment_ids, include_nsfw, Some(user_id))
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|comment| (comment.id, comment))
+            .collect();
+
+    let items = entries
+        .into_iter()
+        .filter_map(|entry| match (entry.post_id, entry.comment_id) {
+            (Some(post_id), _) => Some(SavedItem::Post {
+                saved_at: entry.saved_at,
+                post: posts.remove(&post_id)?,
+            }),
+            (None, Some(comment_id)) => Some(SavedItem::Comment {
+                saved_at: entry.saved_at,
+                comment: CommentView::from(comments.remove(&comment_id)?),
+            }),
+            (None, None) => None,
+        })
+        .collect();
+
+    Ok(Json(SavedPage { items, next_cursor }))
+}
+
+/// Flags each post on the page with whether the viewer saved it. Anonymous viewers'
+/// pages are left as they are.
+pub async fn mark_saved_posts(
+    posts: &dyn PostRepository,
+    viewer_id: Option<i32>,
+    page: &mut PostPage,
+) -> Result<(), actix_web::Error> {
+    let Some(viewer_id) = viewer_id else {
+        return Ok(());
+    };
+
+    let saved = posts
+        .get_saved_post_ids(viewer_id, &page.post_ids())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    page.mark_saved(&saved);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod saved_api_tests {
+    use super::*;
+    use crate::api::post::get_posts_by_sub;
+    use crate::auth::token::token_tests::test_keys;
+    use crate::model::post::{Post, PostStatus};
+    use crate::model::sub::{Sub, SubVisibility};
+    use crate::model::user::DbAddUser;
+    use crate::repo::{self, memory::InMemoryRepo, sub::SubRepository};
+    use actix_web::{test, App};
+    use chrono::Utc;
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_saved_posts_are_flagged_in_the_saver_listings() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let reader_id = UserRepository::create_user(
+            repo.as_ref(),
+            &DbAddUser {
+                username: "reader".to_string(),
+                password_hash: String::new(),
+                created_at: Utc::now(),
+                email: None,
+                email_verified_at: None,
+            },
+        )
+        .await
+        .unwrap();
+        SubRepository::create_sub(
+            repo.as_ref(),
+            &Sub {
+                name: "rust".to_string(),
+                description: "Rust".to_string(),
+                created_at: Utc::now(),
+                nsfw: false,
+                spoiler_by_default: false,
+                subscribers: 0,
+                visibility: SubVisibility::Public,
+                icon_url: None,
+                banner_url: None,
+                primary_color: None,
+                accent_color: None,
+            },
+        )
+        .await
+        .unwrap();
+        let post_id = PostRepository::create_post(
+            repo.as_ref(),
+            &Post {
+                id: Uuid::new_v4(),
+                sub: "rust".to_string(),
+                user_id: reader_id,
+                author_flair: None,
+                title: "title".to_string(),
+                content: "content".to_string(),
+                timestamp: Utc::now(),
+                removal: None,
+                nsfw: false,
+                spoiler: false,
+                publish_at: None,
+                language: None,
+                score: 0,
+                status: PostStatus::Published,
+                edited_at: None,
+                pin_order: None,
+                locked: false,
+                lock_reason: None,
+                archived: false,
+                flair_id: None,
+                crosspost_parent_id: None,
+                link_url: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(save_post)
+                .service(unsave_post)
+                .service(get_posts_by_sub),
+        )
+        .await;
+        let token = test_keys().issue(reader_id).unwrap();
+        let bearer = ("Authorization", format!("Bearer {}", token.access_token));
+        let listing_request = |authorization: Option<(&'static str, String)>| {
+            let mut request = test::TestRequest::get().uri("/posts/for_sub/rust");
+            if let Some(header) = authorization {
+                request = request.insert_header(header);
+            }
+            request.to_request()
+        };
+
+        let page: serde_json::Value =
+            test::call_and_read_body_json(&app, listing_request(Some(bearer.clone()))).await;
+        assert_eq!(page["posts"][0]["saved"], false);
+
+        let request = test::TestRequest::put()
+            .uri(&format!("/posts/{}/save", post_id))
+            .insert_header(bearer.clone())
+            .to_request();
+        let saved: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(saved["saved"], true);
+
+        let page: serde_json::Value =
+            test::call_and_read_body_json(&app, listing_request(Some(bearer.clone()))).await;
+        assert_eq!(page["posts"][0]["saved"], true);
+        let page: serde_json::Value =
+            test::call_and_read_body_json(&app, listing_request(None)).await;
+        assert!(page["posts"][0].get("saved").is_none());
+
+        let request = test::TestRequest::delete()
+            .uri(&format!("/posts/{}/save", post_id))
+            .insert_header(bearer.clone())
+            .to_request();
+        test::call_service(&app, request).await;
+        let page: serde_json::Value =
+            test::call_and_read_body_json(&app, listing_request(Some(bearer))).await;
+        assert_eq!(page["posts"][0]["saved"], false);
+    }
+}
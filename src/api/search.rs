@@ -0,0 +1,136 @@
+use crate::auth::role::moderation_scope;
+use crate::auth::{AuthenticatedUser, Viewer};
+use crate::error::ApiError;
+use crate::model::dto::UserPublic;
+use crate::model::search::{
+    CommentSearchHit, CommentSearchQuery, NameSearchQuery, PostSearchHit, PostSearchQuery,
+    SearchResults, DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT,
+};
+use crate::model::sub::Sub;
+use crate::repo::{search as search_repo, user::UserRepository};
+use actix_web::{get, http::StatusCode, web::Data, web::Json, web::Query};
+use chrono::Utc;
+use sqlx::PgPool;
+
+/// Visible posts matching `?q=`, optionally within `?sub=`. NSFW posts are only
+/// included for viewers cleared to see them, posts in private subs for their members,
+/// and only posts in the viewer's preferred languages, as in the feeds.
+#[get("/search/posts")]
+pub async fn search_posts(
+    pool: Data<PgPool>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
+    query: Query<PostSearchQuery>,
+) -> Result<Json<SearchResults<PostSearchHit>>, actix_web::Error> {
+    let terms = search_terms(&query.q)?;
+    let (include_nsfw, languages) = match viewer.user_id() {
+        Some(viewer_id) => {
+            let viewer = users
+                .get_user_by_id(viewer_id)
+                .await
+                .map_err(actix_web::error::ErrorNotFound)?;
+            (
+                viewer.can_view_nsfw(Utc::now().date_naive()),
+                viewer.preferred_languages,
+            )
+        }
+        None => (false, Vec::new()),
+    };
+
+    let results = search_repo::search_posts(
+        &pool,
+        terms,
+        query.sub.as_deref(),
+        viewer.user_id(),
+        include_nsfw,
+        &languages,
+        query.sort,
+        search_limit(query.limit),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(SearchResults { results }))
+}
+
+/// Comments matching `?q=`, optionally on `?post_id=` or by `?user_id=`, for tracking
+/// down rule-breaking content. Includes comments already removed or held for review.
+/// Moderators only search the subs they moderate.
+#[get("/search/comments")]
+pub async fn search_comments(
+    pool: Data<PgPool>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
+    query: Query<CommentSearchQuery>,
+) -> Result<Json<SearchResults<CommentSearchHit>>, actix_web::Error> {
+    let moderator_id = moderation_scope(users.get_ref(), caller.user_id).await?;
+    let terms = search_terms(&query.q)?;
+
+    let results = search_repo::search_comments(
+        &pool,
+        terms,
+        query.post_id,
+        query.user_id,
+        moderator_id,
+        query.sort,
+        search_limit(query.limit),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(SearchResults { results }))
+}
+
+/// Users by name, forgiving typos. Ranked by karma after any exact match.
+#[get("/search/users")]
+pub async fn search_users(
+    pool: Data<PgPool>,
+    query: Query<NameSearchQuery>,
+) -> Result<Json<SearchResults<UserPublic>>, actix_web::Error> {
+    let terms = search_terms(&query.q)?;
+
+    let users = search_repo::search_users(&pool, terms, search_limit(query.limit))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(SearchResults {
+        results: users.into_iter().map(UserPublic::from).collect(),
+    }))
+}
+
+/// Subs by name, forgiving typos. Ranked by subscribers after any exact match. Private
+/// subs are only found by their members.
+#[get("/search/subs")]
+pub async fn search_subs(
+    pool: Data<PgPool>,
+    viewer: Viewer,
+    query: Query<NameSearchQuery>,
+) -> Result<Json<SearchResults<Sub>>, actix_web::Error> {
+    let terms = search_terms(&query.q)?;
+
+    let results =
+        search_repo::search_subs(&pool, terms, viewer.user_id(), search_limit(query.limit))
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(SearchResults { results }))
+}
+
+fn search_limit(limit: Option<i64>) -> i64 {
+    limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT)
+}
+
+fn search_terms(q: &str) -> Result<&str, ApiError> {
+    let terms = q.trim();
+    if terms.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "empty_search",
+            "A search needs at least one word to look for",
+        ));
+    }
+
+    Ok(terms)
+}
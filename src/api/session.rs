@@ -0,0 +1,45 @@
+use crate::auth::AuthenticatedUser;
+use crate::model::session::ActiveSession;
+use crate::repo::session as session_repo;
+use actix_web::{delete, get, web::Data, web::Json, web::Path, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Browser sessions and refresh token logins that haven't ended.
+#[get("/users/{user_id}/sessions")]
+pub async fn get_sessions(
+    pool: Data<PgPool>,
+    user: AuthenticatedUser,
+    path: Path<i32>,
+) -> Result<Json<Vec<ActiveSession>>, actix_web::Error> {
+    let user_id = path.into_inner();
+    user.require_self(user_id)?;
+
+    let sessions = session_repo::get_active_sessions(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(sessions))
+}
+
+/// Signs one device out. Access tokens it already holds keep working until they
+/// expire; `POST /auth/logout` with `all_devices` ends those too.
+#[delete("/users/{user_id}/sessions/{session_id}")]
+pub async fn delete_user_session(
+    pool: Data<PgPool>,
+    user: AuthenticatedUser,
+    path: Path<(i32, Uuid)>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (user_id, session_id) = path.into_inner();
+    user.require_self(user_id)?;
+    user.require_login()?;
+
+    let ended = session_repo::end_session(&pool, user_id, session_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !ended {
+        return Err(actix_web::error::ErrorNotFound("Session not found"));
+    }
+
+    Ok(HttpResponse::Ok().body(format!("Session {} has been ended", session_id)))
+}
@@ -1,105 +1,1337 @@
-use crate::model::sub::Sub;
-use crate::repo::sub as sub_repo;
-use actix_web::{delete, get, patch, post, put, web::Data, web::Json, web::Path, HttpResponse};
+use crate::api::account_requirement::require_account_standing;
+use crate::api::media::attachable_media;
+use crate::auth::role::require_sub_moderator;
+use crate::auth::{
+    Admin, AuthenticatedUser, ManageModerators, ManageSettings, ManageUsers, RequireRole,
+    RequireSubModerator, Viewer,
+};
+use crate::error::ApiError;
+use crate::model::account_requirement::Contribution;
+use crate::model::flair::normalize_color;
+use crate::model::sub::{
+    normalize_sub_name, sub_name_reserved, DiscoveryQuery, ModPermission, ModPermissions,
+    ModPermissionsUpdate, NewJoinRequest, NewModeratorInvite, NewSub, Sub, SubAppearance,
+    SubJoinRequest, SubListQuery, SubMember, SubModerator, SubModeratorInvite, SubSettingsUpdate,
+    SubUpdate, SubscriptionResult, MAX_JOIN_REQUEST_MESSAGE_CHARS, MAX_SUB_NAME_CHARS,
+    MIN_SUB_NAME_CHARS,
+};
+use crate::model::user::Role;
+use crate::repo::{sub as sub_repo, sub::SubRepository, user::UserRepository};
+use actix_web::{
+    delete, get, http::StatusCode, patch, post, put, web::Data, web::Json, web::Path, web::Query,
+    HttpResponse,
+};
 use chrono::Utc;
+use serde_json::json;
 use sqlx::PgPool;
+use uuid::Uuid;
 
+/// Any signed-in user whose account meets the site-wide requirements can start a sub and
+/// becomes its first moderator, with full permissions. Names are unique regardless of
+/// case.
 #[post("/subs")]
 pub async fn create_sub(
-    pool: Data<PgPool>,
-    body: Json<Sub>,
-) -> Result<HttpResponse, actix_web::Error> {
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    user: AuthenticatedUser,
+    body: Json<NewSub>,
+) -> Result<Json<Sub>, actix_web::Error> {
+    require_account_standing(
+        subs.get_ref(),
+        users.get_ref(),
+        user.user_id,
+        None,
+        Contribution::CreateSub,
+    )
+    .await?;
+    let body = body.into_inner();
+    let name = normalize_sub_name(&body.name).ok_or_else(invalid_sub_name_error)?;
+    let taken = subs
+        .sub_name_taken(&name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if taken || sub_name_reserved(&name) {
+        return Err(sub_exists_error().into());
+    }
+
     let new_sub = Sub {
-        name: body.name.clone(),
-        description: body.description.clone(),
+        name,
+        description: body.description,
         created_at: Utc::now(),
+        nsfw: body.nsfw,
+        spoiler_by_default: body.spoiler_by_default,
+        subscribers: 0,
+        visibility: body.visibility,
+        icon_url: None,
+        banner_url: None,
+        primary_color: None,
+        accent_color: None,
     };
+    subs.create_sub(&new_sub)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(db_error) if db_error.is_unique_violation() => sub_exists_error().into(),
+            _ => actix_web::error::ErrorInternalServerError(e),
+        })?;
+    subs.add_sub_moderator(
+        &new_sub.name,
+        user.user_id,
+        Some(user.user_id),
+        ModPermissions::FULL,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let sub_id = sub_repo::create_sub(&pool, &new_sub)
+    Ok(Json(new_sub))
+}
+
+/// Only members may subscribe to a private sub.
+#[put("/subs/{sub}/subscribe")]
+pub async fn subscribe_to_sub(
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    user: AuthenticatedUser,
+    path: Path<String>,
+) -> Result<Json<SubscriptionResult>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    get_readable_sub(
+        subs.get_ref(),
+        users.get_ref(),
+        &sub_name,
+        Some(user.user_id),
+    )
+    .await?;
+
+    subs.subscribe_user_to_sub(user.user_id, &sub_name)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().body(sub_id.to_string()))
+    subscription_result(subs.get_ref(), &sub_name, true).await
 }
 
-#[put("/subs/{sub_name}")]
-pub async fn subscribe_user_to_sub(
-    pool: Data<PgPool>,
+#[delete("/subs/{sub}/subscribe")]
+pub async fn unsubscribe_from_sub(
+    subs: Data<dyn SubRepository>,
+    user: AuthenticatedUser,
     path: Path<String>,
-    body: String,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<Json<SubscriptionResult>, actix_web::Error> {
     let sub_name = path.into_inner();
-    let user_id = match body.parse::<i32>() {
-        Ok(id) => id,
-        Err(_) => return Ok(HttpResponse::BadRequest().body("Invalid user ID")),
-    };
-    sub_repo::subscribe_user_to_sub(&pool, user_id, &sub_name)
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    subs.unsubscribe_user_from_sub(user.user_id, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    subscription_result(subs.get_ref(), &sub_name, false).await
+}
+
+async fn subscription_result(
+    subs: &dyn SubRepository,
+    sub_name: &str,
+    subscribed: bool,
+) -> Result<Json<SubscriptionResult>, actix_web::Error> {
+    let sub = subs
+        .get_sub_by_name(sub_name)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().body(format!("{} has been subscribed to {}", body, sub_name)))
+    Ok(Json(SubscriptionResult {
+        subscribed,
+        subscribers: sub.subscribers,
+    }))
 }
 
+/// Every sub, less the private ones the viewer isn't a member of.
 #[get("/subs")]
-pub async fn get_all_subs(pool: Data<PgPool>) -> Result<Json<Vec<Sub>>, actix_web::Error> {
-    let subs = sub_repo::get_all_subs(&pool)
+pub async fn get_all_subs(
+    subs: Data<dyn SubRepository>,
+    viewer: Viewer,
+    query: Query<SubListQuery>,
+) -> Result<Json<Vec<Sub>>, actix_web::Error> {
+    let subs = subs
+        .get_all_subs(query.sort, viewer.user_id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(subs))
+}
+
+/// Subs gaining subscribers and activity fastest for their size, as of the last time
+/// the background job counted them.
+#[get("/subs/trending")]
+pub async fn get_trending_subs(
+    pool: Data<PgPool>,
+    query: Query<DiscoveryQuery>,
+) -> Result<Json<Vec<Sub>>, actix_web::Error> {
+    let subs = sub_repo::get_trending_subs(&pool, query.limit())
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
     Ok(Json(subs))
 }
 
-#[get("/subs/for_user/{user_id}")]
-pub async fn get_subs_by_user_id(
+/// The busiest subs lately, as of the last time the background job counted them.
+#[get("/subs/popular")]
+pub async fn get_popular_subs(
     pool: Data<PgPool>,
+    query: Query<DiscoveryQuery>,
+) -> Result<Json<Vec<Sub>>, actix_web::Error> {
+    let subs = sub_repo::get_popular_subs(&pool, query.limit())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(subs))
+}
+
+/// The subs the account holder is subscribed to, most recently subscribed first.
+#[get("/users/{user_id}/subscriptions")]
+pub async fn get_user_subscriptions(
+    subs: Data<dyn SubRepository>,
+    caller: AuthenticatedUser,
     path: Path<i32>,
 ) -> Result<Json<Vec<Sub>>, actix_web::Error> {
     let user_id = path.into_inner();
+    caller.require_self(user_id)?;
 
-    let subs = sub_repo::get_subs_by_user_id(&pool, user_id)
+    let subs = subs
+        .get_subs_by_user_id(user_id)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
     Ok(Json(subs))
 }
 
 #[get("/subs/{name}")]
 pub async fn get_sub_by_name(
-    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
     path: Path<String>,
 ) -> Result<Json<Sub>, actix_web::Error> {
     let name = path.into_inner();
 
-    let sub = sub_repo::get_sub_by_name(&pool, &name)
-        .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+    let sub = get_readable_sub(subs.get_ref(), users.get_ref(), &name, viewer.user_id()).await?;
 
     Ok(Json(sub))
 }
 
-#[patch("/subs")]
+#[patch("/subs/{sub}")]
 pub async fn update_sub(
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManageSettings>,
+    path: Path<String>,
+    body: Json<SubUpdate>,
+) -> Result<Json<Sub>, actix_web::Error> {
+    let mut sub = subs
+        .get_sub_by_name(&path.into_inner())
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let body = body.into_inner();
+    if let Some(description) = body.description {
+        sub.description = description;
+    }
+    if let Some(nsfw) = body.nsfw {
+        sub.nsfw = nsfw;
+    }
+    if let Some(spoiler_by_default) = body.spoiler_by_default {
+        sub.spoiler_by_default = spoiler_by_default;
+    }
+    if let Some(visibility) = body.visibility {
+        sub.visibility = visibility;
+    }
+
+    subs.update_sub(&sub)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(sub))
+}
+
+/// The sub's icon and banner come from the moderator's own uploads; its theme colors are
+/// `#rrggbb`.
+#[patch("/subs/{sub}/settings")]
+pub async fn update_sub_settings(
     pool: Data<PgPool>,
-    body: Json<Sub>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let sub = body.into_inner();
-    let (name, description) = sub_repo::update_sub(&pool, &sub)
+    moderator: RequireSubModerator<ManageSettings>,
+    path: Path<String>,
+    body: Json<SubSettingsUpdate>,
+) -> Result<Json<Sub>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    let current = sub_repo::get_sub_appearance(&pool, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let body = body.into_inner();
+    let appearance = SubAppearance {
+        icon_media_id: settings_media(
+            &pool,
+            moderator.user_id,
+            current.icon_media_id,
+            body.icon_media_id,
+        )
+        .await?,
+        banner_media_id: settings_media(
+            &pool,
+            moderator.user_id,
+            current.banner_media_id,
+            body.banner_media_id,
+        )
+        .await?,
+        primary_color: settings_color(current.primary_color, body.primary_color)?,
+        accent_color: settings_color(current.accent_color, body.accent_color)?,
+    };
+
+    sub_repo::update_sub_appearance(&pool, &sub_name, &appearance)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let sub = pool
+        .get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(sub))
+}
+
+/// An upload only has to be the moderator's own when it is newly picked, so settings
+/// another moderator chose are kept as they are.
+async fn settings_media(
+    pool: &PgPool,
+    moderator_id: i32,
+    current: Option<Uuid>,
+    update: Option<Option<Uuid>>,
+) -> Result<Option<Uuid>, actix_web::Error> {
+    match update {
+        None => Ok(current),
+        Some(Some(media_id)) if Some(media_id) != current => {
+            attachable_media(pool, moderator_id, &[media_id]).await?;
+            Ok(Some(media_id))
+        }
+        Some(media_id) => Ok(media_id),
+    }
+}
+
+fn settings_color(
+    current: Option<String>,
+    update: Option<Option<String>>,
+) -> Result<Option<String>, ApiError> {
+    match update {
+        None => Ok(current),
+        Some(None) => Ok(None),
+        Some(Some(color)) => normalize_color(&color).map(Some).ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_theme_color",
+                "Theme colors must be hex colors such as #ff8800",
+            )
+        }),
+    }
+}
+
+#[get("/subs/{sub}/moderators")]
+pub async fn get_sub_moderators(
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    viewer: Viewer,
+    path: Path<String>,
+) -> Result<Json<Vec<SubModerator>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    get_readable_sub(subs.get_ref(), users.get_ref(), &sub_name, viewer.user_id()).await?;
+
+    sub_moderators(subs.get_ref(), &sub_name).await
+}
+
+/// Moderators of a sub with full permissions invite others to join them; nobody becomes a
+/// moderator until they accept. Inviting someone already invited changes nothing. Returns
+/// the pending invites.
+#[post("/subs/{sub}/moderators/invite")]
+pub async fn invite_sub_moderator(
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    moderator: RequireSubModerator<ManageModerators>,
+    path: Path<String>,
+    body: Json<NewModeratorInvite>,
+) -> Result<Json<Vec<SubModeratorInvite>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let user = users
+        .get_user_by_id(body.user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let moderates = subs
+        .get_moderator_permissions(user.id, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if moderates.is_some() {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "already_sub_moderator",
+            format!("{} already moderates {}", user.username, sub_name),
+        )
+        .with_arg("username", user.username)
+        .with_arg("sub", sub_name)
+        .into());
+    }
+
+    let permissions = body.permissions.unwrap_or(ModPermissions::FULL);
+    subs.invite_sub_moderator(&sub_name, user.id, moderator.user_id, permissions)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    sub_moderator_invites(subs.get_ref(), &sub_name).await
+}
+
+#[get("/subs/{sub}/moderators/invites")]
+pub async fn get_sub_moderator_invites(
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManageModerators>,
+    path: Path<String>,
+) -> Result<Json<Vec<SubModeratorInvite>>, actix_web::Error> {
+    sub_moderator_invites(subs.get_ref(), &path.into_inner()).await
+}
 
-    Ok(HttpResponse::Ok().body(format!("{} -> {}", name, description)))
+/// The sub's moderators revoke an invite; the invited user can decline it the same way.
+#[delete("/subs/{sub}/moderators/invites/{user_id}")]
+pub async fn revoke_sub_moderator_invite(
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
+    path: Path<(String, i32)>,
+) -> Result<Json<Vec<SubModeratorInvite>>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+    if caller.user_id != user_id {
+        require_sub_moderator(
+            users.get_ref(),
+            subs.get_ref(),
+            caller.user_id,
+            &sub_name,
+            ModPermission::Full,
+        )
+        .await?;
+    }
+
+    subs.take_sub_moderator_invite(&sub_name, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("No pending invite for that user"))?;
+
+    sub_moderator_invites(subs.get_ref(), &sub_name).await
+}
+
+/// Makes the caller a moderator of the sub, if they were invited. Returns the moderators.
+#[post("/subs/{sub}/moderators/accept")]
+pub async fn accept_sub_moderator_invite(
+    subs: Data<dyn SubRepository>,
+    caller: AuthenticatedUser,
+    path: Path<String>,
+) -> Result<Json<Vec<SubModerator>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+
+    let accepted = subs
+        .accept_sub_moderator_invite(&sub_name, caller.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !accepted {
+        return Err(actix_web::error::ErrorNotFound(
+            "You have no invite to moderate this sub",
+        ));
+    }
+
+    sub_moderators(subs.get_ref(), &sub_name).await
+}
+
+/// Replaces what the moderator may do in the sub.
+#[patch("/subs/{sub}/moderators/{user_id}")]
+pub async fn update_moderator_permissions(
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManageModerators>,
+    path: Path<(String, i32)>,
+    body: Json<ModPermissionsUpdate>,
+) -> Result<Json<Vec<SubModerator>>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+
+    let updated = subs
+        .set_moderator_permissions(&sub_name, user_id, body.permissions)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !updated {
+        return Err(actix_web::error::ErrorNotFound(
+            "User is not a moderator of this sub",
+        ));
+    }
+
+    sub_moderators(subs.get_ref(), &sub_name).await
+}
+
+/// Moderators can step down; removing anyone else takes full permissions.
+#[delete("/subs/{sub}/moderators/{user_id}")]
+pub async fn remove_sub_moderator(
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
+    path: Path<(String, i32)>,
+) -> Result<Json<Vec<SubModerator>>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+    if caller.user_id != user_id {
+        require_sub_moderator(
+            users.get_ref(),
+            subs.get_ref(),
+            caller.user_id,
+            &sub_name,
+            ModPermission::Full,
+        )
+        .await?;
+    }
+
+    let removed = subs
+        .remove_sub_moderator(&sub_name, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !removed {
+        return Err(actix_web::error::ErrorNotFound(
+            "User is not a moderator of this sub",
+        ));
+    }
+
+    sub_moderators(subs.get_ref(), &sub_name).await
 }
 
+/// Members approved to read and post in the sub when it's restricted or private, most
+/// recently approved first.
+#[get("/subs/{sub}/members")]
+pub async fn get_sub_members(
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManageUsers>,
+    path: Path<String>,
+) -> Result<Json<Vec<SubMember>>, actix_web::Error> {
+    sub_members(subs.get_ref(), &path.into_inner()).await
+}
+
+/// Approving someone who is already a member changes nothing; approving someone who asked
+/// to join settles their request. Returns the members.
+#[put("/subs/{sub}/members/{user_id}")]
+pub async fn approve_sub_member(
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    moderator: RequireSubModerator<ManageUsers>,
+    path: Path<(String, i32)>,
+) -> Result<Json<Vec<SubMember>>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+    subs.get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    subs.take_join_request(&sub_name, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    subs.approve_sub_member(&sub_name, user_id, moderator.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    sub_members(subs.get_ref(), &sub_name).await
+}
+
+/// Members can leave; removing anyone else takes the `users` permission.
+#[delete("/subs/{sub}/members/{user_id}")]
+pub async fn remove_sub_member(
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
+    path: Path<(String, i32)>,
+) -> Result<Json<Vec<SubMember>>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+    if caller.user_id != user_id {
+        require_sub_moderator(
+            users.get_ref(),
+            subs.get_ref(),
+            caller.user_id,
+            &sub_name,
+            ModPermission::Users,
+        )
+        .await?;
+    }
+
+    let removed = subs
+        .remove_sub_member(&sub_name, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !removed {
+        return Err(actix_web::error::ErrorNotFound(
+            "User is not a member of this sub",
+        ));
+    }
+
+    sub_members(subs.get_ref(), &sub_name).await
+}
+
+/// Asks a restricted or private sub's moderators to make the caller a member, with an
+/// optional note. Asking again keeps the first request. Returns the caller's request.
+#[post("/subs/{sub}/join-requests")]
+pub async fn request_to_join_sub(
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
+    path: Path<String>,
+    body: Option<Json<NewJoinRequest>>,
+) -> Result<Json<SubJoinRequest>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    let message = body.map(Json::into_inner).unwrap_or_default().message;
+    let sub = subs
+        .get_sub_by_name(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if sub.visibility.open_to_anyone() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "sub_open",
+            format!("Anyone can post in {} without joining", sub_name),
+        )
+        .with_arg("sub", sub_name)
+        .into());
+    }
+    if is_sub_member(
+        subs.get_ref(),
+        users.get_ref(),
+        &sub_name,
+        Some(caller.user_id),
+    )
+    .await?
+    {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "already_sub_member",
+            format!("You are already a member of {}", sub_name),
+        )
+        .with_arg("sub", sub_name)
+        .into());
+    }
+    let message = message.trim();
+    if message.chars().count() > MAX_JOIN_REQUEST_MESSAGE_CHARS {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "join_request_too_long",
+            format!(
+                "A join request's message can be at most {} characters",
+                MAX_JOIN_REQUEST_MESSAGE_CHARS
+            ),
+        )
+        .with_arg("max", MAX_JOIN_REQUEST_MESSAGE_CHARS.to_string())
+        .into());
+    }
+
+    subs.create_join_request(&sub_name, caller.user_id, message)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let request = subs
+        .get_join_requests(&sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .into_iter()
+        .find(|request| request.user_id == caller.user_id)
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("Join request went missing"))?;
+
+    Ok(Json(request))
+}
+
+/// Pending join requests, oldest first.
+#[get("/subs/{sub}/join-requests")]
+pub async fn get_join_requests(
+    subs: Data<dyn SubRepository>,
+    _moderator: RequireSubModerator<ManageUsers>,
+    path: Path<String>,
+) -> Result<Json<Vec<SubJoinRequest>>, actix_web::Error> {
+    join_requests(subs.get_ref(), &path.into_inner()).await
+}
+
+/// Makes the user a member and settles their request. Returns the requests still pending.
+#[post("/subs/{sub}/join-requests/{user_id}/approve")]
+pub async fn approve_join_request(
+    subs: Data<dyn SubRepository>,
+    moderator: RequireSubModerator<ManageUsers>,
+    path: Path<(String, i32)>,
+) -> Result<Json<Vec<SubJoinRequest>>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+
+    subs.take_join_request(&sub_name, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(join_request_not_found)?;
+    subs.approve_sub_member(&sub_name, user_id, moderator.user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    join_requests(subs.get_ref(), &sub_name).await
+}
+
+/// The sub's moderators deny a request; the user who made it can withdraw it the same way.
+/// Returns the requests still pending.
+#[delete("/subs/{sub}/join-requests/{user_id}")]
+pub async fn deny_join_request(
+    subs: Data<dyn SubRepository>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
+    path: Path<(String, i32)>,
+) -> Result<Json<Vec<SubJoinRequest>>, actix_web::Error> {
+    let (sub_name, user_id) = path.into_inner();
+    if caller.user_id != user_id {
+        require_sub_moderator(
+            users.get_ref(),
+            subs.get_ref(),
+            caller.user_id,
+            &sub_name,
+            ModPermission::Users,
+        )
+        .await?;
+    }
+
+    subs.take_join_request(&sub_name, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(join_request_not_found)?;
+
+    join_requests(subs.get_ref(), &sub_name).await
+}
+
+fn join_request_not_found() -> actix_web::Error {
+    actix_web::error::ErrorNotFound("No pending join request from that user")
+}
+
+async fn join_requests(
+    subs: &dyn SubRepository,
+    sub_name: &str,
+) -> Result<Json<Vec<SubJoinRequest>>, actix_web::Error> {
+    let requests = subs
+        .get_join_requests(sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(requests))
+}
+
+async fn sub_moderators(
+    subs: &dyn SubRepository,
+    sub_name: &str,
+) -> Result<Json<Vec<SubModerator>>, actix_web::Error> {
+    let moderators = subs
+        .get_sub_moderators(sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(moderators))
+}
+
+async fn sub_moderator_invites(
+    subs: &dyn SubRepository,
+    sub_name: &str,
+) -> Result<Json<Vec<SubModeratorInvite>>, actix_web::Error> {
+    let invites = subs
+        .get_sub_moderator_invites(sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(invites))
+}
+
+async fn sub_members(
+    subs: &dyn SubRepository,
+    sub_name: &str,
+) -> Result<Json<Vec<SubMember>>, actix_web::Error> {
+    let members = subs
+        .get_sub_members(sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(members))
+}
+
+/// Whether the viewer may read and post in the sub whatever its visibility: approved
+/// members, its moderators and admins may.
+async fn is_sub_member(
+    subs: &dyn SubRepository,
+    users: &dyn UserRepository,
+    sub_name: &str,
+    viewer_id: Option<i32>,
+) -> Result<bool, actix_web::Error> {
+    let Some(viewer_id) = viewer_id else {
+        return Ok(false);
+    };
+    let member = subs
+        .is_sub_member(sub_name, viewer_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if member {
+        return Ok(true);
+    }
+    let moderates = subs
+        .get_moderator_permissions(viewer_id, sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if moderates.is_some() {
+        return Ok(true);
+    }
+    let viewer = users
+        .get_user_by_id(viewer_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    Ok(viewer.role() == Role::Admin)
+}
+
+/// The sub, unless it's private and the viewer isn't one of its members.
+pub async fn get_readable_sub(
+    subs: &dyn SubRepository,
+    users: &dyn UserRepository,
+    sub_name: &str,
+    viewer_id: Option<i32>,
+) -> Result<Sub, actix_web::Error> {
+    let sub = subs
+        .get_sub_by_name(sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if !sub.visibility.readable_by_anyone()
+        && !is_sub_member(subs, users, sub_name, viewer_id).await?
+    {
+        return Err(ApiError::forbidden(
+            "private_sub",
+            format!("Only approved members can read {}", sub_name),
+        )
+        .with_arg("sub", sub_name.to_string())
+        .into());
+    }
+
+    Ok(sub)
+}
+
+/// The sub, if the user may post and comment in it: anyone may in public subs, only
+/// members in restricted and private ones, and nobody who is banned from it or whose
+/// account doesn't meet its requirements for the contribution. Banned users are told why
+/// and until when.
+pub async fn get_postable_sub(
+    subs: &dyn SubRepository,
+    users: &dyn UserRepository,
+    sub_name: &str,
+    user_id: i32,
+    contribution: Contribution,
+) -> Result<Sub, actix_web::Error> {
+    let sub = subs
+        .get_sub_by_name(sub_name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let ban = subs
+        .get_sub_ban(sub_name, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if let Some(ban) = ban {
+        return Err(ApiError::forbidden(
+            "banned_from_sub",
+            format!("You are banned from {}", sub_name),
+        )
+        .with_arg("sub", sub_name.to_string())
+        .with_field("ban", json!(ban))
+        .into());
+    }
+    if !sub.visibility.open_to_anyone()
+        && !is_sub_member(subs, users, sub_name, Some(user_id)).await?
+    {
+        return Err(ApiError::forbidden(
+            "sub_members_only",
+            format!("Only approved members can post in {}", sub_name),
+        )
+        .with_arg("sub", sub_name.to_string())
+        .into());
+    }
+    require_account_standing(subs, users, user_id, Some(sub_name), contribution).await?;
+
+    Ok(sub)
+}
+
+/// Takes the sub's posts, flairs and subscriptions with it.
 #[delete("/subs/{name}")]
 pub async fn delete_sub(
-    pool: Data<PgPool>,
+    subs: Data<dyn SubRepository>,
+    _admin: RequireRole<Admin>,
     path: Path<String>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let name = path.into_inner();
+    subs.get_sub_by_name(&name)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
 
-    sub_repo::delete_sub(&pool, name.clone())
+    subs.delete_sub(name.clone())
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
     Ok(HttpResponse::Ok().body(format!("{} was deleted", name)))
 }
+
+fn invalid_sub_name_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_sub_name",
+        format!(
+            "Sub names are {} to {} letters, digits or underscores, starting with a letter",
+            MIN_SUB_NAME_CHARS, MAX_SUB_NAME_CHARS
+        ),
+    )
+    .with_arg("min", MIN_SUB_NAME_CHARS.to_string())
+    .with_arg("max", MAX_SUB_NAME_CHARS.to_string())
+}
+
+fn sub_exists_error() -> ApiError {
+    ApiError::new(
+        StatusCode::CONFLICT,
+        "sub_exists",
+        "A sub with that name already exists",
+    )
+}
+
+#[cfg(test)]
+mod sub_api_tests {
+    use super::*;
+    use crate::auth::token::token_tests::test_keys;
+    use crate::model::sub::SubVisibility;
+    use crate::model::user::DbAddUser;
+    use crate::repo::{self, memory::InMemoryRepo};
+    use actix_web::{test, App};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_sub_names_are_validated_and_unique() {
+        let repo = Arc::new(InMemoryRepo::default());
+        create_user(&repo, "founder").await;
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(create_sub)
+                .service(get_sub_by_name),
+        )
+        .await;
+        let token = test_keys().issue(1).unwrap().access_token;
+        let create = |name: &str| {
+            test::TestRequest::post()
+                .uri("/subs")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_json(json!({ "name": name, "description": "Rust" }))
+                .to_request()
+        };
+
+        let response = test::call_service(&app, create(" rust ")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = test::call_service(&app, create("Rust")).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = test::call_service(&app, create("r")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = test::call_service(&app, create("Popular")).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let anonymous = test::TestRequest::post()
+            .uri("/subs")
+            .set_json(json!({ "name": "golang" }))
+            .to_request();
+        let response = test::call_service(&app, anonymous).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request = test::TestRequest::get().uri("/subs/rust").to_request();
+        let sub: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(sub["description"], "Rust");
+        let request = test::TestRequest::get().uri("/subs/golang").to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn create_user(repo: &InMemoryRepo, username: &str) -> i32 {
+        UserRepository::create_user(
+            repo,
+            &DbAddUser {
+                username: username.to_string(),
+                password_hash: String::new(),
+                created_at: Utc::now(),
+                email: None,
+                email_verified_at: None,
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_moderators_join_by_accepting_an_invite() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let founder = create_user(&repo, "founder").await;
+        let helper = create_user(&repo, "helper").await;
+        let other = create_user(&repo, "other").await;
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(create_sub)
+                .service(update_sub)
+                .service(get_sub_moderators)
+                .service(get_sub_moderator_invites)
+                .service(invite_sub_moderator)
+                .service(revoke_sub_moderator_invite)
+                .service(accept_sub_moderator_invite)
+                .service(update_moderator_permissions)
+                .service(remove_sub_moderator),
+        )
+        .await;
+        let auth = |user_id: i32| {
+            let token = test_keys().issue(user_id).unwrap().access_token;
+            ("Authorization", format!("Bearer {}", token))
+        };
+        for (user_id, name) in [(founder, "rust"), (other, "golang")] {
+            let request = test::TestRequest::post()
+                .uri("/subs")
+                .insert_header(auth(user_id))
+                .set_json(json!({ "name": name }))
+                .to_request();
+            test::call_service(&app, request).await;
+        }
+        let invite = |caller: i32, user_id: i32| {
+            test::TestRequest::post()
+                .uri("/subs/rust/moderators/invite")
+                .insert_header(auth(caller))
+                .set_json(json!({ "user_id": user_id }))
+                .to_request()
+        };
+        let accept = |caller: i32| {
+            test::TestRequest::post()
+                .uri("/subs/rust/moderators/accept")
+                .insert_header(auth(caller))
+                .to_request()
+        };
+        let describe = |caller: i32, sub: &str| {
+            test::TestRequest::patch()
+                .uri(&format!("/subs/{}", sub))
+                .insert_header(auth(caller))
+                .set_json(json!({ "description": "Updated" }))
+                .to_request()
+        };
+
+        let response = test::call_service(&app, invite(other, helper)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = test::call_service(&app, invite(founder, founder)).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = test::call_service(&app, accept(helper)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let request = test::TestRequest::post()
+            .uri("/subs/rust/moderators/invite")
+            .insert_header(auth(founder))
+            .set_json(json!({ "user_id": helper, "permissions": ["flair"] }))
+            .to_request();
+        let invites: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(invites[0]["username"], "helper");
+        assert_eq!(invites[0]["invited_by"], founder);
+        assert_eq!(invites[0]["permissions"], json!(["flair"]));
+        let response = test::call_service(&app, describe(helper, "rust")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let moderators: serde_json::Value =
+            test::call_and_read_body_json(&app, accept(helper)).await;
+        assert_eq!(moderators[0]["username"], "founder");
+        assert_eq!(moderators[1]["username"], "helper");
+        assert_eq!(moderators[0]["permissions"], json!(["full"]));
+        assert_eq!(moderators[1]["added_by"], founder);
+        let response = test::call_service(&app, accept(helper)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let request = test::TestRequest::get()
+            .uri("/subs/rust/moderators/invites")
+            .insert_header(auth(founder))
+            .to_request();
+        let invites: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(invites, json!([]));
+
+        let response = test::call_service(&app, describe(helper, "rust")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = test::call_service(&app, invite(helper, other)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let request = test::TestRequest::patch()
+            .uri(&format!("/subs/rust/moderators/{}", helper))
+            .insert_header(auth(founder))
+            .set_json(json!({ "permissions": ["flair", "settings"] }))
+            .to_request();
+        let moderators: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(moderators[1]["permissions"], json!(["settings", "flair"]));
+        let response = test::call_service(&app, describe(helper, "rust")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = test::call_service(&app, describe(helper, "golang")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        test::call_service(&app, invite(founder, other)).await;
+        let decline = test::TestRequest::delete()
+            .uri(&format!("/subs/rust/moderators/invites/{}", other))
+            .insert_header(auth(other))
+            .to_request();
+        let invites: serde_json::Value = test::call_and_read_body_json(&app, decline).await;
+        assert_eq!(invites, json!([]));
+        let response = test::call_service(&app, accept(other)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let remove = || {
+            test::TestRequest::delete()
+                .uri(&format!("/subs/rust/moderators/{}", helper))
+                .insert_header(auth(founder))
+                .to_request()
+        };
+        let response = test::call_service(&app, remove()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = test::call_service(&app, remove()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = test::call_service(&app, describe(helper, "rust")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_join_requests_are_approved_or_denied_by_moderators() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let founder = create_user(&repo, "founder").await;
+        let applicant = create_user(&repo, "applicant").await;
+        let other = create_user(&repo, "other").await;
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(create_sub)
+                .service(get_sub_members)
+                .service(request_to_join_sub)
+                .service(get_join_requests)
+                .service(approve_join_request)
+                .service(deny_join_request)
+                .service(get_sub_moderators)
+                .service(subscribe_to_sub),
+        )
+        .await;
+        let auth = |user_id: i32| {
+            let token = test_keys().issue(user_id).unwrap().access_token;
+            ("Authorization", format!("Bearer {}", token))
+        };
+        let create = |name: &str, visibility: &str| {
+            test::TestRequest::post()
+                .uri("/subs")
+                .insert_header(auth(founder))
+                .set_json(json!({ "name": name, "visibility": visibility }))
+                .to_request()
+        };
+        test::call_service(&app, create("rust", "private")).await;
+        test::call_service(&app, create("golang", "public")).await;
+        let join = |caller: i32, sub: &str| {
+            test::TestRequest::post()
+                .uri(&format!("/subs/{}/join-requests", sub))
+                .insert_header(auth(caller))
+                .set_json(json!({ "message": " I write Rust " }))
+                .to_request()
+        };
+        let approve = |caller: i32, user_id: i32| {
+            test::TestRequest::post()
+                .uri(&format!("/subs/rust/join-requests/{}/approve", user_id))
+                .insert_header(auth(caller))
+                .to_request()
+        };
+
+        let response = test::call_service(&app, join(applicant, "golang")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = test::call_service(&app, join(founder, "rust")).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let request: serde_json::Value =
+            test::call_and_read_body_json(&app, join(applicant, "rust")).await;
+        assert_eq!(request["message"], "I write Rust");
+        test::call_service(&app, join(other, "rust")).await;
+
+        let response = test::call_service(&app, approve(applicant, applicant)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let pending: serde_json::Value =
+            test::call_and_read_body_json(&app, approve(founder, applicant)).await;
+        assert_eq!(pending.as_array().unwrap().len(), 1);
+        assert_eq!(pending[0]["user_id"], other);
+        let response = test::call_service(&app, join(applicant, "rust")).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let deny = test::TestRequest::delete()
+            .uri(&format!("/subs/rust/join-requests/{}", other))
+            .insert_header(auth(founder))
+            .to_request();
+        let pending: serde_json::Value = test::call_and_read_body_json(&app, deny).await;
+        assert_eq!(pending, json!([]));
+        let response = test::call_service(&app, approve(founder, other)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let members = test::TestRequest::get()
+            .uri("/subs/rust/members")
+            .insert_header(auth(founder))
+            .to_request();
+        let members: serde_json::Value = test::call_and_read_body_json(&app, members).await;
+        assert_eq!(members.as_array().unwrap().len(), 1);
+        assert_eq!(members[0]["user_id"], applicant);
+
+        // Only members see who moderates a private sub,
+        let moderators = |caller: i32| {
+            test::TestRequest::get()
+                .uri("/subs/rust/moderators")
+                .insert_header(auth(caller))
+                .to_request()
+        };
+        let response = test::call_service(&app, moderators(other)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = test::call_service(&app, moderators(applicant)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // or may subscribe to it
+        let subscribe = |caller: i32| {
+            test::TestRequest::put()
+                .uri("/subs/rust/subscribe")
+                .insert_header(auth(caller))
+                .to_request()
+        };
+        let response = test::call_service(&app, subscribe(other)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let subscribed: serde_json::Value =
+            test::call_and_read_body_json(&app, subscribe(applicant)).await;
+        assert_eq!(subscribed["subscribers"], 1);
+    }
+
+    #[actix_web::test]
+    async fn test_banned_users_cannot_post_until_the_ban_expires() {
+        let repo = InMemoryRepo::default();
+        let founder = create_user(&repo, "founder").await;
+        let troll = create_user(&repo, "troll").await;
+        SubRepository::create_sub(
+            &repo,
+            &Sub {
+                name: "rust".to_string(),
+                description: String::new(),
+                created_at: Utc::now(),
+                nsfw: false,
+                spoiler_by_default: false,
+                subscribers: 0,
+                visibility: SubVisibility::Public,
+                icon_url: None,
+                banner_url: None,
+                primary_color: None,
+                accent_color: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        repo.ban_user_from_sub("rust", troll, founder, "Spam", None)
+            .await
+            .unwrap();
+        let error = get_postable_sub(&repo, &repo, "rust", troll, Contribution::Post)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.error_response().status(), StatusCode::FORBIDDEN);
+        assert!(
+            get_postable_sub(&repo, &repo, "rust", founder, Contribution::Post)
+                .await
+                .is_ok()
+        );
+
+        let expired = Utc::now() - chrono::Duration::minutes(1);
+        repo.ban_user_from_sub("rust", troll, founder, "Spam", Some(expired))
+            .await
+            .unwrap();
+        assert!(
+            get_postable_sub(&repo, &repo, "rust", troll, Contribution::Post)
+                .await
+                .is_ok()
+        );
+        assert!(repo.get_sub_bans("rust").await.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_subscriptions_are_counted_and_listed() {
+        let repo = Arc::new(InMemoryRepo::default());
+        for name in ["rust", "golang"] {
+            SubRepository::create_sub(
+                repo.as_ref(),
+                &Sub {
+                    name: name.to_string(),
+                    description: String::new(),
+                    created_at: Utc::now(),
+                    nsfw: false,
+                    spoiler_by_default: false,
+                    subscribers: 0,
+                    visibility: SubVisibility::Public,
+                    icon_url: None,
+                    banner_url: None,
+                    primary_color: None,
+                    accent_color: None,
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(subscribe_to_sub)
+                .service(unsubscribe_from_sub)
+                .service(get_user_subscriptions)
+                .service(get_all_subs),
+        )
+        .await;
+        let auth = |user_id: i32| {
+            let token = test_keys().issue(user_id).unwrap().access_token;
+            ("Authorization", format!("Bearer {}", token))
+        };
+        let subscribe = |user_id: i32, sub: &str| {
+            test::TestRequest::put()
+                .uri(&format!("/subs/{}/subscribe", sub))
+                .insert_header(auth(user_id))
+                .to_request()
+        };
+
+        test::call_service(&app, subscribe(1, "rust")).await;
+        test::call_service(&app, subscribe(1, "rust")).await;
+        let result: serde_json::Value =
+            test::call_and_read_body_json(&app, subscribe(2, "rust")).await;
+        assert_eq!(result, json!({ "subscribed": true, "subscribers": 2 }));
+        let response = test::call_service(&app, subscribe(1, "nope")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let names = |subs: serde_json::Value| {
+            subs.as_array()
+                .unwrap()
+                .iter()
+                .map(|sub| sub["name"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        let request = test::TestRequest::get().uri("/subs").to_request();
+        let subs = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(names(subs), ["golang", "rust"]);
+        let request = test::TestRequest::get().uri("/subs?sort=top").to_request();
+        let subs = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(names(subs), ["rust", "golang"]);
+
+        let unsubscribe = test::TestRequest::delete()
+            .uri("/subs/rust/subscribe")
+            .insert_header(auth(2))
+            .to_request();
+        let result: serde_json::Value = test::call_and_read_body_json(&app, unsubscribe).await;
+        assert_eq!(result, json!({ "subscribed": false, "subscribers": 1 }));
+
+        let request = test::TestRequest::get()
+            .uri("/users/1/subscriptions")
+            .insert_header(auth(1))
+            .to_request();
+        let subs: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(subs[0]["name"], "rust");
+        assert_eq!(subs[0]["subscribers"], 1);
+        let request = test::TestRequest::get()
+            .uri("/users/1/subscriptions")
+            .insert_header(auth(2))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::auth::{Admin, RequireRole};
+use crate::error::ApiError;
+use crate::model::moderation::ModAction;
+use crate::model::suspension::{NewSuspension, Suspension};
+use crate::model::user::Role;
+use crate::repo::{
+    moderation as moderation_repo, suspension as suspension_repo, user as user_repo,
+    user::UserRepository,
+};
+use actix_web::{
+    delete, get, http::StatusCode, post, put, web::Data, web::Json, web::Path, HttpResponse,
+};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::PgPool;
+
+/// Suspended accounts can still sign in and read, but every write is refused until
+/// `expires_at`, or for good without one. Suspending someone who is already suspended
+/// replaces their suspension. Admins can't be suspended.
+#[post("/admin/suspensions")]
+pub async fn suspend_user(
+    pool: Data<PgPool>,
+    users: Data<dyn UserRepository>,
+    admin: RequireRole<Admin>,
+    body: Json<NewSuspension>,
+) -> Result<Json<Suspension>, actix_web::Error> {
+    let user = users
+        .get_user_by_id(body.user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if body.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "suspension_expiry_in_past",
+            "A suspension can only be set to expire in the future",
+        )
+        .into());
+    }
+    if user.role() >= Role::Admin {
+        return Err(ApiError::forbidden(
+            "cannot_suspend_admin",
+            format!("{} is an admin and can't be suspended", user.username),
+        )
+        .with_arg("username", user.username)
+        .into());
+    }
+
+    let reason = body.reason.trim();
+    suspension_repo::suspend_user(&pool, user.id, admin.user_id, reason, body.expires_at)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    moderation_repo::log_mod_action(
+        &pool,
+        admin.user_id,
+        ModAction::SuspendUser,
+        Some(user.id),
+        None,
+        json!({ "reason": reason, "expires_at": body.expires_at }),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let suspension = suspension_repo::get_active_suspension(&pool, user.id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("Suspension went missing"))?;
+
+    Ok(Json(suspension))
+}
+
+/// Suspensions still in force, most recent first.
+#[get("/admin/suspensions")]
+pub async fn get_suspensions(
+    pool: Data<PgPool>,
+    _admin: RequireRole<Admin>,
+) -> Result<Json<Vec<Suspension>>, actix_web::Error> {
+    suspensions(&pool).await
+}
+
+/// Lifts the account's suspension early. Returns the suspensions still in force.
+#[delete("/admin/suspensions/{user_id}")]
+pub async fn lift_suspension(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<i32>,
+) -> Result<Json<Vec<Suspension>>, actix_web::Error> {
+    let user_id = path.into_inner();
+
+    let lifted = suspension_repo::lift_suspension(&pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !lifted {
+        return Err(actix_web::error::ErrorNotFound(
+            "This account is not suspended",
+        ));
+    }
+    moderation_repo::log_mod_action(
+        &pool,
+        admin.user_id,
+        ModAction::UnsuspendUser,
+        Some(user_id),
+        None,
+        json!({}),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    suspensions(&pool).await
+}
+
+/// Hides the user's posts and comments from everyone but themselves, moderators of the
+/// sub they are in and admins, without telling them.
+#[put("/admin/users/{user_id}/shadowban")]
+pub async fn shadowban_user(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    set_shadowbanned(&pool, admin.user_id, path.into_inner(), true).await
+}
+
+#[delete("/admin/users/{user_id}/shadowban")]
+pub async fn unshadowban_user(
+    pool: Data<PgPool>,
+    admin: RequireRole<Admin>,
+    path: Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    set_shadowbanned(&pool, admin.user_id, path.into_inner(), false).await
+}
+
+async fn set_shadowbanned(
+    pool: &PgPool,
+    admin_id: i32,
+    user_id: i32,
+    shadowbanned: bool,
+) -> Result<HttpResponse, actix_web::Error> {
+    let found = user_repo::set_shadowbanned(pool, user_id, shadowbanned)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !found {
+        return Err(actix_web::error::ErrorNotFound("User not found"));
+    }
+    let action = if shadowbanned {
+        ModAction::ShadowbanUser
+    } else {
+        ModAction::UnshadowbanUser
+    };
+    moderation_repo::log_mod_action(pool, admin_id, action, Some(user_id), None, json!({}))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn suspensions(pool: &PgPool) -> Result<Json<Vec<Suspension>>, actix_web::Error> {
+    let suspensions = suspension_repo::get_suspensions(pool)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(suspensions))
+}
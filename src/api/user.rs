@@ -1,164 +1,547 @@
-use crate::model::user::{DbAddUser, NewUser, User};
-use crate::repo::user as user_repo;
-use actix_web::{delete, get, patch, post, web::Data, web::Json, web::Path, HttpResponse};
-use chrono::Utc;
+use crate::api::filter::{content_blocked_error, load_filters};
+use crate::api::sub::get_readable_sub;
+use crate::auth::role::require_role;
+use crate::auth::{generate_opaque_token, hash_opaque_token, AuthenticatedUser, Viewer};
+use crate::config::{AuthConfig, EmailConfig};
+use crate::error::ApiError;
+use crate::mail::Mailer;
+use crate::model::dto::{UserPrivate, UserPublic};
+use crate::model::filter::apply_filters;
+use crate::model::karma::Karma;
+use crate::model::language::{normalize_language_tag, PreferredLanguages};
+use crate::model::password::PasswordPolicy;
+use crate::model::user::{DateOfBirth, DbAddUser, NewUser, NsfwPreference, Role, User};
+use crate::repo::email_verification as verification_repo;
+use crate::repo::karma as karma_repo;
+use crate::repo::sub::SubRepository;
+use crate::repo::user::UserRepository;
+use actix_web::{
+    delete, get, http::StatusCode, patch, post, put, web::Data, web::Json, web::Path, HttpResponse,
+};
+use chrono::{Duration, Utc};
 use sqlx::PgPool;
 
+/// Usernames are checked against the site-wide word filters. A username can't be
+/// masked or held for review, so any match rejects it. A verification link is mailed
+/// to the new address.
 #[post("/users")]
 pub async fn create_user(
+    users: Data<dyn UserRepository>,
     pool: Data<PgPool>,
+    mailer: Data<Mailer>,
+    email_config: Data<EmailConfig>,
+    password_policy: Data<PasswordPolicy>,
+    auth_config: Data<AuthConfig>,
     body: Json<NewUser>,
-) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    let hashed_password = User::hash_password(&body.password)
-        .map_err(|e| Box::<dyn std::error::Error>::from(e.to_string()))?;
+) -> Result<HttpResponse, actix_web::Error> {
+    let filters = load_filters(&pool, None).await?;
+    if apply_filters(&filters, &body.username, None)
+        .action
+        .is_some()
+    {
+        return Err(content_blocked_error().into());
+    }
+
+    let email = body
+        .email
+        .trim()
+        .parse::<lettre::Address>()
+        .map_err(|_| invalid_email_error())?
+        .to_string();
+
+    require_password_policy(&password_policy, &body.password)?;
+    let hashed_password = User::hash_password(&body.password, &auth_config.password_hashing)
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
 
     let user = DbAddUser {
         username: body.username.clone(),
         password_hash: hashed_password,
-        is_moderator: body.is_moderator,
         created_at: Utc::now(),
+        email: Some(email.clone()),
+        email_verified_at: None,
     };
 
-    let user_id = user_repo::create_user(&pool, &user)
+    let user_id = users
+        .create_user(&user)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(db_error) if db_error.constraint() == Some("users_email_key") => {
+                email_taken_error().into()
+            }
+            _ => actix_web::error::ErrorInternalServerError(e),
+        })?;
+
+    send_verification_email(&pool, mailer, &email_config, user_id, email).await?;
+
+    Ok(HttpResponse::Ok().body(format!("User ID {} has been created", user_id)))
+}
+
+/// Follows the link from a verification email.
+#[get("/users/verify/{token}")]
+pub async fn verify_email(
+    pool: Data<PgPool>,
+    path: Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token_hash = hash_opaque_token(&path.into_inner());
+
+    let user_id = verification_repo::verify_email(&pool, &token_hash)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                "invalid_verification_token",
+                "This verification link is invalid or has expired",
+            )
+        })?;
+
+    Ok(HttpResponse::Ok().body(format!(
+        "User ID {} email address has been verified",
+        user_id
+    )))
+}
+
+/// Sends a fresh verification link, replacing any earlier one.
+#[post("/users/{user_id}/verification_email")]
+pub async fn resend_verification_email(
+    users: Data<dyn UserRepository>,
+    pool: Data<PgPool>,
+    mailer: Data<Mailer>,
+    email_config: Data<EmailConfig>,
+    caller: AuthenticatedUser,
+    path: Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+    caller.require_self(user_id)?;
+
+    let user = users
+        .get_user_by_id(user_id)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if user.email_verified() {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "email_already_verified",
+            "This email address has already been verified",
+        )
+        .into());
+    }
+    let Some(email) = user.email else {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "email_missing",
+            "This account has no email address",
+        )
+        .into());
+    };
+
+    send_verification_email(&pool, mailer, &email_config, user_id, email).await?;
 
-    Ok(HttpResponse::Ok().body(format!("User ID {} has been created", user_id.to_string())))
+    Ok(HttpResponse::Ok().body(format!(
+        "User ID {} verification email has been sent",
+        user_id
+    )))
 }
 
 #[get("/users/id/{user_id}")]
 pub async fn get_user_by_id(
-    pool: Data<PgPool>,
+    users: Data<dyn UserRepository>,
     path: Path<i32>,
-) -> Result<Json<User>, actix_web::Error> {
+    viewer: Viewer,
+) -> Result<HttpResponse, actix_web::Error> {
     let user_id = path.into_inner();
 
-    let user = user_repo::get_user_by_id(&pool, user_id)
+    let user = users
+        .get_user_by_id(user_id)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(Json(user))
+    // Account holders see their own private details; everyone else gets the public view
+    if viewer.user_id() == Some(user_id) {
+        Ok(HttpResponse::Ok().json(UserPrivate::from(user)))
+    } else {
+        Ok(HttpResponse::Ok().json(UserPublic::from(user)))
+    }
+}
+
+/// Karma is public, like the account itself, but the per-sub breakdown leaves out
+/// private subs the viewer isn't a member of.
+#[get("/users/{user_id}/karma")]
+pub async fn get_user_karma(
+    users: Data<dyn UserRepository>,
+    pool: Data<PgPool>,
+    viewer: Viewer,
+    path: Path<i32>,
+) -> Result<Json<Karma>, actix_web::Error> {
+    let user_id = path.into_inner();
+    let user = users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let subs = karma_repo::get_sub_karma(&pool, user_id, viewer.user_id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(Karma {
+        user_id,
+        post_karma: user.post_karma,
+        comment_karma: user.comment_karma,
+        total: user.post_karma + user.comment_karma,
+        subs,
+    }))
 }
 
 #[get("/users/username/{username}")]
 pub async fn get_user_by_username(
-    pool: Data<PgPool>,
+    users: Data<dyn UserRepository>,
     path: Path<String>,
-) -> Result<Json<User>, actix_web::Error> {
+) -> Result<Json<UserPublic>, actix_web::Error> {
     let username = path.into_inner();
 
-    let user = user_repo::get_user_by_username(&pool, &username)
+    let user = users
+        .get_user_by_username(&username)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(Json(user))
+    Ok(Json(user.into()))
 }
 
+/// Members of a private sub are only listed to its members.
 #[get("/users/for_sub/{sub_name}")]
 pub async fn get_users_by_sub(
-    pool: Data<PgPool>,
+    users: Data<dyn UserRepository>,
+    subs: Data<dyn SubRepository>,
+    viewer: Viewer,
     path: Path<String>,
-) -> Result<Json<Vec<User>>, actix_web::Error> {
+) -> Result<Json<Vec<UserPublic>>, actix_web::Error> {
     let sub_name = path.into_inner();
+    get_readable_sub(subs.get_ref(), users.get_ref(), &sub_name, viewer.user_id()).await?;
 
-    let users = user_repo::get_users_by_sub(&pool, &sub_name)
+    let users = users
+        .get_users_by_sub(&sub_name)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(Json(users))
-}
-
-#[get("/users/auth/{user_id}")]
-pub async fn verify_user_password(
-    pool: Data<PgPool>,
-    path: Path<i32>,
-    body: String,
-) -> Result<Json<bool>, actix_web::Error> {
-    let user_id = path.into_inner();
-    let password_attempt = body;
-
-    let user = user_repo::get_user_by_id(&pool, user_id)
-        .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
-
-    let verified = user.verify_password(&password_attempt);
-
-    match verified {
-        Ok(true) => Ok(Json(true)),
-        Ok(false) => Ok(Json(false)),
-        Err(e) => Err(actix_web::error::ErrorInternalServerError(e)),
-    }
+    Ok(Json(users.into_iter().map(UserPublic::from).collect()))
 }
 
 #[get("/users/exists/{username}")]
 pub async fn username_exists(
-    pool: Data<PgPool>,
+    users: Data<dyn UserRepository>,
     path: Path<String>,
 ) -> Result<Json<bool>, actix_web::Error> {
     let username = path.into_inner();
 
-    match user_repo::username_exists(&pool, &username).await {
+    match users.username_exists(&username).await {
         Ok(Some(_user)) => Ok(Json(true)),
         Ok(None) => Ok(Json(false)),
         Err(e) => Err(actix_web::error::ErrorInternalServerError(e)),
     }
 }
 
-#[patch("/users/mods/add/{user_id}")]
-pub async fn grant_mod_status(
-    pool: Data<PgPool>,
+#[patch("/users/update/{user_id}")]
+pub async fn update_user_password(
+    users: Data<dyn UserRepository>,
+    password_policy: Data<PasswordPolicy>,
+    auth_config: Data<AuthConfig>,
+    user: AuthenticatedUser,
     path: Path<i32>,
+    body: String,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = path.into_inner();
+    user.require_self(user_id)?;
+    user.require_login()?;
+    require_password_policy(&password_policy, &body)?;
+    let new_password_hash = User::hash_password(&body, &auth_config.password_hashing)
+        .map_err(|e| Box::<dyn std::error::Error>::from(e.to_string()))?;
 
-    let user_id = user_repo::grant_mod_status(&pool, user_id)
+    let user_id = users
+        .update_user_password(user_id, &new_password_hash)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().body(format!("{} is now a moderator", user_id.to_string())))
+    Ok(HttpResponse::Ok().body(format!("User ID {} password has been updated", user_id)))
 }
 
-#[patch("/users/mods/remove/{user_id}")]
-pub async fn remove_mod_status(
-    pool: Data<PgPool>,
+#[put("/users/{user_id}/date_of_birth")]
+pub async fn set_date_of_birth(
+    users: Data<dyn UserRepository>,
+    user: AuthenticatedUser,
     path: Path<i32>,
+    body: Json<DateOfBirth>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = path.into_inner();
+    user.require_self(user_id)?;
 
-    let user_id = user_repo::remove_mod_status(&pool, user_id)
+    let user_id = users
+        .set_date_of_birth(user_id, body.date_of_birth)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().body(format!("{} is no longer a moderator", user_id.to_string())))
+    Ok(HttpResponse::Ok().body(format!("User ID {} date of birth has been set", user_id)))
 }
 
-#[patch("/users/update/{user_id}")]
-pub async fn update_user_password(
-    pool: Data<PgPool>,
+#[post("/users/{user_id}/nsfw_acknowledgement")]
+pub async fn acknowledge_nsfw(
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
     path: Path<i32>,
-    body: String,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = path.into_inner();
-    let new_password_hash = User::hash_password(&body)
-        .map_err(|e| Box::<dyn std::error::Error>::from(e.to_string()))?;
+    caller.require_self(user_id)?;
+
+    let user = users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if !user.is_adult(Utc::now().date_naive()) {
+        return Err(ApiError::forbidden(
+            "age_requirement_not_met",
+            "A date of birth showing the account holder is an adult is required",
+        )
+        .into());
+    }
+
+    let user_id = users
+        .acknowledge_nsfw(user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!("User ID {} has acknowledged NSFW content", user_id)))
+}
+
+#[put("/users/{user_id}/languages")]
+pub async fn set_preferred_languages(
+    users: Data<dyn UserRepository>,
+    user: AuthenticatedUser,
+    path: Path<i32>,
+    body: Json<PreferredLanguages>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+    user.require_self(user_id)?;
+
+    let mut languages = Vec::with_capacity(body.languages.len());
+    for tag in &body.languages {
+        let language = normalize_language_tag(tag).ok_or_else(|| unknown_language_error(tag))?;
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+
+    let user_id = users
+        .set_preferred_languages(user_id, &languages)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    let user_id = user_repo::update_user_password(&pool, user_id, &new_password_hash)
+    Ok(HttpResponse::Ok().body(format!(
+        "User ID {} preferred languages set to [{}]",
+        user_id,
+        languages.join(", ")
+    )))
+}
+
+/// Whether listings include NSFW posts when they aren't told either way. Has no effect
+/// until the account is cleared to see NSFW content.
+#[put("/users/{user_id}/nsfw_preference")]
+pub async fn set_nsfw_preference(
+    users: Data<dyn UserRepository>,
+    user: AuthenticatedUser,
+    path: Path<i32>,
+    body: Json<NsfwPreference>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+    user.require_self(user_id)?;
+
+    let user_id = users
+        .set_show_nsfw(user_id, body.show_nsfw)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().body(format!("User ID {} password has been updated", user_id.to_string())))
+    Ok(HttpResponse::Ok().body(format!(
+        "User ID {} NSFW listing preference set to {}",
+        user_id, body.show_nsfw
+    )))
 }
 
+/// Account holders can delete their own account; admins can delete anyone's.
 #[delete("/users/{user_id}")]
 pub async fn delete_user(
-    pool: Data<PgPool>,
+    users: Data<dyn UserRepository>,
+    caller: AuthenticatedUser,
     path: Path<i32>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = path.into_inner();
+    caller.require_login()?;
+    if caller.user_id != user_id {
+        require_role(users.get_ref(), caller.user_id, Role::Admin).await?;
+    }
 
-    let user_id = user_repo::delete_user(&pool, user_id)
+    let user_id = users
+        .delete_user(user_id)
         .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().body(format!("{} has been deleted", user_id)))
+}
+
+/// Whether NSFW content may be served to the viewer. Logged-out requests never qualify.
+pub async fn viewer_can_view_nsfw(
+    users: &dyn UserRepository,
+    viewer_id: Option<i32>,
+) -> Result<bool, actix_web::Error> {
+    let Some(viewer_id) = viewer_id else {
+        return Ok(false);
+    };
+
+    let viewer = users
+        .get_user_by_id(viewer_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    Ok(viewer.can_view_nsfw(Utc::now().date_naive()))
+}
+
+/// Whether a listing shows the viewer NSFW posts, going by `?include_nsfw=` or else
+/// their preference. Logged-out requests never see them.
+pub async fn viewer_lists_nsfw(
+    users: &dyn UserRepository,
+    viewer_id: Option<i32>,
+    requested: Option<bool>,
+) -> Result<bool, actix_web::Error> {
+    let Some(viewer_id) = viewer_id else {
+        return Ok(false);
+    };
+
+    let viewer = users
+        .get_user_by_id(viewer_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    Ok(viewer.lists_nsfw(requested, Utc::now().date_naive()))
+}
+
+pub async fn require_nsfw_clearance(
+    users: &dyn UserRepository,
+    viewer_id: Option<i32>,
+) -> Result<(), actix_web::Error> {
+    if viewer_can_view_nsfw(users, viewer_id).await? {
+        return Ok(());
+    }
+
+    Err(nsfw_gate_error().into())
+}
+
+/// Stores a verification link and mails it in the background, so a slow SMTP server
+/// doesn't hold up the request. Delivery failures are only logged.
+async fn send_verification_email(
+    pool: &PgPool,
+    mailer: Data<Mailer>,
+    config: &EmailConfig,
+    user_id: i32,
+    email: String,
+) -> Result<(), actix_web::Error> {
+    let token = generate_opaque_token();
+    let expires_at = Utc::now()
+        + Duration::from_std(config.verification_ttl)
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    verification_repo::create_verification(
+        pool,
+        &hash_opaque_token(&token),
+        user_id,
+        &email,
+        expires_at,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let body = format!(
+        "Confirm your email address for Ferris Forums by opening this link:\n\n{}/users/verify/{}\n",
+        config.public_url.trim_end_matches('/'),
+        token
+    );
+    actix_web::rt::spawn(async move {
+        if let Err(e) = mailer.send(&email, "Verify your email address", body).await {
+            log::error!(
+                "Failed to send verification email to user {}: {}",
+                user_id,
+                e
+            );
+        }
+    });
+
+    Ok(())
+}
+
+/// With `REQUIRE_VERIFIED_EMAIL` set, only accounts with a verified address may post
+/// or comment.
+pub async fn require_verified_email(
+    users: &dyn UserRepository,
+    config: &EmailConfig,
+    user_id: i32,
+) -> Result<(), actix_web::Error> {
+    if !config.require_verified_to_post {
+        return Ok(());
+    }
+
+    let user = users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    if user.email_verified() {
+        return Ok(());
+    }
+
+    Err(ApiError::forbidden(
+        "email_verification_required",
+        "Verify your email address before posting",
+    )
+    .into())
+}
+
+/// Rejects a new password that breaks the policy, listing each broken rule in
+/// `details`.
+fn require_password_policy(policy: &PasswordPolicy, password: &str) -> Result<(), ApiError> {
+    let violations = policy.check(password);
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    Err(ApiError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "weak_password",
+        "That password doesn't meet the password requirements",
+    )
+    .with_details(violations.iter().map(ToString::to_string).collect()))
+}
+
+pub fn invalid_email_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_email",
+        "That is not a valid email address",
+    )
+}
+
+pub fn email_taken_error() -> ApiError {
+    ApiError::new(
+        StatusCode::CONFLICT,
+        "email_taken",
+        "That email address is already in use",
+    )
+}
+
+pub fn nsfw_gate_error() -> ApiError {
+    ApiError::forbidden(
+        "nsfw_acknowledgement_required",
+        "This content is marked NSFW and requires an age-confirmed account",
+    )
+}
 
-    Ok(HttpResponse::Ok().body(format!("{} has been deleted", user_id.to_string())))
+pub fn unknown_language_error(tag: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "unknown_language",
+        format!("{} is not a recognised language tag", tag),
+    )
+    .with_arg("tag", tag)
 }
@@ -0,0 +1,225 @@
+use crate::api::sub::{get_postable_sub, get_readable_sub};
+use crate::auth::role::require_sub_moderator;
+use crate::auth::{AuthenticatedUser, ManageSettings, RequireSubModerator, Viewer};
+use crate::error::ApiError;
+use crate::model::account_requirement::Contribution;
+use crate::model::revision::{DiffQuery, RevisionDiff};
+use crate::model::sub::ModPermission;
+use crate::model::wiki::{
+    normalize_wiki_page_name, WikiEditors, WikiPage, WikiPageEdit, WikiPageSettings,
+    WikiPageSummary, WikiPageView, WikiRevision, MAX_WIKI_PAGE_CHARS, MAX_WIKI_PAGE_NAME_CHARS,
+};
+use crate::repo::{sub::SubRepository, wiki as wiki_repo};
+use actix_web::{get, http::StatusCode, patch, put, web::Data, web::Json, web::Path, web::Query};
+use sqlx::PgPool;
+
+#[get("/subs/{sub}/wiki")]
+pub async fn get_wiki_pages(
+    pool: Data<PgPool>,
+    viewer: Viewer,
+    path: Path<String>,
+) -> Result<Json<Vec<WikiPageSummary>>, actix_web::Error> {
+    let sub_name = path.into_inner();
+    get_readable_sub(pool.get_ref(), pool.get_ref(), &sub_name, viewer.user_id()).await?;
+
+    let pages = wiki_repo::get_wiki_pages(&pool, &sub_name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(pages))
+}
+
+#[get("/subs/{sub}/wiki/{page}")]
+pub async fn get_wiki_page(
+    pool: Data<PgPool>,
+    viewer: Viewer,
+    path: Path<(String, String)>,
+) -> Result<Json<WikiPageView>, actix_web::Error> {
+    let (sub_name, page) = path.into_inner();
+    get_readable_sub(pool.get_ref(), pool.get_ref(), &sub_name, viewer.user_id()).await?;
+
+    let page = existing_wiki_page(&pool, &sub_name, &page).await?;
+
+    Ok(Json(page.into()))
+}
+
+/// Saves the content as the page's next revision. Only moderators with the `settings`
+/// permission can start a page; after that the page's `editors` decide who else may edit
+/// it.
+#[put("/subs/{sub}/wiki/{page}")]
+pub async fn edit_wiki_page(
+    pool: Data<PgPool>,
+    editor: AuthenticatedUser,
+    path: Path<(String, String)>,
+    body: Json<WikiPageEdit>,
+) -> Result<Json<WikiPageView>, actix_web::Error> {
+    let (sub_name, page) = path.into_inner();
+    let page = normalize_wiki_page_name(&page).ok_or_else(invalid_wiki_page_name_error)?;
+    if body.content.chars().count() > MAX_WIKI_PAGE_CHARS {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "wiki_page_too_long",
+            format!(
+                "Wiki pages can be at most {} characters",
+                MAX_WIKI_PAGE_CHARS
+            ),
+        )
+        .with_arg("max", MAX_WIKI_PAGE_CHARS.to_string())
+        .into());
+    }
+    get_readable_sub(
+        pool.get_ref(),
+        pool.get_ref(),
+        &sub_name,
+        Some(editor.user_id),
+    )
+    .await?;
+    let existing = wiki_repo::get_wiki_page(&pool, &sub_name, &page)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    require_wiki_editor(&pool, &sub_name, existing.as_ref(), editor.user_id).await?;
+
+    let saved = wiki_repo::save_wiki_page(
+        &pool,
+        &sub_name,
+        &page,
+        &body.content,
+        body.reason.trim(),
+        editor.user_id,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(saved.into()))
+}
+
+/// Chooses who besides moderators may edit the page.
+#[patch("/subs/{sub}/wiki/{page}/settings")]
+pub async fn update_wiki_page_settings(
+    pool: Data<PgPool>,
+    _moderator: RequireSubModerator<ManageSettings>,
+    path: Path<(String, String)>,
+    body: Json<WikiPageSettings>,
+) -> Result<Json<WikiPageView>, actix_web::Error> {
+    let (sub_name, page) = path.into_inner();
+    let page = normalize_wiki_page_name(&page).ok_or_else(wiki_page_not_found)?;
+
+    let page = wiki_repo::set_wiki_page_editors(&pool, &sub_name, &page, body.editors)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(wiki_page_not_found)?;
+
+    Ok(Json(page.into()))
+}
+
+/// Every revision of the page, newest first.
+#[get("/subs/{sub}/wiki/{page}/revisions")]
+pub async fn get_wiki_revisions(
+    pool: Data<PgPool>,
+    viewer: Viewer,
+    path: Path<(String, String)>,
+) -> Result<Json<Vec<WikiRevision>>, actix_web::Error> {
+    let (sub_name, page) = path.into_inner();
+    get_readable_sub(pool.get_ref(), pool.get_ref(), &sub_name, viewer.user_id()).await?;
+    let page = existing_wiki_page(&pool, &sub_name, &page).await?;
+
+    let revisions = wiki_repo::get_wiki_revisions(&pool, &sub_name, &page.page)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(revisions))
+}
+
+#[get("/subs/{sub}/wiki/{page}/revisions/{from}/diff/{to}")]
+pub async fn get_wiki_revision_diff(
+    pool: Data<PgPool>,
+    viewer: Viewer,
+    path: Path<(String, String, i32, i32)>,
+    query: Query<DiffQuery>,
+) -> Result<Json<RevisionDiff>, actix_web::Error> {
+    let (sub_name, page, from, to) = path.into_inner();
+    get_readable_sub(pool.get_ref(), pool.get_ref(), &sub_name, viewer.user_id()).await?;
+    let page = normalize_wiki_page_name(&page).ok_or_else(wiki_page_not_found)?;
+
+    let mut revisions = Vec::with_capacity(2);
+    for revision in [from, to] {
+        let revision = wiki_repo::get_wiki_revision(&pool, &sub_name, &page, revision)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .ok_or_else(|| actix_web::error::ErrorNotFound("Revision not found"))?;
+        revisions.push(revision.into());
+    }
+
+    Ok(Json(RevisionDiff::between(
+        &revisions[0],
+        &revisions[1],
+        query.granularity,
+    )))
+}
+
+async fn existing_wiki_page(
+    pool: &PgPool,
+    sub_name: &str,
+    page: &str,
+) -> Result<WikiPage, actix_web::Error> {
+    let page = normalize_wiki_page_name(page).ok_or_else(wiki_page_not_found)?;
+    let page = wiki_repo::get_wiki_page(pool, sub_name, &page)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(wiki_page_not_found)?;
+
+    Ok(page)
+}
+
+/// Moderators with the `settings` permission may always edit; anyone else only pages that
+/// already exist and let them, and only while they may post in the sub.
+async fn require_wiki_editor(
+    pool: &PgPool,
+    sub_name: &str,
+    page: Option<&WikiPage>,
+    user_id: i32,
+) -> Result<(), actix_web::Error> {
+    let moderates =
+        require_sub_moderator(pool, pool, user_id, sub_name, ModPermission::Settings).await;
+    let editors = match page {
+        Some(page) if moderates.is_err() => page.editors,
+        _ => return moderates.map(|_| ()),
+    };
+    if editors == WikiEditors::Moderators {
+        return moderates.map(|_| ());
+    }
+
+    get_postable_sub(pool, pool, sub_name, user_id, Contribution::Post).await?;
+    if editors == WikiEditors::Members {
+        let member = pool
+            .is_sub_member(sub_name, user_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        if !member {
+            return Err(ApiError::forbidden(
+                "wiki_members_only",
+                format!("Only approved members of {} can edit this page", sub_name),
+            )
+            .with_arg("sub", sub_name.to_string())
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+fn wiki_page_not_found() -> actix_web::Error {
+    actix_web::error::ErrorNotFound("Wiki page not found")
+}
+
+fn invalid_wiki_page_name_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "invalid_wiki_page_name",
+        format!(
+            "Wiki page names are 1 to {} letters, digits, underscores or hyphens",
+            MAX_WIKI_PAGE_NAME_CHARS
+        ),
+    )
+    .with_arg("max", MAX_WIKI_PAGE_NAME_CHARS.to_string())
+}
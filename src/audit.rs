@@ -0,0 +1,62 @@
+use crate::auth::authenticate;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use sqlx::postgres::{PgConnection, PgPoolOptions};
+use std::future::Future;
+
+// The audit triggers record `changed_by` from the connection's `ferris.actor_id`
+// setting. Requests run under their caller's id, and every connection handed out by
+// the pool is told whose request it is serving, or that it serves none.
+
+tokio::task_local! {
+    static ACTOR_ID: Option<i32>;
+}
+
+/// Runs `work` on behalf of `actor`, so the changes it makes are audited as theirs.
+pub async fn as_actor<F: Future>(actor: Option<i32>, work: F) -> F::Output {
+    ACTOR_ID.scope(actor, work).await
+}
+
+/// Reads don't change audited rows, so only writes look up who is making them.
+/// Requests with invalid credentials are left for the handler to reject.
+pub async fn record_actor(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if req.method().is_safe() {
+        return next.call(req).await;
+    }
+
+    let actor = authenticate(req.request())
+        .await
+        .ok()
+        .flatten()
+        .map(|user| user.user_id);
+    as_actor(actor, next.call(req)).await
+}
+
+/// Pool options that set `ferris.actor_id` on each connection as it is handed out.
+pub fn pool_options() -> PgPoolOptions {
+    PgPoolOptions::new()
+        .after_connect(|conn, _| Box::pin(set_actor(conn)))
+        .before_acquire(|conn, _| {
+            Box::pin(async move {
+                set_actor(conn).await?;
+                Ok(true)
+            })
+        })
+}
+
+async fn set_actor(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    let actor = ACTOR_ID.try_with(|actor| *actor).ok().flatten();
+
+    sqlx::query!(
+        r#"SELECT set_config('ferris.actor_id', $1, false) AS "setting!""#,
+        actor.map(|id| id.to_string()).unwrap_or_default()
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,281 @@
+//! Authentication. API clients send a bearer token from `POST /auth/login`; browsers
+//! can use a session cookie from `POST /auth/session` instead, and bots an API key as
+//! the bearer token. Handlers take `AuthenticatedUser` to require any of them, or
+//! `Viewer` when signing in is optional.
+
+pub mod role;
+pub mod session;
+pub mod token;
+
+use crate::error::ApiError;
+use crate::model::api_key::{hash_api_key, ApiScope, KEY_PREFIX};
+use crate::repo::{
+    api_key as api_key_repo, revocation as revocation_repo, suspension as suspension_repo,
+};
+use actix_web::dev::Payload;
+use actix_web::http::header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::http::{Method, StatusCode};
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest};
+use chrono::DateTime;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+pub use role::{
+    Admin, ManageFlair, ManageModerators, ManagePosts, ManageSettings, ManageUsers, Moderator,
+    RequireRole, RequireSubModerator,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::future::Future;
+use std::pin::Pin;
+pub use token::JwtKeys;
+
+/// Session lookups hit the database, so the extractors resolve asynchronously.
+type AuthFuture<T> = Pin<Box<dyn Future<Output = Result<T, actix_web::Error>>>>;
+
+/// Writes a suspended account may still make, so it can sign out and appeal.
+const SUSPENSION_EXEMPT_PATHS: [&str; 2] = ["/auth/logout", "/appeals"];
+
+/// Length of session and refresh tokens, about 256 bits of randomness.
+pub const OPAQUE_TOKEN_LENGTH: usize = 43;
+
+/// A random token for a session cookie or refresh token.
+pub fn generate_opaque_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(OPAQUE_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Opaque tokens are stored as a SHA-256, so a database leak doesn't hand out live
+/// credentials.
+pub fn hash_opaque_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The account a request was made by, taken from a valid bearer token, API key or
+/// session cookie.
+pub struct AuthenticatedUser {
+    pub user_id: i32,
+    /// Set when the request was signed with one of the user's API keys.
+    pub api_key_id: Option<i64>,
+}
+
+impl AuthenticatedUser {
+    /// Rejects requests that act on an account other than the caller's own.
+    pub fn require_self(&self, user_id: i32) -> Result<(), ApiError> {
+        if self.user_id == user_id {
+            return Ok(());
+        }
+
+        Err(ApiError::forbidden(
+            "not_account_owner",
+            "You can only do this for your own account",
+        ))
+    }
+
+    /// Rejects API keys for account changes, so a leaked key can't be used to mint
+    /// more keys or take over the account.
+    pub fn require_login(&self) -> Result<(), ApiError> {
+        if self.api_key_id.is_none() {
+            return Ok(());
+        }
+
+        Err(ApiError::forbidden(
+            "login_required",
+            "API keys can't be used for this, sign in instead",
+        ))
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = actix_web::Error;
+    type Future = AuthFuture<Self>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            authenticate(&req)
+                .await?
+                .ok_or_else(|| authentication_required_error().into())
+        })
+    }
+}
+
+/// The caller of an endpoint that works signed in or not. Credentials that are present
+/// but invalid are still rejected, so clients notice expired tokens and sessions.
+pub struct Viewer(pub Option<AuthenticatedUser>);
+
+impl Viewer {
+    pub fn user_id(&self) -> Option<i32> {
+        self.0.as_ref().map(|user| user.user_id)
+    }
+}
+
+impl FromRequest for Viewer {
+    type Error = actix_web::Error;
+    type Future = AuthFuture<Self>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move { authenticate(&req).await.map(Viewer) })
+    }
+}
+
+/// The token from the `Authorization: Bearer` header, if one was sent.
+pub fn bearer_token(req: &HttpRequest) -> Result<Option<&str>, ApiError> {
+    let Some(header) = req.headers().get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    header
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| Some(token.trim()))
+        .ok_or_else(invalid_token_error)
+}
+
+/// A bearer token takes precedence over a session cookie.
+pub async fn authenticate(
+    req: &HttpRequest,
+) -> Result<Option<AuthenticatedUser>, actix_web::Error> {
+    let user = if let Some(token) = bearer_token(req)? {
+        if token.starts_with(KEY_PREFIX) {
+            api_key_user(req, token).await?
+        } else {
+            jwt_user(req, token).await?
+        }
+    } else {
+        let Some(session) = session::session_user(req).await? else {
+            return Ok(None);
+        };
+        AuthenticatedUser {
+            user_id: session.user_id,
+            api_key_id: None,
+        }
+    };
+
+    reject_suspended_writes(req, user.user_id).await?;
+    Ok(Some(user))
+}
+
+/// Suspended accounts can read and sign out but not write. Like revocations, this is
+/// only checked when the app has a database.
+async fn reject_suspended_writes(req: &HttpRequest, user_id: i32) -> Result<(), actix_web::Error> {
+    let read = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if read || SUSPENSION_EXEMPT_PATHS.contains(&req.path()) {
+        return Ok(());
+    }
+    let Some(pool) = req.app_data::<Data<PgPool>>() else {
+        return Ok(());
+    };
+
+    let suspension = suspension_repo::get_active_suspension(pool, user_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let Some(suspension) = suspension else {
+        return Ok(());
+    };
+    Err(
+        ApiError::forbidden("account_suspended", "This account is suspended")
+            .with_field("suspension", json!(suspension))
+            .into(),
+    )
+}
+
+/// Tokens revoked by signing out are rejected like expired ones. Apps without a
+/// database, such as handler tests, have no revocations to check.
+async fn jwt_user(req: &HttpRequest, token: &str) -> Result<AuthenticatedUser, actix_web::Error> {
+    let keys = req
+        .app_data::<Data<JwtKeys>>()
+        .expect("JwtKeys must be registered as app data");
+    let claims = keys.verify(token).map_err(|_| invalid_token_error())?;
+    let user_id = claims.sub.parse().map_err(|_| invalid_token_error())?;
+
+    if let Some(pool) = req.app_data::<Data<PgPool>>() {
+        let issued_at = DateTime::from_timestamp(claims.iat, 0).ok_or_else(invalid_token_error)?;
+        let revoked =
+            revocation_repo::is_access_token_revoked(pool, claims.jti, user_id, issued_at)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+        if revoked {
+            return Err(invalid_token_error().into());
+        }
+    }
+
+    Ok(AuthenticatedUser {
+        user_id,
+        api_key_id: None,
+    })
+}
+
+/// Reads need a key with the `read` scope and anything else `write`.
+async fn api_key_user(
+    req: &HttpRequest,
+    token: &str,
+) -> Result<AuthenticatedUser, actix_web::Error> {
+    let pool = req
+        .app_data::<Data<PgPool>>()
+        .expect("PgPool must be registered as app data");
+    let key = api_key_repo::get_api_key(pool, &hash_api_key(token))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(invalid_token_error)?;
+
+    let scope = match *req.method() {
+        Method::GET | Method::HEAD | Method::OPTIONS => ApiScope::Read,
+        _ => ApiScope::Write,
+    };
+    if !key.allows(scope) {
+        return Err(insufficient_scope_error(scope).into());
+    }
+
+    Ok(AuthenticatedUser {
+        user_id: key.user_id,
+        api_key_id: Some(key.id),
+    })
+}
+
+fn authentication_required_error() -> ApiError {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        "authentication_required",
+        "Sign in to do this",
+    )
+    .with_headers(vec![(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))])
+}
+
+fn insufficient_scope_error(scope: ApiScope) -> ApiError {
+    ApiError::new(
+        StatusCode::FORBIDDEN,
+        "insufficient_scope",
+        format!("This API key needs the {} scope to do this", scope),
+    )
+    .with_arg("scope", scope.to_string())
+    .with_headers(vec![(
+        WWW_AUTHENTICATE,
+        HeaderValue::from_str(&format!(
+            r#"Bearer error="insufficient_scope", scope="{}""#,
+            scope
+        ))
+        .expect("scope names are valid header values"),
+    )])
+}
+
+fn invalid_token_error() -> ApiError {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        "invalid_token",
+        "The access token is invalid or has expired",
+    )
+    .with_headers(vec![(
+        WWW_AUTHENTICATE,
+        HeaderValue::from_static(r#"Bearer error="invalid_token""#),
+    )])
+}
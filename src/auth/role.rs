@@ -0,0 +1,271 @@
+use super::{AuthFuture, AuthenticatedUser};
+use crate::error::ApiError;
+use crate::model::sub::ModPermission;
+use crate::model::user::{Role, User};
+use crate::repo::{sub::SubRepository, user::UserRepository};
+use actix_web::dev::Payload;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest};
+use std::marker::PhantomData;
+
+/// A role that a handler can demand through `RequireRole`.
+pub trait RequiredRole {
+    const ROLE: Role;
+}
+
+pub struct Moderator;
+
+impl RequiredRole for Moderator {
+    const ROLE: Role = Role::Moderator;
+}
+
+pub struct Admin;
+
+impl RequiredRole for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+/// The signed-in caller, who holds at least role `R`. Handlers take
+/// `RequireRole<Moderator>` or `RequireRole<Admin>`; anyone below gets a 403.
+pub struct RequireRole<R: RequiredRole> {
+    pub user_id: i32,
+    required: PhantomData<R>,
+}
+
+impl<R: RequiredRole + 'static> FromRequest for RequireRole<R> {
+    type Error = actix_web::Error;
+    type Future = AuthFuture<Self>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let caller = AuthenticatedUser::from_request(req, payload);
+        let users = req
+            .app_data::<Data<dyn UserRepository>>()
+            .expect("UserRepository must be registered as app data")
+            .clone();
+
+        Box::pin(async move {
+            let caller = caller.await?;
+            let user = require_role(users.get_ref(), caller.user_id, R::ROLE).await?;
+
+            Ok(RequireRole {
+                user_id: user.id,
+                required: PhantomData,
+            })
+        })
+    }
+}
+
+/// For checks that depend on the request, such as letting users delete their own
+/// account but requiring an admin to delete anyone else's.
+pub async fn require_role(
+    users: &dyn UserRepository,
+    user_id: i32,
+    role: Role,
+) -> Result<User, actix_web::Error> {
+    let user = users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    if user.role() < role {
+        return Err(ApiError::forbidden(
+            "role_required",
+            format!("This action requires the {} role", role),
+        )
+        .with_arg("role", role.to_string())
+        .into());
+    }
+
+    Ok(user)
+}
+
+/// A moderator permission that a handler can demand through `RequireSubModerator`.
+pub trait RequiredPermission {
+    const PERMISSION: ModPermission;
+}
+
+pub struct ManagePosts;
+
+impl RequiredPermission for ManagePosts {
+    const PERMISSION: ModPermission = ModPermission::Posts;
+}
+
+pub struct ManageUsers;
+
+impl RequiredPermission for ManageUsers {
+    const PERMISSION: ModPermission = ModPermission::Users;
+}
+
+pub struct ManageSettings;
+
+impl RequiredPermission for ManageSettings {
+    const PERMISSION: ModPermission = ModPermission::Settings;
+}
+
+pub struct ManageFlair;
+
+impl RequiredPermission for ManageFlair {
+    const PERMISSION: ModPermission = ModPermission::Flair;
+}
+
+/// Managing the sub's moderators, which takes `full` permissions.
+pub struct ManageModerators;
+
+impl RequiredPermission for ManageModerators {
+    const PERMISSION: ModPermission = ModPermission::Full;
+}
+
+/// The signed-in caller, who moderates the `{sub}` in the request path with permission
+/// `P`, or is an admin. Anyone else gets a 403.
+pub struct RequireSubModerator<P: RequiredPermission> {
+    pub user_id: i32,
+    required: PhantomData<P>,
+}
+
+impl<P: RequiredPermission + 'static> FromRequest for RequireSubModerator<P> {
+    type Error = actix_web::Error;
+    type Future = AuthFuture<Self>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let caller = AuthenticatedUser::from_request(req, payload);
+        let users = req
+            .app_data::<Data<dyn UserRepository>>()
+            .expect("UserRepository must be registered as app data")
+            .clone();
+        let subs = req
+            .app_data::<Data<dyn SubRepository>>()
+            .expect("SubRepository must be registered as app data")
+            .clone();
+        let sub = req
+            .match_info()
+            .get("sub")
+            .expect("RequireSubModerator needs a {sub} path segment")
+            .to_string();
+
+        Box::pin(async move {
+            let caller = caller.await?;
+            let user = require_sub_moderator(
+                users.get_ref(),
+                subs.get_ref(),
+                caller.user_id,
+                &sub,
+                P::PERMISSION,
+            )
+            .await?;
+
+            Ok(RequireSubModerator {
+                user_id: user.id,
+                required: PhantomData,
+            })
+        })
+    }
+}
+
+/// For checks on a sub that isn't in the path, such as the sub of the post being
+/// locked. Admins moderate every sub with full permissions.
+pub async fn require_sub_moderator(
+    users: &dyn UserRepository,
+    subs: &dyn SubRepository,
+    user_id: i32,
+    sub: &str,
+    permission: ModPermission,
+) -> Result<User, actix_web::Error> {
+    let user = users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+
+    if user.role() == Role::Admin {
+        return Ok(user);
+    }
+    let permissions = subs
+        .get_moderator_permissions(user.id, sub)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| {
+            ApiError::forbidden(
+                "sub_moderator_required",
+                format!("Only moderators of {} can do this", sub),
+            )
+            .with_arg("sub", sub.to_string())
+        })?;
+    if !permissions.allows(permission) {
+        return Err(ApiError::forbidden(
+            "mod_permission_required",
+            format!(
+                "Moderators of {} need the {} permission for this",
+                sub, permission
+            ),
+        )
+        .with_arg("sub", sub.to_string())
+        .with_arg("permission", permission.to_string())
+        .into());
+    }
+
+    Ok(user)
+}
+
+/// For listings that span subs, such as the held queue or the mod log. Returns the
+/// moderator whose subs the listing is limited to, or `None` for admins, who see all.
+pub async fn moderation_scope(
+    users: &dyn UserRepository,
+    user_id: i32,
+) -> Result<Option<i32>, actix_web::Error> {
+    let user = require_role(users, user_id, Role::Moderator).await?;
+
+    Ok((user.role() < Role::Admin).then_some(user.id))
+}
+
+#[cfg(test)]
+mod role_tests {
+    use super::*;
+    use crate::auth::token::token_tests::test_keys;
+    use crate::model::sub::ModPermissions;
+    use crate::model::user::DbAddUser;
+    use crate::repo::{self, memory::InMemoryRepo};
+    use actix_web::http::StatusCode;
+    use actix_web::{get, test, App, HttpResponse};
+    use chrono::Utc;
+    use std::sync::Arc;
+
+    #[get("/admin/ping")]
+    async fn admin_ping(admin: RequireRole<Admin>) -> HttpResponse {
+        HttpResponse::Ok().body(admin.user_id.to_string())
+    }
+
+    #[actix_web::test]
+    async fn test_moderators_are_not_admins() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let user_id = UserRepository::create_user(
+            &*repo,
+            &DbAddUser {
+                username: "mod".to_string(),
+                password_hash: String::new(),
+                created_at: Utc::now(),
+                email: None,
+                email_verified_at: None,
+            },
+        )
+        .await
+        .unwrap();
+        repo.add_sub_moderator("rust", user_id, None, ModPermissions::FULL)
+            .await
+            .unwrap();
+        let token = test_keys().issue(user_id).unwrap().access_token;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(test_keys()))
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(admin_ping),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/admin/ping")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,126 @@
+use super::{hash_opaque_token, AuthFuture};
+use crate::config::SessionConfig;
+use crate::error::ApiError;
+use crate::repo::session as session_repo;
+use actix_web::cookie::{time, Cookie};
+use actix_web::dev::Payload;
+use actix_web::http::header::{HeaderValue, SET_COOKIE};
+use actix_web::http::StatusCode;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest};
+use sqlx::PgPool;
+
+/// The account behind the request's session cookie. Unlike `AuthenticatedUser`, bearer
+/// tokens are not accepted.
+pub struct SessionUser {
+    pub user_id: i32,
+    pub token_hash: String,
+}
+
+impl FromRequest for SessionUser {
+    type Error = actix_web::Error;
+    type Future = AuthFuture<Self>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            session_user(&req)
+                .await?
+                .ok_or_else(|| super::authentication_required_error().into())
+        })
+    }
+}
+
+/// Looks up the session named by the request's cookie. No cookie, or no session
+/// configuration, means no session; a cookie for an unknown or expired session is
+/// rejected and cleared.
+pub async fn session_user(req: &HttpRequest) -> Result<Option<SessionUser>, actix_web::Error> {
+    let Some(config) = req.app_data::<Data<SessionConfig>>() else {
+        return Ok(None);
+    };
+    let Some(cookie) = req.cookie(&config.cookie_name) else {
+        return Ok(None);
+    };
+
+    let pool = req
+        .app_data::<Data<PgPool>>()
+        .expect("PgPool must be registered as app data");
+    let token_hash = hash_opaque_token(cookie.value());
+    let user_id = session_repo::get_session_user(pool, &token_hash)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    match user_id {
+        Some(user_id) => Ok(Some(SessionUser {
+            user_id,
+            token_hash,
+        })),
+        None => Err(invalid_session_error(config).into()),
+    }
+}
+
+pub fn session_cookie(config: &SessionConfig, token: String) -> Cookie<'static> {
+    Cookie::build(config.cookie_name.clone(), token)
+        .path("/")
+        .http_only(true)
+        .secure(config.secure)
+        .same_site(config.same_site)
+        .max_age(time::Duration::seconds(config.max_age.as_secs() as i64))
+        .finish()
+}
+
+/// A cookie that makes the browser drop the session cookie.
+pub fn removal_cookie(config: &SessionConfig) -> Cookie<'static> {
+    let mut cookie = session_cookie(config, String::new());
+    cookie.make_removal();
+    cookie
+}
+
+fn invalid_session_error(config: &SessionConfig) -> ApiError {
+    let error = ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        "invalid_session",
+        "Your session has ended, please sign in again",
+    );
+
+    match HeaderValue::from_str(&removal_cookie(config).to_string()) {
+        Ok(cookie) => error.with_headers(vec![(SET_COOKIE, cookie)]),
+        Err(_) => error,
+    }
+}
+
+#[cfg(test)]
+mod session_tests {
+    use super::*;
+    use crate::auth::{generate_opaque_token, OPAQUE_TOKEN_LENGTH};
+    use actix_web::cookie::SameSite;
+    use std::time::Duration;
+
+    fn config() -> SessionConfig {
+        SessionConfig {
+            cookie_name: "ff_session".to_string(),
+            same_site: SameSite::Lax,
+            secure: true,
+            max_age: Duration::from_secs(3600),
+        }
+    }
+
+    #[test]
+    fn test_session_cookie_attributes() {
+        let cookie = session_cookie(&config(), generate_opaque_token());
+
+        assert_eq!(cookie.value().len(), OPAQUE_TOKEN_LENGTH);
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(3600)));
+    }
+
+    #[test]
+    fn test_removal_cookie_expires_immediately() {
+        let cookie = removal_cookie(&config());
+
+        assert_eq!(cookie.value(), "");
+        assert_eq!(cookie.max_age(), Some(time::Duration::ZERO));
+    }
+}
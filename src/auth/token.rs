@@ -0,0 +1,133 @@
+use crate::config::AuthConfig;
+use chrono::Utc;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize)]
+pub struct Claims {
+    /// The user id, as a string per RFC 7519.
+    pub sub: String,
+    pub iss: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub jti: Uuid,
+}
+
+/// The body of a successful login or refresh. `refresh_token` is only set when one
+/// was issued alongside the access token.
+#[derive(Serialize)]
+pub struct AccessToken {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+/// Signs and checks HS256 access tokens.
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    validation: Validation,
+    issuer: String,
+    ttl_secs: i64,
+}
+
+impl JwtKeys {
+    pub fn new(config: &AuthConfig) -> Self {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_issuer(&[&config.issuer]);
+        validation.set_required_spec_claims(&["exp", "iss", "sub"]);
+
+        JwtKeys {
+            encoding: EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+            decoding: DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+            validation,
+            issuer: config.issuer.clone(),
+            ttl_secs: config.access_token_ttl.as_secs() as i64,
+        }
+    }
+
+    pub fn issue(&self, user_id: i32) -> Result<AccessToken, jsonwebtoken::errors::Error> {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            iss: self.issuer.clone(),
+            iat: now,
+            exp: now + self.ttl_secs,
+            jti: Uuid::new_v4(),
+        };
+
+        Ok(AccessToken {
+            access_token: encode(&Header::new(Algorithm::HS256), &claims, &self.encoding)?,
+            token_type: "Bearer",
+            expires_in: self.ttl_secs,
+            refresh_token: None,
+        })
+    }
+
+    /// Checks the signature, issuer and expiry and returns the claims.
+    pub fn verify(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        Ok(decode::<Claims>(token, &self.decoding, &self.validation)?.claims)
+    }
+}
+
+#[cfg(test)]
+pub mod token_tests {
+    use super::*;
+    use crate::model::password::HashParams;
+    use std::time::Duration;
+
+    pub fn test_config() -> AuthConfig {
+        AuthConfig {
+            jwt_secret: "test-secret-that-is-at-least-32-bytes".to_string(),
+            access_token_ttl: Duration::from_secs(900),
+            refresh_token_ttl: Duration::from_secs(86400),
+            issuer: "ferris-forums".to_string(),
+            lockout_threshold: 10,
+            lockout_window: Duration::from_secs(900),
+            lockout_duration: Duration::from_secs(900),
+            password_hashing: HashParams::default(),
+        }
+    }
+
+    pub fn test_keys() -> JwtKeys {
+        JwtKeys::new(&test_config())
+    }
+
+    #[test]
+    fn test_issued_token_verifies() {
+        let keys = test_keys();
+        let token = keys.issue(42).unwrap();
+
+        assert_eq!(token.token_type, "Bearer");
+        assert_eq!(keys.verify(&token.access_token).unwrap().sub, "42");
+    }
+
+    #[test]
+    fn test_token_from_other_secret_is_rejected() {
+        let other = JwtKeys::new(&AuthConfig {
+            jwt_secret: "another-secret-that-is-at-least-32-bytes".to_string(),
+            ..test_config()
+        });
+        let token = other.issue(42).unwrap();
+
+        assert!(test_keys().verify(&token.access_token).is_err());
+    }
+
+    #[test]
+    fn test_expired_token_is_rejected() {
+        let keys = test_keys();
+        let claims = Claims {
+            sub: "42".to_string(),
+            iss: "ferris-forums".to_string(),
+            iat: Utc::now().timestamp() - 3600,
+            exp: Utc::now().timestamp() - 1800,
+            jti: Uuid::new_v4(),
+        };
+        let token = encode(&Header::new(Algorithm::HS256), &claims, &keys.encoding).unwrap();
+
+        assert!(keys.verify(&token).is_err());
+    }
+}
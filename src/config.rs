@@ -0,0 +1,959 @@
+use crate::media::local::LOCAL_MEDIA_PATH;
+use crate::model::api_key::ApiTier;
+use crate::model::oauth::OAuthProvider;
+use crate::model::password::{HashParams, PasswordPolicy};
+use actix_web::cookie::SameSite;
+use actix_web::rt::signal::unix::{signal, SignalKind};
+use actix_web::web::Data;
+use fluent_templates::{langid, LanguageIdentifier};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::Duration;
+
+pub struct Config {
+    pub database_url: String,
+    pub host: String,
+    pub port: u16,
+    pub tcp_enabled: bool,
+    pub tls: Option<TlsConfig>,
+    pub unix_socket: Option<UnixSocketConfig>,
+    pub proxies: ProxyConfig,
+    pub shutdown_timeout_secs: u64,
+    pub runtime_config_path: Option<PathBuf>,
+    pub cors: CorsConfig,
+    pub static_dir: Option<PathBuf>,
+    pub timeouts: TimeoutConfig,
+    pub degraded_mode: DegradedModeConfig,
+    pub default_locale: LanguageIdentifier,
+    pub premium: PremiumConfig,
+    pub posts: PostConfig,
+    pub spam: SpamConfig,
+    pub subs: SubConfig,
+    pub auth: AuthConfig,
+    pub session: SessionConfig,
+    pub oauth: OAuthConfig,
+    pub email: EmailConfig,
+    pub media: MediaConfig,
+    pub password_policy: PasswordPolicy,
+}
+
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+pub struct UnixSocketConfig {
+    pub path: PathBuf,
+    pub mode: Option<u32>,
+    pub owner: Option<u32>,
+    pub group: Option<u32>,
+}
+
+/// Cross-origin access for browser clients. `route_origins` replaces the global
+/// origin list for requests whose path starts with the given prefix; the longest
+/// matching prefix wins. An origin of `*` allows any origin, and can't be combined
+/// with `allow_credentials`.
+#[derive(Clone)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age_secs: usize,
+    pub route_origins: Vec<(String, Vec<String>)>,
+}
+
+impl CorsConfig {
+    fn from_env() -> Self {
+        let route_origins = env::var("CORS_ROUTE_ORIGINS")
+            .map(|routes| parse_route_origins(&routes))
+            .unwrap_or_default();
+
+        let cors = CorsConfig {
+            allowed_origins: env::var("CORS_ALLOWED_ORIGINS")
+                .map(|origins| parse_list(&origins, ','))
+                .unwrap_or_default(),
+            allow_credentials: parse_env_or("CORS_ALLOW_CREDENTIALS", false),
+            max_age_secs: parse_env_or("CORS_MAX_AGE_SECS", 3600),
+            route_origins,
+        };
+        if let Err(e) = cors.validate() {
+            panic!("{}", e);
+        }
+
+        cors
+    }
+
+    /// A wildcard origin with credentials would let any site make requests with the
+    /// user's cookies.
+    fn validate(&self) -> Result<(), String> {
+        let wildcard = self
+            .allowed_origins
+            .iter()
+            .chain(self.route_origins.iter().flat_map(|(_, origins)| origins))
+            .any(|origin| origin == "*");
+        if self.allow_credentials && wildcard {
+            return Err(
+                "CORS_ALLOW_CREDENTIALS=true can't be used with a * origin in \
+                CORS_ALLOWED_ORIGINS or CORS_ROUTE_ORIGINS; list the trusted origins instead"
+                    .to_string(),
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn origin_allowed(&self, origin: &str, path: &str) -> bool {
+        let origins = self
+            .route_origins
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, origins)| origins)
+            .unwrap_or(&self.allowed_origins);
+
+        origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == origin)
+    }
+}
+
+/// How long a handler may run before it is dropped and the client gets a 504.
+/// `route_timeouts` overrides the default for paths starting with the given
+/// prefix; the longest matching prefix wins.
+#[derive(Clone)]
+pub struct TimeoutConfig {
+    pub default_timeout: Duration,
+    pub route_timeouts: Vec<(String, Duration)>,
+}
+
+impl TimeoutConfig {
+    fn from_env() -> Self {
+        TimeoutConfig {
+            default_timeout: Duration::from_secs(parse_env_or("REQUEST_TIMEOUT_SECS", 30)),
+            route_timeouts: env::var("REQUEST_ROUTE_TIMEOUTS")
+                .map(|routes| parse_route_timeouts(&routes))
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn timeout_for(&self, path: &str) -> Duration {
+        self.route_timeouts
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, timeout)| *timeout)
+            .unwrap_or(self.default_timeout)
+    }
+}
+
+/// Controls the database circuit breaker. `acquire_timeout` bounds how long a request
+/// waits for a pool connection before failing.
+#[derive(Clone)]
+pub struct DegradedModeConfig {
+    pub failure_threshold: u32,
+    pub check_interval: Duration,
+    pub acquire_timeout: Duration,
+    pub cache_entries: usize,
+}
+
+impl DegradedModeConfig {
+    fn from_env() -> Self {
+        DegradedModeConfig {
+            failure_threshold: parse_env_or("DB_FAILURE_THRESHOLD", 3),
+            check_interval: Duration::from_secs(parse_env_or("DB_HEALTH_CHECK_INTERVAL_SECS", 5)),
+            acquire_timeout: Duration::from_secs(parse_env_or("DB_ACQUIRE_TIMEOUT_SECS", 5)),
+            cache_entries: parse_env_or("DEGRADED_CACHE_ENTRIES", 1000),
+        }
+    }
+}
+
+/// Access token signing. `jwt_secret` must be at least 32 bytes.
+#[derive(Clone)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    pub access_token_ttl: Duration,
+    pub refresh_token_ttl: Duration,
+    pub issuer: String,
+    /// Failed logins within `lockout_window` that lock the account; 0 disables lockouts.
+    pub lockout_threshold: i64,
+    pub lockout_window: Duration,
+    pub lockout_duration: Duration,
+    pub password_hashing: HashParams,
+}
+
+impl AuthConfig {
+    fn from_env() -> Self {
+        let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+        if jwt_secret.len() < 32 {
+            panic!("JWT_SECRET must be at least 32 bytes long");
+        }
+
+        AuthConfig {
+            jwt_secret,
+            access_token_ttl: Duration::from_secs(parse_env_or("ACCESS_TOKEN_TTL_SECS", 900)),
+            refresh_token_ttl: Duration::from_secs(parse_env_or(
+                "REFRESH_TOKEN_TTL_SECS",
+                30 * 24 * 60 * 60,
+            )),
+            issuer: env_or("JWT_ISSUER", "ferris-forums"),
+            lockout_threshold: parse_env_or("LOGIN_LOCKOUT_THRESHOLD", 10),
+            lockout_window: Duration::from_secs(parse_env_or("LOGIN_LOCKOUT_WINDOW_SECS", 15 * 60)),
+            lockout_duration: Duration::from_secs(parse_env_or("LOGIN_LOCKOUT_SECS", 15 * 60)),
+            password_hashing: hash_params_from_env(),
+        }
+    }
+}
+
+/// The cookie that carries a browser session. It is always `HttpOnly`; `max_age` is
+/// also how long the session lives on the server.
+#[derive(Clone)]
+pub struct SessionConfig {
+    pub cookie_name: String,
+    pub same_site: SameSite,
+    pub secure: bool,
+    pub max_age: Duration,
+}
+
+impl SessionConfig {
+    fn from_env() -> Self {
+        let same_site = match env_or("SESSION_COOKIE_SAMESITE", "lax")
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            "none" => SameSite::None,
+            other => panic!("SESSION_COOKIE_SAMESITE has an invalid value: {}", other),
+        };
+        let secure = parse_env_or("SESSION_COOKIE_SECURE", true);
+        if same_site == SameSite::None && !secure {
+            panic!("SESSION_COOKIE_SAMESITE=none requires SESSION_COOKIE_SECURE=true");
+        }
+
+        SessionConfig {
+            cookie_name: env_or("SESSION_COOKIE_NAME", "ff_session"),
+            same_site,
+            secure,
+            max_age: Duration::from_secs(parse_env_or("SESSION_MAX_AGE_SECS", 14 * 24 * 60 * 60)),
+        }
+    }
+}
+
+/// Social login. A provider is offered only when its client id and secret are set.
+/// `public_url` is where the forum is reachable from browsers, used to build the
+/// callback URLs registered with the providers.
+#[derive(Clone)]
+pub struct OAuthConfig {
+    pub public_url: String,
+    pub success_redirect: String,
+    pub github: Option<OAuthClientConfig>,
+    pub google: Option<OAuthClientConfig>,
+}
+
+#[derive(Clone)]
+pub struct OAuthClientConfig {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+impl OAuthConfig {
+    fn from_env() -> Self {
+        OAuthConfig {
+            public_url: public_url(),
+            success_redirect: env_or("OAUTH_SUCCESS_REDIRECT", "/"),
+            github: OAuthClientConfig::from_env("GITHUB"),
+            google: OAuthClientConfig::from_env("GOOGLE"),
+        }
+    }
+
+    pub fn client(&self, provider: OAuthProvider) -> Option<&OAuthClientConfig> {
+        match provider {
+            OAuthProvider::Github => self.github.as_ref(),
+            OAuthProvider::Google => self.google.as_ref(),
+        }
+    }
+
+    pub fn redirect_uri(&self, provider: OAuthProvider) -> String {
+        format!("{}/auth/oauth/{}/callback", self.public_url, provider)
+    }
+}
+
+impl OAuthClientConfig {
+    fn from_env(prefix: &str) -> Option<Self> {
+        Some(OAuthClientConfig {
+            client_id: env::var(format!("{}_CLIENT_ID", prefix)).ok()?,
+            client_secret: env::var(format!("{}_CLIENT_SECRET", prefix)).ok()?,
+        })
+    }
+}
+
+/// Outgoing mail and address verification. Without `smtp_url` mail is written to the
+/// log instead of being sent, which is only suitable for development.
+#[derive(Clone)]
+pub struct EmailConfig {
+    pub smtp_url: Option<String>,
+    pub from: String,
+    pub public_url: String,
+    pub verification_ttl: Duration,
+    pub require_verified_to_post: bool,
+}
+
+impl EmailConfig {
+    fn from_env() -> Self {
+        EmailConfig {
+            smtp_url: env::var("SMTP_URL").ok(),
+            from: env_or("MAIL_FROM", "Ferris Forums <noreply@localhost>"),
+            public_url: public_url(),
+            verification_ttl: Duration::from_secs(parse_env_or(
+                "EMAIL_VERIFICATION_TTL_SECS",
+                48 * 60 * 60,
+            )),
+            require_verified_to_post: parse_env_or("REQUIRE_VERIFIED_EMAIL", false),
+        }
+    }
+}
+
+/// Where uploaded media is kept. With `s3` set files go to that bucket, otherwise
+/// they are written under `dir` and served by the forum itself. `public_url` is what
+/// a file's key is appended to for clients to fetch it. With `scan_url` set every
+/// upload is sent there to be checked before it is stored.
+#[derive(Clone)]
+pub struct MediaConfig {
+    pub dir: PathBuf,
+    pub s3: Option<S3Config>,
+    pub public_url: String,
+    pub scan_url: Option<String>,
+    pub scan_timeout: Duration,
+}
+
+/// An S3-compatible bucket, addressed path-style so other providers work too.
+#[derive(Clone)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+impl MediaConfig {
+    fn from_env() -> Self {
+        let s3 = env::var("S3_BUCKET").ok().map(|bucket| S3Config {
+            endpoint: env_or("S3_ENDPOINT", "https://s3.amazonaws.com")
+                .trim_end_matches('/')
+                .to_string(),
+            bucket,
+            region: env_or("S3_REGION", "us-east-1"),
+            access_key_id: env::var("S3_ACCESS_KEY_ID")
+                .expect("S3_ACCESS_KEY_ID must be set with S3_BUCKET"),
+            secret_access_key: env::var("S3_SECRET_ACCESS_KEY")
+                .expect("S3_SECRET_ACCESS_KEY must be set with S3_BUCKET"),
+        });
+        let default_public_url = match &s3 {
+            Some(s3) => format!("{}/{}", s3.endpoint, s3.bucket),
+            None => format!("{}{}", public_url(), LOCAL_MEDIA_PATH),
+        };
+
+        MediaConfig {
+            dir: env_or("MEDIA_DIR", "media").into(),
+            s3,
+            public_url: env_or("MEDIA_PUBLIC_URL", &default_public_url)
+                .trim_end_matches('/')
+                .to_string(),
+            scan_url: env::var("MEDIA_SCAN_URL").ok(),
+            scan_timeout: Duration::from_secs(parse_env_or("MEDIA_SCAN_TIMEOUT_SECS", 10)),
+        }
+    }
+}
+
+/// Premium membership. Payment webhooks are rejected unless `webhook_secret` is set;
+/// `expiry_interval` is how often lapsed memberships are switched off.
+#[derive(Clone)]
+pub struct PremiumConfig {
+    pub webhook_secret: Option<String>,
+    pub upload_limit_bytes: u64,
+    pub premium_upload_limit_bytes: u64,
+    pub expiry_interval: Duration,
+}
+
+impl PremiumConfig {
+    fn from_env() -> Self {
+        PremiumConfig {
+            webhook_secret: env::var("PAYMENT_WEBHOOK_SECRET").ok(),
+            upload_limit_bytes: parse_env_or("UPLOAD_LIMIT_BYTES", 10 * 1024 * 1024),
+            premium_upload_limit_bytes: parse_env_or(
+                "PREMIUM_UPLOAD_LIMIT_BYTES",
+                50 * 1024 * 1024,
+            ),
+            expiry_interval: Duration::from_secs(parse_env_or("PREMIUM_EXPIRY_INTERVAL_SECS", 300)),
+        }
+    }
+}
+
+/// Limits on how moderators arrange a sub's posts, and when old posts are archived.
+/// `archive_after` is `None` when archiving is turned off; `archive_interval` is how
+/// often posts are checked and `schedule_interval` how often scheduled posts and sub
+/// post templates are run and expired sub bans and suspensions cleared out.
+/// `link_preview_timeout` bounds fetching a link post's page. `duplicate_link_window` is
+/// how far back a new link post is checked against the sub's posts of the same link,
+/// `None` when the check is turned off.
+#[derive(Clone)]
+pub struct PostConfig {
+    pub max_pins_per_sub: i32,
+    pub archive_after: Option<chrono::Duration>,
+    pub archive_interval: Duration,
+    pub schedule_interval: Duration,
+    pub link_preview_timeout: Duration,
+    pub duplicate_link_window: Option<chrono::Duration>,
+}
+
+impl PostConfig {
+    fn from_env() -> Self {
+        let archive_days: i64 = parse_env_or("ARCHIVE_POSTS_AFTER_DAYS", 180);
+        let duplicate_days: i64 = parse_env_or("DUPLICATE_LINK_WINDOW_DAYS", 30);
+        PostConfig {
+            max_pins_per_sub: parse_env_or("MAX_PINNED_POSTS_PER_SUB", 2),
+            archive_after: (archive_days > 0).then(|| chrono::Duration::days(archive_days)),
+            archive_interval: Duration::from_secs(parse_env_or("ARCHIVE_INTERVAL_SECS", 3600)),
+            schedule_interval: Duration::from_secs(parse_env_or("SCHEDULE_INTERVAL_SECS", 60)),
+            link_preview_timeout: Duration::from_secs(parse_env_or("LINK_PREVIEW_TIMEOUT_SECS", 5)),
+            duplicate_link_window: (duplicate_days > 0)
+                .then(|| chrono::Duration::days(duplicate_days)),
+        }
+    }
+}
+
+/// New posts and comments scoring at least `queue_score` for spam are held for
+/// moderators, and those scoring at least `remove_score` are removed. Either is `None`
+/// when turned off.
+#[derive(Clone)]
+pub struct SpamConfig {
+    pub queue_score: Option<i32>,
+    pub remove_score: Option<i32>,
+}
+
+impl SpamConfig {
+    fn from_env() -> Self {
+        let queue_score: i32 = parse_env_or("SPAM_QUEUE_SCORE", 60);
+        let remove_score: i32 = parse_env_or("SPAM_REMOVE_SCORE", 90);
+        SpamConfig {
+            queue_score: (queue_score > 0).then_some(queue_score),
+            remove_score: (remove_score > 0).then_some(remove_score),
+        }
+    }
+}
+
+/// `stats_interval` is how often the subscriber growth and activity behind trending
+/// and popular subs are recounted.
+#[derive(Clone)]
+pub struct SubConfig {
+    pub stats_interval: Duration,
+}
+
+impl SubConfig {
+    fn from_env() -> Self {
+        SubConfig {
+            stats_interval: Duration::from_secs(parse_env_or("SUB_STATS_INTERVAL_SECS", 900)),
+        }
+    }
+}
+
+/// Reverse proxies whose `Forwarded` and `X-Forwarded-For` headers are believed when
+/// they connect over TCP. Connections from anywhere else are taken at their peer address.
+#[derive(Clone, Default)]
+pub struct ProxyConfig {
+    pub trusted: Vec<IpAddr>,
+}
+
+impl ProxyConfig {
+    fn from_env() -> Self {
+        let trusted = env::var("TRUSTED_PROXIES")
+            .map(|proxies| parse_list(&proxies, ','))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|proxy| {
+                proxy
+                    .parse()
+                    .unwrap_or_else(|_| panic!("TRUSTED_PROXIES must list IP addresses: {}", proxy))
+            })
+            .collect();
+
+        ProxyConfig { trusted }
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let database_url = env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set in .env or environment variables");
+
+        let tls = match (env::var("TLS_CERT_PATH"), env::var("TLS_KEY_PATH")) {
+            (Ok(cert_path), Ok(key_path)) => Some(TlsConfig {
+                cert_path: cert_path.into(),
+                key_path: key_path.into(),
+            }),
+            (Err(_), Err(_)) => None,
+            _ => panic!("TLS_CERT_PATH and TLS_KEY_PATH must be set together"),
+        };
+
+        let unix_socket = env::var("UNIX_SOCKET_PATH")
+            .ok()
+            .map(|path| UnixSocketConfig {
+                path: path.into(),
+                mode: env::var("UNIX_SOCKET_MODE").ok().map(|mode| {
+                    u32::from_str_radix(&mode, 8)
+                        .unwrap_or_else(|_| panic!("UNIX_SOCKET_MODE must be octal: {}", mode))
+                }),
+                owner: parse_env("UNIX_SOCKET_OWNER"),
+                group: parse_env("UNIX_SOCKET_GROUP"),
+            });
+
+        let config = Config {
+            database_url,
+            host: env_or("HOST", "127.0.0.1"),
+            port: parse_env_or("PORT", 8080),
+            tcp_enabled: parse_env_or("TCP_ENABLED", true),
+            tls,
+            unix_socket,
+            proxies: ProxyConfig::from_env(),
+            shutdown_timeout_secs: parse_env_or("SHUTDOWN_TIMEOUT_SECS", 30),
+            runtime_config_path: env::var("RUNTIME_CONFIG_PATH").ok().map(PathBuf::from),
+            cors: CorsConfig::from_env(),
+            static_dir: env::var("STATIC_DIR").ok().map(PathBuf::from),
+            timeouts: TimeoutConfig::from_env(),
+            degraded_mode: DegradedModeConfig::from_env(),
+            default_locale: parse_env_or("DEFAULT_LOCALE", langid!("en-US")),
+            premium: PremiumConfig::from_env(),
+            posts: PostConfig::from_env(),
+            spam: SpamConfig::from_env(),
+            subs: SubConfig::from_env(),
+            auth: AuthConfig::from_env(),
+            session: SessionConfig::from_env(),
+            oauth: OAuthConfig::from_env(),
+            email: EmailConfig::from_env(),
+            media: MediaConfig::from_env(),
+            password_policy: password_policy_from_env(),
+        };
+
+        if !config.tcp_enabled && config.unix_socket.is_none() {
+            panic!("TCP_ENABLED=false requires UNIX_SOCKET_PATH to be set");
+        }
+
+        config
+    }
+}
+
+fn parse_list(value: &str, separator: char) -> Vec<String> {
+    value
+        .split(separator)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parses `/prefix=origin origin;/other=origin` into per-prefix origin lists.
+fn parse_route_origins(value: &str) -> Vec<(String, Vec<String>)> {
+    parse_list(value, ';')
+        .iter()
+        .map(|route| match route.split_once('=') {
+            Some((prefix, origins)) => (prefix.trim().to_string(), parse_list(origins, ' ')),
+            None => panic!("CORS_ROUTE_ORIGINS entry must be prefix=origins: {}", route),
+        })
+        .collect()
+}
+
+/// Parses `/prefix=seconds;/other=seconds` into per-prefix timeouts.
+fn parse_route_timeouts(value: &str) -> Vec<(String, Duration)> {
+    parse_list(value, ';')
+        .iter()
+        .map(|route| {
+            let seconds = route
+                .split_once('=')
+                .and_then(|(prefix, seconds)| Some((prefix, seconds.trim().parse().ok()?)));
+            match seconds {
+                Some((prefix, seconds)) => {
+                    (prefix.trim().to_string(), Duration::from_secs(seconds))
+                }
+                None => panic!(
+                    "REQUEST_ROUTE_TIMEOUTS entry must be prefix=seconds: {}",
+                    route
+                ),
+            }
+        })
+        .collect()
+}
+
+fn hash_params_from_env() -> HashParams {
+    let default = HashParams::default();
+    let params = HashParams {
+        memory_kib: parse_env_or("PASSWORD_HASH_MEMORY_KIB", default.memory_kib),
+        iterations: parse_env_or("PASSWORD_HASH_ITERATIONS", default.iterations),
+        parallelism: parse_env_or("PASSWORD_HASH_PARALLELISM", default.parallelism),
+    };
+    if let Err(e) = params.hasher() {
+        panic!(
+            "PASSWORD_HASH_* settings are not valid Argon2 parameters: {}",
+            e
+        );
+    }
+
+    params
+}
+
+fn password_policy_from_env() -> PasswordPolicy {
+    let default = PasswordPolicy::default();
+
+    PasswordPolicy {
+        min_length: parse_env_or("PASSWORD_MIN_LENGTH", default.min_length),
+        min_character_classes: parse_env_or(
+            "PASSWORD_MIN_CHARACTER_CLASSES",
+            default.min_character_classes,
+        ),
+        deny_common: parse_env_or("PASSWORD_DENY_COMMON", default.deny_common),
+    }
+}
+
+/// The forum's address as seen by browsers, without a trailing slash.
+fn public_url() -> String {
+    env_or("PUBLIC_URL", "http://localhost:8080")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn env_or(key: &str, default: &str) -> String {
+    env::var(key).unwrap_or_else(|_| default.to_string())
+}
+
+fn parse_env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    parse_env(key).unwrap_or(default)
+}
+
+fn parse_env<T: std::str::FromStr>(key: &str) -> Option<T> {
+    env::var(key).ok().map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} has an invalid value: {}", key, value))
+    })
+}
+
+/// Settings that can change while the server is running. They are read from the
+/// TOML file at `RUNTIME_CONFIG_PATH` at startup and again on SIGHUP or
+/// `POST /admin/config/reload`; everything else needs a restart.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    pub log_level: String,
+    pub feature_flags: HashMap<String, bool>,
+    pub quotas: ApiQuotas,
+    pub rate_limits: RateLimits,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        RuntimeConfig {
+            log_level: "debug".to_string(),
+            feature_flags: HashMap::new(),
+            quotas: ApiQuotas::default(),
+            rate_limits: RateLimits::default(),
+        }
+    }
+}
+
+/// Token buckets for the routes that attract abuse. Each group is limited per client
+/// IP and, for signed-in callers, per user as well.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RateLimits {
+    pub enabled: bool,
+    /// `POST /users`
+    pub signup: RateLimit,
+    /// `POST /auth/login` and `POST /auth/session`
+    pub login: RateLimit,
+    /// `POST /posts/{sub}`
+    pub post: RateLimit,
+    /// `POST /posts/{post_id}/comments`
+    pub comment: RateLimit,
+}
+
+/// Up to `burst` requests at once, refilled at `per_minute`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            enabled: true,
+            signup: RateLimit {
+                burst: 5,
+                per_minute: 1,
+            },
+            login: RateLimit {
+                burst: 10,
+                per_minute: 10,
+            },
+            post: RateLimit {
+                burst: 5,
+                per_minute: 2,
+            },
+            comment: RateLimit {
+                burst: 10,
+                per_minute: 6,
+            },
+        }
+    }
+}
+
+/// Requests allowed per API key in each UTC day and month, by tier.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ApiQuotas {
+    pub free: TierQuota,
+    pub standard: TierQuota,
+    pub partner: TierQuota,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct TierQuota {
+    pub daily: i64,
+    pub monthly: i64,
+}
+
+impl Default for ApiQuotas {
+    fn default() -> Self {
+        ApiQuotas {
+            free: TierQuota {
+                daily: 1_000,
+                monthly: 20_000,
+            },
+            standard: TierQuota {
+                daily: 10_000,
+                monthly: 200_000,
+            },
+            partner: TierQuota {
+                daily: 100_000,
+                monthly: 2_000_000,
+            },
+        }
+    }
+}
+
+impl ApiQuotas {
+    pub fn for_tier(&self, tier: ApiTier) -> TierQuota {
+        match tier {
+            ApiTier::Free => self.free,
+            ApiTier::Standard => self.standard,
+            ApiTier::Partner => self.partner,
+        }
+    }
+}
+
+impl RuntimeConfig {
+    fn from_toml(contents: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let config: RuntimeConfig = toml::from_str(contents)?;
+        config.log_level_filter()?;
+
+        Ok(config)
+    }
+
+    fn log_level_filter(&self) -> Result<LevelFilter, Box<dyn Error + Send + Sync>> {
+        LevelFilter::from_str(&self.log_level)
+            .map_err(|_| format!("invalid log_level: {}", self.log_level).into())
+    }
+}
+
+pub struct RuntimeSettings {
+    path: Option<PathBuf>,
+    current: RwLock<RuntimeConfig>,
+}
+
+impl RuntimeSettings {
+    pub fn load(path: Option<PathBuf>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let settings = RuntimeSettings {
+            path,
+            current: RwLock::new(RuntimeConfig::default()),
+        };
+        settings.reload()?;
+
+        Ok(settings)
+    }
+
+    pub fn current(&self) -> RuntimeConfig {
+        self.current
+            .read()
+            .expect("runtime config lock poisoned")
+            .clone()
+    }
+
+    /// Re-reads the runtime config file. On error the previous settings stay in effect.
+    pub fn reload(&self) -> Result<RuntimeConfig, Box<dyn Error + Send + Sync>> {
+        let config = match &self.path {
+            Some(path) => RuntimeConfig::from_toml(&fs::read_to_string(path)?)?,
+            None => RuntimeConfig::default(),
+        };
+
+        log::set_max_level(config.log_level_filter()?);
+        *self.current.write().expect("runtime config lock poisoned") = config.clone();
+
+        Ok(config)
+    }
+}
+
+pub async fn reload_on_sighup(settings: Data<RuntimeSettings>) {
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            log::error!("Could not listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        match settings.reload() {
+            Ok(_) => log::info!("Runtime configuration reloaded"),
+            Err(e) => log::error!("Runtime configuration reload failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    fn cors_config() -> CorsConfig {
+        CorsConfig {
+            allowed_origins: vec!["https://forums.example".to_string()],
+            allow_credentials: false,
+            max_age_secs: 3600,
+            route_origins: parse_route_origins(
+                "/legal=*; /admin=https://admin.example https://ops.example",
+            ),
+        }
+    }
+
+    #[test]
+    fn test_cors_uses_global_origins_by_default() {
+        let cors = cors_config();
+        assert!(cors.origin_allowed("https://forums.example", "/posts/for_sub/rust"));
+        assert!(!cors.origin_allowed("https://evil.example", "/posts/for_sub/rust"));
+    }
+
+    #[test]
+    fn test_cors_route_override_replaces_global_origins() {
+        let cors = cors_config();
+        assert!(cors.origin_allowed("https://ops.example", "/admin/audit"));
+        assert!(!cors.origin_allowed("https://forums.example", "/admin/audit"));
+        assert!(cors.origin_allowed("https://anyone.example", "/legal/takedowns"));
+    }
+
+    #[test]
+    fn test_cors_rejects_wildcard_origin_with_credentials() {
+        let mut cors = cors_config();
+        assert!(cors.validate().is_ok());
+
+        cors.allow_credentials = true;
+        assert!(cors.validate().is_err());
+
+        cors.route_origins.clear();
+        assert!(cors.validate().is_ok());
+        cors.allowed_origins.push("*".to_string());
+        assert!(cors.validate().is_err());
+    }
+
+    #[test]
+    fn test_route_timeout_overrides_default() {
+        let timeouts = TimeoutConfig {
+            default_timeout: Duration::from_secs(30),
+            route_timeouts: parse_route_timeouts("/admin=120; /admin/audit=300"),
+        };
+
+        assert_eq!(
+            timeouts.timeout_for("/posts/for_sub/rust"),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            timeouts.timeout_for("/admin/modlog"),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            timeouts.timeout_for("/admin/audit"),
+            Duration::from_secs(300)
+        );
+    }
+
+    #[test]
+    fn test_runtime_config_parses_flags() {
+        let config = RuntimeConfig::from_toml(
+            r#"
+            log_level = "info"
+
+            [feature_flags]
+            new_ranking = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.log_level, "info");
+        assert_eq!(config.feature_flags.get("new_ranking"), Some(&true));
+    }
+
+    #[test]
+    fn test_runtime_config_rejects_unknown_log_level() {
+        assert!(RuntimeConfig::from_toml(r#"log_level = "loud""#).is_err());
+    }
+
+    #[test]
+    fn test_runtime_config_defaults_missing_fields() {
+        let config = RuntimeConfig::from_toml("").unwrap();
+        assert_eq!(config.log_level, "debug");
+        assert!(config.feature_flags.is_empty());
+        assert_eq!(config.quotas.free.daily, 1_000);
+        assert!(config.rate_limits.enabled);
+    }
+
+    #[test]
+    fn test_runtime_config_overrides_single_rate_limit() {
+        let config = RuntimeConfig::from_toml(
+            r#"
+            [rate_limits.login]
+            burst = 3
+            per_minute = 1
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.rate_limits.login,
+            RateLimit {
+                burst: 3,
+                per_minute: 1
+            }
+        );
+        assert_eq!(config.rate_limits.comment, RateLimits::default().comment);
+    }
+
+    #[test]
+    fn test_runtime_config_overrides_single_tier_quota() {
+        let config = RuntimeConfig::from_toml(
+            r#"
+            [quotas.partner]
+            daily = 5
+            monthly = 50
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.quotas.for_tier(ApiTier::Partner).monthly, 50);
+        assert_eq!(config.quotas.for_tier(ApiTier::Standard).daily, 10_000);
+    }
+}
@@ -0,0 +1,21 @@
+use crate::config::CorsConfig;
+use actix_cors::Cors;
+
+pub fn cors_middleware(config: &CorsConfig) -> Cors {
+    let origins = config.clone();
+    let cors = Cors::default()
+        .allowed_origin_fn(move |origin, request| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| origins.origin_allowed(origin, request.uri.path()))
+        })
+        .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
+        .allow_any_header()
+        .max_age(config.max_age_secs);
+
+    if config.allow_credentials {
+        cors.supports_credentials()
+    } else {
+        cors
+    }
+}
@@ -0,0 +1,98 @@
+use crate::auth::hash_opaque_token;
+use crate::config::{AuthConfig, SessionConfig};
+use crate::error::ApiError;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// Signing in doesn't act on the session, and a browser holding an expired cookie
+/// has to be able to reach them to get a new one.
+const EXEMPT_PATHS: [&str; 3] = ["/auth/login", "/auth/session", "/auth/refresh"];
+
+#[derive(Serialize)]
+pub struct CsrfToken {
+    pub csrf_token: String,
+}
+
+/// The CSRF token for a session: an HMAC of the session's token hash, so it changes
+/// with every sign-in and needs no storage of its own.
+pub fn csrf_token(config: &AuthConfig, session_hash: &str) -> String {
+    hex::encode(csrf_mac(config, session_hash).finalize().into_bytes())
+}
+
+fn csrf_mac(config: &AuthConfig, session_hash: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(config.jwt_secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(session_hash.as_bytes());
+    mac
+}
+
+/// Compared in constant time.
+fn is_valid_token(config: &AuthConfig, session_hash: &str, token: &str) -> bool {
+    let Ok(token) = hex::decode(token.trim()) else {
+        return false;
+    };
+
+    csrf_mac(config, session_hash).verify_slice(&token).is_ok()
+}
+
+/// Requires the session's CSRF token in `X-CSRF-Token` on state-changing requests that
+/// carry a session cookie. Browsers attach the cookie to cross-site requests on their
+/// own, but only the forum's pages can read the token from `GET /auth/csrf`. Requests
+/// with an `Authorization` header authenticate with it instead of the cookie, so they
+/// are exempt.
+pub async fn enforce_csrf(
+    session_config: Data<SessionConfig>,
+    auth_config: Data<AuthConfig>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let sign_in = req.method() == Method::POST && EXEMPT_PATHS.contains(&req.path());
+    if safe || sign_in || req.headers().contains_key(AUTHORIZATION) {
+        return next.call(req).await;
+    }
+    let Some(cookie) = req.cookie(&session_config.cookie_name) else {
+        return next.call(req).await;
+    };
+
+    let session_hash = hash_opaque_token(cookie.value());
+    let valid = req
+        .headers()
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|token| is_valid_token(&auth_config, &session_hash, token));
+    if !valid {
+        return Err(ApiError::forbidden(
+            "csrf_token_invalid",
+            "This request needs a valid CSRF token from GET /auth/csrf",
+        )
+        .into());
+    }
+
+    next.call(req).await
+}
+
+#[cfg(test)]
+mod csrf_tests {
+    use super::*;
+    use crate::auth::token::token_tests::test_config;
+
+    #[test]
+    fn test_token_is_bound_to_the_session() {
+        let config = test_config();
+        let token = csrf_token(&config, "session-a");
+
+        assert!(is_valid_token(&config, "session-a", &token));
+        assert!(!is_valid_token(&config, "session-b", &token));
+        assert!(!is_valid_token(&config, "session-a", "not hex"));
+    }
+}
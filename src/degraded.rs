@@ -0,0 +1,307 @@
+use crate::api::api_key::API_KEY_HEADER;
+use crate::config::{DegradedModeConfig, SessionConfig};
+use crate::error::ApiError;
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::rt::time::{interval, timeout};
+use actix_web::web::{Bytes, Data};
+use actix_web::HttpResponse;
+use sqlx::PgPool;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+
+const MAX_CACHED_BODY_BYTES: usize = 256 * 1024;
+
+/// Opens after `failure_threshold` consecutive failed health checks and closes again
+/// on the first successful one.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    consecutive_failures: AtomicU32,
+    open: AtomicBool,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32) -> Self {
+        CircuitBreaker {
+            failure_threshold: failure_threshold.max(1),
+            consecutive_failures: AtomicU32::new(0),
+            open: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.load(Ordering::Relaxed)
+    }
+
+    pub fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        if self.open.swap(false, Ordering::Relaxed) {
+            log::info!("Database is reachable again, leaving degraded mode");
+        }
+    }
+
+    pub fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.failure_threshold && !self.open.swap(true, Ordering::Relaxed) {
+            log::error!(
+                "Database failed {} health checks in a row, entering degraded mode",
+                failures
+            );
+        }
+    }
+}
+
+struct CachedResponse {
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+/// The most recent successful responses to public reads, keyed by path and query.
+/// Oldest entries are evicted first once `capacity` is reached.
+struct ResponseCache {
+    capacity: usize,
+    entries: HashMap<String, CachedResponse>,
+    order: VecDeque<String>,
+}
+
+impl ResponseCache {
+    fn insert(&mut self, key: String, response: CachedResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key.clone(), response).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+pub struct DatabaseHealth {
+    breaker: CircuitBreaker,
+    cache: Mutex<ResponseCache>,
+}
+
+impl DatabaseHealth {
+    pub fn new(config: &DegradedModeConfig) -> Self {
+        DatabaseHealth {
+            breaker: CircuitBreaker::new(config.failure_threshold),
+            cache: Mutex::new(ResponseCache {
+                capacity: config.cache_entries,
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    fn cached(&self, key: &str) -> Option<HttpResponse> {
+        let cache = self.cache.lock().expect("response cache lock poisoned");
+        cache.entries.get(key).map(|cached| {
+            let mut response = HttpResponse::Ok();
+            if let Some(content_type) = &cached.content_type {
+                response.insert_header((CONTENT_TYPE, content_type.clone()));
+            }
+            response
+                .insert_header(("X-Degraded-Mode", "cached"))
+                .body(cached.body.clone())
+        })
+    }
+
+    fn store(&self, key: String, response: CachedResponse) {
+        self.cache
+            .lock()
+            .expect("response cache lock poisoned")
+            .insert(key, response);
+    }
+}
+
+/// Pings the database every `check_interval` and feeds the result to the breaker.
+/// While the breaker is open this is also what closes it again.
+pub async fn monitor_database(
+    pool: PgPool,
+    health: Data<DatabaseHealth>,
+    config: DegradedModeConfig,
+) {
+    let mut ticks = interval(config.check_interval);
+
+    loop {
+        ticks.tick().await;
+        let check = timeout(
+            config.check_interval,
+            sqlx::query("SELECT 1").execute(&pool),
+        )
+        .await;
+        match check {
+            Ok(Ok(_)) => health.breaker.record_success(),
+            Ok(Err(e)) => {
+                log::warn!("Database health check failed: {}", e);
+                health.breaker.record_failure();
+            }
+            Err(_) => {
+                log::warn!("Database health check timed out");
+                health.breaker.record_failure();
+            }
+        }
+    }
+}
+
+/// Whether the request carries any credential. Signed-in responses can depend on who
+/// is asking, and the cache is shared by every caller.
+fn has_credentials(req: &ServiceRequest) -> bool {
+    let headers = req.headers();
+    if headers.contains_key(AUTHORIZATION) || headers.contains_key(API_KEY_HEADER) {
+        return true;
+    }
+    req.app_data::<Data<SessionConfig>>()
+        .is_some_and(|config| req.cookie(&config.cookie_name).is_some())
+}
+
+fn is_cacheable(req: &ServiceRequest) -> bool {
+    req.method() == Method::GET && !req.path().starts_with("/admin") && !has_credentials(req)
+}
+
+/// Fails fast with a 503 while the breaker is open, serving the last good copy of
+/// anonymous public reads where one is cached. While the database is healthy,
+/// successful anonymous public reads are recorded so they are available when it isn't.
+pub async fn degraded_mode(
+    health: Data<DatabaseHealth>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let cacheable = is_cacheable(&req);
+    let key = req.uri().to_string();
+
+    if health.breaker.is_open() {
+        if let Some(response) = cacheable.then(|| health.cached(&key)).flatten() {
+            return Ok(req.into_response(response));
+        }
+        return Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "database_unavailable",
+            "The forum is temporarily unavailable, please try again shortly",
+        )
+        .into());
+    }
+
+    let response = next.call(req).await?.map_into_boxed_body();
+    if !cacheable || response.status() != StatusCode::OK {
+        return Ok(response);
+    }
+
+    let (req, response) = response.into_parts();
+    let (response, body) = response.into_parts();
+    let body = to_bytes(body)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if body.len() <= MAX_CACHED_BODY_BYTES {
+        health.store(
+            key,
+            CachedResponse {
+                content_type: response.headers().get(CONTENT_TYPE).cloned(),
+                body: body.clone(),
+            },
+        );
+    }
+
+    Ok(ServiceResponse::new(
+        req,
+        response.set_body(BoxBody::new(body)),
+    ))
+}
+
+#[cfg(test)]
+mod degraded_tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{call_and_read_body, init_service, try_call_service, TestRequest};
+    use actix_web::{web, App, HttpRequest};
+    use std::time::Duration;
+
+    #[test]
+    fn test_breaker_opens_after_threshold_and_closes_on_success() {
+        let breaker = CircuitBreaker::new(2);
+
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+        breaker.record_failure();
+        assert!(breaker.is_open());
+
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+    }
+
+    #[test]
+    fn test_response_cache_evicts_oldest_entry() {
+        let mut cache = ResponseCache {
+            capacity: 2,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        };
+        for key in ["/a", "/b", "/a", "/c"] {
+            cache.insert(
+                key.to_string(),
+                CachedResponse {
+                    content_type: None,
+                    body: Bytes::from_static(b"{}"),
+                },
+            );
+        }
+
+        assert!(!cache.entries.contains_key("/a"));
+        assert!(cache.entries.contains_key("/b"));
+        assert!(cache.entries.contains_key("/c"));
+    }
+
+    #[actix_web::test]
+    async fn test_authenticated_response_is_not_served_to_anonymous_callers() {
+        let health = Data::new(DatabaseHealth::new(&DegradedModeConfig {
+            failure_threshold: 1,
+            check_interval: Duration::from_secs(1),
+            acquire_timeout: Duration::from_secs(1),
+            cache_entries: 8,
+        }));
+        let middleware_health = health.clone();
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| {
+                    degraded_mode(middleware_health.clone(), req, next)
+                }))
+                .route(
+                    "/auth/me",
+                    web::get().to(|req: HttpRequest| async move {
+                        match req.headers().get(AUTHORIZATION) {
+                            Some(_) => HttpResponse::Ok().body("private"),
+                            None => HttpResponse::Ok().body("public"),
+                        }
+                    }),
+                ),
+        )
+        .await;
+
+        let request = TestRequest::get()
+            .uri("/auth/me")
+            .insert_header((AUTHORIZATION, "Bearer token"))
+            .to_request();
+        let body = call_and_read_body(&app, request).await;
+        assert_eq!(body, Bytes::from_static(b"private"));
+
+        health.breaker.record_failure();
+        let request = TestRequest::get().uri("/auth/me").to_request();
+        let response = try_call_service(&app, request).await;
+        let status = match response {
+            Ok(response) => response.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,92 @@
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// An error with a stable, machine-readable code that clients can branch on,
+/// rendered as `{"code": ..., "message": ...}`. `args` are the values interpolated
+/// into `message`, kept so it can be re-rendered in another language. `fields` are
+/// extra values added to the body: `details` lists extra codes, such as each rule a
+/// rejected password broke, and others carry data like the posts a duplicate link was
+/// already posted in. `headers` are added to the error response, e.g. quota details on a 429.
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    #[serde(flatten)]
+    pub fields: BTreeMap<&'static str, Value>,
+    #[serde(skip)]
+    pub args: BTreeMap<&'static str, String>,
+    #[serde(skip)]
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+            fields: BTreeMap::new(),
+            args: BTreeMap::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn with_arg(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.args.insert(name, value.into());
+        self
+    }
+
+    pub fn with_details(mut self, details: Vec<String>) -> Self {
+        if details.is_empty() {
+            return self;
+        }
+        if let Value::Array(listed) = self
+            .fields
+            .entry("details")
+            .or_insert_with(|| Value::Array(Vec::new()))
+        {
+            listed.extend(details.into_iter().map(Value::from));
+        }
+        self
+    }
+
+    pub fn with_field(mut self, name: &'static str, value: Value) -> Self {
+        self.fields.insert(name, value);
+        self
+    }
+
+    pub fn with_headers(mut self, headers: Vec<(HeaderName, HeaderValue)>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    pub fn forbidden(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, code, message)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status);
+        for header in &self.headers {
+            response.insert_header(header.clone());
+        }
+        response.json(self)
+    }
+}
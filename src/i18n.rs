@@ -0,0 +1,152 @@
+use crate::error::ApiError;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, ACCEPT_LANGUAGE, CONTENT_LANGUAGE};
+use actix_web::middleware::Next;
+use actix_web::ResponseError;
+use fluent_langneg::{accepted_languages, negotiate_languages, NegotiationStrategy};
+use fluent_templates::fluent_bundle::FluentValue;
+use fluent_templates::{static_loader, LanguageIdentifier, Loader};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+// Message catalogues live in `locales/<language>/*.ftl` and are compiled into the binary.
+// Message ids are the stable codes used by `ApiError` and notifications.
+static_loader! {
+    static LOCALES = {
+        locales: "./locales",
+        fallback_language: "en-US",
+        customise: |bundle| bundle.set_use_isolating(false),
+    };
+}
+
+/// Picks the best available locale for an `Accept-Language` header, falling back to
+/// the deployment's default when nothing requested is available.
+pub fn negotiate(
+    accept_language: Option<&str>,
+    default: &LanguageIdentifier,
+) -> LanguageIdentifier {
+    let requested = accept_language
+        .map(accepted_languages::parse)
+        .unwrap_or_default();
+    let available: Vec<LanguageIdentifier> = LOCALES.locales().cloned().collect();
+
+    negotiate_languages(
+        &requested,
+        &available,
+        Some(default),
+        NegotiationStrategy::Lookup,
+    )
+    .first()
+    .map(|locale| (*locale).clone())
+    .unwrap_or_else(|| default.clone())
+}
+
+/// The message for `code` in `locale`, or `None` if no catalogue defines it.
+pub fn localize(
+    locale: &LanguageIdentifier,
+    code: &str,
+    args: &BTreeMap<&'static str, String>,
+) -> Option<String> {
+    let args: HashMap<Cow<'static, str>, FluentValue> = args
+        .iter()
+        .map(|(name, value)| (Cow::Borrowed(*name), FluentValue::from(value.as_str())))
+        .collect();
+
+    LOCALES.try_lookup_with_args(locale, code, &args)
+}
+
+fn localize_error(error: &ApiError, locale: &LanguageIdentifier) -> Option<ApiError> {
+    localize(locale, error.code, &error.args).map(|message| ApiError {
+        status: error.status,
+        code: error.code,
+        message,
+        fields: error.fields.clone(),
+        args: error.args.clone(),
+        headers: error.headers.clone(),
+    })
+}
+
+/// Rewrites the message of any `ApiError` produced further down the chain in the
+/// caller's language. Errors without a catalogue entry keep their English message.
+pub async fn localize_errors(
+    default_locale: LanguageIdentifier,
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let accept_language = req
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let locale = negotiate(accept_language.as_deref(), &default_locale);
+
+    let response = match next.call(req).await {
+        Ok(response) => response.map_into_boxed_body(),
+        Err(error) => {
+            return match error
+                .as_error::<ApiError>()
+                .and_then(|api_error| localize_error(api_error, &locale))
+            {
+                Some(localized) => Err(localized.into()),
+                None => Err(error),
+            };
+        }
+    };
+
+    let localized = response
+        .response()
+        .error()
+        .and_then(|error| error.as_error::<ApiError>())
+        .and_then(|api_error| localize_error(api_error, &locale));
+
+    Ok(match localized {
+        Some(localized) => {
+            let mut response = response.into_response(localized.error_response());
+            if let Ok(language) = HeaderValue::from_str(&locale.to_string()) {
+                response.headers_mut().insert(CONTENT_LANGUAGE, language);
+            }
+            response
+        }
+        None => response,
+    })
+}
+
+#[cfg(test)]
+mod i18n_tests {
+    use super::*;
+    use fluent_templates::langid;
+
+    const ENGLISH: LanguageIdentifier = langid!("en-US");
+
+    #[test]
+    fn test_negotiate_prefers_highest_weighted_available_locale() {
+        let locale = negotiate(Some("fr;q=0.9, de-AT;q=0.8, en;q=0.5"), &ENGLISH);
+        assert_eq!(locale, langid!("de"));
+    }
+
+    #[test]
+    fn test_negotiate_falls_back_to_default() {
+        assert_eq!(negotiate(Some("fr"), &ENGLISH), ENGLISH);
+        assert_eq!(negotiate(None, &langid!("de")), langid!("de"));
+    }
+
+    #[test]
+    fn test_every_locale_defines_the_english_codes() {
+        for locale in LOCALES.locales() {
+            for code in [
+                "request_timeout",
+                "database_unavailable",
+                "invalid_api_key",
+                "invalid_token",
+            ] {
+                assert!(
+                    localize(locale, code, &BTreeMap::new()).is_some(),
+                    "{} missing {}",
+                    locale,
+                    code
+                );
+            }
+        }
+    }
+}
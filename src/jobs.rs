@@ -0,0 +1,137 @@
+//! Periodic background work spawned at startup.
+
+use crate::model::sub::SUB_STATS_WINDOW_DAYS;
+use crate::repo::{
+    post as post_repo, post_template as post_template_repo, premium as premium_repo,
+    sub as sub_repo, suspension as suspension_repo,
+};
+use actix_web::rt::time::interval;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::time::Duration;
+
+/// Switches off premium for members whose subscriptions have all run out. Grants,
+/// revocations and webhooks update the flag straight away; this catches expiry.
+pub async fn expire_premium_memberships(pool: PgPool, every: Duration) {
+    let mut ticker = interval(every);
+
+    loop {
+        ticker.tick().await;
+        match premium_repo::expire_memberships(&pool).await {
+            Ok(0) => {}
+            Ok(expired) => log::info!("Expired {} premium membership(s)", expired),
+            Err(e) => log::error!("Premium expiry check failed: {}", e),
+        }
+    }
+}
+
+/// Publishes scheduled posts once their `publish_at` has passed.
+pub async fn publish_scheduled_posts(pool: PgPool, every: Duration) {
+    let mut ticker = interval(every);
+
+    loop {
+        ticker.tick().await;
+        match post_repo::publish_scheduled_posts(&pool).await {
+            Ok(0) => {}
+            Ok(published) => log::info!("Published {} scheduled post(s)", published),
+            Err(e) => log::error!("Scheduled publishing failed: {}", e),
+        }
+    }
+}
+
+/// Makes the posts of sub templates whose next run has come, once per template even if
+/// runs were missed while the server was down.
+pub async fn run_post_templates(pool: PgPool, every: Duration) {
+    let mut ticker = interval(every);
+
+    loop {
+        ticker.tick().await;
+        let now = Utc::now();
+        let templates = match post_template_repo::get_due_post_templates(&pool, now).await {
+            Ok(templates) => templates,
+            Err(e) => {
+                log::error!("Scheduled post check failed: {}", e);
+                continue;
+            }
+        };
+        for template in templates {
+            let run_at = template.repeat.latest_due(template.next_run_at, now);
+            let next_run_at = template.repeat.after(run_at);
+            let post = template.post_at(run_at);
+            let posted = match post_template_repo::claim_post_template_run(
+                &pool,
+                template.id,
+                template.next_run_at,
+                next_run_at,
+            )
+            .await
+            {
+                Ok(true) => post_repo::create_post(&pool, &post).await.map(Some),
+                Ok(false) => Ok(None),
+                Err(e) => Err(e),
+            };
+            match posted {
+                Ok(Some(post_id)) => {
+                    log::info!("Posted scheduled post {} in {}", post_id, template.sub)
+                }
+                Ok(None) => {}
+                Err(e) => log::error!("Scheduled post {} failed: {}", template.id, e),
+            }
+        }
+    }
+}
+
+/// Clears out sub bans that have run out.
+pub async fn expire_sub_bans(pool: PgPool, every: Duration) {
+    let mut ticker = interval(every);
+
+    loop {
+        ticker.tick().await;
+        match sub_repo::expire_sub_bans(&pool).await {
+            Ok(0) => {}
+            Ok(expired) => log::info!("Expired {} sub ban(s)", expired),
+            Err(e) => log::error!("Sub ban expiry failed: {}", e),
+        }
+    }
+}
+
+/// Clears out site-wide suspensions that have run out, unsuspending the accounts.
+pub async fn expire_suspensions(pool: PgPool, every: Duration) {
+    let mut ticker = interval(every);
+
+    loop {
+        ticker.tick().await;
+        match suspension_repo::expire_suspensions(&pool).await {
+            Ok(0) => {}
+            Ok(expired) => log::info!("Expired {} suspension(s)", expired),
+            Err(e) => log::error!("Suspension expiry failed: {}", e),
+        }
+    }
+}
+
+/// Recounts the subscriber growth and activity behind trending and popular subs.
+pub async fn refresh_sub_stats(pool: PgPool, every: Duration) {
+    let mut ticker = interval(every);
+
+    loop {
+        ticker.tick().await;
+        let since = Utc::now() - chrono::Duration::days(SUB_STATS_WINDOW_DAYS);
+        if let Err(e) = sub_repo::refresh_sub_stats(&pool, since).await {
+            log::error!("Sub stats refresh failed: {}", e);
+        }
+    }
+}
+
+/// Archives published posts once they are older than `after`.
+pub async fn archive_old_posts(pool: PgPool, after: chrono::Duration, every: Duration) {
+    let mut ticker = interval(every);
+
+    loop {
+        ticker.tick().await;
+        match post_repo::archive_posts_before(&pool, Utc::now() - after).await {
+            Ok(0) => {}
+            Ok(archived) => log::info!("Archived {} post(s)", archived),
+            Err(e) => log::error!("Post archiving failed: {}", e),
+        }
+    }
+}
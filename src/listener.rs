@@ -0,0 +1,59 @@
+use crate::config::UnixSocketConfig;
+use listenfd::ListenFd;
+use std::fs;
+use std::io;
+use std::net::TcpListener;
+use std::os::unix::fs::{chown, FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixListener;
+
+pub enum InheritedListener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// Collects listening sockets passed in by a supervisor (systemd socket activation
+/// via `LISTEN_FDS`, or tools like systemfd). A supervisor keeps these sockets open
+/// across restarts, so connections queue in the kernel while the new process starts
+/// instead of being refused.
+pub fn inherited_listeners() -> io::Result<Vec<InheritedListener>> {
+    let mut fds = ListenFd::from_env();
+    let mut listeners = Vec::with_capacity(fds.len());
+
+    for index in 0..fds.len() {
+        // A failed take leaves the descriptor in place, so fall through to the Unix check.
+        if let Ok(Some(listener)) = fds.take_tcp_listener(index) {
+            listeners.push(InheritedListener::Tcp(listener));
+        } else if let Some(listener) = fds.take_unix_listener(index)? {
+            listeners.push(InheritedListener::Unix(listener));
+        }
+    }
+
+    Ok(listeners)
+}
+
+/// Removes a socket file left behind by a previous run so the path can be bound again.
+/// Refuses to touch anything at the path that isn't a socket.
+pub fn remove_stale_socket(socket: &UnixSocketConfig) -> io::Result<()> {
+    match fs::symlink_metadata(&socket.path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(&socket.path),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", socket.path.display()),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Applies the configured mode and ownership once the socket file has been created.
+pub fn apply_socket_permissions(socket: &UnixSocketConfig) -> io::Result<()> {
+    if let Some(mode) = socket.mode {
+        fs::set_permissions(&socket.path, fs::Permissions::from_mode(mode))?;
+    }
+
+    if socket.owner.is_some() || socket.group.is_some() {
+        chown(&socket.path, socket.owner, socket.group)?;
+    }
+
+    Ok(())
+}
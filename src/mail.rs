@@ -0,0 +1,44 @@
+use crate::config::EmailConfig;
+use lettre::message::Mailbox;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::error::Error;
+
+pub type MailError = Box<dyn Error + Send + Sync>;
+
+/// Sends plain-text mail over SMTP, or logs it when no SMTP server is configured.
+pub struct Mailer {
+    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(config: &EmailConfig) -> Self {
+        let transport = config.smtp_url.as_deref().map(|url| {
+            AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+                .unwrap_or_else(|e| panic!("SMTP_URL is invalid: {}", e))
+                .build()
+        });
+        let from = config
+            .from
+            .parse()
+            .unwrap_or_else(|e| panic!("MAIL_FROM is invalid: {}", e));
+
+        Mailer { transport, from }
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), MailError> {
+        let Some(transport) = &self.transport else {
+            log::info!("Mail to {} ({}):\n{}", to, subject, body);
+            return Ok(());
+        };
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject)
+            .body(body)?;
+        transport.send(message).await?;
+
+        Ok(())
+    }
+}
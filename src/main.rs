@@ -1,38 +1,260 @@
 mod api;
+mod audit;
+mod auth;
+mod config;
+mod cors;
+mod csrf;
+mod degraded;
+mod error;
+mod i18n;
+mod jobs;
+mod link_preview;
+mod listener;
+mod mail;
+mod media;
 mod model;
+mod quota;
+mod rate_limit;
+mod render;
 mod repo;
 mod routing;
+mod spa;
+mod spam;
+#[cfg(test)]
+mod test_support;
+mod timeout;
+mod tls;
+mod ui;
 
-use actix_web::{middleware::Logger, web::Data, App, HttpServer};
+use actix_web::{
+    middleware::{from_fn, Logger},
+    web::Data,
+    App, HttpServer,
+};
 
-use sqlx::postgres::PgPoolOptions;
-use std::env;
+use config::{Config, RuntimeSettings};
+use listener::InheritedListener;
+use std::sync::Arc;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    std::env::set_var("RUST_LOG", "debug");
     std::env::set_var("RUST_BACKTRACE", "1");
-    env_logger::init();
+    // Verbosity is governed by the runtime config's log_level so it can change without a restart
+    env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Trace)
+        .init();
 
-    let database_url = env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set in .env or environment variables");
-    let pool = PgPoolOptions::new()
+    let config = Config::from_env();
+    let runtime_settings = Data::new(
+        RuntimeSettings::load(config.runtime_config_path.clone())
+            .expect("Could not load the runtime configuration"),
+    );
+    actix_web::rt::spawn(config::reload_on_sighup(runtime_settings.clone()));
+
+    let pool = audit::pool_options()
         .max_connections(5)
-        .connect(&database_url)
+        .acquire_timeout(config.degraded_mode.acquire_timeout)
+        .connect(&config.database_url)
         .await
         .expect("Could not connect to the database");
 
-    HttpServer::new(move || {
+    let database_health = Data::new(degraded::DatabaseHealth::new(&config.degraded_mode));
+    actix_web::rt::spawn(degraded::monitor_database(
+        pool.clone(),
+        database_health.clone(),
+        config.degraded_mode.clone(),
+    ));
+
+    actix_web::rt::spawn(jobs::expire_premium_memberships(
+        pool.clone(),
+        config.premium.expiry_interval,
+    ));
+    actix_web::rt::spawn(jobs::publish_scheduled_posts(
+        pool.clone(),
+        config.posts.schedule_interval,
+    ));
+    actix_web::rt::spawn(jobs::run_post_templates(
+        pool.clone(),
+        config.posts.schedule_interval,
+    ));
+    actix_web::rt::spawn(jobs::expire_sub_bans(
+        pool.clone(),
+        config.posts.schedule_interval,
+    ));
+    actix_web::rt::spawn(jobs::expire_suspensions(
+        pool.clone(),
+        config.posts.schedule_interval,
+    ));
+    actix_web::rt::spawn(jobs::refresh_sub_stats(
+        pool.clone(),
+        config.subs.stats_interval,
+    ));
+    if let Some(after) = config.posts.archive_after {
+        actix_web::rt::spawn(jobs::archive_old_posts(
+            pool.clone(),
+            after,
+            config.posts.archive_interval,
+        ));
+    }
+
+    let app_pool = pool.clone();
+    let repo_pool = Arc::new(pool.clone());
+    let cors_config = config.cors.clone();
+    let static_dir = config.static_dir.clone();
+    let timeout_config = config.timeouts.clone();
+    let default_locale = config.default_locale.clone();
+    let premium_config = Data::new(config.premium.clone());
+    let post_config = Data::new(config.posts.clone());
+    let spam_config = Data::new(config.spam.clone());
+    let jwt_keys = Data::new(auth::JwtKeys::new(&config.auth));
+    let session_config = Data::new(config.session.clone());
+    let auth_config = Data::new(config.auth.clone());
+    let oauth_config = Data::new(config.oauth.clone());
+    let email_config = Data::new(config.email.clone());
+    let password_policy = Data::new(config.password_policy.clone());
+    let mailer = Data::new(mail::Mailer::new(&config.email));
+    let rate_limiter = Data::new(rate_limit::RateLimiter::default());
+    let proxy_config = Data::new(config.proxies.clone());
+    let media_store = Data::from(media::media_store(&config.media));
+    let media_scanner = Data::new(media::scan::MediaScanner::new(&config.media));
+    let local_media_dir = config.media.s3.is_none().then(|| config.media.dir.clone());
+    if let Some(dir) = &local_media_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut server = HttpServer::new(move || {
         let logger = Logger::default();
-        App::new()
+        let timeouts = timeout_config.clone();
+        let health = database_health.clone();
+        let locale = default_locale.clone();
+        let quota_pool = app_pool.clone();
+        let quota_settings = runtime_settings.clone();
+        let limiter = rate_limiter.clone();
+        let limit_settings = runtime_settings.clone();
+        let csrf_session = session_config.clone();
+        let csrf_auth = auth_config.clone();
+        let app = App::new()
+            .wrap(from_fn(audit::record_actor))
+            .wrap(from_fn(move |req, next| {
+                csrf::enforce_csrf(csrf_session.clone(), csrf_auth.clone(), req, next)
+            }))
+            .wrap(from_fn(move |req, next| {
+                quota::enforce_quota(quota_pool.clone(), quota_settings.clone(), req, next)
+            }))
+            // Outside the quota check, so throttled requests don't use up quota
+            .wrap(from_fn(move |req, next| {
+                rate_limit::enforce_rate_limits(limiter.clone(), limit_settings.clone(), req, next)
+            }))
+            .wrap(from_fn(move |req, next| {
+                degraded::degraded_mode(health.clone(), req, next)
+            }))
+            .wrap(from_fn(move |req, next| {
+                timeout::enforce_timeout(timeouts.clone(), req, next)
+            }))
+            .wrap(cors::cors_middleware(&cors_config))
+            .wrap(from_fn(move |req, next| {
+                i18n::localize_errors(locale.clone(), req, next)
+            }))
             .wrap(logger)
-            .app_data(Data::new(pool.clone()))
+            .app_data(Data::new(app_pool.clone()))
+            .app_data(runtime_settings.clone())
+            .app_data(premium_config.clone())
+            .app_data(post_config.clone())
+            .app_data(spam_config.clone())
+            .app_data(jwt_keys.clone())
+            .app_data(session_config.clone())
+            .app_data(proxy_config.clone())
+            .app_data(auth_config.clone())
+            .app_data(oauth_config.clone())
+            .app_data(email_config.clone())
+            .app_data(password_policy.clone())
+            .app_data(mailer.clone())
+            .app_data(media_store.clone())
+            .app_data(media_scanner.clone())
+            .configure(|cfg| repo::register(cfg, repo_pool.clone()))
             .configure(routing::configure_post_routes)
             .configure(routing::configure_comment_routes)
+            .configure(routing::configure_auth_routes)
+            .configure(routing::configure_oauth_routes)
             .configure(routing::configure_user_routes)
             .configure(routing::configure_sub_routes)
-    })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+            .configure(routing::configure_audit_routes)
+            .configure(routing::configure_moderation_routes)
+            .configure(routing::configure_legal_routes)
+            .configure(routing::configure_config_routes)
+            .configure(routing::configure_experiment_routes)
+            .configure(routing::configure_api_key_routes)
+            .configure(routing::configure_premium_routes)
+            .configure(routing::configure_filter_routes)
+            .configure(routing::configure_account_requirement_routes)
+            .configure(routing::configure_appeal_routes)
+            .configure(routing::configure_automod_routes)
+            .configure(routing::configure_media_routes)
+            .configure(routing::configure_saved_routes)
+            .configure(routing::configure_flair_routes)
+            .configure(routing::configure_post_template_routes)
+            .configure(routing::configure_rule_routes)
+            .configure(routing::configure_ban_routes)
+            .configure(routing::configure_mod_note_routes)
+            .configure(routing::configure_suspension_routes)
+            .configure(routing::configure_report_routes)
+            .configure(routing::configure_wiki_routes)
+            .configure(routing::configure_feed_routes)
+            .configure(routing::configure_search_routes)
+            .configure(routing::configure_render_routes)
+            .configure(routing::configure_ui_routes);
+
+        let app = match &local_media_dir {
+            Some(dir) => app.service(media::local::local_media_files(dir)),
+            None => app,
+        };
+        // Registered last so API routes always take precedence over frontend files
+        match &static_dir {
+            Some(dir) => app.service(spa::spa_files(dir)),
+            None => app,
+        }
+    });
+
+    let tls_config = config
+        .tls
+        .as_ref()
+        .map(tls::load_server_config)
+        .transpose()?;
+
+    let inherited = listener::inherited_listeners()?;
+    if inherited.is_empty() {
+        if config.tcp_enabled {
+            let address = (config.host.as_str(), config.port);
+            server = match &tls_config {
+                Some(tls_config) => server.bind_rustls_0_23(address, tls_config.clone())?,
+                None => server.bind(address)?,
+            };
+        }
+
+        if let Some(socket) = &config.unix_socket {
+            listener::remove_stale_socket(socket)?;
+            server = server.bind_uds(&socket.path)?;
+            listener::apply_socket_permissions(socket)?;
+        }
+    } else {
+        log::info!("Using {} inherited listener(s)", inherited.len());
+        for inherited_listener in inherited {
+            server = match inherited_listener {
+                InheritedListener::Tcp(tcp) => match &tls_config {
+                    Some(tls_config) => server.listen_rustls_0_23(tcp, tls_config.clone())?,
+                    None => server.listen(tcp)?,
+                },
+                InheritedListener::Unix(unix) => server.listen_uds(unix)?,
+            };
+        }
+    }
+
+    // On SIGTERM actix stops accepting, then gives in-flight requests up to the
+    // shutdown timeout to finish before the pool is closed.
+    server
+        .shutdown_timeout(config.shutdown_timeout_secs)
+        .run()
+        .await?;
+    pool.close().await;
+
+    Ok(())
 }
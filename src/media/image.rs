@@ -0,0 +1,160 @@
+//! Decoded images and scaling them down for renditions.
+
+use crate::media::png;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::io::Cursor;
+
+/// Largest image, in pixels, that will be decoded. Bigger uploads are kept but get no
+/// renditions.
+pub const MAX_PIXELS: usize = 25_000_000;
+
+/// 8-bit RGBA pixels, row by row.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Image {
+    /// The upload as an image, for the types renditions can be made of.
+    pub fn decode(content_type: &str, bytes: &[u8]) -> Option<Self> {
+        match content_type {
+            "image/png" => png::decode(bytes),
+            "image/jpeg" => decode_as(ImageFormat::Jpeg, bytes),
+            "image/gif" => decode_as(ImageFormat::Gif, bytes),
+            "image/webp" => decode_as(ImageFormat::WebP, bytes),
+            _ => None,
+        }
+    }
+
+    /// The image scaled down to fit a `max` by `max` box, keeping its aspect ratio, or
+    /// `None` if it already fits. Each pixel averages the area it covers, weighted by
+    /// alpha so transparent pixels don't darken the edges.
+    pub fn fit_within(&self, max: u32) -> Option<Image> {
+        if self.width <= max && self.height <= max {
+            return None;
+        }
+        let (width, height) = if self.width >= self.height {
+            (max, scaled(self.height, max, self.width))
+        } else {
+            (scaled(self.width, max, self.height), max)
+        };
+
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            let (top, bottom) = span(y, height, self.height);
+            for x in 0..width {
+                let (left, right) = span(x, width, self.width);
+                let mut sums = [0u64; 4];
+                for source_y in top..bottom {
+                    let row = source_y as usize * self.width as usize;
+                    for source_x in left..right {
+                        let pixel = &self.rgba[(row + source_x as usize) * 4..][..4];
+                        let alpha = pixel[3] as u64;
+                        sums[0] += pixel[0] as u64 * alpha;
+                        sums[1] += pixel[1] as u64 * alpha;
+                        sums[2] += pixel[2] as u64 * alpha;
+                        sums[3] += alpha;
+                    }
+                }
+                let count = ((bottom - top) * (right - left)) as u64;
+                let channel = |sum: u64| match sums[3] {
+                    0 => 0,
+                    alpha => (sum / alpha) as u8,
+                };
+                rgba.extend_from_slice(&[
+                    channel(sums[0]),
+                    channel(sums[1]),
+                    channel(sums[2]),
+                    (sums[3] / count) as u8,
+                ]);
+            }
+        }
+
+        Some(Image {
+            width,
+            height,
+            rgba,
+        })
+    }
+}
+
+/// Decodes the first frame of a JPEG, GIF or WebP, or `None` if it is malformed or
+/// larger than `MAX_PIXELS`.
+fn decode_as(format: ImageFormat, bytes: &[u8]) -> Option<Image> {
+    let decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .ok()?;
+    let (width, height) = decoder.dimensions();
+    if width == 0 || height == 0 || width as usize * height as usize > MAX_PIXELS {
+        return None;
+    }
+    let rgba = DynamicImage::from_decoder(decoder).ok()?.into_rgba8();
+
+    Some(Image {
+        width,
+        height,
+        rgba: rgba.into_raw(),
+    })
+}
+
+/// `length` scaled by `to / from`, at least 1.
+fn scaled(length: u32, to: u32, from: u32) -> u32 {
+    ((length as u64 * to as u64 / from as u64) as u32).max(1)
+}
+
+/// The source rows or columns that target row or column `index` covers.
+fn span(index: u32, target: u32, source: u32) -> (u32, u32) {
+    let start = (index as u64 * source as u64 / target as u64) as u32;
+    let end = ((index as u64 + 1) * source as u64 / target as u64) as u32;
+    (start, end.max(start + 1))
+}
+
+#[cfg(test)]
+mod image_tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_within_keeps_the_aspect_ratio_and_averages() {
+        // 4x2: the left half red, the right half blue with a transparent pixel
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let clear = [0, 0, 0, 0];
+        let image = Image {
+            width: 4,
+            height: 2,
+            rgba: [red, red, blue, clear, red, red, blue, blue].concat(),
+        };
+
+        let small = image.fit_within(2).unwrap();
+        assert_eq!((small.width, small.height), (2, 1));
+        assert_eq!(small.rgba, [[255, 0, 0, 255], [0, 0, 255, 191]].concat());
+        assert!(image.fit_within(4).is_none());
+    }
+
+    #[test]
+    fn test_jpeg_gif_and_webp_are_decoded() {
+        let pixels =
+            image::RgbaImage::from_raw(2, 1, vec![255, 0, 0, 255, 0, 0, 255, 255]).unwrap();
+        for (content_type, format) in [
+            ("image/jpeg", ImageFormat::Jpeg),
+            ("image/gif", ImageFormat::Gif),
+            ("image/webp", ImageFormat::WebP),
+        ] {
+            let mut bytes = Cursor::new(Vec::new());
+            // JPEG has no alpha channel
+            let source = match format {
+                ImageFormat::Jpeg => DynamicImage::from(pixels.clone()).into_rgb8().into(),
+                _ => DynamicImage::from(pixels.clone()),
+            };
+            source.write_to(&mut bytes, format).unwrap();
+
+            let decoded = Image::decode(content_type, bytes.get_ref()).unwrap();
+            assert_eq!((decoded.width, decoded.height), (2, 1), "{}", content_type);
+            if format != ImageFormat::Jpeg {
+                assert_eq!(decoded.rgba, pixels.as_raw().as_slice(), "{}", content_type);
+            }
+        }
+        assert!(Image::decode("image/jpeg", b"\xff\xd8not a jpeg").is_none());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::media::{MediaStore, StoreError};
+use actix_files::Files;
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+/// Where the forum serves the files of a [`LocalStore`].
+pub const LOCAL_MEDIA_PATH: &str = "/media/files";
+
+/// Files in a directory on this machine, served at [`LOCAL_MEDIA_PATH`]. Only suitable
+/// for a single server.
+pub struct LocalStore {
+    dir: PathBuf,
+    public_url: String,
+}
+
+impl LocalStore {
+    pub fn new(dir: PathBuf, public_url: String) -> Self {
+        LocalStore { dir, public_url }
+    }
+}
+
+#[async_trait(?Send)]
+impl MediaStore for LocalStore {
+    async fn put(&self, key: &str, _content_type: &str, body: Bytes) -> Result<(), StoreError> {
+        let dir = self.dir.clone();
+        let path = dir.join(key);
+        actix_web::web::block(move || {
+            std::fs::create_dir_all(&dir)?;
+            std::fs::write(path, body)
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_url, key)
+    }
+}
+
+/// Serves the uploads in `dir`. Directories aren't listed, so files can only be
+/// fetched by their key.
+pub fn local_media_files(dir: &Path) -> Files {
+    Files::new(LOCAL_MEDIA_PATH, dir)
+}
+
+#[cfg(test)]
+mod local_store_tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_put_creates_the_directory_and_writes_the_file() {
+        let dir = std::env::temp_dir().join(format!("ferris-media-{}", uuid::Uuid::new_v4()));
+        let store = LocalStore::new(dir.clone(), "http://localhost/media/files".to_string());
+
+        store
+            .put("crab.png", "image/png", Bytes::from_static(b"\x89PNG"))
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read(dir.join("crab.png")).unwrap(), b"\x89PNG");
+        assert_eq!(
+            store.url("crab.png"),
+            "http://localhost/media/files/crab.png"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,38 @@
+//! Storage for uploaded media.
+
+pub mod image;
+pub mod local;
+pub mod multipart;
+pub mod png;
+pub mod rendition;
+pub mod s3;
+pub mod sanitize;
+pub mod scan;
+
+use crate::config::MediaConfig;
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::Arc;
+
+pub type StoreError = Box<dyn Error>;
+
+/// Somewhere uploaded files can be written under a key and later fetched from.
+#[async_trait(?Send)]
+pub trait MediaStore: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, body: Bytes) -> Result<(), StoreError>;
+
+    /// Where clients fetch the file stored under `key`.
+    fn url(&self, key: &str) -> String;
+}
+
+/// The configured S3 bucket, or the local media directory without one.
+pub fn media_store(config: &MediaConfig) -> Arc<dyn MediaStore> {
+    match &config.s3 {
+        Some(s3) => Arc::new(s3::S3Store::new(s3.clone(), config.public_url.clone())),
+        None => Arc::new(local::LocalStore::new(
+            config.dir.clone(),
+            config.public_url.clone(),
+        )),
+    }
+}
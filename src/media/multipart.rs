@@ -0,0 +1,94 @@
+//! Just enough `multipart/form-data` parsing to take one file out of an upload form.
+
+use actix_web::mime::Mime;
+use actix_web::web::Bytes;
+
+pub struct FilePart {
+    /// The type the client declared for the file, if any.
+    pub content_type: Option<String>,
+    pub bytes: Bytes,
+}
+
+/// The part of the form named `field`, or `None` if the body isn't well-formed
+/// `multipart/form-data` or has no such part.
+pub fn file_part(content_type: &Mime, body: &Bytes, field: &str) -> Option<FilePart> {
+    if content_type.type_() != "multipart" || content_type.subtype() != "form-data" {
+        return None;
+    }
+    let boundary = content_type.get_param("boundary")?;
+    let delimiter = format!("\r\n--{}", boundary.as_str());
+
+    // The first delimiter may start the body, without the line break before it
+    let mut position = find(body, &delimiter.as_bytes()[2..])? + delimiter.len() - 2;
+    loop {
+        let rest = &body[position..];
+        if rest.starts_with(b"--") {
+            return None;
+        }
+        let headers_start = position + rest.strip_prefix(b"\r\n").map(|_| 2)?;
+        let headers_end = headers_start + find(&body[headers_start..], b"\r\n\r\n")?;
+        let content_start = headers_end + 4;
+        let content_end = content_start + find(&body[content_start..], delimiter.as_bytes())?;
+
+        let headers = std::str::from_utf8(&body[headers_start..headers_end]).ok()?;
+        let mut name = None;
+        let mut part_type = None;
+        for line in headers.split("\r\n") {
+            let (header, value) = line.split_once(':')?;
+            if header.eq_ignore_ascii_case("content-disposition") {
+                for param in value.split(';').skip(1) {
+                    let Some((key, value)) = param.trim().split_once('=') else {
+                        continue;
+                    };
+                    if key.trim() == "name" {
+                        name = Some(value.trim_matches('"').to_string());
+                    }
+                }
+            } else if header.eq_ignore_ascii_case("content-type") {
+                part_type = Some(value.trim().to_ascii_lowercase());
+            }
+        }
+
+        if name.as_deref() == Some(field) {
+            return Some(FilePart {
+                content_type: part_type,
+                bytes: body.slice(content_start..content_end),
+            });
+        }
+        position = content_end + delimiter.len();
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod multipart_tests {
+    use super::*;
+
+    #[test]
+    fn test_file_part_is_found_among_other_fields() {
+        let content_type: Mime = "multipart/form-data; boundary=XyZ".parse().unwrap();
+        let body = Bytes::from_static(
+            b"--XyZ\r\n\
+              Content-Disposition: form-data; name=\"caption\"\r\n\r\n\
+              a crab\r\n\
+              --XyZ\r\n\
+              Content-Disposition: form-data; name=\"file\"; filename=\"crab.png\"\r\n\
+              Content-Type: image/png\r\n\r\n\
+              \x89PNG\r\n--X\r\n\
+              --XyZ--\r\n",
+        );
+
+        let part = file_part(&content_type, &body, "file").unwrap();
+        assert_eq!(part.content_type.as_deref(), Some("image/png"));
+        assert_eq!(&part.bytes[..], b"\x89PNG\r\n--X");
+
+        assert!(file_part(&content_type, &body, "avatar").is_none());
+        let json: Mime = "application/json".parse().unwrap();
+        assert!(file_part(&json, &body, "file").is_none());
+    }
+}
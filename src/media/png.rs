@@ -0,0 +1,248 @@
+//! A PNG codec covering what renditions need: 8-bit, non-interlaced images in, RGBA
+//! out.
+
+use crate::media::image::{Image, MAX_PIXELS};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use std::io::{Read, Write};
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The image, or `None` if it is malformed, too large, or uses a bit depth or
+/// interlacing this decoder doesn't handle.
+pub fn decode(bytes: &[u8]) -> Option<Image> {
+    let mut rest = bytes.strip_prefix(SIGNATURE)?;
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut data = Vec::new();
+
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes(rest[..4].try_into().ok()?) as usize;
+        let kind = &rest[4..8];
+        let chunk = rest.get(8..8 + length)?;
+        rest = rest.get(12 + length..)?;
+        match kind {
+            b"IHDR" => header = Some(Header::parse(chunk)?),
+            b"PLTE" => palette = chunk,
+            b"tRNS" => transparency = chunk,
+            b"IDAT" => data.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+
+    let header = header?;
+    let channels = header.channels()?;
+    if header.bit_depth != 8 || header.interlaced {
+        return None;
+    }
+    let (width, height) = (header.width as usize, header.height as usize);
+    if width == 0 || height == 0 || width * height > MAX_PIXELS {
+        return None;
+    }
+
+    let stride = width * channels;
+    let expected = height * (stride + 1);
+    let mut filtered = Vec::with_capacity(expected);
+    ZlibDecoder::new(data.as_slice())
+        .take(expected as u64)
+        .read_to_end(&mut filtered)
+        .ok()?;
+    if filtered.len() != expected {
+        return None;
+    }
+    let pixels = unfilter(&filtered, stride, channels)?;
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for pixel in pixels.chunks_exact(channels) {
+        match header.color_type {
+            0 => rgba.extend_from_slice(&[pixel[0], pixel[0], pixel[0], 255]),
+            2 => rgba.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 255]),
+            3 => {
+                let index = pixel[0] as usize;
+                let color = palette.get(index * 3..index * 3 + 3)?;
+                let alpha = transparency.get(index).copied().unwrap_or(255);
+                rgba.extend_from_slice(&[color[0], color[1], color[2], alpha]);
+            }
+            4 => rgba.extend_from_slice(&[pixel[0], pixel[0], pixel[0], pixel[1]]),
+            _ => rgba.extend_from_slice(pixel),
+        }
+    }
+
+    Some(Image {
+        width: header.width,
+        height: header.height,
+        rgba,
+    })
+}
+
+/// The image as an RGBA PNG.
+pub fn encode(image: &Image) -> Vec<u8> {
+    let stride = image.width as usize * 4;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let mut filtered = Vec::with_capacity(stride + 1);
+    for row in image.rgba.chunks_exact(stride) {
+        // Sub filtering: each byte as the difference from the pixel to its left
+        filtered.clear();
+        filtered.push(1);
+        filtered.extend_from_slice(&row[..4]);
+        filtered.extend(
+            row.windows(5)
+                .map(|window| window[4].wrapping_sub(window[0])),
+        );
+        // Writing into a Vec can't fail
+        encoder.write_all(&filtered).unwrap();
+    }
+    let data = encoder.finish().unwrap();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&image.width.to_be_bytes());
+    header.extend_from_slice(&image.height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &data);
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn parse(chunk: &[u8]) -> Option<Self> {
+        if chunk.len() != 13 {
+            return None;
+        }
+
+        Some(Header {
+            width: u32::from_be_bytes(chunk[..4].try_into().ok()?),
+            height: u32::from_be_bytes(chunk[4..8].try_into().ok()?),
+            bit_depth: chunk[8],
+            color_type: chunk[9],
+            interlaced: chunk[12] != 0,
+        })
+    }
+
+    fn channels(&self) -> Option<usize> {
+        match self.color_type {
+            0 | 3 => Some(1),
+            2 => Some(3),
+            4 => Some(2),
+            6 => Some(4),
+            _ => None,
+        }
+    }
+}
+
+/// Undoes the per-row filters, leaving the raw samples.
+fn unfilter(filtered: &[u8], stride: usize, bpp: usize) -> Option<Vec<u8>> {
+    let mut pixels = Vec::with_capacity(filtered.len());
+    let mut previous = vec![0u8; stride];
+
+    for line in filtered.chunks_exact(stride + 1) {
+        let (filter, line) = (line[0], &line[1..]);
+        let mut row = vec![0u8; stride];
+        for i in 0..stride {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            let up = previous[i];
+            let up_left = if i >= bpp { previous[i - bpp] } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return None,
+            };
+            row[i] = line[i].wrapping_add(predicted);
+        }
+        pixels.extend_from_slice(&row);
+        previous = row;
+    }
+
+    Some(pixels)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (to_left, to_up, to_up_left) = (
+        (estimate - left as i16).abs(),
+        (estimate - up as i16).abs(),
+        (estimate - up_left as i16).abs(),
+    );
+
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+#[cfg(test)]
+mod png_tests {
+    use super::*;
+
+    #[test]
+    fn test_encoded_images_decode_to_the_same_pixels() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            rgba: (0..24).map(|i| (i * 10) as u8).collect(),
+        };
+
+        let decoded = decode(&encode(&image)).unwrap();
+        assert_eq!((decoded.width, decoded.height), (3, 2));
+        assert_eq!(decoded.rgba, image.rgba);
+    }
+
+    #[test]
+    fn test_filtered_palette_images_are_expanded_to_rgba() {
+        let mut png = SIGNATURE.to_vec();
+        // 2x2, 8-bit palette
+        write_chunk(&mut png, b"IHDR", &[0, 0, 0, 2, 0, 0, 0, 2, 8, 3, 0, 0, 0]);
+        write_chunk(&mut png, b"PLTE", &[255, 0, 0, 0, 0, 255]);
+        write_chunk(&mut png, b"tRNS", &[128]);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        // A Sub-filtered row then an Up-filtered one
+        encoder.write_all(&[1, 0, 1, 2, 1, 255]).unwrap();
+        write_chunk(&mut png, b"IDAT", &encoder.finish().unwrap());
+        write_chunk(&mut png, b"IEND", &[]);
+
+        let image = decode(&png).unwrap();
+        assert_eq!(
+            image.rgba,
+            [
+                [255, 0, 0, 128],
+                [0, 0, 255, 255],
+                [0, 0, 255, 255],
+                [255, 0, 0, 128]
+            ]
+            .concat()
+        );
+        assert!(decode(b"\x89PNG\r\n\x1a\nnot really").is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::media::image::Image;
+use crate::media::{png, MediaStore};
+use crate::model::media::{Media, Rendition, RenditionKind};
+use crate::repo::media as media_repo;
+use actix_web::web::{self, Bytes, Data};
+use sqlx::PgPool;
+
+/// Makes the upload's renditions and stores them next to the original. Runs after the
+/// upload has been answered; failures are only logged, leaving clients with the
+/// original.
+pub async fn generate_renditions(
+    pool: PgPool,
+    store: Data<dyn MediaStore>,
+    media: Media,
+    original: Bytes,
+) {
+    let content_type = media.content_type.clone();
+    let scaled = web::block(move || {
+        let Some(image) = Image::decode(&content_type, &original) else {
+            return Vec::new();
+        };
+        RenditionKind::ALL
+            .into_iter()
+            .filter_map(|kind| {
+                let scaled = image.fit_within(kind.max_size())?;
+                Some((kind, scaled.width, scaled.height, png::encode(&scaled)))
+            })
+            .collect()
+    })
+    .await;
+    let scaled = match scaled {
+        Ok(scaled) => scaled,
+        Err(e) => {
+            log::error!("Could not scale media {}: {}", media.id, e);
+            return;
+        }
+    };
+
+    for (kind, width, height, bytes) in scaled {
+        let storage_key = format!("{}_{}.png", media.id, kind);
+        if let Err(e) = store.put(&storage_key, "image/png", bytes.into()).await {
+            log::error!("Could not store the {} of media {}: {}", kind, media.id, e);
+            continue;
+        }
+        let rendition = Rendition {
+            kind,
+            url: store.url(&storage_key),
+            storage_key,
+            width: width as i32,
+            height: height as i32,
+        };
+        if let Err(e) = media_repo::save_rendition(&pool, media.id, &rendition).await {
+            log::error!("Could not save the {} of media {}: {}", kind, media.id, e);
+        }
+    }
+}
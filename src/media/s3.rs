@@ -0,0 +1,133 @@
+use crate::config::S3Config;
+use crate::media::{MediaStore, StoreError};
+use actix_web::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use url::Url;
+
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Objects in an S3-compatible bucket, written with signature version 4 requests.
+pub struct S3Store {
+    config: S3Config,
+    public_url: String,
+}
+
+impl S3Store {
+    pub fn new(config: S3Config, public_url: String) -> Self {
+        S3Store { config, public_url }
+    }
+}
+
+#[async_trait(?Send)]
+impl MediaStore for S3Store {
+    async fn put(&self, key: &str, content_type: &str, body: Bytes) -> Result<(), StoreError> {
+        let path = format!("/{}/{}", self.config.bucket, key);
+        let endpoint = Url::parse(&self.config.endpoint)?;
+        let host = match endpoint.port() {
+            Some(port) => format!("{}:{}", endpoint.host_str().unwrap_or_default(), port),
+            None => endpoint.host_str().unwrap_or_default().to_string(),
+        };
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let signed = sign_request(&self.config, "PUT", &host, &path, &payload_hash, Utc::now());
+
+        let http = awc::Client::builder().timeout(UPLOAD_TIMEOUT).finish();
+        let response = http
+            .put(format!("{}{}", self.config.endpoint, path))
+            .insert_header((CONTENT_TYPE, content_type))
+            .insert_header(("x-amz-content-sha256", payload_hash))
+            .insert_header(("x-amz-date", signed.amz_date))
+            .insert_header((AUTHORIZATION, signed.authorization))
+            .send_body(body)
+            .await?;
+        if !response.status().is_success() {
+            return Err(format!("S3 answered {}", response.status()).into());
+        }
+
+        Ok(())
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_url, key)
+    }
+}
+
+struct SignedRequest {
+    amz_date: String,
+    authorization: String,
+}
+
+/// Signs a request whose only signed headers are `host`, `x-amz-content-sha256` and
+/// `x-amz-date`.
+fn sign_request(
+    config: &S3Config,
+    method: &str,
+    host: &str,
+    path: &str,
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> SignedRequest {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+
+    let canonical_request = format!(
+        "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        method, path, host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = signing_key(&config.secret_access_key, &date, &config.region, "s3");
+    let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+    SignedRequest {
+        authorization: format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            config.access_key_id, scope, signed_headers, signature
+        ),
+        amz_date,
+    }
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let key = hmac(&key, region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    hmac(&key, b"aws4_request")
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod s3_tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key_matches_the_aws_example() {
+        // From the AWS documentation on deriving a signature version 4 signing key
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+
+        assert_eq!(
+            hex::encode(key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! Removing metadata such as EXIF and GPS tags from uploads before they are stored.
+//!
+//! PNGs are decoded and re-encoded, which keeps nothing but the pixels. The other
+//! formats are rewritten without their metadata segments, leaving the image data
+//! untouched, except for JPEGs and WebPs whose EXIF orientation turns them: those are
+//! decoded, turned upright and re-encoded, since the orientation goes with the EXIF
+//! data. GIFs carry no orientation.
+
+use crate::media::image::MAX_PIXELS;
+use crate::media::png;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::io::Cursor;
+
+/// Quality of JPEGs re-encoded to apply their orientation.
+const JPEG_QUALITY: u8 = 90;
+
+/// The upload without its metadata, or `None` if it isn't a well-formed file of its
+/// type.
+pub fn sanitize(content_type: &str, bytes: &[u8]) -> Option<Vec<u8>> {
+    match content_type {
+        "image/png" => sanitize_png(bytes),
+        "image/jpeg" => reorient(ImageFormat::Jpeg, bytes).or_else(|| strip_jpeg(bytes)),
+        "image/gif" => strip_gif(bytes),
+        "image/webp" => reorient(ImageFormat::WebP, bytes).or_else(|| strip_webp(bytes)),
+        _ => None,
+    }
+}
+
+/// Chunks that only carry metadata: EXIF, text fields and the modification time.
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+fn sanitize_png(bytes: &[u8]) -> Option<Vec<u8>> {
+    let chunks = png_chunks(bytes)?;
+    // Re-encoding would keep only the first frame of an animated PNG
+    let animated = chunks.iter().any(|(kind, _)| *kind == b"acTL");
+    if !animated {
+        if let Some(image) = png::decode(bytes) {
+            return Some(png::encode(&image));
+        }
+    }
+
+    let mut stripped = bytes[..8].to_vec();
+    for (kind, chunk) in chunks {
+        if !PNG_METADATA_CHUNKS.contains(&kind) {
+            stripped.extend_from_slice(chunk);
+        }
+    }
+
+    Some(stripped)
+}
+
+/// Each chunk's type and its bytes, length and checksum included.
+fn png_chunks(bytes: &[u8]) -> Option<Vec<(&[u8; 4], &[u8])>> {
+    let mut rest = bytes.strip_prefix(b"\x89PNG\r\n\x1a\n")?;
+    let mut chunks = Vec::new();
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let kind = rest.get(4..8)?.try_into().ok()?;
+        let chunk = rest.get(..12 + length)?;
+        chunks.push((kind, chunk));
+        rest = &rest[chunk.len()..];
+        if kind == b"IEND" {
+            break;
+        }
+    }
+
+    Some(chunks)
+}
+
+/// The image turned upright and re-encoded without any metadata, or `None` if its
+/// orientation is already upright, or it is malformed or too large to decode, in which
+/// case it is only stripped.
+fn reorient(format: ImageFormat, bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .ok()?;
+    let orientation = decoder.orientation().ok()?;
+    let (width, height) = decoder.dimensions();
+    if orientation == Orientation::NoTransforms || width as usize * height as usize > MAX_PIXELS {
+        return None;
+    }
+    let mut image = DynamicImage::from_decoder(decoder).ok()?;
+    image.apply_orientation(orientation);
+
+    let mut encoded = Cursor::new(Vec::new());
+    match format {
+        ImageFormat::Jpeg => image
+            .into_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY)),
+        _ => image
+            .into_rgba8()
+            .write_with_encoder(WebPEncoder::new_lossless(&mut encoded)),
+    }
+    .ok()?;
+
+    Some(encoded.into_inner())
+}
+
+/// Drops the application segments other than JFIF (APP0), ICC profiles (APP2) and
+/// Adobe's colour transform (APP14), and comments.
+fn strip_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+    if !bytes.starts_with(b"\xff\xd8") {
+        return None;
+    }
+    let mut stripped = bytes[..2].to_vec();
+    let mut position = 2;
+
+    loop {
+        if *bytes.get(position)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(position + 1)?;
+        match marker {
+            // Fill bytes before a marker
+            0xff => {
+                position += 1;
+                continue;
+            }
+            // The compressed image data runs to the end
+            0xda | 0xd9 => {
+                stripped.extend_from_slice(&bytes[position..]);
+                return Some(stripped);
+            }
+            0x01 | 0xd0..=0xd7 => {
+                stripped.extend_from_slice(&bytes[position..position + 2]);
+                position += 2;
+                continue;
+            }
+            _ => {}
+        }
+
+        let length = u16::from_be_bytes(bytes.get(position + 2..position + 4)?.try_into().ok()?);
+        let segment = bytes.get(position..position + 2 + length as usize)?;
+        let metadata = matches!(marker, 0xe1 | 0xe3..=0xed | 0xef | 0xfe);
+        if !metadata {
+            stripped.extend_from_slice(segment);
+        }
+        position += segment.len();
+    }
+}
+
+/// Drops comments and application extensions, keeping the ones that make
+/// animations loop.
+fn strip_gif(bytes: &[u8]) -> Option<Vec<u8>> {
+    if !bytes.starts_with(b"GIF87a") && !bytes.starts_with(b"GIF89a") {
+        return None;
+    }
+    let flags = *bytes.get(10)?;
+    let mut position = 13 + color_table_length(flags);
+    let mut stripped = bytes.get(..position)?.to_vec();
+
+    loop {
+        match *bytes.get(position)? {
+            0x3b => {
+                stripped.push(0x3b);
+                return Some(stripped);
+            }
+            0x2c => {
+                let flags = *bytes.get(position + 9)?;
+                // The descriptor, any local colour table and the LZW code size
+                let data_start = position + 10 + color_table_length(flags) + 1;
+                let end = sub_blocks_end(bytes, data_start)?;
+                stripped.extend_from_slice(&bytes[position..end]);
+                position = end;
+            }
+            0x21 => {
+                let label = *bytes.get(position + 1)?;
+                let end = sub_blocks_end(bytes, position + 2)?;
+                let looping = label == 0xff
+                    && matches!(
+                        bytes.get(position + 3..position + 14),
+                        Some(b"NETSCAPE2.0" | b"ANIMEXTS1.0")
+                    );
+                if label == 0xf9 || label == 0x01 || looping {
+                    stripped.extend_from_slice(&bytes[position..end]);
+                }
+                position = end;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn color_table_length(flags: u8) -> usize {
+    if flags & 0x80 == 0 {
+        return 0;
+    }
+    3 << ((flags & 0x07) + 1)
+}
+
+/// Where the data sub-blocks starting at `position` end, past their terminator.
+fn sub_blocks_end(bytes: &[u8], mut position: usize) -> Option<usize> {
+    loop {
+        let size = *bytes.get(position)? as usize;
+        position += 1 + size;
+        if size == 0 {
+            return Some(position);
+        }
+    }
+}
+
+/// Drops the EXIF and XMP chunks and clears the flags announcing them.
+fn strip_webp(bytes: &[u8]) -> Option<Vec<u8>> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WEBP" {
+        return None;
+    }
+    let mut chunks = Vec::new();
+    let mut rest = &bytes[12..];
+    while !rest.is_empty() {
+        let kind = rest.get(..4)?;
+        let size = u32::from_le_bytes(rest.get(4..8)?.try_into().ok()?) as usize;
+        let padded = 8 + size + size % 2;
+        let chunk = rest.get(..padded).or_else(|| rest.get(..8 + size))?;
+        rest = &rest[chunk.len()..];
+
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let mut chunk = chunk.to_vec();
+                // The EXIF and XMP metadata flags
+                *chunk.get_mut(8)? &= !0x0c;
+                chunks.extend_from_slice(&chunk);
+            }
+            _ => chunks.extend_from_slice(chunk),
+        }
+    }
+
+    let mut stripped = b"RIFF".to_vec();
+    stripped.extend_from_slice(&(4 + chunks.len() as u32).to_le_bytes());
+    stripped.extend_from_slice(b"WEBP");
+    stripped.extend_from_slice(&chunks);
+
+    Some(stripped)
+}
+
+#[cfg(test)]
+mod sanitize_tests {
+    use super::*;
+
+    #[test]
+    fn test_jpeg_loses_exif_and_comments_but_keeps_the_image() {
+        let jpeg = [
+            &b"\xff\xd8"[..],
+            b"\xff\xe0\x00\x06JFIF",
+            b"\xff\xe1\x00\x0aExif\0\0GP",
+            b"\xff\xfe\x00\x05hi!",
+            b"\xff\xdb\x00\x03\x01",
+            b"\xff\xda\x00\x02\x12\x34\xff\x00\xff\xd9",
+        ]
+        .concat();
+
+        let stripped = sanitize("image/jpeg", &jpeg).unwrap();
+        assert_eq!(
+            stripped,
+            [
+                &b"\xff\xd8"[..],
+                b"\xff\xe0\x00\x06JFIF",
+                b"\xff\xdb\x00\x03\x01",
+                b"\xff\xda\x00\x02\x12\x34\xff\x00\xff\xd9",
+            ]
+            .concat()
+        );
+        assert!(sanitize("image/jpeg", b"\xff\xd8\xff\xe1\x00\xff").is_none());
+    }
+
+    #[test]
+    fn test_rotated_jpeg_is_turned_upright() {
+        // 2x1, red on the left and blue on the right
+        let pixels = image::RgbImage::from_raw(2, 1, vec![255, 0, 0, 0, 0, 255]).unwrap();
+        let mut encoded = Cursor::new(Vec::new());
+        pixels
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, 100))
+            .unwrap();
+        let encoded = encoded.into_inner();
+        // EXIF orientation 6: rotate 90° clockwise to display
+        let jpeg = [
+            &encoded[..2],
+            b"\xff\xe1\x00\x22Exif\0\0MM\0\x2a\0\0\0\x08",
+            b"\x00\x01\x01\x12\x00\x03\x00\x00\x00\x01\x00\x06\x00\x00\x00\x00\x00\x00",
+            &encoded[2..],
+        ]
+        .concat();
+
+        let upright = sanitize("image/jpeg", &jpeg).unwrap();
+        assert!(!upright.windows(4).any(|window| window == b"Exif"));
+        let image = crate::media::image::Image::decode("image/jpeg", &upright).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        let (top, bottom) = (&image.rgba[..4], &image.rgba[4..]);
+        assert!(top[0] > 200 && top[2] < 50, "{:?}", top);
+        assert!(bottom[2] > 200 && bottom[0] < 50, "{:?}", bottom);
+
+        assert_eq!(sanitize("image/jpeg", &encoded).unwrap(), encoded);
+    }
+
+    #[test]
+    fn test_gif_keeps_looping_but_not_comments() {
+        let gif = [
+            &b"GIF89a\x01\x00\x01\x00\x80\x00\x00"[..],
+            b"\x00\x00\x00\xff\xff\xff",
+            b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00",
+            b"\x21\xfe\x05hello\x00",
+            b"\x21\xff\x0bXMP DataXMP\x02<x\x00",
+            b"\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00",
+            b"\x3b",
+        ]
+        .concat();
+
+        let stripped = sanitize("image/gif", &gif).unwrap();
+        assert_eq!(
+            stripped,
+            [
+                &b"GIF89a\x01\x00\x01\x00\x80\x00\x00"[..],
+                b"\x00\x00\x00\xff\xff\xff",
+                b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00",
+                b"\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00",
+                b"\x3b",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_webp_loses_its_exif_chunk_and_flag() {
+        let chunks = [
+            &b"VP8X\x0a\x00\x00\x00\x08\x00\x00\x00\x00\x00\x00\x00\x00\x00"[..],
+            b"VP8L\x01\x00\x00\x00\x2f\x00",
+            b"EXIF\x03\x00\x00\x00GPS\x00",
+        ]
+        .concat();
+        let mut webp = b"RIFF".to_vec();
+        webp.extend_from_slice(&(4 + chunks.len() as u32).to_le_bytes());
+        webp.extend_from_slice(b"WEBP");
+        webp.extend_from_slice(&chunks);
+
+        let stripped = sanitize("image/webp", &webp).unwrap();
+        assert_eq!(
+            stripped,
+            [
+                &b"RIFF\x20\x00\x00\x00WEBP"[..],
+                b"VP8X\x0a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00",
+                b"VP8L\x01\x00\x00\x00\x2f\x00",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_png_is_reencoded_without_metadata() {
+        let image = crate::media::image::Image {
+            width: 1,
+            height: 1,
+            rgba: vec![1, 2, 3, 255],
+        };
+        let clean = png::encode(&image);
+        // An eXIf chunk between IHDR and IDAT
+        let mut tagged = clean[..33].to_vec();
+        tagged.extend_from_slice(b"\x00\x00\x00\x03eXIfGPS\x00\x00\x00\x00");
+        tagged.extend_from_slice(&clean[33..]);
+
+        let sanitized = sanitize("image/png", &tagged).unwrap();
+        assert_eq!(sanitized, clean);
+    }
+}
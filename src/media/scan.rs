@@ -0,0 +1,45 @@
+use crate::config::MediaConfig;
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use std::error::Error;
+use std::time::Duration;
+
+pub type ScanError = Box<dyn Error>;
+
+/// Hands uploads to an external malware scanner before they are stored and become
+/// reachable. The scanner is sent the file as the body of a `POST` and answers
+/// `200 OK` if it is clean or `422 Unprocessable Entity` to reject it. Without a
+/// scanner configured every upload passes.
+pub struct MediaScanner {
+    url: Option<String>,
+    timeout: Duration,
+}
+
+impl MediaScanner {
+    pub fn new(config: &MediaConfig) -> Self {
+        MediaScanner {
+            url: config.scan_url.clone(),
+            timeout: config.scan_timeout,
+        }
+    }
+
+    /// Whether the file may be stored.
+    pub async fn is_clean(&self, content_type: &str, body: Bytes) -> Result<bool, ScanError> {
+        let Some(url) = &self.url else {
+            return Ok(true);
+        };
+
+        let http = awc::Client::builder().timeout(self.timeout).finish();
+        let response = http
+            .post(url)
+            .insert_header((CONTENT_TYPE, content_type))
+            .send_body(body)
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(true),
+            StatusCode::UNPROCESSABLE_ENTITY => Ok(false),
+            status => Err(format!("the scanner answered {}", status).into()),
+        }
+    }
+}
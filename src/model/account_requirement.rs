@@ -0,0 +1,125 @@
+use crate::model::user::User;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Contribution {
+    Post,
+    Comment,
+    CreateSub,
+}
+
+/// How old an account must be, and how much post and comment karma it needs together,
+/// to make a contribution. Site-wide when `sub` is `None`.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct AccountRequirement {
+    pub id: i32,
+    pub sub: Option<String>,
+    pub contribution: Contribution,
+    pub min_account_age_days: i32,
+    pub min_karma: i32,
+    pub created_by: Option<i32>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// `PUT /admin/account_requirements`: replaces the requirement for the contribution in
+/// the sub, or site-wide.
+#[derive(Deserialize)]
+pub struct NewAccountRequirement {
+    pub sub: Option<String>,
+    pub contribution: Contribution,
+    #[serde(default)]
+    pub min_account_age_days: i32,
+    #[serde(default)]
+    pub min_karma: i32,
+}
+
+#[derive(Deserialize)]
+pub struct AccountRequirementQuery {
+    pub sub: Option<String>,
+}
+
+/// The strictest of several requirements.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug, Default)]
+pub struct Threshold {
+    pub min_account_age_days: i32,
+    pub min_karma: i32,
+}
+
+impl Threshold {
+    pub fn strictest(requirements: &[AccountRequirement]) -> Self {
+        requirements
+            .iter()
+            .fold(Threshold::default(), |threshold, requirement| Threshold {
+                min_account_age_days: threshold
+                    .min_account_age_days
+                    .max(requirement.min_account_age_days),
+                min_karma: threshold.min_karma.max(requirement.min_karma),
+            })
+    }
+
+    pub fn met_by(&self, user: &User, now: DateTime<Utc>) -> bool {
+        let karma = i64::from(user.post_karma) + i64::from(user.comment_karma);
+
+        now - user.created_at >= Duration::days(i64::from(self.min_account_age_days))
+            && karma >= i64::from(self.min_karma)
+    }
+}
+
+#[cfg(test)]
+mod account_requirement_model_tests {
+    use super::*;
+
+    fn requirement(min_account_age_days: i32, min_karma: i32) -> AccountRequirement {
+        AccountRequirement {
+            id: 1,
+            sub: None,
+            contribution: Contribution::Post,
+            min_account_age_days,
+            min_karma,
+            created_by: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_strictest_threshold_applies() {
+        let threshold = Threshold::strictest(&[requirement(7, 0), requirement(1, 50)]);
+        assert_eq!(
+            threshold,
+            Threshold {
+                min_account_age_days: 7,
+                min_karma: 50
+            }
+        );
+
+        let now = Utc::now();
+        let mut user = User {
+            id: 1,
+            username: "newbie".to_string(),
+            password_hash: String::new(),
+            is_moderator: false,
+            is_admin: false,
+            created_at: now - Duration::days(10),
+            date_of_birth: None,
+            nsfw_acknowledged_at: None,
+            preferred_languages: Vec::new(),
+            is_premium: false,
+            email: None,
+            email_verified_at: None,
+            post_karma: 30,
+            comment_karma: 0,
+            show_nsfw: true,
+        };
+        assert!(!threshold.met_by(&user, now));
+        user.comment_karma = 20;
+        assert!(threshold.met_by(&user, now));
+        user.created_at = now - Duration::days(6);
+        assert!(!threshold.met_by(&user, now));
+        assert!(Threshold::default().met_by(&user, now));
+    }
+}
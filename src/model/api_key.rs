@@ -0,0 +1,262 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use strum_macros::Display;
+
+/// Lets a bearer token be told apart from a JWT without a database lookup.
+pub const KEY_PREFIX: &str = "ffk_";
+const KEY_LENGTH: usize = 40;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ApiTier {
+    Free,
+    Standard,
+    Partner,
+}
+
+/// What a key may do when used in place of a login. `write` includes `read`.
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ApiScope {
+    Read,
+    Write,
+}
+
+pub struct ApiKey {
+    pub id: i64,
+    pub user_id: i32,
+    pub tier: ApiTier,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ApiKey {
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+            || (scope == ApiScope::Read && self.scopes.contains(&ApiScope::Write))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewApiKey {
+    #[serde(default)]
+    pub label: String,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<ApiScope>,
+}
+
+fn default_scopes() -> Vec<ApiScope> {
+    vec![ApiScope::Read]
+}
+
+/// Returned once at creation; only the hash of `key` is stored.
+#[derive(Serialize)]
+pub struct CreatedApiKey {
+    pub id: i64,
+    pub key: String,
+    pub tier: ApiTier,
+    pub scopes: Vec<ApiScope>,
+}
+
+/// A key as listed to its owner, without the key itself.
+#[derive(Serialize)]
+pub struct ApiKeySummary {
+    pub id: i64,
+    pub label: String,
+    pub tier: ApiTier,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct TierUpdate {
+    pub tier: ApiTier,
+}
+
+/// Requests counted so far in the current UTC day and month.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ApiUsage {
+    pub today: i64,
+    pub this_month: i64,
+}
+
+#[derive(Serialize, Display, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+#[derive(Serialize, Debug)]
+pub struct QuotaWindow {
+    pub period: QuotaPeriod,
+    pub used: i64,
+    pub limit: i64,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl QuotaWindow {
+    pub fn remaining(&self) -> i64 {
+        (self.limit - self.used).max(0)
+    }
+}
+
+#[derive(Serialize)]
+pub struct UsageReport {
+    pub tier: ApiTier,
+    pub daily: QuotaWindow,
+    pub monthly: QuotaWindow,
+}
+
+impl UsageReport {
+    pub fn new(
+        tier: ApiTier,
+        usage: ApiUsage,
+        daily_limit: i64,
+        monthly_limit: i64,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let today = now.date_naive();
+        let next_day = today + Duration::days(1);
+        let next_month = if today.month() == 12 {
+            NaiveDate::from_ymd_opt(today.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(today.year(), today.month() + 1, 1)
+        }
+        .expect("the first of a month is a valid date");
+
+        UsageReport {
+            tier,
+            daily: QuotaWindow {
+                period: QuotaPeriod::Daily,
+                used: usage.today,
+                limit: daily_limit,
+                resets_at: midnight(next_day),
+            },
+            monthly: QuotaWindow {
+                period: QuotaPeriod::Monthly,
+                used: usage.this_month,
+                limit: monthly_limit,
+                resets_at: midnight(next_month),
+            },
+        }
+    }
+
+    /// The first window with nothing left, if any.
+    pub fn exhausted(&self) -> Option<&QuotaWindow> {
+        [&self.daily, &self.monthly]
+            .into_iter()
+            .find(|window| window.remaining() == 0)
+    }
+
+    /// The window that will run out first, which is what quota headers describe.
+    pub fn binding(&self) -> &QuotaWindow {
+        match self.exhausted() {
+            Some(window) => window,
+            None if self.monthly.remaining() < self.daily.remaining() => &self.monthly,
+            None => &self.daily,
+        }
+    }
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc()
+}
+
+pub fn generate_api_key() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(KEY_LENGTH)
+        .map(char::from)
+        .collect();
+
+    format!("{}{}", KEY_PREFIX, secret)
+}
+
+/// Keys are random and long, so a fast unsalted hash is enough and keeps lookups
+/// to a single indexed query.
+pub fn hash_api_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod api_key_model_tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn report(today: i64, this_month: i64) -> UsageReport {
+        UsageReport::new(
+            ApiTier::Free,
+            ApiUsage { today, this_month },
+            100,
+            1_000,
+            Utc.with_ymd_and_hms(2024, 12, 31, 15, 30, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_windows_reset_at_next_utc_day_and_month() {
+        let report = report(0, 0);
+        assert_eq!(
+            report.daily.resets_at,
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            report.monthly.resets_at,
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_exhausted_reports_the_spent_window() {
+        assert!(report(99, 500).exhausted().is_none());
+        assert_eq!(
+            report(100, 500).exhausted().map(|window| window.period),
+            Some(QuotaPeriod::Daily)
+        );
+        assert_eq!(
+            report(10, 1_000).exhausted().map(|window| window.period),
+            Some(QuotaPeriod::Monthly)
+        );
+    }
+
+    #[test]
+    fn test_binding_window_has_least_remaining() {
+        assert_eq!(report(10, 500).binding().period, QuotaPeriod::Daily);
+        assert_eq!(report(10, 950).binding().period, QuotaPeriod::Monthly);
+    }
+
+    #[test]
+    fn test_write_scope_includes_read() {
+        let key = |scopes| ApiKey {
+            id: 1,
+            user_id: 1,
+            tier: ApiTier::Free,
+            scopes,
+        };
+
+        assert!(key(vec![ApiScope::Write]).allows(ApiScope::Read));
+        assert!(!key(vec![ApiScope::Read]).allows(ApiScope::Write));
+        assert!(!key(vec![]).allows(ApiScope::Read));
+    }
+
+    #[test]
+    fn test_generated_keys_are_unique_and_hash_stably() {
+        let (first, second) = (generate_api_key(), generate_api_key());
+        assert!(first.starts_with(KEY_PREFIX));
+        assert_ne!(first, second);
+        assert_eq!(hash_api_key(&first), hash_api_key(&first));
+        assert_eq!(hash_api_key(&first).len(), 64);
+    }
+}
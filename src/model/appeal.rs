@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+use uuid::Uuid;
+
+/// Longest appeal, or moderator response to one, in characters.
+pub const MAX_APPEAL_MESSAGE_CHARS: usize = 2_000;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AppealKind {
+    PostRemoval,
+    CommentRemoval,
+    SubBan,
+    Suspension,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AppealStatus {
+    Pending,
+    Granted,
+    Denied,
+}
+
+/// A user asking for a removal or ban to be reversed. Appeals of suspensions have no
+/// `sub` and go to admins; the rest go to the sub's moderators.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Appeal {
+    pub id: i64,
+    pub user_id: i32,
+    pub kind: AppealKind,
+    pub sub: Option<String>,
+    pub post_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+    pub message: String,
+    pub status: AppealStatus,
+    pub resolved_by: Option<i32>,
+    /// What the moderator told the user when they decided.
+    pub response: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// What is being appealed, tagged by `kind`.
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AppealTarget {
+    PostRemoval { post_id: Uuid },
+    CommentRemoval { comment_id: Uuid },
+    SubBan { sub: String },
+    Suspension,
+}
+
+impl AppealTarget {
+    pub fn kind(&self) -> AppealKind {
+        match self {
+            AppealTarget::PostRemoval { .. } => AppealKind::PostRemoval,
+            AppealTarget::CommentRemoval { .. } => AppealKind::CommentRemoval,
+            AppealTarget::SubBan { .. } => AppealKind::SubBan,
+            AppealTarget::Suspension => AppealKind::Suspension,
+        }
+    }
+}
+
+/// `POST /appeals`.
+#[derive(Deserialize)]
+pub struct NewAppeal {
+    #[serde(flatten)]
+    pub target: AppealTarget,
+    pub message: String,
+}
+
+/// `POST /appeals/{id}/resolve`. Granting an appeal reverses what was appealed.
+#[derive(Deserialize)]
+pub struct AppealResolution {
+    pub granted: bool,
+    #[serde(default)]
+    pub response: String,
+}
+
+/// Lists pending appeals unless another status is asked for.
+#[derive(Deserialize)]
+pub struct AppealQuery {
+    pub status: Option<AppealStatus>,
+}
+
+#[cfg(test)]
+mod appeal_model_tests {
+    use super::*;
+
+    #[test]
+    fn test_appeals_are_tagged_by_kind() {
+        let post_id = Uuid::new_v4();
+        let appeal: NewAppeal = serde_json::from_value(serde_json::json!({
+            "kind": "post_removal",
+            "post_id": post_id,
+            "message": "It was on topic",
+        }))
+        .unwrap();
+        assert_eq!(appeal.target, AppealTarget::PostRemoval { post_id });
+        assert_eq!(appeal.target.kind(), AppealKind::PostRemoval);
+
+        let appeal: NewAppeal = serde_json::from_value(serde_json::json!({
+            "kind": "suspension",
+            "message": "My account was compromised",
+        }))
+        .unwrap();
+        assert_eq!(appeal.target, AppealTarget::Suspension);
+
+        assert!(serde_json::from_value::<NewAppeal>(serde_json::json!({
+            "kind": "sub_ban",
+            "message": "Which sub?",
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub table_name: String,
+    pub row_key: String,
+    pub operation: String,
+    pub old_data: Option<Value>,
+    pub new_data: Option<Value>,
+    pub changed_by: Option<i32>,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    pub table: Option<String>,
+    pub row_key: Option<String>,
+    pub limit: Option<i64>,
+}
@@ -0,0 +1,446 @@
+use crate::model::report::MAX_REPORT_REASON_CHARS;
+use chrono::{DateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use url::Url;
+
+/// Most rules one sub may have.
+pub const MAX_AUTOMOD_RULES: usize = 100;
+/// Longest reply a `comment` action may post, in characters.
+pub const MAX_AUTOMOD_COMMENT_CHARS: usize = 10_000;
+
+static BODY_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"https?://[^\s<>()\[\]"']+"#).unwrap());
+
+/// A sub's AutoModerator rules as they are stored. `updated_by` is the moderator who
+/// saved them last, and who `comment` actions reply as.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SubAutomod {
+    pub sub: String,
+    pub rules: Vec<AutomodRule>,
+    pub updated_by: Option<i32>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// `PUT /subs/{sub}/automod`: replaces every rule of the sub.
+#[derive(Deserialize)]
+pub struct AutomodUpdate {
+    pub rules: Vec<AutomodRule>,
+}
+
+/// `POST /subs/{sub}/automod/test`: a post, or a comment if `title` is left out.
+#[derive(Deserialize)]
+pub struct AutomodTest {
+    /// Rules to try instead of the saved ones.
+    pub rules: Option<Vec<AutomodRule>>,
+    /// Whose account the content is checked as; the caller's if left out.
+    pub author_id: Option<i32>,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub content: String,
+    pub url: Option<String>,
+}
+
+/// A rule is checked against every new post and comment in its sub, and its actions are
+/// taken when all of its conditions hold.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AutomodRule {
+    /// Shown in dry runs and on the reports `flag` files.
+    pub name: String,
+    #[serde(default)]
+    pub applies_to: AutomodTarget,
+    #[serde(default)]
+    pub conditions: AutomodConditions,
+    pub actions: Vec<AutomodAction>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AutomodTarget {
+    #[default]
+    Any,
+    Post,
+    Comment,
+}
+
+/// A rule without conditions matches everything it applies to. Unknown fields are
+/// refused, since a misspelt condition would otherwise be dropped and widen the rule.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AutomodConditions {
+    /// Matches authors whose account is younger than this many days.
+    pub account_age_below_days: Option<i64>,
+    /// Matches authors whose post and comment karma together is below this.
+    pub karma_below: Option<i64>,
+    /// Case-insensitive. Comments have no title, so rules with this never match them.
+    pub title_regex: Option<String>,
+    /// Case-insensitive.
+    pub body_regex: Option<String>,
+    /// Matches a post's link or any link in the body on one of these domains or their
+    /// subdomains.
+    #[serde(default)]
+    pub domains: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AutomodAction {
+    /// Removes the content as a moderator would.
+    Remove,
+    /// Reports the content to the modqueue and leaves it up. The rule's name is the
+    /// report's reason unless one is given.
+    Flag {
+        #[serde(default)]
+        reason: String,
+    },
+    /// Sets one of the sub's post flairs. Comments are left alone.
+    Flair { flair_id: i32 },
+    /// Replies to the content.
+    Comment { text: String },
+}
+
+/// What a rule is checked against. `title` is `None` for comments.
+pub struct AutomodSubject<'a> {
+    pub title: Option<&'a str>,
+    pub body: &'a str,
+    pub link_url: Option<&'a str>,
+    pub author_created_at: DateTime<Utc>,
+    pub author_karma: i64,
+}
+
+/// Every action the matching rules call for, in rule order.
+#[derive(Serialize, Clone, PartialEq, Debug, Default)]
+pub struct AutomodVerdict {
+    /// The names of the rules that matched.
+    pub matched: Vec<String>,
+    pub remove: bool,
+    /// Report reasons, one per `flag` action.
+    pub flags: Vec<String>,
+    /// The first matching `flair` action wins.
+    pub flair_id: Option<i32>,
+    /// Replies, one per `comment` action.
+    pub comments: Vec<String>,
+}
+
+impl AutomodVerdict {
+    pub fn is_empty(&self) -> bool {
+        self.matched.is_empty()
+    }
+}
+
+fn compile_condition(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+/// The domain lowercased and without a leading `www.`, if it looks like a host name.
+pub fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().to_lowercase();
+    let domain = domain.strip_prefix("www.").unwrap_or(&domain);
+    let valid = !domain.is_empty()
+        && domain
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '.' || c == '-');
+
+    valid.then(|| domain.to_string())
+}
+
+/// Checks the rules can be evaluated, and tidies their domains and texts. On failure,
+/// returns the offending rule's name and what is wrong with it.
+pub fn validate_rules(rules: &mut [AutomodRule]) -> Result<(), (String, String)> {
+    for rule in rules.iter_mut() {
+        rule.name = rule.name.trim().to_string();
+        validate_rule(rule).map_err(|problem| (rule.name.clone(), problem))?;
+    }
+
+    Ok(())
+}
+
+fn validate_rule(rule: &mut AutomodRule) -> Result<(), String> {
+    if rule.name.is_empty() {
+        return Err("every rule needs a name".to_string());
+    }
+    if rule.actions.is_empty() {
+        return Err("a rule needs at least one action".to_string());
+    }
+
+    let conditions = &mut rule.conditions;
+    for pattern in [&conditions.title_regex, &conditions.body_regex]
+        .into_iter()
+        .flatten()
+    {
+        compile_condition(pattern).map_err(|err| err.to_string())?;
+    }
+    conditions.domains = conditions
+        .domains
+        .iter()
+        .map(|domain| normalize_domain(domain).ok_or_else(|| format!("{domain:?} is not a domain")))
+        .collect::<Result<_, _>>()?;
+
+    for action in &mut rule.actions {
+        match action {
+            AutomodAction::Flag { reason } => {
+                *reason = reason.trim().to_string();
+                if reason.chars().count() > MAX_REPORT_REASON_CHARS {
+                    return Err(format!(
+                        "flag reasons can be at most {MAX_REPORT_REASON_CHARS} characters"
+                    ));
+                }
+            }
+            AutomodAction::Comment { text } => {
+                *text = text.trim().to_string();
+                let chars = text.chars().count();
+                if !(1..=MAX_AUTOMOD_COMMENT_CHARS).contains(&chars) {
+                    return Err(format!(
+                        "comments need 1 to {MAX_AUTOMOD_COMMENT_CHARS} characters"
+                    ));
+                }
+            }
+            AutomodAction::Remove | AutomodAction::Flair { .. } => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// The `http` and `https` links in the text.
+pub fn find_links(text: &str) -> impl Iterator<Item = &str> {
+    BODY_LINK.find_iter(text).map(|link| link.as_str())
+}
+
+/// The domain a link points at, as `normalize_domain` would write it.
+fn link_domain(link: &str) -> Option<String> {
+    Url::parse(link).ok()?.host_str().and_then(normalize_domain)
+}
+
+fn on_domain(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|rest| rest.ends_with('.'))
+}
+
+impl AutomodRule {
+    pub fn flair_ids(&self) -> impl Iterator<Item = i32> + '_ {
+        self.actions.iter().filter_map(|action| match action {
+            AutomodAction::Flair { flair_id } => Some(*flair_id),
+            _ => None,
+        })
+    }
+
+    fn matches(&self, subject: &AutomodSubject, now: DateTime<Utc>) -> bool {
+        let applies = match self.applies_to {
+            AutomodTarget::Any => true,
+            AutomodTarget::Post => subject.title.is_some(),
+            AutomodTarget::Comment => subject.title.is_none(),
+        };
+        if !applies {
+            return false;
+        }
+
+        let conditions = &self.conditions;
+        if let Some(days) = conditions.account_age_below_days {
+            if (now - subject.author_created_at).num_days() >= days {
+                return false;
+            }
+        }
+        if let Some(karma) = conditions.karma_below {
+            if subject.author_karma >= karma {
+                return false;
+            }
+        }
+        // Patterns are validated when the rules are saved
+        if let Some(pattern) = &conditions.title_regex {
+            let Some(title) = subject.title else {
+                return false;
+            };
+            if !compile_condition(pattern).is_ok_and(|regex| regex.is_match(title)) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &conditions.body_regex {
+            if !compile_condition(pattern).is_ok_and(|regex| regex.is_match(subject.body)) {
+                return false;
+            }
+        }
+        if !conditions.domains.is_empty() {
+            let hosts: Vec<String> = subject
+                .link_url
+                .into_iter()
+                .chain(find_links(subject.body))
+                .filter_map(link_domain)
+                .collect();
+            let linked = hosts.iter().any(|host| {
+                conditions
+                    .domains
+                    .iter()
+                    .any(|domain| on_domain(host, domain))
+            });
+            if !linked {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Checks the subject against every rule, in order.
+pub fn evaluate(rules: &[AutomodRule], subject: &AutomodSubject) -> AutomodVerdict {
+    let now = Utc::now();
+    let mut verdict = AutomodVerdict::default();
+
+    for rule in rules.iter().filter(|rule| rule.matches(subject, now)) {
+        verdict.matched.push(rule.name.clone());
+        for action in &rule.actions {
+            match action {
+                AutomodAction::Remove => verdict.remove = true,
+                AutomodAction::Flag { reason } if reason.is_empty() => {
+                    verdict.flags.push(rule.name.clone())
+                }
+                AutomodAction::Flag { reason } => verdict.flags.push(reason.clone()),
+                AutomodAction::Flair { flair_id } => {
+                    if subject.title.is_some() {
+                        verdict.flair_id = verdict.flair_id.or(Some(*flair_id));
+                    }
+                }
+                AutomodAction::Comment { text } => verdict.comments.push(text.clone()),
+            }
+        }
+    }
+
+    verdict
+}
+
+#[cfg(test)]
+mod automod_model_tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+
+    fn rules(value: serde_json::Value) -> Vec<AutomodRule> {
+        let mut rules: Vec<AutomodRule> = serde_json::from_value(value).unwrap();
+        validate_rules(&mut rules).unwrap();
+        rules
+    }
+
+    fn post<'a>(title: &'a str, body: &'a str, link_url: Option<&'a str>) -> AutomodSubject<'a> {
+        AutomodSubject {
+            title: Some(title),
+            body,
+            link_url,
+            author_created_at: Utc::now() - Duration::days(365),
+            author_karma: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_all_conditions_must_hold() {
+        let rules = rules(json!([{
+            "name": "New accounts selling things",
+            "conditions": { "account_age_below_days": 7, "title_regex": "^\\[sale\\]" },
+            "actions": [{ "type": "remove" }],
+        }]));
+
+        let mut subject = post("[SALE] Old keyboards", "", None);
+        assert!(evaluate(&rules, &subject).is_empty());
+
+        subject.author_created_at = Utc::now() - Duration::days(2);
+        let verdict = evaluate(&rules, &subject);
+        assert_eq!(verdict.matched, ["New accounts selling things"]);
+        assert!(verdict.remove);
+
+        subject.title = Some("Old keyboards");
+        assert!(evaluate(&rules, &subject).is_empty());
+    }
+
+    #[test]
+    fn test_domains_match_links_and_subdomains() {
+        let rules = rules(json!([{
+            "name": "Shorteners",
+            "conditions": { "domains": ["WWW.bit.ly"] },
+            "actions": [{ "type": "flag" }],
+        }]));
+
+        let linked = post("Look", "", Some("https://bit.ly/abc"));
+        assert_eq!(evaluate(&rules, &linked).flags, ["Shorteners"]);
+        let in_body = post("Look", "see (https://go.bit.ly/x) now", None);
+        assert_eq!(evaluate(&rules, &in_body).flags, ["Shorteners"]);
+        let lookalike = post("Look", "https://notbit.ly/x", None);
+        assert!(evaluate(&rules, &lookalike).is_empty());
+    }
+
+    #[test]
+    fn test_targets_and_post_only_actions() {
+        let rules = rules(json!([
+            {
+                "name": "Low karma",
+                "conditions": { "karma_below": 10 },
+                "actions": [
+                    { "type": "flair", "flair_id": 3 },
+                    { "type": "comment", "text": " Welcome! " },
+                ],
+            },
+            {
+                "name": "Questions",
+                "applies_to": "post",
+                "conditions": { "body_regex": "\\?$" },
+                "actions": [{ "type": "flair", "flair_id": 4 }],
+            },
+        ]));
+
+        let mut question = post("Help", "How do lifetimes work?", None);
+        question.author_karma = 0;
+        let verdict = evaluate(&rules, &question);
+        assert_eq!(verdict.matched, ["Low karma", "Questions"]);
+        assert_eq!(verdict.flair_id, Some(3));
+        assert_eq!(verdict.comments, ["Welcome!"]);
+
+        let comment = AutomodSubject {
+            title: None,
+            ..question
+        };
+        let verdict = evaluate(&rules, &comment);
+        assert_eq!(verdict.matched, ["Low karma"]);
+        assert_eq!(verdict.flair_id, None);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_refused() {
+        for (rule, problem) in [
+            (
+                json!({ "name": "", "actions": [{ "type": "remove" }] }),
+                "name",
+            ),
+            (json!({ "name": "Nothing", "actions": [] }), "action"),
+            (
+                json!({
+                    "name": "Bad regex",
+                    "conditions": { "body_regex": "(" },
+                    "actions": [{ "type": "remove" }],
+                }),
+                "regex",
+            ),
+            (
+                json!({
+                    "name": "Bad domain",
+                    "conditions": { "domains": ["example.com/path"] },
+                    "actions": [{ "type": "remove" }],
+                }),
+                "domain",
+            ),
+        ] {
+            let mut rules: Vec<AutomodRule> = serde_json::from_value(json!([rule])).unwrap();
+            let (_, message) = validate_rules(&mut rules).unwrap_err();
+            assert!(message.contains(problem), "{message}");
+        }
+
+        let misspelt = json!([{
+            "name": "Typo",
+            "conditions": { "karma_bellow": 10 },
+            "actions": [{ "type": "remove" }],
+        }]);
+        assert!(serde_json::from_value::<Vec<AutomodRule>>(misspelt).is_err());
+    }
+}
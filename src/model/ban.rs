@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A user barred from posting and commenting in a sub. Bans without `expires_at` last
+/// until a moderator lifts them.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SubBan {
+    pub user_id: i32,
+    pub username: String,
+    pub banned_by: Option<i32>,
+    pub reason: String,
+    pub banned_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// `POST /subs/{sub}/bans`. Banning someone already banned replaces their ban.
+#[derive(Deserialize)]
+pub struct NewSubBan {
+    pub user_id: i32,
+    #[serde(default)]
+    pub reason: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
@@ -1,20 +1,404 @@
+use crate::model::dto::CommentView;
+use crate::model::flair::AuthorFlair;
+use crate::model::moderation::RemovalKind;
+use crate::render;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum_macros::{Display, EnumString};
 use uuid::Uuid;
 
-#[derive(Serialize)]
+#[derive(Clone)]
 pub struct Comment {
     pub id: Uuid,
     pub post_id: Uuid,
     pub user_id: i32,
+    pub author_flair: Option<AuthorFlair>,
     pub content: String,
     pub timestamp: DateTime<Utc>,
     pub parent_id: Option<Uuid>,
+    pub removal: Option<RemovalKind>,
+    /// Upvotes minus downvotes.
+    pub score: i32,
+    /// When the content was last changed, if it ever was.
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+impl Comment {
+    pub fn rendered_html(&self) -> String {
+        render::markdown_to_html(&self.content)
+    }
 }
 
 #[derive(Deserialize)]
 pub struct NewComment {
-    pub user_id: i32,
     pub content: String,
     pub parent_id: Option<Uuid>,
+    /// Uploads of the author's to attach, in order.
+    #[serde(default)]
+    pub media_ids: Vec<Uuid>,
+}
+
+/// How comments are ordered, from `?sort=`.
+#[derive(Deserialize, Display, EnumString, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum CommentSort {
+    /// Highest share of upvotes first, trusting comments with more votes more; see
+    /// `repo::comment::wilson_rank`.
+    #[default]
+    Best,
+    /// Highest score first.
+    Top,
+    /// Newest first.
+    New,
+    /// Oldest first.
+    Old,
+    /// Many votes split evenly between up and down first.
+    Controversial,
+}
+
+pub const DEFAULT_COMMENT_LIMIT: i64 = 100;
+/// Hard cap on one page of comments, whatever the client asks for.
+pub const MAX_COMMENT_LIMIT: i64 = 500;
+
+/// `GET /posts/{post_id}/comments?sort=&limit=&after=`, where `after` is the
+/// `next_cursor` of the previous page.
+#[derive(Deserialize)]
+pub struct CommentListQuery {
+    pub sort: Option<CommentSort>,
+    pub limit: Option<i64>,
+    pub after: Option<String>,
+}
+
+impl CommentListQuery {
+    /// Flat listings read like a conversation, oldest first, unless asked otherwise.
+    pub fn sort(&self) -> CommentSort {
+        self.sort.unwrap_or(CommentSort::Old)
+    }
+}
+
+/// The last comment on a page: its rank under the page's sort, with the comment id
+/// breaking ties, as for post listings.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CommentCursor {
+    pub sort: CommentSort,
+    pub rank: f64,
+    pub id: Uuid,
+}
+
+impl CommentCursor {
+    pub fn encode(&self) -> String {
+        hex::encode(format!(
+            "{}:{:016x}:{}",
+            self.sort,
+            self.rank.to_bits(),
+            self.id
+        ))
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let decoded = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+        let mut parts = decoded.splitn(3, ':');
+        let sort = parts.next()?.parse().ok()?;
+        let rank = f64::from_bits(u64::from_str_radix(parts.next()?, 16).ok()?);
+        let id = parts.next()?.parse().ok()?;
+
+        Some(CommentCursor { sort, rank, id })
+    }
+}
+
+/// A listed comment with its rank under the listing's sort, for building cursors.
+pub struct RankedComment {
+    pub comment: Comment,
+    pub rank: f64,
+}
+
+/// One page of a post's comments. `total` counts every comment on the post;
+/// `next_cursor` is absent on the last page.
+#[derive(Serialize)]
+pub struct CommentPage {
+    pub comments: Vec<CommentView>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+pub const DEFAULT_TREE_DEPTH: i32 = 5;
+pub const MAX_TREE_DEPTH: i32 = 10;
+pub const DEFAULT_TREE_WIDTH: i64 = 20;
+pub const MAX_TREE_WIDTH: i64 = 100;
+
+/// `?sort=&depth=&limit=&after=` on comment trees: how siblings are ordered, how many
+/// levels to nest, how many replies to include under each comment (and at the top
+/// level), and the last top-level comment already shown, to continue after it.
+#[derive(Deserialize)]
+pub struct CommentTreeQuery {
+    #[serde(default)]
+    pub sort: CommentSort,
+    pub depth: Option<i32>,
+    pub limit: Option<i64>,
+    pub after: Option<Uuid>,
+}
+
+impl CommentTreeQuery {
+    pub fn depth(&self) -> i32 {
+        self.depth
+            .unwrap_or(DEFAULT_TREE_DEPTH)
+            .clamp(1, MAX_TREE_DEPTH)
+    }
+
+    pub fn width(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_TREE_WIDTH)
+            .clamp(1, MAX_TREE_WIDTH)
+    }
+}
+
+/// A comment fetched for a tree, with the number of direct replies it has in all,
+/// fetched or not.
+pub struct TreeComment {
+    pub comment: Comment,
+    pub reply_count: i64,
+}
+
+/// A comment with its replies nested under it. `more_replies` counts the direct
+/// replies left out by the depth or width limit.
+#[derive(Serialize)]
+pub struct CommentNode {
+    #[serde(flatten)]
+    pub comment: CommentView,
+    pub replies: Vec<CommentNode>,
+    pub more_replies: i64,
+}
+
+/// The top of a tree. `more_comments` counts the top-level comments left out by the
+/// width limit; they can be fetched by passing the last one shown as `?after=`.
+#[derive(Serialize)]
+pub struct CommentTree {
+    pub comments: Vec<CommentNode>,
+    pub more_comments: i64,
+}
+
+impl CommentTree {
+    /// Nests `comments` under their parents, starting from the replies to `parent_id`
+    /// (top-level comments for `None`), of which `total` remain to be shown. Siblings
+    /// keep their order in `comments`.
+    pub fn build(
+        comments: Vec<TreeComment>,
+        parent_id: Option<Uuid>,
+        total: i64,
+        votes: &HashMap<Uuid, i16>,
+    ) -> Self {
+        let mut children: HashMap<Option<Uuid>, Vec<TreeComment>> = HashMap::new();
+        for comment in comments {
+            children
+                .entry(comment.comment.parent_id)
+                .or_default()
+                .push(comment);
+        }
+
+        let top = children.remove(&parent_id).unwrap_or_default();
+        let more_comments = total - top.len() as i64;
+        CommentTree {
+            comments: tree_nodes(top, &mut children, votes),
+            more_comments,
+        }
+    }
+}
+
+pub const DEFAULT_CONTEXT_ANCESTORS: i32 = 3;
+pub const MAX_CONTEXT_ANCESTORS: i32 = 10;
+
+/// `GET /comments/{comment_id}/context?ancestors=&sort=`: how many parents to include
+/// above the comment, and how to order its replies.
+#[derive(Deserialize)]
+pub struct CommentContextQuery {
+    #[serde(default)]
+    pub sort: CommentSort,
+    pub ancestors: Option<i32>,
+}
+
+impl CommentContextQuery {
+    pub fn ancestors(&self) -> i32 {
+        self.ancestors
+            .unwrap_or(DEFAULT_CONTEXT_ANCESTORS)
+            .clamp(0, MAX_CONTEXT_ANCESTORS)
+    }
+}
+
+/// A permalinked comment with the chain of parents above it, the top-most first, and
+/// its direct replies nested under it.
+#[derive(Serialize)]
+pub struct CommentContext {
+    pub ancestors: Vec<CommentView>,
+    pub comment: CommentNode,
+}
+
+impl CommentContext {
+    /// `replies` are the comment's direct replies, of which it has `reply_count`.
+    pub fn build(
+        ancestors: Vec<Comment>,
+        comment: Comment,
+        replies: Vec<TreeComment>,
+        reply_count: i64,
+        votes: &HashMap<Uuid, i16>,
+    ) -> Self {
+        let view = |comment: Comment| CommentView {
+            vote: votes.get(&comment.id).copied(),
+            ..CommentView::from(comment)
+        };
+        let replies = CommentTree::build(replies, Some(comment.id), reply_count, votes);
+
+        CommentContext {
+            ancestors: ancestors.into_iter().map(view).collect(),
+            comment: CommentNode {
+                comment: view(comment),
+                replies: replies.comments,
+                more_replies: replies.more_comments,
+            },
+        }
+    }
+}
+
+fn tree_nodes(
+    siblings: Vec<TreeComment>,
+    children: &mut HashMap<Option<Uuid>, Vec<TreeComment>>,
+    votes: &HashMap<Uuid, i16>,
+) -> Vec<CommentNode> {
+    siblings
+        .into_iter()
+        .map(
+            |TreeComment {
+                 comment,
+                 reply_count,
+             }| {
+                let replies = children.remove(&Some(comment.id)).unwrap_or_default();
+                let replies = tree_nodes(replies, children, votes);
+                CommentNode {
+                    more_replies: reply_count - replies.len() as i64,
+                    replies,
+                    comment: CommentView {
+                        vote: votes.get(&comment.id).copied(),
+                        ..CommentView::from(comment)
+                    },
+                }
+            },
+        )
+        .collect()
+}
+
+pub struct ThreadComment {
+    pub depth: usize,
+    pub comment: Comment,
+}
+
+/// Orders a flat list of comments depth-first so each reply directly follows its
+/// parent, keeping siblings in their original order. Comments whose parent isn't
+/// in the list are treated as top-level.
+pub fn thread_order(comments: Vec<Comment>) -> Vec<ThreadComment> {
+    let ids: Vec<Uuid> = comments.iter().map(|c| c.id).collect();
+    let mut children: HashMap<Option<Uuid>, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        let parent = comment.parent_id.filter(|parent| ids.contains(parent));
+        children.entry(parent).or_default().push(comment);
+    }
+
+    let mut ordered = Vec::with_capacity(ids.len());
+    let mut stack: Vec<(usize, Comment)> = children
+        .remove(&None)
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .map(|comment| (0, comment))
+        .collect();
+
+    while let Some((depth, comment)) = stack.pop() {
+        if let Some(replies) = children.remove(&Some(comment.id)) {
+            stack.extend(replies.into_iter().rev().map(|reply| (depth + 1, reply)));
+        }
+        ordered.push(ThreadComment { depth, comment });
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+mod comment_model_tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn comment(id: u128, parent: Option<u128>) -> Comment {
+        Comment {
+            id: Uuid::from_u128(id),
+            post_id: Uuid::nil(),
+            user_id: 1,
+            content: id.to_string(),
+            timestamp: Utc::now(),
+            parent_id: parent.map(Uuid::from_u128),
+            removal: None,
+            score: 0,
+            edited_at: None,
+            author_flair: None,
+        }
+    }
+
+    #[test]
+    fn test_thread_order_nests_replies_under_parents() {
+        let comments = vec![
+            comment(1, None),
+            comment(2, None),
+            comment(3, Some(1)),
+            comment(4, Some(3)),
+            comment(5, Some(2)),
+        ];
+
+        let ordered: Vec<(usize, String)> = thread_order(comments)
+            .into_iter()
+            .map(|entry| (entry.depth, entry.comment.content))
+            .collect();
+
+        assert_eq!(
+            ordered,
+            vec![
+                (0, "1".to_string()),
+                (1, "3".to_string()),
+                (2, "4".to_string()),
+                (0, "2".to_string()),
+                (1, "5".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_thread_order_promotes_orphans() {
+        let ordered = thread_order(vec![comment(7, Some(99))]);
+        assert_eq!(ordered.len(), 1);
+        assert_eq!(ordered[0].depth, 0);
+    }
+
+    #[test]
+    fn test_tree_counts_replies_left_out() {
+        let tree = CommentTree::build(
+            vec![
+                TreeComment {
+                    comment: comment(1, None),
+                    reply_count: 3,
+                },
+                TreeComment {
+                    comment: comment(2, Some(1)),
+                    reply_count: 1,
+                },
+            ],
+            None,
+            4,
+            &HashMap::from([(Uuid::from_u128(2), 1)]),
+        );
+
+        assert_eq!(tree.more_comments, 3);
+        let top = &tree.comments[0];
+        assert_eq!(top.more_replies, 2);
+        assert_eq!(top.replies[0].comment.vote, Some(1));
+        assert_eq!(top.replies[0].more_replies, 1);
+        assert!(top.replies[0].replies.is_empty());
+    }
 }
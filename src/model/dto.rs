@@ -0,0 +1,171 @@
+//! Response bodies for the API. Database models are never serialized directly;
+//! each view lists exactly the columns a client may see.
+
+use crate::model::comment::Comment;
+use crate::model::flair::AuthorFlair;
+use crate::model::media::Media;
+use crate::model::moderation::RemovalKind;
+use crate::model::post::Post;
+use crate::model::sub::Sub;
+use crate::model::user::User;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// What anyone may see about an account.
+#[derive(Serialize, Clone)]
+pub struct UserPublic {
+    pub id: i32,
+    pub username: String,
+    pub is_moderator: bool,
+    pub is_admin: bool,
+    pub is_premium: bool,
+    pub created_at: DateTime<Utc>,
+    pub post_karma: i32,
+    pub comment_karma: i32,
+}
+
+/// What an account holder may see about their own account.
+#[derive(Serialize)]
+pub struct UserPrivate {
+    pub id: i32,
+    pub username: String,
+    pub is_moderator: bool,
+    pub is_admin: bool,
+    pub created_at: DateTime<Utc>,
+    pub date_of_birth: Option<NaiveDate>,
+    pub nsfw_acknowledged_at: Option<DateTime<Utc>>,
+    pub preferred_languages: Vec<String>,
+    pub show_nsfw: bool,
+    pub is_premium: bool,
+    /// Premium perk: clients should not show ads to this account.
+    pub ad_free: bool,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub post_karma: i32,
+    pub comment_karma: i32,
+}
+
+#[derive(Serialize)]
+pub struct CommentView {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub user_id: i32,
+    pub author_flair: Option<AuthorFlair>,
+    pub content: String,
+    pub rendered_html: String,
+    pub timestamp: DateTime<Utc>,
+    pub parent_id: Option<Uuid>,
+    pub removal: Option<RemovalKind>,
+    pub score: i32,
+    pub edited_at: Option<DateTime<Utc>>,
+    /// The caller's own vote, for signed-in callers who voted on the comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vote: Option<i16>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<Media>,
+    /// Why a moderator removed the comment, shown only to its author.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removal_reason: Option<String>,
+}
+
+/// A post with its author and sub embedded, for clients rendering lists of posts
+/// from many subs. `author` is absent if the account no longer exists.
+#[derive(Serialize)]
+pub struct PostWithContext {
+    pub post: Post,
+    pub author: Option<UserPublic>,
+    pub sub: Sub,
+}
+
+impl From<User> for UserPublic {
+    fn from(user: User) -> Self {
+        UserPublic {
+            id: user.id,
+            username: user.username,
+            is_moderator: user.is_moderator,
+            is_admin: user.is_admin,
+            is_premium: user.is_premium,
+            created_at: user.created_at,
+            post_karma: user.post_karma,
+            comment_karma: user.comment_karma,
+        }
+    }
+}
+
+impl From<User> for UserPrivate {
+    fn from(user: User) -> Self {
+        UserPrivate {
+            id: user.id,
+            username: user.username,
+            is_moderator: user.is_moderator,
+            is_admin: user.is_admin,
+            created_at: user.created_at,
+            date_of_birth: user.date_of_birth,
+            nsfw_acknowledged_at: user.nsfw_acknowledged_at,
+            preferred_languages: user.preferred_languages,
+            show_nsfw: user.show_nsfw,
+            is_premium: user.is_premium,
+            ad_free: user.is_premium,
+            email: user.email,
+            email_verified: user.email_verified_at.is_some(),
+            post_karma: user.post_karma,
+            comment_karma: user.comment_karma,
+        }
+    }
+}
+
+impl From<Comment> for CommentView {
+    fn from(comment: Comment) -> Self {
+        CommentView {
+            rendered_html: comment.rendered_html(),
+            id: comment.id,
+            post_id: comment.post_id,
+            user_id: comment.user_id,
+            author_flair: comment.author_flair,
+            content: comment.content,
+            timestamp: comment.timestamp,
+            parent_id: comment.parent_id,
+            removal: comment.removal,
+            score: comment.score,
+            edited_at: comment.edited_at,
+            vote: None,
+            media: Vec::new(),
+            removal_reason: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod dto_tests {
+    use super::*;
+
+    #[test]
+    fn test_user_views_never_include_password_hash() {
+        let user = || User {
+            id: 1,
+            username: "ferris".to_string(),
+            password_hash: "$argon2id$secret".to_string(),
+            is_moderator: false,
+            is_admin: false,
+            created_at: Utc::now(),
+            date_of_birth: NaiveDate::from_ymd_opt(1990, 1, 1),
+            nsfw_acknowledged_at: None,
+            preferred_languages: vec!["en".to_string()],
+            is_premium: false,
+            email: None,
+            email_verified_at: None,
+            post_karma: 0,
+            comment_karma: 0,
+            show_nsfw: true,
+        };
+
+        let public = serde_json::to_value(UserPublic::from(user())).unwrap();
+        let private = serde_json::to_value(UserPrivate::from(user())).unwrap();
+
+        assert!(public.get("password_hash").is_none());
+        assert!(public.get("date_of_birth").is_none());
+        assert!(private.get("password_hash").is_none());
+        assert_eq!(private["date_of_birth"], "1990-01-01");
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Assignments are computed in basis points so ramps can later go finer than 1%.
+const BUCKETS: u64 = 10_000;
+
+#[derive(Serialize, Clone)]
+pub struct Experiment {
+    pub key: String,
+    pub description: String,
+    pub variants: Vec<String>,
+    pub ramp_percent: i32,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct NewExperiment {
+    pub key: String,
+    #[serde(default)]
+    pub description: String,
+    pub variants: Vec<String>,
+    #[serde(default)]
+    pub ramp_percent: i32,
+}
+
+#[derive(Deserialize)]
+pub struct ExperimentUpdate {
+    pub ramp_percent: Option<i32>,
+    pub active: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct Assignment {
+    pub experiment: String,
+    /// `None` when the user falls outside the ramp or the experiment is inactive.
+    pub variant: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct VariantExposures {
+    pub variant: String,
+    pub users: i64,
+}
+
+/// FNV-1a. Assignments must stay stable across releases and platforms, which rules
+/// out `std`'s unspecified `DefaultHasher`.
+fn stable_hash(input: &str) -> u64 {
+    input.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Experiment {
+    /// Deterministically places `user_id` in the experiment. Ramping up only ever adds
+    /// users: everyone included at a lower percentage stays in with the same variant.
+    pub fn assign(&self, user_id: i32) -> Option<&str> {
+        if !self.active || self.variants.is_empty() {
+            return None;
+        }
+
+        let bucket = stable_hash(&format!("{}:{}", self.key, user_id)) % BUCKETS;
+        if bucket >= self.ramp_percent.clamp(0, 100) as u64 * BUCKETS / 100 {
+            return None;
+        }
+
+        let variant =
+            stable_hash(&format!("{}:{}:variant", self.key, user_id)) % self.variants.len() as u64;
+        Some(&self.variants[variant as usize])
+    }
+}
+
+#[cfg(test)]
+mod experiment_model_tests {
+    use super::*;
+
+    fn experiment(ramp_percent: i32) -> Experiment {
+        Experiment {
+            key: "new_ranking".to_string(),
+            description: String::new(),
+            variants: vec!["control".to_string(), "treatment".to_string()],
+            ramp_percent,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_assignment_is_deterministic() {
+        let experiment = experiment(50);
+        for user_id in 0..100 {
+            assert_eq!(experiment.assign(user_id), experiment.assign(user_id));
+        }
+    }
+
+    #[test]
+    fn test_ramp_controls_share_of_users() {
+        let assigned = (0..10_000)
+            .filter(|user_id| experiment(20).assign(*user_id).is_some())
+            .count();
+        assert!((1_700..2_300).contains(&assigned), "{} assigned", assigned);
+
+        assert!((0..1_000).all(|user_id| experiment(0).assign(user_id).is_none()));
+        assert!((0..1_000).all(|user_id| experiment(100).assign(user_id).is_some()));
+    }
+
+    #[test]
+    fn test_ramping_up_keeps_existing_assignments() {
+        let (small, large) = (experiment(10), experiment(60));
+        for user_id in 0..1_000 {
+            if let Some(variant) = small.assign(user_id) {
+                assert_eq!(large.assign(user_id), Some(variant));
+            }
+        }
+    }
+
+    #[test]
+    fn test_inactive_experiment_assigns_nobody() {
+        let mut experiment = experiment(100);
+        experiment.active = false;
+        assert_eq!(experiment.assign(1), None);
+    }
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest custom feed name, in characters.
+pub const MAX_FEED_NAME_CHARS: usize = 50;
+/// Most subs one custom feed may gather.
+pub const MAX_FEED_SUBS: usize = 100;
+
+/// A named collection of subs whose posts a user reads as one feed.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct CustomFeed {
+    pub name: String,
+    /// In name order.
+    pub subs: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `POST /users/{id}/feeds`.
+#[derive(Deserialize)]
+pub struct NewCustomFeed {
+    pub name: String,
+    pub subs: Vec<String>,
+}
+
+/// `PUT /users/{id}/feeds/{name}` replaces the feed's subs.
+#[derive(Deserialize)]
+pub struct CustomFeedUpdate {
+    pub subs: Vec<String>,
+}
+
+/// The feed name in lowercase, if it is 1 to 50 letters, digits, `_` or `-`.
+pub fn normalize_feed_name(name: &str) -> Option<String> {
+    let name = name.trim().to_ascii_lowercase();
+    let valid = (1..=MAX_FEED_NAME_CHARS).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    valid.then_some(name)
+}
+
+#[cfg(test)]
+mod feed_tests {
+    use super::*;
+
+    #[test]
+    fn test_feed_names_are_lowercased_and_validated() {
+        assert_eq!(normalize_feed_name(" Systems ").as_deref(), Some("systems"));
+        assert_eq!(
+            normalize_feed_name("rust-and-go").as_deref(),
+            Some("rust-and-go")
+        );
+        assert_eq!(normalize_feed_name(""), None);
+        assert_eq!(normalize_feed_name("my feed"), None);
+    }
+}
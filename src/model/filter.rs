@@ -0,0 +1,248 @@
+use crate::model::dto::CommentView;
+use crate::model::post::Post;
+use chrono::{DateTime, Utc};
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum MatchKind {
+    /// The whole word or phrase, ignoring case.
+    Exact,
+    /// Like `Exact`, with `*` standing for any run of letters or digits.
+    Wildcard,
+    Regex,
+    /// Links to the domain or its subdomains, with or without a scheme. Domain filters
+    /// can only queue or block.
+    Domain,
+}
+
+/// Declared from least to most severe: when several filters match, the most
+/// severe action wins.
+#[derive(
+    Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug,
+)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum FilterAction {
+    /// Replace the matched text with asterisks and publish.
+    Mask,
+    /// Publish nothing until a moderator approves it.
+    Queue,
+    /// Reject the submission.
+    Block,
+}
+
+#[derive(Serialize)]
+pub struct WordFilter {
+    pub id: i64,
+    pub sub: Option<String>,
+    pub pattern: String,
+    pub match_kind: MatchKind,
+    pub locale: Option<String>,
+    pub action: FilterAction,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct NewWordFilter {
+    pub sub: Option<String>,
+    pub pattern: String,
+    pub match_kind: MatchKind,
+    pub locale: Option<String>,
+    pub action: FilterAction,
+}
+
+#[derive(Deserialize)]
+pub struct WordFilterQuery {
+    pub sub: Option<String>,
+}
+
+/// Everything waiting for a moderator because a `queue` filter matched it.
+#[derive(Serialize)]
+pub struct HeldContent {
+    pub posts: Vec<Post>,
+    pub comments: Vec<CommentView>,
+}
+
+pub fn compile_pattern(pattern: &str, kind: MatchKind) -> Result<Regex, regex::Error> {
+    let source = match kind {
+        MatchKind::Exact => format!(r"\b{}\b", regex::escape(pattern)),
+        MatchKind::Wildcard => format!(
+            r"\b{}\b",
+            pattern
+                .split('*')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(r"\w*")
+        ),
+        MatchKind::Regex => pattern.to_string(),
+        MatchKind::Domain => format!(
+            r#"\b(?:https?://)?(?:[\w-]+\.)*{}\b(?:[/?#:][^\s<>()\[\]"']*)?"#,
+            regex::escape(pattern)
+        ),
+    };
+
+    RegexBuilder::new(&source).case_insensitive(true).build()
+}
+
+impl WordFilter {
+    /// Lists for a particular language apply to text in that language, and to text
+    /// whose language is unknown.
+    fn applies_to(&self, language: Option<&str>) -> bool {
+        match (&self.locale, language) {
+            (Some(locale), Some(language)) => locale == language,
+            _ => true,
+        }
+    }
+}
+
+pub struct FilterOutcome {
+    /// The most severe action of any matching filter.
+    pub action: Option<FilterAction>,
+    /// The text with every `Mask` match replaced by asterisks.
+    pub text: String,
+}
+
+/// The most severe action of any domain filter matching a post's link.
+pub fn check_link(filters: &[WordFilter], link: &str) -> Option<FilterAction> {
+    filters
+        .iter()
+        .filter(|filter| filter.match_kind == MatchKind::Domain)
+        .filter(|filter| {
+            compile_pattern(&filter.pattern, filter.match_kind)
+                .is_ok_and(|matcher| matcher.is_match(link))
+        })
+        .map(|filter| filter.action)
+        .max()
+}
+
+pub fn apply_filters(filters: &[WordFilter], text: &str, language: Option<&str>) -> FilterOutcome {
+    let mut outcome = FilterOutcome {
+        action: None,
+        text: text.to_string(),
+    };
+
+    for filter in filters.iter().filter(|filter| filter.applies_to(language)) {
+        // Patterns are validated when the filter is created
+        let Ok(matcher) = compile_pattern(&filter.pattern, filter.match_kind) else {
+            continue;
+        };
+        if !matcher.is_match(text) {
+            continue;
+        }
+
+        outcome.action = outcome.action.max(Some(filter.action));
+        if filter.action == FilterAction::Mask {
+            outcome.text = matcher
+                .replace_all(&outcome.text, |caps: &Captures| {
+                    "*".repeat(caps[0].chars().count())
+                })
+                .into_owned();
+        }
+    }
+
+    outcome
+}
+
+#[cfg(test)]
+mod filter_model_tests {
+    use super::*;
+
+    fn filter(pattern: &str, match_kind: MatchKind, action: FilterAction) -> WordFilter {
+        WordFilter {
+            id: 1,
+            sub: None,
+            pattern: pattern.to_string(),
+            match_kind,
+            locale: None,
+            action,
+            created_by: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_exact_matches_whole_words_ignoring_case() {
+        let filters = [filter("heck", MatchKind::Exact, FilterAction::Mask)];
+
+        let outcome = apply_filters(&filters, "What the HECK, checkers?", None);
+        assert_eq!(outcome.text, "What the ****, checkers?");
+        assert_eq!(outcome.action, Some(FilterAction::Mask));
+    }
+
+    #[test]
+    fn test_wildcard_and_regex_patterns() {
+        let wildcard = [filter("spam*", MatchKind::Wildcard, FilterAction::Block)];
+        assert_eq!(
+            apply_filters(&wildcard, "buy spammy pills", None).action,
+            Some(FilterAction::Block)
+        );
+        assert_eq!(apply_filters(&wildcard, "no spa here", None).action, None);
+
+        let regex = [filter(
+            r"\d{3}-\d{4}",
+            MatchKind::Regex,
+            FilterAction::Queue,
+        )];
+        assert_eq!(
+            apply_filters(&regex, "call 555-1234", None).action,
+            Some(FilterAction::Queue)
+        );
+    }
+
+    #[test]
+    fn test_domain_filters_match_links_and_subdomains() {
+        let filters = [filter("bit.ly", MatchKind::Domain, FilterAction::Queue)];
+
+        for text in [
+            "see https://bit.ly/abc?x=1",
+            "bare bit.ly/abc link",
+            "http://go.BIT.ly",
+        ] {
+            assert_eq!(
+                apply_filters(&filters, text, None).action,
+                Some(FilterAction::Queue),
+                "{text}"
+            );
+        }
+        for text in ["https://notbit.ly/abc", "bit.lyrics", "orbit.ly"] {
+            assert_eq!(apply_filters(&filters, text, None).action, None, "{text}");
+        }
+        assert_eq!(
+            check_link(&filters, "https://www.bit.ly/x"),
+            Some(FilterAction::Queue)
+        );
+
+        let words = [filter("ly", MatchKind::Exact, FilterAction::Block)];
+        assert_eq!(check_link(&words, "https://bit.ly/x"), None);
+    }
+
+    #[test]
+    fn test_most_severe_action_wins() {
+        let filters = [
+            filter("darn", MatchKind::Exact, FilterAction::Mask),
+            filter("scam", MatchKind::Exact, FilterAction::Queue),
+        ];
+
+        let outcome = apply_filters(&filters, "darn scam", None);
+        assert_eq!(outcome.action, Some(FilterAction::Queue));
+        assert_eq!(outcome.text, "**** scam");
+    }
+
+    #[test]
+    fn test_locale_lists_only_apply_to_their_language() {
+        let mut german = filter("mist", MatchKind::Exact, FilterAction::Mask);
+        german.locale = Some("de".to_string());
+        let filters = [german];
+
+        assert_eq!(apply_filters(&filters, "mist", Some("en")).action, None);
+        assert!(apply_filters(&filters, "mist", Some("de")).action.is_some());
+        assert!(apply_filters(&filters, "mist", None).action.is_some());
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+/// Longest flair text, in characters.
+pub const MAX_FLAIR_TEXT_CHARS: usize = 64;
+
+/// A label a sub offers for its posts. `mod_only` flairs can only be set by moderators.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Flair {
+    pub id: i32,
+    pub sub: String,
+    pub text: String,
+    /// `#rrggbb`.
+    pub color: String,
+    pub mod_only: bool,
+}
+
+/// A flair users can pick for themselves in a sub, shown next to their name there.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct UserFlair {
+    pub id: i32,
+    pub sub: String,
+    pub text: String,
+    pub color: String,
+}
+
+/// The user flair an author picked in the sub a post or comment is in.
+#[derive(Serialize, sqlx::Type, Clone, PartialEq, Debug)]
+#[sqlx(type_name = "author_flair")]
+pub struct AuthorFlair {
+    pub text: String,
+    pub color: String,
+}
+
+#[derive(Deserialize)]
+pub struct NewFlair {
+    pub text: String,
+    pub color: String,
+    #[serde(default)]
+    pub mod_only: bool,
+}
+
+/// `PATCH /subs/{sub}/flairs/{id}`: fields left out are kept.
+#[derive(Deserialize)]
+pub struct FlairUpdate {
+    pub text: Option<String>,
+    pub color: Option<String>,
+    pub mod_only: Option<bool>,
+}
+
+/// `POST /subs/{sub}/userflairs`.
+#[derive(Deserialize)]
+pub struct NewUserFlair {
+    pub text: String,
+    pub color: String,
+}
+
+/// `PATCH /subs/{sub}/userflairs/{id}`: fields left out are kept.
+#[derive(Deserialize)]
+pub struct UserFlairUpdate {
+    pub text: Option<String>,
+    pub color: Option<String>,
+}
+
+/// `PUT /subs/{sub}/userflair`; a `null` flair clears the caller's.
+#[derive(Deserialize)]
+pub struct UserFlairChoice {
+    pub flair_id: Option<i32>,
+}
+
+/// `PATCH /posts/{id}/flair`; a `null` flair clears it.
+#[derive(Deserialize)]
+pub struct PostFlairRequest {
+    pub flair_id: Option<i32>,
+}
+
+/// Flair text with surrounding whitespace trimmed, if it isn't empty or too long.
+pub fn normalize_flair_text(text: &str) -> Option<String> {
+    let text = text.trim();
+    let chars = text.chars().count();
+    (1..=MAX_FLAIR_TEXT_CHARS)
+        .contains(&chars)
+        .then(|| text.to_string())
+}
+
+/// `#RRGGBB` in lowercase, with or without the `#`.
+pub fn normalize_color(color: &str) -> Option<String> {
+    let hex = color.trim().trim_start_matches('#');
+    (hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| format!("#{}", hex.to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod flair_tests {
+    use super::*;
+
+    #[test]
+    fn test_flair_colors_are_normalized() {
+        assert_eq!(normalize_color("#FF8800").as_deref(), Some("#ff8800"));
+        assert_eq!(normalize_color("00aa11").as_deref(), Some("#00aa11"));
+        assert_eq!(normalize_color("#fff"), None);
+        assert_eq!(normalize_color("#gg0000"), None);
+    }
+}
@@ -0,0 +1,20 @@
+use serde::Serialize;
+
+/// Karma a user earned in one sub.
+#[derive(Serialize)]
+pub struct SubKarma {
+    pub sub: String,
+    pub post_karma: i32,
+    pub comment_karma: i32,
+}
+
+/// `GET /users/{user_id}/karma`: the user's totals and where they were earned, highest
+/// first.
+#[derive(Serialize)]
+pub struct Karma {
+    pub user_id: i32,
+    pub post_karma: i32,
+    pub comment_karma: i32,
+    pub total: i32,
+    pub subs: Vec<SubKarma>,
+}
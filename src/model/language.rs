@@ -0,0 +1,67 @@
+use fluent_templates::LanguageIdentifier;
+use isolang::Language;
+use serde::Deserialize;
+
+/// Detection on very short texts is mostly guesswork, so those stay untagged.
+const MIN_DETECTION_CHARS: usize = 20;
+/// whatlang's own `is_reliable` rejects most single sentences, so use a looser bar.
+const MIN_DETECTION_CONFIDENCE: f64 = 0.5;
+
+#[derive(Deserialize)]
+pub struct PreferredLanguages {
+    pub languages: Vec<String>,
+}
+
+/// Reduces a BCP 47 tag such as `en-GB` to the ISO 639-1 code (`en`) that content
+/// is tagged with. Returns `None` for anything that isn't a known language.
+pub fn normalize_language_tag(tag: &str) -> Option<String> {
+    let tag: LanguageIdentifier = tag.trim().parse().ok()?;
+    let language = tag.language.as_str();
+
+    Language::from_639_1(language).map(|_| language.to_string())
+}
+
+/// Guesses the ISO 639-1 code of `text`, if detection is confident.
+pub fn detect_language(text: &str) -> Option<String> {
+    if text.chars().count() < MIN_DETECTION_CHARS {
+        return None;
+    }
+
+    let info =
+        whatlang::detect(text).filter(|info| info.confidence() >= MIN_DETECTION_CONFIDENCE)?;
+    Language::from_639_3(info.lang().code())
+        .and_then(|language| language.to_639_1())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod language_model_tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_language_tag_keeps_primary_subtag() {
+        assert_eq!(normalize_language_tag("en-GB"), Some("en".to_string()));
+        assert_eq!(normalize_language_tag("DE"), Some("de".to_string()));
+        assert_eq!(normalize_language_tag("not a language"), None);
+        assert_eq!(normalize_language_tag("xx"), None);
+    }
+
+    #[test]
+    fn test_detect_language_recognizes_common_languages() {
+        assert_eq!(
+            detect_language("I talked with my friends about the new book yesterday evening"),
+            Some("en".to_string())
+        );
+        assert_eq!(
+            detect_language(
+                "Ich habe gestern Abend mit meinen Freunden über das neue Buch gesprochen"
+            ),
+            Some("de".to_string())
+        );
+    }
+
+    #[test]
+    fn test_detect_language_skips_short_text() {
+        assert_eq!(detect_language("ok"), None);
+    }
+}
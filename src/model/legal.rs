@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TakedownStatus {
+    Received,
+    Actioned,
+    Rejected,
+    Restored,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum DocumentKind {
+    Notice,
+    CounterNotice,
+    Correspondence,
+}
+
+#[derive(Serialize)]
+pub struct TakedownCase {
+    pub id: Uuid,
+    pub status: TakedownStatus,
+    pub complainant_name: String,
+    pub complainant_contact: String,
+    pub claim: String,
+    pub handled_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct TakedownItem {
+    pub post_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+}
+
+#[derive(Serialize)]
+pub struct TakedownDocument {
+    pub id: i64,
+    pub kind: DocumentKind,
+    pub title: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct TakedownCaseResponse {
+    pub case: TakedownCase,
+    pub items: Vec<TakedownItem>,
+    pub documents: Vec<TakedownDocument>,
+}
+
+#[derive(Deserialize)]
+pub struct NewTakedown {
+    pub complainant_name: String,
+    pub complainant_contact: String,
+    pub claim: String,
+    #[serde(default)]
+    pub post_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub comment_ids: Vec<Uuid>,
+    pub notice: String,
+}
+
+#[derive(Deserialize)]
+pub struct NewTakedownDocument {
+    pub kind: DocumentKind,
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+pub struct CounterNotice {
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+pub struct TakedownListQuery {
+    pub status: Option<TakedownStatus>,
+}
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Longest link a link post may carry.
+pub const MAX_LINK_URL_CHARS: usize = 2048;
+
+/// What a link post's page says about itself, from its OpenGraph tags or, failing
+/// those, its `<title>` and description.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct LinkPreview {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
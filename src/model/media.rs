@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use strum_macros::Display;
+use uuid::Uuid;
+
+/// Most media one post or comment may carry.
+pub const MAX_ATTACHMENTS: usize = 10;
+
+/// An uploaded file. `storage_key` names it in the media store; clients fetch it
+/// from `url`.
+#[derive(Serialize, Clone, Debug)]
+pub struct Media {
+    pub id: Uuid,
+    pub user_id: i32,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub url: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+    /// Smaller copies, once they have been made. Images already small enough get none.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renditions: Vec<Rendition>,
+}
+
+/// A scaled-down copy of an uploaded image.
+#[derive(Serialize, Clone, Debug)]
+pub struct Rendition {
+    pub kind: RenditionKind,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub url: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Serialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum RenditionKind {
+    Thumbnail,
+    Preview,
+}
+
+impl RenditionKind {
+    pub const ALL: [RenditionKind; 2] = [RenditionKind::Thumbnail, RenditionKind::Preview];
+
+    /// The square the rendition is scaled to fit.
+    pub fn max_size(self) -> u32 {
+        match self {
+            RenditionKind::Thumbnail => 320,
+            RenditionKind::Preview => 1080,
+        }
+    }
+}
+
+/// The image types uploads may be, with the extension their files are stored under.
+const IMAGE_TYPES: [(&str, &str); 4] = [
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+];
+
+/// The image type the file's leading bytes identify it as, whatever it claims to be.
+pub fn sniff_image_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+pub fn image_extension(content_type: &str) -> Option<&'static str> {
+    IMAGE_TYPES
+        .iter()
+        .find(|(image_type, _)| *image_type == content_type)
+        .map(|(_, extension)| *extension)
+}
+
+#[cfg(test)]
+mod media_model_tests {
+    use super::*;
+
+    #[test]
+    fn test_images_are_recognised_by_their_contents() {
+        assert_eq!(
+            sniff_image_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(
+            sniff_image_type(b"\xff\xd8\xff\xe0JFIF"),
+            Some("image/jpeg")
+        );
+        assert_eq!(
+            sniff_image_type(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(sniff_image_type(b"<svg xmlns=\"\"/>"), None);
+        assert_eq!(image_extension("image/jpeg"), Some("jpg"));
+        assert_eq!(image_extension("image/svg+xml"), None);
+    }
+}
@@ -1,4 +1,40 @@
+pub mod account_requirement;
+pub mod api_key;
+pub mod appeal;
+pub mod audit;
+pub mod automod;
+pub mod ban;
 pub mod comment;
+pub mod dto;
+pub mod experiment;
+pub mod feed;
+pub mod filter;
+pub mod flair;
+pub mod karma;
+pub mod language;
+pub mod legal;
+pub mod link_preview;
+pub mod media;
+pub mod mod_note;
+pub mod moderation;
+pub mod modqueue;
+pub mod oauth;
+pub mod password;
+pub mod poll;
 pub mod post;
+pub mod post_template;
+pub mod premium;
+pub mod refresh_token;
+pub mod removal_reason;
+pub mod report;
+pub mod revision;
+pub mod rule;
+pub mod saved;
+pub mod search;
+pub mod session;
+pub mod spam;
 pub mod sub;
+pub mod suspension;
 pub mod user;
+pub mod vote;
+pub mod wiki;
@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest mod note, in characters.
+pub const MAX_MOD_NOTE_CHARS: usize = 1_000;
+
+/// A note a sub's moderators keep on a user. Only the sub's moderators see it.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct ModNote {
+    pub id: i64,
+    pub sub: String,
+    pub user_id: i32,
+    /// `None` once the moderator's account is deleted.
+    pub author_id: Option<i32>,
+    pub author_username: Option<String>,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `POST /subs/{sub}/users/{id}/notes`.
+#[derive(Deserialize)]
+pub struct NewModNote {
+    pub note: String,
+}
+
+/// An item in a moderation view, such as the mod queue or ban list, with the sub's notes
+/// on the user it concerns, most recent first.
+#[derive(Serialize)]
+pub struct WithModNotes<T> {
+    #[serde(flatten)]
+    pub item: T,
+    pub mod_notes: Vec<ModNote>,
+}
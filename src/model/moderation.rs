@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum_macros::Display;
+
+#[derive(Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum ModAction {
+    NukeUserContent,
+    LegalTakedown,
+    LegalRejection,
+    LegalRestoration,
+    AddWordFilter,
+    RemoveWordFilter,
+    ApproveFilteredContent,
+    PurgePost,
+    PurgeComment,
+    PinPost,
+    UnpinPost,
+    LockPost,
+    UnlockPost,
+    UnarchivePost,
+    CreateFlair,
+    UpdateFlair,
+    DeleteFlair,
+    CreateUserFlair,
+    UpdateUserFlair,
+    DeleteUserFlair,
+    CreatePostTemplate,
+    UpdatePostTemplate,
+    DeletePostTemplate,
+    CreateRule,
+    UpdateRule,
+    DeleteRule,
+    BanUser,
+    UnbanUser,
+    ApprovePost,
+    ApproveComment,
+    RemovePost,
+    RemoveComment,
+    CreateRemovalReason,
+    UpdateRemovalReason,
+    DeleteRemovalReason,
+    SuspendUser,
+    UnsuspendUser,
+    ShadowbanUser,
+    UnshadowbanUser,
+    UpdateAutomod,
+    SetAccountRequirement,
+    RemoveAccountRequirement,
+    GrantAppeal,
+    DenyAppeal,
+    AddModNote,
+    DeleteModNote,
+}
+
+/// Why a post or comment is no longer visible. Legal removals are kept
+/// distinct from moderator removals so threads can say which one applied.
+/// `Filter` content is held by a word filter until a moderator reviews it.
+/// `Deleted` content was deleted by its author and is gone for good.
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum RemovalKind {
+    Moderator,
+    Legal,
+    Filter,
+    Deleted,
+}
+
+#[derive(Serialize)]
+pub struct ModLogEntry {
+    pub id: i64,
+    pub moderator_id: Option<i32>,
+    pub action: String,
+    pub target_user_id: Option<i32>,
+    pub sub: Option<String>,
+    pub details: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct ModLogQuery {
+    pub moderator_id: Option<i32>,
+    pub target_user_id: Option<i32>,
+    pub sub: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct NukeRequest {
+    pub sub: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct NukeSummary {
+    pub user_id: i32,
+    pub posts_removed: i64,
+    pub comments_removed: i64,
+}
@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Serialize, sqlx::Type, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ModQueueKind {
+    Post,
+    Comment,
+}
+
+/// A post or comment waiting for a moderator: reported, held by a `queue` word filter or
+/// for its spam score, or both.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct ModQueueItem {
+    pub kind: ModQueueKind,
+    pub id: Uuid,
+    /// The post itself, or the post the comment is on.
+    pub post_id: Uuid,
+    pub author_id: i32,
+    /// `None` for comments.
+    pub title: Option<String>,
+    /// The original content, even while it is held.
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    /// Whether a word filter or its spam score is holding it back.
+    pub held: bool,
+    /// Reports not yet dealt with.
+    pub report_count: i64,
+    /// The distinct reasons reporters gave.
+    pub reasons: Vec<String>,
+    /// The distinct sub rules reporters named.
+    pub rule_ids: Vec<i32>,
+    /// How likely it looked to be spam when it was submitted, from 0 to 100. `None` if
+    /// it scored nothing.
+    pub spam_score: Option<i32>,
+    /// The signals that added to the spam score.
+    pub spam_reasons: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ModQueueState {
+    Approved,
+    Removed,
+}
+
+/// What a moderator's decision on a queue item did.
+#[derive(Serialize)]
+pub struct ModQueueResolution {
+    pub kind: ModQueueKind,
+    pub id: Uuid,
+    pub state: ModQueueState,
+    pub reports_resolved: u64,
+}
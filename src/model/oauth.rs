@@ -0,0 +1,214 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+use url::Url;
+
+const MAX_USERNAME_LENGTH: usize = 32;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum OAuthProvider {
+    Github,
+    Google,
+}
+
+impl OAuthProvider {
+    pub fn authorize_endpoint(self) -> &'static str {
+        match self {
+            OAuthProvider::Github => "https://github.com/login/oauth/authorize",
+            OAuthProvider::Google => "https://accounts.google.com/o/oauth2/v2/auth",
+        }
+    }
+
+    pub fn token_endpoint(self) -> &'static str {
+        match self {
+            OAuthProvider::Github => "https://github.com/login/oauth/access_token",
+            OAuthProvider::Google => "https://oauth2.googleapis.com/token",
+        }
+    }
+
+    pub fn userinfo_endpoint(self) -> &'static str {
+        match self {
+            OAuthProvider::Github => "https://api.github.com/user",
+            OAuthProvider::Google => "https://openidconnect.googleapis.com/v1/userinfo",
+        }
+    }
+
+    fn scope(self) -> &'static str {
+        match self {
+            OAuthProvider::Github => "read:user user:email",
+            OAuthProvider::Google => "openid email profile",
+        }
+    }
+
+    /// Where to send the browser to ask the user for consent.
+    pub fn authorize_url(self, client_id: &str, redirect_uri: &str, state: &str) -> Url {
+        let mut url = Url::parse(self.authorize_endpoint()).expect("provider URLs are valid");
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", self.scope())
+            .append_pair("state", state);
+        url
+    }
+}
+
+/// Query string the provider redirects back with. `error` is set instead of `code`
+/// when the user declines.
+#[derive(Deserialize)]
+pub struct OAuthCallback {
+    pub code: Option<String>,
+    pub state: String,
+    pub error: Option<String>,
+}
+
+/// A pending authorization request, consumed by the callback. `nonce_hash` is the hash
+/// of the nonce cookie given to the browser that started it.
+pub struct OAuthState {
+    pub provider: OAuthProvider,
+    pub link_user_id: Option<i32>,
+    pub nonce_hash: String,
+}
+
+/// Token endpoint response. GitHub reports failures with a 200 and `error`.
+#[derive(Deserialize)]
+pub struct ProviderToken {
+    pub access_token: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GithubUser {
+    pub id: i64,
+    pub login: String,
+    pub email: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GoogleUser {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+}
+
+/// The provider's account, reduced to what is stored locally.
+pub struct ProviderIdentity {
+    pub provider: OAuthProvider,
+    pub subject: String,
+    pub email: Option<String>,
+    /// Whether the provider vouches for `email`.
+    pub email_verified: bool,
+    /// Starting point for the username of a newly created account.
+    pub preferred_username: String,
+}
+
+impl From<GithubUser> for ProviderIdentity {
+    fn from(user: GithubUser) -> Self {
+        ProviderIdentity {
+            provider: OAuthProvider::Github,
+            subject: user.id.to_string(),
+            // GitHub only shows a public profile address once it has been verified
+            email_verified: user.email.is_some(),
+            email: user.email,
+            preferred_username: user.login,
+        }
+    }
+}
+
+impl From<GoogleUser> for ProviderIdentity {
+    fn from(user: GoogleUser) -> Self {
+        let preferred_username = user
+            .email
+            .as_deref()
+            .and_then(|email| email.split('@').next())
+            .map(str::to_string)
+            .or(user.name)
+            .unwrap_or_default();
+
+        ProviderIdentity {
+            provider: OAuthProvider::Google,
+            subject: user.sub,
+            email: user.email,
+            email_verified: user.email_verified.unwrap_or(false),
+            preferred_username,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct UserIdentity {
+    pub provider: OAuthProvider,
+    pub email: Option<String>,
+    pub linked_at: DateTime<Utc>,
+}
+
+/// Reduces a provider's name for the user to letters, digits, `_` and `-`, for use as
+/// a local username.
+pub fn derive_username(preferred: &str) -> String {
+    let username: String = preferred
+        .chars()
+        .map(|c| {
+            if c.is_whitespace() || c == '.' {
+                '_'
+            } else {
+                c
+            }
+        })
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .take(MAX_USERNAME_LENGTH)
+        .collect();
+
+    if username.is_empty() {
+        "user".to_string()
+    } else {
+        username
+    }
+}
+
+#[cfg(test)]
+mod oauth_model_tests {
+    use super::*;
+
+    #[test]
+    fn test_authorize_url_carries_client_and_state() {
+        let url = OAuthProvider::Github.authorize_url(
+            "client",
+            "https://forum.example/auth/oauth/github/callback",
+            "abc",
+        );
+        let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+
+        assert_eq!(url.host_str(), Some("github.com"));
+        assert!(pairs.contains(&("client_id".to_string(), "client".to_string())));
+        assert!(pairs.contains(&("state".to_string(), "abc".to_string())));
+        assert!(pairs.contains(&(
+            "redirect_uri".to_string(),
+            "https://forum.example/auth/oauth/github/callback".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_derive_username() {
+        assert_eq!(derive_username("Jane Doe"), "Jane_Doe");
+        assert_eq!(derive_username("jane.doe+forum"), "jane_doeforum");
+        assert_eq!(derive_username("ß✓"), "user");
+        assert_eq!(derive_username(&"a".repeat(40)).len(), MAX_USERNAME_LENGTH);
+    }
+
+    #[test]
+    fn test_google_identity_prefers_email_local_part() {
+        let identity = ProviderIdentity::from(GoogleUser {
+            sub: "123".to_string(),
+            email: Some("jane@example.com".to_string()),
+            email_verified: Some(true),
+            name: Some("Jane".to_string()),
+        });
+
+        assert_eq!(identity.subject, "123");
+        assert_eq!(identity.preferred_username, "jane");
+    }
+}
@@ -0,0 +1,126 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use strum_macros::Display;
+
+/// Widely used passwords, one per line, compared case-insensitively.
+static COMMON_PASSWORDS: LazyLock<HashSet<&'static str>> =
+    LazyLock::new(|| include_str!("common_passwords.txt").lines().collect());
+
+/// What a password has to look like before it is hashed.
+#[derive(Clone, Debug)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    /// How many of lowercase letters, uppercase letters, digits and symbols must appear.
+    pub min_character_classes: usize,
+    pub deny_common: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: 8,
+            min_character_classes: 0,
+            deny_common: true,
+        }
+    }
+}
+
+/// Argon2id cost for new password hashes. Hashes made with other settings still
+/// verify, and are redone with these the next time their owner signs in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HashParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for HashParams {
+    fn default() -> Self {
+        HashParams {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl HashParams {
+    pub fn hasher(&self) -> Result<Argon2<'static>, argon2::Error> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)?;
+
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+#[derive(Serialize, Display, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PasswordViolation {
+    TooShort,
+    TooFewCharacterClasses,
+    CommonPassword,
+}
+
+impl PasswordPolicy {
+    /// Every rule the password breaks, so clients can report them all at once.
+    pub fn check(&self, password: &str) -> Vec<PasswordViolation> {
+        let mut violations = Vec::new();
+
+        if password.chars().count() < self.min_length {
+            violations.push(PasswordViolation::TooShort);
+        }
+        if character_classes(password) < self.min_character_classes {
+            violations.push(PasswordViolation::TooFewCharacterClasses);
+        }
+        if self.deny_common && COMMON_PASSWORDS.contains(password.to_lowercase().as_str()) {
+            violations.push(PasswordViolation::CommonPassword);
+        }
+
+        violations
+    }
+}
+
+fn character_classes(password: &str) -> usize {
+    let classes: [fn(char) -> bool; 4] = [
+        char::is_lowercase,
+        char::is_uppercase,
+        |c| c.is_ascii_digit(),
+        |c| !c.is_alphanumeric(),
+    ];
+
+    classes
+        .iter()
+        .filter(|class| password.chars().any(class))
+        .count()
+}
+
+#[cfg(test)]
+mod password_model_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy() {
+        let policy = PasswordPolicy::default();
+        assert_eq!(policy.check("short"), vec![PasswordViolation::TooShort]);
+        assert_eq!(
+            policy.check("Password1"),
+            vec![PasswordViolation::CommonPassword]
+        );
+        assert!(policy.check("correct horse battery").is_empty());
+    }
+
+    #[test]
+    fn test_character_classes() {
+        let policy = PasswordPolicy {
+            min_character_classes: 3,
+            ..PasswordPolicy::default()
+        };
+        assert_eq!(
+            policy.check("lowercaseonly"),
+            vec![PasswordViolation::TooFewCharacterClasses]
+        );
+        assert!(policy.check("Mixed-case words").is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const MIN_POLL_OPTIONS: usize = 2;
+pub const MAX_POLL_OPTIONS: usize = 10;
+/// Longest poll option, in characters.
+pub const MAX_POLL_OPTION_CHARS: usize = 100;
+
+/// The `poll` of a new post. With `hide_results`, the default, voters only see the
+/// counts once they have voted or the poll has closed.
+#[derive(Deserialize)]
+pub struct NewPoll {
+    pub options: Vec<String>,
+    pub ends_at: Option<DateTime<Utc>>,
+    #[serde(default = "hide_results_by_default")]
+    pub hide_results: bool,
+}
+
+fn hide_results_by_default() -> bool {
+    true
+}
+
+impl NewPoll {
+    /// The options with surrounding whitespace trimmed, if there are enough of them,
+    /// none is empty or too long, and the poll doesn't end before `now`.
+    pub fn normalized_options(&self, now: DateTime<Utc>) -> Option<Vec<String>> {
+        if self.ends_at.is_some_and(|ends_at| ends_at <= now)
+            || !(MIN_POLL_OPTIONS..=MAX_POLL_OPTIONS).contains(&self.options.len())
+        {
+            return None;
+        }
+
+        self.options
+            .iter()
+            .map(|option| {
+                let option = option.trim();
+                (1..=MAX_POLL_OPTION_CHARS)
+                    .contains(&option.chars().count())
+                    .then(|| option.to_string())
+            })
+            .collect()
+    }
+}
+
+/// A post's poll with every option's vote count.
+#[derive(Clone, Debug)]
+pub struct Poll {
+    pub ends_at: Option<DateTime<Utc>>,
+    pub hide_results: bool,
+    pub options: Vec<PollOption>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PollOption {
+    pub id: i32,
+    pub text: String,
+    pub votes: i64,
+}
+
+impl Poll {
+    pub fn is_closed(&self, now: DateTime<Utc>) -> bool {
+        self.ends_at.is_some_and(|ends_at| ends_at <= now)
+    }
+
+    pub fn has_option(&self, option_id: i32) -> bool {
+        self.options.iter().any(|option| option.id == option_id)
+    }
+
+    /// The poll as `viewer_vote`'s caster sees it: counts are left out while they're
+    /// hidden from them.
+    pub fn view(self, viewer_vote: Option<i32>, now: DateTime<Utc>) -> PollView {
+        let closed = self.is_closed(now);
+        let results_hidden = self.hide_results && viewer_vote.is_none() && !closed;
+
+        PollView {
+            ends_at: self.ends_at,
+            closed,
+            results_hidden,
+            total_votes: (!results_hidden).then(|| self.options.iter().map(|o| o.votes).sum()),
+            vote: viewer_vote,
+            options: self
+                .options
+                .into_iter()
+                .map(|option| PollOptionView {
+                    id: option.id,
+                    text: option.text,
+                    votes: (!results_hidden).then_some(option.votes),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PollView {
+    pub ends_at: Option<DateTime<Utc>>,
+    pub closed: bool,
+    pub results_hidden: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_votes: Option<i64>,
+    /// The option the caller voted for, if they're signed in and have voted.
+    pub vote: Option<i32>,
+    pub options: Vec<PollOptionView>,
+}
+
+#[derive(Serialize)]
+pub struct PollOptionView {
+    pub id: i32,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub votes: Option<i64>,
+}
+
+/// `POST /posts/{id}/poll/vote`.
+#[derive(Deserialize)]
+pub struct PollVoteRequest {
+    pub option_id: i32,
+}
+
+#[cfg(test)]
+mod poll_model_tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn poll(hide_results: bool, ends_at: Option<DateTime<Utc>>) -> Poll {
+        Poll {
+            ends_at,
+            hide_results,
+            options: vec![
+                PollOption {
+                    id: 1,
+                    text: "Yes".to_string(),
+                    votes: 3,
+                },
+                PollOption {
+                    id: 2,
+                    text: "No".to_string(),
+                    votes: 1,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_results_are_hidden_until_voted_or_closed() {
+        let now = Utc::now();
+
+        let open = poll(true, Some(now + Duration::hours(1))).view(None, now);
+        assert!(open.results_hidden);
+        assert!(open.total_votes.is_none());
+        assert!(open.options.iter().all(|option| option.votes.is_none()));
+
+        let voted = poll(true, None).view(Some(2), now);
+        assert_eq!(voted.total_votes, Some(4));
+        let closed = poll(true, Some(now - Duration::hours(1))).view(None, now);
+        assert!(closed.closed);
+        assert_eq!(closed.options[0].votes, Some(3));
+        assert!(!poll(false, None).view(None, now).results_hidden);
+    }
+}
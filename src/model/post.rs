@@ -1,27 +1,398 @@
-use crate::model::comment::Comment;
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use crate::model::dto::CommentView;
+use crate::model::flair::AuthorFlair;
+use crate::model::link_preview::LinkPreview;
+use crate::model::media::Media;
+use crate::model::moderation::RemovalKind;
+use crate::model::poll::{NewPoll, PollView};
+use crate::render;
+use chrono::{DateTime, Duration, Utc};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashSet;
+use strum_macros::{Display, EnumString};
 use uuid::Uuid;
 
-#[derive(Serialize)]
+/// Serialized with its content rendered as `rendered_html`, so it is written by hand.
+#[derive(Clone)]
 pub struct Post {
     pub id: Uuid,
     pub sub: String,
     pub user_id: i32,
+    pub author_flair: Option<AuthorFlair>,
     pub title: String,
     pub content: String,
     pub timestamp: DateTime<Utc>,
+    pub removal: Option<RemovalKind>,
+    pub nsfw: bool,
+    /// Spoilers are hidden behind a warning until the reader chooses to see them.
+    pub spoiler: bool,
+    pub language: Option<String>,
+    /// Upvotes minus downvotes.
+    pub score: i32,
+    pub status: PostStatus,
+    /// When a scheduled draft will be published.
+    pub publish_at: Option<DateTime<Utc>>,
+    /// When the content was last changed, if it ever was.
+    pub edited_at: Option<DateTime<Utc>>,
+    /// Where the post is pinned among its sub's pinned posts, lowest first.
+    pub pin_order: Option<i32>,
+    /// Locked threads take no new comments.
+    pub locked: bool,
+    pub lock_reason: Option<String>,
+    /// Archived posts are old enough to take no more votes or comments.
+    pub archived: bool,
+    pub flair_id: Option<i32>,
+    /// The post this one is a crosspost of.
+    pub crosspost_parent_id: Option<Uuid>,
+    /// The page a link post points to.
+    pub link_url: Option<String>,
+}
+
+impl Post {
+    pub fn rendered_html(&self) -> String {
+        render::markdown_to_html(&self.content)
+    }
+}
+
+impl Serialize for Post {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut post = serializer.serialize_struct("Post", 23)?;
+        post.serialize_field("id", &self.id)?;
+        post.serialize_field("sub", &self.sub)?;
+        post.serialize_field("user_id", &self.user_id)?;
+        post.serialize_field("author_flair", &self.author_flair)?;
+        post.serialize_field("title", &self.title)?;
+        post.serialize_field("content", &self.content)?;
+        post.serialize_field("rendered_html", &self.rendered_html())?;
+        post.serialize_field("timestamp", &self.timestamp)?;
+        post.serialize_field("removal", &self.removal)?;
+        post.serialize_field("nsfw", &self.nsfw)?;
+        post.serialize_field("spoiler", &self.spoiler)?;
+        post.serialize_field("language", &self.language)?;
+        post.serialize_field("score", &self.score)?;
+        post.serialize_field("status", &self.status)?;
+        post.serialize_field("publish_at", &self.publish_at)?;
+        post.serialize_field("edited_at", &self.edited_at)?;
+        post.serialize_field("pin_order", &self.pin_order)?;
+        post.serialize_field("locked", &self.locked)?;
+        post.serialize_field("lock_reason", &self.lock_reason)?;
+        post.serialize_field("archived", &self.archived)?;
+        post.serialize_field("flair_id", &self.flair_id)?;
+        post.serialize_field("crosspost_parent_id", &self.crosspost_parent_id)?;
+        post.serialize_field("link_url", &self.link_url)?;
+        post.end()
+    }
+}
+
+/// Drafts are only visible to their author and stay out of listings and search until
+/// published.
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Default, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PostStatus {
+    Draft,
+    #[default]
+    Published,
 }
 
 #[derive(Deserialize)]
 pub struct NewPost {
-    pub user_id: i32,
     pub title: String,
+    /// Link posts can leave the body out.
+    #[serde(default)]
     pub content: String,
+    /// Makes the post a link post.
+    pub url: Option<String>,
+    /// Posts the link even if the sub already has a recent post of it.
+    #[serde(default)]
+    pub repost: bool,
+    #[serde(default)]
+    pub nsfw: bool,
+    /// Defaults to the sub's `spoiler_by_default`.
+    pub spoiler: Option<bool>,
+    /// Schedules the post: it is saved as a draft and published at this time.
+    pub publish_at: Option<DateTime<Utc>>,
+    pub language: Option<String>,
+    /// Makes the post a poll.
+    #[serde(default)]
+    pub poll: Option<NewPoll>,
+    /// Uploads of the author's to attach, in order.
+    #[serde(default)]
+    pub media_ids: Vec<Uuid>,
+}
+
+/// `POST /posts/drafts`: a new post with the sub it's meant for, which `POST /posts/{sub}`
+/// takes from its path.
+#[derive(Deserialize)]
+pub struct NewDraft {
+    pub sub: String,
+    #[serde(flatten)]
+    pub post: NewPost,
+}
+
+/// `POST /posts/{id}/crosspost`. Without a `title` the crosspost takes the original's.
+#[derive(Deserialize)]
+pub struct CrosspostRequest {
+    pub sub: String,
+    pub title: Option<String>,
+}
+
+/// `PATCH /subs/{sub}/posts/{id}/pin`. Without an `order` the post goes after the
+/// sub's other pinned posts.
+#[derive(Deserialize, Default)]
+pub struct PinRequest {
+    pub order: Option<i32>,
+}
+
+/// `PATCH /posts/{id}/lock`.
+#[derive(Deserialize, Default)]
+pub struct LockRequest {
+    pub reason: Option<String>,
+}
+
+/// `PATCH /posts/{id}/flags`. Flags left out keep their current value.
+#[derive(Deserialize)]
+pub struct PostFlagsRequest {
+    pub nsfw: Option<bool>,
+    pub spoiler: Option<bool>,
+}
+
+/// Whether the post is hidden from the caller's listings after the change.
+#[derive(Serialize)]
+pub struct HideResult {
+    pub hidden: bool,
+}
+
+/// How post listings are ordered, from `?sort=`.
+#[derive(Deserialize, Display, EnumString, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum PostSort {
+    /// Score with a decay for age; see `repo::post::hot_rank`.
+    Hot,
+    /// Highest score first.
+    Top,
+    /// Newest first.
+    #[default]
+    New,
+    /// Many votes split evenly between up and down first.
+    Controversial,
+}
+
+/// How far back `top` and `controversial` listings look, from `?t=`.
+#[derive(Deserialize, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeWindow {
+    Day,
+    Week,
+    #[default]
+    All,
+}
+
+impl PostSort {
+    /// The oldest post a listing sorted this way includes. `hot` and `new` already
+    /// favour recent posts, so the window only narrows `top` and `controversial`.
+    pub fn since(self, window: TimeWindow, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if !matches!(self, PostSort::Top | PostSort::Controversial) {
+            return None;
+        }
+
+        match window {
+            TimeWindow::Day => Some(now - Duration::days(1)),
+            TimeWindow::Week => Some(now - Duration::weeks(1)),
+            TimeWindow::All => None,
+        }
+    }
+}
+
+pub const DEFAULT_LISTING_LIMIT: i64 = 25;
+pub const MAX_LISTING_LIMIT: i64 = 100;
+/// Least score a post needs to be listed in `GET /feed/popular`.
+pub const POPULAR_MIN_SCORE: i32 = 2;
+
+/// `GET /posts/for_sub/{sub}` and `GET /feed/all` take `?sort=&t=&limit=&after=`, where
+/// `after` is the `next_cursor` of the previous page, `?flair=` to only list posts
+/// with that flair, and `?include_nsfw=` to override the viewer's `show_nsfw` preference.
+#[derive(Deserialize)]
+pub struct ListingQuery {
+    #[serde(default)]
+    pub sort: PostSort,
+    #[serde(default)]
+    pub t: TimeWindow,
+    pub limit: Option<i64>,
+    pub after: Option<String>,
+    pub flair: Option<i32>,
+    pub include_nsfw: Option<bool>,
+}
+
+/// Which page of a listing to fetch.
+pub struct Listing {
+    pub sort: PostSort,
+    pub since: Option<DateTime<Utc>>,
+    pub after: Option<ListingCursor>,
+    pub limit: i64,
+    pub flair: Option<i32>,
+    /// Leaves out the posts this user has hidden, and posts in private subs they may
+    /// not read.
+    pub viewer: Option<i32>,
+}
+
+impl Listing {
+    /// The first page of a listing over all time.
+    pub fn first_page(sort: PostSort, limit: i64) -> Self {
+        Listing {
+            sort,
+            since: None,
+            after: None,
+            limit,
+            flair: None,
+            viewer: None,
+        }
+    }
+}
+
+/// The position of the last post on a page: its rank under the listing's sort, with
+/// the post id breaking ties. The next page starts after it, so posts published in
+/// the meantime don't shift pages the way an offset would.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ListingCursor {
+    pub sort: PostSort,
+    pub rank: f64,
+    pub id: Uuid,
+}
+
+impl ListingCursor {
+    /// Opaque to clients; it only has to survive a round trip through a query string.
+    pub fn encode(&self) -> String {
+        hex::encode(format!(
+            "{}:{:016x}:{}",
+            self.sort,
+            self.rank.to_bits(),
+            self.id
+        ))
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let decoded = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+        let mut parts = decoded.splitn(3, ':');
+        let sort = parts.next()?.parse().ok()?;
+        let rank = f64::from_bits(u64::from_str_radix(parts.next()?, 16).ok()?);
+        let id = parts.next()?.parse().ok()?;
+
+        Some(ListingCursor { sort, rank, id })
+    }
+}
+
+/// A listed post with its rank under the listing's sort, for building cursors.
+pub struct RankedPost {
+    pub post: Post,
+    pub rank: f64,
+}
+
+/// One page of a listing. `next_cursor` is absent on the last page.
+#[derive(Serialize)]
+pub struct PostPage {
+    pub posts: Vec<FeedPost>,
+    pub next_cursor: Option<String>,
+}
+
+/// A listed post, with whether the signed-in viewer has saved it.
+#[derive(Serialize)]
+pub struct FeedPost {
+    #[serde(flatten)]
+    pub post: Post,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved: Option<bool>,
+}
+
+impl From<Post> for FeedPost {
+    fn from(post: Post) -> Self {
+        FeedPost { post, saved: None }
+    }
+}
+
+impl PostPage {
+    /// Listings fetch one post more than the page holds; if it's there, another page
+    /// follows, starting after the last post shown.
+    pub fn new(mut ranked: Vec<RankedPost>, listing: &Listing) -> Self {
+        let more = ranked.len() as i64 > listing.limit;
+        ranked.truncate(listing.limit.max(0) as usize);
+        let next_cursor = ranked.last().filter(|_| more).map(|last| {
+            ListingCursor {
+                sort: listing.sort,
+                rank: last.rank,
+                id: last.post.id,
+            }
+            .encode()
+        });
+
+        PostPage {
+            posts: ranked
+                .into_iter()
+                .map(|ranked| FeedPost::from(ranked.post))
+                .collect(),
+            next_cursor,
+        }
+    }
+
+    /// Flags each post with whether it is among the viewer's `saved` posts.
+    pub fn mark_saved(&mut self, saved: &HashSet<Uuid>) {
+        for listed in &mut self.posts {
+            listed.saved = Some(saved.contains(&listed.post.id));
+        }
+    }
+
+    pub fn post_ids(&self) -> Vec<Uuid> {
+        self.posts.iter().map(|listed| listed.post.id).collect()
+    }
+}
+
+/// `GET /posts?ids=` takes a comma-separated list of post ids.
+#[derive(Deserialize)]
+pub struct PostBatchQuery {
+    pub ids: String,
+}
+
+/// `POST /posts/by_ids`, for lists too long for a query string.
+#[derive(Deserialize)]
+pub struct PostBatchRequest {
+    pub ids: Vec<Uuid>,
 }
 
 #[derive(Serialize)]
 pub struct PostResponse {
     pub post: Post,
-    pub comments: Vec<Comment>,
+    pub comments: Vec<CommentView>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll: Option<PollView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<Media>,
+    /// A link post's preview, once its page has been fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_preview: Option<LinkPreview>,
+    /// The post this one is a crosspost of, while it can still be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crosspost_parent: Option<Post>,
+    /// Why a moderator removed the post, shown only to its author.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removal_reason: Option<String>,
+}
+
+#[cfg(test)]
+mod post_model_tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trips() {
+        let cursor = ListingCursor {
+            sort: PostSort::Hot,
+            rank: 12_345.678,
+            id: Uuid::new_v4(),
+        };
+
+        assert_eq!(ListingCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(ListingCursor::decode("not a cursor"), None);
+        assert_eq!(ListingCursor::decode(&hex::encode("bogus:0:0")), None);
+    }
 }
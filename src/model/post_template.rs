@@ -0,0 +1,149 @@
+use crate::model::language::detect_language;
+use crate::model::post::{Post, PostStatus};
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+use uuid::Uuid;
+
+/// How often a sub's post template is posted.
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Repeat {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Repeat {
+    /// The run after `at`.
+    pub fn after(self, at: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Repeat::Daily => at + Duration::days(1),
+            Repeat::Weekly => at + Duration::weeks(1),
+            Repeat::Monthly => at.checked_add_months(Months::new(1)).unwrap_or(at),
+        }
+    }
+
+    /// The latest run due by `now` of those from `at` on, so runs missed while the server
+    /// was down are skipped rather than all posted at once.
+    pub fn latest_due(self, mut at: DateTime<Utc>, now: DateTime<Utc>) -> DateTime<Utc> {
+        while self.after(at) <= now {
+            at = self.after(at);
+        }
+        at
+    }
+}
+
+/// A post made in a sub on a schedule, such as a weekly questions thread. The title
+/// and content may use `{date}`, `{year}`, `{month}`, `{day}` and `{weekday}`, filled
+/// in with the date of each run.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct PostTemplate {
+    pub id: i32,
+    pub sub: String,
+    /// The moderator the posts are made as.
+    pub author_id: i32,
+    pub title: String,
+    pub content: String,
+    pub repeat: Repeat,
+    pub next_run_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PostTemplate {
+    /// The post for the run at `at`, made as the template's author.
+    pub fn post_at(&self, at: DateTime<Utc>) -> Post {
+        let title = interpolate(&self.title, at);
+        let content = interpolate(&self.content, at);
+        Post {
+            id: Uuid::new_v4(),
+            sub: self.sub.clone(),
+            user_id: self.author_id,
+            language: detect_language(&format!("{}\n{}", title, content)),
+            title,
+            content,
+            timestamp: Utc::now(),
+            removal: None,
+            nsfw: false,
+            spoiler: false,
+            score: 0,
+            status: PostStatus::Published,
+            publish_at: None,
+            edited_at: None,
+            pin_order: None,
+            locked: false,
+            lock_reason: None,
+            archived: false,
+            flair_id: None,
+            crosspost_parent_id: None,
+            link_url: None,
+            author_flair: None,
+        }
+    }
+}
+
+/// `POST /subs/{sub}/scheduled-posts`.
+#[derive(Deserialize)]
+pub struct NewPostTemplate {
+    pub title: String,
+    #[serde(default)]
+    pub content: String,
+    pub repeat: Repeat,
+    /// When it is first posted.
+    pub starts_at: DateTime<Utc>,
+}
+
+/// `PATCH /subs/{sub}/scheduled-posts/{id}`: fields left out are kept.
+#[derive(Deserialize)]
+pub struct PostTemplateUpdate {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub repeat: Option<Repeat>,
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+/// Fills in the template's date variables for a run at `at`.
+pub fn interpolate(template: &str, at: DateTime<Utc>) -> String {
+    [
+        ("{date}", "%Y-%m-%d"),
+        ("{year}", "%Y"),
+        ("{month}", "%B"),
+        ("{day}", "%-d"),
+        ("{weekday}", "%A"),
+    ]
+    .iter()
+    .fold(template.to_string(), |text, (variable, format)| {
+        text.replace(variable, &at.format(format).to_string())
+    })
+}
+
+#[cfg(test)]
+mod post_template_tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_date_variables_are_filled_in() {
+        let at = Utc.with_ymd_and_hms(2024, 11, 18, 9, 0, 0).unwrap();
+        assert_eq!(
+            interpolate(
+                "{weekday} questions, {month} {day} {year} ({date}) {other}",
+                at
+            ),
+            "Monday questions, November 18 2024 (2024-11-18) {other}"
+        );
+    }
+
+    #[test]
+    fn test_missed_runs_are_skipped() {
+        let at = Utc.with_ymd_and_hms(2024, 1, 31, 9, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 2, 14, 9, 0, 0).unwrap();
+        assert_eq!(
+            Repeat::Weekly.latest_due(at, now),
+            Utc.with_ymd_and_hms(2024, 2, 14, 9, 0, 0).unwrap()
+        );
+        assert_eq!(Repeat::Monthly.latest_due(at, now), at);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::config::PremiumConfig;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use strum_macros::Display;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SubscriptionSource {
+    Manual,
+    Payment,
+}
+
+#[derive(Serialize)]
+pub struct PremiumSubscription {
+    pub id: i64,
+    pub source: SubscriptionSource,
+    pub external_id: Option<String>,
+    pub granted_by: Option<i32>,
+    pub starts_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct PremiumGrant {
+    pub days: i32,
+}
+
+/// A payment provider webhook delivery. `id` identifies the event, so redeliveries
+/// can be recognised.
+#[derive(Deserialize)]
+pub struct PaymentEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: PaymentEventKind,
+    pub data: PaymentSubscription,
+}
+
+#[derive(Deserialize, Display, Clone, Copy)]
+pub enum PaymentEventKind {
+    #[serde(rename = "subscription.activated")]
+    #[strum(serialize = "subscription.activated")]
+    Activated,
+    #[serde(rename = "subscription.renewed")]
+    #[strum(serialize = "subscription.renewed")]
+    Renewed,
+    /// Stops renewal; the member keeps premium until `current_period_end`.
+    #[serde(rename = "subscription.cancelled")]
+    #[strum(serialize = "subscription.cancelled")]
+    Cancelled,
+}
+
+#[derive(Deserialize)]
+pub struct PaymentSubscription {
+    pub subscription_id: String,
+    pub user_id: i32,
+    pub current_period_end: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AwardType {
+    Helpful,
+    Wholesome,
+    Gold,
+    Platinum,
+}
+
+impl AwardType {
+    const ALL: [AwardType; 4] = [
+        AwardType::Helpful,
+        AwardType::Wholesome,
+        AwardType::Gold,
+        AwardType::Platinum,
+    ];
+
+    pub fn premium_only(self) -> bool {
+        matches!(self, AwardType::Gold | AwardType::Platinum)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewAward {
+    pub award_type: AwardType,
+}
+
+/// How many awards of one type a post or comment has received.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AwardCount {
+    pub award_type: AwardType,
+    pub count: i64,
+}
+
+/// What an account's membership entitles it to.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Perks {
+    pub ad_free: bool,
+    pub max_upload_bytes: u64,
+    pub award_types: Vec<AwardType>,
+}
+
+impl Perks {
+    pub fn for_member(is_premium: bool, config: &PremiumConfig) -> Self {
+        Perks {
+            ad_free: is_premium,
+            max_upload_bytes: if is_premium {
+                config.premium_upload_limit_bytes
+            } else {
+                config.upload_limit_bytes
+            },
+            award_types: AwardType::ALL
+                .into_iter()
+                .filter(|award| is_premium || !award.premium_only())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PremiumStatus {
+    pub user_id: i32,
+    pub is_premium: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub perks: Perks,
+}
+
+/// Checks a hex HMAC-SHA256 of the raw request body, compared in constant time.
+pub fn verify_webhook_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod premium_model_tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config() -> PremiumConfig {
+        PremiumConfig {
+            webhook_secret: None,
+            upload_limit_bytes: 10,
+            premium_upload_limit_bytes: 50,
+            expiry_interval: Duration::from_secs(300),
+        }
+    }
+
+    #[test]
+    fn test_premium_members_get_larger_uploads_and_exclusive_awards() {
+        let free = Perks::for_member(false, &config());
+        let premium = Perks::for_member(true, &config());
+
+        assert!(!free.ad_free && premium.ad_free);
+        assert_eq!((free.max_upload_bytes, premium.max_upload_bytes), (10, 50));
+        assert!(!free.award_types.contains(&AwardType::Gold));
+        assert!(premium.award_types.contains(&AwardType::Platinum));
+    }
+
+    #[test]
+    fn test_webhook_signature_must_match_body_and_secret() {
+        let body = br#"{"id":"evt_1"}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(verify_webhook_signature("secret", body, &signature));
+        assert!(!verify_webhook_signature("other", body, &signature));
+        assert!(!verify_webhook_signature("secret", b"{}", &signature));
+        assert!(!verify_webhook_signature("secret", body, "not hex"));
+    }
+
+    #[test]
+    fn test_payment_event_parses_provider_payload() {
+        let event: PaymentEvent = serde_json::from_str(
+            r#"{
+                "id": "evt_1",
+                "type": "subscription.renewed",
+                "data": {
+                    "subscription_id": "sub_1",
+                    "user_id": 7,
+                    "current_period_end": "2024-11-09T00:00:00Z"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(event.kind.to_string(), "subscription.renewed");
+        assert_eq!(event.data.user_id, 7);
+    }
+}
@@ -0,0 +1,26 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// `refresh_token` is the one to revoke along with the access token the request was
+/// made with. `all_devices` ends every login of the account instead.
+#[derive(Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub all_devices: bool,
+}
+
+/// What presenting a refresh token led to.
+#[derive(Debug, PartialEq)]
+pub enum RefreshOutcome {
+    /// The token was swapped for a new one in the same family.
+    Rotated { user_id: i32 },
+    /// The token had already been used or revoked, so its family has been revoked.
+    Reused,
+    /// Unknown or expired.
+    Invalid,
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest removal reason title, in characters.
+pub const MAX_REMOVAL_REASON_TITLE_CHARS: usize = 100;
+/// Longest removal message, template and note together, in characters.
+pub const MAX_REMOVAL_MESSAGE_CHARS: usize = 2_000;
+
+/// A message moderators of a sub can send along when they remove a post or comment.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct RemovalReason {
+    pub id: i32,
+    pub sub: String,
+    /// Shown to moderators when picking a reason.
+    pub title: String,
+    /// Shown to the author of the removed content.
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `POST /subs/{sub}/removal_reasons`.
+#[derive(Deserialize)]
+pub struct NewRemovalReason {
+    pub title: String,
+    pub message: String,
+}
+
+/// `PATCH /subs/{sub}/removal_reasons/{id}`: fields left out are kept.
+#[derive(Deserialize)]
+pub struct RemovalReasonUpdate {
+    pub title: Option<String>,
+    pub message: Option<String>,
+}
+
+/// The optional body of `POST /modqueue/{id}/remove`.
+#[derive(Deserialize, Default)]
+pub struct Removal {
+    /// One of the sub's removal reasons.
+    pub reason_id: Option<i32>,
+    /// Added below the reason's message, or sent on its own.
+    #[serde(default)]
+    pub note: String,
+}
+
+/// The title with surrounding whitespace trimmed, if it isn't empty or too long.
+pub fn normalize_removal_reason_title(title: &str) -> Option<String> {
+    let title = title.trim();
+    let chars = title.chars().count();
+    (1..=MAX_REMOVAL_REASON_TITLE_CHARS)
+        .contains(&chars)
+        .then(|| title.to_string())
+}
+
+/// The message with surrounding whitespace trimmed, if it isn't empty or too long.
+pub fn normalize_removal_message(message: &str) -> Option<String> {
+    let message = message.trim();
+    let chars = message.chars().count();
+    (1..=MAX_REMOVAL_MESSAGE_CHARS)
+        .contains(&chars)
+        .then(|| message.to_string())
+}
+
+/// The message the author is shown: the reason's message and the note, either of which
+/// may be missing. `None` if both are.
+pub fn removal_message(reason: Option<&RemovalReason>, note: &str) -> Option<String> {
+    let parts: Vec<&str> = reason
+        .map(|reason| reason.message.as_str())
+        .into_iter()
+        .chain([note.trim()])
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+
+#[cfg(test)]
+mod removal_reason_tests {
+    use super::*;
+
+    #[test]
+    fn test_removal_message_joins_reason_and_note() {
+        let reason = RemovalReason {
+            id: 1,
+            sub: "rust".to_string(),
+            title: "Off topic".to_string(),
+            message: "Posts must be about Rust.".to_string(),
+            created_at: Utc::now(),
+        };
+
+        assert_eq!(
+            removal_message(Some(&reason), " Try r/golang ").as_deref(),
+            Some("Posts must be about Rust.\n\nTry r/golang")
+        );
+        assert_eq!(removal_message(None, "Spam").as_deref(), Some("Spam"));
+        assert_eq!(removal_message(None, "  "), None);
+    }
+}
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Longest report reason, in characters.
+pub const MAX_REPORT_REASON_CHARS: usize = 500;
+
+/// A user's report of a post or comment to the sub's moderators.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Report {
+    pub id: i64,
+    pub sub: String,
+    pub post_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+    /// `None` for reports AutoModerator filed.
+    pub reporter_id: Option<i32>,
+    /// The sub rule the content breaks, if the reporter named one.
+    pub rule_id: Option<i32>,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `POST /posts/{id}/report` and `POST /comments/{id}/report`. A report names a rule of
+/// the sub, gives a reason, or both.
+#[derive(Deserialize)]
+pub struct NewReport {
+    #[serde(default)]
+    pub reason: String,
+    pub rule_id: Option<i32>,
+}
+
+/// What a report is about.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReportTarget {
+    Post(Uuid),
+    Comment(Uuid),
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+#[derive(Serialize, Clone)]
+pub struct Revision {
+    pub revision: i32,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffGranularity {
+    #[default]
+    Line,
+    Word,
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    #[serde(default)]
+    pub granularity: DiffGranularity,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiffChunk {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+#[derive(Serialize)]
+pub struct RevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub changes: Vec<DiffChunk>,
+}
+
+impl RevisionDiff {
+    pub fn between(from: &Revision, to: &Revision, granularity: DiffGranularity) -> Self {
+        let diff = match granularity {
+            DiffGranularity::Line => TextDiff::from_lines(&from.content, &to.content),
+            DiffGranularity::Word => TextDiff::from_words(&from.content, &to.content),
+        };
+
+        let mut changes: Vec<DiffChunk> = Vec::new();
+        let mut insertions = 0;
+        let mut deletions = 0;
+
+        for change in diff.iter_all_changes() {
+            let op = match change.tag() {
+                ChangeTag::Equal => DiffOp::Equal,
+                ChangeTag::Insert => {
+                    insertions += 1;
+                    DiffOp::Insert
+                }
+                ChangeTag::Delete => {
+                    deletions += 1;
+                    DiffOp::Delete
+                }
+            };
+
+            // Merge runs of the same operation so word diffs stay readable
+            match changes.last_mut() {
+                Some(last) if last.op == op => last.text.push_str(change.value()),
+                _ => changes.push(DiffChunk {
+                    op,
+                    text: change.value().to_string(),
+                }),
+            }
+        }
+
+        RevisionDiff {
+            from: from.revision,
+            to: to.revision,
+            insertions,
+            deletions,
+            changes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod revision_model_tests {
+    use super::*;
+
+    fn revision(revision: i32, content: &str) -> Revision {
+        Revision {
+            revision,
+            content: content.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_line_diff_reports_changed_lines() {
+        let from = revision(1, "first line\nsecond line\n");
+        let to = revision(2, "first line\nedited line\n");
+
+        let diff = RevisionDiff::between(&from, &to, DiffGranularity::Line);
+
+        assert_eq!(diff.insertions, 1);
+        assert_eq!(diff.deletions, 1);
+        assert_eq!(diff.changes[0].op, DiffOp::Equal);
+        assert_eq!(diff.changes[1].text, "second line\n");
+        assert_eq!(diff.changes[2].text, "edited line\n");
+    }
+
+    #[test]
+    fn test_word_diff_merges_adjacent_changes() {
+        let from = revision(1, "the quick fox");
+        let to = revision(3, "the slow fox");
+
+        let diff = RevisionDiff::between(&from, &to, DiffGranularity::Word);
+
+        let ops: Vec<&DiffOp> = diff.changes.iter().map(|c| &c.op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                &DiffOp::Equal,
+                &DiffOp::Delete,
+                &DiffOp::Insert,
+                &DiffOp::Equal
+            ]
+        );
+        assert_eq!(diff.from, 1);
+        assert_eq!(diff.to, 3);
+    }
+
+    #[test]
+    fn test_identical_revisions_have_no_changes() {
+        let from = revision(1, "unchanged");
+        let to = revision(2, "unchanged");
+
+        let diff = RevisionDiff::between(&from, &to, DiffGranularity::Line);
+
+        assert_eq!(diff.insertions, 0);
+        assert_eq!(diff.deletions, 0);
+        assert_eq!(diff.changes.len(), 1);
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest rule title, in characters.
+pub const MAX_RULE_TITLE_CHARS: usize = 100;
+
+/// One of a sub's rules. Rules are shown lowest `order` first.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SubRule {
+    pub id: i32,
+    pub sub: String,
+    pub title: String,
+    pub description: String,
+    pub order: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `POST /subs/{sub}/rules`. Without an `order` the rule goes after the others.
+#[derive(Deserialize)]
+pub struct NewSubRule {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub order: Option<i32>,
+}
+
+/// `PATCH /subs/{sub}/rules/{id}`: fields left out are kept.
+#[derive(Deserialize)]
+pub struct SubRuleUpdate {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub order: Option<i32>,
+}
+
+/// The title with surrounding whitespace trimmed, if it isn't empty or too long.
+pub fn normalize_rule_title(title: &str) -> Option<String> {
+    let title = title.trim();
+    let chars = title.chars().count();
+    (1..=MAX_RULE_TITLE_CHARS)
+        .contains(&chars)
+        .then(|| title.to_string())
+}
+
+#[cfg(test)]
+mod rule_tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_titles_are_trimmed_and_bounded() {
+        assert_eq!(
+            normalize_rule_title("  Be civil ").as_deref(),
+            Some("Be civil")
+        );
+        assert_eq!(normalize_rule_title("   "), None);
+        assert_eq!(
+            normalize_rule_title(&"x".repeat(MAX_RULE_TITLE_CHARS + 1)),
+            None
+        );
+    }
+}
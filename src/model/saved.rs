@@ -0,0 +1,51 @@
+use crate::model::dto::CommentView;
+use crate::model::post::Post;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const DEFAULT_SAVED_LIMIT: i64 = 25;
+pub const MAX_SAVED_LIMIT: i64 = 100;
+
+/// `GET /users/{id}/saved?limit=&after=`, where `after` is the `next_cursor` of the
+/// previous page.
+#[derive(Deserialize)]
+pub struct SavedQuery {
+    pub limit: Option<i64>,
+    pub after: Option<String>,
+}
+
+/// A row of `saved_items`: exactly one of `post_id` and `comment_id` is set.
+pub struct SavedEntry {
+    pub id: i64,
+    pub post_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+    pub saved_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SavedItem {
+    Post {
+        saved_at: DateTime<Utc>,
+        post: Post,
+    },
+    Comment {
+        saved_at: DateTime<Utc>,
+        comment: CommentView,
+    },
+}
+
+/// One page of saved items, most recently saved first. `next_cursor` is absent on the
+/// last page.
+#[derive(Serialize)]
+pub struct SavedPage {
+    pub items: Vec<SavedItem>,
+    pub next_cursor: Option<String>,
+}
+
+/// Whether the item is saved after the change.
+#[derive(Serialize)]
+pub struct SaveResult {
+    pub saved: bool,
+}
@@ -0,0 +1,75 @@
+use crate::model::dto::CommentView;
+use crate::model::post::Post;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use uuid::Uuid;
+
+pub const DEFAULT_SEARCH_LIMIT: i64 = 25;
+pub const MAX_SEARCH_LIMIT: i64 = 100;
+
+/// How search results are ordered, from `?sort=`.
+#[derive(Deserialize, Display, EnumString, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum SearchSort {
+    /// Best match first, with matches in titles counting more than in content.
+    #[default]
+    Relevance,
+    /// Newest first.
+    New,
+    /// Highest score first.
+    Top,
+}
+
+/// `GET /search/posts?q=&sub=&sort=&limit=`. `q` takes web search syntax: quoted
+/// phrases, `or`, and `-` to exclude a word.
+#[derive(Deserialize)]
+pub struct PostSearchQuery {
+    pub q: String,
+    pub sub: Option<String>,
+    #[serde(default)]
+    pub sort: SearchSort,
+    pub limit: Option<i64>,
+}
+
+/// A post matching a search. `snippet` is an excerpt of its content around the
+/// matches, HTML-escaped, with the matching words wrapped in `<mark>`.
+#[derive(Serialize)]
+pub struct PostSearchHit {
+    #[serde(flatten)]
+    pub post: Post,
+    pub rank: f32,
+    pub snippet: String,
+}
+
+/// `GET /search/comments?q=&post_id=&user_id=&sort=&limit=`, with `q` as for posts.
+#[derive(Deserialize)]
+pub struct CommentSearchQuery {
+    pub q: String,
+    pub post_id: Option<Uuid>,
+    pub user_id: Option<i32>,
+    #[serde(default)]
+    pub sort: SearchSort,
+    pub limit: Option<i64>,
+}
+
+/// A comment matching a search, with `snippet` as for posts.
+#[derive(Serialize)]
+pub struct CommentSearchHit {
+    #[serde(flatten)]
+    pub comment: CommentView,
+    pub rank: f32,
+    pub snippet: String,
+}
+
+/// `GET /search/users?q=&limit=` and `GET /search/subs?q=&limit=`.
+#[derive(Deserialize)]
+pub struct NameSearchQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct SearchResults<T> {
+    pub results: Vec<T>,
+}
@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Longer user agents are cut off before they are stored.
+pub const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// Where a sign-in came from, recorded when it starts and whenever its refresh token is
+/// used.
+pub struct DeviceInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[derive(Serialize, sqlx::Type, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    /// A browser session cookie from `POST /auth/session` or a provider sign-in.
+    Cookie,
+    /// A refresh token from `POST /auth/login`, along with its rotated successors.
+    RefreshToken,
+}
+
+/// A login that hasn't ended, as listed under `GET /users/{user_id}/sessions`.
+#[derive(Serialize)]
+pub struct ActiveSession {
+    pub id: Uuid,
+    pub kind: SessionKind,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub signed_in_at: DateTime<Utc>,
+    /// For cookies the last request, to within a minute; for refresh tokens the last
+    /// refresh.
+    pub last_seen_at: DateTime<Utc>,
+}
@@ -0,0 +1,158 @@
+use chrono::Duration;
+use serde::Serialize;
+use strum_macros::Display;
+
+/// How far back the author's posts and comments are counted for posting velocity.
+pub const VELOCITY_WINDOW_MINUTES: i64 = 10;
+/// Submissions within the velocity window that don't count against the author.
+const VELOCITY_ALLOWANCE: i64 = 3;
+/// How far back the author's content in other subs is checked for copies.
+pub const DUPLICATE_WINDOW_HOURS: i64 = 24;
+/// Shorter text is too common to count as copied.
+pub const MIN_DUPLICATE_CHARS: usize = 20;
+pub const MAX_SPAM_SCORE: i32 = 100;
+
+/// What is known about new content and its author when it is scored.
+pub struct SpamSignals {
+    /// Other subs the author posted the same text or link to lately.
+    pub duplicate_subs: i64,
+    /// Links in the text.
+    pub links: usize,
+    pub words: usize,
+    /// The author's posts and comments within the velocity window.
+    pub recent_submissions: i64,
+    pub account_age: Duration,
+    /// Post and comment karma together.
+    pub karma: i64,
+}
+
+#[derive(Serialize, Display, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SpamReason {
+    DuplicateContent,
+    LinkDensity,
+    PostingVelocity,
+    NewAccount,
+}
+
+/// From 0 to `MAX_SPAM_SCORE`, with the signals that added to it.
+#[derive(Serialize, Clone, PartialEq, Debug, Default)]
+pub struct SpamScore {
+    pub score: i32,
+    pub reasons: Vec<SpamReason>,
+}
+
+impl SpamScore {
+    fn add(&mut self, points: i32, reason: SpamReason) {
+        if points > 0 {
+            self.score += points;
+            self.reasons.push(reason);
+        }
+    }
+}
+
+pub fn score(signals: &SpamSignals) -> SpamScore {
+    let mut score = SpamScore::default();
+
+    score.add(
+        (signals.duplicate_subs * 30).min(60) as i32,
+        SpamReason::DuplicateContent,
+    );
+
+    if signals.links > 0 {
+        let density = signals.links as f64 / signals.words.max(1) as f64;
+        // A link or two in a longer text is ordinary
+        let mut points = if density >= 0.1 {
+            ((density * 100.0) as i32).min(30)
+        } else {
+            0
+        };
+        if signals.links >= 5 {
+            points = points.max(20);
+        }
+        score.add(points, SpamReason::LinkDensity);
+    }
+
+    score.add(
+        ((signals.recent_submissions - VELOCITY_ALLOWANCE).max(0) * 10).min(30) as i32,
+        SpamReason::PostingVelocity,
+    );
+
+    let age_points = if signals.account_age < Duration::days(1) {
+        20
+    } else if signals.account_age < Duration::days(7) {
+        10
+    } else {
+        0
+    };
+    let karma_points = if signals.karma < 0 { 10 } else { 0 };
+    score.add(age_points + karma_points, SpamReason::NewAccount);
+
+    score.score = score.score.min(MAX_SPAM_SCORE);
+    score
+}
+
+#[cfg(test)]
+mod spam_model_tests {
+    use super::*;
+
+    fn signals() -> SpamSignals {
+        SpamSignals {
+            duplicate_subs: 0,
+            links: 0,
+            words: 50,
+            recent_submissions: 0,
+            account_age: Duration::days(365),
+            karma: 100,
+        }
+    }
+
+    #[test]
+    fn test_ordinary_content_scores_nothing() {
+        let ordinary = SpamSignals {
+            links: 1,
+            recent_submissions: 3,
+            ..signals()
+        };
+
+        assert_eq!(score(&ordinary), SpamScore::default());
+    }
+
+    #[test]
+    fn test_signals_add_up() {
+        let spammy = SpamSignals {
+            duplicate_subs: 1,
+            links: 3,
+            words: 10,
+            account_age: Duration::hours(2),
+            ..signals()
+        };
+
+        let scored = score(&spammy);
+        assert_eq!(scored.score, 30 + 30 + 20);
+        assert_eq!(
+            scored.reasons,
+            [
+                SpamReason::DuplicateContent,
+                SpamReason::LinkDensity,
+                SpamReason::NewAccount
+            ]
+        );
+    }
+
+    #[test]
+    fn test_score_is_capped() {
+        let flood = SpamSignals {
+            duplicate_subs: 5,
+            links: 20,
+            words: 20,
+            recent_submissions: 20,
+            account_age: Duration::minutes(5),
+            karma: -10,
+        };
+
+        assert_eq!(score(&flood).score, MAX_SPAM_SCORE);
+        assert_eq!(score(&flood).reasons.len(), 4);
+    }
+}
@@ -1,9 +1,356 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use strum_macros::Display;
+use uuid::Uuid;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Sub {
     pub name: String,
     pub description: String,
     pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub nsfw: bool,
+    /// New posts are marked as spoilers unless their author says otherwise.
+    #[serde(default)]
+    pub spoiler_by_default: bool,
+    #[serde(default)]
+    pub subscribers: i64,
+    #[serde(default)]
+    pub visibility: SubVisibility,
+    #[serde(default)]
+    pub icon_url: Option<String>,
+    #[serde(default)]
+    pub banner_url: Option<String>,
+    /// `#rrggbb`, for frontends to theme the sub with.
+    #[serde(default)]
+    pub primary_color: Option<String>,
+    #[serde(default)]
+    pub accent_color: Option<String>,
+}
+
+/// A sub's branding as it is stored: the icon and banner are uploads, by id.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SubAppearance {
+    pub icon_media_id: Option<Uuid>,
+    pub banner_media_id: Option<Uuid>,
+    pub primary_color: Option<String>,
+    pub accent_color: Option<String>,
+}
+
+/// `PATCH /subs/{sub}/settings`: fields left out are kept and `null` clears them.
+#[derive(Deserialize)]
+pub struct SubSettingsUpdate {
+    #[serde(default, deserialize_with = "nullable")]
+    pub icon_media_id: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub banner_media_id: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub primary_color: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub accent_color: Option<Option<String>>,
+}
+
+/// Tells a field sent as `null` apart from one left out, which `#[serde(default)]` makes
+/// `None`.
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+/// Who may read and post in a sub. Approved members, the sub's moderators and admins may
+/// always do both.
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Default, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SubVisibility {
+    #[default]
+    Public,
+    /// Anyone may read it, but only members may post or comment.
+    Restricted,
+    /// Only members may read it; it is left out of listings for everyone else.
+    Private,
+}
+
+impl SubVisibility {
+    pub fn readable_by_anyone(self) -> bool {
+        self != SubVisibility::Private
+    }
+
+    pub fn open_to_anyone(self) -> bool {
+        self == SubVisibility::Public
+    }
+}
+
+/// Whether the caller is subscribed to the sub after the change, and how many are.
+#[derive(Serialize)]
+pub struct SubscriptionResult {
+    pub subscribed: bool,
+    pub subscribers: i64,
+}
+
+/// Something a sub's moderator may be trusted with. `Full` covers everything, including
+/// managing the sub's other moderators.
+#[derive(Serialize, Deserialize, Display, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ModPermission {
+    /// Pinning, locking, flagging and approving posts and comments, and recurring posts.
+    Posts,
+    /// Removing a user's content, reading edit history and approving members.
+    Users,
+    /// The sub's description, flags, rules and wiki, and its word filters.
+    Settings,
+    /// Post and user flairs.
+    Flair,
+    Full,
+}
+
+impl ModPermission {
+    pub const ALL: [ModPermission; 5] = [
+        ModPermission::Posts,
+        ModPermission::Users,
+        ModPermission::Settings,
+        ModPermission::Flair,
+        ModPermission::Full,
+    ];
+
+    fn bit(self) -> i32 {
+        1 << self as i32
+    }
+}
+
+/// A moderator's permissions, stored as a bitset and sent as a list of names.
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Debug)]
+#[serde(from = "Vec<ModPermission>", into = "Vec<ModPermission>")]
+#[sqlx(transparent)]
+pub struct ModPermissions(i32);
+
+impl ModPermissions {
+    pub const FULL: ModPermissions = ModPermissions(1 << ModPermission::Full as i32);
+
+    pub fn allows(self, permission: ModPermission) -> bool {
+        self.0 & (ModPermission::Full.bit() | permission.bit()) != 0
+    }
+}
+
+impl From<Vec<ModPermission>> for ModPermissions {
+    fn from(permissions: Vec<ModPermission>) -> Self {
+        ModPermissions(
+            permissions
+                .iter()
+                .fold(0, |bits, permission| bits | permission.bit()),
+        )
+    }
+}
+
+impl From<ModPermissions> for Vec<ModPermission> {
+    fn from(permissions: ModPermissions) -> Self {
+        ModPermission::ALL
+            .into_iter()
+            .filter(|permission| permissions.0 & permission.bit() != 0)
+            .collect()
+    }
+}
+
+/// A user a moderator approved to read and post in a restricted or private sub.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SubMember {
+    pub user_id: i32,
+    pub username: String,
+    pub approved_by: Option<i32>,
+    pub approved_at: DateTime<Utc>,
+}
+
+/// A user asking to become a member of a sub, waiting for a moderator to decide.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SubJoinRequest {
+    pub user_id: i32,
+    pub username: String,
+    pub message: String,
+    pub requested_at: DateTime<Utc>,
+}
+
+/// `POST /subs/{sub}/join-requests`.
+#[derive(Deserialize, Default)]
+pub struct NewJoinRequest {
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Longest note a user can leave with a join request.
+pub const MAX_JOIN_REQUEST_MESSAGE_CHARS: usize = 500;
+
+/// A moderator of a sub and who appointed them; `added_by` is `None` for moderators
+/// carried over from site-wide moderation.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SubModerator {
+    pub user_id: i32,
+    pub username: String,
+    pub added_by: Option<i32>,
+    pub added_at: DateTime<Utc>,
+    pub permissions: ModPermissions,
+}
+
+/// An invitation to moderate a sub, waiting for the invited user to accept it.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SubModeratorInvite {
+    pub user_id: i32,
+    pub username: String,
+    pub invited_by: Option<i32>,
+    pub invited_at: DateTime<Utc>,
+    /// What they may do once they accept.
+    pub permissions: ModPermissions,
+}
+
+/// `POST /subs/{sub}/moderators/invite`.
+#[derive(Deserialize)]
+pub struct NewModeratorInvite {
+    pub user_id: i32,
+    /// Full permissions if left out.
+    pub permissions: Option<ModPermissions>,
+}
+
+/// `PATCH /subs/{sub}/moderators/{user_id}`.
+#[derive(Deserialize)]
+pub struct ModPermissionsUpdate {
+    pub permissions: ModPermissions,
+}
+
+/// Sub names are 3 to 21 letters, digits or underscores, starting with a letter.
+pub const MIN_SUB_NAME_CHARS: usize = 3;
+pub const MAX_SUB_NAME_CHARS: usize = 21;
+/// Names taken by routes under `/subs`, whatever their case.
+const RESERVED_SUB_NAMES: [&str; 2] = ["popular", "trending"];
+
+/// How `GET /subs` is ordered, from `?sort=`.
+#[derive(Deserialize, Display, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum SubSort {
+    /// Alphabetical.
+    #[default]
+    Name,
+    /// Newest first.
+    New,
+    /// Most subscribers first.
+    Top,
+}
+
+/// `GET /subs?sort=`.
+#[derive(Deserialize)]
+pub struct SubListQuery {
+    #[serde(default)]
+    pub sort: SubSort,
+}
+
+/// How far back the subscriber growth and activity behind trending and popular subs
+/// are counted.
+pub const SUB_STATS_WINDOW_DAYS: i64 = 7;
+pub const DEFAULT_DISCOVERY_LIMIT: i64 = 25;
+pub const MAX_DISCOVERY_LIMIT: i64 = 100;
+
+/// `GET /subs/trending` and `GET /subs/popular` take `?limit=`.
+#[derive(Deserialize)]
+pub struct DiscoveryQuery {
+    pub limit: Option<i64>,
+}
+
+impl DiscoveryQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_DISCOVERY_LIMIT)
+            .clamp(1, MAX_DISCOVERY_LIMIT)
+    }
+}
+
+/// `POST /subs`.
+#[derive(Deserialize)]
+pub struct NewSub {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub nsfw: bool,
+    #[serde(default)]
+    pub spoiler_by_default: bool,
+    #[serde(default)]
+    pub visibility: SubVisibility,
+}
+
+/// `PATCH /subs/{name}`: fields left out are kept.
+#[derive(Deserialize)]
+pub struct SubUpdate {
+    pub description: Option<String>,
+    pub nsfw: Option<bool>,
+    pub spoiler_by_default: Option<bool>,
+    pub visibility: Option<SubVisibility>,
+}
+
+/// The name with surrounding whitespace trimmed, if it is a valid sub name.
+pub fn normalize_sub_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    let valid = (MIN_SUB_NAME_CHARS..=MAX_SUB_NAME_CHARS).contains(&name.len())
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then(|| name.to_string())
+}
+
+/// Whether the name can never be a sub's because a route under `/subs` uses it.
+pub fn sub_name_reserved(name: &str) -> bool {
+    RESERVED_SUB_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod sub_tests {
+    use super::*;
+
+    #[test]
+    fn test_sub_names_are_validated() {
+        assert_eq!(
+            normalize_sub_name(" rust_lang ").as_deref(),
+            Some("rust_lang")
+        );
+        assert_eq!(normalize_sub_name("r2d2").as_deref(), Some("r2d2"));
+        assert_eq!(normalize_sub_name("rs"), None);
+        assert_eq!(normalize_sub_name("2fast"), None);
+        assert_eq!(normalize_sub_name("rust lang"), None);
+        assert_eq!(normalize_sub_name("a_name_that_is_too_long"), None);
+        assert!(sub_name_reserved("Trending"));
+        assert!(!sub_name_reserved("trend"));
+    }
+    #[test]
+    fn test_full_permissions_allow_everything() {
+        let permissions: ModPermissions =
+            serde_json::from_value(serde_json::json!(["posts", "flair"])).unwrap();
+        assert!(permissions.allows(ModPermission::Posts));
+        assert!(permissions.allows(ModPermission::Flair));
+        assert!(!permissions.allows(ModPermission::Settings));
+        assert!(!permissions.allows(ModPermission::Full));
+        assert_eq!(
+            serde_json::to_value(permissions).unwrap(),
+            serde_json::json!(["posts", "flair"])
+        );
+
+        assert!(ModPermission::ALL
+            .into_iter()
+            .all(|permission| ModPermissions::FULL.allows(permission)));
+    }
+    #[test]
+    fn test_settings_tell_null_from_left_out() {
+        let update: SubSettingsUpdate = serde_json::from_value(serde_json::json!({
+            "icon_media_id": null,
+            "primary_color": "#ff8800",
+        }))
+        .unwrap();
+        assert_eq!(update.icon_media_id, Some(None));
+        assert_eq!(update.banner_media_id, None);
+        assert_eq!(update.primary_color, Some(Some("#ff8800".to_string())));
+        assert_eq!(update.accent_color, None);
+    }
 }
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// An account barred from doing anything but read across the whole site. Suspensions
+/// without `expires_at` are permanent bans that last until an admin lifts them.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Suspension {
+    pub user_id: i32,
+    pub username: String,
+    pub suspended_by: Option<i32>,
+    pub reason: String,
+    pub suspended_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// `POST /admin/suspensions`. Suspending someone already suspended replaces their
+/// suspension.
+#[derive(Deserialize)]
+pub struct NewSuspension {
+    pub user_id: i32,
+    #[serde(default)]
+    pub reason: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
@@ -1,23 +1,56 @@
+use crate::model::password::HashParams;
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    Algorithm, Argon2, Params, Version,
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use strum_macros::Display;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
 pub struct User {
     pub id: i32,
     pub username: String,
     pub password_hash: String,
     pub is_moderator: bool,
+    pub is_admin: bool,
     pub created_at: DateTime<Utc>,
+    pub date_of_birth: Option<NaiveDate>,
+    pub nsfw_acknowledged_at: Option<DateTime<Utc>>,
+    pub preferred_languages: Vec<String>,
+    pub is_premium: bool,
+    pub email: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// Net votes on the user's posts, kept current as votes change.
+    pub post_karma: i32,
+    /// Net votes on the user's comments, kept current as votes change.
+    pub comment_karma: i32,
+    /// Whether listings include NSFW posts by default, for users cleared to see them.
+    pub show_nsfw: bool,
+}
+
+pub const NSFW_MINIMUM_AGE: u32 = 18;
+
+/// What an account may do, in increasing order of privilege. Each role includes the
+/// ones below it.
+#[derive(Serialize, Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Role {
+    User,
+    /// Moderates subs: removes content, manages word filters and the held queue.
+    Moderator,
+    /// Runs the site: appoints moderators, handles legal requests and site settings.
+    Admin,
 }
 
 impl User {
-    pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    pub fn hash_password(
+        password: &str,
+        params: &HashParams,
+    ) -> Result<String, argon2::password_hash::Error> {
         let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
+        let argon2 = params.hasher()?;
         let password_hash = argon2
             .hash_password(password.as_bytes(), &salt)?
             .to_string();
@@ -25,8 +58,18 @@ impl User {
         Ok(password_hash)
     }
 
+    /// Checks against the stored hash using the algorithm and cost it was made with,
+    /// so hashes from before a change to `HashParams` keep working.
     pub fn verify_password(&self, password: &str) -> Result<bool, argon2::password_hash::Error> {
-        let parsed_stored_hash = PasswordHash::new(&self.password_hash)?;
+        User::verify_password_hash(&self.password_hash, password)
+    }
+
+    /// As `verify_password`, for a hash that doesn't belong to a user.
+    pub fn verify_password_hash(
+        password_hash: &str,
+        password: &str,
+    ) -> Result<bool, argon2::password_hash::Error> {
+        let parsed_stored_hash = PasswordHash::new(password_hash)?;
 
         let result = Argon2::default()
             .verify_password(password.as_bytes(), &parsed_stored_hash)
@@ -34,13 +77,79 @@ impl User {
 
         Ok(result)
     }
+
+    /// Whether the stored hash was made with anything other than Argon2id at `params`.
+    pub fn needs_rehash(&self, params: &HashParams) -> bool {
+        let Ok(hash) = PasswordHash::new(&self.password_hash) else {
+            return true;
+        };
+        let Ok(stored) = Params::try_from(&hash) else {
+            return true;
+        };
+
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+            || stored.m_cost() != params.memory_kib
+            || stored.t_cost() != params.iterations
+            || stored.p_cost() != params.parallelism
+    }
+
+    pub fn is_adult(&self, today: NaiveDate) -> bool {
+        match self.date_of_birth {
+            Some(date_of_birth) => today
+                .years_since(date_of_birth)
+                .is_some_and(|age| age >= NSFW_MINIMUM_AGE),
+            None => false,
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        if self.is_admin {
+            Role::Admin
+        } else if self.is_moderator {
+            Role::Moderator
+        } else {
+            Role::User
+        }
+    }
+
+    pub fn email_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
+
+    /// NSFW content is only served to adults who have also accepted the interstitial.
+    pub fn can_view_nsfw(&self, today: NaiveDate) -> bool {
+        self.is_adult(today) && self.nsfw_acknowledged_at.is_some()
+    }
+
+    /// Whether listings show the user NSFW posts: never without clearance, otherwise as
+    /// the listing's `?include_nsfw=` asks or else by their `show_nsfw` preference.
+    pub fn lists_nsfw(&self, requested: Option<bool>, today: NaiveDate) -> bool {
+        self.can_view_nsfw(today) && requested.unwrap_or(self.show_nsfw)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NsfwPreference {
+    pub show_nsfw: bool,
+}
+
+#[derive(Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct DateOfBirth {
+    pub date_of_birth: NaiveDate,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct NewUser {
     pub username: String,
     pub password: String,
-    pub is_moderator: bool,
+    pub email: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -48,8 +157,9 @@ pub struct DbAddUser {
     pub username: String,
     #[serde(skip_serializing)]
     pub password_hash: String,
-    pub is_moderator: bool,
     pub created_at: DateTime<Utc>,
+    pub email: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
 }
 
 #[cfg(test)]
@@ -58,7 +168,7 @@ mod user_model_tests {
     #[test]
     fn test_hash_password_success() {
         let password = "strongpassword";
-        let result = User::hash_password(password);
+        let result = User::hash_password(password, &HashParams::default());
         assert!(
             result.is_ok(),
             "Password hashing failed: {:?}",
@@ -69,14 +179,24 @@ mod user_model_tests {
     #[test]
     fn test_verify_password_success() {
         let password = "strongpassword";
-        let password_hash = User::hash_password(password).unwrap();
+        let password_hash = User::hash_password(password, &HashParams::default()).unwrap();
 
         let user = User {
             id: 1,
             username: "testuser".to_string(),
             password_hash,
             is_moderator: false,
+            is_admin: false,
             created_at: Utc::now(),
+            date_of_birth: None,
+            nsfw_acknowledged_at: None,
+            preferred_languages: Vec::new(),
+            is_premium: false,
+            email: None,
+            email_verified_at: None,
+            post_karma: 0,
+            comment_karma: 0,
+            show_nsfw: true,
         };
 
         let result = user.verify_password(password);
@@ -87,17 +207,107 @@ mod user_model_tests {
     fn test_verify_password_failure() {
         let password = "strongpassword";
         let wrong_password = "wrongpassword";
-        let password_hash = User::hash_password(password).unwrap();
+        let password_hash = User::hash_password(password, &HashParams::default()).unwrap();
 
         let user = User {
             id: 1,
             username: "testuser".to_string(),
             password_hash,
             is_moderator: false,
+            is_admin: false,
             created_at: Utc::now(),
+            date_of_birth: None,
+            nsfw_acknowledged_at: None,
+            preferred_languages: Vec::new(),
+            is_premium: false,
+            email: None,
+            email_verified_at: None,
+            post_karma: 0,
+            comment_karma: 0,
+            show_nsfw: true,
         };
 
         let result = user.verify_password(wrong_password);
         assert!(!result.unwrap(), "Password verification should have failed");
     }
+
+    #[test]
+    fn test_verify_password_hash_without_a_user() {
+        let password_hash = User::hash_password("strongpassword", &HashParams::default()).unwrap();
+
+        assert!(User::verify_password_hash(&password_hash, "strongpassword").unwrap());
+        assert!(!User::verify_password_hash(&password_hash, "wrongpassword").unwrap());
+        assert!(User::verify_password_hash("not a hash", "strongpassword").is_err());
+    }
+
+    #[test]
+    fn test_cheaper_hash_needs_rehash() {
+        let cheap = HashParams {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let mut user = user_born_on(None);
+        user.password_hash = User::hash_password("strongpassword", &cheap).unwrap();
+
+        assert!(user.verify_password("strongpassword").unwrap());
+        assert!(user.needs_rehash(&HashParams::default()));
+        assert!(!user.needs_rehash(&cheap));
+    }
+
+    fn user_born_on(date_of_birth: Option<NaiveDate>) -> User {
+        User {
+            id: 1,
+            username: "testuser".to_string(),
+            password_hash: String::new(),
+            is_moderator: false,
+            is_admin: false,
+            created_at: Utc::now(),
+            date_of_birth,
+            nsfw_acknowledged_at: None,
+            preferred_languages: Vec::new(),
+            is_premium: false,
+            email: None,
+            email_verified_at: None,
+            post_karma: 0,
+            comment_karma: 0,
+            show_nsfw: true,
+        }
+    }
+
+    #[test]
+    fn test_is_adult_on_eighteenth_birthday() {
+        let today = NaiveDate::from_ymd_opt(2024, 10, 5).unwrap();
+        let user = user_born_on(NaiveDate::from_ymd_opt(2006, 10, 5));
+        assert!(user.is_adult(today));
+
+        let user = user_born_on(NaiveDate::from_ymd_opt(2006, 10, 6));
+        assert!(!user.is_adult(today));
+    }
+
+    #[test]
+    fn test_can_view_nsfw_requires_acknowledgement() {
+        let today = NaiveDate::from_ymd_opt(2024, 10, 5).unwrap();
+        let mut user = user_born_on(NaiveDate::from_ymd_opt(1990, 1, 1));
+        assert!(!user.can_view_nsfw(today));
+
+        user.nsfw_acknowledged_at = Some(Utc::now());
+        assert!(user.can_view_nsfw(today));
+
+        user.date_of_birth = None;
+        assert!(!user.can_view_nsfw(today));
+    }
+
+    #[test]
+    fn test_admin_role_outranks_moderator() {
+        let mut user = user_born_on(None);
+        assert_eq!(user.role(), Role::User);
+
+        user.is_moderator = true;
+        assert_eq!(user.role(), Role::Moderator);
+
+        user.is_admin = true;
+        assert_eq!(user.role(), Role::Admin);
+        assert!(Role::Admin > Role::Moderator && Role::Moderator > Role::User);
+    }
 }
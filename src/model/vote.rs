@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// `PUT /posts/{id}/vote` takes `{"value": 1}` to upvote or `{"value": -1}` to downvote.
+#[derive(Deserialize)]
+pub struct VoteRequest {
+    pub value: i16,
+}
+
+impl VoteRequest {
+    pub fn is_valid(&self) -> bool {
+        self.value == 1 || self.value == -1
+    }
+}
+
+/// The score after a vote changed, and the caller's vote, `None` once withdrawn.
+#[derive(Serialize)]
+pub struct VoteResult {
+    pub score: i32,
+    pub vote: Option<i16>,
+}
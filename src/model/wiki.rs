@@ -0,0 +1,123 @@
+use crate::model::revision::Revision;
+use crate::render;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+/// Longest wiki page name, in characters.
+pub const MAX_WIKI_PAGE_NAME_CHARS: usize = 64;
+/// Longest wiki page, in characters.
+pub const MAX_WIKI_PAGE_CHARS: usize = 100_000;
+
+/// Who may edit a wiki page besides the sub's moderators with the `settings` permission,
+/// who also create pages and choose this.
+#[derive(Serialize, Deserialize, sqlx::Type, Display, Default, Clone, Copy, PartialEq, Debug)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum WikiEditors {
+    #[default]
+    Moderators,
+    /// The sub's approved members.
+    Members,
+    /// Anyone who may post in the sub.
+    Anyone,
+}
+
+/// A page of a sub's wiki as of its latest revision.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct WikiPage {
+    pub sub: String,
+    pub page: String,
+    pub content: String,
+    pub revision: i32,
+    pub editors: WikiEditors,
+    pub edited_by: Option<i32>,
+    pub edited_at: DateTime<Utc>,
+}
+
+/// A wiki page with its content rendered as `rendered_html`.
+#[derive(Serialize)]
+pub struct WikiPageView {
+    #[serde(flatten)]
+    pub page: WikiPage,
+    pub rendered_html: String,
+}
+
+impl From<WikiPage> for WikiPageView {
+    fn from(page: WikiPage) -> Self {
+        WikiPageView {
+            rendered_html: render::markdown_to_html(&page.content),
+            page,
+        }
+    }
+}
+
+/// `GET /subs/{sub}/wiki` lists pages without their content.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct WikiPageSummary {
+    pub page: String,
+    pub revision: i32,
+    pub edited_at: DateTime<Utc>,
+}
+
+/// One saved version of a wiki page, with why it was edited.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct WikiRevision {
+    pub revision: i32,
+    pub content: String,
+    pub edited_by: Option<i32>,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<WikiRevision> for Revision {
+    fn from(revision: WikiRevision) -> Self {
+        Revision {
+            revision: revision.revision,
+            content: revision.content,
+            created_at: revision.created_at,
+        }
+    }
+}
+
+/// `PUT /subs/{sub}/wiki/{page}`.
+#[derive(Deserialize)]
+pub struct WikiPageEdit {
+    pub content: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// `PATCH /subs/{sub}/wiki/{page}/settings`.
+#[derive(Deserialize)]
+pub struct WikiPageSettings {
+    pub editors: WikiEditors,
+}
+
+/// The page name in lowercase, if it is 1 to 64 letters, digits, `_` or `-`.
+pub fn normalize_wiki_page_name(page: &str) -> Option<String> {
+    let page = page.trim().to_ascii_lowercase();
+    let valid = (1..=MAX_WIKI_PAGE_NAME_CHARS).contains(&page.len())
+        && page
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    valid.then_some(page)
+}
+
+#[cfg(test)]
+mod wiki_tests {
+    use super::*;
+
+    #[test]
+    fn test_wiki_page_names_are_lowercased_and_validated() {
+        assert_eq!(normalize_wiki_page_name(" FAQ ").as_deref(), Some("faq"));
+        assert_eq!(
+            normalize_wiki_page_name("getting-started_2").as_deref(),
+            Some("getting-started_2")
+        );
+        assert_eq!(normalize_wiki_page_name(""), None);
+        assert_eq!(normalize_wiki_page_name("read me"), None);
+        assert_eq!(normalize_wiki_page_name("../index"), None);
+    }
+}
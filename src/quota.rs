@@ -0,0 +1,90 @@
+use crate::api::api_key::{presented_api_key, require_api_key};
+use crate::config::RuntimeSettings;
+use crate::error::ApiError;
+use crate::model::api_key::{QuotaWindow, UsageReport};
+use crate::repo::api_key as api_key_repo;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use chrono::Utc;
+use sqlx::PgPool;
+
+/// Checking usage shouldn't use up quota, or a client that ran out couldn't find out when.
+const EXEMPT_PATHS: [&str; 1] = ["/me/usage"];
+
+/// Counts requests made with an API key against the key's tier quotas and rejects
+/// them with 429 once a daily or monthly quota is spent. Requests without a key (the
+/// forum's own frontends) aren't metered. Usage is counted and checked in one statement,
+/// so parallel requests can't slip past a quota together.
+pub async fn enforce_quota(
+    pool: PgPool,
+    settings: Data<RuntimeSettings>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if presented_api_key(req.headers())?.is_none() || EXEMPT_PATHS.contains(&req.path()) {
+        return next.call(req).await;
+    }
+
+    let key = require_api_key(&pool, req.headers()).await?;
+    let quota = settings.current().quotas.for_tier(key.tier);
+
+    let recorded = api_key_repo::record_request(&pool, key.id, quota.daily, quota.monthly)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let Some(usage) = recorded else {
+        let usage = api_key_repo::get_usage(&pool, key.id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let report = UsageReport::new(key.tier, usage, quota.daily, quota.monthly, Utc::now());
+        let window = report.exhausted().unwrap_or_else(|| report.binding());
+        return Err(quota_exceeded_error(window, &report).into());
+    };
+    let report = UsageReport::new(key.tier, usage, quota.daily, quota.monthly, Utc::now());
+
+    let mut response = next.call(req).await?;
+    for (name, value) in quota_headers(&report) {
+        response.headers_mut().insert(name, value);
+    }
+
+    Ok(response)
+}
+
+fn quota_exceeded_error(window: &QuotaWindow, report: &UsageReport) -> ApiError {
+    let retry_after = (window.resets_at - Utc::now()).num_seconds().max(0);
+
+    ApiError::new(
+        StatusCode::TOO_MANY_REQUESTS,
+        "quota_exceeded",
+        format!(
+            "The {} API quota for this key has been used up",
+            window.period
+        ),
+    )
+    .with_arg("period", window.period.to_string())
+    .with_headers(quota_headers(report))
+    .with_headers(vec![(RETRY_AFTER, HeaderValue::from(retry_after))])
+}
+
+/// `X-Quota-*` headers describing whichever window runs out first.
+fn quota_headers(report: &UsageReport) -> Vec<(HeaderName, HeaderValue)> {
+    let window = report.binding();
+
+    vec![
+        (
+            HeaderName::from_static("x-quota-limit"),
+            HeaderValue::from(window.limit),
+        ),
+        (
+            HeaderName::from_static("x-quota-remaining"),
+            HeaderValue::from(window.remaining()),
+        ),
+        (
+            HeaderName::from_static("x-quota-reset"),
+            HeaderValue::from(window.resets_at.timestamp()),
+        ),
+    ]
+}
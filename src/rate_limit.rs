@@ -0,0 +1,458 @@
+use crate::auth::Viewer;
+use crate::config::{ProxyConfig, RateLimit, RateLimits, RuntimeSettings};
+use crate::error::ApiError;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{
+    HeaderMap, HeaderName, HeaderValue, FORWARDED, RETRY_AFTER, X_FORWARDED_FOR,
+};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::HttpRequest;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use strum_macros::Display;
+
+/// Buckets that have refilled completely carry no state worth keeping, so once this
+/// many are tracked the full ones are dropped.
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+#[derive(Display, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[strum(serialize_all = "snake_case")]
+pub enum RateLimitGroup {
+    Signup,
+    Login,
+    Post,
+    Comment,
+}
+
+impl RateLimitGroup {
+    pub fn for_request(method: &Method, path: &str) -> Option<Self> {
+        if method != Method::POST {
+            return None;
+        }
+
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match segments.as_slice() {
+            ["users"] => Some(RateLimitGroup::Signup),
+            ["auth", "login"] | ["auth", "session"] => Some(RateLimitGroup::Login),
+            ["posts", "by_ids"] => None,
+            ["posts", _] => Some(RateLimitGroup::Post),
+            ["posts", _, "comments"] => Some(RateLimitGroup::Comment),
+            _ => None,
+        }
+    }
+
+    fn limit(self, limits: &RateLimits) -> RateLimit {
+        match self {
+            RateLimitGroup::Signup => limits.signup,
+            RateLimitGroup::Login => limits.login,
+            RateLimitGroup::Post => limits.post,
+            RateLimitGroup::Comment => limits.comment,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Client {
+    Ip(IpAddr),
+    User(i32),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(limit: RateLimit, now: Instant) -> Self {
+        Bucket {
+            tokens: limit.burst as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second(limit)).min(limit.burst as f64);
+        self.updated = now;
+    }
+
+    fn is_full(&self, limit: RateLimit, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * per_second(limit) >= limit.burst as f64
+    }
+
+    fn state(&self, limit: RateLimit) -> BucketState {
+        let rate = per_second(limit);
+
+        BucketState {
+            limit: limit.burst,
+            remaining: self.tokens.floor() as u32,
+            retry_after: Duration::from_secs_f64(((1.0 - self.tokens) / rate).max(0.0)),
+            reset_after: Duration::from_secs_f64((limit.burst as f64 - self.tokens) / rate),
+        }
+    }
+}
+
+fn per_second(limit: RateLimit) -> f64 {
+    limit.per_minute as f64 / 60.0
+}
+
+/// What the `X-RateLimit-*` headers report about a bucket.
+#[derive(Debug)]
+pub struct BucketState {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Until the next request would be allowed.
+    pub retry_after: Duration,
+    /// Until the bucket is full again.
+    pub reset_after: Duration,
+}
+
+pub enum Decision {
+    Allowed(BucketState),
+    Limited(BucketState),
+}
+
+/// In-memory token buckets, shared by all workers. Limits are per process, so each
+/// instance behind a load balancer enforces its own.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<(RateLimitGroup, Client), Bucket>>,
+}
+
+impl RateLimiter {
+    /// A request costs one token from every client bucket, and is only allowed if each
+    /// of them has one. The state reported is that of the emptiest bucket.
+    fn check(
+        &self,
+        group: RateLimitGroup,
+        clients: &[Client],
+        limits: &RateLimits,
+        now: Instant,
+    ) -> Decision {
+        let limit = group.limit(limits);
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        if buckets.len() >= MAX_TRACKED_BUCKETS {
+            buckets.retain(|(group, _), bucket| !bucket.is_full(group.limit(limits), now));
+        }
+
+        for client in clients {
+            buckets
+                .entry((group, *client))
+                .or_insert_with(|| Bucket::full(limit, now))
+                .refill(limit, now);
+        }
+        let allowed = clients
+            .iter()
+            .all(|client| buckets[&(group, *client)].tokens >= 1.0);
+
+        let mut emptiest: Option<(f64, BucketState)> = None;
+        for client in clients {
+            let bucket = buckets
+                .get_mut(&(group, *client))
+                .expect("bucket was just refilled");
+            if allowed {
+                bucket.tokens -= 1.0;
+            }
+            if emptiest
+                .as_ref()
+                .is_none_or(|(tokens, _)| bucket.tokens < *tokens)
+            {
+                emptiest = Some((bucket.tokens, bucket.state(limit)));
+            }
+        }
+        let (_, state) = emptiest.expect("every request has at least one client");
+
+        if allowed {
+            Decision::Allowed(state)
+        } else {
+            Decision::Limited(state)
+        }
+    }
+}
+
+/// IPv6 clients usually control a whole /64, so they are limited by that prefix.
+fn client_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => ip,
+        IpAddr::V6(v6) => {
+            let segments = v6.segments();
+            IpAddr::V6(Ipv6Addr::new(
+                segments[0],
+                segments[1],
+                segments[2],
+                segments[3],
+                0,
+                0,
+                0,
+                0,
+            ))
+        }
+    }
+}
+
+/// The client's address: the peer address, unless the peer is a trusted proxy or the
+/// connection came over the Unix socket (which only a local reverse proxy reaches). The
+/// client is then the last address in `Forwarded` or `X-Forwarded-For` that isn't a
+/// trusted proxy, since everything before it could have been sent by the client.
+pub fn request_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let trusted = req
+        .app_data::<Data<ProxyConfig>>()
+        .map(|proxies| proxies.trusted.as_slice())
+        .unwrap_or_default();
+    let peer = req.peer_addr().map(|addr| addr.ip());
+    if let Some(peer) = peer.filter(|peer| !trusted.contains(peer)) {
+        return Some(peer);
+    }
+
+    for node in forwarded_chain(req.headers()).into_iter().rev() {
+        match node {
+            Some(ip) if trusted.contains(&ip) => continue,
+            Some(ip) => return Some(ip),
+            // An obfuscated or unknown hop: nothing before it can be told apart from
+            // what the client sent
+            None => break,
+        }
+    }
+
+    peer
+}
+
+/// The addresses in `Forwarded`, or without it `X-Forwarded-For`, client first. `None`
+/// stands for an entry that isn't an IP address.
+fn forwarded_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name: HeaderName| {
+        headers
+            .get_all(name)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+    };
+
+    let forwarded: Vec<_> = values(FORWARDED)
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (name, node) = pair.split_once('=')?;
+                name.trim().eq_ignore_ascii_case("for").then_some(node)
+            })
+        })
+        .map(parse_node)
+        .collect();
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    values(X_FORWARDED_FOR).map(parse_node).collect()
+}
+
+/// `192.0.2.1`, `192.0.2.1:4711`, `2001:db8::1` or `"[2001:db8::1]:4711"`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Some(bracketed) = node.strip_prefix('[') {
+        return bracketed.split(']').next()?.parse().ok();
+    }
+
+    node.parse::<IpAddr>()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+/// Throttles the route groups in `RateLimits` by client IP and signed-in user, and
+/// answers with 429 once a bucket is empty. Limited responses carry
+/// `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset`.
+pub async fn enforce_rate_limits(
+    limiter: Data<RateLimiter>,
+    settings: Data<RuntimeSettings>,
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let limits = settings.current().rate_limits;
+    let group = RateLimitGroup::for_request(req.method(), req.path());
+    let Some(group) = group.filter(|group| {
+        let limit = group.limit(&limits);
+        limits.enabled && limit.burst > 0 && limit.per_minute > 0
+    }) else {
+        return next.call(req).await;
+    };
+
+    let mut clients = Vec::with_capacity(2);
+    if let Some(ip) = request_ip(req.request()) {
+        clients.push(Client::Ip(client_ip(ip)));
+    }
+    // Bad credentials are left for the handler to reject
+    if let Some(user_id) = req.extract::<Viewer>().await.ok().and_then(|v| v.user_id()) {
+        clients.push(Client::User(user_id));
+    }
+    if clients.is_empty() {
+        return next.call(req).await;
+    }
+
+    let state = match limiter.check(group, &clients, &limits, Instant::now()) {
+        Decision::Allowed(state) => state,
+        Decision::Limited(state) => return Err(rate_limited_error(group, &state).into()),
+    };
+
+    let mut response = next.call(req).await?;
+    for (name, value) in rate_limit_headers(&state) {
+        response.headers_mut().insert(name, value);
+    }
+
+    Ok(response)
+}
+
+fn rate_limited_error(group: RateLimitGroup, state: &BucketState) -> ApiError {
+    let retry_after = state.retry_after.as_secs_f64().ceil() as u64;
+
+    ApiError::new(
+        StatusCode::TOO_MANY_REQUESTS,
+        "rate_limited",
+        format!(
+            "Too many {} requests, try again in {} seconds",
+            group, retry_after
+        ),
+    )
+    .with_arg("group", group.to_string())
+    .with_arg("retry_after", retry_after.to_string())
+    .with_headers(rate_limit_headers(state))
+    .with_headers(vec![(RETRY_AFTER, HeaderValue::from(retry_after))])
+}
+
+fn rate_limit_headers(state: &BucketState) -> Vec<(HeaderName, HeaderValue)> {
+    let reset = Utc::now().timestamp() + state.reset_after.as_secs_f64().ceil() as i64;
+
+    vec![
+        (
+            HeaderName::from_static("x-ratelimit-limit"),
+            HeaderValue::from(state.limit),
+        ),
+        (
+            HeaderName::from_static("x-ratelimit-remaining"),
+            HeaderValue::from(state.remaining),
+        ),
+        (
+            HeaderName::from_static("x-ratelimit-reset"),
+            HeaderValue::from(reset),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod rate_limit_tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        burst: 2,
+        per_minute: 60,
+    };
+
+    fn limits() -> RateLimits {
+        RateLimits {
+            enabled: true,
+            signup: LIMIT,
+            login: LIMIT,
+            post: LIMIT,
+            comment: LIMIT,
+        }
+    }
+
+    fn remaining(decision: Decision) -> Option<u32> {
+        match decision {
+            Decision::Allowed(state) => Some(state.remaining),
+            Decision::Limited(_) => None,
+        }
+    }
+
+    #[test]
+    fn test_routes_map_to_groups() {
+        let group = |path| RateLimitGroup::for_request(&Method::POST, path);
+        assert_eq!(group("/users"), Some(RateLimitGroup::Signup));
+        assert_eq!(group("/auth/session"), Some(RateLimitGroup::Login));
+        assert_eq!(group("/posts/rust"), Some(RateLimitGroup::Post));
+        assert_eq!(group("/posts/by_ids"), None);
+        assert_eq!(group("/posts/abc/comments"), Some(RateLimitGroup::Comment));
+        assert_eq!(RateLimitGroup::for_request(&Method::GET, "/users"), None);
+    }
+
+    #[test]
+    fn test_bucket_empties_and_refills() {
+        let limiter = RateLimiter::default();
+        let client = [Client::Ip(IpAddr::from([127, 0, 0, 1]))];
+        let start = Instant::now();
+        let check = |now| limiter.check(RateLimitGroup::Login, &client, &limits(), now);
+
+        assert_eq!(remaining(check(start)), Some(1));
+        assert_eq!(remaining(check(start)), Some(0));
+        assert_eq!(remaining(check(start)), None);
+        assert_eq!(remaining(check(start + Duration::from_secs(1))), Some(0));
+    }
+
+    #[test]
+    fn test_limited_user_costs_no_ip_tokens() {
+        let limiter = RateLimiter::default();
+        let ip = Client::Ip(IpAddr::from([127, 0, 0, 1]));
+        let now = Instant::now();
+        limiter.check(RateLimitGroup::Post, &[Client::User(1)], &limits(), now);
+        limiter.check(RateLimitGroup::Post, &[Client::User(1)], &limits(), now);
+
+        let decision = limiter.check(RateLimitGroup::Post, &[ip, Client::User(1)], &limits(), now);
+        assert!(matches!(decision, Decision::Limited(_)));
+        assert_eq!(
+            remaining(limiter.check(RateLimitGroup::Post, &[ip], &limits(), now)),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_ipv6_clients_share_their_prefix() {
+        let first: IpAddr = "2001:db8:1:2::1".parse().unwrap();
+        let second: IpAddr = "2001:db8:1:2:ffff::9".parse().unwrap();
+        assert_eq!(client_ip(first), client_ip(second));
+    }
+
+    #[test]
+    fn test_forwarded_addresses_are_only_believed_from_trusted_proxies() {
+        let proxies = Data::new(ProxyConfig {
+            trusted: vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()],
+        });
+        let ip = |peer: &str, name: &str, value: &str| {
+            let req = actix_web::test::TestRequest::default()
+                .peer_addr(peer.parse().unwrap())
+                .insert_header((name, value))
+                .app_data(proxies.clone())
+                .to_http_request();
+            request_ip(&req).unwrap().to_string()
+        };
+
+        // A client connecting directly can't claim another address
+        assert_eq!(
+            ip("203.0.113.7:1234", "x-forwarded-for", "192.0.2.1"),
+            "203.0.113.7"
+        );
+        // Behind the proxies, the client is the last untrusted hop
+        assert_eq!(
+            ip(
+                "10.0.0.1:80",
+                "x-forwarded-for",
+                "192.0.2.1, 198.51.100.4, 10.0.0.2"
+            ),
+            "198.51.100.4"
+        );
+        assert_eq!(
+            ip(
+                "10.0.0.1:80",
+                "forwarded",
+                "for=192.0.2.1, for=\"[2001:db8::1]:4711\";proto=https"
+            ),
+            "2001:db8::1"
+        );
+        assert_eq!(
+            ip("10.0.0.1:80", "x-forwarded-for", "192.0.2.1, unknown"),
+            "10.0.0.1"
+        );
+    }
+}
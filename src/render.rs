@@ -0,0 +1,215 @@
+//! Markdown for posts and comments, rendered on the server so every client shows the
+//! same markup and none has to get sanitization right on its own.
+//!
+//! pulldown-cmark renders CommonMark with strikethrough, and ammonia then cleans the
+//! result down to the tags the renderer is meant to produce. Raw HTML in the input is
+//! shown as text, images become plain links, and only web and mail addresses and
+//! relative paths are linked.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+/// Elements nest at most this deep; the tags of deeper ones are dropped and their text
+/// kept, so hostile input can't build a huge tree.
+const MAX_NESTING: usize = 8;
+
+const LINK_REL: &str = "nofollow noopener noreferrer";
+
+const ALLOWED_TAGS: [&str; 19] = [
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "em",
+    "strong",
+    "del",
+    "code",
+    "pre",
+    "blockquote",
+    "ul",
+    "ol",
+    "li",
+    "a",
+    "hr",
+    "br",
+];
+
+#[derive(Deserialize)]
+pub struct PreviewRequest {
+    pub markdown: String,
+}
+
+#[derive(Serialize)]
+pub struct Preview {
+    pub html: String,
+}
+
+pub fn markdown_to_html(markdown: &str) -> String {
+    let parser = Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH);
+    let mut kept = Vec::new();
+    let events = parser.filter_map(|event| match event {
+        Event::Start(tag) => {
+            let keep = kept.iter().filter(|&&kept| kept).count() < MAX_NESTING;
+            let tag = keep.then(|| safe_tag(tag)).flatten();
+            kept.push(tag.is_some());
+            tag.map(Event::Start)
+        }
+        Event::End(end) => kept
+            .pop()
+            .unwrap_or(false)
+            .then(|| Event::End(safe_end(end))),
+        Event::Html(text) | Event::InlineHtml(text) => Some(Event::Text(text)),
+        event => Some(event),
+    });
+
+    let mut rendered = String::with_capacity(markdown.len() + markdown.len() / 4);
+    html::push_html(&mut rendered, events);
+    sanitizer().clean(&rendered).to_string()
+}
+
+/// Images are linked rather than embedded, links to anything but a safe destination are
+/// left as their text, and a code block's language is cut down to a plain name.
+fn safe_tag(tag: Tag) -> Option<Tag> {
+    match tag {
+        Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }
+        | Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        } => is_safe_destination(&dest_url).then_some(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }),
+        Tag::CodeBlock(CodeBlockKind::Fenced(info)) => {
+            let language: String = info
+                .trim()
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '#'))
+                .collect();
+            Some(Tag::CodeBlock(CodeBlockKind::Fenced(CowStr::from(
+                language,
+            ))))
+        }
+        Tag::HtmlBlock => Some(Tag::Paragraph),
+        tag => Some(tag),
+    }
+}
+
+fn safe_end(end: TagEnd) -> TagEnd {
+    match end {
+        TagEnd::Image => TagEnd::Link,
+        TagEnd::HtmlBlock => TagEnd::Paragraph,
+        end => end,
+    }
+}
+
+/// Keeps only the markup `markdown_to_html` writes itself, in case a Markdown construct
+/// renders to something unexpected.
+fn sanitizer() -> &'static ammonia::Builder<'static> {
+    static SANITIZER: OnceLock<ammonia::Builder<'static>> = OnceLock::new();
+    SANITIZER.get_or_init(|| {
+        let mut builder = ammonia::Builder::empty();
+        builder
+            .tags(HashSet::from(ALLOWED_TAGS))
+            .add_tag_attributes("a", ["href", "title"])
+            .add_tag_attributes("ol", ["start"])
+            .add_tag_attributes("code", ["class"])
+            .url_schemes(HashSet::from(["http", "https", "mailto"]))
+            .link_rel(Some(LINK_REL));
+        builder
+    })
+}
+
+/// Web and mail URLs, and relative ones. Anything else with a scheme, `javascript:`
+/// included, is rendered as plain text.
+fn is_safe_destination(destination: &str) -> bool {
+    if destination.is_empty() || destination.chars().any(char::is_control) {
+        return false;
+    }
+
+    let lower = destination.to_ascii_lowercase();
+    match lower.find([':', '/', '?', '#']) {
+        Some(end) if lower[end..].starts_with(':') => {
+            matches!(&lower[..end], "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_html_is_escaped() {
+        assert_eq!(
+            markdown_to_html("<script>alert('hi')</script>"),
+            "<p>&lt;script&gt;alert('hi')&lt;/script&gt;</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_markup() {
+        assert_eq!(
+            markdown_to_html("**bold** *em* `a<b` ~~gone~~ snake_case_name 2 * 3 * 4"),
+            "<p><strong>bold</strong> <em>em</em> <code>a&lt;b</code> <del>gone</del> \
+             snake_case_name 2 * 3 * 4</p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("*a **b** c*  \nnext"),
+            "<p><em>a <strong>b</strong> c</em><br>\nnext</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_only_safe_links_are_rendered() {
+        assert_eq!(
+            markdown_to_html("[ok](https://example.com) [bad](javascript:alert) <http://a.b>"),
+            "<p><a href=\"https://example.com\" rel=\"nofollow noopener noreferrer\">ok</a> bad \
+             <a href=\"http://a.b\" rel=\"nofollow noopener noreferrer\">http://a.b</a></p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("[\"><img>](/s/rust)"),
+            "<p><a href=\"/s/rust\" rel=\"nofollow noopener noreferrer\">\"&gt;&lt;img&gt;</a></p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("![cat](https://example.com/cat.png) ![x](javascript:alert)"),
+            "<p><a href=\"https://example.com/cat.png\" rel=\"nofollow noopener noreferrer\">cat</a> \
+             x</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            markdown_to_html("# Title\n\n- one\n- two\n\n1. first\n\n> quoted\n\n```rust\n<b>\n```\n---"),
+            "<h1>Title</h1>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n<ol>\n<li>first</li>\n</ol>\n\
+             <blockquote>\n<p>quoted</p>\n</blockquote>\n\
+             <pre><code class=\"language-rust\">&lt;b&gt;\n</code></pre>\n<hr>\n"
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting_stays_text() {
+        let html = markdown_to_html(&format!("{} deep", ">".repeat(10_000)));
+        assert_eq!(html.matches("<blockquote>").count(), MAX_NESTING);
+        assert!(html.contains("\ndeep</blockquote>"));
+
+        let html = markdown_to_html(&"[*".repeat(10_000));
+        assert!(html.starts_with("<p>[<em>[</em>[<em>[</em>"));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::model::account_requirement::{AccountRequirement, Contribution, NewAccountRequirement};
+use sqlx::PgPool;
+
+/// Replaces the requirement for the contribution in the sub, or site-wide.
+pub async fn set_account_requirement(
+    pool: &PgPool,
+    requirement: &NewAccountRequirement,
+    created_by: i32,
+) -> Result<AccountRequirement, sqlx::Error> {
+    let requirement = sqlx::query_as!(
+        AccountRequirement,
+        r#"
+        INSERT INTO account_requirements (sub, contribution, min_account_age_days, min_karma,
+            created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT ((COALESCE(sub, '')), contribution) DO UPDATE
+        SET min_account_age_days = $3, min_karma = $4, created_by = $5, updated_at = NOW()
+        RETURNING id, sub, contribution AS "contribution: Contribution", min_account_age_days,
+            min_karma, created_by, updated_at
+        "#,
+        requirement.sub,
+        requirement.contribution.to_string(),
+        requirement.min_account_age_days,
+        requirement.min_karma,
+        created_by
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(requirement)
+}
+
+/// The site-wide requirements, plus those of `sub` if given.
+pub async fn get_account_requirements(
+    pool: &PgPool,
+    sub: Option<&str>,
+) -> Result<Vec<AccountRequirement>, sqlx::Error> {
+    let requirements = sqlx::query_as!(
+        AccountRequirement,
+        r#"
+        SELECT id, sub, contribution AS "contribution: Contribution", min_account_age_days,
+            min_karma, created_by, updated_at
+        FROM account_requirements
+        WHERE sub IS NULL OR sub = $1
+        ORDER BY sub NULLS FIRST, contribution
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(requirements)
+}
+
+/// The requirements a contribution in `sub`, or outside of any sub, has to meet.
+pub async fn get_applicable_requirements(
+    pool: &PgPool,
+    sub: Option<&str>,
+    contribution: Contribution,
+) -> Result<Vec<AccountRequirement>, sqlx::Error> {
+    let requirements = get_account_requirements(pool, sub).await?;
+
+    Ok(requirements
+        .into_iter()
+        .filter(|requirement| requirement.contribution == contribution)
+        .collect())
+}
+
+pub async fn get_account_requirement(
+    pool: &PgPool,
+    requirement_id: i32,
+) -> Result<Option<AccountRequirement>, sqlx::Error> {
+    let requirement = sqlx::query_as!(
+        AccountRequirement,
+        r#"
+        SELECT id, sub, contribution AS "contribution: Contribution", min_account_age_days,
+            min_karma, created_by, updated_at
+        FROM account_requirements
+        WHERE id = $1
+        "#,
+        requirement_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(requirement)
+}
+
+pub async fn delete_account_requirement(
+    pool: &PgPool,
+    requirement_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM account_requirements
+        WHERE id = $1
+        "#,
+        requirement_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod account_requirement_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_requirements_are_replaced_per_sub_and_contribution() {
+        let db = TestDatabase::new().await;
+        let admin = UserFixture::new("admin").insert(&db.pool).await;
+        let rust = SubFixture::new("rust").insert(&db.pool).await;
+        let go = SubFixture::new("go").insert(&db.pool).await;
+        let requirement = |sub: Option<&str>, contribution, min_karma| NewAccountRequirement {
+            sub: sub.map(String::from),
+            contribution,
+            min_account_age_days: 1,
+            min_karma,
+        };
+
+        for new in [
+            requirement(None, Contribution::Post, 5),
+            requirement(None, Contribution::Post, 10),
+            requirement(Some(&rust.name), Contribution::Post, 50),
+            requirement(Some(&rust.name), Contribution::Comment, 20),
+            requirement(Some(&go.name), Contribution::Post, 100),
+        ] {
+            set_account_requirement(&db.pool, &new, admin.id)
+                .await
+                .unwrap();
+        }
+
+        let applicable =
+            get_applicable_requirements(&db.pool, Some(&rust.name), Contribution::Post)
+                .await
+                .unwrap();
+        let karma: Vec<(Option<&str>, i32)> = applicable
+            .iter()
+            .map(|requirement| (requirement.sub.as_deref(), requirement.min_karma))
+            .collect();
+        assert_eq!(karma, [(None, 10), (Some("rust"), 50)]);
+        assert_eq!(
+            get_account_requirements(&db.pool, None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let site_wide = applicable[0].id;
+        assert!(delete_account_requirement(&db.pool, site_wide)
+            .await
+            .unwrap());
+        assert!(get_account_requirement(&db.pool, site_wide)
+            .await
+            .unwrap()
+            .is_none());
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,191 @@
+use crate::model::api_key::{ApiKey, ApiKeySummary, ApiScope, ApiTier, ApiUsage};
+use sqlx::PgPool;
+
+pub async fn create_api_key(
+    pool: &PgPool,
+    user_id: i32,
+    key_hash: &str,
+    label: &str,
+    scopes: &[ApiScope],
+) -> Result<(i64, ApiTier), sqlx::Error> {
+    let scopes: Vec<String> = scopes.iter().map(ApiScope::to_string).collect();
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO api_keys (user_id, key_hash, label, scopes)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, tier AS "tier: ApiTier"
+        "#,
+        user_id,
+        key_hash,
+        label,
+        &scopes
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((created.id, created.tier))
+}
+
+pub async fn get_api_key(pool: &PgPool, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, user_id, tier AS "tier: ApiTier", scopes AS "scopes: Vec<ApiScope>"
+        FROM api_keys
+        WHERE key_hash = $1 AND revoked_at IS NULL
+        "#,
+        key_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(key)
+}
+
+/// The user's keys that haven't been revoked, newest first.
+pub async fn get_api_keys(pool: &PgPool, user_id: i32) -> Result<Vec<ApiKeySummary>, sqlx::Error> {
+    let keys = sqlx::query_as!(
+        ApiKeySummary,
+        r#"
+        SELECT id, label, tier AS "tier: ApiTier", scopes AS "scopes: Vec<ApiScope>", created_at
+        FROM api_keys
+        WHERE user_id = $1 AND revoked_at IS NULL
+        ORDER BY created_at DESC, id DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(keys)
+}
+
+pub async fn set_api_key_tier(
+    pool: &PgPool,
+    key_id: i64,
+    tier: ApiTier,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE api_keys
+        SET tier = $2
+        WHERE id = $1
+        "#,
+        key_id,
+        tier.to_string()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn revoke_api_key(pool: &PgPool, user_id: i32, key_id: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE api_keys
+        SET revoked_at = NOW()
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        key_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn get_usage(pool: &PgPool, key_id: i64) -> Result<ApiUsage, sqlx::Error> {
+    let usage = sqlx::query_as!(
+        ApiUsage,
+        r#"
+        SELECT
+            COALESCE(SUM(requests) FILTER (WHERE day = (NOW() AT TIME ZONE 'UTC')::DATE), 0)::BIGINT AS "today!",
+            COALESCE(SUM(requests), 0)::BIGINT AS "this_month!"
+        FROM api_usage
+        WHERE api_key_id = $1
+        AND day >= DATE_TRUNC('month', NOW() AT TIME ZONE 'UTC')::DATE
+        "#,
+        key_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(usage)
+}
+
+/// Counts one request against today's usage and returns the updated totals, or `None`
+/// without counting it once the daily or monthly limit is reached. The daily check runs
+/// against the locked row, so concurrent requests can't overshoot the limit.
+pub async fn record_request(
+    pool: &PgPool,
+    key_id: i64,
+    daily_limit: i64,
+    monthly_limit: i64,
+) -> Result<Option<ApiUsage>, sqlx::Error> {
+    let usage = sqlx::query_as!(
+        ApiUsage,
+        r#"
+        WITH earlier AS (
+            SELECT COALESCE(SUM(requests), 0)::BIGINT AS requests
+            FROM api_usage
+            WHERE api_key_id = $1
+            AND day >= DATE_TRUNC('month', NOW() AT TIME ZONE 'UTC')::DATE
+            AND day < (NOW() AT TIME ZONE 'UTC')::DATE
+        )
+        INSERT INTO api_usage (api_key_id, day, requests)
+        SELECT $1, (NOW() AT TIME ZONE 'UTC')::DATE, 1
+        FROM earlier
+        WHERE $2::BIGINT > 0 AND earlier.requests < $3
+        ON CONFLICT (api_key_id, day) DO UPDATE SET requests = api_usage.requests + 1
+        WHERE api_usage.requests < $2
+        AND api_usage.requests + (SELECT requests FROM earlier) < $3
+        RETURNING
+            requests AS "today!",
+            requests + (SELECT requests FROM earlier) AS "this_month!"
+        "#,
+        key_id,
+        daily_limit,
+        monthly_limit
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod api_key_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::UserFixture;
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_record_request_stops_at_the_limit() {
+        let db = TestDatabase::new().await;
+        let user = UserFixture::new("bot").insert(&db.pool).await;
+        let (key_id, _) = create_api_key(&db.pool, user.id, "hash", "bot", &[ApiScope::Read])
+            .await
+            .unwrap();
+
+        let first = record_request(&db.pool, key_id, 2, 10).await.unwrap();
+        assert_eq!(
+            first,
+            Some(ApiUsage {
+                today: 1,
+                this_month: 1
+            })
+        );
+        assert!(record_request(&db.pool, key_id, 2, 10)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(record_request(&db.pool, key_id, 2, 10).await.unwrap(), None);
+        assert_eq!(record_request(&db.pool, key_id, 10, 2).await.unwrap(), None);
+
+        let usage = get_usage(&db.pool, key_id).await.unwrap();
+        assert_eq!(usage.today, 2);
+    }
+}
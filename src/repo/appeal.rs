@@ -0,0 +1,188 @@
+use crate::model::appeal::{Appeal, AppealKind, AppealStatus};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn create_appeal(
+    pool: &PgPool,
+    user_id: i32,
+    kind: AppealKind,
+    sub: Option<&str>,
+    post_id: Option<Uuid>,
+    comment_id: Option<Uuid>,
+    message: &str,
+) -> Result<Appeal, sqlx::Error> {
+    let appeal = sqlx::query_as!(
+        Appeal,
+        r#"
+        INSERT INTO appeals (user_id, kind, sub, post_id, comment_id, message)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, user_id, kind AS "kind: AppealKind", sub, post_id, comment_id, message,
+            status AS "status: AppealStatus", resolved_by, response, created_at, resolved_at
+        "#,
+        user_id,
+        kind.to_string(),
+        sub,
+        post_id,
+        comment_id,
+        message
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(appeal)
+}
+
+pub async fn get_appeal(pool: &PgPool, appeal_id: i64) -> Result<Option<Appeal>, sqlx::Error> {
+    let appeal = sqlx::query_as!(
+        Appeal,
+        r#"
+        SELECT id, user_id, kind AS "kind: AppealKind", sub, post_id, comment_id, message,
+            status AS "status: AppealStatus", resolved_by, response, created_at, resolved_at
+        FROM appeals
+        WHERE id = $1
+        "#,
+        appeal_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(appeal)
+}
+
+/// The user's appeals, most recent first.
+pub async fn get_user_appeals(pool: &PgPool, user_id: i32) -> Result<Vec<Appeal>, sqlx::Error> {
+    let appeals = sqlx::query_as!(
+        Appeal,
+        r#"
+        SELECT id, user_id, kind AS "kind: AppealKind", sub, post_id, comment_id, message,
+            status AS "status: AppealStatus", resolved_by, response, created_at, resolved_at
+        FROM appeals
+        WHERE user_id = $1
+        ORDER BY created_at DESC, id DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(appeals)
+}
+
+/// Appeals to the sub's moderators, or with `sub` as `None` appeals of suspensions,
+/// oldest first.
+pub async fn get_appeals(
+    pool: &PgPool,
+    sub: Option<&str>,
+    status: AppealStatus,
+) -> Result<Vec<Appeal>, sqlx::Error> {
+    let appeals = sqlx::query_as!(
+        Appeal,
+        r#"
+        SELECT id, user_id, kind AS "kind: AppealKind", sub, post_id, comment_id, message,
+            status AS "status: AppealStatus", resolved_by, response, created_at, resolved_at
+        FROM appeals
+        WHERE sub IS NOT DISTINCT FROM $1 AND status = $2
+        ORDER BY created_at, id
+        "#,
+        sub,
+        status.to_string()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(appeals)
+}
+
+/// Records the decision, unless the appeal was already decided.
+pub async fn resolve_appeal(
+    pool: &PgPool,
+    appeal_id: i64,
+    status: AppealStatus,
+    resolved_by: i32,
+    response: Option<&str>,
+) -> Result<Option<Appeal>, sqlx::Error> {
+    let appeal = sqlx::query_as!(
+        Appeal,
+        r#"
+        UPDATE appeals
+        SET status = $2, resolved_by = $3, response = $4, resolved_at = NOW()
+        WHERE id = $1 AND status = 'pending'
+        RETURNING id, user_id, kind AS "kind: AppealKind", sub, post_id, comment_id, message,
+            status AS "status: AppealStatus", resolved_by, response, created_at, resolved_at
+        "#,
+        appeal_id,
+        status.to_string(),
+        resolved_by,
+        response
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(appeal)
+}
+
+#[cfg(test)]
+mod appeal_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_one_pending_appeal_per_ban() {
+        let db = TestDatabase::new().await;
+        let user = UserFixture::new("banned").insert(&db.pool).await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let rust = SubFixture::new("rust").insert(&db.pool).await;
+        let appeal = |message| {
+            create_appeal(
+                &db.pool,
+                user.id,
+                AppealKind::SubBan,
+                Some(&rust.name),
+                None,
+                None,
+                message,
+            )
+        };
+
+        let first = appeal("Please").await.unwrap();
+        assert!(appeal("Please again").await.is_err());
+        let pending = get_appeals(&db.pool, Some(&rust.name), AppealStatus::Pending)
+            .await
+            .unwrap();
+        assert_eq!(pending, std::slice::from_ref(&first));
+
+        let denied = resolve_appeal(
+            &db.pool,
+            first.id,
+            AppealStatus::Denied,
+            moderator.id,
+            Some("No"),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(denied.status, AppealStatus::Denied);
+        assert_eq!(denied.response.as_deref(), Some("No"));
+        assert!(resolve_appeal(
+            &db.pool,
+            first.id,
+            AppealStatus::Granted,
+            moderator.id,
+            None
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+        appeal("Please, it has been a month").await.unwrap();
+        assert_eq!(get_user_appeals(&db.pool, user.id).await.unwrap().len(), 2);
+        assert!(get_appeals(&db.pool, None, AppealStatus::Pending)
+            .await
+            .unwrap()
+            .is_empty());
+
+        db.finish().await;
+    }
+}
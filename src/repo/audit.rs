@@ -0,0 +1,80 @@
+use crate::model::audit::{AuditEntry, AuditQuery};
+use sqlx::PgPool;
+
+const DEFAULT_AUDIT_LIMIT: i64 = 100;
+const MAX_AUDIT_LIMIT: i64 = 1000;
+
+pub async fn get_audit_entries(
+    pool: &PgPool,
+    query: &AuditQuery,
+) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_LIMIT)
+        .clamp(1, MAX_AUDIT_LIMIT);
+
+    let entries = sqlx::query_as!(
+        AuditEntry,
+        r#"
+        SELECT id, table_name, row_key, operation, old_data, new_data, changed_by, changed_at
+        FROM audit_log
+        WHERE ($1::TEXT IS NULL OR table_name = $1)
+        AND ($2::TEXT IS NULL OR row_key = $2)
+        ORDER BY changed_at DESC, id DESC
+        LIMIT $3
+        "#,
+        query.table,
+        query.row_key,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod audit_repo_tests {
+    use super::*;
+    use crate::audit::as_actor;
+    use crate::repo::sub as sub_repo;
+    use crate::test_support::fixtures::{SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_changes_are_audited_as_the_acting_user() {
+        let db = TestDatabase::new().await;
+        let moderator = UserFixture::new("mod").insert(&db.pool).await;
+        let troll = UserFixture::new("troll").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+
+        as_actor(
+            Some(moderator.id),
+            sub_repo::ban_user_from_sub(&db.pool, &sub.name, troll.id, moderator.id, "", None),
+        )
+        .await
+        .unwrap();
+        sub_repo::unban_user_from_sub(&db.pool, &sub.name, troll.id)
+            .await
+            .unwrap();
+
+        let entries = get_audit_entries(
+            &db.pool,
+            &AuditQuery {
+                table: Some("sub_bans".to_string()),
+                row_key: Some(troll.id.to_string()),
+                limit: None,
+            },
+        )
+        .await
+        .unwrap();
+        let changes: Vec<(&str, Option<i32>)> = entries
+            .iter()
+            .map(|entry| (entry.operation.as_str(), entry.changed_by))
+            .collect();
+        assert_eq!(changes, [("DELETE", None), ("INSERT", Some(moderator.id))]);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,90 @@
+use crate::model::automod::{AutomodRule, SubAutomod};
+use sqlx::types::Json;
+use sqlx::PgPool;
+
+/// The sub's rules, or `None` if its moderators never saved any.
+pub async fn get_automod(pool: &PgPool, sub: &str) -> Result<Option<SubAutomod>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT sub, rules AS "rules: Json<Vec<AutomodRule>>", updated_by, updated_at
+        FROM sub_automod
+        WHERE sub = $1
+        "#,
+        sub
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| SubAutomod {
+        sub: row.sub,
+        rules: row.rules.0,
+        updated_by: row.updated_by,
+        updated_at: Some(row.updated_at),
+    }))
+}
+
+/// Replaces the sub's rules.
+pub async fn save_automod(
+    pool: &PgPool,
+    sub: &str,
+    rules: &[AutomodRule],
+    updated_by: i32,
+) -> Result<SubAutomod, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO sub_automod (sub, rules, updated_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (sub) DO UPDATE
+        SET rules = $2, updated_by = $3, updated_at = NOW()
+        RETURNING sub, rules AS "rules: Json<Vec<AutomodRule>>", updated_by, updated_at
+        "#,
+        sub,
+        Json(rules) as _,
+        updated_by
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(SubAutomod {
+        sub: row.sub,
+        rules: row.rules.0,
+        updated_by: row.updated_by,
+        updated_at: Some(row.updated_at),
+    })
+}
+
+#[cfg(test)]
+mod automod_repo_tests {
+    use super::*;
+    use crate::model::automod::AutomodAction;
+    use crate::test_support::fixtures::{SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_saving_rules_replaces_them() {
+        let db = TestDatabase::new().await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        assert!(get_automod(&db.pool, &sub.name).await.unwrap().is_none());
+
+        let rule = |name: &str| AutomodRule {
+            name: name.to_string(),
+            applies_to: Default::default(),
+            conditions: Default::default(),
+            actions: vec![AutomodAction::Remove],
+        };
+        save_automod(&db.pool, &sub.name, &[rule("First")], moderator.id)
+            .await
+            .unwrap();
+        save_automod(&db.pool, &sub.name, &[rule("Second")], moderator.id)
+            .await
+            .unwrap();
+
+        let automod = get_automod(&db.pool, &sub.name).await.unwrap().unwrap();
+        assert_eq!(automod.rules, [rule("Second")]);
+        assert_eq!(automod.updated_by, Some(moderator.id));
+
+        db.finish().await;
+    }
+}
@@ -1,39 +1,178 @@
-use crate::model::comment::Comment;
+use crate::model::comment::{Comment, CommentCursor, CommentSort, RankedComment, TreeComment};
+use crate::model::flair::AuthorFlair;
+use crate::model::media::Media;
+use crate::model::moderation::RemovalKind;
+use crate::model::revision::Revision;
+use crate::repo::karma as karma_repo;
+use crate::repo::media as media_repo;
+use crate::repo::removal_reason as removal_reason_repo;
+use crate::repo::saved as saved_repo;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
+use std::collections::HashMap;
 use uuid::Uuid;
 
+/// A listing row: a comment and its `comment_rank`.
+struct ListedComment {
+    id: Uuid,
+    post_id: Uuid,
+    user_id: i32,
+    content: String,
+    timestamp: DateTime<Utc>,
+    parent_id: Option<Uuid>,
+    removal: Option<RemovalKind>,
+    score: i32,
+    edited_at: Option<DateTime<Utc>>,
+    author_flair: Option<AuthorFlair>,
+    rank: f64,
+}
+
+impl From<ListedComment> for RankedComment {
+    fn from(listed: ListedComment) -> Self {
+        RankedComment {
+            comment: Comment {
+                id: listed.id,
+                post_id: listed.post_id,
+                user_id: listed.user_id,
+                content: listed.content,
+                timestamp: listed.timestamp,
+                parent_id: listed.parent_id,
+                removal: listed.removal,
+                score: listed.score,
+                edited_at: listed.edited_at,
+                author_flair: listed.author_flair,
+            },
+            rank: listed.rank,
+        }
+    }
+}
+
 pub async fn create_comment(pool: &PgPool, comment: &Comment) -> Result<Uuid, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
     sqlx::query!(
         r#"
-        INSERT INTO comments (id, post_id, user_id, content, timestamp, parent_id)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO comments (id, post_id, user_id, content, timestamp, parent_id,
+            removal_kind, removed_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, CASE WHEN $7::TEXT IS NULL THEN NULL ELSE NOW() END)
         "#,
         comment.id,
         comment.post_id,
         comment.user_id,
         comment.content,
         comment.timestamp,
-        comment.parent_id
+        comment.parent_id,
+        comment.removal.map(|removal| removal.to_string()),
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    sqlx::query!(
+        r#"
+        INSERT INTO comment_revisions (comment_id, revision, content, created_at)
+        VALUES ($1, 1, $2, $3)
+        "#,
+        comment.id,
+        comment.content,
+        comment.timestamp,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
     Ok(comment.id)
 }
 
+pub async fn get_comment(pool: &PgPool, comment_id: Uuid) -> Result<Comment, sqlx::Error> {
+    let comment = sqlx::query_as!(
+        Comment,
+        r#"
+        SELECT id, post_id, user_id,
+            CASE removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                WHEN 'deleted' THEN '[deleted]'
+                ELSE content
+            END AS "content!",
+            timestamp, parent_id, removal_kind AS "removal: RemovalKind", score, edited_at,
+            author_flair(user_id, post_id) AS "author_flair: AuthorFlair"
+        FROM comments
+        WHERE id = $1
+        "#,
+        comment_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(comment)
+}
+
+/// The comments among `comment_ids` on published posts, leaving out those on NSFW
+/// posts unless `include_nsfw`. Order is unspecified.
+pub async fn get_comments_by_ids(
+    pool: &PgPool,
+    comment_ids: &[Uuid],
+    include_nsfw: bool,
+    viewer: Option<i32>,
+) -> Result<Vec<Comment>, sqlx::Error> {
+    let comments = sqlx::query_as!(
+        Comment,
+        r#"
+        SELECT comments.id, comments.post_id, comments.user_id,
+            CASE comments.removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                WHEN 'deleted' THEN '[deleted]'
+                ELSE comments.content
+            END AS "content!",
+            comments.timestamp, comments.parent_id,
+            comments.removal_kind AS "removal: RemovalKind", comments.score, comments.edited_at,
+            author_flair(comments.user_id, comments.post_id) AS "author_flair: AuthorFlair"
+        FROM comments
+        INNER JOIN posts ON posts.id = comments.post_id
+        INNER JOIN subs ON subs.name = posts.sub
+        WHERE comments.id = ANY($1) AND posts.status = 'published'
+        AND ($2 OR NOT (posts.nsfw OR subs.nsfw))
+        AND author_visible(comments.user_id, posts.sub, $3)
+        "#,
+        comment_ids,
+        include_nsfw,
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(comments)
+}
+
 pub async fn get_comments_by_post(
     pool: &PgPool,
     post_id: Uuid,
+    viewer: Option<i32>,
 ) -> Result<Vec<Comment>, sqlx::Error> {
     let comments = sqlx::query_as!(
         Comment,
         r#"
-        SELECT id, post_id, user_id, content, timestamp, parent_id
+        SELECT id, post_id, user_id,
+            CASE removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                WHEN 'deleted' THEN '[deleted]'
+                ELSE content
+            END AS "content!",
+            timestamp, parent_id, removal_kind AS "removal: RemovalKind", score, edited_at,
+            author_flair(user_id, post_id) AS "author_flair: AuthorFlair"
         FROM comments
-        WHERE post_id = $1
+        WHERE post_id = $1 AND author_visible(user_id, (SELECT sub FROM posts WHERE posts.id = $1), $2)
         ORDER BY timestamp ASC
         "#,
-        post_id
+        post_id,
+        viewer
     )
     .fetch_all(pool)
     .await?;
@@ -41,36 +180,804 @@ pub async fn get_comments_by_post(
     Ok(comments)
 }
 
+/// Up to `limit + 1` of the post's comments after `after` in `sort` order, so the
+/// caller can tell whether another page follows.
+pub async fn get_comment_page(
+    pool: &PgPool,
+    post_id: Uuid,
+    sort: CommentSort,
+    after: Option<CommentCursor>,
+    limit: i64,
+    viewer: Option<i32>,
+) -> Result<Vec<RankedComment>, sqlx::Error> {
+    let comments = sqlx::query_as!(
+        ListedComment,
+        r#"
+        SELECT id, post_id, user_id,
+            CASE removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                WHEN 'deleted' THEN '[deleted]'
+                ELSE content
+            END AS "content!",
+            timestamp, parent_id, removal_kind AS "removal: RemovalKind", score, edited_at,
+            author_flair(user_id, post_id) AS "author_flair: AuthorFlair",
+            comment_rank($2, score, upvotes, downvotes, timestamp) AS "rank!"
+        FROM comments
+        WHERE post_id = $1 AND author_visible(user_id, (SELECT sub FROM posts WHERE posts.id = $1), $6)
+        AND ($3::DOUBLE PRECISION IS NULL OR (
+            comment_rank($2, score, upvotes, downvotes, timestamp), id
+        ) < ($3, $4))
+        ORDER BY "rank!" DESC, id DESC
+        LIMIT $5::BIGINT + 1
+        "#,
+        post_id,
+        sort.to_string(),
+        after.map(|cursor| cursor.rank),
+        after.map(|cursor| cursor.id),
+        limit,
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(comments.into_iter().map(RankedComment::from).collect())
+}
+
+pub async fn count_comments(
+    pool: &PgPool,
+    post_id: Uuid,
+    viewer: Option<i32>,
+) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM comments
+        WHERE post_id = $1 AND author_visible(user_id, (SELECT sub FROM posts WHERE posts.id = $1), $2)
+        "#,
+        post_id,
+        viewer
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Replies to `parent_id` (top-level comments for `None`) and their replies in turn,
+/// `depth` levels deep, taking at most `width` replies under each comment, with
+/// siblings in `sort` order. `after` continues the top level after that comment, for
+/// loading more replies than an earlier tree included.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_comment_tree(
+    pool: &PgPool,
+    post_id: Uuid,
+    parent_id: Option<Uuid>,
+    sort: CommentSort,
+    after: Option<Uuid>,
+    depth: i32,
+    width: i64,
+    viewer: Option<i32>,
+) -> Result<Vec<TreeComment>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        WITH RECURSIVE thread AS (
+            (
+                SELECT id, 1 AS depth
+                FROM comments
+                WHERE post_id = $1 AND parent_id IS NOT DISTINCT FROM $2
+                AND author_visible(user_id, (SELECT sub FROM posts WHERE posts.id = $1), $7)
+                AND ($5::UUID IS NULL OR (
+                    comment_rank($6, score, upvotes, downvotes, timestamp), id
+                ) < (
+                    SELECT comment_rank($6, score, upvotes, downvotes, timestamp), id
+                    FROM comments
+                    WHERE id = $5
+                ))
+                ORDER BY comment_rank($6, score, upvotes, downvotes, timestamp) DESC, id DESC
+                LIMIT $4
+            )
+            UNION ALL
+            SELECT reply.id, thread.depth + 1
+            FROM thread
+            CROSS JOIN LATERAL (
+                SELECT id
+                FROM comments
+                WHERE comments.parent_id = thread.id
+                AND author_visible(user_id, (SELECT sub FROM posts WHERE posts.id = $1), $7)
+                ORDER BY comment_rank($6, score, upvotes, downvotes, timestamp) DESC, id DESC
+                LIMIT $4
+            ) AS reply
+            WHERE thread.depth < $3
+        )
+        SELECT comments.id, comments.post_id, comments.user_id,
+            CASE comments.removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                WHEN 'deleted' THEN '[deleted]'
+                ELSE comments.content
+            END AS "content!",
+            comments.timestamp, comments.parent_id,
+            comments.removal_kind AS "removal: RemovalKind", comments.score,
+            comments.edited_at,
+            author_flair(comments.user_id, comments.post_id) AS "author_flair: AuthorFlair",
+            (
+                SELECT COUNT(*) FROM comments AS replies
+                WHERE replies.parent_id = comments.id
+                AND author_visible(replies.user_id, (SELECT sub FROM posts WHERE posts.id = $1), $7)
+            ) AS "reply_count!"
+        FROM thread
+        INNER JOIN comments ON comments.id = thread.id
+        ORDER BY thread.depth,
+            comment_rank(
+                $6, comments.score, comments.upvotes, comments.downvotes, comments.timestamp
+            ) DESC,
+            comments.id DESC
+        "#,
+        post_id,
+        parent_id,
+        depth,
+        width,
+        after,
+        sort.to_string(),
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TreeComment {
+            comment: Comment {
+                id: row.id,
+                post_id: row.post_id,
+                user_id: row.user_id,
+                content: row.content,
+                timestamp: row.timestamp,
+                parent_id: row.parent_id,
+                removal: row.removal,
+                score: row.score,
+                edited_at: row.edited_at,
+                author_flair: row.author_flair,
+            },
+            reply_count: row.reply_count,
+        })
+        .collect())
+}
+
+/// Direct replies to `parent_id`, or top-level comments for `None`, that sort after
+/// `after` in a tree sorted by `sort`.
+pub async fn count_replies(
+    pool: &PgPool,
+    post_id: Uuid,
+    parent_id: Option<Uuid>,
+    sort: CommentSort,
+    after: Option<Uuid>,
+    viewer: Option<i32>,
+) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM comments
+        WHERE post_id = $1 AND parent_id IS NOT DISTINCT FROM $2
+        AND author_visible(user_id, (SELECT sub FROM posts WHERE posts.id = $1), $5)
+        AND ($4::UUID IS NULL OR (
+            comment_rank($3, score, upvotes, downvotes, timestamp), id
+        ) < (
+            SELECT comment_rank($3, score, upvotes, downvotes, timestamp), id
+            FROM comments
+            WHERE id = $4
+        ))
+        "#,
+        post_id,
+        parent_id,
+        sort.to_string(),
+        after,
+        viewer
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Up to `limit` comments above `comment_id` in its thread, the top-most first.
+pub async fn get_comment_ancestors(
+    pool: &PgPool,
+    comment_id: Uuid,
+    limit: i32,
+    viewer: Option<i32>,
+) -> Result<Vec<Comment>, sqlx::Error> {
+    let comments = sqlx::query_as!(
+        Comment,
+        r#"
+        WITH RECURSIVE anchor AS (
+            SELECT post_id FROM comments WHERE id = $1
+        ),
+        chain AS (
+            SELECT parent_id AS id, 1 AS distance
+            FROM comments
+            WHERE id = $1 AND parent_id IS NOT NULL AND $2 > 0
+            UNION ALL
+            SELECT comments.parent_id, chain.distance + 1
+            FROM chain
+            INNER JOIN comments ON comments.id = chain.id
+            INNER JOIN anchor ON comments.post_id = anchor.post_id
+            WHERE comments.parent_id IS NOT NULL AND chain.distance < $2
+        )
+        SELECT comments.id, comments.post_id, comments.user_id,
+            CASE comments.removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                WHEN 'deleted' THEN '[deleted]'
+                ELSE comments.content
+            END AS "content!",
+            comments.timestamp, comments.parent_id,
+            comments.removal_kind AS "removal: RemovalKind", comments.score,
+            comments.edited_at,
+            author_flair(comments.user_id, comments.post_id) AS "author_flair: AuthorFlair"
+        FROM chain
+        INNER JOIN comments ON comments.id = chain.id
+        INNER JOIN anchor ON comments.post_id = anchor.post_id
+        INNER JOIN posts ON posts.id = comments.post_id
+        WHERE author_visible(comments.user_id, posts.sub, $3)
+        ORDER BY chain.distance DESC
+        "#,
+        comment_id,
+        limit,
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(comments)
+}
+
+/// Saves an edit as a new revision. `removal` is how the filters would store the new
+/// content; it doesn't replace a removal the comment already has.
 pub async fn update_comment(
     pool: &PgPool,
     comment_id: Uuid,
     new_comment: String,
+    removal: Option<RemovalKind>,
 ) -> Result<Uuid, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
     sqlx::query!(
         r#"
         UPDATE comments
-        SET content = $1
+        SET content = $1, edited_at = NOW(),
+            removed_at = CASE WHEN removal_kind IS NULL AND $3::TEXT IS NOT NULL
+                THEN NOW() ELSE removed_at END,
+            removal_kind = COALESCE(removal_kind, $3)
         WHERE id = $2
         "#,
         new_comment,
         comment_id,
+        removal.map(|removal| removal.to_string()),
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO comment_revisions (comment_id, revision, content)
+        SELECT $1, COALESCE(MAX(revision), 0) + 1, $2
+        FROM comment_revisions
+        WHERE comment_id = $1
+        "#,
+        comment_id,
+        new_comment,
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     Ok(comment_id)
 }
 
+pub async fn get_comment_revision(
+    pool: &PgPool,
+    comment_id: Uuid,
+    revision: i32,
+) -> Result<Revision, sqlx::Error> {
+    let revision = sqlx::query_as!(
+        Revision,
+        r#"
+        SELECT revision, content, created_at
+        FROM comment_revisions
+        WHERE comment_id = $1 AND revision = $2
+        "#,
+        comment_id,
+        revision
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(revision)
+}
+
+/// Every revision of the comment, oldest first; the last is its current content.
+pub async fn get_comment_revisions(
+    pool: &PgPool,
+    comment_id: Uuid,
+) -> Result<Vec<Revision>, sqlx::Error> {
+    let revisions = sqlx::query_as!(
+        Revision,
+        r#"
+        SELECT revision, content, created_at
+        FROM comment_revisions
+        WHERE comment_id = $1
+        ORDER BY revision ASC
+        "#,
+        comment_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(revisions)
+}
+
+/// Deletes the comment for its author, leaving a `[deleted]` tombstone so its replies
+/// keep their place in the thread. The content and its revisions are erased; an
+/// earlier moderator or legal removal keeps its kind.
 pub async fn delete_comment(pool: &PgPool, comment_id: Uuid) -> Result<Uuid, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
     sqlx::query!(
         r#"
-        DELETE FROM comments
+        UPDATE comments
+        SET content = '[deleted]',
+            removal_kind = CASE
+                WHEN removal_kind IN ('moderator', 'legal') THEN removal_kind
+                ELSE 'deleted'
+            END,
+            removed_at = COALESCE(removed_at, NOW()),
+            removed_by = COALESCE(removed_by, user_id)
         WHERE id = $1
         "#,
         comment_id,
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    sqlx::query!(
+        r#"
+        DELETE FROM comment_revisions
+        WHERE comment_id = $1
+        "#,
+        comment_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
     Ok(comment_id)
 }
+
+/// Records the user's vote on a comment, or withdraws it for `None`, and returns the
+/// new score. Works like `set_post_vote`, locking the comment row and updating the
+/// author's karma.
+pub async fn set_comment_vote(
+    pool: &PgPool,
+    comment_id: Uuid,
+    user_id: i32,
+    vote: Option<i16>,
+) -> Result<i32, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        SELECT id FROM comments
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        comment_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let previous = sqlx::query_scalar!(
+        r#"
+        DELETE FROM comment_votes
+        WHERE comment_id = $1 AND user_id = $2
+        RETURNING value
+        "#,
+        comment_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(value) = vote {
+        sqlx::query!(
+            r#"
+            INSERT INTO comment_votes (user_id, comment_id, value)
+            VALUES ($1, $2, $3)
+            "#,
+            user_id,
+            comment_id,
+            value
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let change = i32::from(vote.unwrap_or(0)) - i32::from(previous.unwrap_or(0));
+    let count = |value: i16| i32::from(vote == Some(value)) - i32::from(previous == Some(value));
+    let comment = sqlx::query!(
+        r#"
+        UPDATE comments
+        SET score = score + $2, upvotes = upvotes + $3, downvotes = downvotes + $4
+        WHERE id = $1
+        RETURNING score, user_id, (SELECT sub FROM posts WHERE posts.id = post_id) AS "sub!"
+        "#,
+        comment_id,
+        change,
+        count(1),
+        count(-1)
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if change != 0 {
+        karma_repo::add_karma(&mut tx, comment.user_id, &comment.sub, 0, change).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(comment.score)
+}
+
+/// The user's votes on the comments of a post, by comment id.
+pub async fn get_comment_votes(
+    pool: &PgPool,
+    post_id: Uuid,
+    user_id: i32,
+) -> Result<HashMap<Uuid, i16>, sqlx::Error> {
+    let votes = sqlx::query!(
+        r#"
+        SELECT comment_votes.comment_id, comment_votes.value
+        FROM comment_votes
+        JOIN comments ON comments.id = comment_votes.comment_id
+        WHERE comments.post_id = $1 AND comment_votes.user_id = $2
+        "#,
+        post_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(votes
+        .into_iter()
+        .map(|vote| (vote.comment_id, vote.value))
+        .collect())
+}
+
+#[async_trait]
+pub trait CommentRepository: Send + Sync {
+    async fn create_comment(&self, comment: &Comment) -> Result<Uuid, sqlx::Error>;
+    async fn get_comment(&self, comment_id: Uuid) -> Result<Comment, sqlx::Error>;
+    async fn get_comments_by_post(
+        &self,
+        post_id: Uuid,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Comment>, sqlx::Error>;
+    async fn get_comment_page(
+        &self,
+        post_id: Uuid,
+        sort: CommentSort,
+        after: Option<CommentCursor>,
+        limit: i64,
+        viewer: Option<i32>,
+    ) -> Result<Vec<RankedComment>, sqlx::Error>;
+    async fn count_comments(&self, post_id: Uuid, viewer: Option<i32>) -> Result<i64, sqlx::Error>;
+    async fn get_comment_media(
+        &self,
+        post_id: Uuid,
+    ) -> Result<HashMap<Uuid, Vec<Media>>, sqlx::Error>;
+    async fn get_comment_removal_reasons(
+        &self,
+        post_id: Uuid,
+    ) -> Result<HashMap<Uuid, String>, sqlx::Error>;
+    #[allow(clippy::too_many_arguments)]
+    async fn get_comment_tree(
+        &self,
+        post_id: Uuid,
+        parent_id: Option<Uuid>,
+        sort: CommentSort,
+        after: Option<Uuid>,
+        depth: i32,
+        width: i64,
+        viewer: Option<i32>,
+    ) -> Result<Vec<TreeComment>, sqlx::Error>;
+    async fn count_replies(
+        &self,
+        post_id: Uuid,
+        parent_id: Option<Uuid>,
+        sort: CommentSort,
+        after: Option<Uuid>,
+        viewer: Option<i32>,
+    ) -> Result<i64, sqlx::Error>;
+    async fn get_comment_ancestors(
+        &self,
+        comment_id: Uuid,
+        limit: i32,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Comment>, sqlx::Error>;
+    async fn update_comment(
+        &self,
+        comment_id: Uuid,
+        new_comment: String,
+        removal: Option<RemovalKind>,
+    ) -> Result<Uuid, sqlx::Error>;
+    async fn get_comment_revision(
+        &self,
+        comment_id: Uuid,
+        revision: i32,
+    ) -> Result<Revision, sqlx::Error>;
+    async fn get_comment_revisions(&self, comment_id: Uuid) -> Result<Vec<Revision>, sqlx::Error>;
+    async fn delete_comment(&self, comment_id: Uuid) -> Result<Uuid, sqlx::Error>;
+    async fn set_comment_vote(
+        &self,
+        comment_id: Uuid,
+        user_id: i32,
+        vote: Option<i16>,
+    ) -> Result<i32, sqlx::Error>;
+    async fn get_comment_votes(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+    ) -> Result<HashMap<Uuid, i16>, sqlx::Error>;
+    async fn save_comment(&self, comment_id: Uuid, user_id: i32) -> Result<(), sqlx::Error>;
+    async fn unsave_comment(&self, comment_id: Uuid, user_id: i32) -> Result<(), sqlx::Error>;
+}
+
+#[async_trait]
+impl CommentRepository for PgPool {
+    async fn create_comment(&self, comment: &Comment) -> Result<Uuid, sqlx::Error> {
+        create_comment(self, comment).await
+    }
+
+    async fn get_comment(&self, comment_id: Uuid) -> Result<Comment, sqlx::Error> {
+        get_comment(self, comment_id).await
+    }
+
+    async fn get_comments_by_post(
+        &self,
+        post_id: Uuid,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Comment>, sqlx::Error> {
+        get_comments_by_post(self, post_id, viewer).await
+    }
+
+    async fn get_comment_page(
+        &self,
+        post_id: Uuid,
+        sort: CommentSort,
+        after: Option<CommentCursor>,
+        limit: i64,
+        viewer: Option<i32>,
+    ) -> Result<Vec<RankedComment>, sqlx::Error> {
+        get_comment_page(self, post_id, sort, after, limit, viewer).await
+    }
+
+    async fn count_comments(&self, post_id: Uuid, viewer: Option<i32>) -> Result<i64, sqlx::Error> {
+        count_comments(self, post_id, viewer).await
+    }
+
+    async fn get_comment_media(
+        &self,
+        post_id: Uuid,
+    ) -> Result<HashMap<Uuid, Vec<Media>>, sqlx::Error> {
+        media_repo::get_comment_media(self, post_id).await
+    }
+
+    async fn get_comment_removal_reasons(
+        &self,
+        post_id: Uuid,
+    ) -> Result<HashMap<Uuid, String>, sqlx::Error> {
+        removal_reason_repo::get_comment_removal_reasons(self, post_id).await
+    }
+
+    async fn get_comment_tree(
+        &self,
+        post_id: Uuid,
+        parent_id: Option<Uuid>,
+        sort: CommentSort,
+        after: Option<Uuid>,
+        depth: i32,
+        width: i64,
+        viewer: Option<i32>,
+    ) -> Result<Vec<TreeComment>, sqlx::Error> {
+        get_comment_tree(self, post_id, parent_id, sort, after, depth, width, viewer).await
+    }
+
+    async fn count_replies(
+        &self,
+        post_id: Uuid,
+        parent_id: Option<Uuid>,
+        sort: CommentSort,
+        after: Option<Uuid>,
+        viewer: Option<i32>,
+    ) -> Result<i64, sqlx::Error> {
+        count_replies(self, post_id, parent_id, sort, after, viewer).await
+    }
+
+    async fn get_comment_ancestors(
+        &self,
+        comment_id: Uuid,
+        limit: i32,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Comment>, sqlx::Error> {
+        get_comment_ancestors(self, comment_id, limit, viewer).await
+    }
+
+    async fn update_comment(
+        &self,
+        comment_id: Uuid,
+        new_comment: String,
+        removal: Option<RemovalKind>,
+    ) -> Result<Uuid, sqlx::Error> {
+        update_comment(self, comment_id, new_comment, removal).await
+    }
+
+    async fn get_comment_revision(
+        &self,
+        comment_id: Uuid,
+        revision: i32,
+    ) -> Result<Revision, sqlx::Error> {
+        get_comment_revision(self, comment_id, revision).await
+    }
+
+    async fn get_comment_revisions(&self, comment_id: Uuid) -> Result<Vec<Revision>, sqlx::Error> {
+        get_comment_revisions(self, comment_id).await
+    }
+
+    async fn delete_comment(&self, comment_id: Uuid) -> Result<Uuid, sqlx::Error> {
+        delete_comment(self, comment_id).await
+    }
+
+    async fn set_comment_vote(
+        &self,
+        comment_id: Uuid,
+        user_id: i32,
+        vote: Option<i16>,
+    ) -> Result<i32, sqlx::Error> {
+        set_comment_vote(self, comment_id, user_id, vote).await
+    }
+
+    async fn get_comment_votes(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+    ) -> Result<HashMap<Uuid, i16>, sqlx::Error> {
+        get_comment_votes(self, post_id, user_id).await
+    }
+
+    async fn save_comment(&self, comment_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        saved_repo::save_comment(self, comment_id, user_id).await
+    }
+
+    async fn unsave_comment(&self, comment_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        saved_repo::unsave_comment(self, comment_id, user_id).await
+    }
+}
+
+/// The lower bound of the 95% Wilson score interval for a comment's share of upvotes,
+/// so one upvote ranks below ten upvotes and no downvotes. Copy of the `wilson_rank`
+/// SQL function for the in-memory repo, like `repo::post::hot_rank`.
+#[cfg(test)]
+pub fn wilson_rank(upvotes: i32, downvotes: i32) -> f64 {
+    const Z: f64 = 1.96;
+
+    let total = f64::from(upvotes + downvotes);
+    if total <= 0.0 {
+        return 0.0;
+    }
+
+    let share = f64::from(upvotes) / total;
+    let spread = Z * (share * (1.0 - share) / total + Z * Z / (4.0 * total * total)).sqrt();
+    (share + Z * Z / (2.0 * total) - spread) / (1.0 + Z * Z / total)
+}
+
+#[cfg(test)]
+mod comment_repo_tests {
+    use super::*;
+    use crate::repo::user as user_repo;
+    use crate::test_support::fixtures::{CommentFixture, PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[test]
+    fn test_wilson_rank_trusts_more_votes() {
+        assert_eq!(wilson_rank(0, 0), 0.0);
+        assert!(wilson_rank(10, 0) > wilson_rank(1, 0));
+        assert!(wilson_rank(100, 10) > wilson_rank(10, 1));
+        assert!(wilson_rank(5, 5) < wilson_rank(6, 4));
+        assert!((wilson_rank(1, 0) - 0.206_543).abs() < 1e-6);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_shadowbanned_comments_are_hidden_from_other_readers() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let troll = UserFixture::new("troll").insert(&db.pool).await;
+        let reader = UserFixture::new("reader").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let kept = CommentFixture::new(&post, &author).insert(&db.pool).await;
+        CommentFixture::new(&post, &troll).insert(&db.pool).await;
+        CommentFixture::new(&post, &troll)
+            .reply_to(&kept)
+            .insert(&db.pool)
+            .await;
+        user_repo::set_shadowbanned(&db.pool, troll.id, true)
+            .await
+            .unwrap();
+
+        let shown = get_comments_by_post(&db.pool, post.id, Some(reader.id))
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = shown.iter().map(|comment| comment.id).collect();
+        assert_eq!(ids, [kept.id]);
+        assert_eq!(count_comments(&db.pool, post.id, None).await.unwrap(), 1);
+        assert_eq!(
+            count_comments(&db.pool, post.id, Some(troll.id))
+                .await
+                .unwrap(),
+            3
+        );
+        let tree = get_comment_tree(
+            &db.pool,
+            post.id,
+            None,
+            CommentSort::New,
+            None,
+            2,
+            10,
+            Some(reader.id),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].reply_count, 0);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_ancestors_stay_on_the_comments_post() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let other = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let foreign = CommentFixture::new(&other, &author).insert(&db.pool).await;
+        let parent = CommentFixture::new(&post, &author).insert(&db.pool).await;
+        let reply = CommentFixture::new(&post, &author)
+            .reply_to(&parent)
+            .insert(&db.pool)
+            .await;
+        sqlx::query("UPDATE comments SET parent_id = $1 WHERE id = $2")
+            .bind(foreign.id)
+            .bind(parent.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let ancestors = get_comment_ancestors(&db.pool, reply.id, 10, None)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = ancestors.iter().map(|comment| comment.id).collect();
+        assert_eq!(ids, [parent.id]);
+
+        db.finish().await;
+    }
+}
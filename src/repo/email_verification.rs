@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Stores a verification link for `email`. Earlier links for the user stop working.
+pub async fn create_verification(
+    pool: &PgPool,
+    token_hash: &str,
+    user_id: i32,
+    email: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM email_verifications
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO email_verifications (token_hash, user_id, email, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        token_hash,
+        user_id,
+        email,
+        expires_at
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Uses up a verification link and marks the address verified. Returns the user, or
+/// `None` if the link is unknown, expired, or for an address the user no longer has.
+pub async fn verify_email(pool: &PgPool, token_hash: &str) -> Result<Option<i32>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let pending = sqlx::query!(
+        r#"
+        DELETE FROM email_verifications
+        WHERE token_hash = $1 AND expires_at > NOW()
+        RETURNING user_id, email
+        "#,
+        token_hash
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(pending) = pending else {
+        return Ok(None);
+    };
+
+    let verified = sqlx::query!(
+        r#"
+        UPDATE users
+        SET email_verified_at = NOW()
+        WHERE id = $1 AND email = $2
+        "#,
+        pending.user_id,
+        pending.email
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    Ok((verified > 0).then_some(pending.user_id))
+}
+
+#[cfg(test)]
+mod email_verification_repo_tests {
+    use super::*;
+    use crate::repo::user as user_repo;
+    use crate::test_support::fixtures::UserFixture;
+    use crate::test_support::TestDatabase;
+    use chrono::Duration;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_verification_link_works_once() {
+        let db = TestDatabase::new().await;
+        let user = UserFixture::new("reader")
+            .with_email("reader@example.com")
+            .insert(&db.pool)
+            .await;
+        let expires_at = Utc::now() + Duration::hours(1);
+        create_verification(&db.pool, "token", user.id, "reader@example.com", expires_at)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            verify_email(&db.pool, "token").await.unwrap(),
+            Some(user.id)
+        );
+        assert_eq!(verify_email(&db.pool, "token").await.unwrap(), None);
+        let user = user_repo::get_user_by_id(&db.pool, user.id).await.unwrap();
+        assert!(user.email_verified());
+
+        db.finish().await;
+    }
+}
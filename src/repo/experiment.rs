@@ -0,0 +1,122 @@
+use crate::model::experiment::{Experiment, ExperimentUpdate, NewExperiment, VariantExposures};
+use sqlx::PgPool;
+
+pub async fn create_experiment(
+    pool: &PgPool,
+    experiment: &NewExperiment,
+) -> Result<String, sqlx::Error> {
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO experiments (key, description, variants, ramp_percent)
+        VALUES ($1, $2, $3, $4)
+        RETURNING key
+        "#,
+        experiment.key,
+        experiment.description,
+        &experiment.variants,
+        experiment.ramp_percent
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(created.key)
+}
+
+pub async fn get_experiments(pool: &PgPool) -> Result<Vec<Experiment>, sqlx::Error> {
+    let experiments = sqlx::query_as!(
+        Experiment,
+        r#"
+        SELECT key, description, variants, ramp_percent, active, created_at, updated_at
+        FROM experiments
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(experiments)
+}
+
+pub async fn get_experiment(pool: &PgPool, key: &str) -> Result<Experiment, sqlx::Error> {
+    let experiment = sqlx::query_as!(
+        Experiment,
+        r#"
+        SELECT key, description, variants, ramp_percent, active, created_at, updated_at
+        FROM experiments
+        WHERE key = $1
+        "#,
+        key
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(experiment)
+}
+
+pub async fn update_experiment(
+    pool: &PgPool,
+    key: &str,
+    update: &ExperimentUpdate,
+) -> Result<Experiment, sqlx::Error> {
+    let experiment = sqlx::query_as!(
+        Experiment,
+        r#"
+        UPDATE experiments
+        SET ramp_percent = COALESCE($2, ramp_percent),
+            active = COALESCE($3, active),
+            updated_at = NOW()
+        WHERE key = $1
+        RETURNING key, description, variants, ramp_percent, active, created_at, updated_at
+        "#,
+        key,
+        update.ramp_percent,
+        update.active
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(experiment)
+}
+
+pub async fn log_exposure(
+    pool: &PgPool,
+    key: &str,
+    user_id: i32,
+    variant: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO experiment_exposures (experiment_key, user_id, variant)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (experiment_key, user_id) DO NOTHING
+        "#,
+        key,
+        user_id,
+        variant
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_exposure_counts(
+    pool: &PgPool,
+    key: &str,
+) -> Result<Vec<VariantExposures>, sqlx::Error> {
+    let counts = sqlx::query_as!(
+        VariantExposures,
+        r#"
+        SELECT variant, COUNT(*) AS "users!"
+        FROM experiment_exposures
+        WHERE experiment_key = $1
+        GROUP BY variant
+        ORDER BY variant
+        "#,
+        key
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(counts)
+}
@@ -0,0 +1,215 @@
+use crate::model::feed::CustomFeed;
+use sqlx::PgPool;
+
+/// Fails with a unique violation if the user already has a feed by that name.
+pub async fn create_custom_feed(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+    subs: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO custom_feeds (user_id, name)
+        VALUES ($1, $2)
+        "#,
+        user_id,
+        name
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO custom_feed_subs (user_id, feed, sub)
+        SELECT $1, $2, UNNEST($3::TEXT[])
+        "#,
+        user_id,
+        name,
+        subs
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// The user's feeds in name order.
+pub async fn get_custom_feeds(pool: &PgPool, user_id: i32) -> Result<Vec<CustomFeed>, sqlx::Error> {
+    let feeds = sqlx::query_as!(
+        CustomFeed,
+        r#"
+        SELECT name,
+            ARRAY(
+                SELECT sub FROM custom_feed_subs
+                WHERE custom_feed_subs.user_id = custom_feeds.user_id
+                AND custom_feed_subs.feed = custom_feeds.name
+                ORDER BY sub
+            ) AS "subs!",
+            created_at
+        FROM custom_feeds
+        WHERE user_id = $1
+        ORDER BY name
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(feeds)
+}
+
+pub async fn get_custom_feed(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+) -> Result<Option<CustomFeed>, sqlx::Error> {
+    let feed = sqlx::query_as!(
+        CustomFeed,
+        r#"
+        SELECT name,
+            ARRAY(
+                SELECT sub FROM custom_feed_subs
+                WHERE custom_feed_subs.user_id = custom_feeds.user_id
+                AND custom_feed_subs.feed = custom_feeds.name
+                ORDER BY sub
+            ) AS "subs!",
+            created_at
+        FROM custom_feeds
+        WHERE user_id = $1 AND name = $2
+        "#,
+        user_id,
+        name
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(feed)
+}
+
+/// Replaces the feed's subs. Returns whether the user has such a feed.
+pub async fn set_custom_feed_subs(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+    subs: &[String],
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let feed = sqlx::query_scalar!(
+        r#"
+        SELECT name
+        FROM custom_feeds
+        WHERE user_id = $1 AND name = $2
+        FOR UPDATE
+        "#,
+        user_id,
+        name
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if feed.is_none() {
+        return Ok(false);
+    }
+    sqlx::query!(
+        r#"
+        DELETE FROM custom_feed_subs
+        WHERE user_id = $1 AND feed = $2
+        "#,
+        user_id,
+        name
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO custom_feed_subs (user_id, feed, sub)
+        SELECT $1, $2, UNNEST($3::TEXT[])
+        "#,
+        user_id,
+        name,
+        subs
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Returns whether the user had such a feed.
+pub async fn delete_custom_feed(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM custom_feeds
+        WHERE user_id = $1 AND name = $2
+        "#,
+        user_id,
+        name
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod feed_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_custom_feeds_keep_their_subs() {
+        let db = TestDatabase::new().await;
+        let reader = UserFixture::new("reader").insert(&db.pool).await;
+        let rust = SubFixture::new("rust").insert(&db.pool).await;
+        let golang = SubFixture::new("golang").insert(&db.pool).await;
+        let subs = [rust.name.clone(), golang.name.clone()];
+
+        create_custom_feed(&db.pool, reader.id, "systems", &subs)
+            .await
+            .unwrap();
+        let duplicate = create_custom_feed(&db.pool, reader.id, "systems", &subs).await;
+        assert!(duplicate
+            .unwrap_err()
+            .as_database_error()
+            .is_some_and(|e| e.is_unique_violation()));
+        let feed = get_custom_feed(&db.pool, reader.id, "systems")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(feed.subs, ["golang", "rust"]);
+
+        assert!(
+            set_custom_feed_subs(&db.pool, reader.id, "systems", &subs[..1])
+                .await
+                .unwrap()
+        );
+        assert!(!set_custom_feed_subs(&db.pool, reader.id, "web", &subs)
+            .await
+            .unwrap());
+        let feeds = get_custom_feeds(&db.pool, reader.id).await.unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].subs, ["rust"]);
+
+        assert!(delete_custom_feed(&db.pool, reader.id, "systems")
+            .await
+            .unwrap());
+        assert!(get_custom_feeds(&db.pool, reader.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        db.finish().await;
+    }
+}
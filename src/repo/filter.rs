@@ -0,0 +1,177 @@
+use crate::model::comment::Comment;
+use crate::model::filter::{FilterAction, MatchKind, NewWordFilter, WordFilter};
+use crate::model::flair::AuthorFlair;
+use crate::model::moderation::RemovalKind;
+use crate::model::post::{Post, PostStatus};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn create_filter(
+    pool: &PgPool,
+    filter: &NewWordFilter,
+    created_by: i32,
+) -> Result<i64, sqlx::Error> {
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO word_filters (sub, pattern, match_kind, locale, action, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+        filter.sub,
+        filter.pattern,
+        filter.match_kind.to_string(),
+        filter.locale,
+        filter.action.to_string(),
+        created_by
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(created.id)
+}
+
+/// The site-wide filters, plus those of `sub` if given.
+pub async fn get_filters(pool: &PgPool, sub: Option<&str>) -> Result<Vec<WordFilter>, sqlx::Error> {
+    let filters = sqlx::query_as!(
+        WordFilter,
+        r#"
+        SELECT id, sub, pattern, match_kind AS "match_kind: MatchKind", locale,
+            action AS "action: FilterAction", created_by, created_at
+        FROM word_filters
+        WHERE sub IS NULL OR sub = $1
+        ORDER BY id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(filters)
+}
+
+pub async fn get_filter(pool: &PgPool, filter_id: i64) -> Result<Option<WordFilter>, sqlx::Error> {
+    let filter = sqlx::query_as!(
+        WordFilter,
+        r#"
+        SELECT id, sub, pattern, match_kind AS "match_kind: MatchKind", locale,
+            action AS "action: FilterAction", created_by, created_at
+        FROM word_filters
+        WHERE id = $1
+        "#,
+        filter_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(filter)
+}
+
+pub async fn delete_filter(
+    pool: &PgPool,
+    filter_id: i64,
+) -> Result<Option<WordFilter>, sqlx::Error> {
+    let deleted = sqlx::query_as!(
+        WordFilter,
+        r#"
+        DELETE FROM word_filters
+        WHERE id = $1
+        RETURNING id, sub, pattern, match_kind AS "match_kind: MatchKind", locale,
+            action AS "action: FilterAction", created_by, created_at
+        "#,
+        filter_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(deleted)
+}
+
+/// Posts held by a `queue` filter, oldest first, with their original content. Given
+/// `moderator_id`, only posts in the subs they moderate.
+pub async fn get_held_posts(
+    pool: &PgPool,
+    moderator_id: Option<i32>,
+) -> Result<Vec<Post>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        Post,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        WHERE posts.removal_kind = 'filter'
+        AND ($1::INTEGER IS NULL OR posts.sub IN (
+            SELECT sub FROM sub_moderators WHERE user_id = $1
+        ))
+        ORDER BY posts.timestamp ASC
+        "#,
+        moderator_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts)
+}
+
+pub async fn get_held_comments(
+    pool: &PgPool,
+    moderator_id: Option<i32>,
+) -> Result<Vec<Comment>, sqlx::Error> {
+    let comments = sqlx::query_as!(
+        Comment,
+        r#"
+        SELECT id, post_id, user_id, content, timestamp, parent_id,
+            removal_kind AS "removal: RemovalKind", score, edited_at,
+            author_flair(user_id, post_id) AS "author_flair: AuthorFlair"
+        FROM comments
+        WHERE removal_kind = 'filter'
+        AND ($1::INTEGER IS NULL OR post_id IN (
+            SELECT posts.id FROM posts
+            INNER JOIN sub_moderators ON sub_moderators.sub = posts.sub
+            WHERE sub_moderators.user_id = $1
+        ))
+        ORDER BY timestamp ASC
+        "#,
+        moderator_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(comments)
+}
+
+/// Publishes a held post. Returns `false` if it wasn't held.
+pub async fn approve_post(pool: &PgPool, post_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE posts
+        SET removed_at = NULL, removed_by = NULL, removal_kind = NULL
+        WHERE id = $1 AND removal_kind = 'filter'
+        "#,
+        post_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Publishes a held comment. Returns `false` if it wasn't held.
+pub async fn approve_comment(pool: &PgPool, comment_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE comments
+        SET removed_at = NULL, removed_by = NULL, removal_kind = NULL
+        WHERE id = $1 AND removal_kind = 'filter'
+        "#,
+        comment_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,361 @@
+use crate::model::flair::{Flair, UserFlair};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn create_flair(
+    pool: &PgPool,
+    sub: &str,
+    text: &str,
+    color: &str,
+    mod_only: bool,
+) -> Result<Flair, sqlx::Error> {
+    let flair = sqlx::query_as!(
+        Flair,
+        r#"
+        INSERT INTO flairs (sub, text, color, mod_only)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, sub, text, color, mod_only
+        "#,
+        sub,
+        text,
+        color,
+        mod_only
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(flair)
+}
+
+pub async fn get_flairs(pool: &PgPool, sub: &str) -> Result<Vec<Flair>, sqlx::Error> {
+    let flairs = sqlx::query_as!(
+        Flair,
+        r#"
+        SELECT id, sub, text, color, mod_only
+        FROM flairs
+        WHERE sub = $1
+        ORDER BY id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(flairs)
+}
+
+/// The flair, if it belongs to `sub`.
+pub async fn get_flair(
+    pool: &PgPool,
+    sub: &str,
+    flair_id: i32,
+) -> Result<Option<Flair>, sqlx::Error> {
+    let flair = sqlx::query_as!(
+        Flair,
+        r#"
+        SELECT id, sub, text, color, mod_only
+        FROM flairs
+        WHERE id = $1 AND sub = $2
+        "#,
+        flair_id,
+        sub
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(flair)
+}
+
+/// Replaces the flair's fields; `None` if it doesn't belong to `sub`.
+pub async fn update_flair(pool: &PgPool, flair: &Flair) -> Result<Option<Flair>, sqlx::Error> {
+    let updated = sqlx::query_as!(
+        Flair,
+        r#"
+        UPDATE flairs
+        SET text = $3, color = $4, mod_only = $5
+        WHERE id = $1 AND sub = $2
+        RETURNING id, sub, text, color, mod_only
+        "#,
+        flair.id,
+        flair.sub,
+        flair.text,
+        flair.color,
+        flair.mod_only
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(updated)
+}
+
+/// Posts wearing the flair lose it. Returns whether the flair belonged to `sub`.
+pub async fn delete_flair(pool: &PgPool, sub: &str, flair_id: i32) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM flairs
+        WHERE id = $1 AND sub = $2
+        "#,
+        flair_id,
+        sub
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+pub async fn set_post_flair(
+    pool: &PgPool,
+    post_id: Uuid,
+    flair_id: Option<i32>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE posts
+        SET flair_id = $2
+        WHERE id = $1
+        "#,
+        post_id,
+        flair_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn create_user_flair(
+    pool: &PgPool,
+    sub: &str,
+    text: &str,
+    color: &str,
+) -> Result<UserFlair, sqlx::Error> {
+    let flair = sqlx::query_as!(
+        UserFlair,
+        r#"
+        INSERT INTO user_flairs (sub, text, color)
+        VALUES ($1, $2, $3)
+        RETURNING id, sub, text, color
+        "#,
+        sub,
+        text,
+        color
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(flair)
+}
+
+pub async fn get_user_flairs(pool: &PgPool, sub: &str) -> Result<Vec<UserFlair>, sqlx::Error> {
+    let flairs = sqlx::query_as!(
+        UserFlair,
+        r#"
+        SELECT id, sub, text, color
+        FROM user_flairs
+        WHERE sub = $1
+        ORDER BY id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(flairs)
+}
+
+/// The user flair, if it belongs to `sub`.
+pub async fn get_user_flair(
+    pool: &PgPool,
+    sub: &str,
+    flair_id: i32,
+) -> Result<Option<UserFlair>, sqlx::Error> {
+    let flair = sqlx::query_as!(
+        UserFlair,
+        r#"
+        SELECT id, sub, text, color
+        FROM user_flairs
+        WHERE id = $1 AND sub = $2
+        "#,
+        flair_id,
+        sub
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(flair)
+}
+
+/// Replaces the user flair's text and color; `None` if it doesn't belong to `sub`.
+pub async fn update_user_flair(
+    pool: &PgPool,
+    flair: &UserFlair,
+) -> Result<Option<UserFlair>, sqlx::Error> {
+    let updated = sqlx::query_as!(
+        UserFlair,
+        r#"
+        UPDATE user_flairs
+        SET text = $3, color = $4
+        WHERE id = $1 AND sub = $2
+        RETURNING id, sub, text, color
+        "#,
+        flair.id,
+        flair.sub,
+        flair.text,
+        flair.color
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(updated)
+}
+
+/// Users who picked the flair are left without one. Returns whether the flair
+/// belonged to `sub`.
+pub async fn delete_user_flair(
+    pool: &PgPool,
+    sub: &str,
+    flair_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM user_flairs
+        WHERE id = $1 AND sub = $2
+        "#,
+        flair_id,
+        sub
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+/// Sets the user's flair in `sub`, or clears it for `None`.
+pub async fn choose_user_flair(
+    pool: &PgPool,
+    user_id: i32,
+    sub: &str,
+    flair_id: Option<i32>,
+) -> Result<(), sqlx::Error> {
+    match flair_id {
+        Some(flair_id) => {
+            sqlx::query!(
+                r#"
+                INSERT INTO user_flair_choices (user_id, sub, flair_id)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, sub) DO UPDATE SET flair_id = EXCLUDED.flair_id
+                "#,
+                user_id,
+                sub,
+                flair_id
+            )
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query!(
+                r#"
+                DELETE FROM user_flair_choices
+                WHERE user_id = $1 AND sub = $2
+                "#,
+                user_id,
+                sub
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod flair_repo_tests {
+    use super::*;
+    use crate::model::post::{Listing, PostSort};
+    use crate::repo::post as post_repo;
+    use crate::test_support::fixtures::{PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_listings_filter_by_flair_until_it_is_deleted() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let flaired = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let flair = create_flair(&db.pool, "rust", "Question", "#ff8800", false)
+            .await
+            .unwrap();
+        set_post_flair(&db.pool, flaired.id, Some(flair.id))
+            .await
+            .unwrap();
+        // Narrowed listings include pinned posts in their place
+        post_repo::pin_post(&db.pool, flaired.id, None, 2)
+            .await
+            .unwrap();
+
+        let listing = Listing {
+            flair: Some(flair.id),
+            ..Listing::first_page(PostSort::New, 10)
+        };
+        let listed = post_repo::get_posts_by_sub(&db.pool, "rust", false, &listing)
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].post.id, flaired.id);
+        assert_eq!(listed[0].post.flair_id, Some(flair.id));
+
+        assert!(delete_flair(&db.pool, "rust", flair.id).await.unwrap());
+        assert!(post_repo::get_post(&db.pool, flaired.id)
+            .await
+            .unwrap()
+            .flair_id
+            .is_none());
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_chosen_user_flair_is_shown_in_its_sub() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let rust = SubFixture::new("rust").insert(&db.pool).await;
+        let go = SubFixture::new("go").insert(&db.pool).await;
+        let in_rust = PostFixture::new(&rust, &author).insert(&db.pool).await;
+        let in_go = PostFixture::new(&go, &author).insert(&db.pool).await;
+        let flair = create_user_flair(&db.pool, "rust", "Core team", "#b7410e")
+            .await
+            .unwrap();
+
+        choose_user_flair(&db.pool, author.id, "rust", Some(flair.id))
+            .await
+            .unwrap();
+        let shown = post_repo::get_post(&db.pool, in_rust.id)
+            .await
+            .unwrap()
+            .author_flair
+            .unwrap();
+        assert_eq!(shown.text, "Core team");
+        assert_eq!(shown.color, "#b7410e");
+        assert!(post_repo::get_post(&db.pool, in_go.id)
+            .await
+            .unwrap()
+            .author_flair
+            .is_none());
+
+        choose_user_flair(&db.pool, author.id, "rust", None)
+            .await
+            .unwrap();
+        assert!(post_repo::get_post(&db.pool, in_rust.id)
+            .await
+            .unwrap()
+            .author_flair
+            .is_none());
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,183 @@
+use crate::model::karma::SubKarma;
+use sqlx::PgPool;
+
+/// Adds a vote's effect to the author's karma totals and to their karma in the sub.
+/// Called from the vote transactions so karma can't drift from the scores.
+pub async fn add_karma(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+    sub: &str,
+    post_change: i32,
+    comment_change: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET post_karma = post_karma + $2, comment_karma = comment_karma + $3
+        WHERE id = $1
+        "#,
+        user_id,
+        post_change,
+        comment_change
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_sub_karma (user_id, sub, post_karma, comment_karma)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id, sub) DO UPDATE
+        SET post_karma = user_sub_karma.post_karma + EXCLUDED.post_karma,
+            comment_karma = user_sub_karma.comment_karma + EXCLUDED.comment_karma
+        "#,
+        user_id,
+        sub,
+        post_change,
+        comment_change
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// The user's karma in each sub, leaving out private subs `viewer` isn't a member of.
+pub async fn get_sub_karma(
+    pool: &PgPool,
+    user_id: i32,
+    viewer: Option<i32>,
+) -> Result<Vec<SubKarma>, sqlx::Error> {
+    let subs = sqlx::query_as!(
+        SubKarma,
+        r#"
+        SELECT user_sub_karma.sub, user_sub_karma.post_karma, user_sub_karma.comment_karma
+        FROM user_sub_karma
+        INNER JOIN subs ON subs.name = user_sub_karma.sub
+        WHERE user_sub_karma.user_id = $1
+        AND (user_sub_karma.post_karma <> 0 OR user_sub_karma.comment_karma <> 0)
+        AND (subs.visibility <> 'private' OR sub_member(subs.name, $2))
+        ORDER BY user_sub_karma.post_karma + user_sub_karma.comment_karma DESC,
+            user_sub_karma.sub ASC
+        "#,
+        user_id,
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subs)
+}
+
+#[cfg(test)]
+mod karma_repo_tests {
+    use super::*;
+    use crate::model::audit::AuditQuery;
+    use crate::repo::{
+        audit as audit_repo, comment as comment_repo, post as post_repo, sub as sub_repo,
+        user as user_repo,
+    };
+    use crate::test_support::fixtures::{CommentFixture, PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_votes_move_the_authors_karma() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let voter = UserFixture::new("voter").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let comment = CommentFixture::new(&post, &author).insert(&db.pool).await;
+
+        post_repo::set_post_vote(&db.pool, post.id, voter.id, Some(1))
+            .await
+            .unwrap();
+        comment_repo::set_comment_vote(&db.pool, comment.id, voter.id, Some(1))
+            .await
+            .unwrap();
+        comment_repo::set_comment_vote(&db.pool, comment.id, voter.id, Some(-1))
+            .await
+            .unwrap();
+
+        let author = user_repo::get_user_by_id(&db.pool, author.id)
+            .await
+            .unwrap();
+        assert_eq!((author.post_karma, author.comment_karma), (1, -1));
+
+        let subs = get_sub_karma(&db.pool, author.id, None).await.unwrap();
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].sub, "rust");
+        assert_eq!((subs[0].post_karma, subs[0].comment_karma), (1, -1));
+
+        post_repo::set_post_vote(&db.pool, post.id, voter.id, None)
+            .await
+            .unwrap();
+        comment_repo::set_comment_vote(&db.pool, comment.id, voter.id, None)
+            .await
+            .unwrap();
+        assert!(get_sub_karma(&db.pool, author.id, None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_karma_changes_are_not_audited() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let voter = UserFixture::new("voter").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+
+        post_repo::set_post_vote(&db.pool, post.id, voter.id, Some(1))
+            .await
+            .unwrap();
+
+        let entries = audit_repo::get_audit_entries(
+            &db.pool,
+            &AuditQuery {
+                table: Some("users".to_string()),
+                row_key: Some(author.id.to_string()),
+                limit: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(entries.iter().all(|entry| entry.operation != "UPDATE"));
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_private_sub_karma_is_only_shown_to_members() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let voter = UserFixture::new("voter").insert(&db.pool).await;
+        let sub = SubFixture::new("secret").private().insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        sub_repo::approve_sub_member(&db.pool, "secret", author.id, author.id)
+            .await
+            .unwrap();
+
+        post_repo::set_post_vote(&db.pool, post.id, voter.id, Some(1))
+            .await
+            .unwrap();
+
+        assert!(get_sub_karma(&db.pool, author.id, Some(voter.id))
+            .await
+            .unwrap()
+            .is_empty());
+        let subs = get_sub_karma(&db.pool, author.id, Some(author.id))
+            .await
+            .unwrap();
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].sub, "secret");
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,290 @@
+use crate::model::legal::{
+    DocumentKind, NewTakedown, TakedownCase, TakedownDocument, TakedownItem, TakedownStatus,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn create_takedown(pool: &PgPool, takedown: &NewTakedown) -> Result<Uuid, sqlx::Error> {
+    let case_id = Uuid::new_v4();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO takedown_cases (id, complainant_name, complainant_contact, claim)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        case_id,
+        takedown.complainant_name,
+        takedown.complainant_contact,
+        takedown.claim,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO takedown_items (case_id, post_id)
+        SELECT $1, UNNEST($2::UUID[])
+        "#,
+        case_id,
+        &takedown.post_ids,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO takedown_items (case_id, comment_id)
+        SELECT $1, UNNEST($2::UUID[])
+        "#,
+        case_id,
+        &takedown.comment_ids,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO takedown_documents (case_id, kind, title, body)
+        VALUES ($1, 'notice', 'Takedown notice', $2)
+        "#,
+        case_id,
+        takedown.notice,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(case_id)
+}
+
+pub async fn get_takedown_case(pool: &PgPool, case_id: Uuid) -> Result<TakedownCase, sqlx::Error> {
+    let case = sqlx::query_as!(
+        TakedownCase,
+        r#"
+        SELECT id, status AS "status: TakedownStatus", complainant_name, complainant_contact,
+            claim, handled_by, created_at, updated_at
+        FROM takedown_cases
+        WHERE id = $1
+        "#,
+        case_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(case)
+}
+
+pub async fn get_takedown_cases(
+    pool: &PgPool,
+    status: Option<TakedownStatus>,
+) -> Result<Vec<TakedownCase>, sqlx::Error> {
+    let cases = sqlx::query_as!(
+        TakedownCase,
+        r#"
+        SELECT id, status AS "status: TakedownStatus", complainant_name, complainant_contact,
+            claim, handled_by, created_at, updated_at
+        FROM takedown_cases
+        WHERE ($1::TEXT IS NULL OR status = $1)
+        ORDER BY created_at DESC
+        "#,
+        status.map(|s| s.to_string())
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(cases)
+}
+
+pub async fn get_takedown_items(
+    pool: &PgPool,
+    case_id: Uuid,
+) -> Result<Vec<TakedownItem>, sqlx::Error> {
+    let items = sqlx::query_as!(
+        TakedownItem,
+        r#"
+        SELECT post_id, comment_id
+        FROM takedown_items
+        WHERE case_id = $1
+        "#,
+        case_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(items)
+}
+
+pub async fn get_takedown_documents(
+    pool: &PgPool,
+    case_id: Uuid,
+) -> Result<Vec<TakedownDocument>, sqlx::Error> {
+    let documents = sqlx::query_as!(
+        TakedownDocument,
+        r#"
+        SELECT id, kind AS "kind: DocumentKind", title, body, created_at
+        FROM takedown_documents
+        WHERE case_id = $1
+        ORDER BY created_at ASC, id ASC
+        "#,
+        case_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(documents)
+}
+
+pub async fn add_takedown_document(
+    pool: &PgPool,
+    case_id: Uuid,
+    kind: DocumentKind,
+    title: &str,
+    body: &str,
+) -> Result<i64, sqlx::Error> {
+    let document = sqlx::query!(
+        r#"
+        INSERT INTO takedown_documents (case_id, kind, title, body)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+        case_id,
+        kind.to_string(),
+        title,
+        body
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(document.id)
+}
+
+/// Marks every still-visible item of the case as removed for legal reasons.
+/// Items a moderator already removed keep their moderator removal.
+pub async fn apply_takedown(
+    pool: &PgPool,
+    case_id: Uuid,
+    admin_id: i32,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE posts
+        SET removed_at = NOW(), removed_by = $2, removal_kind = 'legal'
+        WHERE removal_kind IS NULL
+        AND id IN (SELECT post_id FROM takedown_items WHERE case_id = $1)
+        "#,
+        case_id,
+        admin_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE comments
+        SET removed_at = NOW(), removed_by = $2, removal_kind = 'legal'
+        WHERE removal_kind IS NULL
+        AND id IN (SELECT comment_id FROM takedown_items WHERE case_id = $1)
+        "#,
+        case_id,
+        admin_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    set_takedown_status(&mut tx, case_id, TakedownStatus::Actioned, admin_id).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn reject_takedown(
+    pool: &PgPool,
+    case_id: Uuid,
+    admin_id: i32,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_takedown_status(&mut tx, case_id, TakedownStatus::Rejected, admin_id).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Records the counter-notice and lifts the legal removals made for this case.
+pub async fn restore_takedown(
+    pool: &PgPool,
+    case_id: Uuid,
+    admin_id: i32,
+    title: &str,
+    body: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO takedown_documents (case_id, kind, title, body)
+        VALUES ($1, 'counter_notice', $2, $3)
+        "#,
+        case_id,
+        title,
+        body
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE posts
+        SET removed_at = NULL, removed_by = NULL, removal_kind = NULL
+        WHERE removal_kind = 'legal'
+        AND id IN (SELECT post_id FROM takedown_items WHERE case_id = $1)
+        "#,
+        case_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE comments
+        SET removed_at = NULL, removed_by = NULL, removal_kind = NULL
+        WHERE removal_kind = 'legal'
+        AND id IN (SELECT comment_id FROM takedown_items WHERE case_id = $1)
+        "#,
+        case_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    set_takedown_status(&mut tx, case_id, TakedownStatus::Restored, admin_id).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn set_takedown_status(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    case_id: Uuid,
+    status: TakedownStatus,
+    admin_id: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE takedown_cases
+        SET status = $2, handled_by = $3, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        case_id,
+        status.to_string(),
+        admin_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use crate::model::link_preview::LinkPreview;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn save_link_preview(
+    pool: &PgPool,
+    post_id: Uuid,
+    preview: &LinkPreview,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO link_previews (post_id, title, description, thumbnail_url, fetched_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (post_id) DO UPDATE
+        SET title = EXCLUDED.title, description = EXCLUDED.description,
+            thumbnail_url = EXCLUDED.thumbnail_url, fetched_at = EXCLUDED.fetched_at
+        "#,
+        post_id,
+        preview.title,
+        preview.description,
+        preview.thumbnail_url,
+        preview.fetched_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_link_preview(
+    pool: &PgPool,
+    post_id: Uuid,
+) -> Result<Option<LinkPreview>, sqlx::Error> {
+    let preview = sqlx::query_as!(
+        LinkPreview,
+        r#"
+        SELECT title, description, thumbnail_url, fetched_at
+        FROM link_previews
+        WHERE post_id = $1
+        "#,
+        post_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(preview)
+}
+
+/// Gives a crosspost the preview already fetched for its original, if there is one.
+pub async fn copy_link_preview(pool: &PgPool, from: Uuid, to: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO link_previews (post_id, title, description, thumbnail_url, fetched_at)
+        SELECT $2, title, description, thumbnail_url, fetched_at
+        FROM link_previews
+        WHERE post_id = $1
+        ON CONFLICT (post_id) DO NOTHING
+        "#,
+        from,
+        to
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
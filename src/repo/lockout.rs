@@ -0,0 +1,162 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// When the account's lockout ends, if it is locked now.
+pub async fn get_lockout(
+    pool: &PgPool,
+    user_id: i32,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let locked_until = sqlx::query_scalar!(
+        r#"
+        SELECT locked_until
+        FROM account_lockouts
+        WHERE user_id = $1 AND locked_until > NOW()
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(locked_until)
+}
+
+/// Records a failed password check. Once `threshold` failures fall within `window`
+/// the account is locked until `locked_until`, which is returned.
+pub async fn record_failure(
+    pool: &PgPool,
+    user_id: i32,
+    threshold: i64,
+    window_start: DateTime<Utc>,
+    locked_until: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM login_failures
+        WHERE user_id = $1 AND failed_at <= $2
+        "#,
+        user_id,
+        window_start
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO login_failures (user_id)
+        VALUES ($1)
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let failures = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "failures!"
+        FROM login_failures
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if failures < threshold {
+        tx.commit().await?;
+        return Ok(None);
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO account_lockouts (user_id, locked_until)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET locked_at = NOW(), locked_until = $2
+        "#,
+        user_id,
+        locked_until
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM login_failures
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(locked_until))
+}
+
+/// Forgets failed attempts after a successful login.
+pub async fn clear_failures(pool: &PgPool, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM login_failures
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Unlocks the account and forgets its failed attempts. Returns whether it was locked.
+pub async fn clear_lockout(pool: &PgPool, user_id: i32) -> Result<bool, sqlx::Error> {
+    clear_failures(pool, user_id).await?;
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM account_lockouts
+        WHERE user_id = $1 AND locked_until > NOW()
+        "#,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod lockout_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::UserFixture;
+    use crate::test_support::TestDatabase;
+    use chrono::Duration;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_third_failure_in_window_locks_account() {
+        let db = TestDatabase::new().await;
+        let user = UserFixture::new("target").insert(&db.pool).await;
+        let window_start = Utc::now() - Duration::minutes(15);
+        let locked_until = Utc::now() + Duration::minutes(15);
+
+        for _ in 0..2 {
+            let locked = record_failure(&db.pool, user.id, 3, window_start, locked_until)
+                .await
+                .unwrap();
+            assert_eq!(locked, None);
+        }
+        let locked = record_failure(&db.pool, user.id, 3, window_start, locked_until)
+            .await
+            .unwrap();
+        assert!(locked.is_some());
+        assert!(get_lockout(&db.pool, user.id).await.unwrap().is_some());
+
+        assert!(clear_lockout(&db.pool, user.id).await.unwrap());
+        assert_eq!(get_lockout(&db.pool, user.id).await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
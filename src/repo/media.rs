@@ -0,0 +1,273 @@
+use crate::model::media::{Media, Rendition, RenditionKind};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub async fn create_media(pool: &PgPool, media: &Media) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO media (id, user_id, storage_key, url, content_type, size_bytes, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        media.id,
+        media.user_id,
+        media.storage_key,
+        media.url,
+        media.content_type,
+        media.size_bytes,
+        media.created_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// How many of `media_ids` the user uploaded.
+pub async fn count_owned_media(
+    pool: &PgPool,
+    user_id: i32,
+    media_ids: &[Uuid],
+) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM media
+        WHERE user_id = $1 AND id = ANY($2)
+        "#,
+        user_id,
+        media_ids
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Attaches the media to the post in the order given.
+pub async fn attach_post_media(
+    pool: &PgPool,
+    post_id: Uuid,
+    media_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO post_media (post_id, media_id, position)
+        SELECT $1, attached.media_id, attached.position
+        FROM UNNEST($2::UUID[]) WITH ORDINALITY AS attached(media_id, position)
+        "#,
+        post_id,
+        media_ids
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_post_media(pool: &PgPool, post_id: Uuid) -> Result<Vec<Media>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT media.id, media.user_id, media.storage_key, media.url, media.content_type,
+            media.size_bytes, media.created_at
+        FROM post_media
+        INNER JOIN media ON media.id = post_media.media_id
+        WHERE post_media.post_id = $1
+        ORDER BY post_media.position
+        "#,
+        post_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+    let mut renditions = get_renditions(pool, &ids).await?;
+    let media = rows
+        .into_iter()
+        .map(|row| Media {
+            renditions: renditions.remove(&row.id).unwrap_or_default(),
+            id: row.id,
+            user_id: row.user_id,
+            storage_key: row.storage_key,
+            url: row.url,
+            content_type: row.content_type,
+            size_bytes: row.size_bytes,
+            created_at: row.created_at,
+        })
+        .collect();
+
+    Ok(media)
+}
+
+/// Attaches the media to the comment in the order given.
+pub async fn attach_comment_media(
+    pool: &PgPool,
+    comment_id: Uuid,
+    media_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO comment_media (comment_id, media_id, position)
+        SELECT $1, attached.media_id, attached.position
+        FROM UNNEST($2::UUID[]) WITH ORDINALITY AS attached(media_id, position)
+        "#,
+        comment_id,
+        media_ids
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The media of every comment on the post, by comment.
+pub async fn get_comment_media(
+    pool: &PgPool,
+    post_id: Uuid,
+) -> Result<HashMap<Uuid, Vec<Media>>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT comment_media.comment_id, media.id, media.user_id, media.storage_key, media.url,
+            media.content_type, media.size_bytes, media.created_at
+        FROM comment_media
+        INNER JOIN comments ON comments.id = comment_media.comment_id
+        INNER JOIN media ON media.id = comment_media.media_id
+        WHERE comments.post_id = $1
+        ORDER BY comment_media.comment_id, comment_media.position
+        "#,
+        post_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+    let renditions = get_renditions(pool, &ids).await?;
+    let mut media: HashMap<Uuid, Vec<Media>> = HashMap::new();
+    for row in rows {
+        media.entry(row.comment_id).or_default().push(Media {
+            // A file attached to several comments keeps its renditions for each
+            renditions: renditions.get(&row.id).cloned().unwrap_or_default(),
+            id: row.id,
+            user_id: row.user_id,
+            storage_key: row.storage_key,
+            url: row.url,
+            content_type: row.content_type,
+            size_bytes: row.size_bytes,
+            created_at: row.created_at,
+        });
+    }
+
+    Ok(media)
+}
+
+pub async fn save_rendition(
+    pool: &PgPool,
+    media_id: Uuid,
+    rendition: &Rendition,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO media_renditions (media_id, kind, storage_key, url, width, height)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (media_id, kind) DO UPDATE
+        SET storage_key = EXCLUDED.storage_key, url = EXCLUDED.url,
+            width = EXCLUDED.width, height = EXCLUDED.height
+        "#,
+        media_id,
+        rendition.kind.to_string(),
+        rendition.storage_key,
+        rendition.url,
+        rendition.width,
+        rendition.height
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The renditions of each of the media, smallest first.
+async fn get_renditions(
+    pool: &PgPool,
+    media_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<Rendition>>, sqlx::Error> {
+    if media_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT media_id, kind AS "kind: RenditionKind", storage_key, url, width, height
+        FROM media_renditions
+        WHERE media_id = ANY($1)
+        ORDER BY media_id, width * height
+        "#,
+        media_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut renditions: HashMap<Uuid, Vec<Rendition>> = HashMap::new();
+    for row in rows {
+        renditions.entry(row.media_id).or_default().push(Rendition {
+            kind: row.kind,
+            storage_key: row.storage_key,
+            url: row.url,
+            width: row.width,
+            height: row.height,
+        });
+    }
+
+    Ok(renditions)
+}
+
+#[cfg(test)]
+mod media_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+    use chrono::Utc;
+
+    fn upload(user_id: i32, name: &str) -> Media {
+        Media {
+            id: Uuid::new_v4(),
+            user_id,
+            storage_key: format!("{}.png", name),
+            url: format!("http://localhost/media/files/{}.png", name),
+            content_type: "image/png".to_string(),
+            size_bytes: 8,
+            created_at: Utc::now(),
+            renditions: Vec::new(),
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_post_media_keeps_attachment_order() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let other = UserFixture::new("other").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let (first, second) = (upload(author.id, "first"), upload(author.id, "second"));
+        let foreign = upload(other.id, "foreign");
+        for media in [&first, &second, &foreign] {
+            create_media(&db.pool, media).await.unwrap();
+        }
+
+        let ids = [second.id, first.id, foreign.id];
+        assert_eq!(
+            count_owned_media(&db.pool, author.id, &ids).await.unwrap(),
+            2
+        );
+        attach_post_media(&db.pool, post.id, &ids[..2])
+            .await
+            .unwrap();
+
+        let attached = get_post_media(&db.pool, post.id).await.unwrap();
+        let attached: Vec<Uuid> = attached.iter().map(|media| media.id).collect();
+        assert_eq!(attached, vec![second.id, first.id]);
+
+        db.finish().await;
+    }
+}
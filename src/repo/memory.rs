@@ -0,0 +1,1604 @@
+use crate::model::account_requirement::{AccountRequirement, Contribution};
+use crate::model::ban::SubBan;
+use crate::model::comment::{Comment, CommentCursor, CommentSort, RankedComment, TreeComment};
+use crate::model::link_preview::LinkPreview;
+use crate::model::media::Media;
+use crate::model::mod_note::ModNote;
+use crate::model::moderation::RemovalKind;
+use crate::model::poll::{Poll, PollOption};
+use crate::model::post::{Listing, Post, PostSort, PostStatus, RankedPost, POPULAR_MIN_SCORE};
+use crate::model::revision::Revision;
+use crate::model::sub::{
+    ModPermissions, Sub, SubJoinRequest, SubMember, SubModerator, SubModeratorInvite, SubSort,
+};
+use crate::model::user::{DbAddUser, User};
+use crate::repo::comment::{wilson_rank, CommentRepository};
+use crate::repo::post::{controversy_rank, hot_rank, PostRepository};
+use crate::repo::{sub::SubRepository, user::UserRepository};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// An in-memory stand-in for Postgres used by handler tests. It mirrors the visible
+/// behaviour of the SQL (tombstones, effective NSFW, listing filters) but not its
+/// constraints, so tests should only seed data the real schema would accept.
+#[derive(Default)]
+pub struct InMemoryRepo {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    users: Vec<User>,
+    subs: Vec<Sub>,
+    subscriptions: Vec<(i32, String)>,
+    sub_moderators: Vec<(String, SubModerator)>,
+    sub_moderator_invites: Vec<(String, SubModeratorInvite)>,
+    sub_members: Vec<(String, SubMember)>,
+    sub_join_requests: Vec<(String, SubJoinRequest)>,
+    sub_bans: Vec<(String, SubBan)>,
+    posts: Vec<Post>,
+    comments: Vec<Comment>,
+    post_votes: HashMap<(Uuid, i32), i16>,
+    comment_votes: HashMap<(Uuid, i32), i16>,
+    post_revisions: HashMap<Uuid, Vec<Revision>>,
+    comment_revisions: HashMap<Uuid, Vec<Revision>>,
+    polls: HashMap<Uuid, Poll>,
+    poll_votes: HashMap<(Uuid, i32), i32>,
+    link_previews: HashMap<Uuid, LinkPreview>,
+    saved_posts: HashSet<(Uuid, i32)>,
+    saved_comments: HashSet<(Uuid, i32)>,
+    hidden_posts: HashSet<(Uuid, i32)>,
+}
+
+impl State {
+    fn user_mut(&mut self, user_id: i32) -> Result<&mut User, sqlx::Error> {
+        self.users
+            .iter_mut()
+            .find(|user| user.id == user_id)
+            .ok_or(sqlx::Error::RowNotFound)
+    }
+
+    /// Keeps the stored user's derived `is_moderator` in step with `sub_moderators`.
+    fn refresh_is_moderator(&mut self, user_id: i32) -> Result<(), sqlx::Error> {
+        let moderates = self
+            .sub_moderators
+            .iter()
+            .any(|(_, moderator)| moderator.user_id == user_id);
+        self.user_mut(user_id)?.is_moderator = moderates;
+        Ok(())
+    }
+
+    /// As the SQL `sub_member`: approved members, the sub's moderators and admins.
+    fn is_sub_member(&self, sub_name: &str, viewer: Option<i32>) -> bool {
+        let Some(viewer) = viewer else {
+            return false;
+        };
+        self.sub_members
+            .iter()
+            .any(|(sub, member)| sub == sub_name && member.user_id == viewer)
+            || self
+                .sub_moderators
+                .iter()
+                .any(|(sub, moderator)| sub == sub_name && moderator.user_id == viewer)
+            || self
+                .users
+                .iter()
+                .any(|user| user.id == viewer && user.is_admin)
+    }
+
+    /// Whether posts in the sub may be listed for the viewer.
+    fn sub_readable_by(&self, sub_name: &str, viewer: Option<i32>) -> bool {
+        self.subs
+            .iter()
+            .find(|sub| sub.name == sub_name)
+            .is_none_or(|sub| sub.visibility.readable_by_anyone())
+            || self.is_sub_member(sub_name, viewer)
+    }
+
+    fn sub_is_nsfw(&self, sub_name: &str) -> bool {
+        self.subs.iter().any(|sub| sub.name == sub_name && sub.nsfw)
+    }
+
+    /// The post as `repo::post` returns it: effective NSFW flag applied.
+    fn visible_post(&self, post: &Post) -> Post {
+        Post {
+            nsfw: post.nsfw || self.sub_is_nsfw(&post.sub),
+            ..post.clone()
+        }
+    }
+
+    fn listed_posts(&self, include_nsfw: bool, filter: impl Fn(&Post) -> bool) -> Vec<Post> {
+        self.posts
+            .iter()
+            .filter(|post| {
+                post.removal.is_none() && post.status == PostStatus::Published && filter(post)
+            })
+            .map(|post| self.visible_post(post))
+            .filter(|post| include_nsfw || !post.nsfw)
+            .collect()
+    }
+
+    /// The comment's `comment_rank` under `sort`.
+    fn comment_rank(&self, sort: CommentSort, comment: &Comment) -> f64 {
+        let count = |value: i16| {
+            self.comment_votes
+                .iter()
+                .filter(|((voted_comment, _), vote)| {
+                    *voted_comment == comment.id && **vote == value
+                })
+                .count() as i32
+        };
+        let seconds = comment.timestamp.timestamp_micros() as f64 / 1_000_000.0;
+        match sort {
+            CommentSort::Best => wilson_rank(count(1), count(-1)),
+            CommentSort::Top => f64::from(comment.score),
+            CommentSort::New => seconds,
+            CommentSort::Old => -seconds,
+            CommentSort::Controversial => controversy_rank(count(1), count(-1)),
+        }
+    }
+
+    fn is_hidden(&self, post: &Post, hidden_for: Option<i32>) -> bool {
+        hidden_for.is_some_and(|user_id| self.hidden_posts.contains(&(post.id, user_id)))
+    }
+
+    /// Ranks and pages listed posts as the SQL listings do.
+    fn ranked_posts(&self, posts: Vec<Post>, listing: &Listing) -> Vec<RankedPost> {
+        let rank = |post: &Post| match listing.sort {
+            PostSort::Hot => hot_rank(post.score, post.timestamp),
+            PostSort::Top => f64::from(post.score),
+            PostSort::New => post.timestamp.timestamp_micros() as f64 / 1_000_000.0,
+            PostSort::Controversial => {
+                let count = |value: i16| {
+                    self.post_votes
+                        .iter()
+                        .filter(|((voted_post, _), vote)| *voted_post == post.id && **vote == value)
+                        .count() as i32
+                };
+                controversy_rank(count(1), count(-1))
+            }
+        };
+        let mut ranked: Vec<RankedPost> = posts
+            .into_iter()
+            .filter(|post| listing.since.is_none_or(|since| post.timestamp >= since))
+            .filter(|post| !self.is_hidden(post, listing.viewer))
+            .filter(|post| {
+                listing
+                    .flair
+                    .is_none_or(|flair| post.flair_id == Some(flair))
+            })
+            .map(|post| RankedPost {
+                rank: rank(&post),
+                post,
+            })
+            .filter(|ranked| {
+                listing
+                    .after
+                    .is_none_or(|after| (ranked.rank, ranked.post.id) < (after.rank, after.id))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.rank.total_cmp(&a.rank).then(b.post.id.cmp(&a.post.id)));
+        ranked.truncate(listing.limit.max(0) as usize + 1);
+        ranked
+    }
+}
+
+fn tombstone(content: &str, removal: Option<RemovalKind>) -> String {
+    match removal {
+        Some(RemovalKind::Legal) => "[removed for legal reasons]".to_string(),
+        Some(RemovalKind::Moderator) => "[removed]".to_string(),
+        Some(RemovalKind::Filter) => "[awaiting moderator review]".to_string(),
+        Some(RemovalKind::Deleted) => "[deleted]".to_string(),
+        None => content.to_string(),
+    }
+}
+
+/// Removed posts lose their title too, except ones their author deleted.
+fn title_tombstone(title: &str, removal: Option<RemovalKind>) -> String {
+    match removal {
+        Some(RemovalKind::Deleted) | None => title.to_string(),
+        Some(_) => tombstone(title, removal),
+    }
+}
+
+/// Deleting keeps an earlier moderator or legal removal's kind, as `delete_post` does.
+fn deleted_removal(removal: Option<RemovalKind>) -> RemovalKind {
+    match removal {
+        Some(kind @ (RemovalKind::Moderator | RemovalKind::Legal)) => kind,
+        _ => RemovalKind::Deleted,
+    }
+}
+
+fn with_subscribers(state: &State, sub: &Sub) -> Sub {
+    let subscribers = state
+        .subscriptions
+        .iter()
+        .filter(|(_, name)| *name == sub.name)
+        .count();
+    Sub {
+        subscribers: subscribers as i64,
+        ..sub.clone()
+    }
+}
+
+fn ban_in_force(ban: &SubBan) -> bool {
+    ban.expires_at
+        .is_none_or(|expires_at| expires_at > Utc::now())
+}
+
+fn push_revision(revisions: &mut HashMap<Uuid, Vec<Revision>>, id: Uuid, content: &str) {
+    let history = revisions.entry(id).or_default();
+    history.push(Revision {
+        revision: history.len() as i32 + 1,
+        content: content.to_string(),
+        created_at: Utc::now(),
+    });
+}
+
+fn find_revision(
+    revisions: &HashMap<Uuid, Vec<Revision>>,
+    id: Uuid,
+    revision: i32,
+) -> Result<Revision, sqlx::Error> {
+    revisions
+        .get(&id)
+        .and_then(|history| history.iter().find(|r| r.revision == revision))
+        .cloned()
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+impl InMemoryRepo {
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("in-memory repo lock poisoned")
+    }
+
+    /// The post's comments ranked under `sort`, in the order the SQL sorts them.
+    async fn ranked_comments(
+        &self,
+        post_id: Uuid,
+        sort: CommentSort,
+    ) -> Result<Vec<RankedComment>, sqlx::Error> {
+        let comments = self.get_comments_by_post(post_id, None).await?;
+        let state = self.state();
+        let mut ranked: Vec<RankedComment> = comments
+            .into_iter()
+            .map(|comment| RankedComment {
+                rank: state.comment_rank(sort, &comment),
+                comment,
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            b.rank
+                .total_cmp(&a.rank)
+                .then(b.comment.id.cmp(&a.comment.id))
+        });
+        Ok(ranked)
+    }
+
+    /// The post's comments in the order trees list siblings.
+    async fn tree_order(
+        &self,
+        post_id: Uuid,
+        sort: CommentSort,
+    ) -> Result<Vec<Comment>, sqlx::Error> {
+        let ranked = self.ranked_comments(post_id, sort).await?;
+        Ok(ranked.into_iter().map(|ranked| ranked.comment).collect())
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryRepo {
+    async fn create_user(&self, user: &DbAddUser) -> Result<i32, sqlx::Error> {
+        let mut state = self.state();
+        let id = state.users.len() as i32 + 1;
+        state.users.push(User {
+            id,
+            username: user.username.clone(),
+            password_hash: user.password_hash.clone(),
+            is_moderator: false,
+            is_admin: false,
+            created_at: user.created_at,
+            date_of_birth: None,
+            nsfw_acknowledged_at: None,
+            preferred_languages: Vec::new(),
+            is_premium: false,
+            email: user.email.clone(),
+            email_verified_at: user.email_verified_at,
+            post_karma: 0,
+            comment_karma: 0,
+            show_nsfw: true,
+        });
+
+        Ok(id)
+    }
+
+    async fn get_user_by_id(&self, user_id: i32) -> Result<User, sqlx::Error> {
+        self.state().user_mut(user_id).map(|user| user.clone())
+    }
+
+    async fn get_users_by_ids(&self, user_ids: &[i32]) -> Result<Vec<User>, sqlx::Error> {
+        Ok(self
+            .state()
+            .users
+            .iter()
+            .filter(|user| user_ids.contains(&user.id))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<User, sqlx::Error> {
+        self.username_exists(username)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)
+    }
+
+    async fn get_users_by_sub(&self, sub_name: &str) -> Result<Vec<User>, sqlx::Error> {
+        let state = self.state();
+        Ok(state
+            .users
+            .iter()
+            .filter(|user| {
+                state
+                    .subscriptions
+                    .iter()
+                    .any(|(user_id, sub)| *user_id == user.id && sub == sub_name)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn username_exists(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        Ok(self
+            .state()
+            .users
+            .iter()
+            .find(|user| user.username == username)
+            .cloned())
+    }
+
+    async fn update_user_password(&self, user_id: i32, new_hash: &str) -> Result<i32, sqlx::Error> {
+        self.state().user_mut(user_id)?.password_hash = new_hash.to_string();
+        Ok(user_id)
+    }
+
+    async fn set_date_of_birth(
+        &self,
+        user_id: i32,
+        date_of_birth: NaiveDate,
+    ) -> Result<i32, sqlx::Error> {
+        self.state().user_mut(user_id)?.date_of_birth = Some(date_of_birth);
+        Ok(user_id)
+    }
+
+    async fn acknowledge_nsfw(&self, user_id: i32) -> Result<i32, sqlx::Error> {
+        self.state().user_mut(user_id)?.nsfw_acknowledged_at = Some(Utc::now());
+        Ok(user_id)
+    }
+
+    async fn set_preferred_languages(
+        &self,
+        user_id: i32,
+        languages: &[String],
+    ) -> Result<i32, sqlx::Error> {
+        self.state().user_mut(user_id)?.preferred_languages = languages.to_vec();
+        Ok(user_id)
+    }
+
+    async fn set_show_nsfw(&self, user_id: i32, show_nsfw: bool) -> Result<i32, sqlx::Error> {
+        self.state().user_mut(user_id)?.show_nsfw = show_nsfw;
+        Ok(user_id)
+    }
+
+    async fn delete_user(&self, user_id: i32) -> Result<i32, sqlx::Error> {
+        self.state().users.retain(|user| user.id != user_id);
+        Ok(user_id)
+    }
+
+    /// Shadowbans need Postgres, so every author is visible here.
+    async fn author_visible(
+        &self,
+        _author_id: i32,
+        _sub: &str,
+        _viewer: Option<i32>,
+    ) -> Result<bool, sqlx::Error> {
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl SubRepository for InMemoryRepo {
+    async fn create_sub(&self, sub: &Sub) -> Result<String, sqlx::Error> {
+        self.state().subs.push(sub.clone());
+        Ok(sub.name.clone())
+    }
+
+    async fn sub_name_taken(&self, name: &str) -> Result<bool, sqlx::Error> {
+        Ok(self
+            .state()
+            .subs
+            .iter()
+            .any(|sub| sub.name.eq_ignore_ascii_case(name)))
+    }
+
+    async fn subscribe_user_to_sub(&self, user_id: i32, sub_name: &str) -> Result<(), sqlx::Error> {
+        let mut state = self.state();
+        let subscription = (user_id, sub_name.to_string());
+        if !state.subscriptions.contains(&subscription) {
+            state.subscriptions.push(subscription);
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe_user_from_sub(
+        &self,
+        user_id: i32,
+        sub_name: &str,
+    ) -> Result<(), sqlx::Error> {
+        self.state()
+            .subscriptions
+            .retain(|(id, name)| !(*id == user_id && name == sub_name));
+        Ok(())
+    }
+
+    async fn get_all_subs(
+        &self,
+        sort: SubSort,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Sub>, sqlx::Error> {
+        let state = self.state();
+        let mut subs: Vec<Sub> = state
+            .subs
+            .iter()
+            .filter(|sub| state.sub_readable_by(&sub.name, viewer))
+            .map(|sub| with_subscribers(&state, sub))
+            .collect();
+        subs.sort_by_key(|sub| sub.name.to_lowercase());
+        match sort {
+            SubSort::Name => {}
+            SubSort::New => subs.sort_by_key(|sub| std::cmp::Reverse(sub.created_at)),
+            SubSort::Top => subs.sort_by_key(|sub| std::cmp::Reverse(sub.subscribers)),
+        }
+        Ok(subs)
+    }
+
+    async fn get_sub_by_name(&self, name: &str) -> Result<Sub, sqlx::Error> {
+        let state = self.state();
+        state
+            .subs
+            .iter()
+            .find(|sub| sub.name == name)
+            .map(|sub| with_subscribers(&state, sub))
+            .ok_or(sqlx::Error::RowNotFound)
+    }
+
+    async fn get_subs_by_names(&self, names: &[String]) -> Result<Vec<Sub>, sqlx::Error> {
+        let state = self.state();
+        Ok(state
+            .subs
+            .iter()
+            .filter(|sub| names.contains(&sub.name))
+            .map(|sub| with_subscribers(&state, sub))
+            .collect())
+    }
+
+    async fn get_subs_by_user_id(&self, user_id: i32) -> Result<Vec<Sub>, sqlx::Error> {
+        let state = self.state();
+        Ok(state
+            .subs
+            .iter()
+            .filter(|sub| {
+                state
+                    .subscriptions
+                    .iter()
+                    .any(|(id, name)| *id == user_id && *name == sub.name)
+            })
+            .map(|sub| with_subscribers(&state, sub))
+            .collect())
+    }
+
+    async fn update_sub(&self, sub: &Sub) -> Result<(String, String), sqlx::Error> {
+        let mut state = self.state();
+        if let Some(existing) = state.subs.iter_mut().find(|s| s.name == sub.name) {
+            existing.description = sub.description.clone();
+            existing.nsfw = sub.nsfw;
+            existing.spoiler_by_default = sub.spoiler_by_default;
+            existing.visibility = sub.visibility;
+        }
+        Ok((sub.name.clone(), sub.description.clone()))
+    }
+
+    async fn delete_sub(&self, name: String) -> Result<(), sqlx::Error> {
+        let mut state = self.state();
+        state.subs.retain(|sub| sub.name != name);
+        let moderators: Vec<i32> = state
+            .sub_moderators
+            .iter()
+            .filter(|(sub, _)| *sub == name)
+            .map(|(_, moderator)| moderator.user_id)
+            .collect();
+        state.sub_moderators.retain(|(sub, _)| *sub != name);
+        state.sub_moderator_invites.retain(|(sub, _)| *sub != name);
+        state.sub_members.retain(|(sub, _)| *sub != name);
+        state.sub_join_requests.retain(|(sub, _)| *sub != name);
+        state.sub_bans.retain(|(sub, _)| *sub != name);
+        for user_id in moderators {
+            state.refresh_is_moderator(user_id)?;
+        }
+        Ok(())
+    }
+
+    async fn get_moderator_permissions(
+        &self,
+        user_id: i32,
+        sub: &str,
+    ) -> Result<Option<ModPermissions>, sqlx::Error> {
+        Ok(self
+            .state()
+            .sub_moderators
+            .iter()
+            .find(|(name, moderator)| name == sub && moderator.user_id == user_id)
+            .map(|(_, moderator)| moderator.permissions))
+    }
+
+    async fn add_sub_moderator(
+        &self,
+        sub: &str,
+        user_id: i32,
+        added_by: Option<i32>,
+        permissions: ModPermissions,
+    ) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        if state
+            .sub_moderators
+            .iter()
+            .any(|(name, moderator)| name == sub && moderator.user_id == user_id)
+        {
+            return Ok(false);
+        }
+        let username = state.user_mut(user_id)?.username.clone();
+        state.sub_moderators.push((
+            sub.to_string(),
+            SubModerator {
+                user_id,
+                username,
+                added_by,
+                added_at: Utc::now(),
+                permissions,
+            },
+        ));
+        state.refresh_is_moderator(user_id)?;
+        Ok(true)
+    }
+
+    async fn set_moderator_permissions(
+        &self,
+        sub: &str,
+        user_id: i32,
+        permissions: ModPermissions,
+    ) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        let moderator = state
+            .sub_moderators
+            .iter_mut()
+            .find(|(name, moderator)| name == sub && moderator.user_id == user_id);
+        Ok(moderator
+            .map(|(_, moderator)| moderator.permissions = permissions)
+            .is_some())
+    }
+
+    async fn remove_sub_moderator(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        let before = state.sub_moderators.len();
+        state
+            .sub_moderators
+            .retain(|(name, moderator)| !(name == sub && moderator.user_id == user_id));
+        let removed = state.sub_moderators.len() < before;
+        if removed {
+            state.refresh_is_moderator(user_id)?;
+        }
+        Ok(removed)
+    }
+
+    async fn get_sub_moderators(&self, sub: &str) -> Result<Vec<SubModerator>, sqlx::Error> {
+        Ok(self
+            .state()
+            .sub_moderators
+            .iter()
+            .filter(|(name, _)| name == sub)
+            .map(|(_, moderator)| moderator.clone())
+            .collect())
+    }
+
+    async fn invite_sub_moderator(
+        &self,
+        sub: &str,
+        user_id: i32,
+        invited_by: i32,
+        permissions: ModPermissions,
+    ) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        if state
+            .sub_moderator_invites
+            .iter()
+            .any(|(name, invite)| name == sub && invite.user_id == user_id)
+        {
+            return Ok(false);
+        }
+        let username = state.user_mut(user_id)?.username.clone();
+        state.sub_moderator_invites.push((
+            sub.to_string(),
+            SubModeratorInvite {
+                user_id,
+                username,
+                invited_by: Some(invited_by),
+                invited_at: Utc::now(),
+                permissions,
+            },
+        ));
+        Ok(true)
+    }
+
+    async fn get_sub_moderator_invites(
+        &self,
+        sub: &str,
+    ) -> Result<Vec<SubModeratorInvite>, sqlx::Error> {
+        Ok(self
+            .state()
+            .sub_moderator_invites
+            .iter()
+            .filter(|(name, _)| name == sub)
+            .map(|(_, invite)| invite.clone())
+            .collect())
+    }
+
+    async fn take_sub_moderator_invite(
+        &self,
+        sub: &str,
+        user_id: i32,
+    ) -> Result<Option<SubModeratorInvite>, sqlx::Error> {
+        let mut state = self.state();
+        let position = state
+            .sub_moderator_invites
+            .iter()
+            .position(|(name, invite)| name == sub && invite.user_id == user_id);
+        Ok(position.map(|index| state.sub_moderator_invites.remove(index).1))
+    }
+
+    async fn accept_sub_moderator_invite(
+        &self,
+        sub: &str,
+        user_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        let Some(position) = state
+            .sub_moderator_invites
+            .iter()
+            .position(|(name, invite)| name == sub && invite.user_id == user_id)
+        else {
+            return Ok(false);
+        };
+        let username = state.user_mut(user_id)?.username.clone();
+        let (_, invite) = state.sub_moderator_invites.remove(position);
+        let moderating = state
+            .sub_moderators
+            .iter()
+            .any(|(name, moderator)| name == sub && moderator.user_id == user_id);
+        if !moderating {
+            state.sub_moderators.push((
+                sub.to_string(),
+                SubModerator {
+                    user_id,
+                    username,
+                    added_by: invite.invited_by,
+                    added_at: Utc::now(),
+                    permissions: invite.permissions,
+                },
+            ));
+            state.refresh_is_moderator(user_id)?;
+        }
+        Ok(true)
+    }
+
+    async fn is_sub_member(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error> {
+        Ok(self
+            .state()
+            .sub_members
+            .iter()
+            .any(|(name, member)| name == sub && member.user_id == user_id))
+    }
+
+    async fn approve_sub_member(
+        &self,
+        sub: &str,
+        user_id: i32,
+        approved_by: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        if state
+            .sub_members
+            .iter()
+            .any(|(name, member)| name == sub && member.user_id == user_id)
+        {
+            return Ok(false);
+        }
+        let username = state.user_mut(user_id)?.username.clone();
+        state.sub_members.push((
+            sub.to_string(),
+            SubMember {
+                user_id,
+                username,
+                approved_by: Some(approved_by),
+                approved_at: Utc::now(),
+            },
+        ));
+        Ok(true)
+    }
+
+    async fn remove_sub_member(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        let before = state.sub_members.len();
+        state
+            .sub_members
+            .retain(|(name, member)| !(name == sub && member.user_id == user_id));
+        Ok(state.sub_members.len() < before)
+    }
+
+    async fn get_sub_members(&self, sub: &str) -> Result<Vec<SubMember>, sqlx::Error> {
+        let mut members: Vec<SubMember> = self
+            .state()
+            .sub_members
+            .iter()
+            .filter(|(name, _)| name == sub)
+            .map(|(_, member)| member.clone())
+            .collect();
+        members.reverse();
+        Ok(members)
+    }
+
+    async fn create_join_request(
+        &self,
+        sub: &str,
+        user_id: i32,
+        message: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        if state
+            .sub_join_requests
+            .iter()
+            .any(|(name, request)| name == sub && request.user_id == user_id)
+        {
+            return Ok(false);
+        }
+        let username = state.user_mut(user_id)?.username.clone();
+        state.sub_join_requests.push((
+            sub.to_string(),
+            SubJoinRequest {
+                user_id,
+                username,
+                message: message.to_string(),
+                requested_at: Utc::now(),
+            },
+        ));
+        Ok(true)
+    }
+
+    async fn get_join_requests(&self, sub: &str) -> Result<Vec<SubJoinRequest>, sqlx::Error> {
+        Ok(self
+            .state()
+            .sub_join_requests
+            .iter()
+            .filter(|(name, _)| name == sub)
+            .map(|(_, request)| request.clone())
+            .collect())
+    }
+
+    async fn take_join_request(
+        &self,
+        sub: &str,
+        user_id: i32,
+    ) -> Result<Option<SubJoinRequest>, sqlx::Error> {
+        let mut state = self.state();
+        let position = state
+            .sub_join_requests
+            .iter()
+            .position(|(name, request)| name == sub && request.user_id == user_id);
+        Ok(position.map(|index| state.sub_join_requests.remove(index).1))
+    }
+
+    async fn ban_user_from_sub(
+        &self,
+        sub: &str,
+        user_id: i32,
+        banned_by: i32,
+        reason: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), sqlx::Error> {
+        let mut state = self.state();
+        let username = state.user_mut(user_id)?.username.clone();
+        state
+            .sub_bans
+            .retain(|(name, ban)| !(name == sub && ban.user_id == user_id));
+        state.sub_bans.push((
+            sub.to_string(),
+            SubBan {
+                user_id,
+                username,
+                banned_by: Some(banned_by),
+                reason: reason.to_string(),
+                banned_at: Utc::now(),
+                expires_at,
+            },
+        ));
+        Ok(())
+    }
+
+    async fn get_sub_ban(&self, sub: &str, user_id: i32) -> Result<Option<SubBan>, sqlx::Error> {
+        Ok(self
+            .state()
+            .sub_bans
+            .iter()
+            .find(|(name, ban)| name == sub && ban.user_id == user_id && ban_in_force(ban))
+            .map(|(_, ban)| ban.clone()))
+    }
+
+    async fn get_sub_bans(&self, sub: &str) -> Result<Vec<SubBan>, sqlx::Error> {
+        let mut bans: Vec<SubBan> = self
+            .state()
+            .sub_bans
+            .iter()
+            .filter(|(name, ban)| name == sub && ban_in_force(ban))
+            .map(|(_, ban)| ban.clone())
+            .collect();
+        bans.reverse();
+        Ok(bans)
+    }
+
+    async fn unban_user_from_sub(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        let before = state.sub_bans.len();
+        state
+            .sub_bans
+            .retain(|(name, ban)| !(name == sub && ban.user_id == user_id && ban_in_force(ban)));
+        Ok(state.sub_bans.len() < before)
+    }
+
+    /// Account requirements need Postgres, so anyone may contribute here.
+    async fn get_account_requirements(
+        &self,
+        _sub: Option<&str>,
+        _contribution: Contribution,
+    ) -> Result<Vec<AccountRequirement>, sqlx::Error> {
+        Ok(Vec::new())
+    }
+
+    /// Mod notes need Postgres, so there are none here.
+    async fn get_mod_notes(
+        &self,
+        _sub: &str,
+        _user_ids: &[i32],
+    ) -> Result<Vec<ModNote>, sqlx::Error> {
+        Ok(Vec::new())
+    }
+}
+
+#[async_trait]
+impl PostRepository for InMemoryRepo {
+    async fn create_post(&self, post: &Post) -> Result<Uuid, sqlx::Error> {
+        let mut state = self.state();
+        push_revision(&mut state.post_revisions, post.id, &post.content);
+        state.posts.push(post.clone());
+        Ok(post.id)
+    }
+
+    async fn get_post(&self, post_id: Uuid) -> Result<Post, sqlx::Error> {
+        let state = self.state();
+        let post = state
+            .posts
+            .iter()
+            .find(|post| post.id == post_id)
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        Ok(Post {
+            title: title_tombstone(&post.title, post.removal),
+            content: tombstone(&post.content, post.removal),
+            link_url: post.link_url.clone().filter(|_| post.removal.is_none()),
+            ..state.visible_post(post)
+        })
+    }
+
+    async fn get_posts_by_ids(
+        &self,
+        post_ids: &[Uuid],
+        include_nsfw: bool,
+        _viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        let state = self.state();
+        Ok(state
+            .posts
+            .iter()
+            .filter(|post| post_ids.contains(&post.id) && post.status == PostStatus::Published)
+            .map(|post| Post {
+                title: title_tombstone(&post.title, post.removal),
+                content: tombstone(&post.content, post.removal),
+                link_url: post.link_url.clone().filter(|_| post.removal.is_none()),
+                ..state.visible_post(post)
+            })
+            .filter(|post| include_nsfw || !post.nsfw)
+            .collect())
+    }
+
+    async fn get_posts_by_sub(
+        &self,
+        sub_name: &str,
+        include_nsfw: bool,
+        listing: &Listing,
+    ) -> Result<Vec<RankedPost>, sqlx::Error> {
+        let state = self.state();
+        let posts = state.listed_posts(include_nsfw, |post| {
+            post.sub == sub_name && (post.pin_order.is_none() || listing.flair.is_some())
+        });
+        Ok(state.ranked_posts(posts, listing))
+    }
+
+    async fn get_pinned_posts(
+        &self,
+        sub_name: &str,
+        include_nsfw: bool,
+        hidden_for: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        let state = self.state();
+        let mut posts = state.listed_posts(include_nsfw, |post| {
+            post.sub == sub_name && post.pin_order.is_some() && !state.is_hidden(post, hidden_for)
+        });
+        posts.sort_by_key(|post| (post.pin_order, post.id));
+        Ok(posts)
+    }
+
+    async fn pin_post(
+        &self,
+        post_id: Uuid,
+        order: Option<i32>,
+        max_pins: i32,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let mut state = self.state();
+        let sub = state
+            .posts
+            .iter()
+            .find(|post| post.id == post_id)
+            .map(|post| post.sub.clone())
+            .ok_or(sqlx::Error::RowNotFound)?;
+        let pins: Vec<i32> = state
+            .posts
+            .iter()
+            .filter(|post| post.sub == sub && post.id != post_id && post.removal.is_none())
+            .filter_map(|post| post.pin_order)
+            .collect();
+        if pins.len() >= max_pins as usize {
+            return Ok(None);
+        }
+
+        let order = order.unwrap_or(pins.iter().max().unwrap_or(&0) + 1);
+        if let Some(post) = state.posts.iter_mut().find(|post| post.id == post_id) {
+            post.pin_order = Some(order);
+        }
+        Ok(Some(order))
+    }
+
+    async fn unpin_post(&self, post_id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        let pinned = state
+            .posts
+            .iter_mut()
+            .find(|post| post.id == post_id && post.pin_order.is_some());
+        Ok(pinned.is_some_and(|post| {
+            post.pin_order = None;
+            true
+        }))
+    }
+
+    async fn lock_post(&self, post_id: Uuid, reason: Option<&str>) -> Result<(), sqlx::Error> {
+        if let Some(post) = self
+            .state()
+            .posts
+            .iter_mut()
+            .find(|post| post.id == post_id)
+        {
+            post.locked = true;
+            post.lock_reason = reason.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    async fn set_post_flags(
+        &self,
+        post_id: Uuid,
+        nsfw: bool,
+        spoiler: bool,
+    ) -> Result<(), sqlx::Error> {
+        if let Some(post) = self
+            .state()
+            .posts
+            .iter_mut()
+            .find(|post| post.id == post_id)
+        {
+            post.nsfw = nsfw;
+            post.spoiler = spoiler;
+        }
+        Ok(())
+    }
+
+    async fn unlock_post(&self, post_id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        let locked = state
+            .posts
+            .iter_mut()
+            .find(|post| post.id == post_id && post.locked);
+        Ok(locked.is_some_and(|post| {
+            post.locked = false;
+            post.lock_reason = None;
+            true
+        }))
+    }
+
+    async fn create_poll(
+        &self,
+        post_id: Uuid,
+        options: &[String],
+        ends_at: Option<DateTime<Utc>>,
+        hide_results: bool,
+    ) -> Result<(), sqlx::Error> {
+        let mut state = self.state();
+        let first_id = state
+            .polls
+            .values()
+            .map(|poll| poll.options.len() as i32)
+            .sum::<i32>()
+            + 1;
+        let options = options
+            .iter()
+            .zip(first_id..)
+            .map(|(text, id)| PollOption {
+                id,
+                text: text.clone(),
+                votes: 0,
+            })
+            .collect();
+        state.polls.insert(
+            post_id,
+            Poll {
+                ends_at,
+                hide_results,
+                options,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_poll(&self, post_id: Uuid) -> Result<Option<Poll>, sqlx::Error> {
+        let state = self.state();
+        Ok(state.polls.get(&post_id).map(|poll| {
+            let mut poll = poll.clone();
+            for option in &mut poll.options {
+                option.votes = state
+                    .poll_votes
+                    .iter()
+                    .filter(|(&(post, _), &voted)| post == post_id && voted == option.id)
+                    .count() as i64;
+            }
+            poll
+        }))
+    }
+
+    async fn save_link_preview(
+        &self,
+        post_id: Uuid,
+        preview: &LinkPreview,
+    ) -> Result<(), sqlx::Error> {
+        self.state().link_previews.insert(post_id, preview.clone());
+        Ok(())
+    }
+
+    async fn get_link_preview(&self, post_id: Uuid) -> Result<Option<LinkPreview>, sqlx::Error> {
+        Ok(self.state().link_previews.get(&post_id).cloned())
+    }
+
+    /// Uploads need Postgres, so posts here never have media.
+    async fn get_post_media(&self, _post_id: Uuid) -> Result<Vec<Media>, sqlx::Error> {
+        Ok(Vec::new())
+    }
+
+    /// Removal reasons need Postgres, so removed posts here never have one.
+    async fn get_post_removal_reason(&self, _post_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+        Ok(None)
+    }
+
+    async fn get_poll_vote(&self, post_id: Uuid, user_id: i32) -> Result<Option<i32>, sqlx::Error> {
+        Ok(self.state().poll_votes.get(&(post_id, user_id)).copied())
+    }
+
+    async fn cast_poll_vote(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+        option_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        if state.poll_votes.contains_key(&(post_id, user_id)) {
+            return Ok(false);
+        }
+        state.poll_votes.insert((post_id, user_id), option_id);
+        Ok(true)
+    }
+
+    async fn get_posts_by_user(
+        &self,
+        user_id: i32,
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        let state = self.state();
+        let mut posts = state.listed_posts(include_nsfw, |post| {
+            post.user_id == user_id && state.sub_readable_by(&post.sub, viewer)
+        });
+        posts.sort_by_key(|post| std::cmp::Reverse(post.timestamp));
+        Ok(posts)
+    }
+
+    async fn get_crossposts(
+        &self,
+        post_id: Uuid,
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        let state = self.state();
+        let mut posts = state.listed_posts(include_nsfw, |post| {
+            post.crosspost_parent_id == Some(post_id) && state.sub_readable_by(&post.sub, viewer)
+        });
+        posts.sort_by_key(|post| std::cmp::Reverse(post.timestamp));
+        Ok(posts)
+    }
+
+    async fn get_drafts_by_user(&self, user_id: i32) -> Result<Vec<Post>, sqlx::Error> {
+        let state = self.state();
+        let mut drafts: Vec<Post> = state
+            .posts
+            .iter()
+            .filter(|post| post.user_id == user_id && post.status == PostStatus::Draft)
+            .map(|post| state.visible_post(post))
+            .collect();
+        drafts.sort_by_key(|post| std::cmp::Reverse(post.timestamp));
+        Ok(drafts)
+    }
+
+    async fn publish_post(&self, post_id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut state = self.state();
+        let draft = state
+            .posts
+            .iter_mut()
+            .find(|post| post.id == post_id && post.status == PostStatus::Draft);
+        Ok(draft.is_some_and(|post| {
+            post.status = PostStatus::Published;
+            post.timestamp = Utc::now();
+            post.publish_at = None;
+            true
+        }))
+    }
+
+    async fn get_all_posts(
+        &self,
+        include_nsfw: bool,
+        languages: &[String],
+        listing: &Listing,
+    ) -> Result<Vec<RankedPost>, sqlx::Error> {
+        let state = self.state();
+        let posts = state.listed_posts(include_nsfw, |post| {
+            state.sub_readable_by(&post.sub, listing.viewer)
+                && (languages.is_empty()
+                    || post
+                        .language
+                        .as_ref()
+                        .is_none_or(|language| languages.contains(language)))
+        });
+        Ok(state.ranked_posts(posts, listing))
+    }
+
+    async fn get_popular_posts(
+        &self,
+        include_nsfw: bool,
+        languages: &[String],
+        listing: &Listing,
+    ) -> Result<Vec<RankedPost>, sqlx::Error> {
+        let state = self.state();
+        let posts = state.listed_posts(include_nsfw, |post| {
+            state.sub_readable_by(&post.sub, None)
+                && post.score >= POPULAR_MIN_SCORE
+                && (languages.is_empty()
+                    || post
+                        .language
+                        .as_ref()
+                        .is_none_or(|language| languages.contains(language)))
+        });
+        Ok(state.ranked_posts(posts, listing))
+    }
+
+    async fn update_post(
+        &self,
+        post_id: Uuid,
+        update_content: String,
+        removal: Option<RemovalKind>,
+    ) -> Result<Uuid, sqlx::Error> {
+        let mut state = self.state();
+        if let Some(post) = state.posts.iter_mut().find(|post| post.id == post_id) {
+            post.content = update_content.clone();
+            post.edited_at = Some(Utc::now());
+            post.removal = post.removal.or(removal);
+            push_revision(&mut state.post_revisions, post_id, &update_content);
+        }
+        Ok(post_id)
+    }
+
+    async fn get_post_revision(
+        &self,
+        post_id: Uuid,
+        revision: i32,
+    ) -> Result<Revision, sqlx::Error> {
+        find_revision(&self.state().post_revisions, post_id, revision)
+    }
+
+    async fn delete_post(&self, post_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut state = self.state();
+        if let Some(post) = state.posts.iter_mut().find(|post| post.id == post_id) {
+            post.content = "[deleted]".to_string();
+            post.removal = Some(deleted_removal(post.removal));
+        }
+        state.post_revisions.remove(&post_id);
+        Ok(())
+    }
+
+    async fn set_post_vote(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+        vote: Option<i16>,
+    ) -> Result<i32, sqlx::Error> {
+        let mut state = self.state();
+        let index = state
+            .posts
+            .iter()
+            .position(|post| post.id == post_id)
+            .ok_or(sqlx::Error::RowNotFound)?;
+        let previous = match vote {
+            Some(value) => state.post_votes.insert((post_id, user_id), value),
+            None => state.post_votes.remove(&(post_id, user_id)),
+        };
+
+        let change = i32::from(vote.unwrap_or(0)) - i32::from(previous.unwrap_or(0));
+        let post = &mut state.posts[index];
+        post.score += change;
+        let (score, author_id) = (post.score, post.user_id);
+        if let Ok(author) = state.user_mut(author_id) {
+            author.post_karma += change;
+        }
+        Ok(score)
+    }
+
+    async fn save_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        self.state().saved_posts.insert((post_id, user_id));
+        Ok(())
+    }
+
+    async fn unsave_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        self.state().saved_posts.remove(&(post_id, user_id));
+        Ok(())
+    }
+
+    async fn get_saved_post_ids(
+        &self,
+        user_id: i32,
+        post_ids: &[Uuid],
+    ) -> Result<HashSet<Uuid>, sqlx::Error> {
+        let state = self.state();
+        Ok(post_ids
+            .iter()
+            .filter(|post_id| state.saved_posts.contains(&(**post_id, user_id)))
+            .copied()
+            .collect())
+    }
+
+    async fn hide_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        self.state().hidden_posts.insert((post_id, user_id));
+        Ok(())
+    }
+
+    async fn unhide_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        self.state().hidden_posts.remove(&(post_id, user_id));
+        Ok(())
+    }
+
+    async fn get_hidden_posts(
+        &self,
+        user_id: i32,
+        include_nsfw: bool,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        let state = self.state();
+        Ok(state
+            .posts
+            .iter()
+            .filter(|post| {
+                post.status == PostStatus::Published
+                    && state.hidden_posts.contains(&(post.id, user_id))
+            })
+            .map(|post| state.visible_post(post))
+            .filter(|post| include_nsfw || !post.nsfw)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl CommentRepository for InMemoryRepo {
+    async fn create_comment(&self, comment: &Comment) -> Result<Uuid, sqlx::Error> {
+        let mut state = self.state();
+        push_revision(&mut state.comment_revisions, comment.id, &comment.content);
+        state.comments.push(comment.clone());
+        Ok(comment.id)
+    }
+
+    async fn get_comment(&self, comment_id: Uuid) -> Result<Comment, sqlx::Error> {
+        self.state()
+            .comments
+            .iter()
+            .find(|comment| comment.id == comment_id)
+            .map(|comment| Comment {
+                content: tombstone(&comment.content, comment.removal),
+                ..comment.clone()
+            })
+            .ok_or(sqlx::Error::RowNotFound)
+    }
+
+    async fn get_comments_by_post(
+        &self,
+        post_id: Uuid,
+        _viewer: Option<i32>,
+    ) -> Result<Vec<Comment>, sqlx::Error> {
+        let mut comments: Vec<Comment> = self
+            .state()
+            .comments
+            .iter()
+            .filter(|comment| comment.post_id == post_id)
+            .map(|comment| Comment {
+                content: tombstone(&comment.content, comment.removal),
+                ..comment.clone()
+            })
+            .collect();
+        comments.sort_by_key(|comment| comment.timestamp);
+        Ok(comments)
+    }
+
+    async fn get_comment_page(
+        &self,
+        post_id: Uuid,
+        sort: CommentSort,
+        after: Option<CommentCursor>,
+        limit: i64,
+        _viewer: Option<i32>,
+    ) -> Result<Vec<RankedComment>, sqlx::Error> {
+        let mut ranked = self.ranked_comments(post_id, sort).await?;
+        ranked.retain(|ranked| {
+            after.is_none_or(|after| (ranked.rank, ranked.comment.id) < (after.rank, after.id))
+        });
+        ranked.truncate(limit.max(0) as usize + 1);
+        Ok(ranked)
+    }
+
+    async fn get_comment_tree(
+        &self,
+        post_id: Uuid,
+        parent_id: Option<Uuid>,
+        sort: CommentSort,
+        after: Option<Uuid>,
+        depth: i32,
+        width: i64,
+        _viewer: Option<i32>,
+    ) -> Result<Vec<TreeComment>, sqlx::Error> {
+        let comments = self.tree_order(post_id, sort).await?;
+        let replies_to = |parent: Option<Uuid>| {
+            comments
+                .iter()
+                .filter(move |comment| comment.parent_id == parent)
+        };
+
+        let mut tree = Vec::new();
+        let mut level: Vec<Option<Uuid>> = vec![parent_id];
+        for level_index in 0..depth {
+            let mut next = Vec::new();
+            for parent in level {
+                let siblings: Vec<&Comment> = match after.filter(|_| level_index == 0) {
+                    Some(after) => replies_to(parent)
+                        .skip_while(|comment| comment.id != after)
+                        .skip(1)
+                        .collect(),
+                    None => replies_to(parent).collect(),
+                };
+                for comment in siblings.into_iter().take(width.max(0) as usize) {
+                    next.push(Some(comment.id));
+                    tree.push(TreeComment {
+                        comment: comment.clone(),
+                        reply_count: replies_to(Some(comment.id)).count() as i64,
+                    });
+                }
+            }
+            level = next;
+        }
+        Ok(tree)
+    }
+
+    async fn count_replies(
+        &self,
+        post_id: Uuid,
+        parent_id: Option<Uuid>,
+        sort: CommentSort,
+        after: Option<Uuid>,
+        _viewer: Option<i32>,
+    ) -> Result<i64, sqlx::Error> {
+        let comments = self.tree_order(post_id, sort).await?;
+        let siblings = comments
+            .iter()
+            .filter(|comment| comment.parent_id == parent_id);
+        Ok(match after {
+            Some(after) => siblings
+                .skip_while(|comment| comment.id != after)
+                .skip(1)
+                .count(),
+            None => siblings.count(),
+        } as i64)
+    }
+
+    async fn count_comments(
+        &self,
+        post_id: Uuid,
+        _viewer: Option<i32>,
+    ) -> Result<i64, sqlx::Error> {
+        Ok(self
+            .state()
+            .comments
+            .iter()
+            .filter(|comment| comment.post_id == post_id)
+            .count() as i64)
+    }
+
+    /// Uploads need Postgres, so comments here never have media.
+    async fn get_comment_media(
+        &self,
+        _post_id: Uuid,
+    ) -> Result<HashMap<Uuid, Vec<Media>>, sqlx::Error> {
+        Ok(HashMap::new())
+    }
+
+    async fn get_comment_removal_reasons(
+        &self,
+        _post_id: Uuid,
+    ) -> Result<HashMap<Uuid, String>, sqlx::Error> {
+        Ok(HashMap::new())
+    }
+
+    async fn get_comment_ancestors(
+        &self,
+        comment_id: Uuid,
+        limit: i32,
+        _viewer: Option<i32>,
+    ) -> Result<Vec<Comment>, sqlx::Error> {
+        let mut ancestors = Vec::new();
+        let comment = self.get_comment(comment_id).await?;
+        let mut parent_id = comment.parent_id;
+        while let Some(id) = parent_id.filter(|_| ancestors.len() < limit.max(0) as usize) {
+            let parent = self.get_comment(id).await?;
+            if parent.post_id != comment.post_id {
+                break;
+            }
+            parent_id = parent.parent_id;
+            ancestors.push(parent);
+        }
+        ancestors.reverse();
+        Ok(ancestors)
+    }
+
+    async fn update_comment(
+        &self,
+        comment_id: Uuid,
+        new_comment: String,
+        removal: Option<RemovalKind>,
+    ) -> Result<Uuid, sqlx::Error> {
+        let mut state = self.state();
+        if let Some(comment) = state.comments.iter_mut().find(|c| c.id == comment_id) {
+            comment.content = new_comment.clone();
+            comment.edited_at = Some(Utc::now());
+            comment.removal = comment.removal.or(removal);
+            push_revision(&mut state.comment_revisions, comment_id, &new_comment);
+        }
+        Ok(comment_id)
+    }
+
+    async fn get_comment_revision(
+        &self,
+        comment_id: Uuid,
+        revision: i32,
+    ) -> Result<Revision, sqlx::Error> {
+        find_revision(&self.state().comment_revisions, comment_id, revision)
+    }
+
+    async fn get_comment_revisions(&self, comment_id: Uuid) -> Result<Vec<Revision>, sqlx::Error> {
+        Ok(self
+            .state()
+            .comment_revisions
+            .get(&comment_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn delete_comment(&self, comment_id: Uuid) -> Result<Uuid, sqlx::Error> {
+        let mut state = self.state();
+        if let Some(comment) = state.comments.iter_mut().find(|c| c.id == comment_id) {
+            comment.content = "[deleted]".to_string();
+            comment.removal = Some(deleted_removal(comment.removal));
+        }
+        state.comment_revisions.remove(&comment_id);
+        Ok(comment_id)
+    }
+
+    async fn set_comment_vote(
+        &self,
+        comment_id: Uuid,
+        user_id: i32,
+        vote: Option<i16>,
+    ) -> Result<i32, sqlx::Error> {
+        let mut state = self.state();
+        let index = state
+            .comments
+            .iter()
+            .position(|comment| comment.id == comment_id)
+            .ok_or(sqlx::Error::RowNotFound)?;
+        let previous = match vote {
+            Some(value) => state.comment_votes.insert((comment_id, user_id), value),
+            None => state.comment_votes.remove(&(comment_id, user_id)),
+        };
+
+        let change = i32::from(vote.unwrap_or(0)) - i32::from(previous.unwrap_or(0));
+        let comment = &mut state.comments[index];
+        comment.score += change;
+        let (score, author_id) = (comment.score, comment.user_id);
+        if let Ok(author) = state.user_mut(author_id) {
+            author.comment_karma += change;
+        }
+        Ok(score)
+    }
+
+    async fn get_comment_votes(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+    ) -> Result<HashMap<Uuid, i16>, sqlx::Error> {
+        let state = self.state();
+        Ok(state
+            .comments
+            .iter()
+            .filter(|comment| comment.post_id == post_id)
+            .filter_map(|comment| {
+                let vote = state.comment_votes.get(&(comment.id, user_id))?;
+                Some((comment.id, *vote))
+            })
+            .collect())
+    }
+
+    async fn save_comment(&self, comment_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        self.state().saved_comments.insert((comment_id, user_id));
+        Ok(())
+    }
+
+    async fn unsave_comment(&self, comment_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        self.state().saved_comments.remove(&(comment_id, user_id));
+        Ok(())
+    }
+}
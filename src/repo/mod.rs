@@ -1,4 +1,59 @@
+pub mod account_requirement;
+pub mod api_key;
+pub mod appeal;
+pub mod audit;
+pub mod automod;
 pub mod comment;
+pub mod email_verification;
+pub mod experiment;
+pub mod feed;
+pub mod filter;
+pub mod flair;
+pub mod karma;
+pub mod legal;
+pub mod link_preview;
+pub mod lockout;
+pub mod media;
+#[cfg(test)]
+pub mod memory;
+pub mod mod_note;
+pub mod moderation;
+pub mod modqueue;
+pub mod oauth;
+pub mod poll;
 pub mod post;
+pub mod post_template;
+pub mod premium;
+pub mod refresh_token;
+pub mod removal_reason;
+pub mod report;
+pub mod revocation;
+pub mod rule;
+pub mod saved;
+pub mod search;
+pub mod session;
+pub mod spam;
 pub mod sub;
+pub mod suspension;
 pub mod user;
+pub mod wiki;
+
+use actix_web::web::{Data, ServiceConfig};
+use comment::CommentRepository;
+use post::PostRepository;
+use std::sync::Arc;
+use sub::SubRepository;
+use user::UserRepository;
+
+/// Registers `repo` as the user, sub, post and comment repository so handlers can take
+/// `Data<dyn UserRepository>` and friends. The app passes its `PgPool`; handler tests
+/// pass a `memory::InMemoryRepo` instead.
+pub fn register<R>(cfg: &mut ServiceConfig, repo: Arc<R>)
+where
+    R: UserRepository + SubRepository + PostRepository + CommentRepository + 'static,
+{
+    cfg.app_data(Data::from(repo.clone() as Arc<dyn UserRepository>))
+        .app_data(Data::from(repo.clone() as Arc<dyn SubRepository>))
+        .app_data(Data::from(repo.clone() as Arc<dyn PostRepository>))
+        .app_data(Data::from(repo as Arc<dyn CommentRepository>));
+}
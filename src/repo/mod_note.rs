@@ -0,0 +1,134 @@
+use crate::model::mod_note::ModNote;
+use sqlx::PgPool;
+
+pub async fn create_mod_note(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+    author_id: i32,
+    note: &str,
+) -> Result<ModNote, sqlx::Error> {
+    let note = sqlx::query_as!(
+        ModNote,
+        r#"
+        WITH inserted AS (
+            INSERT INTO mod_notes (sub, user_id, author_id, note)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, sub, user_id, author_id, note, created_at
+        )
+        SELECT inserted.id, inserted.sub, inserted.user_id, inserted.author_id,
+            users.username AS "author_username?", inserted.note, inserted.created_at
+        FROM inserted
+        LEFT JOIN users ON users.id = inserted.author_id
+        "#,
+        sub,
+        user_id,
+        author_id,
+        note
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(note)
+}
+
+/// The sub's notes on any of the users, most recent first.
+pub async fn get_mod_notes(
+    pool: &PgPool,
+    sub: &str,
+    user_ids: &[i32],
+) -> Result<Vec<ModNote>, sqlx::Error> {
+    let notes = sqlx::query_as!(
+        ModNote,
+        r#"
+        SELECT mod_notes.id, mod_notes.sub, mod_notes.user_id, mod_notes.author_id,
+            users.username AS "author_username?", mod_notes.note, mod_notes.created_at
+        FROM mod_notes
+        LEFT JOIN users ON users.id = mod_notes.author_id
+        WHERE mod_notes.sub = $1 AND mod_notes.user_id = ANY($2)
+        ORDER BY mod_notes.created_at DESC, mod_notes.id DESC
+        "#,
+        sub,
+        user_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(notes)
+}
+
+/// Returns whether the sub had a note on the user with that id.
+pub async fn delete_mod_note(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+    note_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM mod_notes
+        WHERE id = $1 AND sub = $2 AND user_id = $3
+        "#,
+        note_id,
+        sub,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod mod_note_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_notes_are_kept_per_sub() {
+        let db = TestDatabase::new().await;
+        let troll = UserFixture::new("troll").insert(&db.pool).await;
+        let other = UserFixture::new("other").insert(&db.pool).await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let rust = SubFixture::new("rust").insert(&db.pool).await;
+        let go = SubFixture::new("go").insert(&db.pool).await;
+
+        let first = create_mod_note(&db.pool, &rust.name, troll.id, moderator.id, "Warned")
+            .await
+            .unwrap();
+        assert_eq!(first.author_username.as_deref(), Some("moderator"));
+        let second = create_mod_note(&db.pool, &rust.name, troll.id, moderator.id, "Again")
+            .await
+            .unwrap();
+        create_mod_note(&db.pool, &go.name, troll.id, moderator.id, "Elsewhere")
+            .await
+            .unwrap();
+        create_mod_note(&db.pool, &rust.name, other.id, moderator.id, "Someone else")
+            .await
+            .unwrap();
+
+        let notes = get_mod_notes(&db.pool, &rust.name, &[troll.id])
+            .await
+            .unwrap();
+        let ids: Vec<i64> = notes.iter().map(|note| note.id).collect();
+        assert_eq!(ids, [second.id, first.id]);
+
+        assert!(!delete_mod_note(&db.pool, &go.name, troll.id, first.id)
+            .await
+            .unwrap());
+        assert!(delete_mod_note(&db.pool, &rust.name, troll.id, first.id)
+            .await
+            .unwrap());
+        assert_eq!(
+            get_mod_notes(&db.pool, &rust.name, &[troll.id, other.id])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,416 @@
+use crate::model::moderation::{ModAction, ModLogEntry, ModLogQuery, NukeRequest, NukeSummary};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const NUKE_BATCH_SIZE: i64 = 500;
+const DEFAULT_MOD_LOG_LIMIT: i64 = 100;
+const MAX_MOD_LOG_LIMIT: i64 = 1000;
+
+pub async fn log_mod_action(
+    pool: &PgPool,
+    moderator_id: i32,
+    action: ModAction,
+    target_user_id: Option<i32>,
+    sub: Option<&str>,
+    details: Value,
+) -> Result<i64, sqlx::Error> {
+    let entry = sqlx::query!(
+        r#"
+        INSERT INTO mod_log (moderator_id, action, target_user_id, sub, details)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+        moderator_id,
+        action.to_string(),
+        target_user_id,
+        sub,
+        details
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(entry.id)
+}
+
+/// Given `moderator_scope`, only entries for the subs that moderator moderates, leaving
+/// out site-wide actions.
+pub async fn get_mod_log(
+    pool: &PgPool,
+    query: &ModLogQuery,
+    moderator_scope: Option<i32>,
+) -> Result<Vec<ModLogEntry>, sqlx::Error> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_MOD_LOG_LIMIT)
+        .clamp(1, MAX_MOD_LOG_LIMIT);
+
+    let entries = sqlx::query_as!(
+        ModLogEntry,
+        r#"
+        SELECT id, moderator_id, action, target_user_id, sub, details, created_at
+        FROM mod_log
+        WHERE ($1::INTEGER IS NULL OR moderator_id = $1)
+        AND ($2::INTEGER IS NULL OR target_user_id = $2)
+        AND ($3::TEXT IS NULL OR sub = $3)
+        AND ($4::INTEGER IS NULL OR sub IN (SELECT sub FROM sub_moderators WHERE user_id = $4))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $5
+        "#,
+        query.moderator_id,
+        query.target_user_id,
+        query.sub,
+        moderator_scope,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+/// Soft-removes every post and comment by `user_id` matching the request scope.
+/// Rows are removed in fixed-size batches, each committed on its own, so a
+/// prolific account doesn't hold locks on large parts of the content tables.
+pub async fn nuke_user_content(
+    pool: &PgPool,
+    user_id: i32,
+    moderator_id: i32,
+    request: &NukeRequest,
+) -> Result<NukeSummary, sqlx::Error> {
+    let mut posts_removed = 0;
+    loop {
+        let removed = sqlx::query!(
+            r#"
+            WITH batch AS (
+                SELECT id FROM posts
+                WHERE user_id = $1 AND removed_at IS NULL
+                AND ($2::TEXT IS NULL OR sub = $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR timestamp < $4)
+                LIMIT $5
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE posts
+            SET removed_at = NOW(), removed_by = $6, removal_kind = 'moderator'
+            FROM batch
+            WHERE posts.id = batch.id
+            "#,
+            user_id,
+            request.sub,
+            request.since,
+            request.until,
+            NUKE_BATCH_SIZE,
+            moderator_id
+        )
+        .execute(pool)
+        .await?
+        .rows_affected() as i64;
+
+        posts_removed += removed;
+        if removed < NUKE_BATCH_SIZE {
+            break;
+        }
+    }
+
+    let mut comments_removed = 0;
+    loop {
+        let removed = sqlx::query!(
+            r#"
+            WITH batch AS (
+                SELECT id FROM comments
+                WHERE user_id = $1 AND removed_at IS NULL
+                AND ($2::TEXT IS NULL OR post_id IN (SELECT id FROM posts WHERE sub = $2))
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR timestamp < $4)
+                LIMIT $5
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE comments
+            SET removed_at = NOW(), removed_by = $6, removal_kind = 'moderator'
+            FROM batch
+            WHERE comments.id = batch.id
+            "#,
+            user_id,
+            request.sub,
+            request.since,
+            request.until,
+            NUKE_BATCH_SIZE,
+            moderator_id
+        )
+        .execute(pool)
+        .await?
+        .rows_affected() as i64;
+
+        comments_removed += removed;
+        if removed < NUKE_BATCH_SIZE {
+            break;
+        }
+    }
+
+    log_mod_action(
+        pool,
+        moderator_id,
+        ModAction::NukeUserContent,
+        Some(user_id),
+        request.sub.as_deref(),
+        json!({
+            "posts_removed": posts_removed,
+            "comments_removed": comments_removed,
+            "since": request.since,
+            "until": request.until,
+        }),
+    )
+    .await?;
+
+    Ok(NukeSummary {
+        user_id,
+        posts_removed,
+        comments_removed,
+    })
+}
+
+/// Deletes a post outright, with its comments, votes and revisions, for content that
+/// mustn't survive even as a tombstone. Returns its author and sub, or `None` if there
+/// was no such post.
+pub async fn purge_post(
+    pool: &PgPool,
+    post_id: Uuid,
+) -> Result<Option<(i32, String)>, sqlx::Error> {
+    let purged = sqlx::query!(
+        r#"
+        DELETE FROM posts
+        WHERE id = $1
+        RETURNING user_id, sub
+        "#,
+        post_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(purged.map(|post| (post.user_id, post.sub)))
+}
+
+/// Deletes a comment outright, as for `purge_post`. Its replies would be left without
+/// a parent, so they are purged with it.
+pub async fn purge_comment(
+    pool: &PgPool,
+    comment_id: Uuid,
+) -> Result<Option<(i32, String)>, sqlx::Error> {
+    let purged = sqlx::query!(
+        r#"
+        DELETE FROM comments
+        WHERE id = $1
+        RETURNING user_id, (SELECT sub FROM posts WHERE posts.id = post_id) AS "sub!"
+        "#,
+        comment_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(purged.map(|comment| (comment.user_id, comment.sub)))
+}
+
+#[cfg(test)]
+mod moderation_repo_tests {
+    use super::*;
+    use crate::model::moderation::RemovalKind;
+    use crate::model::post::{Listing, PostSort};
+    use crate::model::search::SearchSort;
+    use crate::model::sub::ModPermissions;
+    use crate::repo::{
+        comment as comment_repo, filter as filter_repo, post as post_repo, search as search_repo,
+        sub as sub_repo,
+    };
+    use crate::test_support::fixtures::{CommentFixture, PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_nuke_removes_content_and_logs_action() {
+        let db = TestDatabase::new().await;
+        let moderator = UserFixture::new("mod").insert(&db.pool).await;
+        let spammer = UserFixture::new("spammer").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &moderator).insert(&db.pool).await;
+        let spam_post = PostFixture::new(&sub, &spammer).insert(&db.pool).await;
+        let parent = CommentFixture::new(&post, &moderator)
+            .insert(&db.pool)
+            .await;
+        CommentFixture::new(&post, &spammer)
+            .reply_to(&parent)
+            .insert(&db.pool)
+            .await;
+
+        let request = NukeRequest {
+            sub: None,
+            since: None,
+            until: None,
+        };
+        let summary = nuke_user_content(&db.pool, spammer.id, moderator.id, &request)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.posts_removed, 1);
+        assert_eq!(summary.comments_removed, 1);
+        let remaining = post_repo::get_posts_by_sub(
+            &db.pool,
+            "rust",
+            false,
+            &Listing::first_page(PostSort::New, 10),
+        )
+        .await
+        .unwrap();
+        assert!(remaining.iter().all(|p| p.post.id != spam_post.id));
+        let comments = comment_repo::get_comments_by_post(&db.pool, post.id, None)
+            .await
+            .unwrap();
+        assert!(comments.iter().any(|c| c.content == "[removed]"));
+
+        let log = get_mod_log(
+            &db.pool,
+            &ModLogQuery {
+                moderator_id: Some(moderator.id),
+                target_user_id: None,
+                sub: None,
+                limit: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(log.len(), 1);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_deleted_comments_keep_replies_until_purged() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let parent = CommentFixture::new(&post, &author).insert(&db.pool).await;
+        let reply = CommentFixture::new(&post, &author)
+            .reply_to(&parent)
+            .insert(&db.pool)
+            .await;
+
+        comment_repo::delete_comment(&db.pool, parent.id)
+            .await
+            .unwrap();
+        let comments = comment_repo::get_comments_by_post(&db.pool, post.id, None)
+            .await
+            .unwrap();
+        assert_eq!(comments.len(), 2);
+        let deleted = comments.iter().find(|c| c.id == parent.id).unwrap();
+        assert_eq!(deleted.content, "[deleted]");
+        assert_eq!(deleted.removal, Some(RemovalKind::Deleted));
+
+        let purged = purge_comment(&db.pool, parent.id).await.unwrap();
+        assert_eq!(purged, Some((author.id, "rust".to_string())));
+        assert!(comment_repo::get_comment(&db.pool, reply.id).await.is_err());
+        assert_eq!(
+            purge_post(&db.pool, post.id).await.unwrap().map(|p| p.0),
+            Some(author.id)
+        );
+        assert!(post_repo::get_post(&db.pool, post.id).await.is_err());
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_moderators_only_see_their_own_subs() {
+        let db = TestDatabase::new().await;
+        let moderator = UserFixture::new("mod").insert(&db.pool).await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let rust = SubFixture::new("rust").insert(&db.pool).await;
+        let go = SubFixture::new("go").insert(&db.pool).await;
+        sub_repo::add_sub_moderator(&db.pool, "rust", moderator.id, None, ModPermissions::FULL)
+            .await
+            .unwrap();
+        let mut held = Vec::new();
+        for sub in [&rust, &go] {
+            let post = PostFixture::new(sub, &author).insert(&db.pool).await;
+            let comment = CommentFixture::new(&post, &author)
+                .content("Buy cheap watches here")
+                .insert(&db.pool)
+                .await;
+            sqlx::query(
+                "UPDATE posts SET removal_kind = 'filter', removed_at = NOW() WHERE id = $1",
+            )
+            .bind(post.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            sqlx::query(
+                "UPDATE comments SET removal_kind = 'filter', removed_at = NOW() WHERE id = $1",
+            )
+            .bind(comment.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            log_mod_action(
+                &db.pool,
+                moderator.id,
+                ModAction::RemovePost,
+                Some(author.id),
+                Some(&sub.name),
+                Value::Null,
+            )
+            .await
+            .unwrap();
+            held.push((post, comment));
+        }
+        let (rust_post, rust_comment) = &held[0];
+
+        let hits = search_repo::search_comments(
+            &db.pool,
+            "cheap",
+            None,
+            None,
+            Some(moderator.id),
+            SearchSort::New,
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].comment.id, rust_comment.id);
+
+        let posts = filter_repo::get_held_posts(&db.pool, Some(moderator.id))
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].id, rust_post.id);
+        let comments = filter_repo::get_held_comments(&db.pool, Some(moderator.id))
+            .await
+            .unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].id, rust_comment.id);
+        assert_eq!(
+            filter_repo::get_held_posts(&db.pool, None)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let query = ModLogQuery {
+            moderator_id: None,
+            target_user_id: None,
+            sub: None,
+            limit: None,
+        };
+        let log = get_mod_log(&db.pool, &query, Some(moderator.id))
+            .await
+            .unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].sub.as_deref(), Some("rust"));
+        assert_eq!(get_mod_log(&db.pool, &query, None).await.unwrap().len(), 2);
+
+        db.finish().await;
+    }
+}
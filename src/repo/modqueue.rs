@@ -0,0 +1,240 @@
+use crate::model::modqueue::{ModQueueItem, ModQueueKind};
+use crate::model::report::ReportTarget;
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// The sub's posts and comments with open reports or held by a word filter or for their
+/// spam score, most reported first, then oldest first. Content that is otherwise removed or deleted is left out.
+pub async fn get_mod_queue(pool: &PgPool, sub: &str) -> Result<Vec<ModQueueItem>, sqlx::Error> {
+    let items = sqlx::query_as!(
+        ModQueueItem,
+        r#"
+        WITH open_reports AS (
+            SELECT post_id, comment_id, COUNT(*) AS report_count,
+                ARRAY_AGG(DISTINCT reason) FILTER (WHERE reason <> '') AS reasons,
+                ARRAY_AGG(DISTINCT rule_id) FILTER (WHERE rule_id IS NOT NULL) AS rule_ids
+            FROM reports
+            WHERE sub = $1 AND resolved_at IS NULL
+            GROUP BY post_id, comment_id
+        )
+        SELECT kind AS "kind!: ModQueueKind", id AS "id!", post_id AS "post_id!",
+            author_id AS "author_id!", title, content AS "content!",
+            created_at AS "created_at!", held AS "held!",
+            COALESCE(report_count, 0) AS "report_count!",
+            COALESCE(reasons, '{}') AS "reasons!", COALESCE(rule_ids, '{}') AS "rule_ids!",
+            spam_score, spam_reasons AS "spam_reasons!"
+        FROM (
+            SELECT 'post' AS kind, posts.id, posts.id AS post_id, posts.user_id AS author_id,
+                posts.title, posts.content, posts.timestamp AS created_at,
+                posts.removal_kind IS NOT NULL AS held, open_reports.report_count,
+                open_reports.reasons, open_reports.rule_ids, posts.spam_score, posts.spam_reasons
+            FROM posts
+            LEFT JOIN open_reports ON open_reports.post_id = posts.id
+            WHERE posts.sub = $1
+            AND (posts.removal_kind = 'filter'
+                OR (posts.removal_kind IS NULL AND open_reports.report_count IS NOT NULL))
+            UNION ALL
+            SELECT 'comment', comments.id, comments.post_id, comments.user_id, NULL,
+                comments.content, comments.timestamp, comments.removal_kind IS NOT NULL,
+                open_reports.report_count, open_reports.reasons, open_reports.rule_ids,
+                comments.spam_score, comments.spam_reasons
+            FROM comments
+            INNER JOIN posts ON posts.id = comments.post_id
+            LEFT JOIN open_reports ON open_reports.comment_id = comments.id
+            WHERE posts.sub = $1
+            AND (comments.removal_kind = 'filter'
+                OR (comments.removal_kind IS NULL AND open_reports.report_count IS NOT NULL))
+        ) AS queue
+        ORDER BY "report_count!" DESC, "created_at!" ASC
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(items)
+}
+
+/// Marks the content approved, publishing it if a word filter or moderator had removed
+/// it, and resolves its open reports. Returns how many reports were resolved, or `None`
+/// if the content was taken down for legal reasons or deleted by its author.
+pub async fn approve_queue_item(
+    pool: &PgPool,
+    target: ReportTarget,
+    moderator_id: i32,
+) -> Result<Option<u64>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let approved = match target {
+        ReportTarget::Post(post_id) => sqlx::query!(
+            r#"
+            UPDATE posts
+            SET approved_at = NOW(), approved_by = $2,
+                removed_at = NULL, removed_by = NULL, removal_kind = NULL, removal_reason = NULL
+            WHERE id = $1 AND (removal_kind IS NULL OR removal_kind IN ('filter', 'moderator'))
+            "#,
+            post_id,
+            moderator_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected(),
+        ReportTarget::Comment(comment_id) => sqlx::query!(
+            r#"
+            UPDATE comments
+            SET approved_at = NOW(), approved_by = $2,
+                removed_at = NULL, removed_by = NULL, removal_kind = NULL, removal_reason = NULL
+            WHERE id = $1 AND (removal_kind IS NULL OR removal_kind IN ('filter', 'moderator'))
+            "#,
+            comment_id,
+            moderator_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected(),
+    };
+    if approved == 0 {
+        return Ok(None);
+    }
+    let resolved = resolve_reports(&mut tx, target, moderator_id).await?;
+
+    tx.commit().await?;
+
+    Ok(Some(resolved))
+}
+
+/// Removes the content as a moderator, unless it is already gone, and resolves its open
+/// reports. `reason` is kept for the author to read. Returns how many reports were
+/// resolved.
+pub async fn remove_queue_item(
+    pool: &PgPool,
+    target: ReportTarget,
+    moderator_id: i32,
+    reason: Option<&str>,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    match target {
+        ReportTarget::Post(post_id) => {
+            sqlx::query!(
+                r#"
+                UPDATE posts
+                SET removed_at = NOW(), removed_by = $2, removal_kind = 'moderator',
+                    removal_reason = $3, approved_at = NULL, approved_by = NULL
+                WHERE id = $1 AND (removal_kind IS NULL OR removal_kind = 'filter')
+                "#,
+                post_id,
+                moderator_id,
+                reason
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        ReportTarget::Comment(comment_id) => {
+            sqlx::query!(
+                r#"
+                UPDATE comments
+                SET removed_at = NOW(), removed_by = $2, removal_kind = 'moderator',
+                    removal_reason = $3, approved_at = NULL, approved_by = NULL
+                WHERE id = $1 AND (removal_kind IS NULL OR removal_kind = 'filter')
+                "#,
+                comment_id,
+                moderator_id,
+                reason
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    let resolved = resolve_reports(&mut tx, target, moderator_id).await?;
+
+    tx.commit().await?;
+
+    Ok(resolved)
+}
+
+async fn resolve_reports(
+    tx: &mut Transaction<'_, Postgres>,
+    target: ReportTarget,
+    moderator_id: i32,
+) -> Result<u64, sqlx::Error> {
+    let (post_id, comment_id) = match target {
+        ReportTarget::Post(post_id) => (Some(post_id), None),
+        ReportTarget::Comment(comment_id) => (None, Some(comment_id)),
+    };
+    let resolved = sqlx::query!(
+        r#"
+        UPDATE reports
+        SET resolved_at = NOW(), resolved_by = $3
+        WHERE resolved_at IS NULL
+        AND (post_id = $1 OR comment_id = $2)
+        "#,
+        post_id,
+        comment_id,
+        moderator_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(resolved.rows_affected())
+}
+
+#[cfg(test)]
+mod modqueue_repo_tests {
+    use super::*;
+    use crate::repo::report::create_report;
+    use crate::test_support::fixtures::{CommentFixture, PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_decisions_clear_the_queue() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let comment = CommentFixture::new(&post, &author).insert(&db.pool).await;
+        PostFixture::new(&sub, &author).insert(&db.pool).await;
+        for (reporter, reason) in [("first", "Spam"), ("second", "Spam"), ("third", "")] {
+            let reporter = UserFixture::new(reporter).insert(&db.pool).await;
+            let target = ReportTarget::Post(post.id);
+            create_report(&db.pool, &sub.name, target, Some(reporter.id), None, reason)
+                .await
+                .unwrap();
+        }
+        let target = ReportTarget::Comment(comment.id);
+        create_report(
+            &db.pool,
+            &sub.name,
+            target,
+            Some(moderator.id),
+            None,
+            "Rude",
+        )
+        .await
+        .unwrap();
+
+        let queue = get_mod_queue(&db.pool, &sub.name).await.unwrap();
+        let counts: Vec<(ModQueueKind, i64)> = queue
+            .iter()
+            .map(|item| (item.kind, item.report_count))
+            .collect();
+        assert_eq!(
+            counts,
+            [(ModQueueKind::Post, 3), (ModQueueKind::Comment, 1)]
+        );
+        assert_eq!(queue[0].reasons, ["Spam"]);
+
+        let resolved = approve_queue_item(&db.pool, ReportTarget::Post(post.id), moderator.id)
+            .await
+            .unwrap();
+        assert_eq!(resolved, Some(3));
+        let resolved = remove_queue_item(&db.pool, target, moderator.id, Some("Be nice"))
+            .await
+            .unwrap();
+        assert_eq!(resolved, 1);
+        assert!(get_mod_queue(&db.pool, &sub.name).await.unwrap().is_empty());
+
+        db.finish().await;
+    }
+}
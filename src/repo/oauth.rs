@@ -0,0 +1,171 @@
+use crate::model::oauth::{OAuthProvider, OAuthState, ProviderIdentity, UserIdentity};
+use crate::model::user::DbAddUser;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Stores a pending authorization request and clears out abandoned ones.
+pub async fn create_state(
+    pool: &PgPool,
+    state_hash: &str,
+    nonce_hash: &str,
+    provider: OAuthProvider,
+    link_user_id: Option<i32>,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM oauth_states
+        WHERE expires_at <= NOW()
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO oauth_states (state_hash, nonce_hash, provider, link_user_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        state_hash,
+        nonce_hash,
+        provider.to_string(),
+        link_user_id,
+        expires_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes and returns a pending request, unless it is unknown or has expired. It is
+/// removed even if the caller then finds the browser nonce doesn't match.
+pub async fn take_state(
+    pool: &PgPool,
+    state_hash: &str,
+) -> Result<Option<OAuthState>, sqlx::Error> {
+    let state = sqlx::query_as!(
+        OAuthState,
+        r#"
+        DELETE FROM oauth_states
+        WHERE state_hash = $1 AND expires_at > NOW()
+        RETURNING provider AS "provider: OAuthProvider", link_user_id, nonce_hash
+        "#,
+        state_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(state)
+}
+
+pub async fn get_identity_user(
+    pool: &PgPool,
+    provider: OAuthProvider,
+    subject: &str,
+) -> Result<Option<i32>, sqlx::Error> {
+    let user_id = sqlx::query_scalar!(
+        r#"
+        SELECT user_id FROM user_identities
+        WHERE provider = $1 AND subject = $2
+        "#,
+        provider.to_string(),
+        subject
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user_id)
+}
+
+pub async fn link_identity(
+    pool: &PgPool,
+    user_id: i32,
+    identity: &ProviderIdentity,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_identities (provider, subject, user_id, email)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        identity.provider.to_string(),
+        identity.subject,
+        user_id,
+        identity.email
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Creates the local account for a first-time provider login together with its identity.
+pub async fn create_user_with_identity(
+    pool: &PgPool,
+    user: &DbAddUser,
+    identity: &ProviderIdentity,
+) -> Result<i32, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let user_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO users (username, password_hash, created_at, email, email_verified_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+        user.username,
+        user.password_hash,
+        user.created_at,
+        user.email,
+        user.email_verified_at,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_identities (provider, subject, user_id, email)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        identity.provider.to_string(),
+        identity.subject,
+        user_id,
+        identity.email
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(user_id)
+}
+
+pub async fn get_identities(pool: &PgPool, user_id: i32) -> Result<Vec<UserIdentity>, sqlx::Error> {
+    let identities = sqlx::query_as!(
+        UserIdentity,
+        r#"
+        SELECT provider AS "provider: OAuthProvider", email, linked_at
+        FROM user_identities
+        WHERE user_id = $1
+        ORDER BY linked_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(identities)
+}
+
+pub async fn email_in_use(pool: &PgPool, email: &str) -> Result<bool, sqlx::Error> {
+    let in_use = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM users WHERE LOWER(email) = LOWER($1)) AS "in_use!"
+        "#,
+        email
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(in_use)
+}
@@ -0,0 +1,167 @@
+use crate::model::poll::{Poll, PollOption};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Attaches a poll to the post, its options numbered in the order given.
+pub async fn create_poll(
+    pool: &PgPool,
+    post_id: Uuid,
+    options: &[String],
+    ends_at: Option<DateTime<Utc>>,
+    hide_results: bool,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO polls (post_id, ends_at, hide_results)
+        VALUES ($1, $2, $3)
+        "#,
+        post_id,
+        ends_at,
+        hide_results
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO poll_options (post_id, position, text)
+        SELECT $1, options.position, options.text
+        FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS options(text, position)
+        "#,
+        post_id,
+        options
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// The post's poll with its vote counts, if it has one.
+pub async fn get_poll(pool: &PgPool, post_id: Uuid) -> Result<Option<Poll>, sqlx::Error> {
+    let Some(poll) = sqlx::query!(
+        r#"
+        SELECT ends_at, hide_results
+        FROM polls
+        WHERE post_id = $1
+        "#,
+        post_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let options = sqlx::query_as!(
+        PollOption,
+        r#"
+        SELECT poll_options.id, poll_options.text, COUNT(poll_votes.user_id) AS "votes!"
+        FROM poll_options
+        LEFT JOIN poll_votes ON poll_votes.option_id = poll_options.id
+        WHERE poll_options.post_id = $1
+        GROUP BY poll_options.id
+        ORDER BY poll_options.position
+        "#,
+        post_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(Poll {
+        ends_at: poll.ends_at,
+        hide_results: poll.hide_results,
+        options,
+    }))
+}
+
+/// The option the user voted for in the post's poll.
+pub async fn get_poll_vote(
+    pool: &PgPool,
+    post_id: Uuid,
+    user_id: i32,
+) -> Result<Option<i32>, sqlx::Error> {
+    let option_id = sqlx::query_scalar!(
+        r#"
+        SELECT option_id
+        FROM poll_votes
+        WHERE post_id = $1 AND user_id = $2
+        "#,
+        post_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(option_id)
+}
+
+/// Records the user's vote. Votes can't be changed: returns `false` without
+/// recording anything if the user already voted in this poll.
+pub async fn cast_poll_vote(
+    pool: &PgPool,
+    post_id: Uuid,
+    user_id: i32,
+    option_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let cast = sqlx::query!(
+        r#"
+        INSERT INTO poll_votes (post_id, user_id, option_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (post_id, user_id) DO NOTHING
+        "#,
+        post_id,
+        user_id,
+        option_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(cast.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod poll_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_second_vote_is_not_counted() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let voter = UserFixture::new("voter").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let options = ["Tabs".to_string(), "Spaces".to_string()];
+        create_poll(&db.pool, post.id, &options, None, true)
+            .await
+            .unwrap();
+
+        let poll = get_poll(&db.pool, post.id).await.unwrap().unwrap();
+        let (tabs, spaces) = (poll.options[0].id, poll.options[1].id);
+        assert_eq!(poll.options[1].text, "Spaces");
+        assert!(cast_poll_vote(&db.pool, post.id, voter.id, spaces)
+            .await
+            .unwrap());
+        assert!(!cast_poll_vote(&db.pool, post.id, voter.id, tabs)
+            .await
+            .unwrap());
+
+        let poll = get_poll(&db.pool, post.id).await.unwrap().unwrap();
+        assert_eq!(poll.options[0].votes, 0);
+        assert_eq!(poll.options[1].votes, 1);
+        assert_eq!(
+            get_poll_vote(&db.pool, post.id, voter.id).await.unwrap(),
+            Some(spaces)
+        );
+
+        db.finish().await;
+    }
+}
@@ -1,12 +1,98 @@
-use crate::model::post::Post;
+use crate::link_preview::canonical_link_url;
+use crate::model::flair::AuthorFlair;
+use crate::model::link_preview::LinkPreview;
+use crate::model::media::Media;
+use crate::model::moderation::RemovalKind;
+use crate::model::poll::Poll;
+use crate::model::post::{Listing, Post, PostStatus, RankedPost, POPULAR_MIN_SCORE};
+use crate::model::revision::Revision;
+use crate::repo::karma as karma_repo;
+use crate::repo::link_preview as link_preview_repo;
+use crate::repo::media as media_repo;
+use crate::repo::poll as poll_repo;
+use crate::repo::removal_reason as removal_reason_repo;
+use crate::repo::saved as saved_repo;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
+use std::collections::HashSet;
+use url::Url;
 use uuid::Uuid;
 
+/// A listing row: a post and its `listing_rank`.
+struct ListedPost {
+    id: Uuid,
+    sub: String,
+    user_id: i32,
+    title: String,
+    content: String,
+    timestamp: DateTime<Utc>,
+    removal: Option<RemovalKind>,
+    nsfw: bool,
+    language: Option<String>,
+    score: i32,
+    status: PostStatus,
+    edited_at: Option<DateTime<Utc>>,
+    pin_order: Option<i32>,
+    locked: bool,
+    lock_reason: Option<String>,
+    archived: bool,
+    flair_id: Option<i32>,
+    crosspost_parent_id: Option<Uuid>,
+    link_url: Option<String>,
+    spoiler: bool,
+    publish_at: Option<DateTime<Utc>>,
+    author_flair: Option<AuthorFlair>,
+    rank: f64,
+}
+
+impl From<ListedPost> for RankedPost {
+    fn from(listed: ListedPost) -> Self {
+        RankedPost {
+            post: Post {
+                id: listed.id,
+                sub: listed.sub,
+                user_id: listed.user_id,
+                title: listed.title,
+                content: listed.content,
+                timestamp: listed.timestamp,
+                removal: listed.removal,
+                nsfw: listed.nsfw,
+                spoiler: listed.spoiler,
+                publish_at: listed.publish_at,
+                language: listed.language,
+                score: listed.score,
+                status: listed.status,
+                edited_at: listed.edited_at,
+                pin_order: listed.pin_order,
+                locked: listed.locked,
+                lock_reason: listed.lock_reason,
+                archived: listed.archived,
+                flair_id: listed.flair_id,
+                crosspost_parent_id: listed.crosspost_parent_id,
+                link_url: listed.link_url,
+                author_flair: listed.author_flair,
+            },
+            rank: listed.rank,
+        }
+    }
+}
+
 pub async fn create_post(pool: &PgPool, post: &Post) -> Result<Uuid, sqlx::Error> {
+    let canonical_url = post
+        .link_url
+        .as_deref()
+        .and_then(|link| Url::parse(link).ok())
+        .map(|url| canonical_link_url(&url));
+    let mut tx = pool.begin().await?;
+
     sqlx::query!(
         r#"
-        INSERT INTO posts (id, sub, user_id, title, content, timestamp)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO posts (id, sub, user_id, title, content, timestamp, nsfw, language,
+            removal_kind, removed_at, status, crosspost_parent_id, link_url, spoiler, publish_at,
+            canonical_url, flair_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, CASE WHEN $9::TEXT IS NULL THEN NULL ELSE NOW() END,
+            $10, $11, $12, $13, $14, $15, $16)
         "#,
         post.id,
         post.sub,
@@ -14,10 +100,34 @@ pub async fn create_post(pool: &PgPool, post: &Post) -> Result<Uuid, sqlx::Error
         post.title,
         post.content,
         post.timestamp,
+        post.nsfw,
+        post.language,
+        post.removal.map(|removal| removal.to_string()),
+        post.status.to_string(),
+        post.crosspost_parent_id,
+        post.link_url,
+        post.spoiler,
+        post.publish_at,
+        canonical_url,
+        post.flair_id,
     )
-    .execute(pool)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO post_revisions (post_id, revision, content, created_at)
+        VALUES ($1, 1, $2, $3)
+        "#,
+        post.id,
+        post.content,
+        post.timestamp,
+    )
+    .execute(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     Ok(post.id)
 }
 
@@ -25,9 +135,31 @@ pub async fn get_post(pool: &PgPool, post_id: Uuid) -> Result<Post, sqlx::Error>
     let post = sqlx::query_as!(
         Post,
         r#"
-        SELECT id, sub, user_id, title, content, timestamp
+        SELECT posts.id, posts.sub, posts.user_id,
+            CASE posts.removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                ELSE posts.title
+            END AS "title!",
+            CASE posts.removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                WHEN 'deleted' THEN '[deleted]'
+                ELSE posts.content
+            END AS "content!",
+            posts.timestamp, posts.removal_kind AS "removal: RemovalKind",
+            posts.nsfw OR subs.nsfw AS "nsfw!", posts.language, posts.score,
+            posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id,
+            CASE WHEN posts.removal_kind IS NULL THEN posts.link_url END AS link_url,
+            posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair"
         FROM posts
-        WHERE id = $1
+        INNER JOIN subs ON subs.name = posts.sub
+        WHERE posts.id = $1
         "#,
         post_id
     )
@@ -37,15 +169,557 @@ pub async fn get_post(pool: &PgPool, post_id: Uuid) -> Result<Post, sqlx::Error>
     Ok(post)
 }
 
-pub async fn get_posts_by_sub(pool: &PgPool, sub_name: &str) -> Result<Vec<Post>, sqlx::Error> {
+/// Published posts in `sub` made since `since` that link to the same page, newest first.
+pub async fn get_recent_posts_by_link(
+    pool: &PgPool,
+    sub: &str,
+    canonical_url: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<Post>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        Post,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content AS "content!",
+            posts.timestamp, posts.removal_kind AS "removal: RemovalKind",
+            posts.nsfw OR subs.nsfw AS "nsfw!", posts.language, posts.score,
+            posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        WHERE posts.sub = $1 AND posts.canonical_url = $2 AND posts.timestamp >= $3
+            AND posts.status = 'published' AND posts.removal_kind IS NULL
+        ORDER BY posts.timestamp DESC
+        LIMIT 5
+        "#,
+        sub,
+        canonical_url,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts)
+}
+
+/// The requested posts in no particular order. Unknown ids, drafts and posts the viewer
+/// may not see, such as those of shadowbanned authors, are left out; removed posts are returned as tombstones, as by `get_post`.
+pub async fn get_posts_by_ids(
+    pool: &PgPool,
+    post_ids: &[Uuid],
+    include_nsfw: bool,
+    viewer: Option<i32>,
+) -> Result<Vec<Post>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        Post,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id,
+            CASE posts.removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                ELSE posts.title
+            END AS "title!",
+            CASE posts.removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                WHEN 'deleted' THEN '[deleted]'
+                ELSE posts.content
+            END AS "content!",
+            posts.timestamp, posts.removal_kind AS "removal: RemovalKind",
+            posts.nsfw OR subs.nsfw AS "nsfw!", posts.language, posts.score,
+            posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id,
+            CASE WHEN posts.removal_kind IS NULL THEN posts.link_url END AS link_url,
+            posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        WHERE posts.id = ANY($1) AND posts.status = 'published'
+        AND ($2 OR NOT (posts.nsfw OR subs.nsfw))
+        AND author_visible(posts.user_id, posts.sub, $3)
+        "#,
+        post_ids,
+        include_nsfw,
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts)
+}
+
+/// A page of the sub's posts. Returns up to `listing.limit + 1` posts so the caller
+/// can tell whether another page follows; see `PostPage::new`. Pinned posts are left
+/// out, to be shown ahead of the listing (see `get_pinned_posts`), unless the listing
+/// is narrowed to a flair.
+pub async fn get_posts_by_sub(
+    pool: &PgPool,
+    sub_name: &str,
+    include_nsfw: bool,
+    listing: &Listing,
+) -> Result<Vec<RankedPost>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        ListedPost,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair",
+            listing_rank($3, posts.score, posts.upvotes, posts.downvotes, posts.timestamp)
+                AS "rank!"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        LEFT JOIN hidden_posts
+            ON hidden_posts.post_id = posts.id AND hidden_posts.user_id = $9
+        WHERE posts.sub = $1 AND posts.removed_at IS NULL AND posts.status = 'published'
+        AND hidden_posts.post_id IS NULL
+        AND author_visible(posts.user_id, posts.sub, $9)
+        AND (posts.pin_order IS NULL OR $8::INTEGER IS NOT NULL)
+        AND ($8::INTEGER IS NULL OR posts.flair_id = $8)
+        AND ($2 OR NOT (posts.nsfw OR subs.nsfw))
+        AND ($4::TIMESTAMPTZ IS NULL OR posts.timestamp >= $4)
+        AND ($5::DOUBLE PRECISION IS NULL OR (
+            listing_rank($3, posts.score, posts.upvotes, posts.downvotes, posts.timestamp),
+            posts.id
+        ) < ($5, $6))
+        ORDER BY "rank!" DESC, posts.id DESC
+        LIMIT $7::BIGINT + 1
+        "#,
+        sub_name,
+        include_nsfw,
+        listing.sort.to_string(),
+        listing.since,
+        listing.after.map(|cursor| cursor.rank),
+        listing.after.map(|cursor| cursor.id),
+        listing.limit,
+        listing.flair,
+        listing.viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts.into_iter().map(RankedPost::from).collect())
+}
+
+/// The sub's pinned posts that `viewer` may see, in pin order, less any they have hidden.
+pub async fn get_pinned_posts(
+    pool: &PgPool,
+    sub_name: &str,
+    include_nsfw: bool,
+    viewer: Option<i32>,
+) -> Result<Vec<Post>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        Post,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        LEFT JOIN hidden_posts
+            ON hidden_posts.post_id = posts.id AND hidden_posts.user_id = $3
+        WHERE posts.sub = $1 AND posts.pin_order IS NOT NULL
+        AND posts.removed_at IS NULL AND posts.status = 'published'
+        AND hidden_posts.post_id IS NULL
+        AND author_visible(posts.user_id, posts.sub, $3)
+        AND ($2 OR NOT (posts.nsfw OR subs.nsfw))
+        ORDER BY posts.pin_order, posts.id
+        "#,
+        sub_name,
+        include_nsfw,
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts)
+}
+
+/// Pins the post in its sub at `order`, or after the sub's other pins for `None`, and
+/// returns the order it was given. Returns `None` without pinning if the sub already
+/// has `max_pins` other visible pinned posts. The sub row is locked so concurrent pins
+/// can't both squeeze under the limit.
+pub async fn pin_post(
+    pool: &PgPool,
+    post_id: Uuid,
+    order: Option<i32>,
+    max_pins: i32,
+) -> Result<Option<i32>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let sub = sqlx::query_scalar!(
+        r#"
+        SELECT subs.name
+        FROM subs
+        INNER JOIN posts ON posts.sub = subs.name
+        WHERE posts.id = $1
+        FOR UPDATE OF subs
+        "#,
+        post_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let pins = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", MAX(pin_order) AS last
+        FROM posts
+        WHERE sub = $1 AND id <> $2 AND pin_order IS NOT NULL AND removed_at IS NULL
+        "#,
+        sub,
+        post_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if pins.count >= i64::from(max_pins) {
+        return Ok(None);
+    }
+
+    let order = order.unwrap_or(pins.last.unwrap_or(0) + 1);
+    sqlx::query!(
+        r#"
+        UPDATE posts
+        SET pin_order = $2
+        WHERE id = $1
+        "#,
+        post_id,
+        order
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(order))
+}
+
+/// Returns whether the post was pinned.
+pub async fn unpin_post(pool: &PgPool, post_id: Uuid) -> Result<bool, sqlx::Error> {
+    let unpinned = sqlx::query!(
+        r#"
+        UPDATE posts
+        SET pin_order = NULL
+        WHERE id = $1 AND pin_order IS NOT NULL
+        "#,
+        post_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(unpinned.rows_affected() > 0)
+}
+
+/// Locks the thread against new comments, replacing the reason if it was already locked.
+pub async fn lock_post(
+    pool: &PgPool,
+    post_id: Uuid,
+    reason: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE posts
+        SET locked = TRUE, lock_reason = $2
+        WHERE id = $1
+        "#,
+        post_id,
+        reason
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_post_flags(
+    pool: &PgPool,
+    post_id: Uuid,
+    nsfw: bool,
+    spoiler: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE posts
+        SET nsfw = $2, spoiler = $3
+        WHERE id = $1
+        "#,
+        post_id,
+        nsfw,
+        spoiler
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns whether the post was locked.
+pub async fn unlock_post(pool: &PgPool, post_id: Uuid) -> Result<bool, sqlx::Error> {
+    let unlocked = sqlx::query!(
+        r#"
+        UPDATE posts
+        SET locked = FALSE, lock_reason = NULL
+        WHERE id = $1 AND locked
+        "#,
+        post_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(unlocked.rows_affected() > 0)
+}
+
+/// Archives published posts made before `cutoff`, skipping any an admin unarchived,
+/// and returns how many were archived.
+pub async fn archive_posts_before(
+    pool: &PgPool,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let archived = sqlx::query!(
+        r#"
+        UPDATE posts
+        SET archived = TRUE
+        WHERE NOT archived AND NOT archive_exempt AND status = 'published' AND timestamp < $1
+        "#,
+        cutoff
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(archived.rows_affected())
+}
+
+/// Publishes scheduled drafts whose `publish_at` has come, dating each post to its
+/// scheduled time, and returns how many were published. Drafts whose author has since
+/// been banned from the sub, or isn't a member of a restricted or private one, are
+/// unscheduled and left with the author's other drafts instead.
+pub async fn publish_scheduled_posts(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE posts
+        SET publish_at = NULL
+        FROM subs
+        WHERE subs.name = posts.sub AND posts.status = 'draft' AND posts.publish_at <= NOW()
+        AND (
+            EXISTS(
+                SELECT 1 FROM sub_bans
+                WHERE sub_bans.sub = posts.sub AND sub_bans.user_id = posts.user_id
+                AND (sub_bans.expires_at IS NULL OR sub_bans.expires_at > NOW())
+            )
+            OR (subs.visibility <> 'public' AND NOT sub_member(posts.sub, posts.user_id))
+        )
+        "#
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let published = sqlx::query!(
+        r#"
+        UPDATE posts
+        SET status = 'published', timestamp = publish_at, publish_at = NULL
+        WHERE status = 'draft' AND publish_at <= NOW()
+        "#
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(published.rows_affected())
+}
+
+/// Unarchives the post for good; the archiving job leaves it alone from then on.
+/// Returns whether it was archived.
+pub async fn unarchive_post(pool: &PgPool, post_id: Uuid) -> Result<bool, sqlx::Error> {
+    let unarchived = sqlx::query!(
+        r#"
+        UPDATE posts
+        SET archived = FALSE, archive_exempt = TRUE
+        WHERE id = $1 AND archived
+        "#,
+        post_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(unarchived.rows_affected() > 0)
+}
+
+pub async fn get_posts_by_user(
+    pool: &PgPool,
+    user_id: i32,
+    include_nsfw: bool,
+    viewer: Option<i32>,
+) -> Result<Vec<Post>, sqlx::Error> {
     let posts = sqlx::query_as!(
         Post,
         r#"
-        SELECT id, sub, user_id, title, content, timestamp
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair"
         FROM posts
-        WHERE sub = $1
+        INNER JOIN subs ON subs.name = posts.sub
+        WHERE posts.user_id = $1 AND posts.removed_at IS NULL AND posts.status = 'published'
+        AND ($2 OR NOT (posts.nsfw OR subs.nsfw))
+        AND author_visible(posts.user_id, posts.sub, $3)
+        AND (subs.visibility <> 'private' OR sub_member(subs.name, $3))
+        ORDER BY posts.timestamp DESC
+        "#,
+        user_id,
+        include_nsfw,
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts)
+}
+
+/// Published crossposts of the post, newest first.
+pub async fn get_crossposts(
+    pool: &PgPool,
+    post_id: Uuid,
+    include_nsfw: bool,
+    viewer: Option<i32>,
+) -> Result<Vec<Post>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        Post,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        WHERE posts.crosspost_parent_id = $1 AND posts.removed_at IS NULL
+        AND posts.status = 'published'
+        AND ($2 OR NOT (posts.nsfw OR subs.nsfw))
+        AND author_visible(posts.user_id, posts.sub, $3)
+        AND (subs.visibility <> 'private' OR sub_member(subs.name, $3))
+        ORDER BY posts.timestamp DESC
+        "#,
+        post_id,
+        include_nsfw,
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts)
+}
+
+/// The user's unpublished drafts, most recently started first.
+pub async fn get_drafts_by_user(pool: &PgPool, user_id: i32) -> Result<Vec<Post>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        Post,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        WHERE posts.user_id = $1 AND posts.status = 'draft' AND posts.removed_at IS NULL
+        ORDER BY posts.timestamp DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts)
+}
+
+/// Hiding a post that's already hidden does nothing.
+pub async fn hide_post(pool: &PgPool, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO hidden_posts (user_id, post_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, post_id) DO NOTHING
+        "#,
+        user_id,
+        post_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn unhide_post(pool: &PgPool, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM hidden_posts
+        WHERE user_id = $1 AND post_id = $2
+        "#,
+        user_id,
+        post_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The published posts the user has hidden, most recently hidden first.
+pub async fn get_hidden_posts(
+    pool: &PgPool,
+    user_id: i32,
+    include_nsfw: bool,
+) -> Result<Vec<Post>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        Post,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id,
+            CASE posts.removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                ELSE posts.title
+            END AS "title!",
+            CASE posts.removal_kind
+                WHEN 'legal' THEN '[removed for legal reasons]'
+                WHEN 'moderator' THEN '[removed]'
+                WHEN 'filter' THEN '[awaiting moderator review]'
+                WHEN 'deleted' THEN '[deleted]'
+                ELSE posts.content
+            END AS "content!",
+            posts.timestamp, posts.removal_kind AS "removal: RemovalKind",
+            posts.nsfw OR subs.nsfw AS "nsfw!", posts.language, posts.score,
+            posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id,
+            CASE WHEN posts.removal_kind IS NULL THEN posts.link_url END AS link_url,
+            posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair"
+        FROM hidden_posts
+        INNER JOIN posts ON posts.id = hidden_posts.post_id
+        INNER JOIN subs ON subs.name = posts.sub
+        WHERE hidden_posts.user_id = $1 AND posts.status = 'published'
+        AND author_visible(posts.user_id, posts.sub, $1)
+        AND ($2 OR NOT (posts.nsfw OR subs.nsfw))
+        ORDER BY hidden_posts.hidden_at DESC, posts.id DESC
         "#,
-        sub_name
+        user_id,
+        include_nsfw
     )
     .fetch_all(pool)
     .await?;
@@ -53,36 +727,1339 @@ pub async fn get_posts_by_sub(pool: &PgPool, sub_name: &str) -> Result<Vec<Post>
     Ok(posts)
 }
 
+/// Publishes a draft, dating it to now so it enters listings as a new post. Returns
+/// whether there was a draft to publish.
+pub async fn publish_post(pool: &PgPool, post_id: Uuid) -> Result<bool, sqlx::Error> {
+    let published = sqlx::query!(
+        r#"
+        UPDATE posts
+        SET status = 'published', timestamp = NOW(), publish_at = NULL
+        WHERE id = $1 AND status = 'draft'
+        "#,
+        post_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(published.rows_affected() > 0)
+}
+
+/// A page of posts across every sub, as for `get_posts_by_sub`, less those in private subs
+/// the viewer isn't a member of. An empty `languages` list means no language filter;
+/// untagged posts are always included since their language is unknown.
+pub async fn get_all_posts(
+    pool: &PgPool,
+    include_nsfw: bool,
+    languages: &[String],
+    listing: &Listing,
+) -> Result<Vec<RankedPost>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        ListedPost,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair",
+            listing_rank($3, posts.score, posts.upvotes, posts.downvotes, posts.timestamp)
+                AS "rank!"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        LEFT JOIN hidden_posts
+            ON hidden_posts.post_id = posts.id AND hidden_posts.user_id = $9
+        WHERE posts.removed_at IS NULL AND posts.status = 'published'
+        AND hidden_posts.post_id IS NULL
+        AND author_visible(posts.user_id, posts.sub, $9)
+        AND (subs.visibility <> 'private' OR sub_member(subs.name, $9))
+        AND ($1 OR NOT (posts.nsfw OR subs.nsfw))
+        AND (cardinality($2::TEXT[]) = 0 OR posts.language IS NULL OR posts.language = ANY($2))
+        AND ($8::INTEGER IS NULL OR posts.flair_id = $8)
+        AND ($4::TIMESTAMPTZ IS NULL OR posts.timestamp >= $4)
+        AND ($5::DOUBLE PRECISION IS NULL OR (
+            listing_rank($3, posts.score, posts.upvotes, posts.downvotes, posts.timestamp),
+            posts.id
+        ) < ($5, $6))
+        ORDER BY "rank!" DESC, posts.id DESC
+        LIMIT $7::BIGINT + 1
+        "#,
+        include_nsfw,
+        languages,
+        listing.sort.to_string(),
+        listing.since,
+        listing.after.map(|cursor| cursor.rank),
+        listing.after.map(|cursor| cursor.id),
+        listing.limit,
+        listing.flair,
+        listing.viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts.into_iter().map(RankedPost::from).collect())
+}
+
+/// A page of posts as for `get_all_posts`, but only those in subs anyone may read and with
+/// a score of at least `POPULAR_MIN_SCORE`.
+pub async fn get_popular_posts(
+    pool: &PgPool,
+    include_nsfw: bool,
+    languages: &[String],
+    listing: &Listing,
+) -> Result<Vec<RankedPost>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        ListedPost,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair",
+            listing_rank($3, posts.score, posts.upvotes, posts.downvotes, posts.timestamp)
+                AS "rank!"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        LEFT JOIN hidden_posts
+            ON hidden_posts.post_id = posts.id AND hidden_posts.user_id = $9
+        WHERE posts.removed_at IS NULL AND posts.status = 'published'
+        AND hidden_posts.post_id IS NULL
+        AND author_visible(posts.user_id, posts.sub, $9)
+        AND subs.visibility <> 'private'
+        AND posts.score >= $10
+        AND ($1 OR NOT (posts.nsfw OR subs.nsfw))
+        AND (cardinality($2::TEXT[]) = 0 OR posts.language IS NULL OR posts.language = ANY($2))
+        AND ($8::INTEGER IS NULL OR posts.flair_id = $8)
+        AND ($4::TIMESTAMPTZ IS NULL OR posts.timestamp >= $4)
+        AND ($5::DOUBLE PRECISION IS NULL OR (
+            listing_rank($3, posts.score, posts.upvotes, posts.downvotes, posts.timestamp),
+            posts.id
+        ) < ($5, $6))
+        ORDER BY "rank!" DESC, posts.id DESC
+        LIMIT $7::BIGINT + 1
+        "#,
+        include_nsfw,
+        languages,
+        listing.sort.to_string(),
+        listing.since,
+        listing.after.map(|cursor| cursor.rank),
+        listing.after.map(|cursor| cursor.id),
+        listing.limit,
+        listing.flair,
+        listing.viewer,
+        POPULAR_MIN_SCORE
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts.into_iter().map(RankedPost::from).collect())
+}
+
+/// A page of posts from the given subs, as for `get_all_posts` but without a language
+/// filter.
+pub async fn get_posts_in_subs(
+    pool: &PgPool,
+    subs: &[String],
+    include_nsfw: bool,
+    listing: &Listing,
+) -> Result<Vec<RankedPost>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        ListedPost,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair",
+            listing_rank($3, posts.score, posts.upvotes, posts.downvotes, posts.timestamp)
+                AS "rank!"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        LEFT JOIN hidden_posts
+            ON hidden_posts.post_id = posts.id AND hidden_posts.user_id = $9
+        WHERE posts.sub = ANY($1) AND posts.removed_at IS NULL AND posts.status = 'published'
+        AND hidden_posts.post_id IS NULL
+        AND author_visible(posts.user_id, posts.sub, $9)
+        AND (subs.visibility <> 'private' OR sub_member(subs.name, $9))
+        AND ($2 OR NOT (posts.nsfw OR subs.nsfw))
+        AND ($8::INTEGER IS NULL OR posts.flair_id = $8)
+        AND ($4::TIMESTAMPTZ IS NULL OR posts.timestamp >= $4)
+        AND ($5::DOUBLE PRECISION IS NULL OR (
+            listing_rank($3, posts.score, posts.upvotes, posts.downvotes, posts.timestamp),
+            posts.id
+        ) < ($5, $6))
+        ORDER BY "rank!" DESC, posts.id DESC
+        LIMIT $7::BIGINT + 1
+        "#,
+        subs,
+        include_nsfw,
+        listing.sort.to_string(),
+        listing.since,
+        listing.after.map(|cursor| cursor.rank),
+        listing.after.map(|cursor| cursor.id),
+        listing.limit,
+        listing.flair,
+        listing.viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts.into_iter().map(RankedPost::from).collect())
+}
+
+/// A page of posts from the subs `listing.viewer` is subscribed to, as for `get_all_posts` but
+/// without a language filter. Empty for anonymous listings.
+pub async fn get_home_posts(
+    pool: &PgPool,
+    include_nsfw: bool,
+    listing: &Listing,
+) -> Result<Vec<RankedPost>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        ListedPost,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair",
+            listing_rank($2, posts.score, posts.upvotes, posts.downvotes, posts.timestamp)
+                AS "rank!"
+        FROM subscriptions
+        INNER JOIN posts ON posts.sub = subscriptions.sub_name
+        INNER JOIN subs ON subs.name = posts.sub
+        LEFT JOIN hidden_posts
+            ON hidden_posts.post_id = posts.id AND hidden_posts.user_id = $8
+        WHERE subscriptions.user_id = $8
+        AND posts.removed_at IS NULL AND posts.status = 'published'
+        AND hidden_posts.post_id IS NULL
+        AND author_visible(posts.user_id, posts.sub, $8)
+        AND (subs.visibility <> 'private' OR sub_member(subs.name, $8))
+        AND ($1 OR NOT (posts.nsfw OR subs.nsfw))
+        AND ($7::INTEGER IS NULL OR posts.flair_id = $7)
+        AND ($3::TIMESTAMPTZ IS NULL OR posts.timestamp >= $3)
+        AND ($4::DOUBLE PRECISION IS NULL OR (
+            listing_rank($2, posts.score, posts.upvotes, posts.downvotes, posts.timestamp),
+            posts.id
+        ) < ($4, $5))
+        ORDER BY "rank!" DESC, posts.id DESC
+        LIMIT $6::BIGINT + 1
+        "#,
+        include_nsfw,
+        listing.sort.to_string(),
+        listing.since,
+        listing.after.map(|cursor| cursor.rank),
+        listing.after.map(|cursor| cursor.id),
+        listing.limit,
+        listing.flair,
+        listing.viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts.into_iter().map(RankedPost::from).collect())
+}
+
+/// Saves an edit as a new revision. `removal` is how the filters would store the new
+/// content; it doesn't replace a removal the post already has.
 pub async fn update_post(
     pool: &PgPool,
     post_id: Uuid,
     update_content: String,
+    removal: Option<RemovalKind>,
 ) -> Result<Uuid, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
     sqlx::query!(
         r#"
         UPDATE posts
-        SET content = $1
+        SET content = $1, edited_at = NOW(),
+            removed_at = CASE WHEN removal_kind IS NULL AND $3::TEXT IS NOT NULL
+                THEN NOW() ELSE removed_at END,
+            removal_kind = COALESCE(removal_kind, $3)
         WHERE id = $2
         "#,
         update_content,
         post_id,
+        removal.map(|removal| removal.to_string()),
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    sqlx::query!(
+        r#"
+        INSERT INTO post_revisions (post_id, revision, content)
+        SELECT $1, COALESCE(MAX(revision), 0) + 1, $2
+        FROM post_revisions
+        WHERE post_id = $1
+        "#,
+        post_id,
+        update_content,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
     Ok(post_id)
 }
 
+pub async fn get_post_revision(
+    pool: &PgPool,
+    post_id: Uuid,
+    revision: i32,
+) -> Result<Revision, sqlx::Error> {
+    let revision = sqlx::query_as!(
+        Revision,
+        r#"
+        SELECT revision, content, created_at
+        FROM post_revisions
+        WHERE post_id = $1 AND revision = $2
+        "#,
+        post_id,
+        revision
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(revision)
+}
+
+/// Deletes the post for its author: the title stays, but the content and its
+/// revisions are erased and the post leaves listings. Comments are untouched, so the
+/// thread stays readable. An earlier moderator or legal removal keeps its kind.
 pub async fn delete_post(pool: &PgPool, post_id: Uuid) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
     sqlx::query!(
         r#"
-        DELETE FROM posts
+        UPDATE posts
+        SET content = '[deleted]',
+            removal_kind = CASE
+                WHEN removal_kind IN ('moderator', 'legal') THEN removal_kind
+                ELSE 'deleted'
+            END,
+            removed_at = COALESCE(removed_at, NOW()),
+            removed_by = COALESCE(removed_by, user_id)
         WHERE id = $1
         "#,
         post_id
     )
-    .execute(pool)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM post_revisions
+        WHERE post_id = $1
+        "#,
+        post_id
+    )
+    .execute(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     Ok(())
 }
+
+/// Records the user's vote on a post, or withdraws it for `None`, and returns the new
+/// score. The post row is locked first so concurrent votes on it can't be miscounted.
+/// The author's karma changes with the score.
+pub async fn set_post_vote(
+    pool: &PgPool,
+    post_id: Uuid,
+    user_id: i32,
+    vote: Option<i16>,
+) -> Result<i32, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        SELECT id FROM posts
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        post_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let previous = sqlx::query_scalar!(
+        r#"
+        DELETE FROM post_votes
+        WHERE post_id = $1 AND user_id = $2
+        RETURNING value
+        "#,
+        post_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(value) = vote {
+        sqlx::query!(
+            r#"
+            INSERT INTO post_votes (user_id, post_id, value)
+            VALUES ($1, $2, $3)
+            "#,
+            user_id,
+            post_id,
+            value
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let change = i32::from(vote.unwrap_or(0)) - i32::from(previous.unwrap_or(0));
+    let count = |value: i16| i32::from(vote == Some(value)) - i32::from(previous == Some(value));
+    let post = sqlx::query!(
+        r#"
+        UPDATE posts
+        SET score = score + $2, upvotes = upvotes + $3, downvotes = downvotes + $4
+        WHERE id = $1
+        RETURNING score, user_id, sub
+        "#,
+        post_id,
+        change,
+        count(1),
+        count(-1)
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if change != 0 {
+        karma_repo::add_karma(&mut tx, post.user_id, &post.sub, change, 0).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(post.score)
+}
+
+/// Seconds since this 2005 epoch keep hot ranks small enough to index as doubles.
+#[cfg(test)]
+const HOT_EPOCH: i64 = 1_134_028_003;
+
+/// Reddit's hot ranking: every tenfold increase in score is worth as much as being
+/// 12.5 hours newer. The listings sort by the `hot_rank` SQL function; this copy lets
+/// the in-memory repo rank the same way, so the two must be kept in step.
+#[cfg(test)]
+pub fn hot_rank(score: i32, timestamp: DateTime<Utc>) -> f64 {
+    let order = f64::from(score.abs().max(1)).log10();
+    let seconds = (timestamp.timestamp_micros() - HOT_EPOCH * 1_000_000) as f64 / 1_000_000.0;
+
+    f64::from(score.signum()) * order + seconds / 45_000.0
+}
+
+/// Posts with many votes split evenly rank highest; one-sided posts rank 0. Copy of the
+/// `controversy_rank` SQL function, like `hot_rank`.
+#[cfg(test)]
+pub fn controversy_rank(upvotes: i32, downvotes: i32) -> f64 {
+    if upvotes <= 0 || downvotes <= 0 {
+        return 0.0;
+    }
+
+    let magnitude = f64::from(upvotes + downvotes);
+    let balance = f64::from(upvotes.min(downvotes)) / f64::from(upvotes.max(downvotes));
+    magnitude.powf(balance)
+}
+
+#[async_trait]
+pub trait PostRepository: Send + Sync {
+    async fn create_post(&self, post: &Post) -> Result<Uuid, sqlx::Error>;
+    async fn get_post(&self, post_id: Uuid) -> Result<Post, sqlx::Error>;
+    async fn get_posts_by_ids(
+        &self,
+        post_ids: &[Uuid],
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error>;
+    async fn get_posts_by_sub(
+        &self,
+        sub_name: &str,
+        include_nsfw: bool,
+        listing: &Listing,
+    ) -> Result<Vec<RankedPost>, sqlx::Error>;
+    async fn get_pinned_posts(
+        &self,
+        sub_name: &str,
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error>;
+    async fn pin_post(
+        &self,
+        post_id: Uuid,
+        order: Option<i32>,
+        max_pins: i32,
+    ) -> Result<Option<i32>, sqlx::Error>;
+    async fn unpin_post(&self, post_id: Uuid) -> Result<bool, sqlx::Error>;
+    async fn lock_post(&self, post_id: Uuid, reason: Option<&str>) -> Result<(), sqlx::Error>;
+    async fn unlock_post(&self, post_id: Uuid) -> Result<bool, sqlx::Error>;
+    async fn set_post_flags(
+        &self,
+        post_id: Uuid,
+        nsfw: bool,
+        spoiler: bool,
+    ) -> Result<(), sqlx::Error>;
+    async fn create_poll(
+        &self,
+        post_id: Uuid,
+        options: &[String],
+        ends_at: Option<DateTime<Utc>>,
+        hide_results: bool,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_poll(&self, post_id: Uuid) -> Result<Option<Poll>, sqlx::Error>;
+    async fn save_link_preview(
+        &self,
+        post_id: Uuid,
+        preview: &LinkPreview,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_link_preview(&self, post_id: Uuid) -> Result<Option<LinkPreview>, sqlx::Error>;
+    async fn get_post_media(&self, post_id: Uuid) -> Result<Vec<Media>, sqlx::Error>;
+    async fn get_post_removal_reason(&self, post_id: Uuid) -> Result<Option<String>, sqlx::Error>;
+    async fn get_poll_vote(&self, post_id: Uuid, user_id: i32) -> Result<Option<i32>, sqlx::Error>;
+    async fn cast_poll_vote(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+        option_id: i32,
+    ) -> Result<bool, sqlx::Error>;
+    async fn get_posts_by_user(
+        &self,
+        user_id: i32,
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error>;
+    async fn get_crossposts(
+        &self,
+        post_id: Uuid,
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error>;
+    async fn get_drafts_by_user(&self, user_id: i32) -> Result<Vec<Post>, sqlx::Error>;
+    async fn publish_post(&self, post_id: Uuid) -> Result<bool, sqlx::Error>;
+    async fn get_all_posts(
+        &self,
+        include_nsfw: bool,
+        languages: &[String],
+        listing: &Listing,
+    ) -> Result<Vec<RankedPost>, sqlx::Error>;
+    async fn get_popular_posts(
+        &self,
+        include_nsfw: bool,
+        languages: &[String],
+        listing: &Listing,
+    ) -> Result<Vec<RankedPost>, sqlx::Error>;
+    async fn update_post(
+        &self,
+        post_id: Uuid,
+        update_content: String,
+        removal: Option<RemovalKind>,
+    ) -> Result<Uuid, sqlx::Error>;
+    async fn get_post_revision(
+        &self,
+        post_id: Uuid,
+        revision: i32,
+    ) -> Result<Revision, sqlx::Error>;
+    async fn delete_post(&self, post_id: Uuid) -> Result<(), sqlx::Error>;
+    async fn set_post_vote(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+        vote: Option<i16>,
+    ) -> Result<i32, sqlx::Error>;
+    async fn save_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error>;
+    async fn unsave_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error>;
+    async fn get_saved_post_ids(
+        &self,
+        user_id: i32,
+        post_ids: &[Uuid],
+    ) -> Result<HashSet<Uuid>, sqlx::Error>;
+    async fn hide_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error>;
+    async fn unhide_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error>;
+    async fn get_hidden_posts(
+        &self,
+        user_id: i32,
+        include_nsfw: bool,
+    ) -> Result<Vec<Post>, sqlx::Error>;
+}
+
+#[async_trait]
+impl PostRepository for PgPool {
+    async fn create_post(&self, post: &Post) -> Result<Uuid, sqlx::Error> {
+        create_post(self, post).await
+    }
+
+    async fn get_post(&self, post_id: Uuid) -> Result<Post, sqlx::Error> {
+        get_post(self, post_id).await
+    }
+
+    async fn get_posts_by_ids(
+        &self,
+        post_ids: &[Uuid],
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        get_posts_by_ids(self, post_ids, include_nsfw, viewer).await
+    }
+
+    async fn get_posts_by_sub(
+        &self,
+        sub_name: &str,
+        include_nsfw: bool,
+        listing: &Listing,
+    ) -> Result<Vec<RankedPost>, sqlx::Error> {
+        get_posts_by_sub(self, sub_name, include_nsfw, listing).await
+    }
+
+    async fn get_pinned_posts(
+        &self,
+        sub_name: &str,
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        get_pinned_posts(self, sub_name, include_nsfw, viewer).await
+    }
+
+    async fn pin_post(
+        &self,
+        post_id: Uuid,
+        order: Option<i32>,
+        max_pins: i32,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        pin_post(self, post_id, order, max_pins).await
+    }
+
+    async fn unpin_post(&self, post_id: Uuid) -> Result<bool, sqlx::Error> {
+        unpin_post(self, post_id).await
+    }
+
+    async fn lock_post(&self, post_id: Uuid, reason: Option<&str>) -> Result<(), sqlx::Error> {
+        lock_post(self, post_id, reason).await
+    }
+
+    async fn set_post_flags(
+        &self,
+        post_id: Uuid,
+        nsfw: bool,
+        spoiler: bool,
+    ) -> Result<(), sqlx::Error> {
+        set_post_flags(self, post_id, nsfw, spoiler).await
+    }
+
+    async fn unlock_post(&self, post_id: Uuid) -> Result<bool, sqlx::Error> {
+        unlock_post(self, post_id).await
+    }
+
+    async fn create_poll(
+        &self,
+        post_id: Uuid,
+        options: &[String],
+        ends_at: Option<DateTime<Utc>>,
+        hide_results: bool,
+    ) -> Result<(), sqlx::Error> {
+        poll_repo::create_poll(self, post_id, options, ends_at, hide_results).await
+    }
+
+    async fn get_poll(&self, post_id: Uuid) -> Result<Option<Poll>, sqlx::Error> {
+        poll_repo::get_poll(self, post_id).await
+    }
+
+    async fn save_link_preview(
+        &self,
+        post_id: Uuid,
+        preview: &LinkPreview,
+    ) -> Result<(), sqlx::Error> {
+        link_preview_repo::save_link_preview(self, post_id, preview).await
+    }
+
+    async fn get_link_preview(&self, post_id: Uuid) -> Result<Option<LinkPreview>, sqlx::Error> {
+        link_preview_repo::get_link_preview(self, post_id).await
+    }
+
+    async fn get_post_media(&self, post_id: Uuid) -> Result<Vec<Media>, sqlx::Error> {
+        media_repo::get_post_media(self, post_id).await
+    }
+
+    async fn get_post_removal_reason(&self, post_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+        removal_reason_repo::get_post_removal_reason(self, post_id).await
+    }
+
+    async fn get_poll_vote(&self, post_id: Uuid, user_id: i32) -> Result<Option<i32>, sqlx::Error> {
+        poll_repo::get_poll_vote(self, post_id, user_id).await
+    }
+
+    async fn cast_poll_vote(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+        option_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        poll_repo::cast_poll_vote(self, post_id, user_id, option_id).await
+    }
+
+    async fn get_posts_by_user(
+        &self,
+        user_id: i32,
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        get_posts_by_user(self, user_id, include_nsfw, viewer).await
+    }
+
+    async fn get_crossposts(
+        &self,
+        post_id: Uuid,
+        include_nsfw: bool,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        get_crossposts(self, post_id, include_nsfw, viewer).await
+    }
+
+    async fn get_drafts_by_user(&self, user_id: i32) -> Result<Vec<Post>, sqlx::Error> {
+        get_drafts_by_user(self, user_id).await
+    }
+
+    async fn publish_post(&self, post_id: Uuid) -> Result<bool, sqlx::Error> {
+        publish_post(self, post_id).await
+    }
+
+    async fn get_all_posts(
+        &self,
+        include_nsfw: bool,
+        languages: &[String],
+        listing: &Listing,
+    ) -> Result<Vec<RankedPost>, sqlx::Error> {
+        get_all_posts(self, include_nsfw, languages, listing).await
+    }
+
+    async fn get_popular_posts(
+        &self,
+        include_nsfw: bool,
+        languages: &[String],
+        listing: &Listing,
+    ) -> Result<Vec<RankedPost>, sqlx::Error> {
+        get_popular_posts(self, include_nsfw, languages, listing).await
+    }
+
+    async fn update_post(
+        &self,
+        post_id: Uuid,
+        update_content: String,
+        removal: Option<RemovalKind>,
+    ) -> Result<Uuid, sqlx::Error> {
+        update_post(self, post_id, update_content, removal).await
+    }
+
+    async fn get_post_revision(
+        &self,
+        post_id: Uuid,
+        revision: i32,
+    ) -> Result<Revision, sqlx::Error> {
+        get_post_revision(self, post_id, revision).await
+    }
+
+    async fn delete_post(&self, post_id: Uuid) -> Result<(), sqlx::Error> {
+        delete_post(self, post_id).await
+    }
+
+    async fn set_post_vote(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+        vote: Option<i16>,
+    ) -> Result<i32, sqlx::Error> {
+        set_post_vote(self, post_id, user_id, vote).await
+    }
+
+    async fn save_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        saved_repo::save_post(self, post_id, user_id).await
+    }
+
+    async fn unsave_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        saved_repo::unsave_post(self, post_id, user_id).await
+    }
+
+    async fn get_saved_post_ids(
+        &self,
+        user_id: i32,
+        post_ids: &[Uuid],
+    ) -> Result<HashSet<Uuid>, sqlx::Error> {
+        saved_repo::get_saved_post_ids(self, user_id, post_ids).await
+    }
+
+    async fn hide_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        hide_post(self, post_id, user_id).await
+    }
+
+    async fn unhide_post(&self, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+        unhide_post(self, post_id, user_id).await
+    }
+
+    async fn get_hidden_posts(
+        &self,
+        user_id: i32,
+        include_nsfw: bool,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        get_hidden_posts(self, user_id, include_nsfw).await
+    }
+}
+
+#[cfg(test)]
+mod post_repo_tests {
+    use super::*;
+    use crate::model::post::{ListingCursor, PostPage, PostSort};
+    use crate::model::sub::ModPermissions;
+    use crate::test_support::fixtures::{PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+    use chrono::Duration;
+
+    #[test]
+    fn test_hot_rank_trades_score_for_age() {
+        let now = Utc::now();
+        let earlier = now - Duration::hours(12) - Duration::minutes(30);
+
+        assert!(hot_rank(1, now) > hot_rank(1, earlier));
+        assert!((hot_rank(10, earlier) - hot_rank(1, now)).abs() < 1e-6);
+        assert!(hot_rank(-5, now) < hot_rank(0, now));
+    }
+
+    #[test]
+    fn test_controversy_rank_favours_even_splits() {
+        assert_eq!(controversy_rank(10, 0), 0.0);
+        assert!(controversy_rank(10, 10) > controversy_rank(15, 5));
+        assert!(controversy_rank(20, 20) > controversy_rank(10, 10));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_sub_listing_sorts_by_votes() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let voters = [
+            UserFixture::new("first").insert(&db.pool).await,
+            UserFixture::new("second").insert(&db.pool).await,
+        ];
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let loved = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let split = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let newest = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        for voter in &voters {
+            set_post_vote(&db.pool, loved.id, voter.id, Some(1))
+                .await
+                .unwrap();
+        }
+        set_post_vote(&db.pool, split.id, voters[0].id, Some(1))
+            .await
+            .unwrap();
+        set_post_vote(&db.pool, split.id, voters[1].id, Some(-1))
+            .await
+            .unwrap();
+
+        let pool = &db.pool;
+        let first = |sort| async move {
+            let listing = Listing::first_page(sort, 10);
+            let posts = get_posts_by_sub(pool, "rust", false, &listing).await;
+            posts.unwrap()[0].post.id
+        };
+        assert_eq!(first(PostSort::Top).await, loved.id);
+        assert_eq!(first(PostSort::Hot).await, loved.id);
+        assert_eq!(first(PostSort::New).await, newest.id);
+        assert_eq!(first(PostSort::Controversial).await, split.id);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_sub_listing_pages_with_cursors() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        for _ in 0..3 {
+            PostFixture::new(&sub, &author).insert(&db.pool).await;
+        }
+
+        let mut listing = Listing::first_page(PostSort::Top, 2);
+        let first = PostPage::new(
+            get_posts_by_sub(&db.pool, "rust", false, &listing)
+                .await
+                .unwrap(),
+            &listing,
+        );
+        assert_eq!(first.posts.len(), 2);
+
+        listing.after = first.next_cursor.as_deref().and_then(ListingCursor::decode);
+        let second = PostPage::new(
+            get_posts_by_sub(&db.pool, "rust", false, &listing)
+                .await
+                .unwrap(),
+            &listing,
+        );
+        assert_eq!(second.posts.len(), 1);
+        assert!(second.next_cursor.is_none());
+        assert!(first
+            .posts
+            .iter()
+            .all(|listed| listed.post.id != second.posts[0].post.id));
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_sub_listing_hides_nsfw_unless_requested() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let nsfw_sub = SubFixture::new("after_dark").nsfw().insert(&db.pool).await;
+        PostFixture::new(&sub, &author).insert(&db.pool).await;
+        PostFixture::new(&sub, &author)
+            .nsfw()
+            .insert(&db.pool)
+            .await;
+        let inherited = PostFixture::new(&nsfw_sub, &author).insert(&db.pool).await;
+
+        assert_eq!(
+            get_posts_by_sub(
+                &db.pool,
+                "rust",
+                false,
+                &Listing::first_page(PostSort::New, 10)
+            )
+            .await
+            .unwrap()
+            .len(),
+            1
+        );
+        assert_eq!(
+            get_posts_by_sub(
+                &db.pool,
+                "rust",
+                true,
+                &Listing::first_page(PostSort::New, 10)
+            )
+            .await
+            .unwrap()
+            .len(),
+            2
+        );
+        assert!(get_post(&db.pool, inherited.id).await.unwrap().nsfw);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_hidden_posts_stay_out_of_the_hider_listings() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let reader = UserFixture::new("reader").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let hidden = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let pinned = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        PostFixture::new(&sub, &author).insert(&db.pool).await;
+        pin_post(&db.pool, pinned.id, None, 2).await.unwrap();
+        hide_post(&db.pool, hidden.id, reader.id).await.unwrap();
+        hide_post(&db.pool, pinned.id, reader.id).await.unwrap();
+
+        let listing = Listing {
+            viewer: Some(reader.id),
+            ..Listing::first_page(PostSort::New, 10)
+        };
+        let listed = get_all_posts(&db.pool, false, &[], &listing).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(get_posts_by_sub(&db.pool, "rust", false, &listing)
+            .await
+            .unwrap()
+            .iter()
+            .all(|ranked| ranked.post.id != hidden.id));
+        assert!(get_pinned_posts(&db.pool, "rust", false, Some(reader.id))
+            .await
+            .unwrap()
+            .is_empty());
+        let others = Listing::first_page(PostSort::New, 10);
+        assert_eq!(
+            get_all_posts(&db.pool, false, &[], &others)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+        let hidden_posts = get_hidden_posts(&db.pool, reader.id, false).await.unwrap();
+        assert_eq!(hidden_posts[0].id, pinned.id);
+
+        unhide_post(&db.pool, hidden.id, reader.id).await.unwrap();
+        assert_eq!(
+            get_all_posts(&db.pool, false, &[], &listing)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_shadowbanned_posts_are_only_listed_for_author_and_moderators() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let reader = UserFixture::new("reader").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        crate::repo::sub::add_sub_moderator(
+            &db.pool,
+            "rust",
+            moderator.id,
+            None,
+            ModPermissions::FULL,
+        )
+        .await
+        .unwrap();
+        crate::repo::user::set_shadowbanned(&db.pool, author.id, true)
+            .await
+            .unwrap();
+
+        let listed = |viewer: Option<i32>| {
+            let pool = db.pool.clone();
+            async move {
+                let listing = Listing {
+                    viewer,
+                    ..Listing::first_page(PostSort::New, 10)
+                };
+                get_posts_by_sub(&pool, "rust", false, &listing)
+                    .await
+                    .unwrap()
+                    .len()
+            }
+        };
+        assert_eq!(listed(None).await, 0);
+        assert_eq!(listed(Some(reader.id)).await, 0);
+        assert_eq!(listed(Some(author.id)).await, 1);
+        assert_eq!(listed(Some(moderator.id)).await, 1);
+        assert!(
+            get_posts_by_ids(&db.pool, &[post.id], false, Some(reader.id))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(get_posts_by_user(&db.pool, author.id, false, None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        crate::repo::user::set_shadowbanned(&db.pool, author.id, false)
+            .await
+            .unwrap();
+        assert_eq!(listed(None).await, 1);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_home_lists_only_subscribed_subs() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let reader = UserFixture::new("reader").insert(&db.pool).await;
+        let rust = SubFixture::new("rust").insert(&db.pool).await;
+        let golang = SubFixture::new("golang").insert(&db.pool).await;
+        let subscribed = PostFixture::new(&rust, &author).insert(&db.pool).await;
+        PostFixture::new(&golang, &author).insert(&db.pool).await;
+        crate::repo::sub::subscribe_user_to_sub(&db.pool, reader.id, &rust.name)
+            .await
+            .unwrap();
+
+        let listing = Listing {
+            viewer: Some(reader.id),
+            ..Listing::first_page(PostSort::New, 10)
+        };
+        let listed = get_home_posts(&db.pool, false, &listing).await.unwrap();
+        let ids: Vec<Uuid> = listed.iter().map(|ranked| ranked.post.id).collect();
+        assert_eq!(ids, [subscribed.id]);
+        let anonymous = Listing::first_page(PostSort::New, 10);
+        assert!(get_home_posts(&db.pool, false, &anonymous)
+            .await
+            .unwrap()
+            .is_empty());
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_drafts_are_listed_once_published() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let draft = PostFixture::new(&sub, &author)
+            .draft()
+            .insert(&db.pool)
+            .await;
+
+        let listing = Listing::first_page(PostSort::New, 10);
+        let listed = || async {
+            get_posts_by_sub(&db.pool, "rust", false, &listing)
+                .await
+                .unwrap()
+                .len()
+        };
+        assert_eq!(listed().await, 0);
+        assert!(get_posts_by_user(&db.pool, author.id, false, None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            get_drafts_by_user(&db.pool, author.id).await.unwrap().len(),
+            1
+        );
+
+        assert!(publish_post(&db.pool, draft.id).await.unwrap());
+        assert!(!publish_post(&db.pool, draft.id).await.unwrap());
+        assert_eq!(listed().await, 1);
+        assert!(get_drafts_by_user(&db.pool, author.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_scheduled_posts_are_published_once_due() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let due_at = Utc::now() - chrono::Duration::minutes(1);
+        let due = PostFixture::new(&sub, &author)
+            .scheduled(due_at)
+            .insert(&db.pool)
+            .await;
+        PostFixture::new(&sub, &author)
+            .scheduled(Utc::now() + chrono::Duration::hours(1))
+            .insert(&db.pool)
+            .await;
+
+        assert_eq!(publish_scheduled_posts(&db.pool).await.unwrap(), 1);
+        assert_eq!(publish_scheduled_posts(&db.pool).await.unwrap(), 0);
+
+        let published = get_post(&db.pool, due.id).await.unwrap();
+        assert_eq!(published.status, PostStatus::Published);
+        assert_eq!(published.publish_at, None);
+        assert_eq!(published.timestamp.timestamp(), due_at.timestamp());
+        assert_eq!(
+            get_drafts_by_user(&db.pool, author.id).await.unwrap().len(),
+            1
+        );
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_scheduled_posts_of_banned_authors_are_unscheduled() {
+        let db = TestDatabase::new().await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let due = PostFixture::new(&sub, &author)
+            .scheduled(Utc::now() - Duration::minutes(1))
+            .insert(&db.pool)
+            .await;
+        crate::repo::sub::ban_user_from_sub(
+            &db.pool,
+            "rust",
+            author.id,
+            moderator.id,
+            "spam",
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(publish_scheduled_posts(&db.pool).await.unwrap(), 0);
+
+        let unscheduled = get_post(&db.pool, due.id).await.unwrap();
+        assert_eq!(unscheduled.status, PostStatus::Draft);
+        assert_eq!(unscheduled.publish_at, None);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_pinned_posts_are_limited_and_listed_apart() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let first = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let second = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let third = PostFixture::new(&sub, &author).insert(&db.pool).await;
+
+        assert_eq!(
+            pin_post(&db.pool, first.id, None, 2).await.unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            pin_post(&db.pool, second.id, None, 2).await.unwrap(),
+            Some(2)
+        );
+        assert_eq!(pin_post(&db.pool, third.id, None, 2).await.unwrap(), None);
+        // Moving a pinned post doesn't count against the limit
+        assert_eq!(
+            pin_post(&db.pool, second.id, Some(0), 2).await.unwrap(),
+            Some(0)
+        );
+
+        let pinned = get_pinned_posts(&db.pool, "rust", false, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            pinned.iter().map(|post| post.id).collect::<Vec<_>>(),
+            [second.id, first.id]
+        );
+        let listed = get_posts_by_sub(
+            &db.pool,
+            "rust",
+            false,
+            &Listing::first_page(PostSort::New, 10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].post.id, third.id);
+
+        assert!(unpin_post(&db.pool, first.id).await.unwrap());
+        assert!(!unpin_post(&db.pool, first.id).await.unwrap());
+        assert_eq!(
+            pin_post(&db.pool, third.id, None, 2).await.unwrap(),
+            Some(1)
+        );
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_recent_posts_of_a_link_are_found_in_its_sub() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let rust = SubFixture::new("rust").insert(&db.pool).await;
+        let go = SubFixture::new("go").insert(&db.pool).await;
+        let original = PostFixture::new(&rust, &author)
+            .link("https://www.example.com/news/?utm_source=feed")
+            .insert(&db.pool)
+            .await;
+        PostFixture::new(&go, &author)
+            .link("https://example.com/news")
+            .insert(&db.pool)
+            .await;
+
+        let since = Utc::now() - chrono::Duration::days(1);
+        let found = get_recent_posts_by_link(&db.pool, "rust", "example.com/news", since)
+            .await
+            .unwrap();
+        assert_eq!(
+            found.iter().map(|post| post.id).collect::<Vec<_>>(),
+            [original.id]
+        );
+        assert!(
+            get_recent_posts_by_link(&db.pool, "rust", "example.com/other", since)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_unarchived_posts_are_not_archived_again() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        PostFixture::new(&sub, &author)
+            .draft()
+            .insert(&db.pool)
+            .await;
+        let cutoff = Utc::now() + Duration::minutes(1);
+
+        assert_eq!(archive_posts_before(&db.pool, cutoff).await.unwrap(), 1);
+        assert!(get_post(&db.pool, post.id).await.unwrap().archived);
+
+        assert!(unarchive_post(&db.pool, post.id).await.unwrap());
+        assert!(!unarchive_post(&db.pool, post.id).await.unwrap());
+        assert_eq!(archive_posts_before(&db.pool, cutoff).await.unwrap(), 0);
+        assert!(!get_post(&db.pool, post.id).await.unwrap().archived);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_update_post_records_revision() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author)
+            .content("first draft")
+            .insert(&db.pool)
+            .await;
+
+        update_post(&db.pool, post.id, "second draft".to_string(), None)
+            .await
+            .unwrap();
+
+        let first = get_post_revision(&db.pool, post.id, 1).await.unwrap();
+        let second = get_post_revision(&db.pool, post.id, 2).await.unwrap();
+        assert_eq!(first.content, "first draft");
+        assert_eq!(second.content, "second draft");
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_held_edit_keeps_an_earlier_removal() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+
+        update_post(
+            &db.pool,
+            post.id,
+            "cheap watches".to_string(),
+            Some(RemovalKind::Filter),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            get_post(&db.pool, post.id).await.unwrap().removal,
+            Some(RemovalKind::Filter)
+        );
+
+        update_post(
+            &db.pool,
+            post.id,
+            "cheaper watches".to_string(),
+            Some(RemovalKind::Moderator),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            get_post(&db.pool, post.id).await.unwrap().removal,
+            Some(RemovalKind::Filter)
+        );
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,233 @@
+use crate::model::post_template::{PostTemplate, Repeat};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub async fn create_post_template(
+    pool: &PgPool,
+    sub: &str,
+    author_id: i32,
+    title: &str,
+    content: &str,
+    repeat: Repeat,
+    next_run_at: DateTime<Utc>,
+) -> Result<PostTemplate, sqlx::Error> {
+    let template = sqlx::query_as!(
+        PostTemplate,
+        r#"
+        INSERT INTO post_templates (sub, author_id, title, content, repeat, next_run_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, sub, author_id, title, content, repeat AS "repeat: Repeat", next_run_at,
+            created_at
+        "#,
+        sub,
+        author_id,
+        title,
+        content,
+        repeat.to_string(),
+        next_run_at
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(template)
+}
+
+pub async fn get_post_templates(
+    pool: &PgPool,
+    sub: &str,
+) -> Result<Vec<PostTemplate>, sqlx::Error> {
+    let templates = sqlx::query_as!(
+        PostTemplate,
+        r#"
+        SELECT id, sub, author_id, title, content, repeat AS "repeat: Repeat", next_run_at,
+            created_at
+        FROM post_templates
+        WHERE sub = $1
+        ORDER BY next_run_at, id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(templates)
+}
+
+/// The template, if it belongs to `sub`.
+pub async fn get_post_template(
+    pool: &PgPool,
+    sub: &str,
+    template_id: i32,
+) -> Result<Option<PostTemplate>, sqlx::Error> {
+    let template = sqlx::query_as!(
+        PostTemplate,
+        r#"
+        SELECT id, sub, author_id, title, content, repeat AS "repeat: Repeat", next_run_at,
+            created_at
+        FROM post_templates
+        WHERE id = $1 AND sub = $2
+        "#,
+        template_id,
+        sub
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(template)
+}
+
+/// Replaces the template's fields; `None` if it doesn't belong to `sub`.
+pub async fn update_post_template(
+    pool: &PgPool,
+    template: &PostTemplate,
+) -> Result<Option<PostTemplate>, sqlx::Error> {
+    let updated = sqlx::query_as!(
+        PostTemplate,
+        r#"
+        UPDATE post_templates
+        SET title = $3, content = $4, repeat = $5, next_run_at = $6
+        WHERE id = $1 AND sub = $2
+        RETURNING id, sub, author_id, title, content, repeat AS "repeat: Repeat", next_run_at,
+            created_at
+        "#,
+        template.id,
+        template.sub,
+        template.title,
+        template.content,
+        template.repeat.to_string(),
+        template.next_run_at
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(updated)
+}
+
+/// Returns whether the template belonged to `sub`.
+pub async fn delete_post_template(
+    pool: &PgPool,
+    sub: &str,
+    template_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM post_templates
+        WHERE id = $1 AND sub = $2
+        "#,
+        template_id,
+        sub
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+/// Templates whose next run has come.
+pub async fn get_due_post_templates(
+    pool: &PgPool,
+    now: DateTime<Utc>,
+) -> Result<Vec<PostTemplate>, sqlx::Error> {
+    let templates = sqlx::query_as!(
+        PostTemplate,
+        r#"
+        SELECT id, sub, author_id, title, content, repeat AS "repeat: Repeat", next_run_at,
+            created_at
+        FROM post_templates
+        WHERE next_run_at <= $1
+        ORDER BY next_run_at, id
+        "#,
+        now
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(templates)
+}
+
+/// Moves the template's run at `due_at` on to `next_run_at`. Returns `false` if the run
+/// was already claimed, by another instance or a moderator's edit, so it is posted once.
+pub async fn claim_post_template_run(
+    pool: &PgPool,
+    template_id: i32,
+    due_at: DateTime<Utc>,
+    next_run_at: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let claimed = sqlx::query!(
+        r#"
+        UPDATE post_templates
+        SET next_run_at = $3
+        WHERE id = $1 AND next_run_at = $2
+        "#,
+        template_id,
+        due_at,
+        next_run_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(claimed.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod post_template_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_due_template_runs_are_claimed_once() {
+        let db = TestDatabase::new().await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let now = Utc::now();
+        let due_at = now - chrono::Duration::minutes(5);
+        let template = create_post_template(
+            &db.pool,
+            &sub.name,
+            moderator.id,
+            "{weekday} questions",
+            "Ask away",
+            Repeat::Weekly,
+            due_at,
+        )
+        .await
+        .unwrap();
+        create_post_template(
+            &db.pool,
+            &sub.name,
+            moderator.id,
+            "Later",
+            "",
+            Repeat::Daily,
+            now + chrono::Duration::hours(1),
+        )
+        .await
+        .unwrap();
+
+        let due = get_due_post_templates(&db.pool, now).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, template.id);
+
+        let next_run_at = template
+            .repeat
+            .after(template.repeat.latest_due(template.next_run_at, now));
+        assert!(
+            claim_post_template_run(&db.pool, template.id, template.next_run_at, next_run_at)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !claim_post_template_run(&db.pool, template.id, template.next_run_at, next_run_at)
+                .await
+                .unwrap()
+        );
+        assert!(get_due_post_templates(&db.pool, now)
+            .await
+            .unwrap()
+            .is_empty());
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,286 @@
+use crate::model::premium::{
+    AwardCount, AwardType, PaymentEvent, PaymentEventKind, PremiumSubscription, SubscriptionSource,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn grant_premium(
+    pool: &PgPool,
+    user_id: i32,
+    admin_id: i32,
+    days: i32,
+) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let subscription = sqlx::query!(
+        r#"
+        INSERT INTO premium_subscriptions (user_id, source, granted_by, expires_at)
+        VALUES ($1, 'manual', $2, NOW() + make_interval(days => $3))
+        RETURNING id
+        "#,
+        user_id,
+        admin_id,
+        days
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    refresh_premium_flag(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    Ok(subscription.id)
+}
+
+/// Ends every running subscription of the user now, whatever its source.
+pub async fn revoke_premium(pool: &PgPool, user_id: i32) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE premium_subscriptions
+        SET expires_at = NOW(), cancelled_at = COALESCE(cancelled_at, NOW())
+        WHERE user_id = $1 AND expires_at > NOW()
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    refresh_premium_flag(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_subscriptions(
+    pool: &PgPool,
+    user_id: i32,
+) -> Result<Vec<PremiumSubscription>, sqlx::Error> {
+    let subscriptions = sqlx::query_as!(
+        PremiumSubscription,
+        r#"
+        SELECT id, source AS "source: SubscriptionSource", external_id, granted_by, starts_at,
+            expires_at, cancelled_at
+        FROM premium_subscriptions
+        WHERE user_id = $1
+        ORDER BY expires_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subscriptions)
+}
+
+pub async fn get_premium_expiry(
+    pool: &PgPool,
+    user_id: i32,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, sqlx::Error> {
+    let expiry = sqlx::query_scalar!(
+        r#"
+        SELECT MAX(expires_at)
+        FROM premium_subscriptions
+        WHERE user_id = $1 AND expires_at > NOW()
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(expiry)
+}
+
+/// Records an award on a post or a comment, whichever is given, and returns the awards
+/// it has received by type. Whether the giver may give that type is up to the caller.
+pub async fn give_award(
+    pool: &PgPool,
+    giver_id: i32,
+    award_type: AwardType,
+    post_id: Option<Uuid>,
+    comment_id: Option<Uuid>,
+) -> Result<Vec<AwardCount>, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO awards (award_type, giver_id, post_id, comment_id)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        award_type as AwardType,
+        giver_id,
+        post_id,
+        comment_id
+    )
+    .execute(pool)
+    .await?;
+
+    let counts = sqlx::query_as!(
+        AwardCount,
+        r#"
+        SELECT award_type AS "award_type: AwardType", COUNT(*) AS "count!"
+        FROM awards
+        WHERE ($1::UUID IS NOT NULL AND post_id = $1)
+        OR ($2::UUID IS NOT NULL AND comment_id = $2)
+        GROUP BY award_type
+        ORDER BY award_type
+        "#,
+        post_id,
+        comment_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(counts)
+}
+
+/// Applies a payment webhook event. Returns `false` without changing anything if the
+/// event was already processed.
+pub async fn apply_payment_event(
+    pool: &PgPool,
+    event: &PaymentEvent,
+    payload: &serde_json::Value,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let recorded = sqlx::query!(
+        r#"
+        INSERT INTO payment_webhook_events (event_id, event_type, payload)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (event_id) DO NOTHING
+        "#,
+        event.id,
+        event.kind.to_string(),
+        payload
+    )
+    .execute(&mut *tx)
+    .await?;
+    if recorded.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    match event.kind {
+        PaymentEventKind::Activated | PaymentEventKind::Renewed => {
+            sqlx::query!(
+                r#"
+                INSERT INTO premium_subscriptions (user_id, source, external_id, expires_at)
+                VALUES ($1, 'payment', $2, $3)
+                ON CONFLICT (external_id)
+                DO UPDATE SET expires_at = EXCLUDED.expires_at, cancelled_at = NULL
+                "#,
+                event.data.user_id,
+                event.data.subscription_id,
+                event.data.current_period_end
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        PaymentEventKind::Cancelled => {
+            sqlx::query!(
+                r#"
+                UPDATE premium_subscriptions
+                SET cancelled_at = NOW(), expires_at = $2
+                WHERE external_id = $1
+                "#,
+                event.data.subscription_id,
+                event.data.current_period_end
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    refresh_premium_flag(&mut tx, event.data.user_id).await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Switches off premium for members whose last subscription has run out.
+pub async fn expire_memberships(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET is_premium = FALSE
+        WHERE is_premium
+        AND NOT EXISTS (
+            SELECT 1 FROM premium_subscriptions
+            WHERE premium_subscriptions.user_id = users.id AND expires_at > NOW()
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+async fn refresh_premium_flag(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET is_premium = EXISTS (
+            SELECT 1 FROM premium_subscriptions
+            WHERE premium_subscriptions.user_id = users.id AND expires_at > NOW()
+        )
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod premium_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{CommentFixture, PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_awards_are_counted_per_post_and_comment() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let giver = UserFixture::new("giver").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let comment = CommentFixture::new(&post, &author).insert(&db.pool).await;
+
+        give_award(&db.pool, giver.id, AwardType::Gold, Some(post.id), None)
+            .await
+            .unwrap();
+        let counts = give_award(&db.pool, giver.id, AwardType::Gold, Some(post.id), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            counts,
+            [AwardCount {
+                award_type: AwardType::Gold,
+                count: 2
+            }]
+        );
+
+        let counts = give_award(
+            &db.pool,
+            giver.id,
+            AwardType::Helpful,
+            None,
+            Some(comment.id),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            counts,
+            [AwardCount {
+                award_type: AwardType::Helpful,
+                count: 1
+            }]
+        );
+
+        db.finish().await;
+    }
+}
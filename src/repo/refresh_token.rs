@@ -0,0 +1,178 @@
+use crate::model::refresh_token::RefreshOutcome;
+use crate::model::session::DeviceInfo;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Stores the first token of a new family, issued at login.
+pub async fn create_refresh_token(
+    pool: &PgPool,
+    user_id: i32,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+    device: &DeviceInfo,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (family_id, user_id, token_hash, expires_at, user_agent,
+            ip_address)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        token_hash,
+        expires_at,
+        device.user_agent,
+        device.ip_address
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Marks the presented token used and stores `new_hash` in its place. The row is
+/// locked, so of two requests racing with the same token one rotates and the other
+/// is treated as reuse.
+pub async fn rotate_refresh_token(
+    pool: &PgPool,
+    token_hash: &str,
+    new_hash: &str,
+    expires_at: DateTime<Utc>,
+    device: &DeviceInfo,
+) -> Result<RefreshOutcome, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let presented = sqlx::query!(
+        r#"
+        SELECT id, family_id, user_id, expires_at, used_at, revoked_at
+        FROM refresh_tokens
+        WHERE token_hash = $1
+        FOR UPDATE
+        "#,
+        token_hash
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(presented) = presented else {
+        return Ok(RefreshOutcome::Invalid);
+    };
+
+    if presented.used_at.is_some() {
+        sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = COALESCE(revoked_at, NOW())
+            WHERE family_id = $1
+            "#,
+            presented.family_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        return Ok(RefreshOutcome::Reused);
+    }
+    if presented.revoked_at.is_some() || presented.expires_at <= Utc::now() {
+        return Ok(RefreshOutcome::Invalid);
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE refresh_tokens
+        SET used_at = NOW()
+        WHERE id = $1
+        "#,
+        presented.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (family_id, user_id, token_hash, expires_at, user_agent,
+            ip_address)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        presented.family_id,
+        presented.user_id,
+        new_hash,
+        expires_at,
+        device.user_agent,
+        device.ip_address
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(RefreshOutcome::Rotated {
+        user_id: presented.user_id,
+    })
+}
+
+/// Revokes the family of the user's token `token_hash`, ending that login. Returns
+/// whether the token was found.
+pub async fn revoke_refresh_family(
+    pool: &PgPool,
+    user_id: i32,
+    token_hash: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE refresh_tokens
+        SET revoked_at = COALESCE(revoked_at, NOW())
+        WHERE family_id = (
+            SELECT family_id FROM refresh_tokens
+            WHERE token_hash = $1 AND user_id = $2
+        )
+        "#,
+        token_hash,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod refresh_token_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::UserFixture;
+    use crate::test_support::TestDatabase;
+    use chrono::Duration;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_reusing_a_rotated_token_revokes_the_family() {
+        let db = TestDatabase::new().await;
+        let user = UserFixture::new("mobile").insert(&db.pool).await;
+        let expires_at = Utc::now() + Duration::days(30);
+        let device = DeviceInfo {
+            user_agent: Some("ferris-mobile/1.0".to_string()),
+            ip_address: None,
+        };
+        create_refresh_token(&db.pool, user.id, "first", expires_at, &device)
+            .await
+            .unwrap();
+
+        let rotated = rotate_refresh_token(&db.pool, "first", "second", expires_at, &device)
+            .await
+            .unwrap();
+        assert_eq!(rotated, RefreshOutcome::Rotated { user_id: user.id });
+
+        let replayed = rotate_refresh_token(&db.pool, "first", "third", expires_at, &device)
+            .await
+            .unwrap();
+        assert_eq!(replayed, RefreshOutcome::Reused);
+
+        let successor = rotate_refresh_token(&db.pool, "second", "fourth", expires_at, &device)
+            .await
+            .unwrap();
+        assert_eq!(successor, RefreshOutcome::Invalid);
+
+        db.finish().await;
+    }
+}
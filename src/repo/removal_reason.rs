@@ -0,0 +1,207 @@
+use crate::model::removal_reason::RemovalReason;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub async fn create_removal_reason(
+    pool: &PgPool,
+    sub: &str,
+    title: &str,
+    message: &str,
+) -> Result<RemovalReason, sqlx::Error> {
+    let reason = sqlx::query_as!(
+        RemovalReason,
+        r#"
+        INSERT INTO sub_removal_reasons (sub, title, message)
+        VALUES ($1, $2, $3)
+        RETURNING id, sub, title, message, created_at
+        "#,
+        sub,
+        title,
+        message
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(reason)
+}
+
+/// The sub's removal reasons, oldest first.
+pub async fn get_removal_reasons(
+    pool: &PgPool,
+    sub: &str,
+) -> Result<Vec<RemovalReason>, sqlx::Error> {
+    let reasons = sqlx::query_as!(
+        RemovalReason,
+        r#"
+        SELECT id, sub, title, message, created_at
+        FROM sub_removal_reasons
+        WHERE sub = $1
+        ORDER BY id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(reasons)
+}
+
+/// The reason, if it belongs to `sub`.
+pub async fn get_removal_reason(
+    pool: &PgPool,
+    sub: &str,
+    reason_id: i32,
+) -> Result<Option<RemovalReason>, sqlx::Error> {
+    let reason = sqlx::query_as!(
+        RemovalReason,
+        r#"
+        SELECT id, sub, title, message, created_at
+        FROM sub_removal_reasons
+        WHERE id = $1 AND sub = $2
+        "#,
+        reason_id,
+        sub
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(reason)
+}
+
+/// Replaces the reason's title and message; `None` if it doesn't belong to `sub`.
+pub async fn update_removal_reason(
+    pool: &PgPool,
+    reason: &RemovalReason,
+) -> Result<Option<RemovalReason>, sqlx::Error> {
+    let updated = sqlx::query_as!(
+        RemovalReason,
+        r#"
+        UPDATE sub_removal_reasons
+        SET title = $3, message = $4
+        WHERE id = $1 AND sub = $2
+        RETURNING id, sub, title, message, created_at
+        "#,
+        reason.id,
+        reason.sub,
+        reason.title,
+        reason.message
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(updated)
+}
+
+/// Returns whether the reason belonged to `sub`. Content already removed with it keeps
+/// its message.
+pub async fn delete_removal_reason(
+    pool: &PgPool,
+    sub: &str,
+    reason_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM sub_removal_reasons
+        WHERE id = $1 AND sub = $2
+        "#,
+        reason_id,
+        sub
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+/// The message a moderator left when removing the post, if it is still removed.
+pub async fn get_post_removal_reason(
+    pool: &PgPool,
+    post_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let reason = sqlx::query_scalar!(
+        r#"
+        SELECT removal_reason AS "removal_reason!"
+        FROM posts
+        WHERE id = $1 AND removal_kind = 'moderator' AND removal_reason IS NOT NULL
+        "#,
+        post_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(reason)
+}
+
+/// The messages moderators left when removing comments on the post, by comment id.
+pub async fn get_comment_removal_reasons(
+    pool: &PgPool,
+    post_id: Uuid,
+) -> Result<HashMap<Uuid, String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, removal_reason AS "removal_reason!"
+        FROM comments
+        WHERE post_id = $1 AND removal_kind = 'moderator' AND removal_reason IS NOT NULL
+        "#,
+        post_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.id, row.removal_reason))
+        .collect())
+}
+
+#[cfg(test)]
+mod removal_reason_repo_tests {
+    use super::*;
+    use crate::model::removal_reason::removal_message;
+    use crate::model::report::ReportTarget;
+    use crate::repo::modqueue::{approve_queue_item, remove_queue_item};
+    use crate::test_support::fixtures::{PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_removed_posts_keep_the_message_they_were_sent() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let mut reason =
+            create_removal_reason(&db.pool, &sub.name, "Off topic", "Posts must be about Rust")
+                .await
+                .unwrap();
+        assert!(get_removal_reason(&db.pool, "golang", reason.id)
+            .await
+            .unwrap()
+            .is_none());
+
+        let message = removal_message(Some(&reason), "");
+        let target = ReportTarget::Post(post.id);
+        remove_queue_item(&db.pool, target, moderator.id, message.as_deref())
+            .await
+            .unwrap();
+        reason.message = "Stay on topic".to_string();
+        update_removal_reason(&db.pool, &reason).await.unwrap();
+        assert!(delete_removal_reason(&db.pool, &sub.name, reason.id)
+            .await
+            .unwrap());
+        let kept = get_post_removal_reason(&db.pool, post.id).await.unwrap();
+        assert_eq!(kept.as_deref(), Some("Posts must be about Rust"));
+
+        approve_queue_item(&db.pool, target, moderator.id)
+            .await
+            .unwrap();
+        assert!(get_post_removal_reason(&db.pool, post.id)
+            .await
+            .unwrap()
+            .is_none());
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,96 @@
+use crate::model::report::{Report, ReportTarget};
+use sqlx::PgPool;
+
+/// Files the report, or returns `None` if the reporter has already reported the target.
+pub async fn create_report(
+    pool: &PgPool,
+    sub: &str,
+    target: ReportTarget,
+    reporter_id: Option<i32>,
+    rule_id: Option<i32>,
+    reason: &str,
+) -> Result<Option<Report>, sqlx::Error> {
+    let (post_id, comment_id) = match target {
+        ReportTarget::Post(post_id) => (Some(post_id), None),
+        ReportTarget::Comment(comment_id) => (None, Some(comment_id)),
+    };
+    let report = sqlx::query_as!(
+        Report,
+        r#"
+        INSERT INTO reports (sub, post_id, comment_id, reporter_id, rule_id, reason)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT DO NOTHING
+        RETURNING id, sub, post_id, comment_id, reporter_id, rule_id, reason, created_at
+        "#,
+        sub,
+        post_id,
+        comment_id,
+        reporter_id,
+        rule_id,
+        reason
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod report_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{CommentFixture, PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_reports_are_kept_once_per_reporter() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let reporter = UserFixture::new("reporter").insert(&db.pool).await;
+        let other = UserFixture::new("other").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &author).insert(&db.pool).await;
+        let comment = CommentFixture::new(&post, &author).insert(&db.pool).await;
+        let on_post = ReportTarget::Post(post.id);
+
+        let report = create_report(
+            &db.pool,
+            &sub.name,
+            on_post,
+            Some(reporter.id),
+            None,
+            "Spam",
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            (report.post_id, report.comment_id, report.reason.as_str()),
+            (Some(post.id), None, "Spam")
+        );
+        assert!(create_report(
+            &db.pool,
+            &sub.name,
+            on_post,
+            Some(reporter.id),
+            None,
+            "Again"
+        )
+        .await
+        .unwrap()
+        .is_none());
+        for (target, reporter_id) in [
+            (on_post, other.id),
+            (ReportTarget::Comment(comment.id), reporter.id),
+        ] {
+            assert!(
+                create_report(&db.pool, &sub.name, target, Some(reporter_id), None, "Spam")
+                    .await
+                    .unwrap()
+                    .is_some()
+            );
+        }
+
+        db.finish().await;
+    }
+}
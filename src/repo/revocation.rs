@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Rejects the access token `jti` from now on, and clears out entries for tokens that
+/// have expired since.
+pub async fn revoke_access_token(
+    pool: &PgPool,
+    jti: Uuid,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM revoked_access_tokens
+        WHERE expires_at <= NOW()
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO revoked_access_tokens (jti, expires_at)
+        VALUES ($1, $2)
+        ON CONFLICT (jti) DO NOTHING
+        "#,
+        jti,
+        expires_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether the token was revoked on its own, or issued before its user signed out of
+/// all devices.
+pub async fn is_access_token_revoked(
+    pool: &PgPool,
+    jti: Uuid,
+    user_id: i32,
+    issued_at: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let revoked = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM revoked_access_tokens WHERE jti = $1)
+            OR EXISTS (SELECT 1 FROM users WHERE id = $2 AND tokens_revoked_at > $3)
+            AS "revoked!"
+        "#,
+        jti,
+        user_id,
+        issued_at
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(revoked)
+}
+
+/// Signs the user out everywhere: refresh tokens are revoked, sessions deleted and
+/// access tokens issued until now rejected.
+pub async fn revoke_all_tokens(pool: &PgPool, user_id: i32) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET tokens_revoked_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE refresh_tokens
+        SET revoked_at = COALESCE(revoked_at, NOW())
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM sessions
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod revocation_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::UserFixture;
+    use crate::test_support::TestDatabase;
+    use chrono::Duration;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_signing_out_everywhere_rejects_earlier_tokens() {
+        let db = TestDatabase::new().await;
+        let user = UserFixture::new("laptop").insert(&db.pool).await;
+        let issued_at = Utc::now() - Duration::minutes(1);
+
+        let before = is_access_token_revoked(&db.pool, Uuid::new_v4(), user.id, issued_at)
+            .await
+            .unwrap();
+        assert!(!before);
+
+        revoke_all_tokens(&db.pool, user.id).await.unwrap();
+        let after = is_access_token_revoked(&db.pool, Uuid::new_v4(), user.id, issued_at)
+            .await
+            .unwrap();
+        assert!(after);
+
+        let later = is_access_token_revoked(
+            &db.pool,
+            Uuid::new_v4(),
+            user.id,
+            Utc::now() + Duration::seconds(1),
+        )
+        .await
+        .unwrap();
+        assert!(!later);
+
+        db.finish().await;
+    }
+}
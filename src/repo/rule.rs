@@ -0,0 +1,143 @@
+use crate::model::rule::SubRule;
+use sqlx::PgPool;
+
+/// Adds the rule at `order`, or after the sub's other rules.
+pub async fn create_rule(
+    pool: &PgPool,
+    sub: &str,
+    title: &str,
+    description: &str,
+    order: Option<i32>,
+) -> Result<SubRule, sqlx::Error> {
+    let rule = sqlx::query_as!(
+        SubRule,
+        r#"
+        INSERT INTO sub_rules (sub, title, description, position)
+        VALUES ($1, $2, $3,
+            COALESCE($4, (SELECT COALESCE(MAX(position), 0) + 1 FROM sub_rules WHERE sub = $1)))
+        RETURNING id, sub, title, description, position AS "order", created_at
+        "#,
+        sub,
+        title,
+        description,
+        order
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(rule)
+}
+
+pub async fn get_rules(pool: &PgPool, sub: &str) -> Result<Vec<SubRule>, sqlx::Error> {
+    let rules = sqlx::query_as!(
+        SubRule,
+        r#"
+        SELECT id, sub, title, description, position AS "order", created_at
+        FROM sub_rules
+        WHERE sub = $1
+        ORDER BY position, id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rules)
+}
+
+/// The rule, if it belongs to `sub`.
+pub async fn get_rule(
+    pool: &PgPool,
+    sub: &str,
+    rule_id: i32,
+) -> Result<Option<SubRule>, sqlx::Error> {
+    let rule = sqlx::query_as!(
+        SubRule,
+        r#"
+        SELECT id, sub, title, description, position AS "order", created_at
+        FROM sub_rules
+        WHERE id = $1 AND sub = $2
+        "#,
+        rule_id,
+        sub
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(rule)
+}
+
+/// Replaces the rule's fields; `None` if it doesn't belong to `sub`.
+pub async fn update_rule(pool: &PgPool, rule: &SubRule) -> Result<Option<SubRule>, sqlx::Error> {
+    let updated = sqlx::query_as!(
+        SubRule,
+        r#"
+        UPDATE sub_rules
+        SET title = $3, description = $4, position = $5
+        WHERE id = $1 AND sub = $2
+        RETURNING id, sub, title, description, position AS "order", created_at
+        "#,
+        rule.id,
+        rule.sub,
+        rule.title,
+        rule.description,
+        rule.order
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(updated)
+}
+
+/// Returns whether the rule belonged to `sub`.
+pub async fn delete_rule(pool: &PgPool, sub: &str, rule_id: i32) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM sub_rules
+        WHERE id = $1 AND sub = $2
+        "#,
+        rule_id,
+        sub
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod rule_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::SubFixture;
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_rules_are_listed_in_order() {
+        let db = TestDatabase::new().await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let civil = create_rule(&db.pool, &sub.name, "Be civil", "", None)
+            .await
+            .unwrap();
+        let on_topic = create_rule(&db.pool, &sub.name, "Stay on topic", "", None)
+            .await
+            .unwrap();
+        let first = create_rule(&db.pool, &sub.name, "Read the FAQ", "", Some(0))
+            .await
+            .unwrap();
+        assert_eq!((civil.order, on_topic.order), (1, 2));
+
+        let rules = get_rules(&db.pool, &sub.name).await.unwrap();
+        let ids: Vec<i32> = rules.iter().map(|rule| rule.id).collect();
+        assert_eq!(ids, [first.id, civil.id, on_topic.id]);
+
+        assert!(!delete_rule(&db.pool, "golang", civil.id).await.unwrap());
+        assert!(delete_rule(&db.pool, &sub.name, civil.id).await.unwrap());
+        assert!(get_rule(&db.pool, &sub.name, civil.id)
+            .await
+            .unwrap()
+            .is_none());
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,163 @@
+use crate::model::saved::SavedEntry;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Saving an item that's already saved keeps when it was first saved.
+pub async fn save_post(pool: &PgPool, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO saved_items (user_id, post_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, post_id) DO NOTHING
+        "#,
+        user_id,
+        post_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn unsave_post(pool: &PgPool, post_id: Uuid, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM saved_items
+        WHERE user_id = $1 AND post_id = $2
+        "#,
+        user_id,
+        post_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn save_comment(
+    pool: &PgPool,
+    comment_id: Uuid,
+    user_id: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO saved_items (user_id, comment_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, comment_id) DO NOTHING
+        "#,
+        user_id,
+        comment_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn unsave_comment(
+    pool: &PgPool,
+    comment_id: Uuid,
+    user_id: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM saved_items
+        WHERE user_id = $1 AND comment_id = $2
+        "#,
+        user_id,
+        comment_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Which of `post_ids` the user has saved.
+pub async fn get_saved_post_ids(
+    pool: &PgPool,
+    user_id: i32,
+    post_ids: &[Uuid],
+) -> Result<HashSet<Uuid>, sqlx::Error> {
+    let saved = sqlx::query_scalar!(
+        r#"
+        SELECT post_id AS "post_id!"
+        FROM saved_items
+        WHERE user_id = $1 AND post_id = ANY($2)
+        "#,
+        user_id,
+        post_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(saved.into_iter().collect())
+}
+
+/// Up to `limit + 1` of the user's saved items before the one with id `before`, most
+/// recently saved first, so the caller can tell whether another page follows.
+pub async fn get_saved_entries(
+    pool: &PgPool,
+    user_id: i32,
+    before: Option<i64>,
+    limit: i64,
+) -> Result<Vec<SavedEntry>, sqlx::Error> {
+    let entries = sqlx::query_as!(
+        SavedEntry,
+        r#"
+        SELECT id, post_id, comment_id, saved_at
+        FROM saved_items
+        WHERE user_id = $1 AND ($2::BIGINT IS NULL OR id < $2)
+        ORDER BY id DESC
+        LIMIT $3
+        "#,
+        user_id,
+        before,
+        limit + 1
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod saved_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{CommentFixture, PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_saved_entries_page_newest_first_without_duplicates() {
+        let db = TestDatabase::new().await;
+        let reader = UserFixture::new("reader").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let post = PostFixture::new(&sub, &reader).insert(&db.pool).await;
+        let comment = CommentFixture::new(&post, &reader).insert(&db.pool).await;
+
+        save_post(&db.pool, post.id, reader.id).await.unwrap();
+        save_comment(&db.pool, comment.id, reader.id).await.unwrap();
+        save_post(&db.pool, post.id, reader.id).await.unwrap();
+
+        let first = get_saved_entries(&db.pool, reader.id, None, 1)
+            .await
+            .unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].comment_id, Some(comment.id));
+        let second = get_saved_entries(&db.pool, reader.id, Some(first[0].id), 1)
+            .await
+            .unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].post_id, Some(post.id));
+
+        unsave_post(&db.pool, post.id, reader.id).await.unwrap();
+        assert!(get_saved_post_ids(&db.pool, reader.id, &[post.id])
+            .await
+            .unwrap()
+            .is_empty());
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,561 @@
+use crate::model::comment::Comment;
+use crate::model::dto::CommentView;
+use crate::model::flair::AuthorFlair;
+use crate::model::moderation::RemovalKind;
+use crate::model::post::{Post, PostStatus};
+use crate::model::search::{CommentSearchHit, PostSearchHit, SearchSort};
+use crate::model::sub::{Sub, SubVisibility};
+use crate::model::user::User;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A search row: a post with its match rank and snippet.
+struct SearchedPost {
+    id: Uuid,
+    sub: String,
+    user_id: i32,
+    title: String,
+    content: String,
+    timestamp: DateTime<Utc>,
+    removal: Option<RemovalKind>,
+    nsfw: bool,
+    language: Option<String>,
+    score: i32,
+    status: PostStatus,
+    edited_at: Option<DateTime<Utc>>,
+    pin_order: Option<i32>,
+    locked: bool,
+    lock_reason: Option<String>,
+    archived: bool,
+    flair_id: Option<i32>,
+    crosspost_parent_id: Option<Uuid>,
+    link_url: Option<String>,
+    spoiler: bool,
+    publish_at: Option<DateTime<Utc>>,
+    author_flair: Option<AuthorFlair>,
+    rank: f32,
+    snippet: String,
+}
+
+impl From<SearchedPost> for PostSearchHit {
+    fn from(searched: SearchedPost) -> Self {
+        PostSearchHit {
+            post: Post {
+                id: searched.id,
+                sub: searched.sub,
+                user_id: searched.user_id,
+                title: searched.title,
+                content: searched.content,
+                timestamp: searched.timestamp,
+                removal: searched.removal,
+                nsfw: searched.nsfw,
+                spoiler: searched.spoiler,
+                publish_at: searched.publish_at,
+                language: searched.language,
+                score: searched.score,
+                status: searched.status,
+                edited_at: searched.edited_at,
+                pin_order: searched.pin_order,
+                locked: searched.locked,
+                lock_reason: searched.lock_reason,
+                archived: searched.archived,
+                flair_id: searched.flair_id,
+                crosspost_parent_id: searched.crosspost_parent_id,
+                link_url: searched.link_url,
+                author_flair: searched.author_flair,
+            },
+            rank: searched.rank,
+            snippet: searched.snippet,
+        }
+    }
+}
+
+/// Up to `limit` visible posts matching `query`, in web search syntax, optionally
+/// within one sub. Posts in private subs are left out unless `viewer` is a member, as are
+/// posts by shadowbanned authors that `viewer` may not see. An empty `languages` list means
+/// no language filter; untagged posts are always included, as in `get_all_posts`.
+/// Content is escaped before highlighting so the `<mark>` tags are the only markup in a
+/// snippet.
+#[allow(clippy::too_many_arguments)]
+pub async fn search_posts(
+    pool: &PgPool,
+    query: &str,
+    sub: Option<&str>,
+    viewer: Option<i32>,
+    include_nsfw: bool,
+    languages: &[String],
+    sort: SearchSort,
+    limit: i64,
+) -> Result<Vec<PostSearchHit>, sqlx::Error> {
+    let posts = sqlx::query_as!(
+        SearchedPost,
+        r#"
+        SELECT posts.id, posts.sub, posts.user_id, posts.title, posts.content, posts.timestamp,
+            posts.removal_kind AS "removal: RemovalKind", posts.nsfw OR subs.nsfw AS "nsfw!",
+            posts.language, posts.score, posts.status AS "status: PostStatus", posts.edited_at,
+            posts.pin_order, posts.locked, posts.lock_reason, posts.archived,
+            posts.flair_id, posts.crosspost_parent_id, posts.link_url, posts.spoiler, posts.publish_at,
+            author_flair(posts.user_id, posts.id) AS "author_flair: AuthorFlair",
+            ts_rank_cd(posts.search_vector, query) AS "rank!",
+            ts_headline(
+                'english',
+                replace(replace(replace(posts.content, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+                query,
+                'StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=30, MinWords=10'
+            ) AS "snippet!"
+        FROM posts
+        INNER JOIN subs ON subs.name = posts.sub
+        CROSS JOIN websearch_to_tsquery('english', $1) AS query
+        WHERE posts.search_vector @@ query AND posts.removed_at IS NULL
+        AND posts.status = 'published'
+        AND ($2::TEXT IS NULL OR posts.sub = $2)
+        AND ($3 OR NOT (posts.nsfw OR subs.nsfw))
+        AND (subs.visibility <> 'private' OR sub_member(subs.name, $6))
+        AND author_visible(posts.user_id, posts.sub, $6)
+        AND (cardinality($7::TEXT[]) = 0 OR posts.language IS NULL OR posts.language = ANY($7))
+        ORDER BY
+            CASE $4
+                WHEN 'new' THEN EXTRACT(EPOCH FROM posts.timestamp)::DOUBLE PRECISION
+                WHEN 'top' THEN posts.score::DOUBLE PRECISION
+                ELSE ts_rank_cd(posts.search_vector, query)::DOUBLE PRECISION
+            END DESC,
+            posts.id DESC
+        LIMIT $5
+        "#,
+        query,
+        sub,
+        include_nsfw,
+        sort.to_string(),
+        limit,
+        viewer,
+        languages
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(posts.into_iter().map(PostSearchHit::from).collect())
+}
+
+/// A search row: a comment with its match rank and snippet.
+struct SearchedComment {
+    id: Uuid,
+    post_id: Uuid,
+    user_id: i32,
+    content: String,
+    timestamp: DateTime<Utc>,
+    parent_id: Option<Uuid>,
+    removal: Option<RemovalKind>,
+    score: i32,
+    edited_at: Option<DateTime<Utc>>,
+    author_flair: Option<AuthorFlair>,
+    rank: f32,
+    snippet: String,
+}
+
+impl From<SearchedComment> for CommentSearchHit {
+    fn from(searched: SearchedComment) -> Self {
+        CommentSearchHit {
+            comment: CommentView::from(Comment {
+                id: searched.id,
+                post_id: searched.post_id,
+                user_id: searched.user_id,
+                content: searched.content,
+                timestamp: searched.timestamp,
+                parent_id: searched.parent_id,
+                removal: searched.removal,
+                score: searched.score,
+                edited_at: searched.edited_at,
+                author_flair: searched.author_flair,
+            }),
+            rank: searched.rank,
+            snippet: searched.snippet,
+        }
+    }
+}
+
+/// Up to `limit` comments matching `query`, optionally on one post or by one user, for
+/// moderators. Comments removed by moderators or held by a word filter are included
+/// with their original content; legally removed and deleted ones are not. Given
+/// `moderator_id`, only comments in the subs they moderate are searched.
+#[allow(clippy::too_many_arguments)]
+pub async fn search_comments(
+    pool: &PgPool,
+    query: &str,
+    post_id: Option<Uuid>,
+    user_id: Option<i32>,
+    moderator_id: Option<i32>,
+    sort: SearchSort,
+    limit: i64,
+) -> Result<Vec<CommentSearchHit>, sqlx::Error> {
+    let comments = sqlx::query_as!(
+        SearchedComment,
+        r#"
+        SELECT comments.id, comments.post_id, comments.user_id, comments.content,
+            comments.timestamp, comments.parent_id,
+            comments.removal_kind AS "removal: RemovalKind", comments.score,
+            comments.edited_at,
+            author_flair(comments.user_id, comments.post_id) AS "author_flair: AuthorFlair",
+            ts_rank_cd(comments.search_vector, query) AS "rank!",
+            ts_headline(
+                'english',
+                replace(replace(replace(comments.content, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+                query,
+                'StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=30, MinWords=10'
+            ) AS "snippet!"
+        FROM comments
+        CROSS JOIN websearch_to_tsquery('english', $1) AS query
+        WHERE comments.search_vector @@ query
+        AND (comments.removal_kind IS NULL OR comments.removal_kind NOT IN ('legal', 'deleted'))
+        AND ($2::UUID IS NULL OR comments.post_id = $2)
+        AND ($3::INTEGER IS NULL OR comments.user_id = $3)
+        AND ($4::INTEGER IS NULL OR comments.post_id IN (
+            SELECT posts.id FROM posts
+            INNER JOIN sub_moderators ON sub_moderators.sub = posts.sub
+            WHERE sub_moderators.user_id = $4
+        ))
+        ORDER BY
+            CASE $5
+                WHEN 'new' THEN EXTRACT(EPOCH FROM comments.timestamp)::DOUBLE PRECISION
+                WHEN 'top' THEN comments.score::DOUBLE PRECISION
+                ELSE ts_rank_cd(comments.search_vector, query)::DOUBLE PRECISION
+            END DESC,
+            comments.id DESC
+        LIMIT $6
+        "#,
+        query,
+        post_id,
+        user_id,
+        moderator_id,
+        sort.to_string(),
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(comments.into_iter().map(CommentSearchHit::from).collect())
+}
+
+/// Up to `limit` users whose name contains `query` or is close to it by trigram
+/// similarity. An exact match comes first, then the users with the most karma.
+pub async fn search_users(
+    pool: &PgPool,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<User>, sqlx::Error> {
+    let users = sqlx::query_as!(
+        User,
+        r#"
+        SELECT id, username, password_hash,
+            EXISTS(SELECT 1 FROM sub_moderators WHERE sub_moderators.user_id = users.id)
+                AS "is_moderator!",
+            is_admin, created_at, date_of_birth,
+            nsfw_acknowledged_at, preferred_languages, is_premium, email, email_verified_at,
+            post_karma, comment_karma, show_nsfw
+        FROM users
+        WHERE username % $1 OR strpos(lower(username), lower($1)) > 0
+        ORDER BY lower(username) = lower($1) DESC, post_karma + comment_karma DESC,
+            similarity(username, $1) DESC, username ASC
+        LIMIT $2
+        "#,
+        query,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(users)
+}
+
+/// Up to `limit` subs found like users in `search_users`, the most subscribed first.
+/// Private subs are left out unless `viewer` is a member.
+pub async fn search_subs(
+    pool: &PgPool,
+    query: &str,
+    viewer: Option<i32>,
+    limit: i64,
+) -> Result<Vec<Sub>, sqlx::Error> {
+    let subs = sqlx::query_as!(
+        Sub,
+        r#"
+        SELECT subs.name, subs.description, subs.created_at, subs.nsfw, subs.spoiler_by_default,
+            (SELECT COUNT(*) FROM subscriptions WHERE subscriptions.sub_name = subs.name)
+                AS "subscribers!",
+            subs.visibility AS "visibility: SubVisibility",
+            (SELECT url FROM media WHERE media.id = subs.icon_media_id) AS icon_url,
+            (SELECT url FROM media WHERE media.id = subs.banner_media_id) AS banner_url,
+            subs.primary_color, subs.accent_color
+        FROM subs
+        WHERE (subs.name % $1 OR strpos(lower(subs.name), lower($1)) > 0)
+        AND (subs.visibility <> 'private' OR sub_member(subs.name, $3))
+        ORDER BY lower(subs.name) = lower($1) DESC, "subscribers!" DESC,
+            similarity(subs.name, $1) DESC, subs.name ASC
+        LIMIT $2
+        "#,
+        query,
+        limit,
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subs)
+}
+
+#[cfg(test)]
+mod search_repo_tests {
+    use super::*;
+    use crate::model::sub::SubSort;
+    use crate::repo::sub as sub_repo;
+    use crate::test_support::fixtures::{CommentFixture, PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_post_search_ranks_and_highlights_matches() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let borrowing = PostFixture::new(&sub, &author)
+            .content("The <b>borrow</b> checker rejects two mutable borrows")
+            .insert(&db.pool)
+            .await;
+        PostFixture::new(&sub, &author)
+            .content("Lifetimes and traits")
+            .insert(&db.pool)
+            .await;
+        PostFixture::new(&sub, &author)
+            .content("Borrowing across threads")
+            .nsfw()
+            .insert(&db.pool)
+            .await;
+
+        let hits = search_posts(
+            &db.pool,
+            "borrow",
+            None,
+            None,
+            false,
+            &[],
+            SearchSort::Relevance,
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].post.id, borrowing.id);
+        assert!(hits[0].snippet.contains("<mark>borrow</mark>&lt;/b&gt;"));
+
+        let hits = search_posts(
+            &db.pool,
+            "borrow",
+            Some("rust"),
+            None,
+            true,
+            &[],
+            SearchSort::New,
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(hits.len(), 2);
+
+        let hits = search_posts(
+            &db.pool,
+            "borrow",
+            Some("go"),
+            None,
+            true,
+            &[],
+            SearchSort::Top,
+            10,
+        )
+        .await
+        .unwrap();
+        assert!(hits.is_empty());
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_post_search_keeps_to_preferred_languages() {
+        let db = TestDatabase::new().await;
+        let author = UserFixture::new("author").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let english = PostFixture::new(&sub, &author)
+            .content("Borrow checker tips")
+            .language("en")
+            .insert(&db.pool)
+            .await;
+        PostFixture::new(&sub, &author)
+            .content("Borrow checker Tipps")
+            .language("de")
+            .insert(&db.pool)
+            .await;
+        let untagged = PostFixture::new(&sub, &author)
+            .content("Borrow checker")
+            .insert(&db.pool)
+            .await;
+
+        let search = |languages: Vec<String>| {
+            let pool = db.pool.clone();
+            async move {
+                search_posts(
+                    &pool,
+                    "borrow",
+                    None,
+                    None,
+                    false,
+                    &languages,
+                    SearchSort::New,
+                    10,
+                )
+                .await
+                .unwrap()
+            }
+        };
+        let mut ids: Vec<Uuid> = search(vec!["en".to_string()])
+            .await
+            .iter()
+            .map(|hit| hit.post.id)
+            .collect();
+        ids.sort();
+        let mut expected = vec![english.id, untagged.id];
+        expected.sort();
+        assert_eq!(ids, expected);
+        assert_eq!(search(Vec::new()).await.len(), 3);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_comment_search_filters_by_post_and_author() {
+        let db = TestDatabase::new().await;
+        let alice = UserFixture::new("alice").insert(&db.pool).await;
+        let bob = UserFixture::new("bob").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let first = PostFixture::new(&sub, &alice).insert(&db.pool).await;
+        let second = PostFixture::new(&sub, &alice).insert(&db.pool).await;
+        let spam = CommentFixture::new(&first, &alice)
+            .content("Buy cheap watches here")
+            .insert(&db.pool)
+            .await;
+        CommentFixture::new(&first, &bob)
+            .content("Cheap shots aside, good post")
+            .insert(&db.pool)
+            .await;
+        CommentFixture::new(&second, &alice)
+            .content("Cheap watches again")
+            .insert(&db.pool)
+            .await;
+
+        let hits = search_comments(
+            &db.pool,
+            "cheap",
+            None,
+            None,
+            None,
+            SearchSort::Relevance,
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(hits.len(), 3);
+
+        let hits = search_comments(
+            &db.pool,
+            "watches",
+            Some(first.id),
+            Some(alice.id),
+            None,
+            SearchSort::New,
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].comment.id, spam.id);
+        assert!(hits[0].snippet.contains("<mark>watches</mark>"));
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_name_search_tolerates_typos() {
+        let db = TestDatabase::new().await;
+        let ferris = UserFixture::new("ferris_crab").insert(&db.pool).await;
+        UserFixture::new("someone").insert(&db.pool).await;
+        SubFixture::new("rustlang").insert(&db.pool).await;
+        SubFixture::new("golang").insert(&db.pool).await;
+
+        let users = search_users(&db.pool, "feris_crab", 10).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, ferris.id);
+
+        let subs = search_subs(&db.pool, "rustlnag", None, 10).await.unwrap();
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].name, "rustlang");
+
+        let subs = search_subs(&db.pool, "lang", None, 10).await.unwrap();
+        assert_eq!(subs.len(), 2);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_sub_search_hides_private_subs_from_non_members() {
+        let db = TestDatabase::new().await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let member = UserFixture::new("member").insert(&db.pool).await;
+        let outsider = UserFixture::new("outsider").insert(&db.pool).await;
+        SubFixture::new("rustlang").private().insert(&db.pool).await;
+        sub_repo::approve_sub_member(&db.pool, "rustlang", member.id, moderator.id)
+            .await
+            .unwrap();
+
+        let subs = search_subs(&db.pool, "rustlang", None, 10).await.unwrap();
+        assert!(subs.is_empty());
+        let subs = search_subs(&db.pool, "rustlang", Some(outsider.id), 10)
+            .await
+            .unwrap();
+        assert!(subs.is_empty());
+        let subs = search_subs(&db.pool, "rustlang", Some(member.id), 10)
+            .await
+            .unwrap();
+        assert_eq!(subs.len(), 1);
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_sub_listing_hides_private_subs_from_non_members() {
+        let db = TestDatabase::new().await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let member = UserFixture::new("member").insert(&db.pool).await;
+        let outsider = UserFixture::new("outsider").insert(&db.pool).await;
+        SubFixture::new("rust").insert(&db.pool).await;
+        SubFixture::new("rustlang").private().insert(&db.pool).await;
+        sub_repo::approve_sub_member(&db.pool, "rustlang", member.id, moderator.id)
+            .await
+            .unwrap();
+
+        let names = |subs: Vec<Sub>| subs.into_iter().map(|sub| sub.name).collect::<Vec<_>>();
+        for viewer in [None, Some(outsider.id)] {
+            let subs = sub_repo::get_all_subs(&db.pool, SubSort::Name, viewer)
+                .await
+                .unwrap();
+            assert_eq!(names(subs), ["rust"]);
+        }
+        let subs = sub_repo::get_all_subs(&db.pool, SubSort::Name, Some(member.id))
+            .await
+            .unwrap();
+        assert_eq!(names(subs), ["rust", "rustlang"]);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,193 @@
+use crate::model::session::{ActiveSession, DeviceInfo, SessionKind};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Stores a new session and clears out the user's expired ones.
+pub async fn create_session(
+    pool: &PgPool,
+    token_hash: &str,
+    user_id: i32,
+    expires_at: DateTime<Utc>,
+    device: &DeviceInfo,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM sessions
+        WHERE user_id = $1 AND expires_at <= NOW()
+        "#,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO sessions (token_hash, user_id, expires_at, user_agent, ip_address)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        token_hash,
+        user_id,
+        expires_at,
+        device.user_agent,
+        device.ip_address
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The user a live session belongs to, or `None` if it is unknown or has expired.
+/// `last_seen_at` is only written once a minute, not on every request.
+pub async fn get_session_user(pool: &PgPool, token_hash: &str) -> Result<Option<i32>, sqlx::Error> {
+    let session = sqlx::query!(
+        r#"
+        SELECT user_id, last_seen_at < NOW() - INTERVAL '1 minute' AS "stale!"
+        FROM sessions
+        WHERE token_hash = $1 AND expires_at > NOW()
+        "#,
+        token_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(session) = session else {
+        return Ok(None);
+    };
+    if session.stale {
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET last_seen_at = NOW()
+            WHERE token_hash = $1
+            "#,
+            token_hash
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(Some(session.user_id))
+}
+
+/// The user's live browser sessions and refresh token logins, most recently used first.
+pub async fn get_active_sessions(
+    pool: &PgPool,
+    user_id: i32,
+) -> Result<Vec<ActiveSession>, sqlx::Error> {
+    let sessions = sqlx::query_as!(
+        ActiveSession,
+        r#"
+        SELECT id AS "id!", 'cookie' AS "kind!: SessionKind", user_agent, ip_address,
+            created_at AS "signed_in_at!", last_seen_at AS "last_seen_at!"
+        FROM sessions
+        WHERE user_id = $1 AND expires_at > NOW()
+        UNION ALL
+        SELECT current.family_id, 'refresh_token', current.user_agent, current.ip_address,
+            (SELECT MIN(created_at) FROM refresh_tokens first
+             WHERE first.family_id = current.family_id),
+            current.created_at
+        FROM refresh_tokens current
+        WHERE current.user_id = $1 AND current.used_at IS NULL
+            AND current.revoked_at IS NULL AND current.expires_at > NOW()
+        ORDER BY 6 DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions)
+}
+
+/// Ends one of the user's logins by its id: a browser session is deleted, a refresh
+/// token family revoked. Returns whether a live one was found.
+pub async fn end_session(pool: &PgPool, user_id: i32, id: Uuid) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM sessions
+        WHERE id = $1 AND user_id = $2 AND expires_at > NOW()
+        "#,
+        id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let revoked = sqlx::query!(
+        r#"
+        UPDATE refresh_tokens
+        SET revoked_at = NOW()
+        WHERE family_id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(deleted.rows_affected() + revoked.rows_affected() > 0)
+}
+
+pub async fn delete_session(pool: &PgPool, token_hash: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM sessions
+        WHERE token_hash = $1
+        "#,
+        token_hash
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod session_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::UserFixture;
+    use crate::test_support::TestDatabase;
+    use chrono::Duration;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_ended_session_leaves_the_list() {
+        let db = TestDatabase::new().await;
+        let user = UserFixture::new("tablet").insert(&db.pool).await;
+        let device = DeviceInfo {
+            user_agent: Some("Firefox".to_string()),
+            ip_address: Some("192.0.2.1".to_string()),
+        };
+        create_session(
+            &db.pool,
+            "hash",
+            user.id,
+            Utc::now() + Duration::days(1),
+            &device,
+        )
+        .await
+        .unwrap();
+
+        let sessions = get_active_sessions(&db.pool, user.id).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].kind, SessionKind::Cookie);
+        assert_eq!(sessions[0].user_agent.as_deref(), Some("Firefox"));
+
+        assert!(end_session(&db.pool, user.id, sessions[0].id)
+            .await
+            .unwrap());
+        assert!(get_active_sessions(&db.pool, user.id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(get_session_user(&db.pool, "hash").await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,155 @@
+use crate::model::report::ReportTarget;
+use crate::model::spam::SpamScore;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// How many subs other than `sub` the author posted `content` or `canonical_url` to
+/// since `since`, in posts or comments.
+pub async fn count_duplicate_subs(
+    pool: &PgPool,
+    author_id: i32,
+    sub: &str,
+    content: Option<&str>,
+    canonical_url: Option<&str>,
+    since: DateTime<Utc>,
+) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(DISTINCT copies.sub) AS "count!"
+        FROM (
+            SELECT posts.sub
+            FROM posts
+            WHERE posts.user_id = $1 AND posts.sub <> $2 AND posts.timestamp > $5
+            AND (posts.content = $3 OR posts.canonical_url = $4)
+            UNION ALL
+            SELECT posts.sub
+            FROM comments
+            INNER JOIN posts ON posts.id = comments.post_id
+            WHERE comments.user_id = $1 AND posts.sub <> $2 AND comments.timestamp > $5
+            AND comments.content = $3
+        ) AS copies
+        "#,
+        author_id,
+        sub,
+        content,
+        canonical_url,
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// The author's posts and comments since `since`.
+pub async fn count_recent_submissions(
+    pool: &PgPool,
+    author_id: i32,
+    since: DateTime<Utc>,
+) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT (SELECT COUNT(*) FROM posts WHERE user_id = $1 AND timestamp > $2)
+            + (SELECT COUNT(*) FROM comments WHERE user_id = $1 AND timestamp > $2) AS "count!"
+        "#,
+        author_id,
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+pub async fn record_spam_score(
+    pool: &PgPool,
+    target: ReportTarget,
+    score: &SpamScore,
+) -> Result<(), sqlx::Error> {
+    let reasons: Vec<String> = score.reasons.iter().map(ToString::to_string).collect();
+    match target {
+        ReportTarget::Post(post_id) => {
+            sqlx::query!(
+                r#"
+                UPDATE posts
+                SET spam_score = $2, spam_reasons = $3
+                WHERE id = $1
+                "#,
+                post_id,
+                score.score,
+                &reasons
+            )
+            .execute(pool)
+            .await?;
+        }
+        ReportTarget::Comment(comment_id) => {
+            sqlx::query!(
+                r#"
+                UPDATE comments
+                SET spam_score = $2, spam_reasons = $3
+                WHERE id = $1
+                "#,
+                comment_id,
+                score.score,
+                &reasons
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod spam_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{CommentFixture, PostFixture, SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+    use chrono::Duration;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_copies_in_other_subs_are_counted() {
+        let db = TestDatabase::new().await;
+        let spammer = UserFixture::new("spammer").insert(&db.pool).await;
+        let other = UserFixture::new("other").insert(&db.pool).await;
+        let rust = SubFixture::new("rust").insert(&db.pool).await;
+        let go = SubFixture::new("go").insert(&db.pool).await;
+        let zig = SubFixture::new("zig").insert(&db.pool).await;
+        let pitch = "Buy cheap followers at my site today";
+        PostFixture::new(&rust, &spammer)
+            .content(pitch)
+            .insert(&db.pool)
+            .await;
+        let post = PostFixture::new(&go, &other).insert(&db.pool).await;
+        CommentFixture::new(&post, &spammer)
+            .content(pitch)
+            .insert(&db.pool)
+            .await;
+        PostFixture::new(&zig, &other)
+            .content(pitch)
+            .insert(&db.pool)
+            .await;
+        let since = Utc::now() - Duration::hours(1);
+
+        let copies =
+            count_duplicate_subs(&db.pool, spammer.id, &zig.name, Some(pitch), None, since)
+                .await
+                .unwrap();
+        assert_eq!(copies, 2);
+        let copies =
+            count_duplicate_subs(&db.pool, spammer.id, &rust.name, Some(pitch), None, since)
+                .await
+                .unwrap();
+        assert_eq!(copies, 1);
+        assert_eq!(
+            count_recent_submissions(&db.pool, spammer.id, since)
+                .await
+                .unwrap(),
+            2
+        );
+
+        db.finish().await;
+    }
+}
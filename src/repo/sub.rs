@@ -1,15 +1,27 @@
-use crate::model::sub::Sub;
+use crate::model::account_requirement::{AccountRequirement, Contribution};
+use crate::model::ban::SubBan;
+use crate::model::mod_note::ModNote;
+use crate::model::sub::{
+    ModPermissions, Sub, SubAppearance, SubJoinRequest, SubMember, SubModerator,
+    SubModeratorInvite, SubSort, SubVisibility,
+};
+use crate::repo::{account_requirement as account_requirement_repo, mod_note as mod_note_repo};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 
 pub async fn create_sub(pool: &PgPool, sub: &Sub) -> Result<String, sqlx::Error> {
     sqlx::query!(
         r#"
-        INSERT INTO subs (name, description, created_at)
-        VALUES ($1, $2, $3)
+        INSERT INTO subs (name, description, created_at, nsfw, spoiler_by_default, visibility)
+        VALUES ($1, $2, $3, $4, $5, $6)
         "#,
         sub.name,
         sub.description,
         sub.created_at,
+        sub.nsfw,
+        sub.spoiler_by_default,
+        sub.visibility as SubVisibility,
     )
     .execute(pool)
     .await?;
@@ -17,6 +29,20 @@ pub async fn create_sub(pool: &PgPool, sub: &Sub) -> Result<String, sqlx::Error>
     Ok(sub.name.clone())
 }
 
+/// Whether a sub already goes by the name, ignoring case.
+pub async fn sub_name_taken(pool: &PgPool, name: &str) -> Result<bool, sqlx::Error> {
+    let taken = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM subs WHERE LOWER(name) = LOWER($1)) AS "taken!"
+        "#,
+        name
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(taken)
+}
+
 pub async fn subscribe_user_to_sub(
     pool: &PgPool,
     user_id: i32,
@@ -26,6 +52,26 @@ pub async fn subscribe_user_to_sub(
         r#"
         INSERT INTO subscriptions (user_id, sub_name)
         VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        sub_name
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn unsubscribe_user_from_sub(
+    pool: &PgPool,
+    user_id: i32,
+    sub_name: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM subscriptions
+        WHERE user_id = $1 AND sub_name = $2
         "#,
         user_id,
         sub_name
@@ -36,12 +82,133 @@ pub async fn subscribe_user_to_sub(
     Ok(())
 }
 
-pub async fn get_all_subs(pool: &PgPool) -> Result<Vec<Sub>, sqlx::Error> {
+/// Every sub, less the private ones `viewer` isn't a member of.
+pub async fn get_all_subs(
+    pool: &PgPool,
+    sort: SubSort,
+    viewer: Option<i32>,
+) -> Result<Vec<Sub>, sqlx::Error> {
     let subs = sqlx::query_as!(
         Sub,
         r#"
-        SELECT * FROM subs
-        "#
+        SELECT name, description, created_at, nsfw, spoiler_by_default,
+            (SELECT COUNT(*) FROM subscriptions WHERE subscriptions.sub_name = subs.name)
+                AS "subscribers!",
+            visibility AS "visibility: SubVisibility",
+            (SELECT url FROM media WHERE media.id = subs.icon_media_id) AS icon_url,
+            (SELECT url FROM media WHERE media.id = subs.banner_media_id) AS banner_url,
+            primary_color, accent_color
+        FROM subs
+        WHERE subs.visibility <> 'private' OR sub_member(subs.name, $2)
+        ORDER BY CASE WHEN $1 = 'new' THEN created_at END DESC,
+            CASE WHEN $1 = 'top' THEN
+                (SELECT COUNT(*) FROM subscriptions WHERE subscriptions.sub_name = subs.name)
+            END DESC,
+            LOWER(name)
+        "#,
+        sort.to_string(),
+        viewer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subs)
+}
+
+/// Recounts every sub's subscribers, and the subscribers, posts and comments it gained
+/// since `since`, for the trending and popular listings. A sub's trending score is its
+/// new subscribers plus a fifth of its new posts and comments, over the subscribers it
+/// had before; the ten added below keep a handful of joins to a tiny sub from topping
+/// the list.
+pub async fn refresh_sub_stats(pool: &PgPool, since: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    let refreshed = sqlx::query!(
+        r#"
+        INSERT INTO sub_stats (sub, subscribers, new_subscribers, recent_posts, recent_comments,
+            trending_score, computed_at)
+        SELECT counts.sub, counts.subscribers, counts.new_subscribers, counts.recent_posts,
+            counts.recent_comments,
+            (counts.new_subscribers + (counts.recent_posts + counts.recent_comments) / 5.0)
+                / (counts.subscribers - counts.new_subscribers + 10),
+            NOW()
+        FROM (
+            SELECT subs.name AS sub,
+                (SELECT COUNT(*) FROM subscriptions WHERE subscriptions.sub_name = subs.name)
+                    AS subscribers,
+                (SELECT COUNT(*) FROM subscriptions
+                 WHERE subscriptions.sub_name = subs.name AND subscriptions.subscribed_at >= $1)
+                    AS new_subscribers,
+                (SELECT COUNT(*) FROM posts
+                 WHERE posts.sub = subs.name AND posts.status = 'published'
+                 AND posts.removed_at IS NULL AND posts.timestamp >= $1)
+                    AS recent_posts,
+                (SELECT COUNT(*) FROM comments
+                 INNER JOIN posts ON posts.id = comments.post_id
+                 WHERE posts.sub = subs.name AND comments.removed_at IS NULL
+                 AND comments.timestamp >= $1)
+                    AS recent_comments
+            FROM subs
+        ) AS counts
+        ON CONFLICT (sub) DO UPDATE
+        SET subscribers = EXCLUDED.subscribers, new_subscribers = EXCLUDED.new_subscribers,
+            recent_posts = EXCLUDED.recent_posts, recent_comments = EXCLUDED.recent_comments,
+            trending_score = EXCLUDED.trending_score, computed_at = EXCLUDED.computed_at
+        "#,
+        since
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(refreshed.rows_affected())
+}
+
+/// Up to `limit` subs growing fastest by their last recount, leaving out private subs
+/// and subs that gained nothing.
+pub async fn get_trending_subs(pool: &PgPool, limit: i64) -> Result<Vec<Sub>, sqlx::Error> {
+    let subs = sqlx::query_as!(
+        Sub,
+        r#"
+        SELECT subs.name, subs.description, subs.created_at, subs.nsfw, subs.spoiler_by_default,
+            (SELECT COUNT(*) FROM subscriptions WHERE subscriptions.sub_name = subs.name)
+                AS "subscribers!",
+            subs.visibility AS "visibility: SubVisibility",
+            (SELECT url FROM media WHERE media.id = subs.icon_media_id) AS icon_url,
+            (SELECT url FROM media WHERE media.id = subs.banner_media_id) AS banner_url,
+            subs.primary_color, subs.accent_color
+        FROM subs
+        INNER JOIN sub_stats ON sub_stats.sub = subs.name
+        WHERE subs.visibility <> 'private' AND sub_stats.trending_score > 0
+        ORDER BY sub_stats.trending_score DESC, LOWER(subs.name)
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subs)
+}
+
+/// Up to `limit` subs with the most posts and comments by their last recount, then the
+/// most subscribers, leaving out private subs.
+pub async fn get_popular_subs(pool: &PgPool, limit: i64) -> Result<Vec<Sub>, sqlx::Error> {
+    let subs = sqlx::query_as!(
+        Sub,
+        r#"
+        SELECT subs.name, subs.description, subs.created_at, subs.nsfw, subs.spoiler_by_default,
+            (SELECT COUNT(*) FROM subscriptions WHERE subscriptions.sub_name = subs.name)
+                AS "subscribers!",
+            subs.visibility AS "visibility: SubVisibility",
+            (SELECT url FROM media WHERE media.id = subs.icon_media_id) AS icon_url,
+            (SELECT url FROM media WHERE media.id = subs.banner_media_id) AS banner_url,
+            subs.primary_color, subs.accent_color
+        FROM subs
+        INNER JOIN sub_stats ON sub_stats.sub = subs.name
+        WHERE subs.visibility <> 'private'
+        ORDER BY sub_stats.recent_posts + sub_stats.recent_comments DESC,
+            sub_stats.subscribers DESC, LOWER(subs.name)
+        LIMIT $1
+        "#,
+        limit
     )
     .fetch_all(pool)
     .await?;
@@ -53,7 +220,13 @@ pub async fn get_sub_by_name(pool: &PgPool, name: &str) -> Result<Sub, sqlx::Err
     let sub = sqlx::query_as!(
         Sub,
         r#"
-        SELECT name, description, created_at
+        SELECT name, description, created_at, nsfw, spoiler_by_default,
+            (SELECT COUNT(*) FROM subscriptions WHERE subscriptions.sub_name = subs.name)
+                AS "subscribers!",
+            visibility AS "visibility: SubVisibility",
+            (SELECT url FROM media WHERE media.id = subs.icon_media_id) AS icon_url,
+            (SELECT url FROM media WHERE media.id = subs.banner_media_id) AS banner_url,
+            primary_color, accent_color
         FROM subs
         WHERE name = $1
         "#,
@@ -65,14 +238,43 @@ pub async fn get_sub_by_name(pool: &PgPool, name: &str) -> Result<Sub, sqlx::Err
     Ok(sub)
 }
 
+pub async fn get_subs_by_names(pool: &PgPool, names: &[String]) -> Result<Vec<Sub>, sqlx::Error> {
+    let subs = sqlx::query_as!(
+        Sub,
+        r#"
+        SELECT name, description, created_at, nsfw, spoiler_by_default,
+            (SELECT COUNT(*) FROM subscriptions WHERE subscriptions.sub_name = subs.name)
+                AS "subscribers!",
+            visibility AS "visibility: SubVisibility",
+            (SELECT url FROM media WHERE media.id = subs.icon_media_id) AS icon_url,
+            (SELECT url FROM media WHERE media.id = subs.banner_media_id) AS banner_url,
+            primary_color, accent_color
+        FROM subs
+        WHERE name = ANY($1)
+        "#,
+        names
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subs)
+}
+
 pub async fn get_subs_by_user_id(pool: &PgPool, user_id: i32) -> Result<Vec<Sub>, sqlx::Error> {
     let subs = sqlx::query_as!(
         Sub,
         r#"
-        SELECT subs.*
+        SELECT subs.name, subs.description, subs.created_at, subs.nsfw, subs.spoiler_by_default,
+            (SELECT COUNT(*) FROM subscriptions AS counted WHERE counted.sub_name = subs.name)
+                AS "subscribers!",
+            subs.visibility AS "visibility: SubVisibility",
+            (SELECT url FROM media WHERE media.id = subs.icon_media_id) AS icon_url,
+            (SELECT url FROM media WHERE media.id = subs.banner_media_id) AS banner_url,
+            subs.primary_color, subs.accent_color
         FROM subs
         INNER JOIN subscriptions ON subs.name = subscriptions.sub_name
         WHERE subscriptions.user_id = $1
+        ORDER BY subscriptions.subscribed_at DESC
         "#,
         user_id
     )
@@ -86,10 +288,13 @@ pub async fn update_sub(pool: &PgPool, sub: &Sub) -> Result<(String, String), sq
     sqlx::query!(
         r#"
         UPDATE subs
-        SET description = $1
-        WHERE name = $2
+        SET description = $1, nsfw = $2, spoiler_by_default = $3, visibility = $4
+        WHERE name = $5
         "#,
         sub.description,
+        sub.nsfw,
+        sub.spoiler_by_default,
+        sub.visibility as SubVisibility,
         sub.name,
     )
     .execute(pool)
@@ -98,6 +303,46 @@ pub async fn update_sub(pool: &PgPool, sub: &Sub) -> Result<(String, String), sq
     Ok((sub.name.clone(), sub.description.clone()))
 }
 
+/// Fails with `RowNotFound` if there is no such sub.
+pub async fn get_sub_appearance(pool: &PgPool, name: &str) -> Result<SubAppearance, sqlx::Error> {
+    let appearance = sqlx::query_as!(
+        SubAppearance,
+        r#"
+        SELECT icon_media_id, banner_media_id, primary_color, accent_color
+        FROM subs
+        WHERE name = $1
+        "#,
+        name
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(appearance)
+}
+
+pub async fn update_sub_appearance(
+    pool: &PgPool,
+    name: &str,
+    appearance: &SubAppearance,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subs
+        SET icon_media_id = $1, banner_media_id = $2, primary_color = $3, accent_color = $4
+        WHERE name = $5
+        "#,
+        appearance.icon_media_id,
+        appearance.banner_media_id,
+        appearance.primary_color,
+        appearance.accent_color,
+        name,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn delete_sub(pool: &PgPool, name: String) -> Result<(), sqlx::Error> {
     sqlx::query!(
         r#"
@@ -111,3 +356,925 @@ pub async fn delete_sub(pool: &PgPool, name: String) -> Result<(), sqlx::Error>
 
     Ok(())
 }
+
+/// The user's permissions in the sub, `None` if they don't moderate it. Admins are not
+/// listed as moderators; callers let them through separately.
+pub async fn get_moderator_permissions(
+    pool: &PgPool,
+    user_id: i32,
+    sub: &str,
+) -> Result<Option<ModPermissions>, sqlx::Error> {
+    let permissions = sqlx::query_scalar!(
+        r#"
+        SELECT permissions AS "permissions: ModPermissions"
+        FROM sub_moderators
+        WHERE user_id = $1 AND sub = $2
+        "#,
+        user_id,
+        sub
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(permissions)
+}
+
+/// Returns `false` if the user already moderated the sub.
+pub async fn add_sub_moderator(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+    added_by: Option<i32>,
+    permissions: ModPermissions,
+) -> Result<bool, sqlx::Error> {
+    let added = sqlx::query!(
+        r#"
+        INSERT INTO sub_moderators (sub, user_id, added_by, permissions)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        "#,
+        sub,
+        user_id,
+        added_by,
+        permissions as ModPermissions
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(added.rows_affected() > 0)
+}
+
+/// Returns whether the user moderated the sub.
+pub async fn set_moderator_permissions(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+    permissions: ModPermissions,
+) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query!(
+        r#"
+        UPDATE sub_moderators
+        SET permissions = $3
+        WHERE sub = $1 AND user_id = $2
+        "#,
+        sub,
+        user_id,
+        permissions as ModPermissions
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(updated.rows_affected() > 0)
+}
+
+/// Returns whether the user moderated the sub.
+pub async fn remove_sub_moderator(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let removed = sqlx::query!(
+        r#"
+        DELETE FROM sub_moderators
+        WHERE sub = $1 AND user_id = $2
+        "#,
+        sub,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(removed.rows_affected() > 0)
+}
+
+/// Oldest appointment first.
+pub async fn get_sub_moderators(
+    pool: &PgPool,
+    sub: &str,
+) -> Result<Vec<SubModerator>, sqlx::Error> {
+    let moderators = sqlx::query_as!(
+        SubModerator,
+        r#"
+        SELECT sub_moderators.user_id, users.username, sub_moderators.added_by,
+            sub_moderators.added_at, sub_moderators.permissions AS "permissions: ModPermissions"
+        FROM sub_moderators
+        INNER JOIN users ON users.id = sub_moderators.user_id
+        WHERE sub_moderators.sub = $1
+        ORDER BY sub_moderators.added_at, sub_moderators.user_id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(moderators)
+}
+
+/// Returns `false` if the user was already invited.
+pub async fn invite_sub_moderator(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+    invited_by: i32,
+    permissions: ModPermissions,
+) -> Result<bool, sqlx::Error> {
+    let invited = sqlx::query!(
+        r#"
+        INSERT INTO sub_moderator_invites (sub, user_id, invited_by, permissions)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        "#,
+        sub,
+        user_id,
+        invited_by,
+        permissions as ModPermissions
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(invited.rows_affected() > 0)
+}
+
+/// Oldest invite first.
+pub async fn get_sub_moderator_invites(
+    pool: &PgPool,
+    sub: &str,
+) -> Result<Vec<SubModeratorInvite>, sqlx::Error> {
+    let invites = sqlx::query_as!(
+        SubModeratorInvite,
+        r#"
+        SELECT sub_moderator_invites.user_id, users.username, sub_moderator_invites.invited_by,
+            sub_moderator_invites.invited_at,
+            sub_moderator_invites.permissions AS "permissions: ModPermissions"
+        FROM sub_moderator_invites
+        INNER JOIN users ON users.id = sub_moderator_invites.user_id
+        WHERE sub_moderator_invites.sub = $1
+        ORDER BY sub_moderator_invites.invited_at, sub_moderator_invites.user_id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(invites)
+}
+
+/// Deletes the invite and returns it, if there was one, so that it is only used once.
+pub async fn take_sub_moderator_invite(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+) -> Result<Option<SubModeratorInvite>, sqlx::Error> {
+    let invite = sqlx::query_as!(
+        SubModeratorInvite,
+        r#"
+        WITH taken AS (
+            DELETE FROM sub_moderator_invites
+            WHERE sub = $1 AND user_id = $2
+            RETURNING user_id, invited_by, invited_at, permissions
+        )
+        SELECT taken.user_id AS "user_id!", users.username, taken.invited_by,
+            taken.invited_at AS "invited_at!", taken.permissions AS "permissions!: ModPermissions"
+        FROM taken
+        INNER JOIN users ON users.id = taken.user_id
+        "#,
+        sub,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(invite)
+}
+
+/// Takes the user's invite and makes them a moderator with the invited permissions, in
+/// one transaction so a failure can't use up the invite. Returns whether there was one.
+pub async fn accept_sub_moderator_invite(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let invite = sqlx::query!(
+        r#"
+        DELETE FROM sub_moderator_invites
+        WHERE sub = $1 AND user_id = $2
+        RETURNING invited_by, permissions AS "permissions: ModPermissions"
+        "#,
+        sub,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(invite) = invite else {
+        return Ok(false);
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO sub_moderators (sub, user_id, added_by, permissions)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        "#,
+        sub,
+        user_id,
+        invite.invited_by,
+        invite.permissions as ModPermissions
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Whether a moderator approved the user as a member of the sub. Moderators and admins
+/// aren't listed as members; callers let them through separately.
+pub async fn is_sub_member(pool: &PgPool, sub: &str, user_id: i32) -> Result<bool, sqlx::Error> {
+    let member = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM sub_members WHERE sub = $1 AND user_id = $2) AS "member!"
+        "#,
+        sub,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(member)
+}
+
+/// Returns `false` if the user was already a member.
+pub async fn approve_sub_member(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+    approved_by: i32,
+) -> Result<bool, sqlx::Error> {
+    let approved = sqlx::query!(
+        r#"
+        INSERT INTO sub_members (sub, user_id, approved_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        "#,
+        sub,
+        user_id,
+        approved_by
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(approved.rows_affected() > 0)
+}
+
+/// Returns whether the user was a member.
+pub async fn remove_sub_member(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let removed = sqlx::query!(
+        r#"
+        DELETE FROM sub_members
+        WHERE sub = $1 AND user_id = $2
+        "#,
+        sub,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(removed.rows_affected() > 0)
+}
+
+/// Most recently approved first.
+pub async fn get_sub_members(pool: &PgPool, sub: &str) -> Result<Vec<SubMember>, sqlx::Error> {
+    let members = sqlx::query_as!(
+        SubMember,
+        r#"
+        SELECT sub_members.user_id, users.username, sub_members.approved_by,
+            sub_members.approved_at
+        FROM sub_members
+        INNER JOIN users ON users.id = sub_members.user_id
+        WHERE sub_members.sub = $1
+        ORDER BY sub_members.approved_at DESC, sub_members.user_id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(members)
+}
+
+/// Returns `false` if the user had already asked, leaving their first message in place.
+pub async fn create_join_request(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+    message: &str,
+) -> Result<bool, sqlx::Error> {
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO sub_join_requests (sub, user_id, message)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        "#,
+        sub,
+        user_id,
+        message
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(created.rows_affected() > 0)
+}
+
+/// Oldest request first.
+pub async fn get_join_requests(
+    pool: &PgPool,
+    sub: &str,
+) -> Result<Vec<SubJoinRequest>, sqlx::Error> {
+    let requests = sqlx::query_as!(
+        SubJoinRequest,
+        r#"
+        SELECT sub_join_requests.user_id, users.username, sub_join_requests.message,
+            sub_join_requests.requested_at
+        FROM sub_join_requests
+        INNER JOIN users ON users.id = sub_join_requests.user_id
+        WHERE sub_join_requests.sub = $1
+        ORDER BY sub_join_requests.requested_at, sub_join_requests.user_id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(requests)
+}
+
+/// Removes the user's pending request and returns it, if they had one.
+pub async fn take_join_request(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+) -> Result<Option<SubJoinRequest>, sqlx::Error> {
+    let request = sqlx::query_as!(
+        SubJoinRequest,
+        r#"
+        WITH taken AS (
+            DELETE FROM sub_join_requests
+            WHERE sub = $1 AND user_id = $2
+            RETURNING user_id, message, requested_at
+        )
+        SELECT taken.user_id AS "user_id!", users.username, taken.message AS "message!",
+            taken.requested_at AS "requested_at!"
+        FROM taken
+        INNER JOIN users ON users.id = taken.user_id
+        "#,
+        sub,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(request)
+}
+
+/// Bans the user from the sub, replacing any ban they already had.
+pub async fn ban_user_from_sub(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+    banned_by: i32,
+    reason: &str,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO sub_bans (sub, user_id, banned_by, reason, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (sub, user_id) DO UPDATE
+        SET banned_by = $3, reason = $4, banned_at = NOW(), expires_at = $5
+        "#,
+        sub,
+        user_id,
+        banned_by,
+        reason,
+        expires_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The user's ban from the sub, unless they have none or it has run out.
+pub async fn get_sub_ban(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+) -> Result<Option<SubBan>, sqlx::Error> {
+    let ban = sqlx::query_as!(
+        SubBan,
+        r#"
+        SELECT sub_bans.user_id, users.username, sub_bans.banned_by, sub_bans.reason,
+            sub_bans.banned_at, sub_bans.expires_at
+        FROM sub_bans
+        INNER JOIN users ON users.id = sub_bans.user_id
+        WHERE sub_bans.sub = $1 AND sub_bans.user_id = $2
+        AND (sub_bans.expires_at IS NULL OR sub_bans.expires_at > NOW())
+        "#,
+        sub,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(ban)
+}
+
+/// Bans still in force, most recent first.
+pub async fn get_sub_bans(pool: &PgPool, sub: &str) -> Result<Vec<SubBan>, sqlx::Error> {
+    let bans = sqlx::query_as!(
+        SubBan,
+        r#"
+        SELECT sub_bans.user_id, users.username, sub_bans.banned_by, sub_bans.reason,
+            sub_bans.banned_at, sub_bans.expires_at
+        FROM sub_bans
+        INNER JOIN users ON users.id = sub_bans.user_id
+        WHERE sub_bans.sub = $1
+        AND (sub_bans.expires_at IS NULL OR sub_bans.expires_at > NOW())
+        ORDER BY sub_bans.banned_at DESC, sub_bans.user_id
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(bans)
+}
+
+/// Returns whether the user was banned.
+pub async fn unban_user_from_sub(
+    pool: &PgPool,
+    sub: &str,
+    user_id: i32,
+) -> Result<bool, sqlx::Error> {
+    let removed = sqlx::query!(
+        r#"
+        DELETE FROM sub_bans
+        WHERE sub = $1 AND user_id = $2
+        AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+        sub,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(removed.rows_affected() > 0)
+}
+
+/// Clears out bans that have run out. They stop applying as soon as they expire; this
+/// only keeps the table from growing.
+pub async fn expire_sub_bans(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let expired = sqlx::query!(
+        r#"
+        DELETE FROM sub_bans
+        WHERE expires_at <= NOW()
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(expired.rows_affected())
+}
+
+#[async_trait]
+pub trait SubRepository: Send + Sync {
+    async fn create_sub(&self, sub: &Sub) -> Result<String, sqlx::Error>;
+    async fn sub_name_taken(&self, name: &str) -> Result<bool, sqlx::Error>;
+    async fn subscribe_user_to_sub(&self, user_id: i32, sub_name: &str) -> Result<(), sqlx::Error>;
+    async fn unsubscribe_user_from_sub(
+        &self,
+        user_id: i32,
+        sub_name: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_all_subs(
+        &self,
+        sort: SubSort,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Sub>, sqlx::Error>;
+    async fn get_sub_by_name(&self, name: &str) -> Result<Sub, sqlx::Error>;
+    async fn get_subs_by_names(&self, names: &[String]) -> Result<Vec<Sub>, sqlx::Error>;
+    async fn get_subs_by_user_id(&self, user_id: i32) -> Result<Vec<Sub>, sqlx::Error>;
+    async fn update_sub(&self, sub: &Sub) -> Result<(String, String), sqlx::Error>;
+    async fn delete_sub(&self, name: String) -> Result<(), sqlx::Error>;
+    async fn get_moderator_permissions(
+        &self,
+        user_id: i32,
+        sub: &str,
+    ) -> Result<Option<ModPermissions>, sqlx::Error>;
+    async fn add_sub_moderator(
+        &self,
+        sub: &str,
+        user_id: i32,
+        added_by: Option<i32>,
+        permissions: ModPermissions,
+    ) -> Result<bool, sqlx::Error>;
+    async fn set_moderator_permissions(
+        &self,
+        sub: &str,
+        user_id: i32,
+        permissions: ModPermissions,
+    ) -> Result<bool, sqlx::Error>;
+    async fn remove_sub_moderator(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error>;
+    async fn get_sub_moderators(&self, sub: &str) -> Result<Vec<SubModerator>, sqlx::Error>;
+    async fn invite_sub_moderator(
+        &self,
+        sub: &str,
+        user_id: i32,
+        invited_by: i32,
+        permissions: ModPermissions,
+    ) -> Result<bool, sqlx::Error>;
+    async fn get_sub_moderator_invites(
+        &self,
+        sub: &str,
+    ) -> Result<Vec<SubModeratorInvite>, sqlx::Error>;
+    async fn take_sub_moderator_invite(
+        &self,
+        sub: &str,
+        user_id: i32,
+    ) -> Result<Option<SubModeratorInvite>, sqlx::Error>;
+    async fn accept_sub_moderator_invite(
+        &self,
+        sub: &str,
+        user_id: i32,
+    ) -> Result<bool, sqlx::Error>;
+    async fn is_sub_member(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error>;
+    async fn approve_sub_member(
+        &self,
+        sub: &str,
+        user_id: i32,
+        approved_by: i32,
+    ) -> Result<bool, sqlx::Error>;
+    async fn remove_sub_member(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error>;
+    async fn get_sub_members(&self, sub: &str) -> Result<Vec<SubMember>, sqlx::Error>;
+    async fn create_join_request(
+        &self,
+        sub: &str,
+        user_id: i32,
+        message: &str,
+    ) -> Result<bool, sqlx::Error>;
+    async fn get_join_requests(&self, sub: &str) -> Result<Vec<SubJoinRequest>, sqlx::Error>;
+    async fn take_join_request(
+        &self,
+        sub: &str,
+        user_id: i32,
+    ) -> Result<Option<SubJoinRequest>, sqlx::Error>;
+    async fn ban_user_from_sub(
+        &self,
+        sub: &str,
+        user_id: i32,
+        banned_by: i32,
+        reason: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_sub_ban(&self, sub: &str, user_id: i32) -> Result<Option<SubBan>, sqlx::Error>;
+    async fn get_sub_bans(&self, sub: &str) -> Result<Vec<SubBan>, sqlx::Error>;
+    async fn unban_user_from_sub(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error>;
+    async fn get_account_requirements(
+        &self,
+        sub: Option<&str>,
+        contribution: Contribution,
+    ) -> Result<Vec<AccountRequirement>, sqlx::Error>;
+    async fn get_mod_notes(&self, sub: &str, user_ids: &[i32])
+        -> Result<Vec<ModNote>, sqlx::Error>;
+}
+
+#[async_trait]
+impl SubRepository for PgPool {
+    async fn create_sub(&self, sub: &Sub) -> Result<String, sqlx::Error> {
+        create_sub(self, sub).await
+    }
+
+    async fn sub_name_taken(&self, name: &str) -> Result<bool, sqlx::Error> {
+        sub_name_taken(self, name).await
+    }
+
+    async fn subscribe_user_to_sub(&self, user_id: i32, sub_name: &str) -> Result<(), sqlx::Error> {
+        subscribe_user_to_sub(self, user_id, sub_name).await
+    }
+
+    async fn unsubscribe_user_from_sub(
+        &self,
+        user_id: i32,
+        sub_name: &str,
+    ) -> Result<(), sqlx::Error> {
+        unsubscribe_user_from_sub(self, user_id, sub_name).await
+    }
+
+    async fn get_all_subs(
+        &self,
+        sort: SubSort,
+        viewer: Option<i32>,
+    ) -> Result<Vec<Sub>, sqlx::Error> {
+        get_all_subs(self, sort, viewer).await
+    }
+
+    async fn get_sub_by_name(&self, name: &str) -> Result<Sub, sqlx::Error> {
+        get_sub_by_name(self, name).await
+    }
+
+    async fn get_subs_by_names(&self, names: &[String]) -> Result<Vec<Sub>, sqlx::Error> {
+        get_subs_by_names(self, names).await
+    }
+
+    async fn get_subs_by_user_id(&self, user_id: i32) -> Result<Vec<Sub>, sqlx::Error> {
+        get_subs_by_user_id(self, user_id).await
+    }
+
+    async fn update_sub(&self, sub: &Sub) -> Result<(String, String), sqlx::Error> {
+        update_sub(self, sub).await
+    }
+
+    async fn delete_sub(&self, name: String) -> Result<(), sqlx::Error> {
+        delete_sub(self, name).await
+    }
+
+    async fn get_moderator_permissions(
+        &self,
+        user_id: i32,
+        sub: &str,
+    ) -> Result<Option<ModPermissions>, sqlx::Error> {
+        get_moderator_permissions(self, user_id, sub).await
+    }
+
+    async fn add_sub_moderator(
+        &self,
+        sub: &str,
+        user_id: i32,
+        added_by: Option<i32>,
+        permissions: ModPermissions,
+    ) -> Result<bool, sqlx::Error> {
+        add_sub_moderator(self, sub, user_id, added_by, permissions).await
+    }
+
+    async fn set_moderator_permissions(
+        &self,
+        sub: &str,
+        user_id: i32,
+        permissions: ModPermissions,
+    ) -> Result<bool, sqlx::Error> {
+        set_moderator_permissions(self, sub, user_id, permissions).await
+    }
+
+    async fn remove_sub_moderator(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error> {
+        remove_sub_moderator(self, sub, user_id).await
+    }
+
+    async fn get_sub_moderators(&self, sub: &str) -> Result<Vec<SubModerator>, sqlx::Error> {
+        get_sub_moderators(self, sub).await
+    }
+
+    async fn invite_sub_moderator(
+        &self,
+        sub: &str,
+        user_id: i32,
+        invited_by: i32,
+        permissions: ModPermissions,
+    ) -> Result<bool, sqlx::Error> {
+        invite_sub_moderator(self, sub, user_id, invited_by, permissions).await
+    }
+
+    async fn get_sub_moderator_invites(
+        &self,
+        sub: &str,
+    ) -> Result<Vec<SubModeratorInvite>, sqlx::Error> {
+        get_sub_moderator_invites(self, sub).await
+    }
+
+    async fn take_sub_moderator_invite(
+        &self,
+        sub: &str,
+        user_id: i32,
+    ) -> Result<Option<SubModeratorInvite>, sqlx::Error> {
+        take_sub_moderator_invite(self, sub, user_id).await
+    }
+
+    async fn accept_sub_moderator_invite(
+        &self,
+        sub: &str,
+        user_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        accept_sub_moderator_invite(self, sub, user_id).await
+    }
+
+    async fn is_sub_member(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error> {
+        is_sub_member(self, sub, user_id).await
+    }
+
+    async fn approve_sub_member(
+        &self,
+        sub: &str,
+        user_id: i32,
+        approved_by: i32,
+    ) -> Result<bool, sqlx::Error> {
+        approve_sub_member(self, sub, user_id, approved_by).await
+    }
+
+    async fn remove_sub_member(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error> {
+        remove_sub_member(self, sub, user_id).await
+    }
+
+    async fn get_sub_members(&self, sub: &str) -> Result<Vec<SubMember>, sqlx::Error> {
+        get_sub_members(self, sub).await
+    }
+
+    async fn create_join_request(
+        &self,
+        sub: &str,
+        user_id: i32,
+        message: &str,
+    ) -> Result<bool, sqlx::Error> {
+        create_join_request(self, sub, user_id, message).await
+    }
+
+    async fn get_join_requests(&self, sub: &str) -> Result<Vec<SubJoinRequest>, sqlx::Error> {
+        get_join_requests(self, sub).await
+    }
+
+    async fn take_join_request(
+        &self,
+        sub: &str,
+        user_id: i32,
+    ) -> Result<Option<SubJoinRequest>, sqlx::Error> {
+        take_join_request(self, sub, user_id).await
+    }
+
+    async fn ban_user_from_sub(
+        &self,
+        sub: &str,
+        user_id: i32,
+        banned_by: i32,
+        reason: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), sqlx::Error> {
+        ban_user_from_sub(self, sub, user_id, banned_by, reason, expires_at).await
+    }
+
+    async fn get_sub_ban(&self, sub: &str, user_id: i32) -> Result<Option<SubBan>, sqlx::Error> {
+        get_sub_ban(self, sub, user_id).await
+    }
+
+    async fn get_sub_bans(&self, sub: &str) -> Result<Vec<SubBan>, sqlx::Error> {
+        get_sub_bans(self, sub).await
+    }
+
+    async fn unban_user_from_sub(&self, sub: &str, user_id: i32) -> Result<bool, sqlx::Error> {
+        unban_user_from_sub(self, sub, user_id).await
+    }
+
+    async fn get_account_requirements(
+        &self,
+        sub: Option<&str>,
+        contribution: Contribution,
+    ) -> Result<Vec<AccountRequirement>, sqlx::Error> {
+        account_requirement_repo::get_applicable_requirements(self, sub, contribution).await
+    }
+
+    async fn get_mod_notes(
+        &self,
+        sub: &str,
+        user_ids: &[i32],
+    ) -> Result<Vec<ModNote>, sqlx::Error> {
+        mod_note_repo::get_mod_notes(self, sub, user_ids).await
+    }
+}
+
+#[cfg(test)]
+mod sub_repo_tests {
+    use super::*;
+    use crate::model::media::Media;
+    use crate::repo::media as media_repo;
+    use crate::test_support::fixtures::{SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_sub_appearance_resolves_uploads() {
+        let db = TestDatabase::new().await;
+        let moderator = UserFixture::new("moderator").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        let icon = Media {
+            id: Uuid::new_v4(),
+            user_id: moderator.id,
+            storage_key: "icon.png".to_string(),
+            url: "http://localhost/media/files/icon.png".to_string(),
+            content_type: "image/png".to_string(),
+            size_bytes: 8,
+            created_at: Utc::now(),
+            renditions: Vec::new(),
+        };
+        media_repo::create_media(&db.pool, &icon).await.unwrap();
+
+        let appearance = SubAppearance {
+            icon_media_id: Some(icon.id),
+            primary_color: Some("#ff8800".to_string()),
+            ..SubAppearance::default()
+        };
+        update_sub_appearance(&db.pool, &sub.name, &appearance)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_sub_appearance(&db.pool, &sub.name).await.unwrap(),
+            appearance
+        );
+        let branded = get_sub_by_name(&db.pool, &sub.name).await.unwrap();
+        assert_eq!(branded.icon_url.as_deref(), Some(icon.url.as_str()));
+        assert_eq!(branded.banner_url, None);
+        assert_eq!(branded.primary_color.as_deref(), Some("#ff8800"));
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_accepting_an_invite_adds_the_moderator_once() {
+        let db = TestDatabase::new().await;
+        let founder = UserFixture::new("founder").insert(&db.pool).await;
+        let invited = UserFixture::new("invited").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+        invite_sub_moderator(
+            &db.pool,
+            &sub.name,
+            invited.id,
+            founder.id,
+            ModPermissions::FULL,
+        )
+        .await
+        .unwrap();
+
+        assert!(accept_sub_moderator_invite(&db.pool, &sub.name, invited.id)
+            .await
+            .unwrap());
+        assert_eq!(
+            get_moderator_permissions(&db.pool, invited.id, &sub.name)
+                .await
+                .unwrap(),
+            Some(ModPermissions::FULL)
+        );
+        assert!(get_sub_moderator_invites(&db.pool, &sub.name)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(
+            !accept_sub_moderator_invite(&db.pool, &sub.name, invited.id)
+                .await
+                .unwrap()
+        );
+
+        db.finish().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_trending_subs_need_recent_growth() {
+        let db = TestDatabase::new().await;
+        let big = SubFixture::new("big").insert(&db.pool).await;
+        let small = SubFixture::new("small").insert(&db.pool).await;
+        SubFixture::new("quiet").insert(&db.pool).await;
+        for i in 0..4 {
+            let user = UserFixture::new(&format!("user{}", i))
+                .insert(&db.pool)
+                .await;
+            subscribe_user_to_sub(&db.pool, user.id, &big.name)
+                .await
+                .unwrap();
+            if i == 0 {
+                subscribe_user_to_sub(&db.pool, user.id, &small.name)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let refreshed = refresh_sub_stats(&db.pool, Utc::now()).await.unwrap();
+        assert_eq!(refreshed, 3);
+        assert!(get_trending_subs(&db.pool, 10).await.unwrap().is_empty());
+        let popular = get_popular_subs(&db.pool, 10).await.unwrap();
+        let names: Vec<&str> = popular.iter().map(|sub| sub.name.as_str()).collect();
+        assert_eq!(names, ["big", "small", "quiet"]);
+
+        refresh_sub_stats(&db.pool, Utc::now() - chrono::Duration::days(7))
+            .await
+            .unwrap();
+        let trending = get_trending_subs(&db.pool, 10).await.unwrap();
+        let names: Vec<&str> = trending.iter().map(|sub| sub.name.as_str()).collect();
+        assert_eq!(names, ["big", "small"]);
+
+        db.finish().await;
+    }
+}
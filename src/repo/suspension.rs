@@ -0,0 +1,157 @@
+use crate::model::suspension::Suspension;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Suspends the account, replacing any suspension it already had.
+pub async fn suspend_user(
+    pool: &PgPool,
+    user_id: i32,
+    suspended_by: i32,
+    reason: &str,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_suspensions (user_id, suspended_by, reason, expires_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET suspended_by = $2, reason = $3, suspended_at = NOW(), expires_at = $4
+        "#,
+        user_id,
+        suspended_by,
+        reason,
+        expires_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The account's suspension, unless it has none or it has run out.
+pub async fn get_active_suspension(
+    pool: &PgPool,
+    user_id: i32,
+) -> Result<Option<Suspension>, sqlx::Error> {
+    let suspension = sqlx::query_as!(
+        Suspension,
+        r#"
+        SELECT user_suspensions.user_id, users.username, user_suspensions.suspended_by,
+            user_suspensions.reason, user_suspensions.suspended_at, user_suspensions.expires_at
+        FROM user_suspensions
+        INNER JOIN users ON users.id = user_suspensions.user_id
+        WHERE user_suspensions.user_id = $1
+        AND (user_suspensions.expires_at IS NULL OR user_suspensions.expires_at > NOW())
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(suspension)
+}
+
+/// Suspensions still in force, most recent first.
+pub async fn get_suspensions(pool: &PgPool) -> Result<Vec<Suspension>, sqlx::Error> {
+    let suspensions = sqlx::query_as!(
+        Suspension,
+        r#"
+        SELECT user_suspensions.user_id, users.username, user_suspensions.suspended_by,
+            user_suspensions.reason, user_suspensions.suspended_at, user_suspensions.expires_at
+        FROM user_suspensions
+        INNER JOIN users ON users.id = user_suspensions.user_id
+        WHERE user_suspensions.expires_at IS NULL OR user_suspensions.expires_at > NOW()
+        ORDER BY user_suspensions.suspended_at DESC, user_suspensions.user_id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(suspensions)
+}
+
+/// Returns whether the account was suspended.
+pub async fn lift_suspension(pool: &PgPool, user_id: i32) -> Result<bool, sqlx::Error> {
+    let lifted = sqlx::query!(
+        r#"
+        DELETE FROM user_suspensions
+        WHERE user_id = $1
+        AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(lifted.rows_affected() > 0)
+}
+
+/// Clears out suspensions that have run out. They stop applying as soon as they
+/// expire; this only keeps the table from growing.
+pub async fn expire_suspensions(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let expired = sqlx::query!(
+        r#"
+        DELETE FROM user_suspensions
+        WHERE expires_at <= NOW()
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(expired.rows_affected())
+}
+
+#[cfg(test)]
+mod suspension_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::UserFixture;
+    use crate::test_support::TestDatabase;
+    use chrono::Duration;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_expired_suspensions_stop_applying() {
+        let db = TestDatabase::new().await;
+        let admin = UserFixture::new("admin").insert(&db.pool).await;
+        let troll = UserFixture::new("troll").insert(&db.pool).await;
+        let spammer = UserFixture::new("spammer").insert(&db.pool).await;
+
+        suspend_user(&db.pool, troll.id, admin.id, "Flaming", None)
+            .await
+            .unwrap();
+        let past = Utc::now() - Duration::hours(1);
+        suspend_user(&db.pool, spammer.id, admin.id, "Spam", Some(past))
+            .await
+            .unwrap();
+
+        let suspension = get_active_suspension(&db.pool, troll.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (suspension.reason.as_str(), suspension.expires_at),
+            ("Flaming", None)
+        );
+        assert!(get_active_suspension(&db.pool, spammer.id)
+            .await
+            .unwrap()
+            .is_none());
+        let suspended: Vec<i32> = get_suspensions(&db.pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|suspension| suspension.user_id)
+            .collect();
+        assert_eq!(suspended, [troll.id]);
+
+        assert_eq!(expire_suspensions(&db.pool).await.unwrap(), 1);
+        assert!(!lift_suspension(&db.pool, spammer.id).await.unwrap());
+        assert!(lift_suspension(&db.pool, troll.id).await.unwrap());
+        assert!(get_active_suspension(&db.pool, troll.id)
+            .await
+            .unwrap()
+            .is_none());
+
+        db.finish().await;
+    }
+}
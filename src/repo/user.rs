@@ -1,17 +1,20 @@
 use crate::model::user::{DbAddUser, User};
+use async_trait::async_trait;
+use chrono::NaiveDate;
 use sqlx::PgPool; // For password hashing
 
 pub async fn create_user(pool: &PgPool, user: &DbAddUser) -> Result<i32, sqlx::Error> {
     let user = sqlx::query!(
         r#"
-        INSERT INTO users (username, password_hash, is_moderator, created_at)
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO users (username, password_hash, created_at, email, email_verified_at)
+        VALUES ($1, $2, $3, $4, $5)
         RETURNING id
         "#,
         user.username,
         user.password_hash,
-        user.is_moderator,
         user.created_at,
+        user.email,
+        user.email_verified_at,
     )
     .fetch_one(pool)
     .await?;
@@ -23,7 +26,12 @@ pub async fn get_user_by_id(pool: &PgPool, user_id: i32) -> Result<User, sqlx::E
     let user = sqlx::query_as!(
         User,
         r#"
-        SELECT id, username, password_hash, is_moderator, created_at
+        SELECT id, username, password_hash,
+            EXISTS(SELECT 1 FROM sub_moderators WHERE sub_moderators.user_id = users.id)
+                AS "is_moderator!",
+            is_admin, created_at, date_of_birth,
+            nsfw_acknowledged_at, preferred_languages, is_premium, email, email_verified_at,
+            post_karma, comment_karma, show_nsfw
         FROM users
         WHERE id = $1
         "#,
@@ -35,11 +43,37 @@ pub async fn get_user_by_id(pool: &PgPool, user_id: i32) -> Result<User, sqlx::E
     Ok(user)
 }
 
+pub async fn get_users_by_ids(pool: &PgPool, user_ids: &[i32]) -> Result<Vec<User>, sqlx::Error> {
+    let users = sqlx::query_as!(
+        User,
+        r#"
+        SELECT id, username, password_hash,
+            EXISTS(SELECT 1 FROM sub_moderators WHERE sub_moderators.user_id = users.id)
+                AS "is_moderator!",
+            is_admin, created_at, date_of_birth,
+            nsfw_acknowledged_at, preferred_languages, is_premium, email, email_verified_at,
+            post_karma, comment_karma, show_nsfw
+        FROM users
+        WHERE id = ANY($1)
+        "#,
+        user_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(users)
+}
+
 pub async fn get_user_by_username(pool: &PgPool, username: &str) -> Result<User, sqlx::Error> {
     let user = sqlx::query_as!(
         User,
         r#"
-        SELECT id, username, password_hash, is_moderator, created_at
+        SELECT id, username, password_hash,
+            EXISTS(SELECT 1 FROM sub_moderators WHERE sub_moderators.user_id = users.id)
+                AS "is_moderator!",
+            is_admin, created_at, date_of_birth,
+            nsfw_acknowledged_at, preferred_languages, is_premium, email, email_verified_at,
+            post_karma, comment_karma, show_nsfw
         FROM users
         WHERE username = $1
         "#,
@@ -55,7 +89,12 @@ pub async fn get_users_by_sub(pool: &PgPool, sub_name: &str) -> Result<Vec<User>
     let users = sqlx::query_as!(
         User,
         r#"
-        SELECT users.*
+        SELECT users.id, users.username, users.password_hash,
+            EXISTS(SELECT 1 FROM sub_moderators WHERE sub_moderators.user_id = users.id)
+                AS "is_moderator!",
+            users.is_admin, users.created_at, users.date_of_birth, users.nsfw_acknowledged_at,
+            users.preferred_languages, users.is_premium, users.email, users.email_verified_at,
+            users.post_karma, users.comment_karma, users.show_nsfw
         FROM users
         INNER JOIN subscriptions ON users.id = subscriptions.user_id
         WHERE subscriptions.sub_name = $1
@@ -72,7 +111,12 @@ pub async fn username_exists(pool: &PgPool, username: &str) -> Result<Option<Use
     let user = sqlx::query_as!(
         User,
         r#"
-        SELECT id, username, password_hash, is_moderator, created_at
+        SELECT id, username, password_hash,
+            EXISTS(SELECT 1 FROM sub_moderators WHERE sub_moderators.user_id = users.id)
+                AS "is_moderator!",
+            is_admin, created_at, date_of_birth,
+            nsfw_acknowledged_at, preferred_languages, is_premium, email, email_verified_at,
+            post_karma, comment_karma, show_nsfw
         FROM users
         WHERE username = $1
         "#,
@@ -84,13 +128,38 @@ pub async fn username_exists(pool: &PgPool, username: &str) -> Result<Option<Use
     Ok(user)
 }
 
-pub async fn grant_mod_status(pool: &PgPool, user_id: i32) -> Result<i32, sqlx::Error> {
+pub async fn update_user_password(
+    pool: &PgPool,
+    user_id: i32,
+    new_hash: &str,
+) -> Result<i32, sqlx::Error> {
     sqlx::query!(
         r#"
         UPDATE users
-        SET is_moderator = true
-        WHERE id = $1
+        SET password_hash = $1
+        WHERE id = $2
         "#,
+        new_hash,
+        user_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(user_id)
+}
+
+pub async fn set_date_of_birth(
+    pool: &PgPool,
+    user_id: i32,
+    date_of_birth: NaiveDate,
+) -> Result<i32, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET date_of_birth = $1
+        WHERE id = $2
+        "#,
+        date_of_birth,
         user_id
     )
     .execute(pool)
@@ -99,11 +168,11 @@ pub async fn grant_mod_status(pool: &PgPool, user_id: i32) -> Result<i32, sqlx::
     Ok(user_id)
 }
 
-pub async fn remove_mod_status(pool: &PgPool, user_id: i32) -> Result<i32, sqlx::Error> {
+pub async fn acknowledge_nsfw(pool: &PgPool, user_id: i32) -> Result<i32, sqlx::Error> {
     sqlx::query!(
         r#"
         UPDATE users
-        SET is_moderator = false
+        SET nsfw_acknowledged_at = NOW()
         WHERE id = $1
         "#,
         user_id
@@ -114,19 +183,39 @@ pub async fn remove_mod_status(pool: &PgPool, user_id: i32) -> Result<i32, sqlx:
     Ok(user_id)
 }
 
-pub async fn update_user_password(
+pub async fn set_preferred_languages(
     pool: &PgPool,
     user_id: i32,
-    new_hash: &str,
+    languages: &[String],
 ) -> Result<i32, sqlx::Error> {
     sqlx::query!(
         r#"
         UPDATE users
-        SET password_hash = $1
+        SET preferred_languages = $1
         WHERE id = $2
         "#,
-        new_hash,
-        user_id,
+        languages,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(user_id)
+}
+
+pub async fn set_show_nsfw(
+    pool: &PgPool,
+    user_id: i32,
+    show_nsfw: bool,
+) -> Result<i32, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET show_nsfw = $1
+        WHERE id = $2
+        "#,
+        show_nsfw,
+        user_id
     )
     .execute(pool)
     .await?;
@@ -147,3 +236,144 @@ pub async fn delete_user(pool: &PgPool, user_id: i32) -> Result<i32, sqlx::Error
 
     Ok(user_id)
 }
+
+/// Returns whether there is such a user.
+pub async fn set_shadowbanned(
+    pool: &PgPool,
+    user_id: i32,
+    shadowbanned: bool,
+) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query!(
+        r#"
+        UPDATE users
+        SET shadowbanned = $1
+        WHERE id = $2
+        "#,
+        shadowbanned,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(updated.rows_affected() > 0)
+}
+
+/// Whether `viewer` may see what `author_id` posts in `sub`. Shadowbanned authors are
+/// only visible to themselves, the sub's moderators and admins.
+pub async fn author_visible(
+    pool: &PgPool,
+    author_id: i32,
+    sub: &str,
+    viewer: Option<i32>,
+) -> Result<bool, sqlx::Error> {
+    let visible = sqlx::query_scalar!(
+        r#"
+        SELECT author_visible($1, $2, $3) AS "visible!"
+        "#,
+        author_id,
+        sub,
+        viewer
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(visible)
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn create_user(&self, user: &DbAddUser) -> Result<i32, sqlx::Error>;
+    async fn get_user_by_id(&self, user_id: i32) -> Result<User, sqlx::Error>;
+    async fn get_users_by_ids(&self, user_ids: &[i32]) -> Result<Vec<User>, sqlx::Error>;
+    async fn get_user_by_username(&self, username: &str) -> Result<User, sqlx::Error>;
+    async fn get_users_by_sub(&self, sub_name: &str) -> Result<Vec<User>, sqlx::Error>;
+    async fn username_exists(&self, username: &str) -> Result<Option<User>, sqlx::Error>;
+    async fn update_user_password(&self, user_id: i32, new_hash: &str) -> Result<i32, sqlx::Error>;
+    async fn set_date_of_birth(
+        &self,
+        user_id: i32,
+        date_of_birth: NaiveDate,
+    ) -> Result<i32, sqlx::Error>;
+    async fn acknowledge_nsfw(&self, user_id: i32) -> Result<i32, sqlx::Error>;
+    async fn set_preferred_languages(
+        &self,
+        user_id: i32,
+        languages: &[String],
+    ) -> Result<i32, sqlx::Error>;
+    async fn set_show_nsfw(&self, user_id: i32, show_nsfw: bool) -> Result<i32, sqlx::Error>;
+    async fn delete_user(&self, user_id: i32) -> Result<i32, sqlx::Error>;
+    async fn author_visible(
+        &self,
+        author_id: i32,
+        sub: &str,
+        viewer: Option<i32>,
+    ) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+impl UserRepository for PgPool {
+    async fn create_user(&self, user: &DbAddUser) -> Result<i32, sqlx::Error> {
+        create_user(self, user).await
+    }
+
+    async fn get_user_by_id(&self, user_id: i32) -> Result<User, sqlx::Error> {
+        get_user_by_id(self, user_id).await
+    }
+
+    async fn get_users_by_ids(&self, user_ids: &[i32]) -> Result<Vec<User>, sqlx::Error> {
+        get_users_by_ids(self, user_ids).await
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<User, sqlx::Error> {
+        get_user_by_username(self, username).await
+    }
+
+    async fn get_users_by_sub(&self, sub_name: &str) -> Result<Vec<User>, sqlx::Error> {
+        get_users_by_sub(self, sub_name).await
+    }
+
+    async fn username_exists(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        username_exists(self, username).await
+    }
+
+    async fn update_user_password(&self, user_id: i32, new_hash: &str) -> Result<i32, sqlx::Error> {
+        update_user_password(self, user_id, new_hash).await
+    }
+
+    async fn set_date_of_birth(
+        &self,
+        user_id: i32,
+        date_of_birth: NaiveDate,
+    ) -> Result<i32, sqlx::Error> {
+        set_date_of_birth(self, user_id, date_of_birth).await
+    }
+
+    async fn acknowledge_nsfw(&self, user_id: i32) -> Result<i32, sqlx::Error> {
+        acknowledge_nsfw(self, user_id).await
+    }
+
+    async fn set_preferred_languages(
+        &self,
+        user_id: i32,
+        languages: &[String],
+    ) -> Result<i32, sqlx::Error> {
+        set_preferred_languages(self, user_id, languages).await
+    }
+
+    async fn set_show_nsfw(&self, user_id: i32, show_nsfw: bool) -> Result<i32, sqlx::Error> {
+        set_show_nsfw(self, user_id, show_nsfw).await
+    }
+
+    async fn delete_user(&self, user_id: i32) -> Result<i32, sqlx::Error> {
+        delete_user(self, user_id).await
+    }
+
+    async fn author_visible(
+        &self,
+        author_id: i32,
+        sub: &str,
+        viewer: Option<i32>,
+    ) -> Result<bool, sqlx::Error> {
+        author_visible(self, author_id, sub, viewer).await
+    }
+}
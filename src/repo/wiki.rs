@@ -0,0 +1,210 @@
+use crate::model::wiki::{WikiEditors, WikiPage, WikiPageSummary, WikiRevision};
+use sqlx::PgPool;
+
+/// The sub's pages in name order.
+pub async fn get_wiki_pages(pool: &PgPool, sub: &str) -> Result<Vec<WikiPageSummary>, sqlx::Error> {
+    let pages = sqlx::query_as!(
+        WikiPageSummary,
+        r#"
+        SELECT page, revision, edited_at
+        FROM sub_wiki_pages
+        WHERE sub = $1
+        ORDER BY page
+        "#,
+        sub
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(pages)
+}
+
+pub async fn get_wiki_page(
+    pool: &PgPool,
+    sub: &str,
+    page: &str,
+) -> Result<Option<WikiPage>, sqlx::Error> {
+    let page = sqlx::query_as!(
+        WikiPage,
+        r#"
+        SELECT sub, page, content, revision, editors AS "editors: WikiEditors", edited_by,
+            edited_at
+        FROM sub_wiki_pages
+        WHERE sub = $1 AND page = $2
+        "#,
+        sub,
+        page
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(page)
+}
+
+/// Saves `content` as the page's next revision, creating the page at revision 1.
+pub async fn save_wiki_page(
+    pool: &PgPool,
+    sub: &str,
+    page: &str,
+    content: &str,
+    reason: &str,
+    editor_id: i32,
+) -> Result<WikiPage, sqlx::Error> {
+    let saved = sqlx::query_as!(
+        WikiPage,
+        r#"
+        WITH saved AS (
+            INSERT INTO sub_wiki_pages (sub, page, content, revision, edited_by)
+            VALUES ($1, $2, $3, 1, $5)
+            ON CONFLICT (sub, page) DO UPDATE
+            SET content = EXCLUDED.content, revision = sub_wiki_pages.revision + 1,
+                edited_by = EXCLUDED.edited_by, edited_at = NOW()
+            RETURNING sub, page, content, revision, editors, edited_by, edited_at
+        ), recorded AS (
+            INSERT INTO sub_wiki_revisions (sub, page, revision, content, edited_by, reason,
+                created_at)
+            SELECT sub, page, revision, content, edited_by, $4, edited_at
+            FROM saved
+        )
+        SELECT sub AS "sub!", page AS "page!", content AS "content!", revision AS "revision!",
+            editors AS "editors!: WikiEditors", edited_by, edited_at AS "edited_at!"
+        FROM saved
+        "#,
+        sub,
+        page,
+        content,
+        reason,
+        editor_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(saved)
+}
+
+/// `None` if there is no such page.
+pub async fn set_wiki_page_editors(
+    pool: &PgPool,
+    sub: &str,
+    page: &str,
+    editors: WikiEditors,
+) -> Result<Option<WikiPage>, sqlx::Error> {
+    let updated = sqlx::query_as!(
+        WikiPage,
+        r#"
+        UPDATE sub_wiki_pages
+        SET editors = $3
+        WHERE sub = $1 AND page = $2
+        RETURNING sub, page, content, revision, editors AS "editors: WikiEditors", edited_by,
+            edited_at
+        "#,
+        sub,
+        page,
+        editors as WikiEditors
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(updated)
+}
+
+/// The page's revisions, newest first.
+pub async fn get_wiki_revisions(
+    pool: &PgPool,
+    sub: &str,
+    page: &str,
+) -> Result<Vec<WikiRevision>, sqlx::Error> {
+    let revisions = sqlx::query_as!(
+        WikiRevision,
+        r#"
+        SELECT revision, content, edited_by, reason, created_at
+        FROM sub_wiki_revisions
+        WHERE sub = $1 AND page = $2
+        ORDER BY revision DESC
+        "#,
+        sub,
+        page
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(revisions)
+}
+
+pub async fn get_wiki_revision(
+    pool: &PgPool,
+    sub: &str,
+    page: &str,
+    revision: i32,
+) -> Result<Option<WikiRevision>, sqlx::Error> {
+    let revision = sqlx::query_as!(
+        WikiRevision,
+        r#"
+        SELECT revision, content, edited_by, reason, created_at
+        FROM sub_wiki_revisions
+        WHERE sub = $1 AND page = $2 AND revision = $3
+        "#,
+        sub,
+        page,
+        revision
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(revision)
+}
+
+#[cfg(test)]
+mod wiki_repo_tests {
+    use super::*;
+    use crate::test_support::fixtures::{SubFixture, UserFixture};
+    use crate::test_support::TestDatabase;
+
+    #[actix_web::test]
+    #[ignore = "needs Docker or TEST_DATABASE_URL"]
+    async fn test_each_save_is_a_new_revision() {
+        let db = TestDatabase::new().await;
+        let editor = UserFixture::new("editor").insert(&db.pool).await;
+        let sub = SubFixture::new("rust").insert(&db.pool).await;
+
+        let first = save_wiki_page(&db.pool, &sub.name, "faq", "# FAQ", "", editor.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            (first.revision, first.editors),
+            (1, WikiEditors::Moderators)
+        );
+        set_wiki_page_editors(&db.pool, &sub.name, "faq", WikiEditors::Anyone)
+            .await
+            .unwrap();
+        let second = save_wiki_page(
+            &db.pool,
+            &sub.name,
+            "faq",
+            "# FAQ\n\nAsk away",
+            "Typo",
+            editor.id,
+        )
+        .await
+        .unwrap();
+        assert_eq!((second.revision, second.editors), (2, WikiEditors::Anyone));
+
+        let revisions = get_wiki_revisions(&db.pool, &sub.name, "faq")
+            .await
+            .unwrap();
+        let numbers: Vec<i32> = revisions.iter().map(|revision| revision.revision).collect();
+        assert_eq!(numbers, [2, 1]);
+        assert_eq!(revisions[0].reason, "Typo");
+        let first = get_wiki_revision(&db.pool, &sub.name, "faq", 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.content, "# FAQ");
+        assert!(get_wiki_page(&db.pool, &sub.name, "rules")
+            .await
+            .unwrap()
+            .is_none());
+
+        db.finish().await;
+    }
+}
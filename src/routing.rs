@@ -1,43 +1,336 @@
+use crate::api::account_requirement::*;
+use crate::api::api_key::*;
+use crate::api::appeal::*;
+use crate::api::audit::*;
+use crate::api::auth::*;
+use crate::api::automod::*;
+use crate::api::ban::*;
 use crate::api::comment::*;
+use crate::api::config::*;
+use crate::api::experiment::*;
+use crate::api::feed::*;
+use crate::api::filter::*;
+use crate::api::flair::*;
+use crate::api::legal::*;
+use crate::api::media::*;
+use crate::api::mod_note::*;
+use crate::api::moderation::*;
+use crate::api::modqueue::*;
+use crate::api::oauth::*;
 use crate::api::post::*;
+use crate::api::post_template::*;
+use crate::api::premium::*;
+use crate::api::removal_reason::*;
+use crate::api::render::*;
+use crate::api::report::*;
+use crate::api::rule::*;
+use crate::api::saved::*;
+use crate::api::search::*;
+use crate::api::session::*;
 use crate::api::sub::*;
+use crate::api::suspension::*;
 use crate::api::user::*;
+use crate::api::wiki::*;
+use crate::ui::*;
 use actix_web::web::ServiceConfig;
 
 pub fn configure_user_routes(cfg: &mut ServiceConfig) {
     cfg.service(create_user)
+        .service(verify_email)
+        .service(resend_verification_email)
         .service(get_user_by_id)
         .service(get_user_by_username)
+        .service(get_user_karma)
         .service(get_users_by_sub)
-        .service(verify_user_password)
         .service(username_exists)
-        .service(grant_mod_status)
-        .service(remove_mod_status)
         .service(update_user_password)
+        .service(set_date_of_birth)
+        .service(acknowledge_nsfw)
+        .service(set_preferred_languages)
+        .service(set_nsfw_preference)
         .service(delete_user);
 }
 
+pub fn configure_auth_routes(cfg: &mut ServiceConfig) {
+    cfg.service(login)
+        .service(refresh)
+        .service(logout)
+        .service(create_session)
+        .service(delete_session)
+        .service(get_csrf_token)
+        .service(get_sessions)
+        .service(delete_user_session)
+        .service(get_current_user)
+        .service(clear_lockout);
+}
+
+pub fn configure_oauth_routes(cfg: &mut ServiceConfig) {
+    cfg.service(start_oauth)
+        .service(oauth_callback)
+        .service(get_identities);
+}
+
 pub fn configure_sub_routes(cfg: &mut ServiceConfig) {
     cfg.service(create_sub)
         .service(get_all_subs)
-        .service(get_subs_by_user_id)
+        .service(get_trending_subs)
+        .service(get_popular_subs)
         .service(get_sub_by_name)
         .service(update_sub)
+        .service(update_sub_settings)
+        .service(get_sub_moderators)
+        .service(get_sub_moderator_invites)
+        .service(invite_sub_moderator)
+        .service(revoke_sub_moderator_invite)
+        .service(accept_sub_moderator_invite)
+        .service(update_moderator_permissions)
+        .service(remove_sub_moderator)
+        .service(get_sub_members)
+        .service(approve_sub_member)
+        .service(remove_sub_member)
+        .service(request_to_join_sub)
+        .service(get_join_requests)
+        .service(approve_join_request)
+        .service(deny_join_request)
         .service(delete_sub)
-        .service(subscribe_user_to_sub);
+        .service(subscribe_to_sub)
+        .service(unsubscribe_from_sub)
+        .service(get_user_subscriptions);
 }
 
 pub fn configure_comment_routes(cfg: &mut ServiceConfig) {
     cfg.service(create_comment)
         .service(get_comments)
+        .service(get_comment_tree)
+        .service(get_comment_children)
+        .service(get_comment_context)
         .service(update_comment)
-        .service(delete_comment);
+        .service(get_comment_revision_diff)
+        .service(get_comment_history)
+        .service(delete_comment)
+        .service(vote_comment)
+        .service(delete_comment_vote);
 }
 
 pub fn configure_post_routes(cfg: &mut ServiceConfig) {
-    cfg.service(create_post)
+    // Ahead of create_post, whose `/posts/{sub}` would otherwise claim `/posts/by_ids`
+    // and `/posts/drafts`
+    cfg.service(get_posts_by_ids_body)
+        .service(create_draft)
+        .service(create_post)
+        .service(get_posts_by_ids)
         .service(get_post)
+        .service(crosspost_post)
         .service(get_posts_by_sub)
+        // After get_posts_by_sub, so `/posts/for_sub/duplicates` lists a sub
+        .service(get_post_duplicates)
         .service(update_post)
-        .service(delete_post);
+        .service(publish_post)
+        .service(pin_post)
+        .service(unpin_post)
+        .service(lock_post)
+        .service(unlock_post)
+        .service(set_post_flags)
+        .service(hide_post)
+        .service(unhide_post)
+        .service(get_hidden_posts)
+        .service(get_user_drafts)
+        .service(get_post_revision_diff)
+        .service(delete_post)
+        .service(vote_post)
+        .service(delete_post_vote)
+        .service(vote_in_poll);
+}
+
+pub fn configure_audit_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_audit_log);
+}
+
+pub fn configure_moderation_routes(cfg: &mut ServiceConfig) {
+    cfg.service(nuke_user_content)
+        .service(get_mod_log)
+        .service(purge_post)
+        .service(purge_comment)
+        .service(unarchive_post);
+}
+
+pub fn configure_legal_routes(cfg: &mut ServiceConfig) {
+    cfg.service(submit_takedown)
+        .service(get_takedowns)
+        .service(get_takedown)
+        .service(add_takedown_document)
+        .service(action_takedown)
+        .service(reject_takedown)
+        .service(counter_takedown);
+}
+
+pub fn configure_config_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_runtime_config)
+        .service(reload_runtime_config);
+}
+
+pub fn configure_ui_routes(cfg: &mut ServiceConfig) {
+    cfg.service(subs_page)
+        .service(sub_page)
+        .service(post_page)
+        .service(user_page);
+}
+
+pub fn configure_post_template_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_post_templates)
+        .service(create_post_template)
+        .service(update_post_template)
+        .service(delete_post_template);
+}
+
+pub fn configure_rule_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_rules)
+        .service(create_rule)
+        .service(update_rule)
+        .service(delete_rule);
+}
+
+pub fn configure_ban_routes(cfg: &mut ServiceConfig) {
+    cfg.service(ban_user_from_sub)
+        .service(get_sub_bans)
+        .service(unban_user_from_sub);
+}
+
+pub fn configure_mod_note_routes(cfg: &mut ServiceConfig) {
+    cfg.service(create_mod_note)
+        .service(get_mod_notes)
+        .service(delete_mod_note);
+}
+
+pub fn configure_automod_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_automod)
+        .service(update_automod)
+        .service(test_automod);
+}
+
+pub fn configure_suspension_routes(cfg: &mut ServiceConfig) {
+    cfg.service(suspend_user)
+        .service(get_suspensions)
+        .service(lift_suspension)
+        .service(shadowban_user)
+        .service(unshadowban_user);
+}
+
+pub fn configure_report_routes(cfg: &mut ServiceConfig) {
+    cfg.service(report_post)
+        .service(report_comment)
+        .service(get_mod_queue)
+        .service(approve_queue_item)
+        .service(remove_queue_item)
+        .service(get_removal_reasons)
+        .service(create_removal_reason)
+        .service(update_removal_reason)
+        .service(delete_removal_reason);
+}
+
+pub fn configure_wiki_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_wiki_pages)
+        .service(get_wiki_page)
+        .service(edit_wiki_page)
+        .service(update_wiki_page_settings)
+        .service(get_wiki_revisions)
+        .service(get_wiki_revision_diff);
+}
+
+pub fn configure_flair_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_flairs)
+        .service(create_flair)
+        .service(update_flair)
+        .service(delete_flair)
+        .service(set_post_flair)
+        .service(get_user_flairs)
+        .service(create_user_flair)
+        .service(update_user_flair)
+        .service(delete_user_flair)
+        .service(choose_user_flair);
+}
+
+pub fn configure_feed_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_all_feed)
+        .service(get_home_feed)
+        .service(get_popular_feed)
+        .service(create_custom_feed)
+        .service(get_custom_feeds)
+        .service(get_custom_feed)
+        .service(update_custom_feed)
+        .service(delete_custom_feed)
+        .service(get_custom_feed_posts);
+}
+
+pub fn configure_render_routes(cfg: &mut ServiceConfig) {
+    cfg.service(preview_markdown);
+}
+
+pub fn configure_search_routes(cfg: &mut ServiceConfig) {
+    cfg.service(search_posts)
+        .service(search_comments)
+        .service(search_users)
+        .service(search_subs);
+}
+
+pub fn configure_experiment_routes(cfg: &mut ServiceConfig) {
+    cfg.service(create_experiment)
+        .service(get_experiments)
+        .service(update_experiment)
+        .service(get_experiment_exposures)
+        .service(get_assignment);
+}
+
+pub fn configure_api_key_routes(cfg: &mut ServiceConfig) {
+    cfg.service(create_api_key)
+        .service(get_api_keys)
+        .service(revoke_api_key)
+        .service(set_api_key_tier)
+        .service(get_usage);
+}
+
+pub fn configure_premium_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_premium_status)
+        .service(grant_premium)
+        .service(revoke_premium)
+        .service(get_premium_subscriptions)
+        .service(award_post)
+        .service(award_comment)
+        .service(payment_webhook);
+}
+
+pub fn configure_filter_routes(cfg: &mut ServiceConfig) {
+    // The held-content routes are registered before `/admin/filters/{filter_id}`
+    cfg.service(get_held_content)
+        .service(approve_held_post)
+        .service(approve_held_comment)
+        .service(create_filter)
+        .service(get_filters)
+        .service(delete_filter);
+}
+
+pub fn configure_appeal_routes(cfg: &mut ServiceConfig) {
+    cfg.service(create_appeal)
+        .service(get_my_appeals)
+        .service(get_sub_appeals)
+        .service(get_suspension_appeals)
+        .service(resolve_appeal);
+}
+
+pub fn configure_account_requirement_routes(cfg: &mut ServiceConfig) {
+    cfg.service(set_account_requirement)
+        .service(get_account_requirements)
+        .service(delete_account_requirement);
+}
+
+pub fn configure_media_routes(cfg: &mut ServiceConfig) {
+    cfg.service(upload_media);
+}
+
+pub fn configure_saved_routes(cfg: &mut ServiceConfig) {
+    cfg.service(save_post)
+        .service(unsave_post)
+        .service(save_comment)
+        .service(unsave_comment)
+        .service(get_saved_items);
 }
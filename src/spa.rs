@@ -0,0 +1,34 @@
+use actix_files::{Files, NamedFile};
+use actix_web::dev::{fn_service, ServiceRequest, ServiceResponse};
+use actix_web::http::header::ACCEPT;
+use actix_web::HttpResponse;
+use std::path::{Path, PathBuf};
+
+/// Serves a bundled frontend from `dir`. Browser navigations to paths that aren't
+/// files or API routes get `index.html` so the client-side router can handle them;
+/// other unmatched requests still receive a plain 404.
+pub fn spa_files(dir: &Path) -> Files {
+    let index: PathBuf = dir.join("index.html");
+
+    Files::new("/", dir)
+        .index_file("index.html")
+        .default_handler(fn_service(move |req: ServiceRequest| {
+            let index = index.clone();
+            async move {
+                let (req, _) = req.into_parts();
+                let wants_html = req
+                    .headers()
+                    .get(ACCEPT)
+                    .and_then(|accept| accept.to_str().ok())
+                    .is_some_and(|accept| accept.contains("text/html"));
+
+                let response = if wants_html {
+                    NamedFile::open_async(&index).await?.into_response(&req)
+                } else {
+                    HttpResponse::NotFound().finish()
+                };
+
+                Ok(ServiceResponse::new(req, response))
+            }
+        }))
+}
@@ -0,0 +1,102 @@
+//! Scoring new posts and comments for how likely they are to be spam.
+//!
+//! Scores add up signals about the content and its author (see [`score`]): copies in
+//! other subs, how much of it is links, how fast the author is posting and how new their
+//! account is. Above the configured thresholds content is held or removed, and the score
+//! is kept on it for moderators to see in the modqueue.
+
+use crate::config::SpamConfig;
+use crate::model::automod::find_links;
+use crate::model::moderation::RemovalKind;
+use crate::model::report::ReportTarget;
+use crate::model::spam::{
+    score, SpamScore, SpamSignals, DUPLICATE_WINDOW_HOURS, MIN_DUPLICATE_CHARS,
+    VELOCITY_WINDOW_MINUTES,
+};
+use crate::repo::{spam as spam_repo, user::UserRepository};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+/// Scores new content by `author_id` in `sub`. `title` is `None` for comments and
+/// `canonical_url` is a link post's link.
+pub async fn score_content(
+    pool: &PgPool,
+    author_id: i32,
+    sub: &str,
+    title: Option<&str>,
+    body: &str,
+    canonical_url: Option<&str>,
+) -> Result<SpamScore, actix_web::Error> {
+    let author = UserRepository::get_user_by_id(pool, author_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let now = Utc::now();
+
+    let copied = (body.trim().chars().count() >= MIN_DUPLICATE_CHARS).then_some(body);
+    let duplicate_subs = if copied.is_some() || canonical_url.is_some() {
+        spam_repo::count_duplicate_subs(
+            pool,
+            author_id,
+            sub,
+            copied,
+            canonical_url,
+            now - Duration::hours(DUPLICATE_WINDOW_HOURS),
+        )
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+    } else {
+        0
+    };
+    let recent_submissions = spam_repo::count_recent_submissions(
+        pool,
+        author_id,
+        now - Duration::minutes(VELOCITY_WINDOW_MINUTES),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(score(&SpamSignals {
+        duplicate_subs,
+        links: find_links(body).count(),
+        words: title
+            .into_iter()
+            .chain([body])
+            .flat_map(str::split_whitespace)
+            .count(),
+        recent_submissions,
+        account_age: now - author.created_at,
+        karma: i64::from(author.post_karma) + i64::from(author.comment_karma),
+    }))
+}
+
+/// How content with this score is stored, given how its filters would store it: held
+/// at the queue threshold, removed at the remove threshold.
+pub fn spam_removal(
+    config: &SpamConfig,
+    score: &SpamScore,
+    removal: Option<RemovalKind>,
+) -> Option<RemovalKind> {
+    let reaches = |threshold: Option<i32>| threshold.is_some_and(|at| score.score >= at);
+    if reaches(config.remove_score) {
+        Some(RemovalKind::Moderator)
+    } else if reaches(config.queue_score) {
+        removal.or(Some(RemovalKind::Filter))
+    } else {
+        removal
+    }
+}
+
+/// Keeps the score on the saved content, unless it scored nothing.
+pub async fn record_score(
+    pool: &PgPool,
+    target: ReportTarget,
+    score: &SpamScore,
+) -> Result<(), actix_web::Error> {
+    if score.score == 0 {
+        return Ok(());
+    }
+
+    spam_repo::record_spam_score(pool, target, score)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)
+}
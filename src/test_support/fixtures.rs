@@ -0,0 +1,211 @@
+//! Builders that insert rows through the repo layer with sensible defaults, so tests
+//! only spell out the fields they care about.
+
+use crate::model::comment::Comment;
+use crate::model::password::HashParams;
+use crate::model::post::{Post, PostStatus};
+use crate::model::sub::{Sub, SubVisibility};
+use crate::model::user::{DbAddUser, User};
+use crate::repo::{comment as comment_repo, post as post_repo, sub as sub_repo, user as user_repo};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct UserFixture {
+    username: String,
+    email: Option<String>,
+}
+
+impl UserFixture {
+    pub fn new(username: &str) -> Self {
+        UserFixture {
+            username: username.to_string(),
+            email: None,
+        }
+    }
+
+    pub fn with_email(mut self, email: &str) -> Self {
+        self.email = Some(email.to_string());
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool) -> User {
+        let user = DbAddUser {
+            username: self.username,
+            password_hash: User::hash_password("password", &HashParams::default())
+                .expect("hash fixture password"),
+            created_at: Utc::now(),
+            email: self.email,
+            email_verified_at: None,
+        };
+        let user_id = user_repo::create_user(pool, &user)
+            .await
+            .expect("insert user fixture");
+
+        user_repo::get_user_by_id(pool, user_id)
+            .await
+            .expect("load user fixture")
+    }
+}
+
+pub struct SubFixture {
+    name: String,
+    nsfw: bool,
+    visibility: SubVisibility,
+}
+
+impl SubFixture {
+    pub fn new(name: &str) -> Self {
+        SubFixture {
+            name: name.to_string(),
+            nsfw: false,
+            visibility: SubVisibility::Public,
+        }
+    }
+
+    pub fn nsfw(mut self) -> Self {
+        self.nsfw = true;
+        self
+    }
+
+    pub fn private(mut self) -> Self {
+        self.visibility = SubVisibility::Private;
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool) -> Sub {
+        let sub = Sub {
+            description: format!("All about {}", self.name),
+            name: self.name,
+            created_at: Utc::now(),
+            nsfw: self.nsfw,
+            spoiler_by_default: false,
+            subscribers: 0,
+            visibility: self.visibility,
+            icon_url: None,
+            banner_url: None,
+            primary_color: None,
+            accent_color: None,
+        };
+        sub_repo::create_sub(pool, &sub)
+            .await
+            .expect("insert sub fixture");
+
+        sub
+    }
+}
+
+pub struct PostFixture {
+    post: Post,
+}
+
+impl PostFixture {
+    pub fn new(sub: &Sub, author: &User) -> Self {
+        PostFixture {
+            post: Post {
+                id: Uuid::new_v4(),
+                sub: sub.name.clone(),
+                user_id: author.id,
+                title: "Fixture post".to_string(),
+                content: "Fixture post content".to_string(),
+                timestamp: Utc::now(),
+                removal: None,
+                nsfw: false,
+                language: None,
+                score: 0,
+                status: PostStatus::Published,
+                edited_at: None,
+                pin_order: None,
+                locked: false,
+                lock_reason: None,
+                archived: false,
+                flair_id: None,
+                crosspost_parent_id: None,
+                link_url: None,
+                spoiler: false,
+                publish_at: None,
+                author_flair: None,
+            },
+        }
+    }
+
+    pub fn content(mut self, content: &str) -> Self {
+        self.post.content = content.to_string();
+        self
+    }
+
+    pub fn link(mut self, url: &str) -> Self {
+        self.post.link_url = Some(url.to_string());
+        self
+    }
+
+    pub fn nsfw(mut self) -> Self {
+        self.post.nsfw = true;
+        self
+    }
+
+    pub fn language(mut self, language: &str) -> Self {
+        self.post.language = Some(language.to_string());
+        self
+    }
+
+    pub fn draft(mut self) -> Self {
+        self.post.status = PostStatus::Draft;
+        self
+    }
+
+    pub fn scheduled(mut self, at: DateTime<Utc>) -> Self {
+        self.post.status = PostStatus::Draft;
+        self.post.publish_at = Some(at);
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool) -> Post {
+        post_repo::create_post(pool, &self.post)
+            .await
+            .expect("insert post fixture");
+
+        self.post
+    }
+}
+
+pub struct CommentFixture {
+    comment: Comment,
+}
+
+impl CommentFixture {
+    pub fn new(post: &Post, author: &User) -> Self {
+        CommentFixture {
+            comment: Comment {
+                id: Uuid::new_v4(),
+                post_id: post.id,
+                user_id: author.id,
+                content: "Fixture comment".to_string(),
+                timestamp: Utc::now(),
+                parent_id: None,
+                removal: None,
+                score: 0,
+                edited_at: None,
+                author_flair: None,
+            },
+        }
+    }
+
+    pub fn content(mut self, content: &str) -> Self {
+        self.comment.content = content.to_string();
+        self
+    }
+
+    pub fn reply_to(mut self, parent: &Comment) -> Self {
+        self.comment.parent_id = Some(parent.id);
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool) -> Comment {
+        comment_repo::create_comment(pool, &self.comment)
+            .await
+            .expect("insert comment fixture");
+
+        self.comment
+    }
+}
@@ -0,0 +1,155 @@
+//! Integration test support: a disposable Postgres per test run and a fresh,
+//! migrated database per test.
+//!
+//! Tests that use this are `#[ignore]`d so `cargo test` stays self-contained; run
+//! them with `cargo test -- --ignored`. A Postgres container is started on first
+//! use, or set `TEST_DATABASE_URL` to a server you already have (the role needs
+//! `CREATEDB`). The container is labelled `ferris-forums-tests` and is not removed
+//! when the run ends.
+//!
+//! Each test gets its own database cloned from a migrated template rather than a
+//! transaction that is rolled back: repo functions open their own transactions
+//! from the pool, which can't be nested inside an outer one.
+
+pub mod fixtures;
+
+use crate::audit;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::PgConnectOptions;
+use sqlx::{Executor, PgPool};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+use testcontainers_modules::postgres::Postgres;
+use testcontainers_modules::testcontainers::runners::SyncRunner;
+use testcontainers_modules::testcontainers::{Container, ImageExt};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+static CONTAINER: OnceLock<Container<Postgres>> = OnceLock::new();
+static TEMPLATE: OnceCell<(PgConnectOptions, String)> = OnceCell::const_new();
+
+pub struct TestDatabase {
+    pub pool: PgPool,
+    server: PgConnectOptions,
+    name: String,
+}
+
+impl TestDatabase {
+    pub async fn new() -> Self {
+        let (server, template) = TEMPLATE.get_or_init(create_template).await;
+        let name = format!("ferris_test_{}", Uuid::new_v4().simple());
+
+        let admin = connect(server, "postgres").await;
+        admin
+            .execute(format!(r#"CREATE DATABASE "{}" TEMPLATE "{}""#, name, template).as_str())
+            .await
+            .expect("Could not create the test database");
+        admin.close().await;
+
+        TestDatabase {
+            pool: connect(server, &name).await,
+            server: server.clone(),
+            name,
+        }
+    }
+
+    /// Drops the test's database. Databases of tests that panic are left behind
+    /// and go away with the container.
+    pub async fn finish(self) {
+        self.pool.close().await;
+
+        let admin = connect(&self.server, "postgres").await;
+        admin
+            .execute(format!(r#"DROP DATABASE "{}" WITH (FORCE)"#, self.name).as_str())
+            .await
+            .expect("Could not drop the test database");
+        admin.close().await;
+    }
+}
+
+async fn create_template() -> (PgConnectOptions, String) {
+    let server_url = match std::env::var("TEST_DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => start_container(),
+    };
+    let server: PgConnectOptions = server_url.parse().expect("TEST_DATABASE_URL is invalid");
+    let migrator = sqlx::migrate!("./migrations");
+    let template = template_name(&migrator);
+
+    let admin = connect(&server, "postgres").await;
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)",
+    )
+    .bind(&template)
+    .fetch_one(&admin)
+    .await
+    .expect("Could not look up the template database");
+
+    if !exists {
+        admin
+            .execute(format!(r#"CREATE DATABASE "{}""#, template).as_str())
+            .await
+            .expect("Could not create the template database");
+
+        // Cloning requires that nothing is connected to the template, so close this pool
+        let pool = connect(&server, &template).await;
+        migrator
+            .run(&pool)
+            .await
+            .expect("Could not apply migrations");
+        pool.close().await;
+    }
+    admin.close().await;
+
+    (server, template)
+}
+
+/// Templates are named after the migrations they contain, so a server passed in via
+/// `TEST_DATABASE_URL` keeps one template per schema version instead of one per run.
+fn template_name(migrator: &Migrator) -> String {
+    let mut hasher = DefaultHasher::new();
+    for migration in migrator.iter() {
+        migration.version.hash(&mut hasher);
+        migration.checksum.hash(&mut hasher);
+    }
+
+    format!("ferris_template_{:016x}", hasher.finish())
+}
+
+/// Starts the container on a separate thread because the blocking runner brings its
+/// own async runtime, which can't be started from inside a test's runtime.
+fn start_container() -> String {
+    let container = CONTAINER.get_or_init(|| {
+        std::thread::spawn(|| {
+            Postgres::default()
+                .with_label("ferris-forums-tests", "true")
+                .start()
+                .expect("Could not start the Postgres container; is Docker running?")
+        })
+        .join()
+        .expect("Postgres container startup panicked")
+    });
+
+    let (host, port) = std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                (
+                    container.get_host().expect("container host"),
+                    container.get_host_port_ipv4(5432).expect("container port"),
+                )
+            })
+            .join()
+            .expect("Postgres container lookup panicked")
+    });
+
+    format!("postgres://postgres:postgres@{}:{}/postgres", host, port)
+}
+
+async fn connect(server: &PgConnectOptions, database: &str) -> PgPool {
+    audit::pool_options()
+        .max_connections(5)
+        .connect_with(server.clone().database(database))
+        .await
+        .unwrap_or_else(|e| panic!("Could not connect to test database {}: {}", database, e))
+}
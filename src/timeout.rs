@@ -0,0 +1,31 @@
+use crate::config::TimeoutConfig;
+use crate::error::ApiError;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::rt::time::timeout;
+
+/// Races the rest of the chain against the route's timeout. When the timeout wins the
+/// handler future is dropped, which also drops (and so cancels) any query it was awaiting.
+pub async fn enforce_timeout(
+    config: TimeoutConfig,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let limit = config.timeout_for(req.path());
+    let path = req.path().to_string();
+
+    match timeout(limit, next.call(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            log::warn!("Request to {} timed out after {:?}", path, limit);
+            Err(ApiError::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                "request_timeout",
+                "The request took too long to complete",
+            )
+            .into())
+        }
+    }
+}
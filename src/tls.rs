@@ -0,0 +1,26 @@
+use crate::config::TlsConfig;
+use rustls::ServerConfig;
+use std::fs::File;
+use std::io::{self, BufReader};
+
+/// Builds the rustls server configuration from PEM files. ALPN for HTTP/2 and
+/// HTTP/1.1 is negotiated by actix when the listener is bound.
+pub fn load_server_config(tls: &TlsConfig) -> io::Result<ServerConfig> {
+    let mut cert_reader = BufReader::new(File::open(&tls.cert_path)?);
+    let certs = rustls_pemfile::certs(&mut cert_reader).collect::<Result<Vec<_>, _>>()?;
+
+    let mut key_reader = BufReader::new(File::open(&tls.key_path)?);
+    let key = rustls_pemfile::private_key(&mut key_reader)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no private key found in {}", tls.key_path.display()),
+        )
+    })?;
+
+    ServerConfig::builder_with_provider(rustls::crypto::ring::default_provider().into())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,248 @@
+use crate::api::post::{get_readable_post, require_visible_post};
+use crate::api::sub::get_readable_sub;
+use crate::model::comment::{thread_order, ThreadComment};
+use crate::model::post::{Listing, Post, PostSort, MAX_LISTING_LIMIT};
+use crate::model::sub::{Sub, SubSort};
+use crate::repo::{
+    comment::CommentRepository, post::PostRepository, sub::SubRepository, user::UserRepository,
+};
+use actix_web::{get, web::Data, web::Path, HttpResponse};
+use askama::Template;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+// The HTML interface has no sign-in, so it renders what a logged-out visitor may see:
+// NSFW content and private subs are never shown.
+
+#[derive(Template)]
+#[template(path = "ui/subs.html")]
+struct SubsPage {
+    subs: Vec<Sub>,
+}
+
+#[derive(Template)]
+#[template(path = "ui/sub.html")]
+struct SubPage {
+    sub: Sub,
+    posts: Vec<Post>,
+}
+
+#[derive(Template)]
+#[template(path = "ui/post.html")]
+struct PostPage {
+    post: Post,
+    comments: Vec<ThreadComment>,
+}
+
+#[derive(Template)]
+#[template(path = "ui/user.html")]
+struct UserPage {
+    username: String,
+    is_moderator: bool,
+    created_at: DateTime<Utc>,
+    posts: Vec<Post>,
+}
+
+fn render(template: impl Template) -> Result<HttpResponse, actix_web::Error> {
+    let body = template
+        .render()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(body))
+}
+
+#[get("/ui/subs")]
+pub async fn subs_page(subs: Data<dyn SubRepository>) -> Result<HttpResponse, actix_web::Error> {
+    let subs = subs
+        .get_all_subs(SubSort::Name, None)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    render(SubsPage { subs })
+}
+
+#[get("/ui/s/{sub}")]
+pub async fn sub_page(
+    subs: Data<dyn SubRepository>,
+    posts: Data<dyn PostRepository>,
+    users: Data<dyn UserRepository>,
+    path: Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let sub_name = path.into_inner();
+
+    let sub = get_readable_sub(subs.get_ref(), users.get_ref(), &sub_name, None).await?;
+    let posts = if sub.nsfw {
+        Vec::new()
+    } else {
+        let listing = Listing::first_page(PostSort::Hot, MAX_LISTING_LIMIT);
+        let pinned = posts
+            .get_pinned_posts(&sub_name, false, None)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let ranked = posts
+            .get_posts_by_sub(&sub_name, false, &listing)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        pinned
+            .into_iter()
+            .chain(
+                ranked
+                    .into_iter()
+                    .take(MAX_LISTING_LIMIT as usize)
+                    .map(|ranked| ranked.post),
+            )
+            .collect()
+    };
+
+    render(SubPage { sub, posts })
+}
+
+#[get("/ui/posts/{id}")]
+pub async fn post_page(
+    posts: Data<dyn PostRepository>,
+    comments: Data<dyn CommentRepository>,
+    users: Data<dyn UserRepository>,
+    subs: Data<dyn SubRepository>,
+    path: Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let post_id = path.into_inner();
+
+    let post = get_readable_post(posts.get_ref(), post_id, None).await?;
+    require_visible_post(users.get_ref(), &post, None).await?;
+    get_readable_sub(subs.get_ref(), users.get_ref(), &post.sub, None).await?;
+    if post.nsfw {
+        return Err(actix_web::error::ErrorNotFound("Post not found"));
+    }
+    let comments = comments
+        .get_comments_by_post(post_id, None)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    render(PostPage {
+        post,
+        comments: thread_order(comments),
+    })
+}
+
+#[get("/ui/users/{user_id}")]
+pub async fn user_page(
+    users: Data<dyn UserRepository>,
+    posts: Data<dyn PostRepository>,
+    path: Path<i32>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = path.into_inner();
+
+    let user = users
+        .get_user_by_id(user_id)
+        .await
+        .map_err(actix_web::error::ErrorNotFound)?;
+    let posts = posts
+        .get_posts_by_user(user_id, false, None)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    render(UserPage {
+        username: user.username,
+        is_moderator: user.is_moderator,
+        created_at: user.created_at,
+        posts,
+    })
+}
+
+#[cfg(test)]
+mod ui_tests {
+    use super::*;
+    use crate::model::post::PostStatus;
+    use crate::model::sub::SubVisibility;
+    use crate::model::user::DbAddUser;
+    use crate::repo::{self, memory::InMemoryRepo};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_private_subs_stay_out_of_the_html_interface() {
+        let repo = Arc::new(InMemoryRepo::default());
+        let author = UserRepository::create_user(
+            repo.as_ref(),
+            &DbAddUser {
+                username: "author".to_string(),
+                password_hash: String::new(),
+                created_at: Utc::now(),
+                email: None,
+                email_verified_at: None,
+            },
+        )
+        .await
+        .unwrap();
+        SubRepository::create_sub(
+            repo.as_ref(),
+            &Sub {
+                name: "secret".to_string(),
+                description: "Members only".to_string(),
+                created_at: Utc::now(),
+                nsfw: false,
+                spoiler_by_default: false,
+                subscribers: 0,
+                visibility: SubVisibility::Private,
+                icon_url: None,
+                banner_url: None,
+                primary_color: None,
+                accent_color: None,
+            },
+        )
+        .await
+        .unwrap();
+        let post_id = PostRepository::create_post(
+            repo.as_ref(),
+            &Post {
+                id: Uuid::new_v4(),
+                sub: "secret".to_string(),
+                user_id: author,
+                title: "Hidden plans".to_string(),
+                content: "content".to_string(),
+                timestamp: Utc::now(),
+                removal: None,
+                nsfw: false,
+                language: None,
+                score: 0,
+                status: PostStatus::Published,
+                edited_at: None,
+                pin_order: None,
+                locked: false,
+                lock_reason: None,
+                archived: false,
+                flair_id: None,
+                crosspost_parent_id: None,
+                link_url: None,
+                spoiler: false,
+                publish_at: None,
+                author_flair: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| repo::register(cfg, repo.clone()))
+                .service(sub_page)
+                .service(post_page)
+                .service(user_page),
+        )
+        .await;
+        let get = |uri: String| test::TestRequest::get().uri(&uri).to_request();
+
+        let response = test::call_service(&app, get("/ui/s/secret".to_string())).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = test::call_service(&app, get(format!("/ui/posts/{}", post_id))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = test::call_service(&app, get(format!("/ui/users/{}", author))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = test::read_body(response).await;
+        assert!(!String::from_utf8_lossy(&body).contains("Hidden plans"));
+    }
+}